@@ -1,9 +1,11 @@
+use crate::decimal::Decimal;
 use crate::decoding::Read;
 use crate::encoding::Write;
 use crate::error::Error;
 use std::cmp::PartialEq;
 use std::collections::HashMap;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Any {
@@ -12,14 +14,41 @@ pub enum Any {
     Bool(bool),
     Number(f64),
     BigInt(i64),
+    /// 128-bit signed integer. Values fitting into 64 bits are encoded as [Any::BigInt]. Unless
+    /// `any-extensions` feature is enabled, larger values are encoded as strings of decimal digits,
+    /// since lib0 in JavaScript only supports 64-bit bigints.
+    Int128(i128),
+    /// Fixed-point decimal number. Unless `any-extensions` feature is enabled, it's encoded as
+    /// a string (ie. `"12.50"`), since lib0 in JavaScript has no decimal type.
+    Decimal(Decimal),
+    /// Point in time, represented as a number of milliseconds since Unix epoch (just like
+    /// JavaScript `Date`). Unless `any-extensions` feature is enabled, it's encoded as a number
+    /// of milliseconds, since lib0 in JavaScript has no date type.
+    Date(i64),
     String(Box<str>),
     Buffer(Box<[u8]>),
     Array(Box<[Any]>),
     Map(Box<HashMap<String, Any>>),
 }
 
+/// Maximum depth of nested collections accepted by [Any::decode]. It prevents malicious inputs
+/// from overflowing the stack.
+const MAX_DECODE_DEPTH: usize = 512;
+
+/// Maximum number of collection elements preallocated up front by [Any::decode], regardless of
+/// a declared collection length, which may be forged.
+const MAX_PREALLOC: usize = 1024;
+
 impl Any {
+    /// Decodes a value previously encoded with [Any::encode] (or lib0 `writeAny` in JavaScript).
     pub fn decode<R: Read>(decoder: &mut R) -> Result<Self, Error> {
+        Self::decode_nested(decoder, 0)
+    }
+
+    fn decode_nested<R: Read>(decoder: &mut R, depth: usize) -> Result<Self, Error> {
+        if depth > MAX_DECODE_DEPTH {
+            return Err(Error::UnexpectedValue);
+        }
         Ok(match decoder.read_u8()? {
             // CASE 127: undefined
             127 => Any::Undefined,
@@ -45,27 +74,35 @@ impl Any {
             // CASE 118: Map<string,Any>
             118 => {
                 let len: usize = decoder.read_var()?;
-                let mut map = HashMap::with_capacity(len);
+                let mut map = HashMap::with_capacity(len.min(MAX_PREALLOC));
                 for _ in 0..len {
                     let key = decoder.read_string()?;
-                    map.insert(key.to_owned(), Any::decode(decoder)?);
+                    map.insert(key.to_owned(), Any::decode_nested(decoder, depth + 1)?);
                 }
                 Any::Map(Box::new(map))
             }
             // CASE 117: Array<Any>
             117 => {
                 let len: usize = decoder.read_var()?;
-                let mut arr = Vec::with_capacity(len);
+                let mut arr = Vec::with_capacity(len.min(MAX_PREALLOC));
                 for _ in 0..len {
-                    arr.push(Any::decode(decoder)?);
+                    arr.push(Any::decode_nested(decoder, depth + 1)?);
                 }
                 Any::Array(arr.into_boxed_slice())
             }
             // CASE 116: buffer
             116 => Any::Buffer(Box::from(decoder.read_buf()?.to_owned())),
-            _ => {
-                panic!("Unable to read Any content");
+            // CASE 115: int128
+            115 => Any::Int128(decoder.read_i128()?),
+            // CASE 114: decimal
+            114 => {
+                let scale = decoder.read_u8()?;
+                let mantissa = decoder.read_i128()?;
+                Any::Decimal(Decimal::try_new(mantissa, scale).ok_or(Error::UnexpectedValue)?)
             }
+            // CASE 113: date
+            113 => Any::Date(decoder.read_var()?),
+            _ => return Err(Error::UnexpectedValue),
         })
     }
 
@@ -93,6 +130,13 @@ impl Any {
     // | object<string,any>  | 118      | custom             | Writes {length} then {length} key-value pairs |
     // | array<any>          | 117      | custom             | Writes {length} then {length} json values |
     // | Uint8Array          | 116      | writeVarUint8Array | We use Uint8Array for any kind of binary data |
+    // | int128              | 115      | 16 bytes big endian| Yrs extension, unknown to lib0 in JavaScript |
+    // | decimal             | 114      | custom             | Yrs extension: writes {scale} byte then {mantissa} as int128 |
+    // | date                | 113      | writeVarInt        | Yrs extension: milliseconds since Unix epoch |
+    //
+    // Int128 values fitting into 64 bits are always written as bigint. Yrs extension tags are
+    // written only when `any-extensions` feature is enabled. Otherwise remaining int128 values and
+    // decimals are written as strings and dates as numbers of milliseconds since Unix epoch.
     //
     // Reasons for the decreasing prefix:
     // We need the first bit for extendability (later we may want to encode the
@@ -101,6 +145,7 @@ impl Any {
     //          (defined by the function that uses this library)
     // [31-127] the end of the data range is used for data encoding by
     //          lib0/encoding.js
+    /// Encodes current value in a format compatible with lib0 `writeAny` in JavaScript.
     pub fn encode<W: Write>(&self, encoder: &mut W) {
         match self {
             Any::Undefined => {
@@ -144,6 +189,43 @@ impl Any {
                 encoder.write_u8(122);
                 encoder.write_i64(*num)
             }
+            Any::Int128(num) => {
+                if let Ok(num) = i64::try_from(*num) {
+                    // TYPE 122: BigInt
+                    encoder.write_u8(122);
+                    encoder.write_i64(num)
+                } else if cfg!(feature = "any-extensions") {
+                    // TYPE 115: Int128
+                    encoder.write_u8(115);
+                    encoder.write_i128(*num)
+                } else {
+                    // TYPE 119: String
+                    encoder.write_u8(119);
+                    encoder.write_string(&num.to_string())
+                }
+            }
+            Any::Decimal(num) => {
+                if cfg!(feature = "any-extensions") {
+                    // TYPE 114: Decimal
+                    encoder.write_u8(114);
+                    encoder.write_u8(num.scale());
+                    encoder.write_i128(num.mantissa())
+                } else {
+                    // TYPE 119: String
+                    encoder.write_u8(119);
+                    encoder.write_string(&num.to_string())
+                }
+            }
+            Any::Date(millis) => {
+                if cfg!(feature = "any-extensions") {
+                    // TYPE 113: Date
+                    encoder.write_u8(113);
+                    encoder.write_var(*millis)
+                } else {
+                    // TYPE 123-125: milliseconds as Number
+                    Any::Number(*millis as f64).encode(encoder)
+                }
+            }
             Any::Array(arr) => {
                 // TYPE 117: Array
                 encoder.write_u8(117);
@@ -197,6 +279,9 @@ impl Any {
             Any::Bool(value) => write!(buf, "{}", value).unwrap(),
             Any::Number(value) => write!(buf, "{}", value).unwrap(),
             Any::BigInt(value) => write!(buf, "{}", value).unwrap(),
+            Any::Int128(value) => write!(buf, "{}", value).unwrap(),
+            Any::Decimal(value) => write!(buf, "{}", value).unwrap(),
+            Any::Date(millis) => write!(buf, "\"{}\"", IsoDate(*millis)).unwrap(),
             Any::String(value) => quoted(buf, value.as_ref()),
             Any::Array(values) => {
                 buf.push('[');
@@ -258,6 +343,45 @@ impl Any {
     }
 }
 
+/// Formats milliseconds since Unix epoch as an ISO 8601 UTC date, the same way as JavaScript
+/// `Date.prototype.toISOString` does, eg. `2020-01-31T12:30:00.000Z`.
+pub(crate) struct IsoDate(pub i64);
+
+impl std::fmt::Display for IsoDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const MILLIS_PER_DAY: i64 = 86_400_000;
+        let days = self.0.div_euclid(MILLIS_PER_DAY);
+        let millis = self.0.rem_euclid(MILLIS_PER_DAY);
+
+        // civil date from days since epoch, see: http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        if (0..=9999).contains(&year) {
+            write!(f, "{:04}", year)?;
+        } else {
+            write!(f, "{}{:06}", if year < 0 { '-' } else { '+' }, year.abs())?;
+        }
+        write!(
+            f,
+            "-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            month,
+            day,
+            millis / 3_600_000,
+            millis / 60_000 % 60,
+            millis / 1000 % 60,
+            millis % 1000
+        )
+    }
+}
+
 impl std::fmt::Display for Any {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -266,6 +390,9 @@ impl std::fmt::Display for Any {
             Any::Bool(value) => write!(f, "{}", value),
             Any::Number(value) => write!(f, "{}", value),
             Any::BigInt(value) => write!(f, "{}", value),
+            Any::Int128(value) => write!(f, "{}", value),
+            Any::Decimal(value) => write!(f, "{}", value),
+            Any::Date(millis) => write!(f, "{}", IsoDate(*millis)),
             Any::String(value) => f.write_str(value.as_ref()),
             Any::Array(values) => {
                 write!(f, "[")?;
@@ -337,6 +464,28 @@ impl Into<Any> for i64 {
     }
 }
 
+impl From<i128> for Any {
+    fn from(value: i128) -> Self {
+        Any::Int128(value)
+    }
+}
+
+impl From<SystemTime> for Any {
+    fn from(value: SystemTime) -> Self {
+        let millis = match value.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_millis() as i64,
+            Err(e) => -(e.duration().as_millis() as i64),
+        };
+        Any::Date(millis)
+    }
+}
+
+impl From<Decimal> for Any {
+    fn from(value: Decimal) -> Self {
+        Any::Decimal(value)
+    }
+}
+
 impl Into<Any> for String {
     fn into(self) -> Any {
         Any::String(self.into_boxed_str())
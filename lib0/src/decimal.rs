@@ -0,0 +1,159 @@
+use crate::error::Error;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Fixed-point decimal number, represented as an integer `mantissa` scaled down by a power of ten:
+/// `mantissa * 10^-scale`. Unlike [f64], it represents decimal fractions (like `0.1`) exactly,
+/// which makes it suitable for financial data.
+///
+/// Equality is structural: `1.0` (mantissa `10`, scale `1`) and `1.00` (mantissa `100`, scale
+/// `2`) are not equal, which preserves the precision chosen by the writer.
+///
+/// Example:
+/// ```rust
+/// use lib0::decimal::Decimal;
+///
+/// let price: Decimal = "-1234.56".parse().unwrap();
+/// assert_eq!(price, Decimal::new(-123456, 2));
+/// assert_eq!(price.to_string(), "-1234.56");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+impl Decimal {
+    /// Maximum number of fractional digits supported by [Decimal].
+    pub const MAX_SCALE: u8 = 38;
+
+    /// Creates a new decimal number equal to `mantissa * 10^-scale`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `scale` is greater than [Decimal::MAX_SCALE].
+    pub fn new(mantissa: i128, scale: u8) -> Self {
+        Self::try_new(mantissa, scale).expect("Decimal scale exceeds Decimal::MAX_SCALE")
+    }
+
+    /// Creates a new decimal number equal to `mantissa * 10^-scale`. Returns `None` if `scale`
+    /// is greater than [Decimal::MAX_SCALE].
+    pub fn try_new(mantissa: i128, scale: u8) -> Option<Self> {
+        if scale > Self::MAX_SCALE {
+            None
+        } else {
+            Some(Decimal { mantissa, scale })
+        }
+    }
+
+    /// Returns an unscaled integer value of current decimal.
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Returns a number of fractional digits of current decimal.
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Returns a closest [f64] approximation of current decimal.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if self.mantissa < 0 {
+            f.write_char('-')?;
+        }
+        if scale == 0 {
+            f.write_str(&digits)
+        } else if digits.len() > scale {
+            let (int, frac) = digits.split_at(digits.len() - scale);
+            write!(f, "{}.{}", int, frac)
+        } else {
+            write!(f, "0.{:0>width$}", digits, width = scale)
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    /// Parses a decimal number in a plain notation (eg. `-1234.56`). Exponent notation is not
+    /// supported.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int, frac) = match unsigned.split_once('.') {
+            Some((int, frac)) => (int, frac),
+            None => (unsigned, ""),
+        };
+        let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() || !is_digits(int) || !is_digits(frac) {
+            return Err(Error::Other(format!("invalid decimal number: '{}'", s)));
+        }
+        if frac.len() > Self::MAX_SCALE as usize {
+            return Err(Error::Other(format!("decimal scale too large: '{}'", s)));
+        }
+        let mut digits = String::with_capacity(int.len() + frac.len() + 1);
+        if negative {
+            digits.push('-');
+        }
+        digits.push_str(int);
+        digits.push_str(frac);
+        let mantissa: i128 = digits
+            .parse()
+            .map_err(|_| Error::Other(format!("decimal number out of range: '{}'", s)))?;
+        Ok(Decimal {
+            mantissa,
+            scale: frac.len() as u8,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::decimal::Decimal;
+
+    #[test]
+    fn display() {
+        assert_eq!(Decimal::new(123456, 2).to_string(), "1234.56");
+        assert_eq!(Decimal::new(-5, 3).to_string(), "-0.005");
+        assert_eq!(Decimal::new(42, 0).to_string(), "42");
+        assert_eq!(Decimal::new(0, 2).to_string(), "0.00");
+        assert_eq!(
+            Decimal::new(i128::MIN, 0).to_string(),
+            i128::MIN.to_string()
+        );
+    }
+
+    #[test]
+    fn parse() {
+        for s in [
+            "1234.56",
+            "-0.005",
+            "42",
+            "0.00",
+            "-170141183460469231731687303715884105728",
+        ] {
+            let d: Decimal = s.parse().unwrap();
+            assert_eq!(d.to_string(), s);
+        }
+        assert_eq!("+1.5".parse::<Decimal>().unwrap(), Decimal::new(15, 1));
+        assert_ne!(Decimal::new(10, 1), Decimal::new(100, 2));
+        assert!("".parse::<Decimal>().is_err());
+        assert!(".5".parse::<Decimal>().is_err());
+        assert!("1e10".parse::<Decimal>().is_err());
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("1234567890123456789012345678901234567890"
+            .parse::<Decimal>()
+            .is_err());
+        assert_eq!(Decimal::try_new(1, 39), None);
+    }
+}
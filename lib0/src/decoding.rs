@@ -1,6 +1,13 @@
+//! Decoding primitives compatible with the [lib0](https://github.com/dmonad/lib0) JavaScript
+//! library. All of them are provided by the [Read] trait, which can be implemented over any kind
+//! of input. Out of the box it's implemented for byte slices (using [Cursor]) and - using
+//! [IoReader] - for any [std::io::Read] implementation.
+
 use crate::error::Error;
 use crate::number::{Signed, SignedVarInt, VarInt};
 
+/// [Read] implementation backed by a byte slice. Since the whole input is already in memory,
+/// decoded strings and buffers are borrowed from it without copying.
 #[derive(Default)]
 pub struct Cursor<'a> {
     pub buf: &'a [u8],
@@ -49,7 +56,51 @@ impl<'a> Read for Cursor<'a> {
     }
 }
 
+/// [Read] implementation backed by any [std::io::Read] input, ie. a file or a network stream.
+/// Data is pulled from the underlying input on demand, so that only the currently decoded value
+/// needs to be kept in memory.
+#[derive(Debug)]
+pub struct IoReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+}
+
+impl<R: std::io::Read> IoReader<R> {
+    pub fn new(inner: R) -> Self {
+        IoReader {
+            inner,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Reads all of the remaining bytes of an underlying input.
+    pub fn read_to_end(&mut self) -> Result<&[u8], Error> {
+        self.buf.clear();
+        self.inner.read_to_end(&mut self.buf)?;
+        Ok(&self.buf)
+    }
+
+    /// Returns underlying input back.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: std::io::Read> Read for IoReader<R> {
+    fn read_exact(&mut self, len: usize) -> Result<&[u8], Error> {
+        self.buf.resize(len, 0);
+        match self.inner.read_exact(&mut self.buf) {
+            Ok(()) => Ok(&self.buf),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Err(Error::EndOfBuffer(len)),
+            Err(e) => Err(Error::IO(e)),
+        }
+    }
+}
+
+/// Trait implementing lib0 decoding primitives. Any input capable of reading a slice of bytes
+/// can implement it in order to get access to all of them.
 pub trait Read: Sized {
+    /// Take a slice of the next `len` bytes and advance the position by `len`.
     fn read_exact(&mut self, len: usize) -> Result<&[u8], Error>;
 
     /// Read a single byte.
@@ -102,7 +153,7 @@ pub trait Read: Sized {
     /// Read string of variable length.
     fn read_string(&mut self) -> Result<&str, Error> {
         let buf = self.read_buf()?;
-        Ok(unsafe { std::str::from_utf8_unchecked(buf) })
+        Ok(std::str::from_utf8(buf)?)
     }
 
     /// Read float32 in big endian order
@@ -137,4 +188,12 @@ pub trait Read: Sized {
         buf.copy_from_slice(slice);
         Ok(u64::from_be_bytes(buf))
     }
+
+    /// Read 128-bit signed integer in big endian order
+    fn read_i128(&mut self) -> Result<i128, Error> {
+        let mut buf = [0; 16];
+        let slice = self.read_exact(16)?;
+        buf.copy_from_slice(slice);
+        Ok(i128::from_be_bytes(buf))
+    }
 }
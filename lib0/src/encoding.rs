@@ -1,3 +1,8 @@
+//! Encoding primitives compatible with the [lib0](https://github.com/dmonad/lib0) JavaScript
+//! library. All of them are provided by the [Write] trait, which can be implemented over any kind
+//! of output. Out of the box it's implemented for `Vec<u8>` buffers and - using [IoWriter] - for
+//! any [std::io::Write] implementation.
+
 use crate::number::{Signed, SignedVarInt, VarInt};
 
 impl Write for Vec<u8> {
@@ -10,9 +15,50 @@ impl Write for Vec<u8> {
     }
 }
 
+/// [Write] implementation backed by any [std::io::Write] output, ie. a file or a network stream.
+///
+/// Since lib0 write operations are infallible, the first I/O error that happens is stored and all
+/// writes following it are ignored. It can be retrieved using [IoWriter::finish].
+#[derive(Debug)]
+pub struct IoWriter<W> {
+    inner: W,
+    error: Option<std::io::Error>,
+}
+
+impl<W: std::io::Write> IoWriter<W> {
+    pub fn new(inner: W) -> Self {
+        IoWriter { inner, error: None }
+    }
+
+    /// Flushes underlying output and returns it back, unless any of the previous writes failed.
+    pub fn finish(mut self) -> Result<W, std::io::Error> {
+        match self.error {
+            Some(e) => Err(e),
+            None => {
+                self.inner.flush()?;
+                Ok(self.inner)
+            }
+        }
+    }
+}
+
+impl<W: std::io::Write> Write for IoWriter<W> {
+    fn write_all(&mut self, buf: &[u8]) {
+        if self.error.is_none() {
+            if let Err(e) = self.inner.write_all(buf) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Trait implementing lib0 encoding primitives. Any output capable of writing a slice of bytes
+/// can implement it in order to get access to all of them.
 pub trait Write: Sized {
+    /// Write all bytes of a given buffer as they are.
     fn write_all(&mut self, buf: &[u8]);
 
+    /// Write a single byte.
     fn write_u8(&mut self, value: u8) {
         self.write_all(&[value])
     }
@@ -100,4 +146,10 @@ pub trait Write: Sized {
     fn write_u64(&mut self, num: u64) {
         self.write_all(&num.to_be_bytes())
     }
+
+    /// Write 128-bit signed integer in 16 bytes in big endian order.
+    #[inline]
+    fn write_i128(&mut self, num: i128) {
+        self.write_all(&num.to_be_bytes())
+    }
 }
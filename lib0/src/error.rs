@@ -14,9 +14,30 @@ pub enum Error {
     #[error("while reading, an unexpected value was found")]
     UnexpectedValue,
 
+    #[error("decoded string was not a valid UTF-8 sequence")]
+    InvalidUtf8(#[from] std::str::Utf8Error),
+
+    #[error("unknown type reference number: {0}")]
+    InvalidTypeRef(u8),
+
+    #[error("decoded clock value is outside of the allowed range")]
+    ClockOverflow,
+
+    #[error("payload length mismatch: expected {expected} bytes, found {actual}")]
+    LengthMismatch { expected: usize, actual: usize },
+
+    #[error("payload checksum mismatch: expected {expected:#010x}, found {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+
     #[error("`{0}`")]
     Other(String),
 
+    /// Decoded update couldn't be applied, ie. because it has been rejected by one of the document
+    /// validation hooks. Yrs uses it to report its `UpdateError`, which can be obtained using
+    /// [std::error::Error::downcast_ref].
+    #[error("update couldn't be applied: {0}")]
+    Update(Box<dyn std::error::Error + Send + Sync>),
+
     #[cfg(not(feature = "lib0-serde"))]
     #[error("JSON parsing error: {0}")]
     InvalidJSON(#[from] crate::json_parser::JsonParseError),
@@ -1,5 +1,44 @@
+//! Binary encoding library compatible with [lib0](https://github.com/dmonad/lib0) - the encoding
+//! used by Yjs and its ecosystem: document updates, state vectors as well as the sync and
+//! awareness protocol messages exchanged by providers.
+//!
+//! - [encoding::Write] and [decoding::Read] traits provide encoding primitives: fixed size and
+//!   variable length integers, floats, strings and byte buffers.
+//! - [any::Any] represents JSON-like values, that can be encoded using lib0 encoding.
+//! - [decimal::Decimal] is a fixed-point decimal number, which can be stored in [any::Any].
+//!
+//! Both traits are implemented for in-memory buffers (`Vec<u8>` and [decoding::Cursor]
+//! respectively) as well as for [std::io] streams ([encoding::IoWriter] and
+//! [decoding::IoReader]).
+//!
+//! # Example
+//!
+//! Framing a Yjs sync protocol message (`messageSync` / `messageYjsSyncStep1`) carrying an
+//! encoded state vector:
+//!
+//! ```rust
+//! use lib0::decoding::{Cursor, Read};
+//! use lib0::encoding::Write;
+//!
+//! const MSG_SYNC: u32 = 0;
+//! const MSG_SYNC_STEP_1: u32 = 0;
+//!
+//! let state_vector: &[u8] = &[1, 123, 3];
+//! let mut encoder = Vec::new();
+//! encoder.write_var(MSG_SYNC);
+//! encoder.write_var(MSG_SYNC_STEP_1);
+//! encoder.write_buf(state_vector);
+//! assert_eq!(encoder, vec![0, 0, 3, 1, 123, 3]);
+//!
+//! let mut decoder = Cursor::new(&encoder);
+//! assert_eq!(decoder.read_var::<u32>().unwrap(), MSG_SYNC);
+//! assert_eq!(decoder.read_var::<u32>().unwrap(), MSG_SYNC_STEP_1);
+//! assert_eq!(decoder.read_buf().unwrap(), state_vector);
+//! ```
+
 pub mod any;
 pub mod binary;
+pub mod decimal;
 pub mod decoding;
 pub mod encoding;
 pub mod error;
@@ -8,5 +47,5 @@ pub mod number;
 #[cfg(not(feature = "lib0-serde"))]
 mod json_parser;
 
-#[cfg(feature = "lib0-serde")]
+#[cfg(feature = "serde")]
 pub mod serde;
@@ -7,6 +7,8 @@ use std::mem::size_of;
 pub const F64_MAX_SAFE_INTEGER: f64 = (i64::pow(2, 53) - 1) as f64;
 pub const F64_MIN_SAFE_INTEGER: f64 = -F64_MAX_SAFE_INTEGER;
 
+/// Integer types, which can be written using lib0 variable length encoding: smaller numbers take
+/// fewer bytes. See [Write::write_var] and [Read::read_var].
 pub trait VarInt: Sized + Copy {
     fn write<W: Write>(&self, w: &mut W);
     fn read<R: Read>(r: &mut R) -> Result<Self, Error>;
@@ -274,10 +276,10 @@ fn read_var_i64<R: Read>(reader: &mut R) -> Result<i64, Error> {
     }
     loop {
         r = reader.read_u8()?;
-        num |= (r as i64 & 0b01111111 as i64) << len;
+        num |= i64::wrapping_shl(r as i64 & 0b01111111 as i64, len);
         len += 7;
         if r < 0b10000000 as u8 {
-            return Ok(if is_negative { -num } else { num });
+            return Ok(if is_negative { num.wrapping_neg() } else { num });
         }
         if len > 70 {
             return Err(Error::VarIntSizeExceeded(70));
@@ -285,6 +287,8 @@ fn read_var_i64<R: Read>(reader: &mut R) -> Result<i64, Error> {
     }
 }
 
+/// Signed integer types, which can be written using lib0 variable length encoding while keeping
+/// a sign of zero. See [Write::write_var_signed] and [Read::read_var_signed].
 pub trait SignedVarInt: Sized + Copy {
     fn write_signed<W: Write>(value: &Signed<Self>, w: &mut W);
     fn read_signed<R: Read>(r: &mut R) -> Result<Signed<Self>, Error>;
@@ -368,10 +372,10 @@ impl SignedVarInt for i64 {
         }
         loop {
             r = reader.read_u8()?;
-            num |= (r as i64 & 0b01111111 as i64) << len;
+            num |= i64::wrapping_shl(r as i64 & 0b01111111 as i64, len);
             len += 7;
             if r < 0b10000000 as u8 {
-                let num = if is_negative { -num } else { num };
+                let num = if is_negative { num.wrapping_neg() } else { num };
                 return Ok(Signed::new(num, is_negative));
             }
             if len > 70 {
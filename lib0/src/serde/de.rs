@@ -96,6 +96,27 @@ impl<'de> Deserialize<'de> for Any {
                 }
             }
 
+            fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Any::Int128(v))
+            }
+
+            fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                if v > i128::MAX as u128 {
+                    Err(serde::de::Error::custom(format!(
+                        "Value {} out of range for i128",
+                        v
+                    )))
+                } else {
+                    Ok(Any::Int128(v as i128))
+                }
+            }
+
             fn visit_f32<E>(self, v: f32) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
@@ -254,6 +275,9 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
             Any::Bool(_) => self.deserialize_bool(visitor),
             Any::Number(_) => self.deserialize_f64(visitor),
             Any::BigInt(_) => self.deserialize_i64(visitor),
+            Any::Int128(i) => visitor.visit_i128(*i),
+            Any::Decimal(d) => visitor.visit_string(d.to_string()),
+            Any::Date(millis) => visitor.visit_i64(*millis),
             Any::String(_) => self.deserialize_string(visitor),
             Any::Buffer(_) => self.deserialize_byte_buf(visitor),
             Any::Array(_) => self.deserialize_seq(visitor),
@@ -1,4 +1,4 @@
-use crate::any::Any;
+use crate::any::{Any, IsoDate};
 use serde::ser::{
     SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
     SerializeTupleStruct, SerializeTupleVariant,
@@ -24,6 +24,9 @@ impl Serialize for Any {
             Any::Bool(value) => serializer.serialize_bool(*value),
             Any::Number(value) => serializer.serialize_f64(*value),
             Any::BigInt(value) => serializer.serialize_i64(*value),
+            Any::Int128(value) => serializer.serialize_i128(*value),
+            Any::Decimal(value) => serializer.collect_str(value),
+            Any::Date(millis) => serializer.collect_str(&IsoDate(*millis)),
             Any::String(value) => serializer.serialize_str(value.as_ref()),
             Any::Array(values) => {
                 let mut seq = serializer.serialize_seq(Some(values.len()))?;
@@ -102,6 +105,11 @@ impl Serializer for AnySerializer {
         Ok(Any::BigInt(v))
     }
 
+    #[inline]
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(Any::Int128(v))
+    }
+
     #[inline]
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
         self.serialize_i64(v.into())
@@ -125,6 +133,14 @@ impl Serializer for AnySerializer {
         )
     }
 
+    #[inline]
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i128(
+            v.try_into()
+                .map_err(|_e| AnySerializeError::UnrepresentableInt)?,
+        )
+    }
+
     #[inline]
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
         self.serialize_f64(v.into())
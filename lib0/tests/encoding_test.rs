@@ -1,7 +1,9 @@
 use lib0::any::Any;
-use lib0::decoding::{Cursor, Read};
-use lib0::encoding::Write;
+use lib0::decimal::Decimal;
+use lib0::decoding::{Cursor, IoReader, Read};
+use lib0::encoding::{IoWriter, Write};
 use proptest::prelude::*;
+use std::convert::TryFrom;
 
 pub fn arb_any() -> impl Strategy<Value = Any> {
     let leaf = prop_oneof![
@@ -12,6 +14,10 @@ pub fn arb_any() -> impl Strategy<Value = Any> {
         any::<i64>().prop_map(|i| Any::Number(i as f64)),
         any::<String>().prop_map(|i| Any::String(i.into())),
         any::<Box<[u8]>>().prop_map(Any::Buffer),
+        any::<i128>().prop_map(Any::Int128),
+        any::<i64>().prop_map(Any::Date),
+        (any::<i128>(), 0..=Decimal::MAX_SCALE)
+            .prop_map(|(mantissa, scale)| Any::Decimal(Decimal::new(mantissa, scale))),
     ]
     .boxed();
 
@@ -31,7 +37,55 @@ proptest! {
         any.encode(&mut encoder);
         let mut decoder = Cursor::new(encoder.as_slice());
         let copy = Any::decode(&mut decoder).unwrap();
-        assert_eq!(any, copy);
+        assert_eq!(decoded(any), copy);
+    }
+}
+
+/// Returns a value, which is expected to be decoded back after encoding `any`. Without
+/// `any-extensions` feature, Yrs-specific variants are encoded using representations understood by
+/// lib0 in JavaScript.
+fn decoded(any: Any) -> Any {
+    match any {
+        Any::Int128(num) if i64::try_from(num).is_ok() => Any::BigInt(num as i64),
+        #[cfg(not(feature = "any-extensions"))]
+        Any::Int128(num) => Any::String(num.to_string().into()),
+        #[cfg(not(feature = "any-extensions"))]
+        Any::Decimal(num) => Any::String(num.to_string().into()),
+        #[cfg(not(feature = "any-extensions"))]
+        Any::Date(millis) => Any::Number(millis as f64),
+        Any::Array(arr) => Any::Array(arr.into_vec().into_iter().map(decoded).collect()),
+        Any::Map(map) => Any::Map(Box::new(
+            map.into_iter().map(|(k, v)| (k, decoded(v))).collect(),
+        )),
+        other => other,
+    }
+}
+
+#[cfg(not(feature = "any-extensions"))]
+#[test]
+fn encoding_any_lib0_compatible() {
+    let cases = [
+        (Any::Int128(-42), Any::BigInt(-42)),
+        (
+            Any::Int128(i64::MAX as i128 + 1),
+            Any::String("9223372036854775808".into()),
+        ),
+        (
+            Any::Decimal(Decimal::new(1250, 2)),
+            Any::String("12.50".into()),
+        ),
+        (
+            Any::Date(1_700_000_000_123),
+            Any::Number(1_700_000_000_123.0),
+        ),
+    ];
+    for (any, expected) in cases {
+        let mut encoder = Vec::new();
+        any.encode(&mut encoder);
+        // Yrs extension tags (113-115) are unknown to lib0 in JavaScript
+        assert!(encoder[0] > 115, "{:?}", any);
+        let copy = Any::decode(&mut Cursor::new(encoder.as_slice())).unwrap();
+        assert_eq!(copy, expected);
     }
 }
 
@@ -115,7 +169,7 @@ impl EncodingTypes {
             }
         }
     }
-    fn read(&self, decoder: &mut Cursor) {
+    fn read<R: Read>(&self, decoder: &mut R) {
         match self {
             EncodingTypes::Byte(input) => {
                 let read = decoder.read_u8().unwrap();
@@ -187,7 +241,7 @@ impl EncodingTypes {
             }
             EncodingTypes::Any(input) => {
                 let read = Any::decode(decoder).unwrap();
-                assert_eq!(read, *input);
+                assert_eq!(read, decoded(input.clone()));
             }
         }
     }
@@ -202,3 +256,26 @@ proptest! {
         val.read(&mut decoder)
     }
 }
+
+proptest! {
+    #[test]
+    fn encoding_io_prop(val: EncodingTypes) {
+        let mut encoder = IoWriter::new(Vec::new());
+        val.write(&mut encoder);
+        let buf = encoder.finish().unwrap();
+        let mut decoder = IoReader::new(buf.as_slice());
+        val.read(&mut decoder);
+        assert!(decoder.read_to_end().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn io_reader_end_of_stream() {
+    let mut encoder = Vec::new();
+    encoder.write_string("hello");
+    let mut decoder = IoReader::new(&encoder[..3]);
+    assert!(matches!(
+        decoder.read_string(),
+        Err(lib0::error::Error::EndOfBuffer(5))
+    ));
+}
@@ -2,6 +2,7 @@
 mod test {
     use lib0::any::Any;
     use std::collections::HashMap;
+    use std::time::{Duration, UNIX_EPOCH};
 
     fn roundtrip(any: &Any) -> Any {
         // encode
@@ -82,4 +83,26 @@ mod test {
         let actual = roundtrip(&expected);
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn json_any_date() {
+        let cases = [
+            (0, "1970-01-01T00:00:00.000Z"),
+            (951_782_400_000, "2000-02-29T00:00:00.000Z"),
+            (1_700_000_000_123, "2023-11-14T22:13:20.123Z"),
+            (-1, "1969-12-31T23:59:59.999Z"),
+            (253_402_300_800_000, "+010000-01-01T00:00:00.000Z"),
+            (-62_198_755_200_000, "-000001-01-01T00:00:00.000Z"),
+        ];
+        for (millis, iso) in cases {
+            let any = Any::Date(millis);
+            assert_eq!(any.to_string(), iso);
+            let mut buf = String::new();
+            any.to_json(&mut buf);
+            assert_eq!(buf, format!("\"{}\"", iso));
+        }
+
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(Any::from(time), Any::Date(1_700_000_000_123));
+    }
 }
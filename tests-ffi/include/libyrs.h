@@ -81,6 +81,14 @@ typedef struct YXmlAttrIter {} YXmlAttrIter;
  */
 typedef struct YXmlTreeWalker {} YXmlTreeWalker;
 
+/**
+ * A position within an indexed sequence of a shared type (`YText`, `YArray` or `YXmlText`),
+ * which - unlike an ordinary index - remains valid while concurrent changes are being applied to
+ * that sequence. It can be serialized and sent to remote peers, which can resolve it against
+ * their own document state.
+ */
+typedef struct YRelativePosition {} YRelativePosition;
+
 
 #include <stdarg.h>
 #include <stdbool.h>
@@ -159,6 +167,11 @@ typedef struct YXmlTreeWalker {} YXmlTreeWalker;
  */
 #define Y_XML_TEXT 5
 
+/**
+ * Flag used by `YInput` and `YOutput` to tag content, which is a `YDoc` subdocument.
+ */
+#define Y_DOC 7
+
 /**
  * Flag used to mark a truthy boolean numbers.
  */
@@ -187,6 +200,18 @@ typedef struct YXmlTreeWalker {} YXmlTreeWalker;
  */
 #define Y_OFFSET_UTF32 2
 
+/**
+ * Flag used by `YRelativePosition` to determine, that a position sticks to an element placed
+ * right after it. Insertions made at that position will be placed before the position.
+ */
+#define Y_ASSOC_AFTER 0
+
+/**
+ * Flag used by `YRelativePosition` to determine, that a position sticks to an element placed
+ * right before it. Insertions made at that position will be placed after the position.
+ */
+#define Y_ASSOC_BEFORE -1
+
 /**
  * Error code: couldn't read data from input stream.
  */
@@ -217,6 +242,31 @@ typedef struct YXmlTreeWalker {} YXmlTreeWalker;
  */
 #define ERR_CODE_OTHER 6
 
+/**
+ * Error code: decoded string was not a valid UTF-8 sequence.
+ */
+#define ERR_CODE_INVALID_UTF8 7
+
+/**
+ * Error code: decoded shared type reference was not among known types.
+ */
+#define ERR_CODE_INVALID_TYPE_REF 8
+
+/**
+ * Error code: decoded block clock range was outside of allowed bounds.
+ */
+#define ERR_CODE_CLOCK_OVERFLOW 9
+
+/**
+ * Error code: payload length or checksum didn't match its integrity trailer.
+ */
+#define ERR_CODE_INTEGRITY 10
+
+/**
+ * Error code: update has been rejected by document's filter, write policy or limits.
+ */
+#define ERR_CODE_UPDATE_REJECTED 11
+
 /**
  * Tag used to identify `YPathSegment` storing a *char parameter.
  */
@@ -294,6 +344,7 @@ typedef union YOutputContent {
   struct YOutput *array;
   struct YMapEntry *map;
   Branch *y_type;
+  YDoc *y_doc;
 } YOutputContent;
 
 /**
@@ -487,6 +538,40 @@ typedef struct YAfterTransactionEvent {
  */
 typedef YTransaction YTransaction;
 
+/**
+ * Event generated for callbacks subscribed using `ydoc_observe_subdocs`. It contains subdocuments
+ * added, removed or requested to load within a committed transaction.
+ *
+ * Documents passed in this event are released together with it once a callback returns. Use
+ * `ydoc_clone` in order to keep a reference to any of them.
+ */
+typedef struct YSubdocsEvent {
+  /**
+   * Number of subdocuments stored in `added` field.
+   */
+  int added_len;
+  /**
+   * Number of subdocuments stored in `removed` field.
+   */
+  int removed_len;
+  /**
+   * Number of subdocuments stored in `loaded` field.
+   */
+  int loaded_len;
+  /**
+   * Subdocuments inserted into shared collections of a parent document.
+   */
+  YDoc **added;
+  /**
+   * Subdocuments removed from shared collections of a parent document.
+   */
+  YDoc **removed;
+  /**
+   * Subdocuments, which have been requested to load.
+   */
+  YDoc **loaded;
+} YSubdocsEvent;
+
 typedef struct YMapInputData {
   char **keys;
   struct YInput *values;
@@ -500,6 +585,7 @@ typedef union YInputContent {
   unsigned char *buf;
   struct YInput *values;
   struct YMapInputData map;
+  YDoc *y_doc;
 } YInputContent;
 
 /**
@@ -831,6 +917,16 @@ typedef struct YEventKeyChange {
   const struct YOutput *new_value;
 } YEventKeyChange;
 
+/**
+ * A position within an indexed sequence of a shared type (`YText`, `YArray` or `YXmlText`),
+ * which - unlike an ordinary index - remains valid while concurrent changes are being applied to
+ * that sequence. It can be serialized and sent to remote peers, which can resolve it against
+ * their own document state.
+ *
+ * Use `yrelative_position_destroy` in order to release its resources.
+ */
+typedef YRelativePosition YRelativePosition;
+
 /**
  * Releases all memory-allocated resources bound to given document.
  */
@@ -897,6 +993,59 @@ unsigned int ydoc_observe_after_transaction(YDoc *doc,
 
 void ydoc_unobserve_after_transaction(YDoc *doc, unsigned int subscription_id);
 
+/**
+ * Returns a globally unique identifier of a given document. Unlike client ID, it's shared by all
+ * replicas of the same document and is used to identify subdocuments.
+ *
+ * Returned value is a null-terminated UTF-8 string, which must be released using
+ * [ystring_destroy] function.
+ */
+char *ydoc_guid(const YDoc *doc);
+
+/**
+ * Returns `Y_TRUE` if a given document has been requested to load its contents. Subdocuments
+ * received from remote peers are not loaded unless they were inserted with an auto-load flag or
+ * requested to load using [ydoc_load] function.
+ */
+char ydoc_should_load(const YDoc *doc);
+
+/**
+ * Returns `Y_TRUE` if a given document, when used as a subdocument, should be loaded
+ * automatically by the peers that received it.
+ */
+char ydoc_auto_load(const YDoc *doc);
+
+/**
+ * Requests a given subdocument to be loaded. Subscribers of [ydoc_observe_subdocs] of its parent
+ * document will be notified about it once `parent_txn` is committed. It's up to them to actually
+ * fetch subdocument's contents.
+ */
+void ydoc_load(const YDoc *doc, YTransaction *parent_txn);
+
+/**
+ * Returns a new handle to the same document as a given `doc`. This is useful i.e. to keep
+ * a reference to a subdocument read from a `YOutput` cell or received in `YSubdocsEvent`, after
+ * these have been released.
+ *
+ * Use [ydoc_destroy] in order to release a returned handle. This doesn't affect other handles.
+ */
+YDoc *ydoc_clone(const YDoc *doc);
+
+/**
+ * Subscribes a given callback function `cb` to be notified whenever subdocuments of a given
+ * document have been added, removed or requested to load within a committed transaction.
+ * Returns a subscription ID which can be then used to unsubscribe this callback by using
+ * [ydoc_unobserve_subdocs] function.
+ */
+unsigned int ydoc_observe_subdocs(YDoc *doc,
+                                  void *state,
+                                  void (*cb)(void*, struct YSubdocsEvent*));
+
+/**
+ * Releases a callback subscribed via [ydoc_observe_subdocs] function.
+ */
+void ydoc_unobserve_subdocs(YDoc *doc, unsigned int subscription_id);
+
 /**
  * Starts a new read-write transaction on a given document. All other operations happen in context
  * of a transaction. Yrs transactions do not follow ACID rules. Once a set of operations is
@@ -1087,6 +1236,12 @@ char *yupdate_debug_v2(const unsigned char *update, int update_len);
  * - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
  * - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
  * - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+ * - `ERR_CODE_INVALID_UTF8` (**7**): decoded string was not a valid UTF-8 sequence.
+ * - `ERR_CODE_INVALID_TYPE_REF` (**8**): decoded shared type reference was not among known types.
+ * - `ERR_CODE_CLOCK_OVERFLOW` (**9**): decoded block clock range was outside of allowed bounds.
+ * - `ERR_CODE_INTEGRITY` (**10**): payload length or checksum didn't match its trailer.
+ * - `ERR_CODE_UPDATE_REJECTED` (**11**): update has been rejected by document's filter, write
+ *   policy or limits.
  */
 int ytransaction_apply(YTransaction *txn,
                        const unsigned char *diff,
@@ -1106,6 +1261,12 @@ int ytransaction_apply(YTransaction *txn,
  * - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
  * - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
  * - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+ * - `ERR_CODE_INVALID_UTF8` (**7**): decoded string was not a valid UTF-8 sequence.
+ * - `ERR_CODE_INVALID_TYPE_REF` (**8**): decoded shared type reference was not among known types.
+ * - `ERR_CODE_CLOCK_OVERFLOW` (**9**): decoded block clock range was outside of allowed bounds.
+ * - `ERR_CODE_INTEGRITY` (**10**): payload length or checksum didn't match its trailer.
+ * - `ERR_CODE_UPDATE_REJECTED` (**11**): update has been rejected by document's filter, write
+ *   policy or limits.
  */
 int ytransaction_apply_v2(YTransaction *txn,
                           const unsigned char *diff,
@@ -1123,6 +1284,19 @@ int ytext_len(const Branch *txt);
  */
 char *ytext_string(const Branch *txt);
 
+/**
+ * Returns a string content of a `YText` as it was at the moment when a given `snapshot`
+ * (generated by: `ytransaction_snapshot`) has been made. Content removed since then can only be
+ * restored if a document has GC option flag turned off.
+ *
+ * Returns a null pointer if `snapshot` could not be decoded. Otherwise a returned value is
+ * a null-terminated UTF-8 string, which must be released using [ystring_destroy] function.
+ */
+char *ytext_string_at(const Branch *txt,
+                      YTransaction *txn,
+                      const unsigned char *snapshot,
+                      int snapshot_len);
+
 /**
  * Inserts a null-terminated UTF-8 encoded string a given `index`. `index` value must be between
  * 0 and a length of a `YText` (inclusive, accordingly to [ytext_len] return value), otherwise this
@@ -1221,6 +1395,24 @@ void yarray_remove_range(const Branch *array, YTransaction *txn, int index, int
 
 void yarray_move(const Branch *array, YTransaction *txn, int source, int target);
 
+/**
+ * Moves all elements found within `start`..`end` indexes range (both side inclusive) of a given
+ * `array` into a new position pointed by `target` index. All elements inserted concurrently by
+ * other peers inside of moved range will be moved as well after synchronization.
+ *
+ * `assoc_start`/`assoc_end` flags (either `Y_TRUE` or `Y_FALSE`) are used to mark if a range
+ * should include elements, that might have been inserted concurrently at its edges.
+ *
+ * All indexes must fit into boundaries of an array, otherwise it will panic at runtime.
+ */
+void yarray_move_range(const Branch *array,
+                       YTransaction *txn,
+                       int start,
+                       char assoc_start,
+                       int end,
+                       char assoc_end,
+                       int target);
+
 /**
  * Returns an iterator, which can be used to traverse over all elements of an `array` (`array`'s
  * length can be determined using [yarray_len] function).
@@ -1695,6 +1887,15 @@ struct YInput yinput_ymap(char **keys, struct YInput *values, int len);
  */
 struct YInput yinput_ytext(char *str);
 
+/**
+ * Function constructor used to create a `YInput` cell with a `doc`, which will be inserted as
+ * a subdocument. Inserted document must not be a part of any other document.
+ *
+ * This function doesn't allocate any heap resources and doesn't release any on its own, therefore
+ * its up to a caller to free resources once a structure is no longer needed.
+ */
+struct YInput yinput_ydoc(YDoc *doc);
+
 /**
  * Function constructor used to create a nested `YXmlElement` `YInput` cell with a specified
  * tag name, which must be a null-terminated UTF-8 character pointer.
@@ -1821,6 +2022,15 @@ Branch *youtput_read_ytext(const struct YOutput *val);
  */
 Branch *youtput_read_yxmltext(const struct YOutput *val);
 
+/**
+ * Attempts to read the value for a given `YOutput` pointer as a `YDoc` subdocument.
+ *
+ * Returns a null pointer in case when a value stored under current `YOutput` cell
+ * is not a `YDoc`. Underlying heap resources are released automatically as part of
+ * [youtput_destroy] destructor.
+ */
+YDoc *youtput_read_ydoc(const struct YOutput *val);
+
 /**
  * Subscribes a given callback function `cb` to changes made by this `YText` instance. Callbacks
  * are triggered whenever a `ytransaction_commit` is called.
@@ -2091,4 +2301,60 @@ void yevent_keys_destroy(struct YEventKeyChange *keys, int len);
  */
 char ytype_kind(const Branch *branch);
 
+/**
+ * Creates a relative position pointing to a given `index` of an indexed sequence of a `branch`
+ * (`YText`, `YArray` or `YXmlText`). `assoc` is either `Y_ASSOC_AFTER` or `Y_ASSOC_BEFORE`
+ * and it determines, which neighbour element a position sticks to. If `index` is outside of
+ * a sequence bounds, a position will point to the end of that sequence.
+ *
+ * Returns a null pointer if `branch` has not been integrated into a document. Otherwise a
+ * returned position must be released using [yrelative_position_destroy] function.
+ */
+YRelativePosition *yrelative_position_from_index(const Branch *branch,
+                                                 const YTransaction *txn,
+                                                 int index,
+                                                 int assoc);
+
+/**
+ * Releases all memory-allocated resources bound to a given relative position.
+ */
+void yrelative_position_destroy(YRelativePosition *pos);
+
+/**
+ * Returns an association of a given relative position: either `Y_ASSOC_AFTER` or
+ * `Y_ASSOC_BEFORE`.
+ */
+int yrelative_position_assoc(const YRelativePosition *pos);
+
+/**
+ * Serializes a given relative position into binary payload using lib0 version 1 encoding, which
+ * is compatible with Yjs `encodeRelativePosition`. A length of generated binary will be passed
+ * within a `len` out parameter.
+ *
+ * Once no longer needed, a returned binary can be disposed using [ybinary_destroy] function.
+ */
+unsigned char *yrelative_position_encode(const YRelativePosition *pos, int *len);
+
+/**
+ * Deserializes a relative position from a binary payload (generated by:
+ * [yrelative_position_encode]) of a given length `len`.
+ *
+ * Returns a null pointer if payload could not be decoded. Otherwise a returned position must be
+ * released using [yrelative_position_destroy] function.
+ */
+YRelativePosition *yrelative_position_decode(const unsigned char *binary, int len);
+
+/**
+ * Resolves a relative position into an index within a shared type it points to, using a current
+ * state of a document bound to a given transaction. On success returns `Y_TRUE` and writes
+ * a shared type pointer and an index into `out_branch` and `out_index` out parameters.
+ *
+ * Returns `Y_FALSE` if a position could not be resolved, ie. because an element it's anchored to
+ * has not yet been integrated into a document.
+ */
+char yrelative_position_resolve(const YRelativePosition *pos,
+                                const YTransaction *txn,
+                                Branch **out_branch,
+                                int *out_index);
+
 #endif
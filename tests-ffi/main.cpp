@@ -1376,4 +1376,143 @@ TEST_CASE("YDoc snapshots") {
     ystring_destroy(str);
     ytransaction_commit(txn);
     ydoc_destroy(doc);
-}
\ No newline at end of file
+}
+
+TEST_CASE("YArray move range") {
+    YDoc* doc = ydoc_new_with_id(1);
+    YTransaction* txn = ytransaction_new(doc);
+    Branch* arr = yarray(txn, "test");
+
+    YInput* args = (YInput*)malloc(4 * sizeof(YInput));
+    for (int i = 0; i < 4; i++) {
+        args[i] = yinput_long(i + 1);
+    }
+    yarray_insert_range(arr, txn, 0, args, 4); // state after: [1, 2, 3, 4]
+    free(args);
+
+    yarray_move_range(arr, txn, 1, Y_TRUE, 2, Y_FALSE, 4); // state after: [1, 4, 2, 3]
+
+    long long expected[] = {1, 4, 2, 3};
+    for (int i = 0; i < 4; i++) {
+        YOutput* elem = yarray_get(arr, i);
+        REQUIRE_EQ(*youtput_read_long(elem), expected[i]);
+        youtput_destroy(elem);
+    }
+
+    ytransaction_commit(txn);
+    ydoc_destroy(doc);
+}
+
+TEST_CASE("YRelativePosition encode and resolve") {
+    YDoc* doc = ydoc_new_with_id(1);
+    YTransaction* txn = ytransaction_new(doc);
+    Branch* txt = ytext(txn, "test");
+    ytext_insert(txt, txn, 0, "abc", NULL);
+
+    // remember a position between "a" and "bc"
+    YRelativePosition* pos = yrelative_position_from_index(txt, txn, 1, Y_ASSOC_AFTER);
+    REQUIRE(pos != NULL);
+
+    int len = 0;
+    unsigned char* binary = yrelative_position_encode(pos, &len);
+    yrelative_position_destroy(pos);
+    ytransaction_commit(txn);
+
+    // position should be resolved correctly on a remote peer, that applied concurrent changes
+    int update_len = 0;
+    txn = ytransaction_new(doc);
+    unsigned char* update = ytransaction_state_diff_v1(txn, NULL, 0, &update_len);
+    ytransaction_commit(txn);
+
+    YDoc* remote = ydoc_new_with_id(2);
+    txn = ytransaction_new(remote);
+    Branch* remote_txt = ytext(txn, "test");
+    ytransaction_apply(txn, update, update_len);
+    ytext_insert(remote_txt, txn, 0, "xyz", NULL);
+
+    pos = yrelative_position_decode(binary, len);
+    REQUIRE(pos != NULL);
+    REQUIRE_EQ(yrelative_position_assoc(pos), Y_ASSOC_AFTER);
+
+    Branch* branch = NULL;
+    int index = 0;
+    REQUIRE_EQ(yrelative_position_resolve(pos, txn, &branch, &index), Y_TRUE);
+    REQUIRE(branch == remote_txt);
+    REQUIRE_EQ(index, 4);
+
+    yrelative_position_destroy(pos);
+    ybinary_destroy(binary, len);
+    ybinary_destroy(update, update_len);
+    ytransaction_commit(txn);
+    ydoc_destroy(remote);
+    ydoc_destroy(doc);
+}
+
+typedef struct SubdocsTest {
+    int added;
+    int loaded;
+} SubdocsTest;
+
+void observe_subdocs(void* state, YSubdocsEvent* e) {
+    SubdocsTest* t = (SubdocsTest*) state;
+    t->added += e->added_len;
+    t->loaded += e->loaded_len;
+}
+
+TEST_CASE("YDoc subdocs") {
+    SubdocsTest t;
+    t.added = 0;
+    t.loaded = 0;
+
+    YDoc* doc = ydoc_new_with_id(1);
+    unsigned int subscription_id = ydoc_observe_subdocs(doc, &t, observe_subdocs);
+
+    YTransaction* txn = ytransaction_new(doc);
+    Branch* map = ymap(txn, "test");
+    YDoc* subdoc = ydoc_new_with_id(2);
+    YInput input = yinput_ydoc(subdoc);
+    ymap_insert(map, txn, "sub", &input);
+    ytransaction_commit(txn);
+
+    REQUIRE_EQ(t.added, 1);
+
+    YOutput* out = ymap_get(map, "sub");
+    YDoc* read = youtput_read_ydoc(out);
+    REQUIRE(read != NULL);
+
+    char* expected = ydoc_guid(subdoc);
+    char* actual = ydoc_guid(read);
+    REQUIRE(!strcmp(expected, actual));
+
+    ystring_destroy(expected);
+    ystring_destroy(actual);
+    youtput_destroy(out);
+
+    ydoc_unobserve_subdocs(doc, subscription_id);
+    ydoc_destroy(subdoc);
+    ydoc_destroy(doc);
+}
+
+TEST_CASE("YText read at snapshot") {
+    YOptions o;
+    o.encoding = Y_OFFSET_UTF16;
+    o.id = 1;
+    o.skip_gc = 1;
+
+    YDoc* doc = ydoc_new_with_options(o);
+    YTransaction* txn = ytransaction_new(doc);
+    Branch* txt = ytext(txn, "test");
+    ytext_insert(txt, txn, 0, "hello", NULL);
+
+    int snapshot_len = 0;
+    unsigned char* snapshot = ytransaction_snapshot(txn, &snapshot_len);
+    ytext_insert(txt, txn, 5, " world", NULL);
+
+    char* str = ytext_string_at(txt, txn, snapshot, snapshot_len);
+    REQUIRE(!strcmp(str, "hello"));
+
+    ystring_destroy(str);
+    ybinary_destroy(snapshot, snapshot_len);
+    ytransaction_commit(txn);
+    ydoc_destroy(doc);
+}
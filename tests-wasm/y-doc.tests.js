@@ -130,4 +130,51 @@ export const testSnapshotState = tc => {
     Y.applyUpdate(d2, state)
 
     t.compare(txt2.toString(), 'hello')
-}
\ No newline at end of file
+}
+/**
+ * @param {t.TestCase} tc
+ */
+export const testOnBeforeTransaction = tc => {
+    const doc = new Y.YDoc(1)
+    const text = doc.getText('text')
+    let calls = 0
+    const sub = doc.onBeforeTransaction(() => calls++)
+
+    doc.transact(txn => text.insert(txn, 0, 'hello'))
+    t.compare(calls, 1)
+
+    sub.free()
+    doc.transact(txn => text.insert(txn, 5, ' world'))
+    t.compare(calls, 1) // subscription was released
+}
+
+/**
+ * @param {t.TestCase} tc
+ */
+export const testSubdocs = tc => {
+    const d1 = new Y.YDoc(1)
+    const map1 = d1.getMap('map')
+    let event = null
+    const sub = d1.onSubdocs(e => event = e)
+
+    const subdoc = new Y.YDoc(2)
+    d1.transact(txn => map1.set(txn, 'sub', subdoc))
+
+    t.compare(event.added.map(d => d.guid), [subdoc.guid])
+    t.compare(event.removed, [])
+    t.compare(event.loaded.map(d => d.guid), [subdoc.guid])
+    t.compare(d1.getSubdocGuids(), new Set([subdoc.guid]))
+
+    // subdocuments received from remote peers are not loaded by default
+    const d2 = new Y.YDoc(3)
+    const map2 = d2.getMap('map')
+    Y.applyUpdate(d2, Y.encodeStateAsUpdate(d1))
+    const remote = map2.get('sub')
+    t.compare(remote.guid, subdoc.guid)
+    t.compare(remote.shouldLoad, false)
+
+    d2.transact(txn => remote.load(txn))
+    t.compare(remote.shouldLoad, true)
+
+    sub.free()
+}
@@ -1,5 +1,4 @@
 use lib0::any::Any;
-use lib0::decoding::Cursor;
 use lib0::error::Error;
 use std::collections::HashMap;
 use std::ffi::{c_void, CStr, CString};
@@ -9,6 +8,7 @@ use std::os::raw::{c_char, c_float, c_int, c_long, c_longlong, c_uchar, c_uint,
 use std::ptr::{null, null_mut};
 use std::rc::Rc;
 use yrs::block::{ClientID, ItemContent, Prelim};
+use yrs::position::Assoc;
 use yrs::types::array::ArrayEvent;
 use yrs::types::map::MapEvent;
 use yrs::types::text::TextEvent;
@@ -17,11 +17,11 @@ use yrs::types::{
     Attrs, BranchPtr, Change, Delta, EntryChange, Event, PathSegment, Value, TYPE_REFS_ARRAY,
     TYPE_REFS_MAP, TYPE_REFS_TEXT, TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_TEXT,
 };
-use yrs::updates::decoder::{Decode, DecoderV1, DecoderV2};
+use yrs::updates::decoder::{Decode, DecoderV1};
 use yrs::updates::encoder::{Encode, Encoder, EncoderV1, EncoderV2};
 use yrs::{
-    AfterTransactionEvent, Array, DeleteSet, Map, OffsetKind, Snapshot, Text, Update, XmlElement,
-    XmlText,
+    AfterTransactionEvent, Array, DeleteSet, Map, OffsetKind, Snapshot, SubdocsEvent, Text, Update,
+    XmlElement, XmlText,
 };
 use yrs::{Options, StateVector};
 use yrs::{SubscriptionId, Xml};
@@ -32,10 +32,12 @@ pub const Y_JSON_BOOL: i8 = -8;
 /// Flag used by `YInput` and `YOutput` to tag floating point numbers.
 pub const Y_JSON_NUM: i8 = -7;
 
-/// Flag used by `YInput` and `YOutput` to tag 64-bit integer numbers.
+/// Flag used by `YInput` and `YOutput` to tag 64-bit integer numbers. Dates are passed to
+/// `YOutput` as integers representing milliseconds since Unix epoch.
 pub const Y_JSON_INT: i8 = -6;
 
-/// Flag used by `YInput` and `YOutput` to tag strings.
+/// Flag used by `YInput` and `YOutput` to tag strings. 128-bit integers and decimal numbers,
+/// which have no C counterpart, are passed to `YOutput` as strings in a plain decimal notation.
 pub const Y_JSON_STR: i8 = -5;
 
 /// Flag used by `YInput` and `YOutput` to tag binary content.
@@ -70,6 +72,9 @@ pub const Y_XML_ELEM: i8 = 4;
 /// Flag used by `YInput` and `YOutput` to tag content, which is an `YXmlText` shared type.
 pub const Y_XML_TEXT: i8 = 5;
 
+/// Flag used by `YInput` and `YOutput` to tag content, which is a `YDoc` subdocument.
+pub const Y_DOC: i8 = 7;
+
 /// Flag used to mark a truthy boolean numbers.
 pub const Y_TRUE: c_char = 1;
 
@@ -88,6 +93,14 @@ pub const Y_OFFSET_UTF16: c_int = 1;
 /// by UTF-32 chars of encoded string.
 pub const Y_OFFSET_UTF32: c_int = 2;
 
+/// Flag used by `YRelativePosition` to determine, that a position sticks to an element placed
+/// right after it. Insertions made at that position will be placed before the position.
+pub const Y_ASSOC_AFTER: c_int = 0;
+
+/// Flag used by `YRelativePosition` to determine, that a position sticks to an element placed
+/// right before it. Insertions made at that position will be placed after the position.
+pub const Y_ASSOC_BEFORE: c_int = -1;
+
 /* pub types below are used by cbindgen for c header generation */
 
 /// A Yrs document type. Documents are most important units of collaborative resources management.
@@ -97,7 +110,7 @@ pub const Y_OFFSET_UTF32: c_int = 2;
 ///
 /// Document manages so called root types, which are top-level shared types definitions (as opposed
 /// to recursively nested types).
-pub type Doc = yrs::Doc;
+pub type Doc = yrs::DocRef;
 
 /// Transaction is one of the core types in Yrs. All operations that need to touch a document's
 /// contents (a.k.a. block store), need to be executed in scope of a transaction.
@@ -130,6 +143,14 @@ pub type Attributes = yrs::types::xml::Attributes<'static>;
 /// traverse.
 pub type TreeWalker = yrs::types::xml::TreeWalker<'static>;
 
+/// A position within an indexed sequence of a shared type (`YText`, `YArray` or `YXmlText`),
+/// which - unlike an ordinary index - remains valid while concurrent changes are being applied to
+/// that sequence. It can be serialized and sent to remote peers, which can resolve it against
+/// their own document state.
+///
+/// Use `yrelative_position_destroy` in order to release its resources.
+pub type RelativePosition = yrs::position::RelativePosition;
+
 /// A structure representing single key-value entry of a map output (used by either
 /// embedded JSON-like maps or YMaps).
 #[repr(C)]
@@ -213,6 +234,7 @@ impl Into<Options> for YOptions {
             client_id: self.id as ClientID,
             skip_gc: if self.skip_gc == 0 { false } else { true },
             offset_kind: encoding,
+            ..Options::with_client_id(self.id as ClientID)
         }
     }
 }
@@ -266,7 +288,7 @@ pub unsafe extern "C" fn ybinary_destroy(ptr: *mut c_uchar, len: c_int) {
 /// Use [ydoc_destroy] in order to release created [Doc] resources.
 #[no_mangle]
 pub extern "C" fn ydoc_new() -> *mut Doc {
-    Box::into_raw(Box::new(Doc::new()))
+    Box::into_raw(Box::new(yrs::Doc::new().into()))
 }
 
 /// Creates a new [Doc] instance with a specified `options`.
@@ -274,7 +296,7 @@ pub extern "C" fn ydoc_new() -> *mut Doc {
 /// Use [ydoc_destroy] in order to release created [Doc] resources.
 #[no_mangle]
 pub extern "C" fn ydoc_new_with_options(options: YOptions) -> *mut Doc {
-    Box::into_raw(Box::new(Doc::with_options(options.into())))
+    Box::into_raw(Box::new(yrs::Doc::with_options(options.into()).into()))
 }
 
 /// Returns a unique client identifier of this [Doc] instance.
@@ -349,6 +371,89 @@ pub unsafe extern "C" fn ydoc_unobserve_after_transaction(doc: *mut Doc, subscri
     doc.unobserve_transaction_cleanup(subscription_id as SubscriptionId);
 }
 
+/// Returns a globally unique identifier of a given document. Unlike client ID, it's shared by all
+/// replicas of the same document and is used to identify subdocuments.
+///
+/// Returned value is a null-terminated UTF-8 string, which must be released using
+/// [ystring_destroy] function.
+#[no_mangle]
+pub unsafe extern "C" fn ydoc_guid(doc: *const Doc) -> *mut c_char {
+    let doc = doc.as_ref().unwrap();
+    CString::new(doc.guid().as_ref()).unwrap().into_raw()
+}
+
+/// Returns `Y_TRUE` if a given document has been requested to load its contents. Subdocuments
+/// received from remote peers are not loaded unless they were inserted with an auto-load flag or
+/// requested to load using [ydoc_load] function.
+#[no_mangle]
+pub unsafe extern "C" fn ydoc_should_load(doc: *const Doc) -> c_char {
+    let doc = doc.as_ref().unwrap();
+    if doc.should_load() {
+        Y_TRUE
+    } else {
+        Y_FALSE
+    }
+}
+
+/// Returns `Y_TRUE` if a given document, when used as a subdocument, should be loaded
+/// automatically by the peers that received it.
+#[no_mangle]
+pub unsafe extern "C" fn ydoc_auto_load(doc: *const Doc) -> c_char {
+    let doc = doc.as_ref().unwrap();
+    if doc.auto_load() {
+        Y_TRUE
+    } else {
+        Y_FALSE
+    }
+}
+
+/// Requests a given subdocument to be loaded. Subscribers of [ydoc_observe_subdocs] of its parent
+/// document will be notified about it once `parent_txn` is committed. It's up to them to actually
+/// fetch subdocument's contents.
+#[no_mangle]
+pub unsafe extern "C" fn ydoc_load(doc: *const Doc, parent_txn: *mut Transaction) {
+    assert!(!parent_txn.is_null());
+    let doc = doc.as_ref().unwrap();
+    doc.load(parent_txn.as_mut().unwrap());
+}
+
+/// Returns a new handle to the same document as a given `doc`. This is useful i.e. to keep
+/// a reference to a subdocument read from a `YOutput` cell or received in `YSubdocsEvent`, after
+/// these have been released.
+///
+/// Use [ydoc_destroy] in order to release a returned handle. This doesn't affect other handles.
+#[no_mangle]
+pub unsafe extern "C" fn ydoc_clone(doc: *const Doc) -> *mut Doc {
+    let doc = doc.as_ref().unwrap();
+    Box::into_raw(Box::new(doc.clone()))
+}
+
+/// Subscribes a given callback function `cb` to be notified whenever subdocuments of a given
+/// document have been added, removed or requested to load within a committed transaction.
+/// Returns a subscription ID which can be then used to unsubscribe this callback by using
+/// [ydoc_unobserve_subdocs] function.
+#[no_mangle]
+pub unsafe extern "C" fn ydoc_observe_subdocs(
+    doc: *mut Doc,
+    state: *mut c_void,
+    cb: extern "C" fn(*mut c_void, *mut YSubdocsEvent),
+) -> c_uint {
+    let doc = doc.as_mut().unwrap();
+    let observer = doc.observe_subdocs(move |_, e| {
+        let mut event = YSubdocsEvent::new(e);
+        cb(state, (&mut event) as *mut _);
+    });
+    let subscription_id: u32 = observer.into();
+    subscription_id as c_uint
+}
+
+/// Releases a callback subscribed via [ydoc_observe_subdocs] function.
+#[no_mangle]
+pub unsafe extern "C" fn ydoc_unobserve_subdocs(doc: *mut Doc, subscription_id: c_uint) {
+    let doc = doc.as_mut().unwrap();
+    doc.unobserve_subdocs(subscription_id as SubscriptionId);
+}
+
 /// Starts a new read-write transaction on a given document. All other operations happen in context
 /// of a transaction. Yrs transactions do not follow ACID rules. Once a set of operations is
 /// complete, a transaction can be finished using [ytransaction_commit] function.
@@ -699,6 +804,12 @@ pub unsafe extern "C" fn yupdate_debug_v2(
 /// - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
 /// - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
 /// - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+/// - `ERR_CODE_INVALID_UTF8` (**7**): decoded string was not a valid UTF-8 sequence.
+/// - `ERR_CODE_INVALID_TYPE_REF` (**8**): decoded shared type reference was not among known types.
+/// - `ERR_CODE_CLOCK_OVERFLOW` (**9**): decoded block clock range was outside of allowed bounds.
+/// - `ERR_CODE_INTEGRITY` (**10**): payload length or checksum didn't match its trailer.
+/// - `ERR_CODE_UPDATE_REJECTED` (**11**): update has been rejected by document's filter, write
+///   policy or limits.
 #[no_mangle]
 pub unsafe extern "C" fn ytransaction_apply(
     txn: *mut Transaction,
@@ -711,10 +822,10 @@ pub unsafe extern "C" fn ytransaction_apply(
     let update = std::slice::from_raw_parts(diff as *const u8, diff_len as usize);
     let mut decoder = DecoderV1::from(update);
     match Update::decode(&mut decoder) {
-        Ok(update) => {
-            txn.as_mut().unwrap().apply_update(update);
-            0
-        }
+        Ok(update) => match txn.as_mut().unwrap().try_apply_update(update) {
+            Ok(()) => 0,
+            Err(_) => ERR_CODE_UPDATE_REJECTED,
+        },
         Err(e) => err_code(e),
     }
 }
@@ -732,6 +843,12 @@ pub unsafe extern "C" fn ytransaction_apply(
 /// - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
 /// - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
 /// - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+/// - `ERR_CODE_INVALID_UTF8` (**7**): decoded string was not a valid UTF-8 sequence.
+/// - `ERR_CODE_INVALID_TYPE_REF` (**8**): decoded shared type reference was not among known types.
+/// - `ERR_CODE_CLOCK_OVERFLOW` (**9**): decoded block clock range was outside of allowed bounds.
+/// - `ERR_CODE_INTEGRITY` (**10**): payload length or checksum didn't match its trailer.
+/// - `ERR_CODE_UPDATE_REJECTED` (**11**): update has been rejected by document's filter, write
+///   policy or limits.
 #[no_mangle]
 pub unsafe extern "C" fn ytransaction_apply_v2(
     txn: *mut Transaction,
@@ -743,10 +860,10 @@ pub unsafe extern "C" fn ytransaction_apply_v2(
 
     let mut update = std::slice::from_raw_parts(diff as *const u8, diff_len as usize);
     match Update::decode_v2(&mut update) {
-        Ok(update) => {
-            txn.as_mut().unwrap().apply_update(update);
-            0
-        }
+        Ok(update) => match txn.as_mut().unwrap().try_apply_update(update) {
+            Ok(()) => 0,
+            Err(_) => ERR_CODE_UPDATE_REJECTED,
+        },
         Err(e) => err_code(e),
     }
 }
@@ -769,6 +886,21 @@ pub const ERR_CODE_INVALID_JSON: c_int = 5;
 /// Error code: other error type than the one specified.
 pub const ERR_CODE_OTHER: c_int = 6;
 
+/// Error code: decoded string was not a valid UTF-8 sequence.
+pub const ERR_CODE_INVALID_UTF8: c_int = 7;
+
+/// Error code: decoded shared type reference was not among known types.
+pub const ERR_CODE_INVALID_TYPE_REF: c_int = 8;
+
+/// Error code: decoded block clock range was outside of allowed bounds.
+pub const ERR_CODE_CLOCK_OVERFLOW: c_int = 9;
+
+/// Error code: payload length or checksum didn't match its integrity trailer.
+pub const ERR_CODE_INTEGRITY: c_int = 10;
+
+/// Error code: update has been rejected by document's filter, write policy or limits.
+pub const ERR_CODE_UPDATE_REJECTED: c_int = 11;
+
 fn err_code(e: Error) -> c_int {
     match e {
         Error::IO(_) => ERR_CODE_IO,
@@ -777,6 +909,11 @@ fn err_code(e: Error) -> c_int {
         Error::UnexpectedValue => ERR_CODE_UNEXPECTED_VALUE,
         Error::Other(_) => ERR_CODE_OTHER,
         Error::InvalidJSON(_) => ERR_CODE_INVALID_JSON,
+        Error::InvalidUtf8(_) => ERR_CODE_INVALID_UTF8,
+        Error::InvalidTypeRef(_) => ERR_CODE_INVALID_TYPE_REF,
+        Error::ClockOverflow => ERR_CODE_CLOCK_OVERFLOW,
+        Error::LengthMismatch { .. } | Error::ChecksumMismatch { .. } => ERR_CODE_INTEGRITY,
+        Error::Update(_) => ERR_CODE_UPDATE_REJECTED,
     }
 }
 
@@ -800,6 +937,32 @@ pub unsafe extern "C" fn ytext_string(txt: *const Branch) -> *mut c_char {
     CString::new(str).unwrap().into_raw()
 }
 
+/// Returns a string content of a `YText` as it was at the moment when a given `snapshot`
+/// (generated by: `ytransaction_snapshot`) has been made. Content removed since then can only be
+/// restored if a document has GC option flag turned off.
+///
+/// Returns a null pointer if `snapshot` could not be decoded. Otherwise a returned value is
+/// a null-terminated UTF-8 string, which must be released using [ystring_destroy] function.
+#[no_mangle]
+pub unsafe extern "C" fn ytext_string_at(
+    txt: *const Branch,
+    txn: *mut Transaction,
+    snapshot: *const c_uchar,
+    snapshot_len: c_int,
+) -> *mut c_char {
+    assert!(!txt.is_null());
+    assert!(!txn.is_null());
+
+    let txt = Text::from_raw_branch(txt);
+    let data = std::slice::from_raw_parts(snapshot, snapshot_len as usize);
+    if let Ok(snapshot) = Snapshot::decode_v1(data) {
+        let str = txt.to_string_at(txn.as_mut().unwrap(), &snapshot);
+        CString::new(str).unwrap().into_raw()
+    } else {
+        null_mut()
+    }
+}
+
 /// Inserts a null-terminated UTF-8 encoded string a given `index`. `index` value must be between
 /// 0 and a length of a `YText` (inclusive, accordingly to [ytext_len] return value), otherwise this
 /// function will panic.
@@ -1049,6 +1212,40 @@ pub unsafe extern "C" fn yarray_move(
     array.move_to(txn, source as u32, target as u32)
 }
 
+/// Moves all elements found within `start`..`end` indexes range (both side inclusive) of a given
+/// `array` into a new position pointed by `target` index. All elements inserted concurrently by
+/// other peers inside of moved range will be moved as well after synchronization.
+///
+/// `assoc_start`/`assoc_end` flags (either `Y_TRUE` or `Y_FALSE`) are used to mark if a range
+/// should include elements, that might have been inserted concurrently at its edges.
+///
+/// All indexes must fit into boundaries of an array, otherwise it will panic at runtime.
+#[no_mangle]
+pub unsafe extern "C" fn yarray_move_range(
+    array: *const Branch,
+    txn: *mut Transaction,
+    start: c_int,
+    assoc_start: c_char,
+    end: c_int,
+    assoc_end: c_char,
+    target: c_int,
+) {
+    assert!(!array.is_null());
+    assert!(!txn.is_null());
+
+    let array = Array::from_raw_branch(array);
+    let txn = txn.as_mut().unwrap();
+
+    array.move_range_to(
+        txn,
+        start as u32,
+        assoc_start == Y_TRUE,
+        end as u32,
+        assoc_end == Y_TRUE,
+        target as u32,
+    )
+}
+
 /// Returns an iterator, which can be used to traverse over all elements of an `array` (`array`'s
 /// length can be determined using [yarray_len] function).
 ///
@@ -1951,6 +2148,7 @@ union YInputContent {
     buf: *mut c_uchar,
     values: *mut YInput,
     map: ManuallyDrop<YMapInputData>,
+    y_doc: *mut Doc,
 }
 
 #[repr(C)]
@@ -1966,7 +2164,10 @@ impl Drop for YInput {
 impl Prelim for YInput {
     fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
         unsafe {
-            if self.tag <= 0 {
+            if self.tag == Y_DOC {
+                let doc = self.value.y_doc.as_ref().unwrap().clone();
+                (ItemContent::Doc(doc), None)
+            } else if self.tag <= 0 {
                 let value = self.into();
                 (ItemContent::Any(vec![value]), None)
             } else {
@@ -2156,6 +2357,8 @@ impl Drop for YOutput {
                     self.len as usize,
                     self.len as usize,
                 ));
+            } else if tag == Y_DOC {
+                drop(Box::from_raw(self.value.y_doc));
             }
         }
     }
@@ -2170,6 +2373,10 @@ impl From<Value> for YOutput {
             Value::YMap(v) => Self::from(v),
             Value::YXmlElement(v) => Self::from(v),
             Value::YXmlText(v) => Self::from(v),
+            Value::YWeakLink(v) => Self::from(v.to_json()),
+            Value::YMove(v) => Self::from(v.to_json()),
+            Value::Extension(v) => Self::from(v.to_json()),
+            Value::YDoc(v) => Self::from(v),
         }
     }
 }
@@ -2207,6 +2414,9 @@ impl From<Any> for YOutput {
                         integer: v as c_longlong,
                     },
                 },
+                Any::Int128(v) => Self::from(Any::String(v.to_string().into())),
+                Any::Decimal(v) => Self::from(Any::String(v.to_string().into())),
+                Any::Date(v) => Self::from(Any::BigInt(v)),
                 Any::String(v) => YOutput {
                     tag: Y_JSON_STR,
                     len: v.len() as c_int,
@@ -2315,6 +2525,18 @@ impl From<XmlText> for YOutput {
     }
 }
 
+impl From<Doc> for YOutput {
+    fn from(v: Doc) -> Self {
+        YOutput {
+            tag: Y_DOC,
+            len: 1,
+            value: YOutputContent {
+                y_doc: Box::into_raw(Box::new(v)),
+            },
+        }
+    }
+}
+
 #[repr(C)]
 union YOutputContent {
     flag: c_char,
@@ -2325,6 +2547,7 @@ union YOutputContent {
     array: *mut YOutput,
     map: *mut YMapEntry,
     y_type: *mut Branch,
+    y_doc: *mut Doc,
 }
 
 /// Releases all resources related to a corresponding `YOutput` cell.
@@ -2500,6 +2723,20 @@ pub unsafe extern "C" fn yinput_ytext(str: *mut c_char) -> YInput {
     }
 }
 
+/// Function constructor used to create a `YInput` cell with a `doc`, which will be inserted as
+/// a subdocument. Inserted document must not be a part of any other document.
+///
+/// This function doesn't allocate any heap resources and doesn't release any on its own, therefore
+/// its up to a caller to free resources once a structure is no longer needed.
+#[no_mangle]
+pub unsafe extern "C" fn yinput_ydoc(doc: *mut Doc) -> YInput {
+    YInput {
+        tag: Y_DOC,
+        len: 1,
+        value: YInputContent { y_doc: doc },
+    }
+}
+
 /// Function constructor used to create a nested `YXmlElement` `YInput` cell with a specified
 /// tag name, which must be a null-terminated UTF-8 character pointer.
 ///
@@ -2708,6 +2945,21 @@ pub unsafe extern "C" fn youtput_read_yxmltext(val: *const YOutput) -> *mut Bran
     }
 }
 
+/// Attempts to read the value for a given `YOutput` pointer as a `YDoc` subdocument.
+///
+/// Returns a null pointer in case when a value stored under current `YOutput` cell
+/// is not a `YDoc`. Underlying heap resources are released automatically as part of
+/// [youtput_destroy] destructor.
+#[no_mangle]
+pub unsafe extern "C" fn youtput_read_ydoc(val: *const YOutput) -> *mut Doc {
+    let v = val.as_ref().unwrap();
+    if v.tag == Y_DOC {
+        v.value.y_doc
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
 /// Subscribes a given callback function `cb` to changes made by this `YText` instance. Callbacks
 /// are triggered whenever a `ytransaction_commit` is called.
 /// Returns a subscription ID which can be then used to unsubscribe this callback by using
@@ -2859,6 +3111,65 @@ impl YAfterTransactionEvent {
     }
 }
 
+/// Event generated for callbacks subscribed using `ydoc_observe_subdocs`. It contains subdocuments
+/// added, removed or requested to load within a committed transaction.
+///
+/// Documents passed in this event are released together with it once a callback returns. Use
+/// `ydoc_clone` in order to keep a reference to any of them.
+#[repr(C)]
+pub struct YSubdocsEvent {
+    /// Number of subdocuments stored in `added` field.
+    pub added_len: c_int,
+    /// Number of subdocuments stored in `removed` field.
+    pub removed_len: c_int,
+    /// Number of subdocuments stored in `loaded` field.
+    pub loaded_len: c_int,
+    /// Subdocuments inserted into shared collections of a parent document.
+    pub added: *mut *mut Doc,
+    /// Subdocuments removed from shared collections of a parent document.
+    pub removed: *mut *mut Doc,
+    /// Subdocuments, which have been requested to load.
+    pub loaded: *mut *mut Doc,
+}
+
+impl YSubdocsEvent {
+    unsafe fn new(e: &SubdocsEvent) -> Self {
+        fn into_ptr(docs: &[Doc]) -> *mut *mut Doc {
+            let docs: Vec<*mut Doc> = docs
+                .iter()
+                .map(|doc| Box::into_raw(Box::new(doc.clone())))
+                .collect();
+            Box::into_raw(docs.into_boxed_slice()) as *mut _
+        }
+
+        YSubdocsEvent {
+            added_len: e.added.len() as c_int,
+            removed_len: e.removed.len() as c_int,
+            loaded_len: e.loaded.len() as c_int,
+            added: into_ptr(&e.added),
+            removed: into_ptr(&e.removed),
+            loaded: into_ptr(&e.loaded),
+        }
+    }
+}
+
+impl Drop for YSubdocsEvent {
+    fn drop(&mut self) {
+        unsafe fn release(docs: *mut *mut Doc, len: c_int) {
+            let docs = Vec::from_raw_parts(docs, len as usize, len as usize);
+            for doc in docs {
+                ydoc_destroy(doc);
+            }
+        }
+
+        unsafe {
+            release(self.added, self.added_len);
+            release(self.removed, self.removed_len);
+            release(self.loaded, self.loaded_len);
+        }
+    }
+}
+
 /// Struct representing a state of a document. It contains the last seen clocks for blocks submitted
 /// per any of the clients collaborating on document updates.
 #[repr(C)]
@@ -3588,6 +3899,121 @@ pub unsafe extern "C" fn ytype_kind(branch: *const Branch) -> c_char {
     }
 }
 
+/// Creates a relative position pointing to a given `index` of an indexed sequence of a `branch`
+/// (`YText`, `YArray` or `YXmlText`). `assoc` is either `Y_ASSOC_AFTER` or `Y_ASSOC_BEFORE`
+/// and it determines, which neighbour element a position sticks to. If `index` is outside of
+/// a sequence bounds, a position will point to the end of that sequence.
+///
+/// Returns a null pointer if `branch` has not been integrated into a document. Otherwise a
+/// returned position must be released using [yrelative_position_destroy] function.
+#[no_mangle]
+pub unsafe extern "C" fn yrelative_position_from_index(
+    branch: *const Branch,
+    txn: *const Transaction,
+    index: c_int,
+    assoc: c_int,
+) -> *mut RelativePosition {
+    assert!(!branch.is_null());
+    assert!(!txn.is_null());
+
+    let branch = branch.as_ref().unwrap();
+    let txn = txn.as_ref().unwrap();
+    let assoc = if assoc == Y_ASSOC_BEFORE {
+        Assoc::Before
+    } else {
+        Assoc::After
+    };
+    match RelativePosition::from_type_index(txn, branch, index as u32, assoc) {
+        Some(pos) => Box::into_raw(Box::new(pos)),
+        None => null_mut(),
+    }
+}
+
+/// Releases all memory-allocated resources bound to a given relative position.
+#[no_mangle]
+pub unsafe extern "C" fn yrelative_position_destroy(pos: *mut RelativePosition) {
+    if !pos.is_null() {
+        drop(Box::from_raw(pos));
+    }
+}
+
+/// Returns an association of a given relative position: either `Y_ASSOC_AFTER` or
+/// `Y_ASSOC_BEFORE`.
+#[no_mangle]
+pub unsafe extern "C" fn yrelative_position_assoc(pos: *const RelativePosition) -> c_int {
+    let pos = pos.as_ref().unwrap();
+    match pos.assoc {
+        Assoc::After => Y_ASSOC_AFTER,
+        Assoc::Before => Y_ASSOC_BEFORE,
+    }
+}
+
+/// Serializes a given relative position into binary payload using lib0 version 1 encoding, which
+/// is compatible with Yjs `encodeRelativePosition`. A length of generated binary will be passed
+/// within a `len` out parameter.
+///
+/// Once no longer needed, a returned binary can be disposed using [ybinary_destroy] function.
+#[no_mangle]
+pub unsafe extern "C" fn yrelative_position_encode(
+    pos: *const RelativePosition,
+    len: *mut c_int,
+) -> *mut c_uchar {
+    let pos = pos.as_ref().unwrap();
+    let binary = pos.encode_v1().into_boxed_slice();
+    *len = binary.len() as c_int;
+    Box::into_raw(binary) as *mut c_uchar
+}
+
+/// Deserializes a relative position from a binary payload (generated by:
+/// [yrelative_position_encode]) of a given length `len`.
+///
+/// Returns a null pointer if payload could not be decoded. Otherwise a returned position must be
+/// released using [yrelative_position_destroy] function.
+#[no_mangle]
+pub unsafe extern "C" fn yrelative_position_decode(
+    binary: *const c_uchar,
+    len: c_int,
+) -> *mut RelativePosition {
+    assert!(!binary.is_null());
+
+    let data = std::slice::from_raw_parts(binary, len as usize);
+    if let Ok(pos) = RelativePosition::decode_v1(data) {
+        Box::into_raw(Box::new(pos))
+    } else {
+        null_mut()
+    }
+}
+
+/// Resolves a relative position into an index within a shared type it points to, using a current
+/// state of a document bound to a given transaction. On success returns `Y_TRUE` and writes
+/// a shared type pointer and an index into `out_branch` and `out_index` out parameters.
+///
+/// Returns `Y_FALSE` if a position could not be resolved, ie. because an element it's anchored to
+/// has not yet been integrated into a document.
+#[no_mangle]
+pub unsafe extern "C" fn yrelative_position_resolve(
+    pos: *const RelativePosition,
+    txn: *const Transaction,
+    out_branch: *mut *mut Branch,
+    out_index: *mut c_int,
+) -> c_char {
+    assert!(!txn.is_null());
+
+    let pos = pos.as_ref().unwrap();
+    let txn = txn.as_ref().unwrap();
+    if let Some(abs) = pos.get_absolute(txn) {
+        if !out_branch.is_null() {
+            *out_branch = abs.branch.deref() as *const Branch as *mut Branch;
+        }
+        if !out_index.is_null() {
+            *out_index = abs.index as c_int;
+        }
+        Y_TRUE
+    } else {
+        Y_FALSE
+    }
+}
+
 /// Tag used to identify `YPathSegment` storing a *char parameter.
 pub const Y_EVENT_PATH_KEY: c_char = 1;
 
@@ -4020,4 +4446,116 @@ mod test {
             ydoc_destroy(doc);
         }
     }
+
+    #[test]
+    fn relative_position_roundtrip() {
+        unsafe {
+            let doc = ydoc_new();
+            let txn = ytransaction_new(doc);
+            let name = CString::new("text").unwrap();
+            let txt = ytext(txn, name.as_ptr());
+            let chunk = CString::new("abc").unwrap();
+            ytext_insert(txt, txn, 0, chunk.as_ptr(), null());
+
+            let pos = yrelative_position_from_index(txt, txn, 1, Y_ASSOC_AFTER);
+            assert!(!pos.is_null());
+            assert_eq!(yrelative_position_assoc(pos), Y_ASSOC_AFTER);
+
+            let mut len = 0;
+            let binary = yrelative_position_encode(pos, &mut len);
+            let decoded = yrelative_position_decode(binary, len);
+            ybinary_destroy(binary, len);
+
+            let chunk = CString::new("xyz").unwrap();
+            ytext_insert(txt, txn, 0, chunk.as_ptr(), null());
+
+            let mut branch = null_mut();
+            let mut index = 0;
+            let resolved = yrelative_position_resolve(decoded, txn, &mut branch, &mut index);
+            assert_eq!(resolved, Y_TRUE);
+            assert_eq!(branch, txt);
+            assert_eq!(index, 4);
+
+            yrelative_position_destroy(pos);
+            yrelative_position_destroy(decoded);
+            ytransaction_commit(txn);
+            ydoc_destroy(doc);
+        }
+    }
+
+    #[test]
+    fn array_move_range() {
+        unsafe {
+            let doc = ydoc_new();
+            let txn = ytransaction_new(doc);
+            let name = CString::new("array").unwrap();
+            let array = yarray(txn, name.as_ptr());
+            let values = [
+                yinput_long(1),
+                yinput_long(2),
+                yinput_long(3),
+                yinput_long(4),
+            ];
+            yarray_insert_range(array, txn, 0, values.as_ptr(), 4);
+
+            yarray_move_range(array, txn, 1, Y_TRUE, 2, Y_FALSE, 4);
+
+            let actual: Vec<_> = (0..yarray_len(array))
+                .map(|i| {
+                    let out = yarray_get(array, i);
+                    let value = *youtput_read_long(out);
+                    youtput_destroy(out);
+                    value
+                })
+                .collect();
+            assert_eq!(actual, vec![1, 4, 2, 3]);
+
+            ytransaction_commit(txn);
+            ydoc_destroy(doc);
+        }
+    }
+
+    extern "C" fn count_added_subdocs(state: *mut c_void, e: *mut YSubdocsEvent) {
+        unsafe {
+            let count = (state as *mut c_int).as_mut().unwrap();
+            *count += e.as_ref().unwrap().added_len;
+        }
+    }
+
+    #[test]
+    fn subdocs() {
+        unsafe {
+            let doc = ydoc_new();
+            let mut added: c_int = 0;
+            let sub_id = ydoc_observe_subdocs(
+                doc,
+                &mut added as *mut c_int as *mut c_void,
+                count_added_subdocs,
+            );
+
+            let txn = ytransaction_new(doc);
+            let name = CString::new("map").unwrap();
+            let map = ymap(txn, name.as_ptr());
+            let subdoc = ydoc_new();
+            let input = yinput_ydoc(subdoc);
+            let key = CString::new("sub").unwrap();
+            ymap_insert(map, txn, key.as_ptr(), &input);
+            ytransaction_commit(txn);
+            assert_eq!(added, 1);
+
+            let out = ymap_get(map, key.as_ptr());
+            let read = youtput_read_ydoc(out);
+            let expected = ydoc_guid(subdoc);
+            let actual = ydoc_guid(read);
+            assert_eq!(CStr::from_ptr(expected), CStr::from_ptr(actual));
+            assert_eq!(ydoc_should_load(read), Y_TRUE);
+
+            ystring_destroy(expected);
+            ystring_destroy(actual);
+            youtput_destroy(out);
+            ydoc_unobserve_subdocs(doc, sub_id);
+            ydoc_destroy(subdoc);
+            ydoc_destroy(doc);
+        }
+    }
 }
@@ -0,0 +1,716 @@
+//! Python bindings for Yrs. They expose a document and its shared collections as Python classes,
+//! with transactions usable as context managers and shared collections following Python
+//! container protocols (`len`, indexing, iteration).
+//!
+//! ```python
+//! import y_py as Y
+//!
+//! doc = Y.YDoc()
+//! text = doc.get_text('name')
+//! with doc.begin_transaction() as txn:
+//!     text.extend(txn, 'hello world')
+//! print(str(text))
+//! ```
+
+use lib0::any::Any;
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{
+    timezone_utc_bound, PyBool, PyBytes, PyDateTime, PyDict, PyFloat, PyList, PyLong, PyString,
+    PyTuple,
+};
+use std::collections::HashMap;
+use std::rc::Rc;
+use yrs::block::ClientID;
+use yrs::types::{Attrs, Value};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Array, Doc, DocRef, Map, OffsetKind, Options, StateVector, Text, Transaction, Update};
+
+/// A Yrs document type. Documents are most important units of collaborative resources management.
+/// All shared collections live within a scope of their corresponding documents. All updates are
+/// generated on per document basis (rather than individual shared type). All operations on shared
+/// collections happen via `YTransaction`, which lifetime is also bound to a document.
+///
+/// Text offsets and lengths of all collections are counted in Python string characters (unicode
+/// code points).
+#[pyclass(unsendable)]
+pub struct YDoc(DocRef);
+
+#[pymethods]
+impl YDoc {
+    /// Creates a new document. If `client_id` was passed it will be used as this document
+    /// replica identifier (it's up to caller to ensure that it's unique). Otherwise it will be
+    /// assigned a randomly generated number. Setting `skip_gc` flag disables garbage collection
+    /// of deleted content, which is required to restore document state from snapshots.
+    #[new]
+    #[pyo3(signature = (client_id = None, skip_gc = false))]
+    fn new(client_id: Option<ClientID>, skip_gc: bool) -> Self {
+        let mut options = match client_id {
+            Some(id) => Options::with_client_id(id),
+            None => Options::default(),
+        };
+        options.offset_kind = OffsetKind::Utf32;
+        options.skip_gc = skip_gc;
+        YDoc(Doc::with_options(options).into())
+    }
+
+    /// Unique identifier of this document replica.
+    #[getter]
+    fn client_id(&self) -> ClientID {
+        self.0.client_id
+    }
+
+    /// Globally unique identifier of this document, shared by all its replicas.
+    #[getter]
+    fn guid(&self) -> String {
+        self.0.guid().to_string()
+    }
+
+    /// Returns a new transaction for this document. Transactions are committed once
+    /// `YTransaction.commit` is called or - when used as a context manager - at the end of
+    /// a `with` block.
+    fn begin_transaction(&self) -> YTransaction {
+        YTransaction(Some(self.0.transact()))
+    }
+
+    /// Calls a given `callback` with a new transaction as its only argument, then commits that
+    /// transaction. Returns a value returned by the `callback`.
+    fn transact(&self, callback: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let txn = Bound::new(callback.py(), self.begin_transaction())?;
+        let result = callback.call1((&txn,));
+        txn.borrow_mut().commit();
+        result.map(Into::into)
+    }
+
+    /// Returns a `YText` root-level shared collection stored under a given `name`, creating it
+    /// if it didn't exist before.
+    fn get_text(&self, name: &str) -> YText {
+        YText(self.0.transact().get_text(name))
+    }
+
+    /// Returns a `YArray` root-level shared collection stored under a given `name`, creating it
+    /// if it didn't exist before.
+    fn get_array(&self, name: &str) -> YArray {
+        YArray(self.0.transact().get_array(name))
+    }
+
+    /// Returns a `YMap` root-level shared collection stored under a given `name`, creating it
+    /// if it didn't exist before.
+    fn get_map(&self, name: &str) -> YMap {
+        YMap(self.0.transact().get_map(name))
+    }
+}
+
+/// A transaction in scope of which all reads and updates of a document happen. It can be used as
+/// a context manager, in which case it's committed when leaving the `with` block.
+#[pyclass(unsendable)]
+pub struct YTransaction(Option<Transaction>);
+
+impl YTransaction {
+    fn get(&mut self) -> PyResult<&mut Transaction> {
+        self.0
+            .as_mut()
+            .ok_or_else(|| PyValueError::new_err("Transaction has already been committed"))
+    }
+}
+
+#[pymethods]
+impl YTransaction {
+    /// Returns a `YText` root-level shared collection stored under a given `name`.
+    fn get_text(&mut self, name: &str) -> PyResult<YText> {
+        Ok(YText(self.get()?.get_text(name)))
+    }
+
+    /// Returns a `YArray` root-level shared collection stored under a given `name`.
+    fn get_array(&mut self, name: &str) -> PyResult<YArray> {
+        Ok(YArray(self.get()?.get_array(name)))
+    }
+
+    /// Returns a `YMap` root-level shared collection stored under a given `name`.
+    fn get_map(&mut self, name: &str) -> PyResult<YMap> {
+        Ok(YMap(self.get()?.get_map(name)))
+    }
+
+    /// Encodes a state vector of a document into its binary representation using lib0 v1
+    /// encoding.
+    fn state_vector_v1(&mut self, py: Python) -> PyResult<PyObject> {
+        let sv = self.get()?.state_vector().encode_v1();
+        Ok(PyBytes::new_bound(py, &sv).into())
+    }
+
+    /// Encodes all changes, which are not known to a peer with a given `vector` state vector,
+    /// using lib0 v1 encoding. If no `vector` was given, all document changes are encoded.
+    #[pyo3(signature = (vector = None))]
+    fn diff_v1(&mut self, py: Python, vector: Option<&[u8]>) -> PyResult<PyObject> {
+        let sv = decode_state_vector(vector)?;
+        let diff = self.get()?.encode_diff_v1(&sv);
+        Ok(PyBytes::new_bound(py, &diff).into())
+    }
+
+    /// Applies an update encoded using lib0 v1 encoding.
+    fn apply_v1(&mut self, diff: &[u8]) -> PyResult<()> {
+        let update = Update::decode_v1(diff).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        self.get()?
+            .try_apply_update(update)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Commits current transaction. Further attempts to use it will raise an error.
+    fn commit(&mut self) {
+        // transaction is committed when dropped
+        self.0.take();
+    }
+
+    /// Checks if current transaction has already been committed.
+    #[getter]
+    fn committed(&self) -> bool {
+        self.0.is_none()
+    }
+
+    fn __enter__(slf: PyRefMut<Self>) -> PyRefMut<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> bool {
+        self.commit();
+        false
+    }
+}
+
+/// A shared data type used for collaborative text editing.
+#[pyclass(unsendable)]
+pub struct YText(Text);
+
+#[pymethods]
+impl YText {
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("YText({})", self.0.to_string())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len() as usize
+    }
+
+    /// Returns a string content of this text.
+    fn to_json(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// Inserts a `chunk` of text at a given `index`, optionally formatted with `attributes`.
+    #[pyo3(signature = (txn, index, chunk, attributes = None))]
+    fn insert(
+        &self,
+        txn: &mut YTransaction,
+        index: u32,
+        chunk: &str,
+        attributes: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<()> {
+        let txn = txn.get()?;
+        check_index(index, self.0.len())?;
+        match attributes {
+            Some(attrs) => {
+                let attrs = py_into_attrs(attrs)?;
+                self.0.insert_with_attributes(txn, index, chunk, attrs)
+            }
+            None => self.0.insert(txn, index, chunk),
+        }
+        Ok(())
+    }
+
+    /// Appends a `chunk` of text at the end of this text.
+    fn extend(&self, txn: &mut YTransaction, chunk: &str) -> PyResult<()> {
+        self.0.push(txn.get()?, chunk);
+        Ok(())
+    }
+
+    /// Deletes `length` characters starting at a given `index`.
+    #[pyo3(signature = (txn, index, length = 1))]
+    fn delete(&self, txn: &mut YTransaction, index: u32, length: u32) -> PyResult<()> {
+        let txn = txn.get()?;
+        check_range(index, length, self.0.len())?;
+        self.0.remove_range(txn, index, length);
+        Ok(())
+    }
+
+    /// Applies formatting `attributes` to `length` characters starting at a given `index`.
+    fn format(
+        &self,
+        txn: &mut YTransaction,
+        index: u32,
+        length: u32,
+        attributes: &Bound<'_, PyDict>,
+    ) -> PyResult<()> {
+        let txn = txn.get()?;
+        check_range(index, length, self.0.len())?;
+        self.0
+            .format(txn, index, length, py_into_attrs(attributes)?);
+        Ok(())
+    }
+}
+
+/// A shared collection of ordered values.
+#[pyclass(unsendable)]
+pub struct YArray(Array);
+
+#[pymethods]
+impl YArray {
+    fn __len__(&self) -> usize {
+        self.0.len() as usize
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let list = self.to_json(py);
+        Ok(format!("YArray({})", list.bind(py).repr()?))
+    }
+
+    fn __getitem__(&self, py: Python, index: isize) -> PyResult<PyObject> {
+        let len = self.0.len() as isize;
+        let index = if index < 0 { len + index } else { index };
+        if index < 0 || index >= len {
+            return Err(PyIndexError::new_err("Index out of range"));
+        }
+        match self.0.get(index as u32) {
+            Some(value) => value_into_py(py, value),
+            None => Err(PyIndexError::new_err("Index out of range")),
+        }
+    }
+
+    fn __iter__(&self, py: Python) -> PyResult<ValueIterator> {
+        let values = self
+            .0
+            .iter()
+            .map(|v| value_into_py(py, v))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(ValueIterator(values.into_iter()))
+    }
+
+    /// Converts the contents of this array into a Python list.
+    fn to_json(&self, py: Python) -> PyObject {
+        any_into_py(py, &self.0.to_json())
+    }
+
+    /// Inserts an `item` at a given `index`.
+    fn insert(&self, txn: &mut YTransaction, index: u32, item: &Bound<'_, PyAny>) -> PyResult<()> {
+        let txn = txn.get()?;
+        check_index(index, self.0.len())?;
+        self.0.insert(txn, index, py_into_any(item)?);
+        Ok(())
+    }
+
+    /// Appends an `item` at the end of this array.
+    fn append(&self, txn: &mut YTransaction, item: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.0.push_back(txn.get()?, py_into_any(item)?);
+        Ok(())
+    }
+
+    /// Appends all `items` of a given iterable at the end of this array.
+    fn extend(&self, txn: &mut YTransaction, items: &Bound<'_, PyAny>) -> PyResult<()> {
+        let txn = txn.get()?;
+        let items = items
+            .iter()?
+            .map(|item| py_into_any(&item?))
+            .collect::<PyResult<Vec<_>>>()?;
+        let len = self.0.len();
+        self.0.insert_range(txn, len, items);
+        Ok(())
+    }
+
+    /// Deletes `length` items starting at a given `index`.
+    #[pyo3(signature = (txn, index, length = 1))]
+    fn delete(&self, txn: &mut YTransaction, index: u32, length: u32) -> PyResult<()> {
+        let txn = txn.get()?;
+        check_range(index, length, self.0.len())?;
+        self.0.remove_range(txn, index, length);
+        Ok(())
+    }
+}
+
+/// A shared collection of key-value entries. Like Python dictionaries, keys must be strings.
+#[pyclass(unsendable)]
+pub struct YMap(Map);
+
+#[pymethods]
+impl YMap {
+    fn __len__(&self) -> usize {
+        self.0.len() as usize
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let dict = self.to_json(py);
+        Ok(format!("YMap({})", dict.bind(py).repr()?))
+    }
+
+    fn __contains__(&self, key: &str) -> bool {
+        self.0.contains(key)
+    }
+
+    fn __getitem__(&self, py: Python, key: &str) -> PyResult<PyObject> {
+        match self.0.get(key) {
+            Some(value) => value_into_py(py, value),
+            None => Err(PyKeyError::new_err(key.to_string())),
+        }
+    }
+
+    fn __iter__(&self) -> KeyIterator {
+        self.keys()
+    }
+
+    /// Returns a value stored under a given `key` or `fallback` if no such entry exists.
+    #[pyo3(signature = (key, fallback = None))]
+    fn get(&self, py: Python, key: &str, fallback: Option<PyObject>) -> PyResult<PyObject> {
+        match self.0.get(key) {
+            Some(value) => value_into_py(py, value),
+            None => Ok(fallback.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    /// Returns an iterator over all keys of this map.
+    fn keys(&self) -> KeyIterator {
+        let keys: Vec<String> = self.0.keys().map(str::to_string).collect();
+        KeyIterator(keys.into_iter())
+    }
+
+    /// Returns an iterator over all values of this map.
+    fn values(&self, py: Python) -> PyResult<ValueIterator> {
+        let values = self
+            .0
+            .iter()
+            .map(|(_, v)| value_into_py(py, v))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(ValueIterator(values.into_iter()))
+    }
+
+    /// Returns an iterator over all `(key, value)` entries of this map.
+    fn items(&self, py: Python) -> PyResult<ValueIterator> {
+        let items = self
+            .0
+            .iter()
+            .map(|(k, v)| Ok((k, value_into_py(py, v)?).into_py(py)))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(ValueIterator(items.into_iter()))
+    }
+
+    /// Converts the contents of this map into a Python dictionary.
+    fn to_json(&self, py: Python) -> PyObject {
+        any_into_py(py, &self.0.to_json())
+    }
+
+    /// Stores a `value` under a given `key`, replacing an existing entry.
+    fn set(&self, txn: &mut YTransaction, key: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.0.insert(txn.get()?, key, py_into_any(value)?);
+        Ok(())
+    }
+
+    /// Stores all entries of a given dictionary in this map.
+    fn update(&self, txn: &mut YTransaction, items: &Bound<'_, PyDict>) -> PyResult<()> {
+        let txn = txn.get()?;
+        for (key, value) in items.iter() {
+            let key: String = key.extract()?;
+            self.0.insert(txn, key, py_into_any(&value)?);
+        }
+        Ok(())
+    }
+
+    /// Removes an entry stored under a given `key` and returns its value. If no such entry
+    /// existed, `fallback` is returned instead or - if it wasn't provided - a `KeyError` is
+    /// raised.
+    #[pyo3(signature = (txn, key, fallback = None))]
+    fn pop(
+        &self,
+        py: Python,
+        txn: &mut YTransaction,
+        key: &str,
+        fallback: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        match self.0.remove(txn.get()?, key) {
+            Some(value) => value_into_py(py, value),
+            None => fallback.ok_or_else(|| PyKeyError::new_err(key.to_string())),
+        }
+    }
+}
+
+/// Iterator over values of shared collections.
+#[pyclass(unsendable)]
+pub struct ValueIterator(std::vec::IntoIter<PyObject>);
+
+#[pymethods]
+impl ValueIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<PyObject> {
+        self.0.next()
+    }
+}
+
+/// Iterator over keys of a `YMap`.
+#[pyclass(unsendable)]
+pub struct KeyIterator(std::vec::IntoIter<String>);
+
+#[pymethods]
+impl KeyIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<String> {
+        self.0.next()
+    }
+}
+
+/// Encodes a state vector of a given document into its binary representation using lib0 v1
+/// encoding. It can be passed to `encode_state_as_update` on a remote peer to generate an update
+/// containing only changes unknown to current document.
+#[pyfunction]
+fn encode_state_vector(py: Python, doc: &YDoc) -> PyObject {
+    let sv = doc.0.transact().state_vector().encode_v1();
+    PyBytes::new_bound(py, &sv).into()
+}
+
+/// Encodes all changes of a given document, which are not known to a peer with a given `vector`
+/// state vector, using lib0 v1 encoding. If no `vector` was given, all document changes are
+/// encoded.
+#[pyfunction]
+#[pyo3(signature = (doc, vector = None))]
+fn encode_state_as_update(py: Python, doc: &YDoc, vector: Option<&[u8]>) -> PyResult<PyObject> {
+    let sv = decode_state_vector(vector)?;
+    let update = doc.0.encode_state_as_update_v1(&sv);
+    Ok(PyBytes::new_bound(py, &update).into())
+}
+
+/// Applies an update encoded using lib0 v1 encoding to a given document.
+#[pyfunction]
+fn apply_update(doc: &YDoc, diff: &[u8]) -> PyResult<()> {
+    let update = Update::decode_v1(diff).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    doc.0
+        .transact()
+        .try_apply_update(update)
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pymodule]
+fn y_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<YDoc>()?;
+    m.add_class::<YTransaction>()?;
+    m.add_class::<YText>()?;
+    m.add_class::<YArray>()?;
+    m.add_class::<YMap>()?;
+    m.add_class::<ValueIterator>()?;
+    m.add_class::<KeyIterator>()?;
+    m.add_function(wrap_pyfunction!(encode_state_vector, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_state_as_update, m)?)?;
+    m.add_function(wrap_pyfunction!(apply_update, m)?)?;
+    Ok(())
+}
+
+fn decode_state_vector(vector: Option<&[u8]>) -> PyResult<StateVector> {
+    match vector {
+        Some(data) => {
+            StateVector::decode_v1(data).map_err(|e| PyValueError::new_err(e.to_string()))
+        }
+        None => Ok(StateVector::default()),
+    }
+}
+
+fn check_index(index: u32, len: u32) -> PyResult<()> {
+    if index > len {
+        Err(PyIndexError::new_err("Index out of range"))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_range(index: u32, length: u32, len: u32) -> PyResult<()> {
+    if index as u64 + length as u64 > len as u64 {
+        Err(PyIndexError::new_err("Index out of range"))
+    } else {
+        Ok(())
+    }
+}
+
+fn py_into_attrs(dict: &Bound<'_, PyDict>) -> PyResult<Attrs> {
+    let mut attrs = Attrs::new();
+    for (key, value) in dict.iter() {
+        let key: String = key.extract()?;
+        attrs.insert(Rc::from(key), py_into_any(&value)?);
+    }
+    Ok(attrs)
+}
+
+/// Converts a Python value into a JSON-like [Any]. Integers are stored as 64-bit integers (or
+/// 128-bit ones if they don't fit), datetimes as milliseconds since Unix epoch, while lists and
+/// tuples are stored as arrays.
+fn py_into_any(v: &Bound<'_, PyAny>) -> PyResult<Any> {
+    if v.is_none() {
+        Ok(Any::Null)
+    } else if let Ok(b) = v.downcast::<PyBool>() {
+        Ok(Any::Bool(b.is_true()))
+    } else if let Ok(i) = v.downcast::<PyLong>() {
+        match i.extract::<i64>() {
+            Ok(i) => Ok(Any::BigInt(i)),
+            Err(_) => Ok(Any::Int128(i.extract()?)),
+        }
+    } else if let Ok(f) = v.downcast::<PyFloat>() {
+        Ok(Any::Number(f.value()))
+    } else if let Ok(d) = v.downcast::<PyDateTime>() {
+        let timestamp: f64 = d.call_method0("timestamp")?.extract()?;
+        Ok(Any::Date((timestamp * 1000.0).round() as i64))
+    } else if let Ok(s) = v.downcast::<PyString>() {
+        Ok(Any::String(s.to_str()?.into()))
+    } else if let Ok(b) = v.downcast::<PyBytes>() {
+        Ok(Any::Buffer(b.as_bytes().into()))
+    } else if v.is_instance_of::<PyList>() || v.is_instance_of::<PyTuple>() {
+        let items = v
+            .iter()?
+            .map(|item| py_into_any(&item?))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Any::Array(items.into_boxed_slice()))
+    } else if let Ok(dict) = v.downcast::<PyDict>() {
+        let mut map = HashMap::with_capacity(dict.len());
+        for (key, value) in dict.iter() {
+            let key: String = key.extract()?;
+            map.insert(key, py_into_any(&value)?);
+        }
+        Ok(Any::Map(Box::new(map)))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "Cannot store a value of type '{}' in a shared collection",
+            v.get_type().name()?
+        )))
+    }
+}
+
+fn any_into_py(py: Python, v: &Any) -> PyObject {
+    match v {
+        Any::Null | Any::Undefined => py.None(),
+        Any::Bool(v) => v.into_py(py),
+        Any::Number(v) => v.into_py(py),
+        Any::BigInt(v) => v.into_py(py),
+        Any::Int128(v) => v.into_py(py),
+        Any::Decimal(v) => py
+            .import_bound("decimal")
+            .and_then(|m| m.getattr("Decimal")?.call1((v.to_string(),)))
+            .map(|d| d.unbind())
+            .unwrap_or_else(|_| v.to_string().into_py(py)),
+        Any::Date(v) => {
+            let utc = timezone_utc_bound(py);
+            PyDateTime::from_timestamp_bound(py, *v as f64 / 1000.0, Some(&utc))
+                .map(|d| d.into_py(py))
+                .unwrap_or_else(|_| v.into_py(py))
+        }
+        Any::String(v) => v.as_ref().into_py(py),
+        Any::Buffer(v) => PyBytes::new_bound(py, v).into(),
+        Any::Array(v) => {
+            let items: Vec<PyObject> = v.iter().map(|v| any_into_py(py, v)).collect();
+            PyList::new_bound(py, items).into()
+        }
+        Any::Map(v) => {
+            let dict = PyDict::new_bound(py);
+            for (key, value) in v.iter() {
+                dict.set_item(key, any_into_py(py, value)).unwrap();
+            }
+            dict.into()
+        }
+    }
+}
+
+fn value_into_py(py: Python, v: Value) -> PyResult<PyObject> {
+    Ok(match v {
+        Value::Any(v) => any_into_py(py, &v),
+        Value::YText(v) => YText(v).into_py(py),
+        Value::YArray(v) => YArray(v).into_py(py),
+        Value::YMap(v) => YMap(v).into_py(py),
+        Value::YDoc(v) => YDoc(v).into_py(py),
+        Value::YWeakLink(v) => any_into_py(py, &v.to_json()),
+        Value::YMove(v) => any_into_py(py, &v.to_json()),
+        Value::Extension(v) => any_into_py(py, &v.to_json()),
+        Value::YXmlElement(_) | Value::YXmlText(_) => {
+            return Err(PyTypeError::new_err("XML types are not supported"))
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{py_into_any, value_into_py, YDoc};
+    use lib0::any::Any;
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+    use std::collections::HashMap;
+    use yrs::types::Value;
+
+    #[test]
+    fn value_conversion_roundtrip() {
+        Python::with_gil(|py| {
+            let value = py
+                .eval_bound(
+                    "{'a': [1, 2.5, True, None], 'b': b'\\x01', 'c': 'text'}",
+                    None,
+                    None,
+                )
+                .unwrap();
+            let any = py_into_any(&value).unwrap();
+            let expected = Any::Map(Box::new(HashMap::from([
+                (
+                    "a".to_string(),
+                    Any::Array(
+                        vec![Any::BigInt(1), Any::Number(2.5), Any::Bool(true), Any::Null]
+                            .into_boxed_slice(),
+                    ),
+                ),
+                ("b".to_string(), Any::Buffer(vec![1u8].into_boxed_slice())),
+                ("c".to_string(), Any::String("text".into())),
+            ])));
+            assert_eq!(any, expected);
+
+            let back = value_into_py(py, Value::Any(any)).unwrap();
+            assert!(back.bind(py).eq(value).unwrap());
+        });
+    }
+
+    #[test]
+    fn transaction_context_manager() {
+        Python::with_gil(|py| {
+            let doc = Bound::new(py, YDoc::new(Some(1), false)).unwrap();
+            let globals = PyDict::new_bound(py);
+            globals.set_item("doc", doc).unwrap();
+            py.run_bound(
+                r#"
+text = doc.get_text('text')
+with doc.begin_transaction() as txn:
+    text.extend(txn, 'hello')
+    text.insert(txn, 5, ' world', {'bold': True})
+assert txn.committed
+assert str(text) == 'hello world'
+assert len(text) == 11
+
+array = doc.get_array('array')
+doc.transact(lambda txn: array.extend(txn, [1, 'two', {'three': 3}]))
+assert list(array) == [1, 'two', {'three': 3}]
+assert array[-1] == {'three': 3}
+
+map = doc.get_map('map')
+with doc.begin_transaction() as txn:
+    map.update(txn, {'a': 1, 'b': 2})
+    assert map.pop(txn, 'a') == 1
+assert dict(map.items()) == {'b': 2}
+assert 'b' in map and 'a' not in map
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
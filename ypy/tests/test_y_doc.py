@@ -0,0 +1,84 @@
+import pytest
+import y_py as Y
+
+
+def exchange_updates(docs):
+    for d1 in docs:
+        for d2 in docs:
+            if d1 is not d2:
+                Y.apply_update(d2, Y.encode_state_as_update(d1, Y.encode_state_vector(d2)))
+
+
+def test_text():
+    doc = Y.YDoc(1)
+    text = doc.get_text('text')
+    with doc.begin_transaction() as txn:
+        text.extend(txn, 'hello world')
+        text.insert(txn, 5, ' 🌍')
+        text.delete(txn, 0, 1)
+        text.format(txn, 0, 4, {'bold': True})
+
+    assert str(text) == 'ello 🌍 world'
+    assert len(text) == 12
+
+
+def test_array():
+    doc = Y.YDoc(1)
+    array = doc.get_array('array')
+    doc.transact(lambda txn: array.extend(txn, [1, 2.5, 'three', None, [True], {'k': b'v'}]))
+
+    assert len(array) == 6
+    assert list(array) == [1, 2.5, 'three', None, [True], {'k': b'v'}]
+    assert array[-1] == {'k': b'v'}
+    with pytest.raises(IndexError):
+        array[6]
+
+    with doc.begin_transaction() as txn:
+        array.delete(txn, 1, 3)
+        array.insert(txn, 0, 0)
+        array.append(txn, 'last')
+    assert array.to_json() == [0, 1, [True], {'k': b'v'}, 'last']
+
+
+def test_map():
+    doc = Y.YDoc(1)
+    map = doc.get_map('map')
+    with doc.begin_transaction() as txn:
+        map.update(txn, {'a': 1, 'b': 2})
+        map.set(txn, 'c', 3)
+        assert map.pop(txn, 'a') == 1
+        assert map.pop(txn, 'missing', 'fallback') == 'fallback'
+        with pytest.raises(KeyError):
+            map.pop(txn, 'missing')
+
+    assert len(map) == 2
+    assert 'b' in map and 'a' not in map
+    assert sorted(map) == ['b', 'c']
+    assert dict(map.items()) == {'b': 2, 'c': 3}
+    assert map.get('missing') is None
+    with pytest.raises(KeyError):
+        map['missing']
+
+
+def test_committed_transaction():
+    doc = Y.YDoc(1)
+    text = doc.get_text('text')
+    with doc.begin_transaction() as txn:
+        text.extend(txn, 'hello')
+    assert txn.committed
+    with pytest.raises(ValueError):
+        text.extend(txn, ' world')
+
+
+def test_sync():
+    d1 = Y.YDoc(1)
+    d2 = Y.YDoc(2)
+    t1 = d1.get_text('text')
+    t2 = d2.get_text('text')
+    d1.transact(lambda txn: t1.extend(txn, 'hello'))
+    d2.transact(lambda txn: t2.extend(txn, 'world'))
+
+    exchange_updates([d1, d2])
+
+    assert str(t1) == str(t2)
+    assert len(t1) == 10
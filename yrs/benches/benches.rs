@@ -454,6 +454,65 @@ fn b4_2(c: &mut Criterion, name: &str) {
     );
 }
 
+fn b4_3(c: &mut Criterion, name: &str) {
+    let doc = Doc::new();
+    let txt = {
+        let mut txn = doc.transact();
+        txn.get_text("text")
+    };
+    let mut buf = Vec::with_capacity(400 * 1024);
+    let mut f = std::fs::File::open("./yrs/benches/input/b4-update.bin").unwrap();
+    std::io::Read::read_to_end(&mut f, &mut buf).unwrap();
+    doc.transact()
+        .apply_update(Update::decode_v1(buf.as_slice()).unwrap());
+
+    c.bench_with_input(BenchmarkId::new(name, txt.len()), &txt, |b, txt| {
+        b.iter(|| txt.to_string());
+    });
+}
+
+fn b4_4(c: &mut Criterion, name: &str) {
+    let doc = Doc::new();
+    let txt = {
+        let mut txn = doc.transact();
+        txn.get_text("text")
+    };
+    let mut buf = Vec::with_capacity(400 * 1024);
+    let mut f = std::fs::File::open("./yrs/benches/input/b4-update.bin").unwrap();
+    std::io::Read::read_to_end(&mut f, &mut buf).unwrap();
+    doc.transact()
+        .apply_update(Update::decode_v1(buf.as_slice()).unwrap());
+
+    c.bench_with_input(BenchmarkId::new(name, txt.len()), &txt, |b, txt| {
+        b.iter(|| {
+            let mut txn = doc.transact();
+            let len = txt.len();
+            txt.insert(&mut txn, len, "a");
+        });
+    });
+}
+
+fn b4_5(c: &mut Criterion, name: &str) {
+    let mut buf = Vec::with_capacity(400 * 1024);
+    let mut f = std::fs::File::open("./yrs/benches/input/b4-update.bin").unwrap();
+    std::io::Read::read_to_end(&mut f, &mut buf).unwrap();
+
+    c.bench_with_input(BenchmarkId::new(name, buf.len()), &buf, |b, buf| {
+        b.iter_batched(
+            || {
+                let doc = Doc::new();
+                let txt = doc.transact().get_text("text");
+                doc.transact()
+                    .apply_update(Update::decode_v1(buf.as_slice()).unwrap());
+                (doc, txt)
+            },
+            // text is read for the first time, so there's no cached string to return
+            |(doc, txt)| (txt.to_string(), doc),
+            BatchSize::PerIteration,
+        );
+    });
+}
+
 fn read_input(fpath: &str) -> Vec<TextOp> {
     use std::fs::File;
     use yrs::updates::decoder::DecoderV1;
@@ -533,6 +592,9 @@ fn bench(c: &mut Criterion) {
     b3_4(c, "[B3.4] 20√N clients concurrently insert text in Array");
     b4_2(c, "[B4.2] Apply real-world document snapshot of size");
     b4_1(c, "[B4.1] Apply real-world editing dataset");
+    b4_3(c, "[B4.3] Read real-world document text of length");
+    b4_4(c, "[B4.4] Type at the end of real-world document of length");
+    b4_5(c, "[B4.5] Read cold text of real-world document of size");
 }
 
 criterion_group! {
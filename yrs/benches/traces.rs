@@ -0,0 +1,128 @@
+//! Replays real-world editing traces through [Text], measuring how long it takes to apply,
+//! encode and decode them, as well as how much memory a resulting document occupies.
+//!
+//! Besides the traces shipped in `benches/input`, additional trace files (see: [Trace::open])
+//! can be provided using `YRS_TRACES` environment variable, ie.:
+//!
+//! ```sh
+//! YRS_TRACES=automerge-paper.json cargo bench --features testing --bench traces
+//! ```
+
+use criterion::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use yrs::testing::Trace;
+use yrs::updates::decoder::Decode;
+use yrs::{Doc, OffsetKind, Options, StateVector, Update};
+
+/// Allocator keeping track of a number of bytes currently allocated by the process.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn trace_files() -> Vec<PathBuf> {
+    let mut files = vec![Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("benches")
+        .join("input")
+        .join("b4-editing-trace.bin")];
+    if let Some(paths) = std::env::var_os("YRS_TRACES") {
+        files.extend(std::env::split_paths(&paths));
+    }
+    files
+}
+
+/// JSON traces express positions in Unicode code points, binary ones in UTF-8 bytes.
+fn options(path: &Path) -> Options {
+    let offset_kind = if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+        OffsetKind::Utf32
+    } else {
+        OffsetKind::Bytes
+    };
+    Options {
+        offset_kind,
+        ..Options::default()
+    }
+}
+
+fn replay(trace: &Trace, options: &Options) -> Doc {
+    let doc = Doc::with_options(options.clone());
+    let text = doc.transact().get_text("text");
+    trace.replay(&doc, &text);
+    doc
+}
+
+fn trace_benchmark(c: &mut Criterion, path: &Path) {
+    let trace = Trace::open(path).unwrap();
+    let options = options(path);
+    let name = path.file_stem().unwrap().to_string_lossy().to_string();
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let doc = replay(&trace, &options);
+    let memory = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+    let update_v1 = doc.encode_state_as_update_v1(&StateVector::default());
+    let update_v2 = doc.encode_state_as_update_v2(&StateVector::default());
+    println!(
+        "{}: {} operations, document size in memory: {} bytes, update size: {} bytes (v1), {} bytes (v2)",
+        name,
+        trace.len(),
+        memory,
+        update_v1.len(),
+        update_v2.len()
+    );
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(trace.len() as u64));
+    group.bench_function("apply", |b| b.iter(|| replay(&trace, &options)));
+    group.bench_function("encode v1", |b| {
+        b.iter(|| doc.encode_state_as_update_v1(&StateVector::default()))
+    });
+    group.bench_function("encode v2", |b| {
+        b.iter(|| doc.encode_state_as_update_v2(&StateVector::default()))
+    });
+    group.bench_function("decode v1", |b| {
+        b.iter(|| {
+            let doc = Doc::with_options(options.clone());
+            doc.transact()
+                .apply_update(Update::decode_v1(&update_v1).unwrap());
+            doc
+        })
+    });
+    group.bench_function("decode v2", |b| {
+        b.iter(|| {
+            let doc = Doc::with_options(options.clone());
+            doc.transact()
+                .apply_update(Update::decode_v2(&update_v2).unwrap());
+            doc
+        })
+    });
+    group.finish();
+}
+
+fn bench(c: &mut Criterion) {
+    for path in trace_files() {
+        trace_benchmark(c, &path);
+    }
+}
+
+criterion_group! {
+    name = traces;
+    config = Criterion::default().sample_size(10);
+    targets = bench,
+}
+criterion_main!(traces);
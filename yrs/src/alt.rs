@@ -5,6 +5,11 @@ use crate::StateVector;
 use lib0::decoding::Cursor;
 use lib0::error::Error;
 
+/// Merges a sequence of lib0 v1 encoded `updates` into a single update, without applying them
+/// to any document. This way an update log can be compacted without instantiating a [Doc].
+/// Updates don't need to be complete or ordered, merging is done purely at the encoding level.
+///
+/// [Doc]: crate::Doc
 pub fn merge_updates_v1(updates: &[&[u8]]) -> Result<Vec<u8>, Error> {
     let mut merge = Vec::with_capacity(updates.len());
     for &buf in updates.iter() {
@@ -14,6 +19,8 @@ pub fn merge_updates_v1(updates: &[&[u8]]) -> Result<Vec<u8>, Error> {
     Ok(Update::merge_updates(merge).encode_v1())
 }
 
+/// Merges a sequence of lib0 v2 encoded `updates` into a single update, without applying them
+/// to any document. See [merge_updates_v1] for details.
 pub fn merge_updates_v2(updates: &[&[u8]]) -> Result<Vec<u8>, Error> {
     let mut merge = Vec::with_capacity(updates.len());
     for &buf in updates.iter() {
@@ -23,19 +30,23 @@ pub fn merge_updates_v2(updates: &[&[u8]]) -> Result<Vec<u8>, Error> {
     Ok(Update::merge_updates(merge).encode_v2())
 }
 
-// Computes the state vector from a document update
+/// Computes the state vector from a lib0 v1 encoded document `update`, without applying it to
+/// any document. Returned state vector is encoded using lib0 v1 encoding as well.
 pub fn encode_state_vector_from_update_v1(update: &[u8]) -> Result<Vec<u8>, Error> {
     let update = Update::decode_v1(update)?;
     Ok(update.state_vector().encode_v1())
 }
 
-// Computes the state vector from a document update
+/// Computes the state vector from a lib0 v2 encoded document `update`, without applying it to
+/// any document. Returned state vector is encoded using lib0 v2 encoding as well.
 pub fn encode_state_vector_from_update_v2(update: &[u8]) -> Result<Vec<u8>, Error> {
     let update = Update::decode_v2(update)?;
     Ok(update.state_vector().encode_v2())
 }
 
-// Encode the missing differences to another document update.
+/// Computes a subset of a lib0 v1 encoded document `update`, which contains only changes not
+/// yet observed by a peer with a given `state_vector` (also v1 encoded). It doesn't require
+/// constructing a document.
 pub fn diff_updates_v1(update: &[u8], state_vector: &[u8]) -> Result<Vec<u8>, Error> {
     let sv = StateVector::decode_v1(state_vector)?;
     let cursor = Cursor::new(update);
@@ -47,7 +58,9 @@ pub fn diff_updates_v1(update: &[u8], state_vector: &[u8]) -> Result<Vec<u8>, Er
     Ok(encoder.to_vec())
 }
 
-// Encode the missing differences to another document update.
+/// Computes a subset of a lib0 v2 encoded document `update`, which contains only changes not
+/// yet observed by a peer with a given `state_vector` (also v2 encoded). It doesn't require
+/// constructing a document.
 pub fn diff_updates_v2(update: &[u8], state_vector: &[u8]) -> Result<Vec<u8>, Error> {
     let sv = StateVector::decode_v2(state_vector)?;
     let cursor = Cursor::new(update);
@@ -61,7 +74,15 @@ pub fn diff_updates_v2(update: &[u8], state_vector: &[u8]) -> Result<Vec<u8>, Er
 
 #[cfg(test)]
 mod test {
-    use crate::{diff_updates_v1, encode_state_vector_from_update_v1, merge_updates_v1};
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{
+        diff_updates_v1, diff_updates_v2, encode_state_vector_from_update_v1,
+        encode_state_vector_from_update_v2, merge_updates_v1, merge_updates_v2, Doc, StateVector,
+        Update,
+    };
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
     #[test]
     fn merge_updates_compatibility_v1() {
@@ -121,4 +142,54 @@ mod test {
         let actual = diff_updates_v1(update, state_vector).unwrap();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn merge_updates_log_v2() {
+        let doc = Doc::with_client_id(1);
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let u = updates.clone();
+        let _sub = doc.observe_update_v2(move |_, e| u.borrow_mut().push(e.update.clone()));
+        let txt = doc.transact().get_text("text");
+        for (i, chunk) in ["abc", "def", "ghi"].iter().enumerate() {
+            txt.insert(&mut doc.transact(), i as u32 * 3, chunk);
+        }
+        txt.remove_range(&mut doc.transact(), 1, 4);
+
+        let updates = updates.take();
+        assert_eq!(updates.len(), 4);
+        let refs: Vec<&[u8]> = updates.iter().map(|u| u.as_slice()).collect();
+        let merged = merge_updates_v2(&refs).unwrap();
+
+        let u = Update::decode_v2(merged.as_slice()).unwrap();
+        assert_eq!(u.state_vector().get(&1), 9);
+
+        let remote = Doc::with_client_id(2);
+        let mut txn = remote.transact();
+        txn.apply_update(u);
+        assert_eq!(txn.get_text("text").to_string(), "afghi");
+    }
+
+    #[test]
+    fn state_vector_and_diff_v2() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let txt = txn.get_text("text");
+        txt.insert(&mut txn, 0, "hello");
+        let sv = txn.state_vector();
+        txt.insert(&mut txn, 5, " world");
+        txn.commit();
+        let update = txn.encode_diff_v2(&StateVector::default());
+
+        let actual = encode_state_vector_from_update_v2(&update).unwrap();
+        assert_eq!(
+            StateVector::decode_v2(actual.as_slice()).unwrap(),
+            txn.state_vector()
+        );
+
+        let diff = diff_updates_v2(&update, &sv.encode_v2()).unwrap();
+        let u = Update::decode_v2(diff.as_slice()).unwrap();
+        // diff contains only the " world" insertion
+        assert_eq!(u.state_vector().get(&1), 11);
+        assert_eq!(u.blocks.blocks().count(), 1);
+    }
 }
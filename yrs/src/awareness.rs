@@ -0,0 +1,513 @@
+//! Implementation of the [y-protocols](https://github.com/yjs/y-protocols) awareness protocol.
+//!
+//! Awareness is a simple CRDT used to propagate non-persistent information about peers working
+//! on the same document, like their names, cursor positions or online status. Every peer
+//! (identified by a client ID of its document) owns a single JSON encoded state, which is
+//! overridden whenever it changes. States of the peers, which didn't renew their state in a
+//! timely manner (see: [OUTDATED_TIMEOUT]), are considered offline and removed.
+
+use crate::block::ClientID;
+use crate::event::SubscriptionId;
+use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::encoder::{Encode, Encoder};
+use crate::Doc;
+use lib0::error::Error;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Time (in milliseconds) after which a state of a remote peer, which has not been renewed, is
+/// considered outdated and removed by [Awareness::remove_outdated].
+pub const OUTDATED_TIMEOUT: u64 = 30000;
+
+/// JSON representation of a removed (null) state.
+const NULL_STATE: &str = "null";
+
+type Subscriptions = HashMap<SubscriptionId, Box<dyn Fn(&Awareness, &AwarenessEvent)>>;
+
+/// Awareness keeps track of the states of all peers working on the same document, including
+/// a state of a local peer.
+///
+/// States are represented as JSON strings, to stay compatible with y-protocols. A new instance
+/// starts with an empty object `{}` as a local state.
+pub struct Awareness {
+    doc: Doc,
+    states: HashMap<ClientID, String>,
+    meta: HashMap<ClientID, MetaClientState>,
+    clock: Box<dyn Fn() -> u64>,
+    on_update: Subscriptions,
+    on_change: Subscriptions,
+}
+
+impl Awareness {
+    /// Creates a new awareness instance for a given document. Its local state is identified by
+    /// [Doc::client_id]. Timestamps of state updates are taken from a system clock.
+    pub fn new(doc: Doc) -> Self {
+        Self::with_clock(doc, || {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Creates a new awareness instance for a given document, using a custom `clock` function,
+    /// which returns current time as a number of milliseconds. This can be used on platforms,
+    /// where system clock is not available.
+    pub fn with_clock<F>(doc: Doc, clock: F) -> Self
+    where
+        F: Fn() -> u64 + 'static,
+    {
+        let client_id = doc.client_id;
+        let last_updated = clock();
+        let mut states = HashMap::new();
+        states.insert(client_id, "{}".to_string());
+        let mut meta = HashMap::new();
+        meta.insert(
+            client_id,
+            MetaClientState {
+                clock: 0,
+                last_updated,
+            },
+        );
+        Awareness {
+            doc,
+            states,
+            meta,
+            clock: Box::new(clock),
+            on_update: Subscriptions::new(),
+            on_change: Subscriptions::new(),
+        }
+    }
+
+    /// Returns a document this awareness instance is bound to.
+    pub fn doc(&self) -> &Doc {
+        &self.doc
+    }
+
+    /// Returns a client ID of a local peer.
+    pub fn client_id(&self) -> ClientID {
+        self.doc.client_id
+    }
+
+    /// Returns JSON encoded states of all known peers, including a local one.
+    pub fn clients(&self) -> &HashMap<ClientID, String> {
+        &self.states
+    }
+
+    /// Returns a JSON encoded state of a local peer, if it's set.
+    pub fn local_state(&self) -> Option<&str> {
+        self.states.get(&self.client_id()).map(String::as_str)
+    }
+
+    /// Returns a metadata (clock and time of the last update) of a given peer, if it's known.
+    pub fn meta(&self, client_id: ClientID) -> Option<&MetaClientState> {
+        self.meta.get(&client_id)
+    }
+
+    /// Sets a JSON encoded state of a local peer. This increments a local clock, causing the new
+    /// state to override the previous one on remote peers.
+    pub fn set_local_state<S: Into<String>>(&mut self, json: S) {
+        self.update_local_state(Some(json.into()))
+    }
+
+    /// Removes a state of a local peer, marking it as offline for the remote peers.
+    pub fn clean_local_state(&mut self) {
+        self.update_local_state(None)
+    }
+
+    fn update_local_state(&mut self, new_state: Option<String>) {
+        let client_id = self.client_id();
+        let clock = match self.meta.get(&client_id) {
+            None => 0,
+            Some(meta) => meta.clock + 1,
+        };
+        let prev_state = match &new_state {
+            None => self.states.remove(&client_id),
+            Some(state) => self.states.insert(client_id, state.clone()),
+        };
+        let last_updated = (self.clock)();
+        self.meta.insert(
+            client_id,
+            MetaClientState {
+                clock,
+                last_updated,
+            },
+        );
+
+        let mut event = AwarenessEvent::default();
+        let changed = match (prev_state, new_state) {
+            (_, None) => {
+                event.removed.push(client_id);
+                true
+            }
+            (None, Some(_)) => {
+                event.added.push(client_id);
+                true
+            }
+            (Some(prev), Some(state)) => {
+                event.updated.push(client_id);
+                prev != state
+            }
+        };
+
+        if changed {
+            self.publish(&self.on_change, &event);
+        }
+        self.publish(&self.on_update, &event);
+    }
+
+    /// Removes states of given peers. If a local peer is among them, its clock is incremented, so
+    /// that removal will be propagated to remote peers.
+    pub fn remove_states(&mut self, clients: &[ClientID]) {
+        let local_id = self.client_id();
+        let now = (self.clock)();
+        let mut event = AwarenessEvent::default();
+        for &client_id in clients {
+            if self.states.remove(&client_id).is_some() {
+                if client_id == local_id {
+                    if let Some(meta) = self.meta.get_mut(&client_id) {
+                        meta.clock += 1;
+                        meta.last_updated = now;
+                    }
+                }
+                event.removed.push(client_id);
+            }
+        }
+
+        if !event.removed.is_empty() {
+            self.publish(&self.on_change, &event);
+            self.publish(&self.on_update, &event);
+        }
+    }
+
+    /// Renews a local state if it's about to become outdated and removes states of remote peers,
+    /// which have not been updated in [OUTDATED_TIMEOUT] milliseconds. Since awareness doesn't run
+    /// any timers on its own, this method is expected to be called periodically (y-protocols
+    /// does so every [OUTDATED_TIMEOUT] / 10 milliseconds).
+    pub fn remove_outdated(&mut self) {
+        let local_id = self.client_id();
+        let now = (self.clock)();
+        if let Some(state) = self.local_state() {
+            let last_updated = self.meta.get(&local_id).map(|m| m.last_updated);
+            if OUTDATED_TIMEOUT / 2 <= now.saturating_sub(last_updated.unwrap_or_default()) {
+                let state = state.to_string();
+                self.set_local_state(state);
+            }
+        }
+
+        let outdated: Vec<ClientID> = self
+            .meta
+            .iter()
+            .filter(|(&client_id, meta)| {
+                client_id != local_id
+                    && OUTDATED_TIMEOUT <= now.saturating_sub(meta.last_updated)
+                    && self.states.contains_key(&client_id)
+            })
+            .map(|(&client_id, _)| client_id)
+            .collect();
+        if !outdated.is_empty() {
+            self.remove_states(&outdated);
+        }
+    }
+
+    /// Returns an update containing states of all known peers (including removed ones), which
+    /// can be send to remote peers.
+    pub fn update(&self) -> AwarenessUpdate {
+        let clients: Vec<ClientID> = self.meta.keys().cloned().collect();
+        self.update_with_clients(&clients)
+    }
+
+    /// Returns an update containing states of given peers. Clients, which are not known to this
+    /// awareness instance, are skipped.
+    pub fn update_with_clients(&self, clients: &[ClientID]) -> AwarenessUpdate {
+        let mut entries = HashMap::with_capacity(clients.len());
+        for client_id in clients {
+            if let Some(meta) = self.meta.get(client_id) {
+                let json = match self.states.get(client_id) {
+                    Some(state) => state.clone(),
+                    None => NULL_STATE.to_string(),
+                };
+                entries.insert(
+                    *client_id,
+                    AwarenessUpdateEntry {
+                        clock: meta.clock,
+                        json,
+                    },
+                );
+            }
+        }
+        AwarenessUpdate { clients: entries }
+    }
+
+    /// Applies an update incoming from a remote peer. States are overridden only if their clock
+    /// is higher than the one already known. A local state cannot be removed by a remote peer.
+    pub fn apply_update(&mut self, update: AwarenessUpdate) {
+        let local_id = self.client_id();
+        let now = (self.clock)();
+        let mut event = AwarenessEvent::default();
+        let mut changed = false;
+
+        for (client_id, entry) in update.clients {
+            let AwarenessUpdateEntry { mut clock, json } = entry;
+            let is_null = json == NULL_STATE;
+            let known = self.meta.contains_key(&client_id);
+            let curr_clock = self.meta.get(&client_id).map(|m| m.clock).unwrap_or(0);
+            if curr_clock < clock
+                || (curr_clock == clock && is_null && self.states.contains_key(&client_id))
+            {
+                let prev_state = if is_null {
+                    if client_id == local_id && self.states.contains_key(&client_id) {
+                        // remote client removed our state - we need to override it
+                        clock += 1;
+                        self.states.get(&client_id).cloned()
+                    } else {
+                        self.states.remove(&client_id)
+                    }
+                } else {
+                    self.states.insert(client_id, json.clone())
+                };
+                self.meta.insert(
+                    client_id,
+                    MetaClientState {
+                        clock,
+                        last_updated: now,
+                    },
+                );
+
+                if !known && !is_null {
+                    event.added.push(client_id);
+                    changed = true;
+                } else if known && is_null {
+                    event.removed.push(client_id);
+                    changed = true;
+                } else if !is_null {
+                    if prev_state.as_ref() != Some(&json) {
+                        changed = true;
+                    }
+                    event.updated.push(client_id);
+                }
+            }
+        }
+
+        if changed {
+            self.publish(&self.on_change, &event);
+        }
+        if !event.is_empty() {
+            self.publish(&self.on_update, &event);
+        }
+    }
+
+    /// Subscribes a callback triggered whenever states of the peers have been added, removed or
+    /// their contents have changed.
+    ///
+    /// Returns a subscription ID, which can be used to unsubscribe (see:
+    /// [Awareness::unobserve_change]).
+    pub fn on_change<F>(&mut self, f: F) -> SubscriptionId
+    where
+        F: Fn(&Awareness, &AwarenessEvent) + 'static,
+    {
+        Self::subscribe(&mut self.on_change, f)
+    }
+
+    /// Unsubscribes a callback registered using [Awareness::on_change].
+    pub fn unobserve_change(&mut self, subscription_id: SubscriptionId) {
+        self.on_change.remove(&subscription_id);
+    }
+
+    /// Subscribes a callback triggered whenever states of the peers have been updated, even if
+    /// their contents didn't change. This is useful to propagate local state renewals to remote
+    /// peers.
+    ///
+    /// Returns a subscription ID, which can be used to unsubscribe (see:
+    /// [Awareness::unobserve_update]).
+    pub fn on_update<F>(&mut self, f: F) -> SubscriptionId
+    where
+        F: Fn(&Awareness, &AwarenessEvent) + 'static,
+    {
+        Self::subscribe(&mut self.on_update, f)
+    }
+
+    /// Unsubscribes a callback registered using [Awareness::on_update].
+    pub fn unobserve_update(&mut self, subscription_id: SubscriptionId) {
+        self.on_update.remove(&subscription_id);
+    }
+
+    fn subscribe<F>(subscriptions: &mut Subscriptions, f: F) -> SubscriptionId
+    where
+        F: Fn(&Awareness, &AwarenessEvent) + 'static,
+    {
+        let id = rand::thread_rng().next_u32();
+        subscriptions.insert(id, Box::new(f));
+        id
+    }
+
+    fn publish(&self, subscriptions: &Subscriptions, event: &AwarenessEvent) {
+        for f in subscriptions.values() {
+            f(self, event);
+        }
+    }
+}
+
+/// Metadata of a peer state known to [Awareness].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetaClientState {
+    /// Logical clock of a peer state, incremented with every state change.
+    pub clock: u32,
+    /// Time (in milliseconds) of the last state update received from a peer.
+    pub last_updated: u64,
+}
+
+/// Event triggered by [Awareness] whenever states of the peers have been modified.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AwarenessEvent {
+    /// Peers, whose states have been added.
+    pub added: Vec<ClientID>,
+    /// Peers, whose states have been updated.
+    pub updated: Vec<ClientID>,
+    /// Peers, whose states have been removed.
+    pub removed: Vec<ClientID>,
+}
+
+impl AwarenessEvent {
+    /// Returns all peers affected by this event.
+    pub fn all_changes(&self) -> Vec<ClientID> {
+        let mut res =
+            Vec::with_capacity(self.added.len() + self.updated.len() + self.removed.len());
+        res.extend_from_slice(&self.added);
+        res.extend_from_slice(&self.updated);
+        res.extend_from_slice(&self.removed);
+        res
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Update of peer states, which can be exchanged between [Awareness] instances.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AwarenessUpdate {
+    pub clients: HashMap<ClientID, AwarenessUpdateEntry>,
+}
+
+/// A single peer state entry of [AwarenessUpdate].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwarenessUpdateEntry {
+    /// Logical clock of a peer state.
+    pub clock: u32,
+    /// JSON encoded peer state or `null` if it has been removed.
+    pub json: String,
+}
+
+impl Encode for AwarenessUpdate {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        encoder.write_var(self.clients.len());
+        for (&client_id, entry) in self.clients.iter() {
+            encoder.write_var(client_id);
+            encoder.write_var(entry.clock);
+            encoder.write_string(&entry.json);
+        }
+    }
+}
+
+impl Decode for AwarenessUpdate {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let len: usize = decoder.read_var()?;
+        let mut clients = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let client_id: ClientID = decoder.read_var()?;
+            let clock: u32 = decoder.read_var()?;
+            let json = decoder.read_string()?.to_string();
+            clients.insert(client_id, AwarenessUpdateEntry { clock, json });
+        }
+        Ok(AwarenessUpdate { clients })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::awareness::{
+        Awareness, AwarenessEvent, AwarenessUpdate, AwarenessUpdateEntry, OUTDATED_TIMEOUT,
+    };
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::Doc;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    fn update(dst: &mut Awareness, src: &Awareness) {
+        let bin = src.update().encode_v1();
+        dst.apply_update(AwarenessUpdate::decode_v1(bin.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn awareness_sync() {
+        let mut a1 = Awareness::new(Doc::with_client_id(1));
+        let mut a2 = Awareness::new(Doc::with_client_id(2));
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let c = changes.clone();
+        a2.on_change(move |_, e: &AwarenessEvent| c.borrow_mut().push(e.clone()));
+
+        a1.set_local_state(r#"{"x":3}"#);
+        update(&mut a2, &a1);
+        assert_eq!(a2.clients().get(&1).unwrap(), r#"{"x":3}"#);
+        assert_eq!(a2.meta(1).unwrap().clock, 1);
+        assert_eq!(changes.borrow_mut().pop().unwrap().added, vec![1]);
+
+        a1.set_local_state(r#"{"x":4}"#);
+        update(&mut a2, &a1);
+        assert_eq!(a2.clients().get(&1).unwrap(), r#"{"x":4}"#);
+        assert_eq!(changes.borrow_mut().pop().unwrap().updated, vec![1]);
+
+        // state renewal doesn't trigger change event
+        a1.set_local_state(r#"{"x":4}"#);
+        update(&mut a2, &a1);
+        assert!(changes.borrow().is_empty());
+
+        // stale updates are ignored
+        let stale = a1.update();
+        a1.clean_local_state();
+        update(&mut a2, &a1);
+        assert!(a2.clients().get(&1).is_none());
+        assert_eq!(changes.borrow_mut().pop().unwrap().removed, vec![1]);
+        a2.apply_update(stale);
+        assert!(a2.clients().get(&1).is_none());
+
+        // remote peer cannot remove our own state
+        let mut update = AwarenessUpdate::default();
+        update.clients.insert(
+            2,
+            AwarenessUpdateEntry {
+                clock: a2.meta(2).unwrap().clock,
+                json: "null".to_string(),
+            },
+        );
+        a2.apply_update(update);
+        assert_eq!(a2.local_state(), Some("{}"));
+        assert!(a2.meta(2).unwrap().clock > 0);
+    }
+
+    #[test]
+    fn awareness_outdated() {
+        let now = Rc::new(Cell::new(0u64));
+        let n = now.clone();
+        let mut a1 = Awareness::with_clock(Doc::with_client_id(1), move || n.get());
+        let n = now.clone();
+        let mut a2 = Awareness::with_clock(Doc::with_client_id(2), move || n.get());
+        a1.set_local_state(r#"{"x":1}"#);
+        update(&mut a2, &a1);
+        assert!(a2.clients().contains_key(&1));
+
+        now.set(OUTDATED_TIMEOUT / 2);
+        a1.remove_outdated();
+        a2.remove_outdated();
+        assert_eq!(a1.meta(1).unwrap().clock, 2); // local state has been renewed
+        assert!(a2.clients().contains_key(&1));
+
+        now.set(OUTDATED_TIMEOUT);
+        a2.remove_outdated();
+        assert!(!a2.clients().contains_key(&1));
+        assert!(a2.clients().contains_key(&2));
+    }
+}
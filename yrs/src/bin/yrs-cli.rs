@@ -0,0 +1,614 @@
+//! `yrs-cli` is a small inspection tool for documents and updates produced by Yrs/Yjs. It can be
+//! used to triage corrupted documents without writing any code. Build it with:
+//!
+//! ```bash
+//! cargo install yrs --features cli
+//! ```
+
+use lib0::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::io::{Read, Write};
+use yrs::updates::decoder::Decode;
+use yrs::{Doc, Update};
+
+const USAGE: &str = "\
+yrs-cli - inspect Yrs/Yjs updates and persisted documents
+
+USAGE:
+    yrs-cli [--v2] <COMMAND> <FILE>...
+
+COMMANDS:
+    blocks        print a block-by-block listing of an update and its delete set
+    state-vector  print a state vector of an update
+    diff          compare two updates: <FILE_A> <FILE_B>
+    json          dump document content as JSON
+    update-json   dump full update structure as JSON
+    help          print this message
+
+Every FILE contains a single binary update (use `-` to read it from stdin). When multiple files
+are provided, they are merged together before being inspected, so a persisted log of incremental
+updates can be passed as is.
+
+OPTIONS:
+    --v2          decode files using lib0 v2 encoding (v1 is used by default)";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if let Err(e) = run(&args, &mut out) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run<W: Write>(args: &[String], out: &mut W) -> Result<(), Box<dyn Error>> {
+    let mut v2 = false;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--v2" => v2 = true,
+            "-h" | "--help" => positional.insert(0, "help"),
+            other if other.starts_with("--") => {
+                return Err(format!("unknown option: {}\n\n{}", other, USAGE).into())
+            }
+            other => positional.push(other),
+        }
+    }
+    let (command, files) = match positional.split_first() {
+        Some((command, files)) => (*command, files),
+        None => return Err(USAGE.into()),
+    };
+    if command == "help" {
+        writeln!(out, "{}", USAGE)?;
+        return Ok(());
+    }
+    if files.is_empty() {
+        return Err(format!("no input files provided\n\n{}", USAGE).into());
+    }
+    let output = match command {
+        "blocks" => blocks(&load(files, v2)?),
+        "state-vector" => state_vector(&load(files, v2)?),
+        "diff" => {
+            if files.len() != 2 {
+                return Err("diff requires exactly two input files".into());
+            }
+            let a = load(&files[..1], v2)?;
+            let b = load(&files[1..], v2)?;
+            diff(files[0], &a, files[1], &b)
+        }
+        "json" => document_json(load(files, v2)?),
+        "update-json" => {
+            let mut buf = String::new();
+            write_json(&mut buf, &load(files, v2)?.to_json(), Some(0));
+            buf
+        }
+        other => return Err(format!("unknown command: {}\n\n{}", other, USAGE).into()),
+    };
+    writeln!(out, "{}", output.trim_end())?;
+    Ok(())
+}
+
+/// Reads and decodes all `files`, merging them into a single update.
+fn load(files: &[&str], v2: bool) -> Result<Update, Box<dyn Error>> {
+    let mut updates = Vec::with_capacity(files.len());
+    for &path in files {
+        let data = if path == "-" {
+            let mut buf = Vec::new();
+            std::io::stdin().read_to_end(&mut buf)?;
+            buf
+        } else {
+            std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?
+        };
+        let update = if v2 {
+            Update::decode_v2(&data)
+        } else {
+            Update::decode_v1(&data)
+        };
+        updates.push(update.map_err(|e| format!("{}: failed to decode update: {}", path, e))?);
+    }
+    Ok(if updates.len() == 1 {
+        updates.pop().unwrap()
+    } else {
+        Update::merge_updates(updates)
+    })
+}
+
+fn blocks(update: &Update) -> String {
+    let json = update.to_json();
+    let mut buf = String::new();
+    for client in array(&json, "clients") {
+        let blocks = array(client, "blocks");
+        writeln!(
+            buf,
+            "client {} ({} blocks)",
+            number(client, "client"),
+            blocks.len()
+        )
+        .unwrap();
+        for block in blocks {
+            buf.push_str("  ");
+            write_block(&mut buf, block);
+            buf.push('\n');
+        }
+    }
+    buf.push_str("delete set:\n");
+    write_delete_set(&mut buf, &delete_set(&json));
+    buf
+}
+
+fn write_block(buf: &mut String, block: &Any) {
+    write!(buf, "{} {}", id(get(block, "id")), string(block, "type")).unwrap();
+    write!(buf, " len: {}", number(block, "len")).unwrap();
+    if let Any::Map(map) = block {
+        if let Some(origin) = map.get("origin") {
+            write!(buf, ", origin-l: {}", id(origin)).unwrap();
+        }
+        if let Some(origin) = map.get("right_origin") {
+            write!(buf, ", origin-r: {}", id(origin)).unwrap();
+        }
+        match map.get("parent") {
+            Some(Any::String(name)) => write!(buf, ", parent: '{}'", name).unwrap(),
+            Some(parent) => write!(buf, ", parent: {}", id(parent)).unwrap(),
+            None => {}
+        }
+        if let Some(Any::String(key)) = map.get("parent_sub") {
+            write!(buf, ", parent-sub: '{}'", key).unwrap();
+        }
+        if let Some(content) = map.get("content") {
+            buf.push_str(", content: ");
+            write_json(buf, content, None);
+        }
+    }
+}
+
+fn state_vector(update: &Update) -> String {
+    let sv = update.state_vector();
+    let sorted: BTreeMap<_, _> = sv.iter().collect();
+    let mut buf = String::new();
+    for (client, clock) in sorted {
+        writeln!(buf, "{}: {}", client, clock).unwrap();
+    }
+    buf
+}
+
+fn diff(name_a: &str, a: &Update, name_b: &str, b: &Update) -> String {
+    let mut buf = String::new();
+    let (sv_a, sv_b) = (a.state_vector(), b.state_vector());
+    let clients: BTreeMap<_, _> = sv_a.iter().chain(sv_b.iter()).collect();
+    let mut sv_diff = String::new();
+    for &client in clients.keys() {
+        let (clock_a, clock_b) = (sv_a.get(client), sv_b.get(client));
+        if clock_a != clock_b {
+            writeln!(sv_diff, "  {}: {} -> {}", client, clock_a, clock_b).unwrap();
+        }
+    }
+    if !sv_diff.is_empty() {
+        writeln!(buf, "state vector ({} -> {}):\n{}", name_a, name_b, sv_diff).unwrap();
+    }
+
+    let (json_a, json_b) = (a.to_json(), b.to_json());
+    for (name, json, sv) in [(name_a, &json_a, &sv_b), (name_b, &json_b, &sv_a)] {
+        let mut listing = String::new();
+        for client in array(json, "clients") {
+            let client_id = number(client, "client") as u64;
+            for block in array(client, "blocks") {
+                let clock = number(get(block, "id"), "clock") as u32;
+                let len = number(block, "len") as u32;
+                if string(block, "type") != "skip" && clock + len > sv.get(&client_id) {
+                    listing.push_str("  ");
+                    write_block(&mut listing, block);
+                    listing.push('\n');
+                }
+            }
+        }
+        if !listing.is_empty() {
+            writeln!(buf, "blocks only in {}:\n{}", name, listing).unwrap();
+        }
+    }
+
+    let (ds_a, ds_b) = (delete_set(&json_a), delete_set(&json_b));
+    for (name, ds, other) in [(name_a, &ds_a, &ds_b), (name_b, &ds_b, &ds_a)] {
+        let mut only: BTreeMap<u64, Vec<(u32, u32)>> = BTreeMap::new();
+        for (client, ranges) in ds.iter() {
+            let empty = Vec::new();
+            let subtracted = subtract_ranges(ranges, other.get(client).unwrap_or(&empty));
+            if !subtracted.is_empty() {
+                only.insert(*client, subtracted);
+            }
+        }
+        if !only.is_empty() {
+            writeln!(buf, "deletions only in {}:", name).unwrap();
+            write_delete_set(&mut buf, &only);
+            buf.push('\n');
+        }
+    }
+
+    if buf.is_empty() {
+        buf.push_str("updates are equivalent\n");
+    }
+    buf
+}
+
+/// Returns a delete set as a sorted map of client ID to sorted, non-overlapping `[start, end)`
+/// clock ranges.
+fn delete_set(json: &Any) -> BTreeMap<u64, Vec<(u32, u32)>> {
+    let mut result = BTreeMap::new();
+    for entry in array(json, "delete_set") {
+        let mut ranges: Vec<(u32, u32)> = array(entry, "ranges")
+            .iter()
+            .map(|r| {
+                let start = number(r, "clock") as u32;
+                (start, start + number(r, "len") as u32)
+            })
+            .collect();
+        ranges.sort();
+        result.insert(number(entry, "client") as u64, ranges);
+    }
+    result
+}
+
+fn write_delete_set(buf: &mut String, ds: &BTreeMap<u64, Vec<(u32, u32)>>) {
+    for (client, ranges) in ds.iter() {
+        write!(buf, "  {}:", client).unwrap();
+        for (start, end) in ranges {
+            write!(buf, " [{}..{})", start, end).unwrap();
+        }
+        buf.push('\n');
+    }
+}
+
+/// Returns parts of sorted `ranges` which are not covered by any of sorted `other` ranges.
+fn subtract_ranges(ranges: &[(u32, u32)], other: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut result = Vec::new();
+    for &(mut start, end) in ranges {
+        for &(o_start, o_end) in other {
+            if o_end <= start || o_start >= end {
+                continue;
+            }
+            if o_start > start {
+                result.push((start, o_start));
+            }
+            start = start.max(o_end);
+            if start >= end {
+                break;
+            }
+        }
+        if start < end {
+            result.push((start, end));
+        }
+    }
+    result
+}
+
+/// Kind of a root-level shared collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RootKind {
+    Unknown,
+    Array,
+    XmlFragment,
+    Text,
+    Map,
+}
+
+/// Infers names and kinds of root-level types defined by an update. Root of every item is
+/// resolved either from its explicitly defined parent or from the items it has been inserted
+/// next to, while collection kind is inferred from content of all items belonging to that root.
+fn root_types(update: &Update) -> BTreeMap<String, RootKind> {
+    let json = update.to_json();
+    let mut items = Vec::new();
+    let mut index: HashMap<u64, Vec<(u32, u32, usize)>> = HashMap::new();
+    for client in array(&json, "clients") {
+        for block in array(client, "blocks") {
+            if string(block, "type") == "item" {
+                let id = get(block, "id");
+                let clock = number(id, "clock") as u32;
+                let len = number(block, "len") as u32;
+                index.entry(number(id, "client") as u64).or_default().push((
+                    clock,
+                    clock + len,
+                    items.len(),
+                ));
+                items.push(block);
+            }
+        }
+    }
+    let find = |id: &Any| -> Option<usize> {
+        let blocks = index.get(&(number(id, "client") as u64))?;
+        let clock = number(id, "clock") as u32;
+        let i = blocks.partition_point(|&(_, end, _)| end <= clock);
+        match blocks.get(i) {
+            Some(&(start, _, idx)) if start <= clock => Some(idx),
+            _ => None,
+        }
+    };
+
+    // Some(Some(name)) for items of root type `name`, Some(None) for items of nested types
+    let mut parents: Vec<Option<Option<&str>>> = vec![None; items.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (i, item) in items.iter().enumerate() {
+            if parents[i].is_some() {
+                continue;
+            }
+            let resolved = match get(item, "parent") {
+                Any::String(name) => Some(Some(name.as_ref())),
+                Any::Null => ["origin", "right_origin"]
+                    .iter()
+                    .filter_map(|key| find(get(item, key)))
+                    .find_map(|j| parents[j]),
+                _ => Some(None),
+            };
+            if resolved.is_some() {
+                parents[i] = resolved;
+                changed = true;
+            }
+        }
+    }
+
+    let mut roots = BTreeMap::new();
+    for (item, parent) in items.iter().zip(parents) {
+        if let Some(Some(name)) = parent {
+            let kind = if let Any::Map(map) = item {
+                if map.contains_key("parent_sub") {
+                    RootKind::Map
+                } else {
+                    content_kind(get(item, "content"))
+                }
+            } else {
+                RootKind::Unknown
+            };
+            let e = roots.entry(name.to_owned()).or_insert(kind);
+            *e = kind.max(*e);
+        }
+    }
+    roots
+}
+
+fn content_kind(content: &Any) -> RootKind {
+    let kind = match content {
+        Any::Map(map) => map.keys().next().map(String::as_str),
+        _ => None,
+    };
+    match kind {
+        Some("string") | Some("format") | Some("embed") => RootKind::Text,
+        Some("type") if is_xml(get(get(content, "type"), "type_ref")) => RootKind::XmlFragment,
+        Some("deleted") | None => RootKind::Unknown,
+        Some(_) => RootKind::Array,
+    }
+}
+
+fn document_json(update: Update) -> String {
+    let roots = root_types(&update);
+    let doc = Doc::new();
+    let mut txn = doc.transact();
+    txn.apply_update(update);
+    let mut content = HashMap::new();
+    for (name, kind) in roots {
+        let value = match kind {
+            RootKind::Map => txn.get_map(&name).to_json(),
+            RootKind::Text => Any::String(txn.get_text(&name).to_string().into()),
+            RootKind::XmlFragment => Any::String(txn.get_xml_fragment(&name).to_string().into()),
+            RootKind::Array | RootKind::Unknown => txn.get_array(&name).to_json(),
+        };
+        content.insert(name, value);
+    }
+    let mut buf = String::new();
+    write_json(&mut buf, &Any::Map(Box::new(content)), Some(0));
+    buf
+}
+
+/// Writes JSON representation of a given value with object keys sorted. If `indent` is provided,
+/// output is pretty-printed. Values not supported by JSON are written in their closest
+/// JSON-compatible form.
+fn write_json(buf: &mut String, value: &Any, indent: Option<usize>) {
+    fn newline(buf: &mut String, indent: Option<usize>) {
+        if let Some(indent) = indent {
+            buf.push('\n');
+            buf.extend(std::iter::repeat(' ').take(indent * 2));
+        }
+    }
+    let nested = indent.map(|i| i + 1);
+    match value {
+        Any::Null | Any::Undefined => buf.push_str("null"),
+        Any::Bool(b) => write!(buf, "{}", b).unwrap(),
+        Any::Number(n) if n.is_finite() => write!(buf, "{}", n).unwrap(),
+        Any::Number(_) => buf.push_str("null"),
+        Any::BigInt(n) => write!(buf, "{}", n).unwrap(),
+        Any::Int128(n) => write!(buf, "{}", n).unwrap(),
+        Any::Decimal(n) => write!(buf, "{}", n).unwrap(),
+        Any::Date(_) => write!(buf, "\"{}\"", value).unwrap(),
+        Any::String(s) => write!(buf, "{:?}", s).unwrap(),
+        Any::Buffer(bytes) => {
+            let values: Vec<Any> = bytes.iter().map(|b| Any::Number(*b as f64)).collect();
+            write_json(buf, &Any::Array(values.into()), None)
+        }
+        Any::Array(values) if values.is_empty() => buf.push_str("[]"),
+        Any::Array(values) => {
+            buf.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i != 0 {
+                    buf.push(',');
+                }
+                newline(buf, nested);
+                write_json(buf, value, nested);
+            }
+            newline(buf, indent);
+            buf.push(']');
+        }
+        Any::Map(entries) if entries.is_empty() => buf.push_str("{}"),
+        Any::Map(entries) => {
+            let sorted: BTreeMap<_, _> = entries.iter().collect();
+            buf.push('{');
+            for (i, (key, value)) in sorted.into_iter().enumerate() {
+                if i != 0 {
+                    buf.push(',');
+                }
+                newline(buf, nested);
+                write!(buf, "{:?}:", key).unwrap();
+                if indent.is_some() {
+                    buf.push(' ');
+                }
+                write_json(buf, value, nested);
+            }
+            newline(buf, indent);
+            buf.push('}');
+        }
+    }
+}
+
+fn get<'a>(json: &'a Any, key: &str) -> &'a Any {
+    match json {
+        Any::Map(map) => map.get(key).unwrap_or(&Any::Null),
+        _ => &Any::Null,
+    }
+}
+
+fn array<'a>(json: &'a Any, key: &str) -> &'a [Any] {
+    match get(json, key) {
+        Any::Array(values) => values,
+        _ => &[],
+    }
+}
+
+fn number(json: &Any, key: &str) -> f64 {
+    match get(json, key) {
+        Any::Number(n) => *n,
+        _ => 0.0,
+    }
+}
+
+fn string<'a>(json: &'a Any, key: &str) -> &'a str {
+    match get(json, key) {
+        Any::String(s) => s,
+        _ => "",
+    }
+}
+
+fn is_xml(type_ref: &Any) -> bool {
+    matches!(type_ref, Any::String(name) if name.starts_with("xml_"))
+}
+
+fn id(json: &Any) -> String {
+    format!(
+        "<{}#{}>",
+        number(json, "client") as u64,
+        number(json, "clock") as u32
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use yrs::updates::encoder::Encode;
+    use yrs::StateVector;
+
+    fn run_cli(args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        run(&args, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn write_update(name: &str, doc: &Doc, sv: &StateVector) -> String {
+        let path = std::env::temp_dir().join(format!("yrs-cli-{}-{}", std::process::id(), name));
+        std::fs::write(&path, doc.encode_state_as_update_v1(sv)).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn inspect_update() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact();
+            let text = txn.get_text("text");
+            text.insert(&mut txn, 0, "abc");
+            text.remove_range(&mut txn, 0, 1);
+            let map = txn.get_map("map");
+            map.insert(&mut txn, "key", 1);
+        }
+        let path = write_update("inspect", &doc, &StateVector::default());
+
+        let listing = run_cli(&["blocks", &path]);
+        assert_eq!(
+            listing,
+            "client 1 (3 blocks)\n  \
+             <1#0> item len: 1, parent: 'text', content: {\"deleted\":1}\n  \
+             <1#1> item len: 2, origin-l: <1#0>, content: {\"string\":\"bc\"}\n  \
+             <1#3> item len: 1, parent: 'map', parent-sub: 'key', content: {\"any\":[1]}\n\
+             delete set:\n  \
+             1: [0..1)\n"
+        );
+
+        assert_eq!(run_cli(&["state-vector", &path]), "1: 4\n");
+        assert_eq!(
+            run_cli(&["json", &path]),
+            "{\n  \"map\": {\n    \"key\": 1\n  },\n  \"text\": \"bc\"\n}\n"
+        );
+
+        let update = Update::decode_v1(&std::fs::read(&path).unwrap()).unwrap();
+        let json = run_cli(&["update-json", &path]);
+        let parsed = Update::from_json(&Any::from_json(&json).unwrap()).unwrap();
+        assert_eq!(parsed.encode_v1(), update.encode_v1());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn diff_updates() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact();
+            let text = txn.get_text("text");
+            text.push(&mut txn, "ab");
+        }
+        let a = write_update("diff-a", &doc, &StateVector::default());
+        {
+            let mut txn = doc.transact();
+            let text = txn.get_text("text");
+            text.push(&mut txn, "c");
+            text.remove_range(&mut txn, 0, 1);
+        }
+        let b = write_update("diff-b", &doc, &StateVector::default());
+
+        assert_eq!(run_cli(&["diff", &a, &a]), "updates are equivalent\n");
+        let output = run_cli(&["diff", &a, &b]);
+        assert_eq!(
+            output,
+            format!(
+                "state vector ({a} -> {b}):\n  1: 2 -> 3\n\n\
+                 blocks only in {b}:\n  \
+                 <1#1> item len: 2, origin-l: <1#0>, content: {{\"string\":\"bc\"}}\n\n\
+                 deletions only in {b}:\n  1: [0..1)\n",
+                a = a,
+                b = b
+            )
+        );
+        std::fs::remove_file(a).unwrap();
+        std::fs::remove_file(b).unwrap();
+    }
+
+    #[test]
+    fn subtract() {
+        assert_eq!(subtract_ranges(&[(0, 10)], &[]), vec![(0, 10)]);
+        assert_eq!(
+            subtract_ranges(&[(0, 10), (12, 14)], &[(2, 4), (6, 7), (9, 13)]),
+            vec![(0, 2), (4, 6), (7, 9), (13, 14)]
+        );
+        assert_eq!(subtract_ranges(&[(3, 5)], &[(0, 10)]), vec![]);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let path = std::env::temp_dir().join(format!("yrs-cli-{}-invalid", std::process::id()));
+        std::fs::write(&path, [1, 1, 1, 0, 4]).unwrap();
+        let args = vec!["blocks".to_owned(), path.to_str().unwrap().to_owned()];
+        let err = run(&args, &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("failed to decode update"));
+        std::fs::remove_file(path).unwrap();
+
+        assert!(run(&["unknown".to_owned()], &mut Vec::new()).is_err());
+    }
+}
@@ -2,11 +2,11 @@ use crate::doc::OffsetKind;
 use crate::moving::Move;
 use crate::store::Store;
 use crate::types::{
-    Attrs, Branch, BranchPtr, TypePtr, Value, TYPE_REFS_ARRAY, TYPE_REFS_MAP, TYPE_REFS_TEXT,
-    TYPE_REFS_UNDEFINED, TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_FRAGMENT, TYPE_REFS_XML_HOOK,
-    TYPE_REFS_XML_TEXT,
+    Attrs, Branch, BranchPtr, TypePtr, Value, TYPE_REFS_ARRAY, TYPE_REFS_COUNTER, TYPE_REFS_MAP,
+    TYPE_REFS_TEXT, TYPE_REFS_UNDEFINED, TYPE_REFS_WEAK, TYPE_REFS_XML_ELEMENT,
+    TYPE_REFS_XML_FRAGMENT, TYPE_REFS_XML_HOOK, TYPE_REFS_XML_TEXT,
 };
-use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::decoder::{Decode, Decoder, MAX_PREALLOC};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::*;
 use lib0::any::Any;
@@ -14,10 +14,12 @@ use lib0::error::Error;
 use smallstr::SmallString;
 use std::collections::HashSet;
 use std::hash::Hash;
+use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
 use std::panic;
 use std::ptr::NonNull;
 use std::rc::Rc;
+use std::sync::Arc;
 
 /// Bit flag used to identify [Block::GC].
 pub const BLOCK_GC_REF_NUMBER: u8 = 0;
@@ -55,6 +57,9 @@ pub const BLOCK_SKIP_REF_NUMBER: u8 = 10;
 /// Bit flag used to identify items with content of type [ItemContent::Move].
 pub const BLOCK_ITEM_MOVE_REF_NUMBER: u8 = 11;
 
+/// Bit flag used to identify items with content of type [ItemContent::Extension].
+pub const BLOCK_ITEM_EXTENSION_REF_NUMBER: u8 = 12;
+
 /// Bit flag used to tell if encoded item has right origin defined.
 pub const HAS_RIGHT_ORIGIN: u8 = 0b01000000;
 
@@ -113,7 +118,40 @@ impl BlockPtr {
         }
     }
 
+    /// Points a shared type stored inside of current block (if any) back to this block. It must
+    /// be called whenever a block is moved into its final memory location.
+    pub(crate) fn link_branch(mut self) {
+        let self_ptr = self;
+        if let Block::Item(item) = self.deref_mut() {
+            if let ItemContent::Type(branch) = &mut item.content {
+                branch.item = Some(self_ptr);
+            }
+        }
+    }
+
     pub(crate) fn splice(&mut self, offset: u32, encoding: OffsetKind) -> Option<Box<Block>> {
+        let mut result = None;
+        self.splice_with(offset, encoding, |block| {
+            let mut block = Box::new(block);
+            let ptr = BlockPtr::from(&mut block);
+            result = Some(block);
+            ptr
+        })?;
+        result
+    }
+
+    /// Splits current block at a given `offset`, same as [BlockPtr::splice]. The right half is
+    /// placed in memory by an `alloc` function before it gets linked with its neighbors, which
+    /// allows it to be stored directly inside of a client block arena.
+    pub(crate) fn splice_with<F>(
+        &mut self,
+        offset: u32,
+        encoding: OffsetKind,
+        alloc: F,
+    ) -> Option<BlockPtr>
+    where
+        F: FnOnce(Block) -> BlockPtr,
+    {
         let self_ptr = self.clone();
         if offset == 0 {
             None
@@ -124,7 +162,7 @@ impl BlockPtr {
                     let clock = item.id.clock;
                     let content = item.content.splice(offset as usize, encoding).unwrap();
                     item.len = offset;
-                    let mut new = Box::new(Block::Item(Item {
+                    let new_ptr = alloc(Block::Item(Item {
                         id: ID::new(client, clock + offset),
                         len: content.len(OffsetKind::Utf16),
                         left: Some(self_ptr),
@@ -136,8 +174,8 @@ impl BlockPtr {
                         moved: item.moved.clone(),
                         parent_sub: item.parent_sub.clone(),
                         info: item.info.clone(),
+                        redone: item.redone.map(|id| ID::new(id.client, id.clock + offset)),
                     }));
-                    let new_ptr = BlockPtr::from(&mut new);
 
                     if let Some(Block::Item(right)) = item.right.as_deref_mut() {
                         right.left = Some(new_ptr);
@@ -154,9 +192,9 @@ impl BlockPtr {
 
                     item.right = Some(new_ptr);
 
-                    Some(new)
+                    Some(new_ptr)
                 }
-                Block::GC(gc) => Some(Box::new(Block::GC(gc.slice(offset)))),
+                Block::GC(gc) => Some(alloc(Block::GC(gc.slice(offset)))),
             }
         }
     }
@@ -170,6 +208,7 @@ impl BlockPtr {
             Block::Item(this) => {
                 let store = txn.store_mut();
                 let encoding = store.options.offset_kind;
+                let keep_conflicts = store.options.keep_map_conflicts;
                 if offset > 0 {
                     // offset is used only for locally integrated items
                     this.id.clock += offset;
@@ -238,6 +277,7 @@ impl BlockPtr {
                             parent_ref.start
                         };
 
+                        let origin_left = this.left;
                         let mut left = this.left.clone();
                         let mut conflicting_items = HashSet::new();
                         let mut items_before_origin = HashSet::new();
@@ -288,6 +328,12 @@ impl BlockPtr {
                             }
                         }
                         this.left = left;
+                        // map entries are reported as overrides instead
+                        if this.left != origin_left && this.parent_sub.is_none() {
+                            if let Some(report) = txn.report.as_mut() {
+                                report.reordered += 1;
+                            }
+                        }
                     }
 
                     if this.parent_sub.is_none() {
@@ -329,7 +375,18 @@ impl BlockPtr {
                     } else if let Some(parent_sub) = &this.parent_sub {
                         // set as current parent value if right === null and this is parentSub
                         parent_ref.map.insert(parent_sub.clone(), self_ptr);
-                        if let Some(left) = this.left {
+                        if let Some(mut left) = this.left {
+                            if !left.is_deleted() {
+                                txn.report_override(
+                                    parent_ref,
+                                    parent_sub.clone(),
+                                    *left.id(),
+                                    this.id,
+                                );
+                            }
+                            if keep_conflicts {
+                                keep_map_conflict(&mut left, this.origin);
+                            }
                             // this is the current attribute value of parent. delete right
                             txn.delete(left);
                         }
@@ -384,24 +441,30 @@ impl BlockPtr {
                             this.mark_as_deleted();
                         }
                         ItemContent::Move(m) => m.integrate_block(txn, self_ptr),
-                        ItemContent::Doc(_, _) => {
-                            //// this needs to be reflected in doc.destroy as well
-                            //this.doc._item = item
-                            //transaction.subdocsAdded.add(this.doc)
-                            //if (this.doc.shouldLoad) {
-                            //    transaction.subdocsLoaded.add(this.doc)
-                            //}
-                            todo!()
+                        ItemContent::Doc(doc) => {
+                            let mut store = doc.store.clone();
+                            store.parent = Some(self_ptr);
+                            let subdocs = txn.subdocs_mut();
+                            subdocs.added.insert(doc.addr(), doc.clone());
+                            if doc.should_load() {
+                                subdocs.loaded.insert(doc.addr(), doc.clone());
+                            }
                         }
                         ItemContent::Format(_, _) => {
                             // @todo searchmarker are currently unsupported for rich text documents
                             // /** @type {AbstractType<any>} */ (item.parent)._searchMarker = null
                         }
                         ItemContent::Type(branch) => branch.store = Some(txn.store.clone()),
+                        ItemContent::String(s) => s.move_to(&mut txn.store_mut().strings),
                         _ => {
                             // other types don't define integration-specific actions
                         }
                     }
+                    if let Some(report) = txn.report.as_mut() {
+                        if !matches!(this.content, ItemContent::Deleted(_)) {
+                            report.inserted.insert(this.id, this.len);
+                        }
+                    }
                     txn.add_changed_type(parent_ref, this.parent_sub.clone());
                     let parent_deleted = if let TypePtr::Branch(ptr) = &this.parent {
                         if let Some(block) = ptr.item {
@@ -413,13 +476,21 @@ impl BlockPtr {
                         false
                     };
                     if parent_deleted || (this.parent_sub.is_some() && this.right.is_some()) {
+                        if keep_conflicts && !parent_deleted {
+                            // this value has lost a conflict with a concurrent write
+                            this.info.set_keep();
+                        }
                         // delete if parent is deleted or if this is not the current attribute value of parent
                         true
                     } else {
                         false
                     }
                 } else {
-                    panic!("Defect: item has no parent")
+                    // parent is unknown or has been garbage collected, so is this item
+                    let gc = Block::GC(BlockRange::new(this.id, this.len));
+                    let mut self_ptr = self_ptr;
+                    *self_ptr.deref_mut() = gc;
+                    false
                 }
             }
         }
@@ -458,9 +529,14 @@ impl BlockPtr {
                     && v1.right == Some(other_ptr)
                     && v1.is_deleted() == v2.is_deleted()
                     && v1.moved == v2.moved
+                    && v1.redone.is_none()
+                    && v2.redone.is_none()
                     && v1.content.try_squash(&v2.content)
                 {
-                    v1.len = v1.content.len(OffsetKind::Utf16);
+                    v1.len += v2.len;
+                    if v2.info.is_keep() {
+                        v1.info.set_keep();
+                    }
                     if let Some(Block::Item(right_right)) = v2.right.as_deref_mut() {
                         right_right.left = Some(self_ptr);
                     }
@@ -488,6 +564,28 @@ impl BlockPtr {
     }
 }
 
+/// Used when [Options::keep_map_conflicts] is set and a map entry `prev` is being replaced by
+/// a new value with a given `origin`. If a new value was written concurrently, the content of
+/// `prev` is kept from being garbage collected. Otherwise `prev` and all of its concurrent
+/// siblings have been overridden and can be collected again.
+fn keep_map_conflict(prev: &mut BlockPtr, origin: Option<ID>) {
+    if let Block::Item(item) = prev.deref_mut() {
+        if origin != Some(item.last_id()) {
+            item.info.set_keep();
+        } else {
+            let prev_origin = item.origin;
+            let mut current = Some(*prev);
+            while let Some(Block::Item(item)) = current.as_deref_mut() {
+                if item.origin != prev_origin {
+                    break;
+                }
+                item.info.clear_keep();
+                current = item.left;
+            }
+        }
+    }
+}
+
 impl Deref for BlockPtr {
     type Target = Block;
 
@@ -508,6 +606,12 @@ impl<'a> From<&'a mut Box<Block>> for BlockPtr {
     }
 }
 
+impl<'a> From<&'a mut Block> for BlockPtr {
+    fn from(block: &'a mut Block) -> Self {
+        BlockPtr(NonNull::from(block))
+    }
+}
+
 impl<'a> From<&'a Box<Block>> for BlockPtr {
     fn from(block: &'a Box<Block>) -> Self {
         BlockPtr(unsafe { NonNull::new_unchecked(block.as_ref() as *const Block as *mut Block) })
@@ -844,7 +948,8 @@ const ITEM_FLAG_DELETED: u8 = 0b0000_0100;
 /// Bit flag (2nd bit) for an item, which contents are considered countable.
 const ITEM_FLAG_COUNTABLE: u8 = 0b0000_0010;
 
-/// Bit flag (1st bit) used for an item which should be kept - not used atm.
+/// Bit flag (1st bit) used for an item which should be kept - protected from being garbage
+/// collected even after being deleted.
 const ITEM_FLAG_KEEP: u8 = 0b0000_0001;
 
 #[repr(transparent)]
@@ -876,6 +981,16 @@ impl ItemFlags {
         self.check(ITEM_FLAG_KEEP)
     }
 
+    #[inline]
+    pub fn set_keep(&mut self) {
+        self.set(ITEM_FLAG_KEEP)
+    }
+
+    #[inline]
+    pub fn clear_keep(&mut self) {
+        self.clear(ITEM_FLAG_KEEP)
+    }
+
     #[inline]
     pub fn set_countable(&mut self) {
         self.set(ITEM_FLAG_COUNTABLE)
@@ -896,6 +1011,11 @@ impl ItemFlags {
         self.set(ITEM_FLAG_DELETED)
     }
 
+    #[inline]
+    pub fn clear_deleted(&mut self) {
+        self.clear(ITEM_FLAG_DELETED)
+    }
+
     #[inline]
     pub fn is_deleted(&self) -> bool {
         self.check(ITEM_FLAG_DELETED)
@@ -955,6 +1075,10 @@ pub(crate) struct Item {
 
     /// Bit flag field which contains information about specifics of this item.
     pub info: ItemFlags,
+
+    /// An ID of an item, which has been created to restore current (deleted) item by undo/redo
+    /// operations.
+    pub redone: Option<ID>,
 }
 
 #[derive(PartialEq, Eq, Clone)]
@@ -1022,14 +1146,14 @@ impl Item {
         parent: TypePtr,
         parent_sub: Option<Rc<str>>,
         content: ItemContent,
-    ) -> Box<Block> {
+    ) -> Block {
         let info = ItemFlags::new(if content.is_countable() {
             ITEM_FLAG_COUNTABLE
         } else {
             0
         });
         let len = content.len(OffsetKind::Utf16);
-        let mut item = Box::new(Block::Item(Item {
+        Block::Item(Item {
             id,
             len,
             left,
@@ -1041,12 +1165,8 @@ impl Item {
             parent_sub,
             info,
             moved: None,
-        }));
-        let item_ptr = BlockPtr::from(&mut item);
-        if let ItemContent::Type(branch) = &mut item.as_item_mut().unwrap().content {
-            branch.item = Some(item_ptr);
-        }
-        item
+            redone: None,
+        })
     }
 
     pub fn contains(&self, id: &ID) -> bool {
@@ -1115,12 +1235,12 @@ impl Item {
                 self.parent = branch.into();
             }
             TypePtr::ID(id) => {
-                let ptr = store.blocks.get_block(id).unwrap();
-                self.parent = if let Block::Item(item) = ptr.deref() {
+                let ptr = store.blocks.get_block(id);
+                self.parent = if let Some(Block::Item(item)) = ptr.as_deref() {
                     match &item.content {
                         ItemContent::Type(branch) => TypePtr::Branch(BranchPtr::from(branch)),
-                        ItemContent::Deleted(_) => TypePtr::Unknown,
-                        _ => panic!("Defect: parent points to a block which is not a shared type"),
+                        // deleted parent or malformed update pointing to a non-type block
+                        _ => TypePtr::Unknown,
                     }
                 } else {
                     TypePtr::Unknown
@@ -1155,14 +1275,133 @@ impl Item {
     }
 }
 
-#[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Clone)]
+/// Capacity (in bytes) of a single chunk allocated by [StringArena].
+const STRING_CHUNK_CAPACITY: usize = 16 * 1024;
+
+/// Strings longer than this (in bytes) get a dedicated [StringArena] chunk, so that they don't
+/// leave a partially filled chunk behind.
+const STRING_CHUNK_MAX_SHARED: usize = STRING_CHUNK_CAPACITY / 4;
+
+/// A fixed-capacity byte buffer, which keeps contents of strings allocated by a [StringArena].
+/// It's never reallocated and bytes once written to it are never modified, so that strings can
+/// point to them while the rest of a chunk is still being filled.
+struct StringChunk {
+    ptr: NonNull<u8>,
+    capacity: usize,
+}
+
+impl StringChunk {
+    fn new(capacity: usize) -> Self {
+        let mut buf = ManuallyDrop::new(Vec::<u8>::with_capacity(capacity));
+        StringChunk {
+            ptr: NonNull::new(buf.as_mut_ptr()).unwrap(),
+            capacity: buf.capacity(),
+        }
+    }
+}
+
+impl Drop for StringChunk {
+    fn drop(&mut self) {
+        unsafe { drop(Vec::from_raw_parts(self.ptr.as_ptr(), 0, self.capacity)) }
+    }
+}
+
+// A chunk is written only by an arena, which has allocated it, and only past the bytes already
+// referenced by strings, so that it can be safely read from other threads.
+unsafe impl Send for StringChunk {}
+unsafe impl Sync for StringChunk {}
+
+/// Arena allocator for text contents integrated into a document. Strings are copied one after
+/// another into shared chunks, so that a text is kept in a few large buffers instead of a separate
+/// heap allocation per block, and blocks can be split or squashed without copying their contents.
+/// A chunk is released once there are no more strings pointing to it.
+#[derive(Default)]
+pub(crate) struct StringArena {
+    chunk: Option<Arc<StringChunk>>,
+    len: usize,
+}
+
+impl StringArena {
+    /// Copies a given `str` into current arena, returning a string pointing to that copy.
+    pub(crate) fn alloc(&mut self, str: &str) -> SplittableString {
+        let len = str.len();
+        let (chunk, start) = if len > STRING_CHUNK_MAX_SHARED {
+            (Arc::new(StringChunk::new(len)), 0)
+        } else {
+            match &self.chunk {
+                Some(chunk) if chunk.capacity - self.len >= len => { /* there's enough space */ }
+                _ => {
+                    self.chunk = Some(Arc::new(StringChunk::new(STRING_CHUNK_CAPACITY)));
+                    self.len = 0;
+                }
+            }
+            let start = self.len;
+            self.len += len;
+            (self.chunk.clone().unwrap(), start)
+        };
+        // bytes past `start` have not been handed over to any string yet
+        unsafe {
+            std::ptr::copy_nonoverlapping(str.as_ptr(), chunk.ptr.as_ptr().add(start), len);
+        }
+        SplittableString {
+            content: StringContent::Shared(SharedStr {
+                chunk,
+                start,
+                end: start + len,
+            }),
+        }
+    }
+}
+
+/// A string slice of a [StringChunk].
+#[derive(Clone)]
+struct SharedStr {
+    chunk: Arc<StringChunk>,
+    start: usize,
+    end: usize,
+}
+
+impl SharedStr {
+    fn as_str(&self) -> &str {
+        unsafe {
+            let bytes = std::slice::from_raw_parts(
+                self.chunk.ptr.as_ptr().add(self.start),
+                self.end - self.start,
+            );
+            std::str::from_utf8_unchecked(bytes)
+        }
+    }
+}
+
+#[derive(Clone)]
+enum StringContent {
+    /// A string owned by a block, which has not been integrated into a document yet.
+    Owned(SmallString<[u8; 8]>),
+    /// A string allocated in a document's [StringArena].
+    Shared(SharedStr),
+}
+
+/// String content of an [Item]. Once an item is integrated into a document, its string is moved
+/// into that document's [StringArena].
+#[derive(Clone)]
 pub struct SplittableString {
-    content: SmallString<[u8; 8]>,
+    content: StringContent,
 }
 
 impl SplittableString {
+    /// Returns a number of bytes allocated on the heap by current string. Short strings are kept
+    /// inline and don't allocate, while strings allocated in a [StringArena] report the number
+    /// of bytes they occupy in it.
+    pub(crate) fn heap_size(&self) -> usize {
+        match &self.content {
+            StringContent::Owned(s) if s.spilled() => s.capacity(),
+            StringContent::Owned(_) => 0,
+            StringContent::Shared(s) => s.end - s.start,
+        }
+    }
+
     pub fn len(&self, kind: OffsetKind) -> usize {
-        let len = self.content.len();
+        let len = self.as_str().len();
         if len == 1 {
             len // quite often strings are single-letter, so we don't care about OffsetKind
         } else {
@@ -1176,7 +1415,10 @@ impl SplittableString {
 
     #[inline(always)]
     pub fn as_str(&self) -> &str {
-        self.content.as_str()
+        match &self.content {
+            StringContent::Owned(s) => s.as_str(),
+            StringContent::Shared(s) => s.as_str(),
+        }
     }
 
     #[inline(always)]
@@ -1185,16 +1427,62 @@ impl SplittableString {
     }
 
     pub fn unicode_len(&self) -> usize {
-        self.content.chars().count()
+        self.as_str().chars().count()
+    }
+
+    /// Returns a character starting at a given `offset`, expressed using a given `kind`. Returns
+    /// `None` if `offset` is outside of current string or it points into the middle of a
+    /// character.
+    pub fn char_at(&self, offset: usize, kind: OffsetKind) -> Option<char> {
+        let mut i = 0;
+        for c in self.as_str().chars() {
+            if i == offset {
+                return Some(c);
+            } else if i > offset {
+                break;
+            }
+            i += match kind {
+                OffsetKind::Bytes => c.len_utf8(),
+                OffsetKind::Utf16 => c.len_utf16(),
+                OffsetKind::Utf32 => 1,
+            };
+        }
+        None
     }
 
     pub fn split_at(&self, offset: usize, kind: OffsetKind) -> (&str, &str) {
-        let off = match kind {
+        let off = self.byte_offset(offset, kind);
+        self.as_str().split_at(off)
+    }
+
+    /// Splits current string at a given `offset`, leaving the left part in place and returning
+    /// the right one. Strings allocated in a [StringArena] are split without copying.
+    pub(crate) fn split_off(&mut self, offset: usize, kind: OffsetKind) -> SplittableString {
+        let off = self.byte_offset(offset, kind);
+        match &mut self.content {
+            StringContent::Owned(s) => {
+                let (left, right) = s.split_at(off);
+                let right = SplittableString::from(right);
+                *s = SmallString::from_str(left);
+                right
+            }
+            StringContent::Shared(s) => {
+                let mut right = s.clone();
+                right.start += off;
+                s.end = right.start;
+                SplittableString {
+                    content: StringContent::Shared(right),
+                }
+            }
+        }
+    }
+
+    fn byte_offset(&self, offset: usize, kind: OffsetKind) -> usize {
+        match kind {
             OffsetKind::Bytes => offset,
             OffsetKind::Utf16 => self.map_utf16_offset(offset as u32) as usize,
             OffsetKind::Utf32 => self.map_unicode_offset(offset as u32) as usize,
-        };
-        self.content.split_at(off)
+        }
     }
 
     /// Maps given offset onto block offset. This means, that given an `offset` provided
@@ -1208,7 +1496,7 @@ impl SplittableString {
                 let mut i = 0;
                 // since this offset is used to splitting later on - and we can only split entire
                 // characters - we're computing by characters
-                for c in self.content.chars() {
+                for c in self.as_str().chars() {
                     if remaining == 0 {
                         break;
                     }
@@ -1218,7 +1506,7 @@ impl SplittableString {
                 i
             }
             OffsetKind::Utf32 => self
-                .content
+                .as_str()
                 .chars()
                 .take(offset as usize)
                 .fold(0, |sum, c| sum + c.len_utf16() as u32),
@@ -1226,13 +1514,41 @@ impl SplittableString {
     }
 
     pub fn push_str(&mut self, str: &str) {
-        self.content.push_str(str);
+        match &mut self.content {
+            StringContent::Owned(s) => s.push_str(str),
+            StringContent::Shared(s) => {
+                let mut owned = SmallString::from_str(s.as_str());
+                owned.push_str(str);
+                self.content = StringContent::Owned(owned);
+            }
+        }
+    }
+
+    /// Appends `other` string at the end of current one. Strings allocated one after another in
+    /// the same [StringArena] chunk are joined without copying.
+    pub(crate) fn append(&mut self, other: &SplittableString) {
+        if let (StringContent::Shared(left), StringContent::Shared(right)) =
+            (&mut self.content, &other.content)
+        {
+            if left.end == right.start && Arc::ptr_eq(&left.chunk, &right.chunk) {
+                left.end = right.end;
+                return;
+            }
+        }
+        self.push_str(other.as_str())
+    }
+
+    /// Moves current string into a given `arena`, unless it has been already allocated there.
+    pub(crate) fn move_to(&mut self, arena: &mut StringArena) {
+        if let StringContent::Owned(s) = &self.content {
+            *self = arena.alloc(s.as_str());
+        }
     }
 
     fn map_utf16_offset(&self, offset: u32) -> u32 {
         let mut off = 0;
         let mut i = 0;
-        for c in self.content.chars() {
+        for c in self.as_str().chars() {
             if i >= offset {
                 break;
             }
@@ -1245,7 +1561,7 @@ impl SplittableString {
     fn map_unicode_offset(&self, offset: u32) -> u32 {
         let mut off = 0;
         let mut i = 0;
-        for c in self.content.chars() {
+        for c in self.as_str().chars() {
             if i >= offset {
                 break;
             }
@@ -1256,30 +1572,63 @@ impl SplittableString {
     }
 }
 
+impl PartialEq for SplittableString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SplittableString {}
+
+impl PartialOrd for SplittableString {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SplittableString {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl std::fmt::Debug for SplittableString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SplittableString")
+            .field("content", &self.as_str())
+            .finish()
+    }
+}
+
 impl std::fmt::Display for SplittableString {
     #[inline(always)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.content.fmt(f)
+        self.as_str().fmt(f)
     }
 }
 
 impl Into<SmallString<[u8; 8]>> for SplittableString {
     #[inline(always)]
     fn into(self) -> SmallString<[u8; 8]> {
-        self.content
+        match self.content {
+            StringContent::Owned(s) => s,
+            StringContent::Shared(s) => SmallString::from_str(s.as_str()),
+        }
     }
 }
 
 impl Into<Box<str>> for SplittableString {
     #[inline(always)]
     fn into(self) -> Box<str> {
-        self.content.into_string().into_boxed_str()
+        self.as_str().into()
     }
 }
 
 impl From<SmallString<[u8; 8]>> for SplittableString {
     fn from(content: SmallString<[u8; 8]>) -> Self {
-        SplittableString { content }
+        SplittableString {
+            content: StringContent::Owned(content),
+        }
     }
 }
 
@@ -1294,7 +1643,7 @@ impl Deref for SplittableString {
 
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
-        &self.content
+        self.as_str()
     }
 }
 
@@ -1312,7 +1661,8 @@ pub enum ItemContent {
     /// Deleted elements also don't contribute to an overall length of containing collection type.
     Deleted(u32),
 
-    Doc(Box<str>, Box<Any>),
+    /// A subdocument nested within a parent document.
+    Doc(DocRef),
     JSON(Vec<String>), // String is JSON
     Embed(Box<Any>),
 
@@ -1327,9 +1677,58 @@ pub enum ItemContent {
     /// arrays, maps or XML elements.
     Type(Box<Branch>),
     Move(Box<Move>),
+
+    /// A user-defined content, encoded by a [ContentCodec](crate::ContentCodec).
+    Extension(Box<Extension>),
+}
+
+/// Returns an approximate number of bytes allocated on the heap by a given value.
+fn any_heap_size(any: &Any) -> usize {
+    match any {
+        Any::String(s) => s.len(),
+        Any::Buffer(buf) => buf.len(),
+        Any::Array(values) => values
+            .iter()
+            .map(|value| std::mem::size_of::<Any>() + any_heap_size(value))
+            .sum(),
+        Any::Map(entries) => entries
+            .iter()
+            .map(|(key, value)| {
+                std::mem::size_of::<(String, Any)>() + key.capacity() + any_heap_size(value)
+            })
+            .sum(),
+        _ => 0,
+    }
 }
 
 impl ItemContent {
+    /// Returns an approximate number of bytes allocated on the heap by current content.
+    /// Subdocuments are not included, as they're separate documents.
+    pub(crate) fn heap_size(&self) -> usize {
+        match self {
+            ItemContent::Any(values) => {
+                values.capacity() * std::mem::size_of::<Any>()
+                    + values.iter().map(any_heap_size).sum::<usize>()
+            }
+            ItemContent::Binary(buf) => buf.capacity(),
+            ItemContent::Deleted(_) | ItemContent::Doc(_) => 0,
+            ItemContent::JSON(values) => {
+                values.capacity() * std::mem::size_of::<String>()
+                    + values.iter().map(String::capacity).sum::<usize>()
+            }
+            ItemContent::Embed(value) => std::mem::size_of::<Any>() + any_heap_size(value),
+            ItemContent::Format(key, value) => {
+                key.len() + std::mem::size_of::<Any>() + any_heap_size(value)
+            }
+            ItemContent::String(s) => s.heap_size(),
+            ItemContent::Type(_) => std::mem::size_of::<Branch>(),
+            ItemContent::Move(_) => std::mem::size_of::<Move>(),
+            ItemContent::Extension(ext) => {
+                std::mem::size_of::<Extension>() + ext.tag().len() + ext.payload().len()
+            }
+        }
+    }
+
     /// Returns a reference number used to determine a content type.
     /// It's used during encoding/decoding of a containing block.
     pub fn get_ref_number(&self) -> u8 {
@@ -1337,13 +1736,14 @@ impl ItemContent {
             ItemContent::Any(_) => BLOCK_ITEM_ANY_REF_NUMBER,
             ItemContent::Binary(_) => BLOCK_ITEM_BINARY_REF_NUMBER,
             ItemContent::Deleted(_) => BLOCK_ITEM_DELETED_REF_NUMBER,
-            ItemContent::Doc(_, _) => BLOCK_ITEM_DOC_REF_NUMBER,
+            ItemContent::Doc(_) => BLOCK_ITEM_DOC_REF_NUMBER,
             ItemContent::JSON(_) => BLOCK_ITEM_JSON_REF_NUMBER,
             ItemContent::Embed(_) => BLOCK_ITEM_EMBED_REF_NUMBER,
             ItemContent::Format(_, _) => BLOCK_ITEM_FORMAT_REF_NUMBER,
             ItemContent::String(_) => BLOCK_ITEM_STRING_REF_NUMBER,
             ItemContent::Type(_) => BLOCK_ITEM_TYPE_REF_NUMBER,
             ItemContent::Move(_) => BLOCK_ITEM_MOVE_REF_NUMBER,
+            ItemContent::Extension(_) => BLOCK_ITEM_EXTENSION_REF_NUMBER,
         }
     }
 
@@ -1355,11 +1755,12 @@ impl ItemContent {
         match self {
             ItemContent::Any(_) => true,
             ItemContent::Binary(_) => true,
-            ItemContent::Doc(_, _) => true,
+            ItemContent::Doc(_) => true,
             ItemContent::JSON(_) => true,
             ItemContent::Embed(_) => true,
             ItemContent::String(_) => true,
             ItemContent::Type(_) => true,
+            ItemContent::Extension(_) => true,
             ItemContent::Deleted(_) => false,
             ItemContent::Format(_, _) => false,
             ItemContent::Move(_) => false,
@@ -1397,7 +1798,7 @@ impl ItemContent {
             ItemContent::Binary(v) => vec![Value::Any(Any::Buffer(v.clone().into_boxed_slice()))],
             ItemContent::Move(_) => Vec::default(),
             ItemContent::Deleted(_) => Vec::default(),
-            ItemContent::Doc(_, v) => vec![Value::Any(*v.clone())],
+            ItemContent::Doc(doc) => vec![Value::YDoc(doc.clone())],
             ItemContent::JSON(v) => v
                 .iter()
                 .map(|v| Value::Any(Any::String(v.clone().into_boxed_str())))
@@ -1412,6 +1813,24 @@ impl ItemContent {
                 let branch_ref = BranchPtr::from(c);
                 vec![branch_ref.into()]
             }
+            ItemContent::Extension(e) => vec![Value::Extension(e.clone())],
+        }
+    }
+
+    /// Similar to [get_content], but it only returns a single element at a given `index` and
+    /// doesn't materialize others for performance reasons. For string content `index` is
+    /// expressed using a given `encoding` and must point at the beginning of a character.
+    pub fn get_at(&self, index: usize, encoding: OffsetKind) -> Option<Value> {
+        match self {
+            ItemContent::Any(v) => v.get(index).map(|a| Value::Any(a.clone())),
+            ItemContent::JSON(v) => v
+                .get(index)
+                .map(|v| Value::Any(Any::String(v.clone().into_boxed_str()))),
+            ItemContent::String(v) => v
+                .char_at(index, encoding)
+                .map(|c| Value::Any(Any::String(c.to_string().into_boxed_str()))),
+            other if index == 0 => other.get_first(),
+            _ => None,
         }
     }
 
@@ -1423,7 +1842,7 @@ impl ItemContent {
             ItemContent::Binary(v) => Some(Value::Any(Any::Buffer(v.clone().into_boxed_slice()))),
             ItemContent::Deleted(_) => None,
             ItemContent::Move(_) => None,
-            ItemContent::Doc(_, v) => Some(Value::Any(*v.clone())),
+            ItemContent::Doc(doc) => Some(Value::YDoc(doc.clone())),
             ItemContent::JSON(v) => v
                 .last()
                 .map(|v| Value::Any(Any::String(v.clone().into_boxed_str()))),
@@ -1431,6 +1850,7 @@ impl ItemContent {
             ItemContent::Format(_, _) => None,
             ItemContent::String(v) => Some(Value::Any(Any::String(v.clone().into()))),
             ItemContent::Type(c) => Some(BranchPtr::from(c).into()),
+            ItemContent::Extension(e) => Some(Value::Extension(e.clone())),
         }
     }
 
@@ -1442,7 +1862,7 @@ impl ItemContent {
             ItemContent::Binary(v) => Some(Value::Any(Any::Buffer(v.clone().into_boxed_slice()))),
             ItemContent::Deleted(_) => None,
             ItemContent::Move(_) => None,
-            ItemContent::Doc(_, v) => Some(Value::Any(*v.clone())),
+            ItemContent::Doc(doc) => Some(Value::YDoc(doc.clone())),
             ItemContent::JSON(v) => v
                 .first()
                 .map(|v| Value::Any(Any::String(v.clone().into_boxed_str()))),
@@ -1450,6 +1870,7 @@ impl ItemContent {
             ItemContent::Format(_, _) => None,
             ItemContent::String(v) => Some(Value::Any(Any::String(v.clone().into()))),
             ItemContent::Type(c) => Some(BranchPtr::from(c).into()),
+            ItemContent::Extension(e) => Some(Value::Extension(e.clone())),
         }
     }
 
@@ -1478,6 +1899,8 @@ impl ItemContent {
                 if type_ref == types::TYPE_REFS_XML_ELEMENT || type_ref == types::TYPE_REFS_XML_HOOK
                 {
                     encoder.write_key(inner.name.as_ref().unwrap().as_ref())
+                } else if let Some(link) = inner.link.as_ref() {
+                    link.encode(encoder)
                 }
             }
             ItemContent::Any(any) => {
@@ -1486,11 +1909,15 @@ impl ItemContent {
                     encoder.write_any(&any[i]);
                 }
             }
-            ItemContent::Doc(key, any) => {
-                encoder.write_string(key.as_ref());
-                encoder.write_any(any);
+            ItemContent::Doc(doc) => {
+                encoder.write_string(doc.guid().as_ref());
+                encoder.write_any(&doc.encode_options());
             }
             ItemContent::Move(m) => m.encode(encoder),
+            ItemContent::Extension(e) => {
+                encoder.write_key(e.tag());
+                encoder.write_buf(e.payload());
+            }
         }
     }
 
@@ -1519,6 +1946,8 @@ impl ItemContent {
                 if type_ref == types::TYPE_REFS_XML_ELEMENT || type_ref == types::TYPE_REFS_XML_HOOK
                 {
                     encoder.write_key(inner.name.as_ref().unwrap().as_ref())
+                } else if let Some(link) = inner.link.as_ref() {
+                    link.encode(encoder)
                 }
             }
             ItemContent::Any(any) => {
@@ -1527,11 +1956,15 @@ impl ItemContent {
                     encoder.write_any(&any[i]);
                 }
             }
-            ItemContent::Doc(key, any) => {
-                encoder.write_string(key.as_ref());
-                encoder.write_any(any);
+            ItemContent::Doc(doc) => {
+                encoder.write_string(doc.guid().as_ref());
+                encoder.write_any(&doc.encode_options());
             }
             ItemContent::Move(m) => m.encode(encoder),
+            ItemContent::Extension(e) => {
+                encoder.write_key(e.tag());
+                encoder.write_buf(e.payload());
+            }
         }
     }
 
@@ -1557,6 +1990,8 @@ impl ItemContent {
                 if type_ref == types::TYPE_REFS_XML_ELEMENT || type_ref == types::TYPE_REFS_XML_HOOK
                 {
                     encoder.write_key(inner.name.as_ref().unwrap().as_ref())
+                } else if let Some(link) = inner.link.as_ref() {
+                    link.encode(encoder)
                 }
             }
             ItemContent::Any(any) => {
@@ -1565,11 +2000,15 @@ impl ItemContent {
                     encoder.write_any(a);
                 }
             }
-            ItemContent::Doc(key, any) => {
-                encoder.write_string(key.as_ref());
-                encoder.write_any(any);
+            ItemContent::Doc(doc) => {
+                encoder.write_string(doc.guid().as_ref());
+                encoder.write_any(&doc.encode_options());
             }
             ItemContent::Move(m) => m.encode(encoder),
+            ItemContent::Extension(e) => {
+                encoder.write_key(e.tag());
+                encoder.write_buf(e.payload());
+            }
         }
     }
 
@@ -1578,7 +2017,7 @@ impl ItemContent {
             BLOCK_ITEM_DELETED_REF_NUMBER => Ok(ItemContent::Deleted(decoder.read_len()?)),
             BLOCK_ITEM_JSON_REF_NUMBER => {
                 let mut remaining = decoder.read_len()? as i32;
-                let mut buf = Vec::with_capacity(remaining as usize);
+                let mut buf = Vec::with_capacity((remaining as usize).min(MAX_PREALLOC));
                 while remaining >= 0 {
                     buf.push(decoder.read_string()?.to_owned());
                     remaining -= 1;
@@ -1594,6 +2033,13 @@ impl ItemContent {
             )),
             BLOCK_ITEM_TYPE_REF_NUMBER => {
                 let type_ref = decoder.read_type_ref()?;
+                if type_ref > TYPE_REFS_WEAK && type_ref != TYPE_REFS_UNDEFINED {
+                    return Err(Error::InvalidTypeRef(type_ref));
+                }
+                if type_ref == TYPE_REFS_WEAK {
+                    let source = LinkSource::decode(decoder)?;
+                    return Ok(ItemContent::Type(source.into_branch()));
+                }
                 let name = if type_ref == TYPE_REFS_XML_ELEMENT || type_ref == TYPE_REFS_XML_HOOK {
                     Some(decoder.read_key()?.to_owned())
                 } else {
@@ -1604,7 +2050,7 @@ impl ItemContent {
             }
             BLOCK_ITEM_ANY_REF_NUMBER => {
                 let len = decoder.read_len()? as usize;
-                let mut values = Vec::with_capacity(len);
+                let mut values = Vec::with_capacity(len.min(MAX_PREALLOC));
                 let mut i = 0;
                 while i < len {
                     values.push(decoder.read_any()?);
@@ -1616,14 +2062,50 @@ impl ItemContent {
                 let m = Move::decode(decoder)?;
                 Ok(ItemContent::Move(Box::new(m)))
             }
-            BLOCK_ITEM_DOC_REF_NUMBER => Ok(ItemContent::Doc(
-                decoder.read_string()?.into(),
-                Box::new(decoder.read_any()?),
-            )),
+            BLOCK_ITEM_EXTENSION_REF_NUMBER => {
+                let tag = decoder.read_key()?;
+                let payload = decoder.read_buf()?;
+                let extension = Extension::new(tag, payload);
+                Ok(ItemContent::Extension(Box::new(extension)))
+            }
+            BLOCK_ITEM_DOC_REF_NUMBER => {
+                let guid: Rc<str> = decoder.read_string()?.into();
+                let opts = decoder.read_any()?;
+                let doc = Doc::decode_options(guid, &opts);
+                Ok(ItemContent::Doc(doc.into()))
+            }
             _ => Err(Error::UnexpectedValue),
         }
     }
 
+    /// Creates a copy of current content, which can be used as a content of a new item. Nested
+    /// shared types are copied as empty types of the same kind.
+    pub(crate) fn copy(&self) -> ItemContent {
+        match self {
+            ItemContent::Any(v) => ItemContent::Any(v.clone()),
+            ItemContent::Binary(v) => ItemContent::Binary(v.clone()),
+            ItemContent::Deleted(len) => ItemContent::Deleted(*len),
+            ItemContent::Doc(doc) => {
+                let doc = Doc::decode_options(doc.guid(), &doc.encode_options());
+                ItemContent::Doc(doc.into())
+            }
+            ItemContent::JSON(v) => ItemContent::JSON(v.clone()),
+            ItemContent::Embed(v) => ItemContent::Embed(v.clone()),
+            ItemContent::Format(key, value) => ItemContent::Format(key.clone(), value.clone()),
+            ItemContent::String(v) => ItemContent::String(v.clone()),
+            ItemContent::Type(branch) => {
+                let mut copy = Branch::new(branch.type_ref(), branch.name.clone());
+                copy.link = branch.link;
+                ItemContent::Type(copy)
+            }
+            ItemContent::Move(m) => {
+                let copy = Move::new(m.start.clone(), m.end.clone(), m.priority);
+                ItemContent::Move(Box::new(copy))
+            }
+            ItemContent::Extension(e) => ItemContent::Extension(e.clone()),
+        }
+    }
+
     pub(crate) fn splice(&mut self, offset: usize, encoding: OffsetKind) -> Option<ItemContent> {
         match self {
             ItemContent::Any(value) => {
@@ -1635,9 +2117,7 @@ impl ItemContent {
             }
             ItemContent::String(string) => {
                 // compute offset given in unicode code points into byte position
-                let (left, right) = string.split_at(offset, encoding);
-                let left: SplittableString = left.into();
-                let right: SplittableString = right.into();
+                let right = string.split_off(offset, encoding);
 
                 //TODO: do we need that in Rust?
                 //let split_point = left.chars().last().unwrap();
@@ -1648,8 +2128,6 @@ impl ItemContent {
                 //    left.replace_range((offset-1)..offset, "�");
                 //    right.replace_range(0..1, "�");
                 //}
-                *self = ItemContent::String(left);
-
                 Some(ItemContent::String(right))
             }
             ItemContent::Deleted(len) => {
@@ -1685,7 +2163,7 @@ impl ItemContent {
                 true
             }
             (ItemContent::String(v1), ItemContent::String(v2)) => {
-                v1.push_str(v2.as_str());
+                v1.append(v2);
                 true
             }
             _ => false,
@@ -1695,6 +2173,7 @@ impl ItemContent {
     pub(crate) fn gc(&mut self) {
         match self {
             ItemContent::Type(branch) => {
+                branch.search_markers.take();
                 let mut curr = branch.start.take();
                 while let Some(mut ptr) = curr {
                     if let Block::Item(item) = ptr.deref_mut() {
@@ -1717,32 +2196,6 @@ impl ItemContent {
                     }
                 }
             }
-            ItemContent::Doc(_, _) => {
-                /*
-                if (doc._item) {
-                  console.error('This document was already integrated as a sub-document. You should create a second instance instead with the same guid.')
-                }
-                /**
-                 * @type {Doc}
-                 */
-                this.doc = doc
-                /**
-                 * @type {any}
-                 */
-                const opts = {}
-                this.opts = opts
-                if (!doc.gc) {
-                  opts.gc = false
-                }
-                if (doc.autoLoad) {
-                  opts.autoLoad = true
-                }
-                if (doc.meta !== null) {
-                  opts.meta = doc.meta
-                }
-                 */
-                todo!()
-            }
             _ => {}
         }
     }
@@ -1859,9 +2312,13 @@ impl std::fmt::Display for ItemContent {
                 TYPE_REFS_XML_FRAGMENT => write!(f, "<xml fragment>"),
                 TYPE_REFS_XML_HOOK => write!(f, "<xml hook>"),
                 TYPE_REFS_XML_TEXT => write!(f, "<xml text>"),
+                TYPE_REFS_WEAK => write!(f, "<weak link>"),
+                TYPE_REFS_COUNTER => write!(f, "<counter>"),
                 _ => write!(f, "<undefined type ref>"),
             },
             ItemContent::Move(m) => std::fmt::Display::fmt(m.as_ref(), f),
+            ItemContent::Doc(doc) => write!(f, "<doc: {}>", doc.guid()),
+            ItemContent::Extension(e) => write!(f, "<extension: {}>", e),
             _ => Ok(()),
         }
     }
@@ -1912,25 +2369,51 @@ where
 #[derive(Debug)]
 pub(crate) struct PrelimString(pub SmallString<[u8; 8]>);
 
-impl Prelim for PrelimString {
+impl Prelim for Doc {
     fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
-        (ItemContent::String(self.0.into()), None)
+        (ItemContent::Doc(self.into()), None)
     }
 
     fn integrate(self, _txn: &mut Transaction, _inner_ref: BranchPtr) {}
 }
 
-#[derive(Debug)]
-pub(crate) struct PrelimEmbed(pub Any);
+impl Prelim for DocRef {
+    fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
+        (ItemContent::Doc(self), None)
+    }
 
-impl Prelim for PrelimEmbed {
+    fn integrate(self, _txn: &mut Transaction, _inner_ref: BranchPtr) {}
+}
+
+impl Prelim for PrelimString {
     fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
-        (ItemContent::Embed(Box::new(self.0)), None)
+        (ItemContent::String(self.0.into()), None)
     }
 
     fn integrate(self, _txn: &mut Transaction, _inner_ref: BranchPtr) {}
 }
 
+/// Wrapper around preliminary values used as embeds within a text. Primitive values are stored
+/// as [ItemContent::Embed], while shared types are stored as they are.
+#[derive(Debug)]
+pub(crate) struct PrelimEmbed<V>(pub V);
+
+impl<V: Prelim> Prelim for PrelimEmbed<V> {
+    fn into_content(self, txn: &mut Transaction) -> (ItemContent, Option<Self>) {
+        match self.0.into_content(txn) {
+            (ItemContent::Any(mut values), None) if values.len() == 1 => {
+                let value = values.pop().unwrap();
+                (ItemContent::Embed(Box::new(value)), None)
+            }
+            (content, remainder) => (content, remainder.map(PrelimEmbed)),
+        }
+    }
+
+    fn integrate(self, txn: &mut Transaction, inner_ref: BranchPtr) {
+        self.0.integrate(txn, inner_ref)
+    }
+}
+
 impl std::fmt::Display for ID {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "<{}#{}>", self.client, self.clock)
@@ -1967,8 +2450,9 @@ impl std::fmt::Debug for Block {
 
 #[cfg(test)]
 mod test {
-    use crate::block::SplittableString;
+    use crate::block::{Block, ItemContent, SplittableString, StringArena, StringContent};
     use crate::doc::OffsetKind;
+    use crate::Doc;
     use std::ops::Deref;
 
     #[test]
@@ -2011,4 +2495,76 @@ mod test {
         assert_eq!(a, "Zażółć gęślą jaźń😀");
         assert_eq!(b, "ありがとうございます");
     }
+
+    #[test]
+    fn item_content_get_at() {
+        let content = ItemContent::String("ż😀a".into());
+
+        assert_eq!(content.get_at(2, OffsetKind::Bytes), Some("😀".into()));
+        assert_eq!(content.get_at(6, OffsetKind::Bytes), Some("a".into()));
+        assert_eq!(content.get_at(1, OffsetKind::Bytes), None);
+        assert_eq!(content.get_at(1, OffsetKind::Utf16), Some("😀".into()));
+        assert_eq!(content.get_at(3, OffsetKind::Utf16), Some("a".into()));
+        assert_eq!(content.get_at(2, OffsetKind::Utf16), None);
+        assert_eq!(content.get_at(2, OffsetKind::Utf32), Some("a".into()));
+        assert_eq!(content.get_at(3, OffsetKind::Utf32), None);
+    }
+
+    #[test]
+    fn string_arena() {
+        let mut arena = StringArena::default();
+        let mut hello = arena.alloc("hello");
+        let world = arena.alloc(" wórld");
+        let big = arena.alloc(&"a".repeat(10_000));
+        let exclamation = arena.alloc("!");
+
+        // strings allocated one after another are joined without copying
+        hello.append(&world);
+        assert!(matches!(hello.content, StringContent::Shared(_)));
+        assert_eq!(hello.as_str(), "hello wórld");
+
+        // large strings get their own chunk, so they don't interrupt other strings...
+        hello.append(&exclamation);
+        assert!(matches!(hello.content, StringContent::Shared(_)));
+        assert_eq!(hello.as_str(), "hello wórld!");
+
+        // ... but strings from different chunks need to be copied
+        hello.append(&big);
+        assert!(matches!(hello.content, StringContent::Owned(_)));
+        assert_eq!(hello.len(OffsetKind::Bytes), 10_013);
+
+        // splitting doesn't copy either
+        let mut left = world.clone();
+        let right = left.split_off(3, OffsetKind::Utf16);
+        assert!(matches!(right.content, StringContent::Shared(_)));
+        assert_eq!(left.as_str(), " wó");
+        assert_eq!(right.as_str(), "rld");
+
+        // owned strings are moved into the arena once
+        let mut owned = SplittableString::from("żółw");
+        owned.move_to(&mut arena);
+        assert!(matches!(owned.content, StringContent::Shared(_)));
+        assert_eq!(owned, SplittableString::from("żółw"));
+    }
+
+    #[test]
+    fn integrated_text_in_string_arena() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.transact().get_text("text");
+        for c in "hello world".chars() {
+            let len = text.len();
+            text.insert(&mut doc.transact(), len, &c.to_string());
+        }
+        text.insert(&mut doc.transact(), 5, ",");
+        assert_eq!(text.to_string(), "hello, world");
+
+        let blocks = doc.store.blocks.get(&1).unwrap();
+        for block in blocks.iter() {
+            if let Block::Item(item) = block {
+                if let ItemContent::String(s) = &item.content {
+                    assert!(matches!(s.content, StringContent::Shared(_)), "{}", s);
+                }
+            }
+        }
+    }
 }
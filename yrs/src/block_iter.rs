@@ -1,8 +1,7 @@
-use crate::block::{Block, BlockPtr, Item, ItemContent, Prelim};
+use crate::block::{Block, BlockPtr, Item, ItemContent, Prelim, SplittableString};
 use crate::moving::{Move, RelativePosition};
-use crate::types::array::ArraySliceConcat;
 use crate::types::{BranchPtr, TypePtr, Value};
-use crate::{Transaction, ID};
+use crate::{OffsetKind, Transaction, ID};
 use std::ops::DerefMut;
 
 #[derive(Debug, Clone)]
@@ -35,6 +34,11 @@ impl BlockIter {
         }
     }
 
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
     #[inline]
     pub fn rel(&self) -> u32 {
         self.rel
@@ -68,13 +72,16 @@ impl BlockIter {
         }
     }
 
-    pub fn move_to(&mut self, index: u32, txn: &mut Transaction) {
+    /// Moves current iterator to a given `index`, going either forward or backward depending on
+    /// the current iterator position. Returns an [OutOfBounds] error if `index` is outside of the
+    /// range of an underlying branch.
+    pub fn try_move_to(&mut self, txn: &mut Transaction, index: u32) -> Result<(), OutOfBounds> {
         if index > self.index {
-            if !self.try_forward(txn, index - self.index) {
-                panic!("Block iter couldn't move forward");
-            }
+            self.try_forward(txn, index - self.index)
         } else if index < self.index {
-            self.backward(txn, self.index - index)
+            self.try_backward(txn, self.index - index)
+        } else {
+            Ok(())
         }
     }
 
@@ -94,19 +101,23 @@ impl BlockIter {
         false
     }
 
-    pub fn forward(&mut self, txn: &mut Transaction, len: u32) {
-        if !self.try_forward(txn, len) {
-            panic!("Length exceeded")
+    /// Checks if there are at least `len` elements left after the current iterator position.
+    fn fits(&self, len: u32) -> bool {
+        match self.index.checked_add(len) {
+            Some(end) => end <= self.branch.content_len(),
+            None => false,
         }
     }
 
-    pub fn try_forward(&mut self, txn: &mut Transaction, mut len: u32) -> bool {
+    /// Moves current iterator forward by `len` elements. Returns an [OutOfBounds] error if there
+    /// are not enough elements left to move over.
+    pub fn try_forward(&mut self, txn: &mut Transaction, mut len: u32) -> Result<(), OutOfBounds> {
         if len == 0 && self.next_item.is_none() {
-            return true;
+            return Ok(());
         }
 
-        if self.index + len > self.branch.content_len() || self.next_item.is_none() {
-            return false;
+        if !self.fits(len) || self.next_item.is_none() {
+            return Err(OutOfBounds);
         }
 
         let mut item = self.next_item;
@@ -124,7 +135,7 @@ impl BlockIter {
                 item = self.curr_move; // we iterate to the right after the current condition
                 self.pop(txn);
             } else if item.is_none() {
-                return false;
+                return Err(OutOfBounds);
             } else if let Some(Block::Item(i)) = item.as_deref() {
                 if i.is_countable() && !i.is_deleted() && i.moved == self.curr_move && len > 0 {
                     let item_len = i.content_len(encoding);
@@ -156,7 +167,7 @@ impl BlockIter {
             }
 
             if self.reached_end {
-                return false;
+                return Err(OutOfBounds);
             }
 
             match item.as_deref() {
@@ -167,7 +178,7 @@ impl BlockIter {
 
         self.index -= len;
         self.next_item = item;
-        true
+        Ok(())
     }
 
     fn reduce_moves(&mut self, txn: &mut Transaction) {
@@ -181,9 +192,11 @@ impl BlockIter {
         }
     }
 
-    pub fn backward(&mut self, txn: &mut Transaction, mut len: u32) {
+    /// Moves current iterator backward by `len` elements. Returns an [OutOfBounds] error if
+    /// current iterator position is lower than `len`.
+    pub fn try_backward(&mut self, txn: &mut Transaction, mut len: u32) -> Result<(), OutOfBounds> {
         if self.index < len {
-            panic!("Length exceeded");
+            return Err(OutOfBounds);
         }
         self.index -= len;
         let encoding = txn.store().options.offset_kind;
@@ -195,10 +208,11 @@ impl BlockIter {
                     0
                 };
             }
+            self.reached_end = false;
         }
         if self.rel >= len {
             self.rel -= len;
-            return;
+            return Ok(());
         }
         let mut item = self.next_item;
         if let Some(Block::Item(i)) = item.as_deref() {
@@ -260,6 +274,7 @@ impl BlockIter {
             };
         }
         self.next_item = item;
+        Ok(())
     }
 
     /// We keep the moved-stack across several transactions. Local or remote changes can invalidate
@@ -280,7 +295,9 @@ impl BlockIter {
 
             let moved_item = stack_item.moved_to.as_item().unwrap();
             if let ItemContent::Move(m) = &moved_item.content {
-                if m.start.assoc && (m.start.within_range(start)) || (m.end.within_range(end)) {
+                if m.start.assoc && (m.start.within_range(start))
+                    || m.end.as_ref().map(|e| e.within_range(end)).unwrap_or(false)
+                {
                     let (s, e) = m.get_moved_coords(txn);
                     start = s;
                     end = e;
@@ -293,10 +310,40 @@ impl BlockIter {
         self.reached_end = false;
     }
 
-    pub fn delete(&mut self, txn: &mut Transaction, mut len: u32) {
+    /// Deletes `len` elements, starting from the current iterator position. Returns an
+    /// [OutOfBounds] error if there are not enough elements left to delete. This is checked
+    /// against the length of an iterated collection before any element is removed.
+    pub fn try_delete(&mut self, txn: &mut Transaction, len: u32) -> Result<(), OutOfBounds> {
+        self.delete_with(txn, len, |_| {})
+    }
+
+    /// Deletes `len` elements, starting from the current iterator position, and returns them.
+    /// Returns an [OutOfBounds] error if there are not enough elements left to delete. This is
+    /// checked against the length of an iterated collection before any element is removed.
+    pub fn try_drain(
+        &mut self,
+        txn: &mut Transaction,
+        len: u32,
+    ) -> Result<Vec<Value>, OutOfBounds> {
+        let mut removed = Vec::with_capacity(len as usize);
+        self.delete_with(txn, len, |item| {
+            removed.append(&mut item.content.get_content())
+        })?;
+        Ok(removed)
+    }
+
+    fn delete_with<F>(
+        &mut self,
+        txn: &mut Transaction,
+        mut len: u32,
+        mut f: F,
+    ) -> Result<(), OutOfBounds>
+    where
+        F: FnMut(&Item),
+    {
         let mut item = self.next_item;
-        if self.index + len > self.branch.content_len() {
-            panic!("Length exceeded");
+        if !self.fits(len) {
+            return Err(OutOfBounds);
         }
 
         let encoding = txn.store().options.offset_kind;
@@ -313,7 +360,7 @@ impl BlockIter {
                 {
                     if self.rel > 0 {
                         let mut id = i.id.clone();
-                        id.clock += self.rel;
+                        id.clock += block_offset(i, self.rel, encoding);
                         item = txn.store_mut().blocks.get_item_clean_start(&id);
                         i = if let Some(Block::Item(block)) = item.as_deref() {
                             block
@@ -324,10 +371,11 @@ impl BlockIter {
                     }
                     if len < i.content_len(encoding) {
                         let mut id = i.id.clone();
-                        id.clock += len;
+                        id.clock += block_offset(i, len, encoding);
                         txn.store_mut().blocks.get_item_clean_start(&id);
                     }
                     len -= i.content_len(encoding);
+                    f(i);
                     txn.delete(item.unwrap());
                     if i.right.is_some() {
                         item = i.right;
@@ -340,32 +388,137 @@ impl BlockIter {
             }
             if len > 0 {
                 self.next_item = item;
-                if self.try_forward(txn, 0) {
-                    item = self.next_item;
-                } else {
-                    panic!("Block iter couldn't move forward");
-                }
+                self.try_forward(txn, 0)?;
+                item = self.next_item;
             }
         }
         self.next_item = item;
+        Ok(())
     }
 
     pub(crate) fn slice<T>(
         &mut self,
         txn: &mut Transaction,
-        mut len: u32,
+        len: u32,
         mut value: Vec<Value>,
-    ) -> Option<Vec<Value>>
+    ) -> Result<Vec<Value>, OutOfBounds>
     where
         T: SliceConcat,
     {
-        if self.index + len > self.branch.content_len() {
-            return None;
+        self.read_with(txn, len, |item, offset, len| {
+            let sliced_content = T::slice(&mut item.content, offset as usize, len as usize);
+            let sliced_content_len = sliced_content.len() as u32;
+            value = T::concat(std::mem::take(&mut value), sliced_content);
+            sliced_content_len
+        })?;
+        Ok(value)
+    }
+
+    /// Reads a single element at the current iterator position together with its unique [ID] and
+    /// moves the iterator forward by one. Unlike [BlockIter::slice], this method doesn't materialize
+    /// remaining contents of the block it reads from.
+    ///
+    /// When reading from a string, an element is a single character and the iterator moves over
+    /// its entire length, which - depending on the [OffsetKind] configured for a document - may
+    /// be more than one. Returns `None` if the iterator points into the middle of a character.
+    pub(crate) fn read_entry(&mut self, txn: &mut Transaction) -> Option<(ID, Value)> {
+        let encoding = txn.store().options.offset_kind;
+        let mut entry = None;
+        let mut entry_len = 1;
+        let saved = self.clone();
+        self.read_with(txn, 1, |item, offset, _| {
+            if let Some(value) = item.content.get_at(offset as usize, encoding) {
+                if let ItemContent::String(s) = &item.content {
+                    let c = match s.char_at(offset as usize, encoding) {
+                        Some(c) => c,
+                        None => return 0,
+                    };
+                    entry_len = match encoding {
+                        OffsetKind::Bytes => c.len_utf8() as u32,
+                        OffsetKind::Utf16 => c.len_utf16() as u32,
+                        OffsetKind::Utf32 => 1,
+                    };
+                }
+                let mut id = item.id;
+                id.clock += block_offset(item, offset, encoding);
+                entry = Some((id, value));
+                1
+            } else {
+                0
+            }
+        })
+        .ok()?;
+        if entry_len > 1 {
+            // we've read only the first unit of a multi-unit character, skip over the rest of it
+            if self.try_forward(txn, entry_len - 1).is_err() {
+                *self = saved;
+                return None;
+            }
+        }
+        entry
+    }
+
+    /// Reads `len` elements starting from the current iterator position into a string, moving
+    /// the iterator forward. Lengths are measured using [OffsetKind] configured for a document.
+    /// Non-string elements (eg. embeds) are skipped over, but count towards `len`.
+    pub(crate) fn read_str(
+        &mut self,
+        txn: &mut Transaction,
+        len: u32,
+    ) -> Result<String, OutOfBounds> {
+        let encoding = txn.store().options.offset_kind;
+        let mut buf = String::new();
+        self.read_with(txn, len, |item, offset, len| {
+            let read_len = len.min(item.content_len(encoding) - offset);
+            if let ItemContent::String(s) = &item.content {
+                let (_, tail) = s.split_at(offset as usize, encoding);
+                let tail = SplittableString::from(tail);
+                let (chunk, _) = tail.split_at(read_len as usize, encoding);
+                buf.push_str(chunk);
+            }
+            read_len
+        })?;
+        Ok(buf)
+    }
+
+    /// Walks over `len` countable elements starting from the current iterator position, passing
+    /// every visited item to a `reader` together with an offset within that item and the maximum
+    /// number of elements to read from it. A `reader` must return the number of elements it
+    /// actually consumed. If not all of the elements could be read, the iterator is moved back to
+    /// its original position.
+    fn read_with<F>(
+        &mut self,
+        txn: &mut Transaction,
+        len: u32,
+        reader: F,
+    ) -> Result<(), OutOfBounds>
+    where
+        F: FnMut(&mut Item, u32, u32) -> u32,
+    {
+        if !self.fits(len) {
+            return Err(OutOfBounds);
         }
-        self.index += len;
+        let saved = self.clone();
+        let result = self.read_with_unchecked(txn, len, reader);
+        if result.is_err() {
+            *self = saved;
+        }
+        result
+    }
+
+    fn read_with_unchecked<F>(
+        &mut self,
+        txn: &mut Transaction,
+        mut len: u32,
+        mut reader: F,
+    ) -> Result<(), OutOfBounds>
+    where
+        F: FnMut(&mut Item, u32, u32) -> u32,
+    {
+        let end_index = self.index + len;
         let mut next_item = self.next_item;
         let encoding = txn.store().options.offset_kind;
-        while len > 0 && !self.reached_end {
+        while len > 0 && (!self.reached_end || self.curr_move.is_some()) {
             while let Some(mut ptr) = next_item {
                 if Some(ptr) != self.curr_move_end
                     && ptr.is_countable()
@@ -374,15 +527,17 @@ impl BlockIter {
                 {
                     if let Block::Item(item) = ptr.deref_mut() {
                         if !item.is_deleted() && item.moved == self.curr_move {
-                            let sliced_content =
-                                T::slice(&mut item.content, self.rel as usize, len as usize);
-                            let sliced_content_len = sliced_content.len() as u32;
-                            len -= sliced_content_len;
-                            value = T::concat(value, sliced_content);
-                            if self.rel + sliced_content_len == item.content_len(encoding) {
+                            let read_len = reader(item, self.rel, len);
+                            if read_len == 0 {
+                                // reader was unable to make any progress
+                                self.next_item = next_item;
+                                return Err(OutOfBounds);
+                            }
+                            len -= read_len;
+                            if self.rel + read_len == item.content_len(encoding) {
                                 self.rel = 0;
                             } else {
-                                self.rel += sliced_content_len;
+                                self.rel += read_len;
                                 continue; // do not iterate to item.right
                             }
                         }
@@ -403,24 +558,24 @@ impl BlockIter {
             if (!self.reached_end || self.curr_move.is_some()) && len > 0 {
                 // always set nextItem before any method call
                 self.next_item = next_item;
-                if !self.try_forward(txn, 0) || self.next_item.is_none() {
-                    return None;
+                self.try_forward(txn, 0)?;
+                if self.next_item.is_none() {
+                    return Err(OutOfBounds);
                 }
                 next_item = self.next_item;
             }
         }
         self.next_item = next_item;
-        if len < 0 {
-            self.index -= len;
-        }
-        Some(value)
+        self.index = end_index;
+        Ok(())
     }
 
     fn split_rel(&mut self, txn: &mut Transaction) {
         if self.rel > 0 {
-            if let Some(ptr) = self.next_item {
-                let mut item_id = ptr.id().clone();
-                item_id.clock += self.rel;
+            if let Some(Block::Item(item)) = self.next_item.as_deref() {
+                let encoding = txn.store().options.offset_kind;
+                let mut item_id = item.id;
+                item_id.clock += block_offset(item, self.rel, encoding);
                 self.next_item = txn.store_mut().blocks.get_item_clean_start(&item_id);
                 self.rel = 0;
             }
@@ -428,8 +583,8 @@ impl BlockIter {
     }
 
     pub(crate) fn read_value(&mut self, txn: &mut Transaction) -> Option<Value> {
-        let mut res = self.slice::<ArraySliceConcat>(txn, 1, Vec::default())?;
-        res.pop()
+        let (_, value) = self.read_entry(txn)?;
+        Some(value)
     }
 
     pub fn insert_contents<V: Prelim>(&mut self, txn: &mut Transaction, value: V) {
@@ -450,7 +605,7 @@ impl BlockIter {
         } else {
             None
         };
-        let mut block = Item::new(
+        let block = Item::new(
             id,
             left,
             left.map(|ptr| ptr.last_id()),
@@ -460,21 +615,23 @@ impl BlockIter {
             None,
             content,
         );
-        let mut block_ptr = BlockPtr::from(&mut block);
-
-        block_ptr.integrate(txn, 0);
-
-        let local_block_list = txn.store_mut().blocks.get_client_blocks_mut(id.client);
-        local_block_list.push(block);
+        let (block_ptr, _) = txn.integrate_new_block(block, 0);
 
         if let Some(remainder) = remainder {
             remainder.integrate(txn, inner_ref.unwrap().into())
         }
 
-        if let Some(Block::Item(item)) = right.as_deref() {
-            self.next_item = item.right;
+        // move iterator right after the inserted block
+        if let Some(item) = block_ptr.as_item() {
+            if item.is_countable() && !item.is_deleted() {
+                self.index += item.content_len(txn.store().options.offset_kind);
+            }
+        }
+        if right.is_some() {
+            self.next_item = right;
+            self.reached_end = false;
         } else {
-            self.next_item = left;
+            self.next_item = Some(block_ptr);
             self.reached_end = true;
         }
     }
@@ -483,39 +640,61 @@ impl BlockIter {
         &mut self,
         txn: &mut Transaction,
         start: RelativePosition,
-        end: RelativePosition,
+        end: Option<RelativePosition>,
     ) {
         self.insert_contents(txn, Move::new(start, end, -1));
     }
 
+    /// Returns an iterator over the elements following current iterator position. Elements are
+    /// read lazily one by one, together with their unique block [ID]s.
+    pub fn entries<'a, 'txn>(&'a mut self, txn: &'txn mut Transaction) -> Entries<'a, 'txn> {
+        Entries::new(self, txn)
+    }
+
+    /// Returns an iterator over the elements following current iterator position. Elements are
+    /// read lazily one by one.
     pub fn values<'a, 'txn>(&'a mut self, txn: &'txn mut Transaction) -> Values<'a, 'txn> {
-        Values::new(self, txn)
+        Values(self.entries(txn))
     }
 }
 
-pub struct Values<'a, 'txn> {
+pub struct Entries<'a, 'txn> {
     iter: &'a mut BlockIter,
     txn: &'txn mut Transaction,
 }
 
-impl<'a, 'txn> Values<'a, 'txn> {
+impl<'a, 'txn> Entries<'a, 'txn> {
     fn new(iter: &'a mut BlockIter, txn: &'txn mut Transaction) -> Self {
-        Values { iter, txn }
+        Entries { iter, txn }
+    }
+}
+
+impl<'a, 'txn> Iterator for Entries<'a, 'txn> {
+    type Item = (ID, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.read_entry(self.txn)
     }
 }
 
+pub struct Values<'a, 'txn>(Entries<'a, 'txn>);
+
 impl<'a, 'txn> Iterator for Values<'a, 'txn> {
     type Item = Value;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.iter.reached_end || self.iter.index == self.iter.branch.content_len() {
-            None
-        } else {
-            let mut content = self
-                .iter
-                .slice::<ArraySliceConcat>(self.txn, 1, Vec::default())?;
-            content.pop()
-        }
+        let (_, value) = self.0.next()?;
+        Some(value)
+    }
+}
+
+/// Maps an `offset` expressed in a given `encoding` units into an offset within an item clock
+/// space, which for strings is always based on UTF-16 code units.
+fn block_offset(item: &Item, offset: u32, encoding: OffsetKind) -> u32 {
+    if let ItemContent::String(s) = &item.content {
+        s.block_offset(offset, encoding)
+    } else {
+        offset
     }
 }
 
@@ -540,3 +719,49 @@ pub(crate) trait SliceConcat {
     fn slice(content: &mut ItemContent, offset: usize, len: usize) -> Vec<Value>;
     fn concat(a: Vec<Value>, b: Vec<Value>) -> Vec<Value>;
 }
+
+/// Error returned when trying to access or modify elements at positions, which are outside of the
+/// bounds of a sequential collection, eg. inserting at index greater than [Array] length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBounds;
+
+impl std::fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "index is outside of the bounds of a collection")
+    }
+}
+
+impl std::error::Error for OutOfBounds {}
+
+#[cfg(test)]
+mod test {
+    use crate::block_iter::{BlockIter, OutOfBounds};
+    use crate::types::{BranchPtr, Value};
+    use crate::Doc;
+
+    #[test]
+    fn read_with_restores_position() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let array = txn.get_array("array");
+        for i in 0..4 {
+            array.push_back(&mut txn, i);
+        }
+
+        let mut walker = BlockIter::new(BranchPtr::from(array.as_ref()));
+        let mut visited = 0;
+        let result = walker.read_with(&mut txn, 3, |_, _, _| {
+            visited += 1;
+            // reader gives up after consuming first two elements
+            if visited > 2 {
+                0
+            } else {
+                1
+            }
+        });
+        assert_eq!(result, Err(OutOfBounds));
+        assert_eq!(visited, 3);
+        assert_eq!(walker.index(), 0);
+        assert_eq!(walker.read_value(&mut txn), Some(Value::from(0.0)));
+    }
+}
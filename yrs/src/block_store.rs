@@ -1,13 +1,17 @@
-use crate::block::{Block, BlockPtr, ClientID, ID};
-use crate::updates::decoder::{Decode, Decoder};
+use crate::block::{Block, BlockPtr, BlockRange, ClientID, ItemContent, ID};
+use crate::doc::MemoryUsage;
+use crate::types::TypePtr;
+use crate::updates::decoder::{Decode, Decoder, MAX_PREALLOC};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
 use crate::*;
 use lib0::error::Error;
+use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
-use std::ops::Deref;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
 use std::vec::Vec;
 
 /// State vector is a compact representation of all known blocks inserted and integrated into
@@ -36,7 +40,9 @@ impl StateVector {
     pub(crate) fn from(ss: &BlockStore) -> Self {
         let mut sv = StateVector::default();
         for (client_id, client_struct_list) in ss.clients.iter() {
-            sv.0.insert(*client_id, client_struct_list.get_state());
+            if client_struct_list.len() != 0 {
+                sv.0.insert(*client_id, client_struct_list.get_state());
+            }
         }
         sv
     }
@@ -113,12 +119,76 @@ impl StateVector {
             *e = (*e).max(clock);
         }
     }
+
+    /// Checks if current state vector has observed all of the changes described by `other` state
+    /// vector, ie. if a document represented by `other` doesn't contain any changes unknown to a
+    /// document represented by current state vector.
+    pub fn includes(&self, other: &StateVector) -> bool {
+        other
+            .iter()
+            .all(|(client, &clock)| self.get(client) >= clock)
+    }
+
+    /// Checks if current state vector is causally newer than `other` one: it has observed all of
+    /// the changes known to `other` and at least one more. Returns `false` if both state vectors
+    /// are equal or concurrent (each one of them knows about some changes that the other doesn't).
+    pub fn is_newer_than(&self, other: &StateVector) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Greater)
+    }
+
+    /// Returns clock ranges of all changes known to a current state vector, which haven't been
+    /// observed by `other` state vector.
+    pub fn diff(&self, other: &StateVector) -> IdSet {
+        let mut missing = IdSet::new();
+        for (&client, &clock) in self.iter() {
+            let remote_clock = other.get(&client);
+            if clock > remote_clock {
+                missing.insert(ID::new(client, remote_clock), clock - remote_clock);
+            }
+        }
+        missing
+    }
+}
+
+/// State vectors are partially ordered by their causality: a state vector is greater than another
+/// if it includes all of its changes and some more. Concurrent state vectors are not comparable.
+impl PartialOrd for StateVector {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.includes(other), other.includes(self)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Greater),
+            (false, true) => Some(Ordering::Less),
+            (false, false) => None,
+        }
+    }
+}
+
+impl FromIterator<(ClientID, u32)> for StateVector {
+    fn from_iter<T: IntoIterator<Item = (ClientID, u32)>>(iter: T) -> Self {
+        let mut sv = StateVector::default();
+        for (client, clock) in iter {
+            if clock > 0 {
+                sv.set_max(client, clock);
+            }
+        }
+        sv
+    }
+}
+
+impl<'a> IntoIterator for &'a StateVector {
+    type Item = (&'a ClientID, &'a u32);
+    type IntoIter = std::collections::hash_map::Iter<'a, ClientID, u32>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 impl Decode for StateVector {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
         let len = decoder.read_var::<u32>()? as usize;
-        let mut sv = HashMap::with_capacity_and_hasher(len, BuildHasherDefault::default());
+        let mut sv =
+            HashMap::with_capacity_and_hasher(len.min(MAX_PREALLOC), BuildHasherDefault::default());
         let mut i = 0;
         while i < len {
             let client = decoder.read_var()?;
@@ -177,9 +247,79 @@ impl Decode for Snapshot {
     }
 }
 
-/// A resizable list of blocks inserted by a single client.
+/// Minimal number of blocks stored in a single [BlockArena] chunk.
+const ARENA_CHUNK_MIN: usize = 16;
+
+/// Maximal number of blocks stored in a single [BlockArena] chunk.
+const ARENA_CHUNK_MAX: usize = 1024;
+
+/// Slab allocator for blocks inserted by a single client. Blocks are stored by value in chunks,
+/// which are never reallocated once created, so that a [BlockPtr] to an allocated block stays
+/// valid until that block is released. Released slots are reused by subsequent allocations.
+pub(crate) struct BlockArena {
+    chunks: Vec<Vec<Block>>,
+    free: Vec<BlockPtr>,
+}
+
+impl BlockArena {
+    fn with_capacity(capacity: usize) -> Self {
+        let mut arena = BlockArena {
+            chunks: Vec::new(),
+            free: Vec::new(),
+        };
+        if capacity > 0 {
+            let capacity = capacity.max(ARENA_CHUNK_MIN).min(ARENA_CHUNK_MAX);
+            arena.chunks.push(Vec::with_capacity(capacity));
+        }
+        arena
+    }
+
+    /// Returns a number of bytes allocated by current arena.
+    fn allocated_size(&self) -> usize {
+        let chunks: usize = self.chunks.iter().map(Vec::capacity).sum();
+        chunks * std::mem::size_of::<Block>()
+            + self.free.capacity() * std::mem::size_of::<BlockPtr>()
+    }
+
+    /// Moves a `block` into current arena, returning a pointer to its new location.
+    fn alloc(&mut self, block: Block) -> BlockPtr {
+        let ptr = if let Some(mut ptr) = self.free.pop() {
+            *ptr.deref_mut() = block;
+            ptr
+        } else {
+            let capacity = match self.chunks.last() {
+                None => ARENA_CHUNK_MIN,
+                Some(chunk) if chunk.len() == chunk.capacity() => {
+                    (chunk.capacity() * 2).min(ARENA_CHUNK_MAX)
+                }
+                Some(_) => 0,
+            };
+            if capacity != 0 {
+                self.chunks.push(Vec::with_capacity(capacity));
+            }
+            // a chunk never grows past its capacity, so pushing doesn't move other blocks
+            let chunk = self.chunks.last_mut().unwrap();
+            chunk.push(block);
+            BlockPtr::from(chunk.last_mut().unwrap())
+        };
+        ptr.link_branch();
+        ptr
+    }
+
+    /// Drops the contents of a block under a given pointer and marks its slot for reuse. Pointer
+    /// must not be used after this call.
+    fn free(&mut self, mut ptr: BlockPtr) {
+        let id = *ptr.id();
+        *ptr.deref_mut() = Block::GC(BlockRange::new(id, 0));
+        self.free.push(ptr);
+    }
+}
+
+/// A resizable list of blocks inserted by a single client. Blocks themselves live in a client's
+/// [BlockArena], while the list keeps them ordered by their clock.
 pub(crate) struct ClientBlockList {
-    list: Vec<Box<block::Block>>,
+    list: Vec<BlockPtr>,
+    arena: BlockArena,
 }
 
 impl PartialEq for ClientBlockList {
@@ -203,24 +343,47 @@ impl PartialEq for ClientBlockList {
 
 impl ClientBlockList {
     fn new() -> ClientBlockList {
-        ClientBlockList { list: Vec::new() }
+        ClientBlockList {
+            list: Vec::new(),
+            arena: BlockArena::with_capacity(0),
+        }
+    }
+
+    /// Returns an approximate heap memory used by current block list.
+    pub(crate) fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            blocks: self.arena.allocated_size()
+                + self.list.capacity() * std::mem::size_of::<BlockPtr>(),
+            ..MemoryUsage::default()
+        };
+        for block in self.list.iter() {
+            if let Block::Item(item) = block.deref() {
+                usage.content += item.content.heap_size();
+                if let Some(key) = item.parent_sub.as_ref() {
+                    usage.indexes += key.len();
+                }
+                if let ItemContent::Type(branch) = &item.content {
+                    usage.indexes += branch.index_heap_size();
+                }
+            }
+        }
+        usage
     }
 
     /// Creates a new instance of aclient block list with a predefined capacity.
     pub fn with_capacity(capacity: usize) -> ClientBlockList {
         ClientBlockList {
             list: Vec::with_capacity(capacity),
+            arena: BlockArena::with_capacity(capacity),
         }
     }
 
     pub fn try_get(&self, index: usize) -> Option<BlockPtr> {
-        let block_ref = self.list.get(index)?;
-        Some(BlockPtr::from(block_ref))
+        self.list.get(index).cloned()
     }
 
     pub fn get(&self, index: usize) -> BlockPtr {
-        let block_ref = &self.list[index];
-        BlockPtr::from(block_ref)
+        self.list[index]
     }
 
     /// Gets the last clock sequence number representing the state of inserts made by client
@@ -228,8 +391,10 @@ impl ClientBlockList {
     /// describes a clock sequence number that **will be** assigned, when a new block will be
     /// appended to current list.
     pub fn get_state(&self) -> u32 {
-        let item = self.get(self.list.len() - 1);
-        item.id().clock + item.len()
+        match self.list.last() {
+            Some(item) => item.id().clock + item.len(),
+            None => 0,
+        }
     }
 
     /// Returns first block on the list - since we only initialize [ClientBlockList]
@@ -249,6 +414,10 @@ impl ClientBlockList {
     /// Given a block's identifier clock value, return an offset under which this block could be
     /// found using binary search algorithm.
     pub(crate) fn find_pivot(&self, clock: u32) -> Option<usize> {
+        if self.list.is_empty() {
+            // list may be empty while its first block is being integrated
+            return None;
+        }
         let mut left = 0;
         let mut right = self.list.len() - 1;
         let mut block = self.get(right);
@@ -289,14 +458,21 @@ impl ClientBlockList {
         self.try_get(idx)
     }
 
-    /// Pushes a new block at the end of this block list.
-    pub(crate) fn push(&mut self, block: Box<block::Block>) {
+    /// Moves a `block` into this client's arena, returning a pointer to it. Allocated block is not
+    /// yet a part of this list: once it's integrated, it should be added using [Self::push].
+    pub(crate) fn alloc(&mut self, block: Block) -> BlockPtr {
+        self.arena.alloc(block)
+    }
+
+    /// Pushes a new block, previously allocated with [Self::alloc], at the end of this block list.
+    pub(crate) fn push(&mut self, block: BlockPtr) {
         self.list.push(block);
     }
 
-    /// Inserts a new block at a given `index` position within this block list. This method may
-    /// panic if `index` is greater than a length of the list.
-    fn insert(&mut self, index: usize, block: Box<block::Block>) {
+    /// Inserts a new block, previously allocated with [Self::alloc], at a given `index` position
+    /// within this block list. This method may panic if `index` is greater than a length of
+    /// the list.
+    fn insert(&mut self, index: usize, block: BlockPtr) {
         self.list.insert(index, block);
     }
 
@@ -309,23 +485,34 @@ impl ClientBlockList {
         ClientBlockListIter(self.list.iter())
     }
 
+    /// Removes all blocks starting from a given `index`, releasing them from this client's arena.
+    fn truncate(&mut self, index: usize) {
+        for ptr in self.list.drain(index..) {
+            self.arena.free(ptr);
+        }
+    }
+
     /// Attempts to squash block at a given `index` with a corresponding block on its left side.
     /// If this succeeds, block under a given `index` will be removed, and its contents will be
     /// squashed into its left neighbor. In such case a squash result will be returned in order to
     /// later on rewire left/right neighbor changes that may have occurred as a result of squashing
     /// and block removal.
     pub(crate) fn squash_left(&mut self, index: usize) {
-        let (l, r) = self.list.split_at_mut(index);
-        let mut left = BlockPtr::from(&mut l[index - 1]);
-        let right = BlockPtr::from(&r[0]);
+        let mut left = self.list[index - 1];
+        let right = self.list[index];
         if left.is_deleted() == right.is_deleted() && left.same_type(right.deref()) {
             if left.try_squash(right) {
-                let mut right = self.list.remove(index);
-                let right_ptr = BlockPtr::from(&mut right);
-                if let Block::Item(item) = *right {
-                    if let Some(parent_sub) = item.parent_sub {
+                let right_ptr = self.list.remove(index);
+                if let Block::Item(item) = right_ptr.deref() {
+                    if let TypePtr::Branch(parent) = &item.parent {
+                        parent
+                            .search_markers
+                            .borrow_mut()
+                            .squashed(left, right_ptr, item);
+                    }
+                    if let Some(parent_sub) = &item.parent_sub {
                         let mut parent = item.parent.as_branch().unwrap().clone();
-                        if let Entry::Occupied(mut e) = parent.map.entry(parent_sub) {
+                        if let Entry::Occupied(mut e) = parent.map.entry(parent_sub.clone()) {
                             let r = e.get_mut();
                             if *r == right_ptr {
                                 *r = left;
@@ -333,6 +520,7 @@ impl ClientBlockList {
                         }
                     }
                 }
+                self.arena.free(right_ptr);
             }
         }
     }
@@ -344,14 +532,14 @@ impl Default for ClientBlockList {
     }
 }
 
-pub(crate) struct ClientBlockListIter<'a>(std::slice::Iter<'a, Box<block::Block>>);
+pub(crate) struct ClientBlockListIter<'a>(std::slice::Iter<'a, BlockPtr>);
 
 impl<'a> Iterator for ClientBlockListIter<'a> {
     type Item = &'a Block;
 
     fn next(&mut self) -> Option<Self::Item> {
         let next = self.0.next()?;
-        Some(next.as_ref())
+        Some(next.deref())
     }
 }
 
@@ -400,6 +588,18 @@ impl BlockStore {
         self.clients.get_mut(client)
     }
 
+    /// Removes all blocks, which are not included in a given `state_vector`. It's up to a caller
+    /// to make sure that blocks are split at the clock boundaries defined by `state_vector` and
+    /// that none of the removed blocks are referenced anymore.
+    pub(crate) fn truncate(&mut self, state_vector: &StateVector) {
+        self.clients.retain(|client, blocks| {
+            if let Some(index) = blocks.find_pivot(state_vector.get(client)) {
+                blocks.truncate(index);
+            }
+            !blocks.list.is_empty()
+        });
+    }
+
     /// Returns an iterator over the client and block lists pairs known to a current block store.
     pub fn iter(&self) -> Iter<'_> {
         self.clients.iter()
@@ -429,7 +629,9 @@ impl BlockStore {
         let blocks = self.clients.get_mut(&id.client)?;
         let mut index = blocks.find_pivot(id.clock)?;
         let mut ptr = blocks.get(index);
-        if let Some(new) = ptr.splice(id.clock - ptr.id().clock, OffsetKind::Utf16) {
+        if let Some(new) = ptr.splice_with(id.clock - ptr.id().clock, OffsetKind::Utf16, |b| {
+            blocks.alloc(b)
+        }) {
             blocks.insert(index + 1, new);
             index += 1;
         }
@@ -443,7 +645,9 @@ impl BlockStore {
         let block_id = block.id();
         if id.clock != block_id.clock + block.len() - 1 {
             let new = block
-                .splice(1 + id.clock - block_id.clock, OffsetKind::Utf16)
+                .splice_with(1 + id.clock - block_id.clock, OffsetKind::Utf16, |b| {
+                    blocks.alloc(b)
+                })
                 .unwrap();
             blocks.insert(index + 1, new);
         }
@@ -469,6 +673,13 @@ impl BlockStore {
             .or_insert_with(ClientBlockList::new)
     }
 
+    /// Moves a `block` into an arena of a given `client`, returning a pointer to its new location.
+    /// Allocated block must be added to the client's block list once it has been integrated: new
+    /// blocks should be allocated using [Transaction::integrate_new_block], which takes care of it.
+    pub(crate) fn alloc(&mut self, client: ClientID, block: Block) -> BlockPtr {
+        self.get_client_blocks_mut(client).alloc(block)
+    }
+
     /// Returns a mutable reference to block list for the given `client`. In case when no such list
     /// existed, a new one will be created with predefined `capacity` and returned.
     pub(crate) fn get_client_blocks_with_capacity_mut(
@@ -492,9 +703,8 @@ impl BlockStore {
         let id = block.id().clone();
         let blocks = self.clients.get_mut(&id.client)?;
         let index = blocks.find_pivot(id.clock)?;
-        let mut right = block.splice(offset, encoding)?;
-        let right_ptr = BlockPtr::from(&mut right);
-        blocks.insert(index + 1, right);
+        let right_ptr = block.splice_with(offset, encoding, |b| blocks.alloc(b))?;
+        blocks.insert(index + 1, right_ptr);
 
         Some(right_ptr)
     }
@@ -572,3 +782,118 @@ impl<'a> Iterator for Blocks<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::block::{Block, ClientID, ItemContent};
+    use crate::{Doc, IdSet, PrelimMap, StateVector, ID};
+
+    fn sv(entries: &[(ClientID, u32)]) -> StateVector {
+        entries.iter().cloned().collect()
+    }
+
+    #[test]
+    fn arena_reuses_squashed_blocks() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.transact().get_text("text");
+        for c in ["a", "b", "c", "d"] {
+            let mut txn = doc.transact();
+            let len = txt.len();
+            txt.insert(&mut txn, len, c);
+        }
+        let txn = doc.transact();
+        let blocks = txn.store().blocks.get(&1).unwrap();
+        assert_eq!(blocks.len(), 1);
+        // every new block has been squashed into the first one, reusing the same slot
+        assert_eq!(blocks.arena.chunks.iter().map(Vec::len).sum::<usize>(), 2);
+        assert_eq!(txt.to_string(), "abcd");
+    }
+
+    #[test]
+    fn arena_releases_blocks_with_missing_dependencies() {
+        fn assert_no_leaks(doc: &Doc) {
+            let txn = doc.transact();
+            for (_, blocks) in txn.store().blocks.iter() {
+                let allocated: usize = blocks.arena.chunks.iter().map(Vec::len).sum();
+                assert_eq!(allocated - blocks.arena.free.len(), blocks.len());
+            }
+        }
+
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("text");
+        let mut updates = Vec::new();
+        for (i, c) in ["a", "b", "c"].iter().enumerate() {
+            let sv = d1.transact().state_vector();
+            txt1.insert(&mut d1.transact(), i as u32, c);
+            updates.push(d1.encode_state_as_update_v1(&sv));
+        }
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+        txt2.insert(&mut d2.transact(), 0, "x");
+
+        // "c" and "b" depend on blocks which haven't been received yet
+        d2.transact().apply_update_v1(&updates[2]).unwrap();
+        assert_no_leaks(&d2);
+        d2.transact().apply_update_v1(&updates[1]).unwrap();
+        assert_no_leaks(&d2);
+        assert_eq!(txt2.to_string(), "x");
+
+        d2.transact().apply_update_v1(&updates[0]).unwrap();
+        assert_no_leaks(&d2);
+        d1.transact()
+            .apply_update_v1(&d2.encode_state_as_update_v1(&StateVector::default()))
+            .unwrap();
+        assert_eq!(txt1.to_string(), txt2.to_string());
+        assert_no_leaks(&d1);
+    }
+
+    #[test]
+    fn arena_links_nested_types() {
+        let d1 = Doc::with_client_id(1);
+        let array = d1.transact().get_array("array");
+        array.insert(&mut d1.transact(), 0, PrelimMap::<bool>::new());
+
+        let d2 = Doc::with_client_id(2);
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+        d2.transact().apply_update_v1(&update).unwrap();
+
+        for doc in [&d1, &d2] {
+            let txn = doc.transact();
+            let ptr = txn.store().blocks.get_block(&ID::new(1, 0)).unwrap();
+            if let Block::Item(item) = &*ptr {
+                if let ItemContent::Type(branch) = &item.content {
+                    assert!(std::ptr::eq(&*branch.item.unwrap(), &*ptr));
+                    continue;
+                }
+            }
+            panic!("expected a nested map")
+        }
+    }
+
+    #[test]
+    fn state_vector_ordering() {
+        let a = sv(&[(1, 2), (2, 3)]);
+        let b = sv(&[(1, 2), (2, 5)]);
+        let c = sv(&[(1, 4), (2, 1)]);
+
+        assert!(b.includes(&a));
+        assert!(!a.includes(&b));
+        assert!(b.is_newer_than(&a));
+        assert!(b > a);
+        assert!(!a.is_newer_than(&a.clone()));
+        assert_eq!(a.partial_cmp(&a.clone()), Some(std::cmp::Ordering::Equal));
+        // concurrent state vectors
+        assert_eq!(b.partial_cmp(&c), None);
+        assert!(!b.is_newer_than(&c) && !c.is_newer_than(&b));
+        // zero clocks are the same as missing entries
+        assert_eq!(sv(&[(1, 0)]), StateVector::default());
+
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(2, 3), 2);
+        assert_eq!(b.diff(&a), expected);
+        assert!(a.diff(&b).is_empty());
+
+        let pairs: Vec<_> = (&a).into_iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(sv(&pairs), a);
+    }
+}
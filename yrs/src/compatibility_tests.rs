@@ -102,13 +102,13 @@ fn text_insert_delete() {
     let visited = Rc::new(Cell::new(false));
     let setter = visited.clone();
 
-    let mut doc = Doc::new();
+    let doc = Doc::new();
     let txt = doc.transact().get_text("type");
     let _sub = doc.observe_update_v1(move |_, e| {
         let u = Update::decode_v1(&e.update).unwrap();
         for (actual, expected) in u.blocks.blocks().zip(expected_blocks.as_slice()) {
             if let BlockCarrier::Block(block) = actual {
-                assert_eq!(block, expected);
+                assert_eq!(block.as_ref(), expected);
             }
         }
         assert_eq!(u.delete_set, expected_ds);
@@ -0,0 +1,220 @@
+use crate::block::{Prelim, PrelimString};
+use crate::block_iter::{BlockIter, OutOfBounds};
+use crate::types::{BranchPtr, Value};
+use crate::Transaction;
+
+/// Cursor is a positional pointer over the elements of sequential shared collections like
+/// [Array], [Text] or [XmlText]. Unlike index-based methods of these collections, which have
+/// to find a requested position by traversing the collection from its beginning every time,
+/// cursor remembers its current position. This makes a sequence of reads and edits done at
+/// nearby positions cheap, as each of them only needs to traverse the distance from the
+/// previous position.
+///
+/// Cursor holds a mutable reference to a transaction it was created in, which makes it valid
+/// only within the scope of that transaction.
+///
+/// Example:
+/// ```
+/// use yrs::Doc;
+/// let doc = Doc::new();
+/// let mut txn = doc.transact();
+/// let array = txn.get_array("array");
+/// array.insert_range(&mut txn, 0, [1, 2, 3]);
+///
+/// let mut cursor = array.cursor(&mut txn);
+/// cursor.seek(1).unwrap();
+/// cursor.insert("a");
+/// cursor.insert("b");
+/// cursor.delete(1).unwrap(); // removes `2`
+/// assert_eq!(cursor.index(), 3);
+///
+/// assert_eq!(array.to_json().to_string(), "[1, a, b, 3]");
+/// ```
+pub struct Cursor<'txn> {
+    iter: BlockIter,
+    txn: &'txn mut Transaction,
+}
+
+impl<'txn> Cursor<'txn> {
+    pub(crate) fn new(branch: BranchPtr, txn: &'txn mut Transaction) -> Self {
+        Cursor {
+            iter: BlockIter::new(branch),
+            txn,
+        }
+    }
+
+    /// Returns current cursor position, expressed as an index of the element it points to.
+    pub fn index(&self) -> u32 {
+        self.iter.index()
+    }
+
+    /// Checks if current cursor has reached the end of a collection.
+    pub fn finished(&self) -> bool {
+        self.iter.finished()
+    }
+
+    /// Moves current cursor to a given `index`. Returns an [OutOfBounds] error if `index` is
+    /// greater than the length of an underlying collection.
+    pub fn seek(&mut self, index: u32) -> Result<(), OutOfBounds> {
+        self.iter.try_move_to(self.txn, index)
+    }
+
+    /// Moves current cursor forward by `len` elements.
+    pub fn forward(&mut self, len: u32) -> Result<(), OutOfBounds> {
+        self.iter.try_forward(self.txn, len)
+    }
+
+    /// Moves current cursor backward by `len` elements.
+    pub fn backward(&mut self, len: u32) -> Result<(), OutOfBounds> {
+        self.iter.try_backward(self.txn, len)
+    }
+
+    /// Reads a value at the current cursor position and moves cursor forward by one element.
+    /// Returns `None` if cursor has reached the end of a collection.
+    ///
+    /// When used on [Text], a single character is read and cursor moves over its entire length,
+    /// measured using [OffsetKind] configured for a document. `None` is returned if cursor
+    /// points into the middle of a character.
+    pub fn read(&mut self) -> Option<Value> {
+        self.iter.read_value(self.txn)
+    }
+
+    /// Reads a string of `len` characters starting at the current cursor position and moves
+    /// cursor forward right after it. Length is measured using [OffsetKind] configured for
+    /// a document. Non-string elements (like embeds) are skipped, but they count towards `len`.
+    pub fn read_str(&mut self, len: u32) -> Result<String, OutOfBounds> {
+        self.iter.read_str(self.txn, len)
+    }
+
+    /// Inserts a `value` at the current cursor position. Afterwards cursor is placed right
+    /// after inserted value.
+    ///
+    /// When used on [Text], prefer [Cursor::insert_str] as values inserted by this method are
+    /// not considered a part of a text string.
+    pub fn insert<V: Prelim>(&mut self, value: V) {
+        self.iter.insert_contents(self.txn, value)
+    }
+
+    /// Inserts a `chunk` of text at the current cursor position. Afterwards cursor is placed
+    /// right after inserted text.
+    pub fn insert_str(&mut self, chunk: &str) {
+        if !chunk.is_empty() {
+            self.iter
+                .insert_contents(self.txn, PrelimString(chunk.into()))
+        }
+    }
+
+    /// Deletes `len` elements, starting from the current cursor position. Returns an
+    /// [OutOfBounds] error if there are less than `len` elements after the cursor, in which
+    /// case nothing is deleted.
+    pub fn delete(&mut self, len: u32) -> Result<(), OutOfBounds> {
+        self.iter.try_delete(self.txn, len)
+    }
+}
+
+impl<'txn> Iterator for Cursor<'txn> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Doc, OffsetKind, Options, OutOfBounds};
+    use lib0::any::Any;
+
+    #[test]
+    fn array_cursor() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let array = txn.get_array("array");
+        array.insert_range(&mut txn, 0, [1, 2, 3, 4]);
+
+        let mut cursor = array.cursor(&mut txn);
+        assert_eq!(cursor.seek(5), Err(OutOfBounds));
+        cursor.seek(3).unwrap();
+        cursor.backward(2).unwrap();
+        assert_eq!(cursor.read(), Some(2.0.into()));
+        cursor.insert("a");
+        cursor.insert("b");
+        assert_eq!(cursor.index(), 4);
+        assert_eq!(cursor.delete(3), Err(OutOfBounds));
+        cursor.delete(1).unwrap();
+        let rest: Vec<_> = cursor.collect();
+        assert_eq!(rest, vec![4.0.into()]);
+
+        assert_eq!(
+            array.to_json(),
+            Any::Array(vec![1.into(), 2.into(), "a".into(), "b".into(), 4.into()].into())
+        );
+    }
+
+    #[test]
+    fn text_cursor() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let text = txn.get_text("text");
+        text.push(&mut txn, "hello world");
+
+        let mut cursor = text.cursor(&mut txn);
+        cursor.seek(5).unwrap();
+        cursor.insert_str(",");
+        cursor.forward(1).unwrap();
+        cursor.delete(5).unwrap();
+        for chunk in ["ż", "ó", "ł", "w"] {
+            cursor.insert_str(chunk);
+        }
+        assert_eq!(cursor.index(), 14);
+        cursor.seek(0).unwrap();
+        assert_eq!(cursor.read_str(5).unwrap(), "hello");
+        cursor.forward(2).unwrap();
+        assert_eq!(cursor.read_str(6).unwrap(), "żół");
+        assert_eq!(cursor.read_str(2), Err(OutOfBounds));
+
+        assert_eq!(text.to_string(), "hello, żółw");
+    }
+
+    #[test]
+    fn text_cursor_read_multibyte() {
+        for offset_kind in [OffsetKind::Bytes, OffsetKind::Utf16, OffsetKind::Utf32] {
+            let doc = Doc::with_options(Options {
+                client_id: 1,
+                offset_kind,
+                ..Options::default()
+            });
+            let mut txn = doc.transact();
+            let text = txn.get_text("text");
+            text.push(&mut txn, "żó😀a");
+
+            let mut cursor = text.cursor(&mut txn);
+            let chars: Vec<_> = cursor.by_ref().collect();
+            assert_eq!(
+                chars,
+                vec!["ż".into(), "ó".into(), "😀".into(), "a".into()],
+                "{:?}",
+                offset_kind
+            );
+            assert!(cursor.finished());
+            assert_eq!(cursor.read(), None);
+        }
+    }
+
+    #[test]
+    fn text_cursor_read_inside_character() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let text = txn.get_text("text");
+        text.push(&mut txn, "ż😀");
+
+        // with byte offsets, position 1 points into the middle of `ż`
+        let mut cursor = text.cursor(&mut txn);
+        cursor.seek(1).unwrap();
+        assert_eq!(cursor.read(), None);
+        assert_eq!(cursor.index(), 1);
+        cursor.seek(2).unwrap();
+        assert_eq!(cursor.read(), Some("😀".into()));
+        assert_eq!(cursor.index(), 6);
+    }
+}
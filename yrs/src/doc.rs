@@ -1,12 +1,21 @@
-use crate::block::ClientID;
+use crate::block::{Block, ClientID, ItemContent, ID};
 
-use crate::event::{AfterTransactionEvent, EventHandler, Subscription, UpdateEvent};
+use crate::event::{AfterTransactionEvent, EventHandler, SubdocsEvent, Subscription, UpdateEvent};
 use crate::store::{Store, StoreRef};
-use crate::transaction::Transaction;
+use crate::transaction::{GcStats, Origin, ReadTransaction, Transaction};
+use crate::types::{Branch, BranchPtr, TypePtr};
+use crate::updates::checksum;
+use crate::updates::decoder::Decode;
 use crate::updates::encoder::{Encode, Encoder, EncoderV1, EncoderV2};
-use crate::{DeleteSet, StateVector, SubscriptionId};
+use crate::{
+    Codecs, DeleteSet, ReadTxn, Replica, Roots, Snapshot, StateVector, SubscriptionId, Update,
+};
+use lib0::any::Any;
 use rand::Rng;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
+use std::rc::Rc;
 
 /// A Yrs document type. Documents are most important units of collaborative resources management.
 /// All shared collections live within a scope of their corresponding documents. All updates are
@@ -40,14 +49,26 @@ use std::ops::Deref;
 /// // now apply update to a remote document
 /// remote_txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
 /// ```
+///
+/// Documents can also be nested inside of other documents' shared collections (see
+/// [Doc::subdocs]). Such documents are accessed through [DocRef] handles.
+///
+/// Documents and their shared types are built on top of non-thread-safe pointers, therefore
+/// a document cannot be sent to another thread. Use [DocHandle](crate::DocHandle) to access it
+/// from multiple threads:
+///
+/// ```compile_fail
+/// use yrs::Doc;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+/// assert_send(&Doc::new());
+/// ```
 pub struct Doc {
     /// A unique client identifier, that's also a unique identifier of current document replica.
     pub client_id: ClientID,
-    store: StoreRef,
+    pub(crate) store: StoreRef,
 }
 
-unsafe impl Send for Doc {}
-
 impl Doc {
     /// Creates a new document with a randomized client identifier.
     pub fn new() -> Self {
@@ -67,82 +88,421 @@ impl Doc {
         }
     }
 
+    /// Creates a new document with a randomized client identifier and initializes it with the
+    /// contents of a given `update`. If an update depends on changes, which are not part of it,
+    /// they will be kept as pending until they're delivered.
+    pub fn load_from(update: Update) -> Self {
+        let doc = Self::new();
+        doc.transact().apply_update(update);
+        doc
+    }
+
+    /// Creates a fork of a current document: a new, independent replica, which contains all of
+    /// the current document's content, but has its own randomized client identifier and guid
+    /// (unless [Options::id_generator] is defined).
+    /// Other document options are preserved. Since a fork shares the history of its origin,
+    /// changes made on both documents can still be exchanged and merged later on.
+    ///
+    /// Unlike [DocRef::clone], which returns another handle to the same document, changes made on
+    /// a duplicate are not visible in the original document and vice versa.
+    pub fn duplicate(&self) -> Doc {
+        let mut options = self.store.options.clone();
+        options.client_id = self.store.generate_client_id();
+        options.guid = options.generate_guid();
+        let mut encoder = EncoderV1::new();
+        self.store
+            .encode_diff(&StateVector::default(), &mut encoder);
+        let update = Update::decode_v1(encoder.to_vec().as_slice())
+            .expect("failed to decode document state");
+        let doc = Doc::with_options(options);
+        doc.transact().apply_update(update);
+        doc
+    }
+
+    /// Returns a client identifier currently used to create new blocks. It can differ from
+    /// [Doc::client_id] of this handle, if it has been changed through another handle to the same
+    /// document or after a collision has been detected (see: [Options::on_client_id_collision]).
+    pub fn current_client_id(&self) -> ClientID {
+        self.store.options.client_id
+    }
+
+    /// Changes a client identifier used to create new blocks, ie. when a document has been
+    /// restored from a snapshot shared with other replicas. Blocks created before keep their
+    /// original identifiers.
+    ///
+    /// Returns an error if a given `client_id` was already used by any of the blocks known to a
+    /// current document (other than the ones created under the current identifier), since
+    /// writing under it would mix changes of different replicas. Other handles to the same
+    /// document keep their [Doc::client_id] unchanged - use [Doc::current_client_id] instead.
+    pub fn set_client_id(&mut self, client_id: ClientID) -> Result<(), ClientIdError> {
+        let store = &mut self.store;
+        if client_id != store.options.client_id && store.is_known_client(&client_id) {
+            return Err(ClientIdError::AlreadyInUse(client_id));
+        }
+        store.options.client_id = client_id;
+        self.client_id = client_id;
+        Ok(())
+    }
+
+    /// Changes a client identifier used to create new blocks to a new, randomly generated one
+    /// (or produced by [Options::id_generator]), which has not been used by any of the blocks
+    /// known to a current document. Returns a new client identifier. See: [Doc::set_client_id].
+    pub fn regenerate_client_id(&mut self) -> ClientID {
+        let client_id = self.store.generate_client_id();
+        self.store.options.client_id = client_id;
+        self.client_id = client_id;
+        client_id
+    }
+
+    /// Returns an approximate heap memory used by a current document. It's maintained
+    /// incrementally: only the blocks of clients modified by a transaction are measured again once
+    /// it's committed, which makes this method cheap enough to be called often, ie. to find the
+    /// largest documents to evict or compact (see: [Doc::gc]). Subdocuments and pending updates
+    /// are not included.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let store = &self.store;
+        let mut usage = MemoryUsage::default();
+        for (client_usage, _) in store.memory_usage.values() {
+            usage += *client_usage;
+        }
+        for (name, branch) in store.types.iter() {
+            usage.indexes += name.len() + std::mem::size_of::<Branch>() + branch.index_heap_size();
+        }
+        usage
+    }
+
+    /// Creates a read-only [Replica] of a current document, which can be handed to other threads
+    /// for queries and encoding. Replicas created before a document has been changed again share
+    /// the same snapshot of its state.
+    pub fn replica(&self) -> Replica {
+        Replica::new(self)
+    }
+
+    /// Garbage collects all tombstones of a current document and squashes its blocks together.
+    /// See: [Transaction::gc].
+    pub fn gc(&self) -> GcStats {
+        self.transact().gc()
+    }
+
+    /// Returns statistics about blocks stored within a current document. It can be used to
+    /// diagnose a document bloat, ie. caused by a large number of tombstones.
+    pub fn stats(&self) -> DocStats {
+        let store = &self.store;
+        let roots: HashMap<BranchPtr, Rc<str>> = store
+            .types
+            .iter()
+            .map(|(name, branch)| (BranchPtr::from(branch), name.clone()))
+            .collect();
+        let mut stats = DocStats::default();
+        for (client, blocks) in store.blocks.iter() {
+            stats.blocks.insert(*client, blocks.len());
+            for block in blocks.iter() {
+                let item = match block {
+                    Block::Item(item) => item,
+                    Block::GC(_) => {
+                        stats.gc_blocks += 1;
+                        continue;
+                    }
+                };
+                if item.is_deleted() {
+                    stats.deleted_items += 1;
+                } else {
+                    stats.live_items += 1;
+                }
+                if item.moved.is_some() {
+                    stats.moved_items += 1;
+                }
+
+                // find a root type, current item belongs to
+                let mut parent = item.parent.clone();
+                let root = loop {
+                    match parent {
+                        TypePtr::Branch(branch) => match branch.item.as_deref() {
+                            Some(Block::Item(item)) => parent = item.parent.clone(),
+                            _ => break roots.get(&branch).cloned(),
+                        },
+                        TypePtr::Named(name) => break Some(name),
+                        _ => break None,
+                    }
+                };
+                if let Some(root) = root {
+                    *stats.content_size.entry(root).or_default() +=
+                        encoded_content_size(&item.content);
+                }
+            }
+        }
+        stats
+    }
+
+    /// Converts all root-level types of a current document into a JSON-like map. See:
+    /// [Transaction::to_json].
+    pub fn to_json(&self) -> Any {
+        self.transact().to_json()
+    }
+
+    /// Returns an iterator over names and kinds of all root-level types of a current document,
+    /// ordered by their names. It can be used by generic tools to walk documents of unknown
+    /// structure. See: [Transaction::roots].
+    ///
+    /// Example:
+    /// ```rust
+    /// use yrs::Doc;
+    /// use yrs::types::TypeRef;
+    ///
+    /// let doc = Doc::new();
+    /// doc.transact().get_text("text");
+    /// doc.transact().get_map("map");
+    ///
+    /// let txn = doc.transact_read();
+    /// let roots: Vec<_> = doc.roots(&txn).collect();
+    /// assert_eq!(roots, vec![("map", TypeRef::Map), ("text", TypeRef::Text)]);
+    /// ```
+    pub fn roots<'a, T: ReadTxn>(&self, txn: &'a T) -> Roots<'a> {
+        txn.roots()
+    }
+
     /// Creates a transaction used for all kind of block store operations.
     /// Transaction cleanups & calling event handles happen when the transaction struct is dropped.
     pub fn transact(&self) -> Transaction {
         Transaction::new(self.store.clone())
     }
 
+    /// Creates a read-only transaction. It can be used to read a document state or encode it
+    /// without tracking any changes or triggering update events. See: [ReadTransaction].
+    pub fn transact_read(&self) -> ReadTransaction {
+        ReadTransaction::new(self.store.clone())
+    }
+
+    /// Creates a transaction marked with a given `origin`. Origin can be used later on to recognize
+    /// the source of changes made within that transaction.
+    pub fn transact_with<O: Into<Origin>>(&self, origin: O) -> Transaction {
+        Transaction::with_origin(self.store.clone(), origin.into())
+    }
+
     /// Subscribe callback function for any changes performed within transaction scope. These
     /// changes are encoded using lib0 v1 encoding and can be decoded using [Update::decode_v1] if
     /// necessary or passed to remote peers right away. This callback is triggered on function
-    /// commit.
+    /// commit, but only if a transaction has changed the document. Event also carries an origin
+    /// of a transaction (see: [Doc::transact_with]).
     ///
     /// Returns a subscription, which will unsubscribe function when dropped.
-    pub fn observe_update_v1<F>(&mut self, f: F) -> Subscription<UpdateEvent>
+    pub fn observe_update_v1<F>(&self, f: F) -> Subscription<UpdateEvent>
     where
         F: Fn(&Transaction, &UpdateEvent) -> () + 'static,
     {
-        let eh = self
-            .store
-            .update_v1_events
-            .get_or_insert_with(EventHandler::new);
+        let mut store = self.store.clone();
+        let eh = store.update_v1_events.get_or_insert_with(EventHandler::new);
         eh.subscribe(f)
     }
 
     /// Manually unsubscribes from a callback used in [Doc::observe_update_v1] method.
-    pub fn unobserve_update_v1(&mut self, subscription_id: SubscriptionId) {
-        self.store
-            .update_v1_events
-            .as_mut()
-            .unwrap()
-            .unsubscribe(subscription_id);
+    pub fn unobserve_update_v1(&self, subscription_id: SubscriptionId) {
+        let mut store = self.store.clone();
+        if let Some(handler) = store.update_v1_events.as_mut() {
+            handler.unsubscribe(subscription_id);
+        }
     }
 
     /// Subscribe callback function for any changes performed within transaction scope. These
-    /// changes are encoded using lib0 v1 encoding and can be decoded using [Update::decode_v2] if
+    /// changes are encoded using lib0 v2 encoding and can be decoded using [Update::decode_v2] if
     /// necessary or passed to remote peers right away. This callback is triggered on function
-    /// commit.
+    /// commit, but only if a transaction has changed the document. Event also carries an origin
+    /// of a transaction (see: [Doc::transact_with]).
     ///
     /// Returns a subscription, which will unsubscribe function when dropped.
-    pub fn observe_update_v2<F>(&mut self, f: F) -> Subscription<UpdateEvent>
+    pub fn observe_update_v2<F>(&self, f: F) -> Subscription<UpdateEvent>
     where
         F: Fn(&Transaction, &UpdateEvent) -> () + 'static,
     {
-        let eh = self
-            .store
-            .update_v2_events
-            .get_or_insert_with(EventHandler::new);
+        let mut store = self.store.clone();
+        let eh = store.update_v2_events.get_or_insert_with(EventHandler::new);
         eh.subscribe(f)
     }
 
-    /// Manually unsubscribes from a callback used in [Doc::observe_update_v1] method.
-    pub fn unobserve_update_v2(&mut self, subscription_id: SubscriptionId) {
-        self.store
-            .update_v2_events
-            .as_mut()
-            .unwrap()
-            .unsubscribe(subscription_id);
+    /// Manually unsubscribes from a callback used in [Doc::observe_update_v2] method.
+    pub fn unobserve_update_v2(&self, subscription_id: SubscriptionId) {
+        let mut store = self.store.clone();
+        if let Some(handler) = store.update_v2_events.as_mut() {
+            handler.unsubscribe(subscription_id);
+        }
+    }
+
+    /// Subscribe callback function, which will be called whenever a new transaction is created
+    /// on this document, before any changes are applied within its scope.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    pub fn observe_before_transaction<F>(&self, f: F) -> Subscription<()>
+    where
+        F: Fn(&Transaction) + 'static,
+    {
+        let mut store = self.store.clone();
+        store
+            .before_transaction_events
+            .get_or_insert_with(EventHandler::new)
+            .subscribe(move |txn, _| f(txn))
+    }
+
+    /// Manually unsubscribes from a callback used in [Doc::observe_before_transaction] method.
+    pub fn unobserve_before_transaction(&self, subscription_id: SubscriptionId) {
+        let mut store = self.store.clone();
+        if let Some(handler) = store.before_transaction_events.as_mut() {
+            handler.unsubscribe(subscription_id);
+        }
     }
 
     /// Subscribe callback function to updates on the `Doc`. The callback will receive state updates and
-    /// deletions when a document transaction is committed.
-    pub fn observe_transaction_cleanup<F>(&mut self, f: F) -> Subscription<AfterTransactionEvent>
+    /// deletions when a document transaction is committed. It's called after the transaction
+    /// cleanup has been completed, once all shared type observers have been notified.
+    pub fn observe_transaction_cleanup<F>(&self, f: F) -> Subscription<AfterTransactionEvent>
     where
         F: Fn(&Transaction, &AfterTransactionEvent) -> () + 'static,
     {
-        self.store
+        let mut store = self.store.clone();
+        store
             .after_transaction_events
             .get_or_insert_with(EventHandler::new)
             .subscribe(f)
     }
     /// Cancels the transaction cleanup callback associated with the `subscription_id`
-    pub fn unobserve_transaction_cleanup(&mut self, subscription_id: SubscriptionId) {
-        if let Some(handler) = self.store.after_transaction_events.as_mut() {
+    pub fn unobserve_transaction_cleanup(&self, subscription_id: SubscriptionId) {
+        let mut store = self.store.clone();
+        if let Some(handler) = store.after_transaction_events.as_mut() {
             (*handler).unsubscribe(subscription_id);
         }
     }
 
+    /// Returns a globally unique identifier of this document.
+    pub fn guid(&self) -> Rc<str> {
+        self.store.options.guid.clone()
+    }
+
+    /// Checks if this document has been requested to load its contents. Documents created
+    /// directly are loaded by default, while subdocuments received from remote peers are not
+    /// loaded unless their [Options::auto_load] flag was set.
+    pub fn should_load(&self) -> bool {
+        self.store.options.should_load
+    }
+
+    /// Checks if this document, when used as a subdocument, should be loaded automatically by
+    /// the peers that received it.
+    pub fn auto_load(&self) -> bool {
+        self.store.options.auto_load
+    }
+
+    /// Returns an iterator over all subdocuments currently nested within this document's shared
+    /// collections.
+    pub fn subdocs(&self) -> impl Iterator<Item = &DocRef> {
+        self.store.subdocs.values()
+    }
+
+    /// Returns unique identifiers of all subdocuments currently nested within this document.
+    pub fn subdoc_guids(&self) -> impl Iterator<Item = &Rc<str>> {
+        self.store
+            .subdocs
+            .values()
+            .map(|doc| &doc.store.options.guid)
+    }
+
+    /// Requests current subdocument to be loaded. It's up to the document providers to actually
+    /// fetch its contents once they are notified about it via [Doc::observe_subdocs] callback
+    /// of a parent document, which is triggered when given `parent_txn` is committed.
+    ///
+    /// This method has no effect on documents, which have been loaded already.
+    pub fn load(&self, parent_txn: &mut Transaction) {
+        let mut store = self.store.clone();
+        if !store.options.should_load {
+            if store.parent.is_some() {
+                parent_txn
+                    .subdocs_mut()
+                    .loaded
+                    .insert(self.addr(), DocRef::from(self.share()));
+            }
+            store.options.should_load = true;
+        }
+    }
+
+    /// Subscribe callback function, which will be called whenever subdocuments have been added,
+    /// removed or requested to load within a committed transaction of this document.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    pub fn observe_subdocs<F>(&self, f: F) -> Subscription<SubdocsEvent>
+    where
+        F: Fn(&Transaction, &SubdocsEvent) + 'static,
+    {
+        let mut store = self.store.clone();
+        store
+            .subdocs_events
+            .get_or_insert_with(EventHandler::new)
+            .subscribe(f)
+    }
+
+    /// Manually unsubscribes from a callback used in [Doc::observe_subdocs] method.
+    pub fn unobserve_subdocs(&self, subscription_id: SubscriptionId) {
+        let mut store = self.store.clone();
+        if let Some(handler) = store.subdocs_events.as_mut() {
+            handler.unsubscribe(subscription_id);
+        }
+    }
+
+    /// Returns another handle to the same document. It's not exposed publicly: handles given away
+    /// to the users must be wrapped in [DocRef].
+    pub(crate) fn share(&self) -> Doc {
+        Doc {
+            client_id: self.client_id,
+            store: self.store.clone(),
+        }
+    }
+
+    /// Returns an address of a store shared by all handles to the same document.
+    pub(crate) fn addr(&self) -> DocAddr {
+        self.store.addr()
+    }
+
+    /// Returns options, which are serialized together with this document when it's nested
+    /// inside of another document.
+    pub(crate) fn encode_options(&self) -> Any {
+        let options = &self.store.options;
+        let mut map = HashMap::new();
+        if options.skip_gc {
+            map.insert("gc".to_string(), Any::Bool(false));
+        }
+        if options.auto_load {
+            map.insert("autoLoad".to_string(), Any::Bool(true));
+        }
+        Any::Map(Box::new(map))
+    }
+
+    /// Creates a new subdocument instance out of its `guid` and options serialized using
+    /// [Doc::encode_options].
+    pub(crate) fn decode_options(guid: Rc<str>, opts: &Any) -> Self {
+        let (mut skip_gc, mut auto_load) = (false, false);
+        if let Any::Map(map) = opts {
+            if let Some(Any::Bool(gc)) = map.get("gc") {
+                skip_gc = !*gc;
+            }
+            if let Some(Any::Bool(value)) = map.get("autoLoad") {
+                auto_load = *value;
+            }
+        }
+        Doc::with_options(Options {
+            guid,
+            skip_gc,
+            auto_load,
+            should_load: auto_load,
+            ..Options::default()
+        })
+    }
+
+    /// Returns a snapshot which describes a current state of updates and removals made within
+    /// this document. It can be used later on to read or encode the state of a document
+    /// at this point in time.
+    pub fn snapshot(&self) -> Snapshot {
+        let blocks = &self.store.blocks;
+        let sv = blocks.get_state_vector();
+        let ds = DeleteSet::from(blocks);
+        Snapshot::new(sv, ds)
+    }
+
     pub fn encode_state_as_update<E: Encoder>(&self, sv: &StateVector, encoder: &mut E) {
         let store = self.store.deref();
         store.write_blocks_from(sv, encoder);
@@ -161,6 +521,21 @@ impl Doc {
         self.encode_state_as_update(sv, &mut encoder);
         encoder.to_vec()
     }
+
+    /// Encodes document state using lib0 v1 encoding (just like [Doc::encode_state_as_update_v1])
+    /// and appends an integrity trailer to it. Such payload can be verified and applied using
+    /// [Transaction::apply_update_v1_checked]. See [checksum](crate::updates::checksum) module
+    /// for details.
+    pub fn encode_state_as_update_v1_checked(&self, sv: &StateVector) -> Vec<u8> {
+        checksum::append(self.encode_state_as_update_v1(sv))
+    }
+
+    /// Encodes document state using lib0 v2 encoding (just like [Doc::encode_state_as_update_v2])
+    /// and appends an integrity trailer to it. Such payload can be verified and applied using
+    /// [Transaction::apply_update_v2_checked].
+    pub fn encode_state_as_update_v2_checked(&self, sv: &StateVector) -> Vec<u8> {
+        checksum::append(self.encode_state_as_update_v2(sv))
+    }
 }
 
 impl Default for Doc {
@@ -169,8 +544,103 @@ impl Default for Doc {
     }
 }
 
+impl std::fmt::Debug for Doc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Doc({})", self.store.options.guid)
+    }
+}
+
+/// Documents are considered equal when they share the same [Doc::guid].
+impl PartialEq for Doc {
+    fn eq(&self, other: &Self) -> bool {
+        self.store.options.guid == other.store.options.guid
+    }
+}
+
+/// A reference counted handle to a [Doc]. Unlike [Doc], it can be cloned - all clones point to the
+/// same document. Just like [Doc], it cannot be sent to other threads. Documents which are shared
+/// between many owners, like subdocuments nested inside of other documents' shared collections (see:
+/// [Doc::subdocs]), are accessed through such handles.
+///
+/// ```compile_fail
+/// use yrs::{Doc, DocRef};
+///
+/// fn assert_send<T: Send>(_: T) {}
+/// assert_send(DocRef::from(Doc::new()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocRef(Rc<Doc>);
+
+impl From<Doc> for DocRef {
+    fn from(doc: Doc) -> Self {
+        DocRef(Rc::new(doc))
+    }
+}
+
+impl Deref for DocRef {
+    type Target = Doc;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Statistics about blocks stored within a document. See: [Doc::stats].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DocStats {
+    /// Number of blocks stored per every client.
+    pub blocks: HashMap<ClientID, usize>,
+    /// Number of items, which are not deleted.
+    pub live_items: usize,
+    /// Number of deleted items (tombstones), whose blocks are still kept in a block store.
+    pub deleted_items: usize,
+    /// Number of blocks representing ranges of garbage collected items.
+    pub gc_blocks: usize,
+    /// Number of items, which have been moved to another position.
+    pub moved_items: usize,
+    /// Size (in bytes) of lib0 v1 encoded contents of all items stored per every root type.
+    pub content_size: HashMap<Rc<str>, usize>,
+}
+
+impl DocStats {
+    /// Returns a total number of blocks stored within a document.
+    pub fn total_blocks(&self) -> usize {
+        self.blocks.values().sum()
+    }
+}
+
+/// Approximate heap memory (in bytes) used by a document. See: [Doc::memory_usage].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Memory used by blocks themselves, including the ones representing tombstones and
+    /// garbage collected ranges.
+    pub blocks: usize,
+    /// Memory used by contents of the items, ie. strings, JSON values or binary data.
+    pub content: usize,
+    /// Memory used by indexes: map entry keys and cached text contents of shared collections.
+    pub indexes: usize,
+}
+
+impl MemoryUsage {
+    /// Returns a total memory used by a document.
+    pub fn total(&self) -> usize {
+        self.blocks + self.content + self.indexes
+    }
+}
+
+impl std::ops::AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.blocks += other.blocks;
+        self.content += other.content;
+        self.indexes += other.indexes;
+    }
+}
+
+/// An address of a document store, used to identify document instances.
+pub(crate) type DocAddr = usize;
+
 /// Configuration options of [Doc] instance.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Options {
     /// Globally unique 53-bit long client identifier.
     pub client_id: ClientID,
@@ -178,6 +648,55 @@ pub struct Options {
     pub offset_kind: OffsetKind,
     /// Determines if transactions commits should try to perform GC-ing of deleted items.
     pub skip_gc: bool,
+    /// Optional filter called with an ID and content of every deleted item before it's being
+    /// garbage collected. Items for which it returns `false` are left untouched. It has no effect when
+    /// [Options::skip_gc] is set.
+    pub gc_filter: Option<GcFilter>,
+    /// Optional filter called for every block of an incoming update before it's integrated into
+    /// a document. If it returns `false` for any of the blocks, the entire update is rejected.
+    /// See: [Transaction::try_apply_update] and [Transaction::apply_update_v1].
+    pub update_filter: Option<UpdateFilter>,
+    /// Optional policy called for every shared collection modified (inserted into or deleted
+    /// from) by an incoming update. If it returns `false` for any of them, the entire update is
+    /// rejected. Local changes are not checked. See: [Transaction::try_apply_update] and
+    /// [sync::handle_message](crate::sync::handle_message).
+    pub write_policy: Option<WritePolicy>,
+    /// Limits of a document size, enforced on updates applied from remote peers. See:
+    /// [Transaction::try_apply_update] and [Transaction::apply_update_v1].
+    pub limits: DocLimits,
+    /// Globally unique document identifier. By default it's a randomly generated UUID v4.
+    pub guid: Rc<str>,
+    /// Determines if a document should be loaded. Subdocuments received from remote peers are
+    /// not loaded until [Doc::load] is called, unless [Options::auto_load] is set.
+    pub should_load: bool,
+    /// Determines if a document, when nested as a subdocument, should be loaded automatically
+    /// by the peers which received it.
+    pub auto_load: bool,
+    /// Optional clock used to timestamp every item created locally. Timestamps are kept only in
+    /// memory of a current document instance - they are not a part of the updates exchanged with
+    /// other peers. See: [Transaction::timestamp].
+    pub timestamps: Option<Clock>,
+    /// Determines if values of map entries, which lost a conflict with other values written
+    /// concurrently under the same key, should be kept from being garbage collected, so that
+    /// they can be still accessed with [Map::get_all]. Once an entry is overridden, its former
+    /// conflicting values are collected by the next [Transaction::gc] call.
+    pub keep_map_conflicts: bool,
+    /// Codecs of user-defined content types, which can be stored in a document as
+    /// [Extension](crate::Extension)s.
+    /// See: [Codecs::register].
+    pub codecs: Codecs,
+    /// Optional callback notified when an incoming update contains blocks created under current
+    /// document's client identifier, which were not produced by this replica (their clocks are
+    /// ahead of the local one). This means that another peer uses the same client identifier.
+    /// In such case a document switches to a new, randomly generated client identifier to avoid
+    /// corrupting its contents any further, and the callback is called with both the old and the
+    /// new identifier. See: [Doc::set_client_id].
+    pub on_client_id_collision: Option<CollisionHandler>,
+    /// Optional generator of identifiers assigned by a document on its own: client identifiers
+    /// of its forks (see: [Doc::duplicate]) and the ones used after regenerating a client
+    /// identifier (see: [Doc::regenerate_client_id]), as well as guids of its forks. By default
+    /// these identifiers are random. See: [Options::deterministic].
+    pub id_generator: Option<IdGenerator>,
 }
 
 impl Options {
@@ -186,10 +705,404 @@ impl Options {
             client_id,
             offset_kind: OffsetKind::Bytes,
             skip_gc: false,
+            gc_filter: None,
+            update_filter: None,
+            write_policy: None,
+            limits: DocLimits::default(),
+            guid: uuid_v4(),
+            should_load: true,
+            auto_load: false,
+            timestamps: None,
+            keep_map_conflicts: false,
+            codecs: Codecs::default(),
+            on_client_id_collision: None,
+            id_generator: None,
+        }
+    }
+
+    /// Creates options of a document, which generates all of its identifiers deterministically
+    /// using [IdGenerator::sequence] starting right after a given `client_id`. Documents created
+    /// with the same options and modified in the same way produce byte-for-byte identical
+    /// updates across runs, which makes them useful in tests and fuzzers. Since
+    /// [Options::timestamps] are disabled by default, if needed they should be provided with a
+    /// deterministic [Clock] as well.
+    pub fn deterministic(client_id: ClientID) -> Self {
+        let id_generator = IdGenerator::sequence(client_id + 1);
+        Options {
+            guid: id_generator.next_guid(),
+            id_generator: Some(id_generator),
+            ..Options::with_client_id(client_id)
+        }
+    }
+
+    /// Returns a new guid, produced by [Options::id_generator] or generated at random.
+    pub(crate) fn generate_guid(&self) -> Rc<str> {
+        match &self.id_generator {
+            Some(generator) => generator.next_guid(),
+            None => uuid_v4(),
         }
     }
 }
 
+/// Generates a random UUID v4 string.
+fn uuid_v4() -> Rc<str> {
+    format_uuid(rand::thread_rng().gen())
+}
+
+/// Formats given bytes as a UUID v4 string.
+fn format_uuid(mut b: [u8; 16]) -> Rc<str> {
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    format!(
+        "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+        b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]
+    )
+    .into()
+}
+
+/// Callback used by [Options::gc_filter] to determine if a deleted item can be garbage collected.
+#[derive(Clone)]
+pub struct GcFilter(Rc<GcFilterFn>);
+
+type GcFilterFn = dyn Fn(&ID, &ItemContent) -> bool;
+
+impl GcFilter {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&ID, &ItemContent) -> bool + 'static,
+    {
+        GcFilter(Rc::new(f))
+    }
+
+    pub(crate) fn call(&self, id: &ID, content: &ItemContent) -> bool {
+        (self.0)(id, content)
+    }
+}
+
+impl std::fmt::Debug for GcFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GcFilter({:p})", Rc::as_ptr(&self.0))
+    }
+}
+
+impl PartialEq for GcFilter {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for GcFilter {}
+
+/// Callback used by [Options::on_client_id_collision]. It's called with the colliding client
+/// identifier and a new identifier, which a document has switched to.
+#[derive(Clone)]
+pub struct CollisionHandler(Rc<CollisionHandlerFn>);
+
+type CollisionHandlerFn = dyn Fn(ClientID, ClientID);
+
+impl CollisionHandler {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(ClientID, ClientID) + 'static,
+    {
+        CollisionHandler(Rc::new(f))
+    }
+
+    pub(crate) fn call(&self, old_client_id: ClientID, new_client_id: ClientID) {
+        (self.0)(old_client_id, new_client_id)
+    }
+}
+
+impl std::fmt::Debug for CollisionHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CollisionHandler({:p})", Rc::as_ptr(&self.0))
+    }
+}
+
+impl PartialEq for CollisionHandler {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CollisionHandler {}
+
+/// Error returned by [Doc::set_client_id].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientIdError {
+    /// Client identifier has been already used to create some of the blocks known to a document.
+    /// Reusing it would make new blocks conflict with the existing ones.
+    AlreadyInUse(ClientID),
+}
+
+impl std::fmt::Display for ClientIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientIdError::AlreadyInUse(client_id) => {
+                write!(f, "client id {} is already in use", client_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientIdError {}
+
+/// Generator of identifiers used by [Options::id_generator]. Every generated client identifier
+/// must fit into 53 bits.
+#[derive(Clone)]
+pub struct IdGenerator(Rc<IdGeneratorFn>);
+
+type IdGeneratorFn = dyn Fn() -> ClientID;
+
+impl IdGenerator {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn() -> ClientID + 'static,
+    {
+        IdGenerator(Rc::new(f))
+    }
+
+    /// Generator returning consecutive numbers, starting from a given `start` value.
+    pub fn sequence(start: ClientID) -> Self {
+        let next = Cell::new(start);
+        IdGenerator::new(move || {
+            let id = next.get();
+            next.set(id + 1);
+            id
+        })
+    }
+
+    pub(crate) fn next_client_id(&self) -> ClientID {
+        (self.0)()
+    }
+
+    pub(crate) fn next_guid(&self) -> Rc<str> {
+        let mut b = [0u8; 16];
+        b[..8].copy_from_slice(&self.next_client_id().to_be_bytes());
+        b[8..].copy_from_slice(&self.next_client_id().to_be_bytes());
+        format_uuid(b)
+    }
+}
+
+impl std::fmt::Debug for IdGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IdGenerator({:p})", Rc::as_ptr(&self.0))
+    }
+}
+
+impl PartialEq for IdGenerator {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for IdGenerator {}
+
+/// Wall-clock time, as returned by [Clock]. By convention it's a number of milliseconds since
+/// Unix epoch.
+pub type Timestamp = u64;
+
+/// Source of timestamps used by [Options::timestamps].
+#[derive(Clone)]
+pub struct Clock(Rc<ClockFn>);
+
+type ClockFn = dyn Fn() -> Timestamp;
+
+impl Clock {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn() -> Timestamp + 'static,
+    {
+        Clock(Rc::new(f))
+    }
+
+    /// Clock based on a system time, returning milliseconds since Unix epoch. It's not supported
+    /// on `wasm32-unknown-unknown` target, where a custom [Clock::new] should be used instead.
+    pub fn system() -> Self {
+        Clock::new(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as Timestamp)
+                .unwrap_or_default()
+        })
+    }
+
+    pub(crate) fn now(&self) -> Timestamp {
+        (self.0)()
+    }
+}
+
+impl std::fmt::Debug for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Clock({:p})", Rc::as_ptr(&self.0))
+    }
+}
+
+impl PartialEq for Clock {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Clock {}
+
+/// Limits of a document size (see: [Options::limits]). Updates, which would make a document
+/// exceed any of them, are rejected by [Transaction::try_apply_update] with
+/// [UpdateError::LimitExceeded](crate::UpdateError::LimitExceeded). Binary entry points, like
+/// [Transaction::apply_update_v1], report it as [Error::Update](lib0::error::Error::Update).
+/// Local changes are not limited.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DocLimits {
+    /// Maximum number of blocks stored within a document.
+    pub max_blocks: Option<usize>,
+    /// Maximum size (in bytes) of lib0 v1 encoded contents of all blocks stored within
+    /// a document. See: [DocStats::content_size]. Enforcing this limit requires traversing an
+    /// entire block store every time an update is applied.
+    pub max_content_size: Option<usize>,
+    /// Maximum number of unique clients, which have made changes to a document.
+    pub max_clients: Option<usize>,
+}
+
+/// Returns a size (in bytes) of lib0 v1 encoded item `content`.
+pub(crate) fn encoded_content_size(content: &ItemContent) -> usize {
+    let mut encoder = EncoderV1::new();
+    content.encode(&mut encoder);
+    encoder.to_vec().len()
+}
+
+/// Callback used by [Options::update_filter] to validate blocks of incoming updates.
+#[derive(Clone)]
+pub struct UpdateFilter(Rc<UpdateFilterFn>);
+
+type UpdateFilterFn = dyn Fn(&BlockInfo) -> bool;
+
+impl UpdateFilter {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&BlockInfo) -> bool + 'static,
+    {
+        UpdateFilter(Rc::new(f))
+    }
+
+    pub(crate) fn call(&self, info: &BlockInfo) -> bool {
+        (self.0)(info)
+    }
+}
+
+impl std::fmt::Debug for UpdateFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UpdateFilter({:p})", Rc::as_ptr(&self.0))
+    }
+}
+
+impl PartialEq for UpdateFilter {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for UpdateFilter {}
+
+/// Information about a block of an incoming update, passed to [Options::update_filter] before
+/// that block is integrated into a document.
+#[derive(Debug)]
+pub struct BlockInfo<'a> {
+    /// Unique identifier of a block.
+    pub id: &'a ID,
+    /// Number of elements stored within a block.
+    pub len: u32,
+    /// Content of a block.
+    pub content: &'a ItemContent,
+    /// Name of a root-level type, if block was inserted directly into it. Blocks inserted into
+    /// nested types only refer to their parents by an ID.
+    pub root: Option<&'a str>,
+    /// A key under which a block was inserted, if its parent acts as a map.
+    pub parent_sub: Option<&'a str>,
+}
+
+/// Callback used by [Options::write_policy] to decide which shared collections can be modified
+/// by remote peers. Denied updates fail with [UpdateError::Denied](crate::UpdateError::Denied),
+/// which is also propagated by the [sync](crate::sync) protocol handlers, so that a server can
+/// reply to a sender with an error.
+#[derive(Clone)]
+pub struct WritePolicy(Rc<WritePolicyFn>);
+
+type WritePolicyFn = dyn Fn(&WriteTarget) -> bool;
+
+impl WritePolicy {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&WriteTarget) -> bool + 'static,
+    {
+        WritePolicy(Rc::new(f))
+    }
+
+    /// Creates a policy, which denies all writes to root-level types of given names, including
+    /// all of the collections nested within them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Options, StateVector, Update, UpdateError, WritePolicy, ID};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let remote = Doc::with_client_id(1);
+    /// let mut txn = remote.transact();
+    /// txn.get_map("config").insert(&mut txn, "mode", "relaxed");
+    /// txn.commit();
+    /// let update = remote.encode_state_as_update_v1(&StateVector::default());
+    ///
+    /// let mut options = Options::with_client_id(2);
+    /// options.write_policy = Some(WritePolicy::read_only(["config"]));
+    /// let doc = Doc::with_options(options);
+    /// let result = doc.transact().try_apply_update(Update::decode_v1(&update).unwrap());
+    /// assert_eq!(result, Err(UpdateError::Denied(ID::new(1, 0))));
+    /// ```
+    pub fn read_only<I, S>(roots: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Rc<str>>,
+    {
+        let roots: HashSet<Rc<str>> = roots.into_iter().map(Into::into).collect();
+        WritePolicy::new(move |target| !roots.contains(target.root))
+    }
+
+    pub(crate) fn call(&self, target: &WriteTarget) -> bool {
+        (self.0)(target)
+    }
+}
+
+impl std::fmt::Debug for WritePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WritePolicy({:p})", Rc::as_ptr(&self.0))
+    }
+}
+
+impl PartialEq for WritePolicy {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for WritePolicy {}
+
+/// Shared collection modified by a block of an incoming update, passed to
+/// [Options::write_policy] before that update is applied.
+#[derive(Debug)]
+pub struct WriteTarget<'a> {
+    /// ID of a block, which is being inserted or deleted.
+    pub id: &'a ID,
+    /// Name of a root-level type containing modified collection.
+    pub root: &'a str,
+    /// IDs of blocks of the nested collections on the way from a root-level type to a modified
+    /// collection (the last one). Empty if a root-level type itself is modified. These are the
+    /// same IDs which nested types are inserted with, so that entire subtrees can be protected.
+    pub nested: &'a [ID],
+    /// `true` if a block is being deleted, `false` if it's being inserted.
+    pub deleted: bool,
+}
+
 impl Default for Options {
     fn default() -> Self {
         let client_id: u32 = rand::thread_rng().gen();
@@ -209,15 +1122,53 @@ pub enum OffsetKind {
     Utf32,
 }
 
+/// A unit in which positions within a [Text](crate::Text) can be expressed. Besides the units
+/// supported by [OffsetKind], it includes grapheme clusters, ie. user-perceived characters.
+///
+/// Unlike [OffsetKind], this is not a document option: it's used to convert indexes between
+/// different units with [Text::convert_offset](crate::Text::convert_offset) and
+/// [Text::len_in](crate::Text::len_in).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetUnit {
+    /// UTF-8 bytes.
+    Bytes,
+    /// UTF-16 code units, used by JavaScript and Java strings.
+    Utf16,
+    /// Unicode code points.
+    Utf32,
+    /// Extended grapheme clusters, as defined by Unicode Standard Annex #29, used by Swift
+    /// strings.
+    Grapheme,
+}
+
+impl From<OffsetKind> for OffsetUnit {
+    fn from(kind: OffsetKind) -> Self {
+        match kind {
+            OffsetKind::Bytes => OffsetUnit::Bytes,
+            OffsetKind::Utf16 => OffsetUnit::Utf16,
+            OffsetKind::Utf32 => OffsetUnit::Utf32,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::block::{Block, ItemContent};
+    use crate::block::{Block, ItemContent, ID};
+    use crate::testing::exchange_updates;
+    use crate::types::{Branch, PathSegment, TypeRef};
     use crate::update::Update;
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
-    use crate::{DeleteSet, Doc, Options, StateVector, SubscriptionId};
+    use crate::{
+        ClientIdError, CollisionHandler, DeleteSet, Doc, DocLimits, DocRef, GcFilter, GcStats,
+        LimitKind, MemoryUsage, Options, Origin, PrelimMap, ReadTxn, RenameRootError,
+        RollbackError, StateVector, SubscriptionId, Transaction, UndoManager, UpdateError,
+        UpdateFilter, WritePolicy, WriteTxn, ROOT_ALIASES,
+    };
     use lib0::any::Any;
+    use lib0::error::Error;
     use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
     use std::rc::Rc;
 
     #[test]
@@ -272,6 +1223,33 @@ mod test {
         assert_eq!(actual, "210".to_owned());
     }
 
+    #[test]
+    fn encode_diff_v2() {
+        let d1 = Doc::with_client_id(1);
+        let mut t1 = d1.transact();
+        let txt = t1.get_text("text");
+        let array = t1.get_array("array");
+        txt.insert(&mut t1, 0, "hello world");
+        txt.remove_range(&mut t1, 5, 6);
+        array.insert_range(&mut t1, 0, [1, 2, 3]);
+        t1.commit();
+
+        let d2 = Doc::with_client_id(2);
+        let mut t2 = d2.transact();
+        let update = t1.encode_diff_v2(&t2.state_vector());
+        t2.apply_update(Update::decode_v2(update.as_slice()).unwrap());
+        assert_eq!(t2.get_text("text").to_string(), "hello");
+        assert_eq!(
+            t2.get_array("array").to_json(),
+            Any::Array(vec![1.into(), 2.into(), 3.into()].into())
+        );
+
+        // v1 and v2 encodings describe the same update
+        let v1 = Update::decode_v1(t1.encode_diff_v1(&StateVector::default()).as_slice()).unwrap();
+        let v2 = Update::decode_v2(t1.encode_diff_v2(&StateVector::default()).as_slice()).unwrap();
+        assert_eq!(v1.encode_v1(), v2.encode_v1());
+    }
+
     #[test]
     fn encode_basic() {
         let doc = Doc::with_client_id(1490905955);
@@ -332,7 +1310,7 @@ mod test {
     fn on_update() {
         let counter = Rc::new(Cell::new(0));
         let doc = Doc::new();
-        let mut doc2 = Doc::new();
+        let doc2 = Doc::new();
         let c = counter.clone();
         let sub = doc2.observe_update_v1(move |_txn, e| {
             let u = Update::decode_v1(&e.update).unwrap();
@@ -351,16 +1329,55 @@ mod test {
         }
         assert_eq!(counter.get(), 3); // update has been propagated
 
-        drop(sub);
+        drop(sub);
+
+        {
+            txt.insert(&mut txn, 3, "de");
+            let mut txn2 = doc2.transact();
+            let sv = txn2.state_vector().encode_v1();
+            let u = txn.encode_diff_v1(&StateVector::decode_v1(sv.as_slice()).unwrap());
+            txn2.apply_update(Update::decode_v1(u.as_slice()).unwrap());
+        }
+        assert_eq!(counter.get(), 3); // since subscription has been dropped, update was not propagated
+    }
+
+    #[test]
+    fn on_update_v2() {
+        let doc = Doc::with_client_id(1);
+        let doc2 = Doc::with_client_id(2);
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let u = updates.clone();
+        let sub: SubscriptionId = doc
+            .observe_update_v2(move |_, e| {
+                u.borrow_mut().push((e.update.clone(), e.origin.clone()))
+            })
+            .into();
+
+        let txt = doc.transact().get_text("test");
+        {
+            let mut txn = doc.transact_with("local");
+            txt.insert(&mut txn, 0, "abc");
+        }
+        {
+            // transactions which didn't change anything don't produce updates
+            let _txn = doc.transact();
+        }
+        assert_eq!(updates.borrow().len(), 1);
+
+        let (update, origin) = updates.borrow_mut().pop().unwrap();
+        assert_eq!(origin, Some(Origin::from("local")));
+        let mut txn2 = doc2.transact();
+        txn2.apply_update(Update::decode_v2(update.as_slice()).unwrap());
+        assert_eq!(txn2.get_text("test").to_string(), "abc");
+        drop(txn2);
 
+        doc.unobserve_update_v2(sub);
+        doc2.unobserve_update_v1(sub); // unobserving non-existing subscription is a no-op
         {
+            let mut txn = doc.transact();
             txt.insert(&mut txn, 3, "de");
-            let mut txn2 = doc2.transact();
-            let sv = txn2.state_vector().encode_v1();
-            let u = txn.encode_diff_v1(&StateVector::decode_v1(sv.as_slice()).unwrap());
-            txn2.apply_update(Update::decode_v1(u.as_slice()).unwrap());
         }
-        assert_eq!(counter.get(), 3); // since subscription has been dropped, update was not propagated
+        assert!(updates.borrow().is_empty());
     }
 
     #[test]
@@ -481,7 +1498,7 @@ mod test {
     #[test]
     fn observe_transaction_cleanup() {
         // Setup
-        let mut doc = Doc::new();
+        let doc = Doc::new();
         let mut txn = doc.transact();
         let text = txn.get_text("test");
         let before_state = Rc::new(Cell::new(StateVector::default()));
@@ -518,6 +1535,87 @@ mod test {
         assert_ne!(after_state.take(), txn.after_state);
     }
 
+    #[test]
+    fn before_and_after_transaction() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.transact().get_text("text");
+        let map = doc.transact().get_map("map");
+        let calls = Rc::new(RefCell::new(Vec::new()));
+
+        let c = calls.clone();
+        let _before = doc.observe_before_transaction(move |txn| {
+            c.borrow_mut().push(format!(
+                "before {:?}",
+                txn.origin().map(|o| o.as_ref().to_vec())
+            ));
+        });
+        let c = calls.clone();
+        let _after = doc.observe_transaction_cleanup(move |_, e| {
+            let mut changed: Vec<_> = e
+                .changed_parent_types
+                .iter()
+                .map(|v| v.clone().to_json().to_string())
+                .collect();
+            changed.sort();
+            c.borrow_mut().push(format!("after {:?}", changed));
+        });
+
+        {
+            let mut txn = doc.transact_with("test");
+            text.push(&mut txn, "abc");
+            map.insert(&mut txn, "key", "value");
+        }
+        assert_eq!(
+            calls.take(),
+            vec![
+                "before Some([116, 101, 115, 116])".to_string(),
+                "after [\"abc\", \"{key: value}\"]".to_string(),
+            ]
+        );
+
+        drop(doc.transact());
+        assert_eq!(
+            calls.take(),
+            vec!["before None".to_string(), "after []".to_string()]
+        );
+    }
+
+    #[test]
+    fn transaction_origins() {
+        let doc = Doc::with_client_id(1);
+        let mut text = doc.transact().get_text("test");
+        let origins = Rc::new(RefCell::new(Vec::new()));
+
+        let o = origins.clone();
+        let _sub_update = doc.observe_update_v1(move |_, e| {
+            o.borrow_mut().push(("update", e.origin.clone()));
+        });
+        let o = origins.clone();
+        let _sub_cleanup = doc.observe_transaction_cleanup(move |_, e| {
+            o.borrow_mut().push(("cleanup", e.origin.clone()));
+        });
+        let o = origins.clone();
+        let _sub_text = text.observe(move |txn, _| {
+            o.borrow_mut().push(("text", txn.origin().cloned()));
+        });
+
+        text.push(&mut doc.transact_with("local"), "a");
+        assert_eq!(
+            origins.take(),
+            vec![
+                ("text", Some(Origin::from("local"))),
+                ("cleanup", Some(Origin::from("local"))),
+                ("update", Some(Origin::from("local"))),
+            ]
+        );
+
+        text.push(&mut doc.transact(), "b");
+        assert_eq!(
+            origins.take(),
+            vec![("text", None), ("cleanup", None), ("update", None)]
+        );
+    }
+
     #[test]
     fn partially_duplicated_update() {
         let d1 = Doc::with_client_id(1);
@@ -540,7 +1638,7 @@ mod test {
     fn incremental_observe_update() {
         const INPUT: &'static str = "hello";
 
-        let mut d1 = Doc::with_client_id(1);
+        let d1 = Doc::with_client_id(1);
         let txt1 = d1.transact().get_text("text");
         let acc = Rc::new(RefCell::new(String::new()));
 
@@ -659,4 +1757,1021 @@ mod test {
 
         assert_eq!(txt2.to_string(), "hello".to_string());
     }
+
+    #[test]
+    fn gc_filter() {
+        let mut options = Options::with_client_id(1);
+        options.gc_filter = Some(GcFilter::new(|id, _| id.clock >= 5));
+        let doc = Doc::with_options(options);
+        let txt = doc.transact().get_text("text");
+        txt.insert(&mut doc.transact(), 0, "hello world");
+        let snapshot = doc.snapshot();
+        txt.remove_range(&mut doc.transact(), 0, 5);
+        txt.remove_range(&mut doc.transact(), 0, 6);
+
+        let mut txn = doc.transact();
+        let mut gced = Vec::new();
+        let mut curr = txt.inner().start;
+        while let Some(Block::Item(item)) = curr.as_deref() {
+            gced.push(matches!(item.content, ItemContent::Deleted(_)));
+            curr = item.right;
+        }
+        assert_eq!(gced, vec![false, true]);
+        assert_eq!(txt.to_string_at(&mut txn, &snapshot), "hello");
+    }
+
+    #[test]
+    fn subdocs() {
+        fn guids(docs: &[DocRef]) -> Vec<String> {
+            let mut guids: Vec<_> = docs.iter().map(|d| d.guid().to_string()).collect();
+            guids.sort();
+            guids
+        }
+
+        fn subdoc(guid: &str) -> Doc {
+            Doc::with_options(Options {
+                guid: guid.into(),
+                ..Options::default()
+            })
+        }
+
+        let doc = Doc::with_client_id(1);
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_c = events.clone();
+        let _sub = doc.observe_subdocs(move |_, e| {
+            let added = guids(&e.added);
+            let removed = guids(&e.removed);
+            let loaded = guids(&e.loaded);
+            events_c.borrow_mut().push((added, removed, loaded));
+        });
+
+        let map = doc.transact().get_map("mysubdocs");
+        {
+            let mut txn = doc.transact();
+            map.insert(&mut txn, "a", subdoc("a"));
+        }
+        assert_eq!(
+            events.borrow_mut().pop(),
+            Some((vec!["a".to_string()], vec![], vec!["a".to_string()]))
+        );
+
+        let doc_a = map.get("a").unwrap().to_ydoc().unwrap();
+        assert_eq!(doc_a.guid().as_ref(), "a");
+        {
+            // already loaded document won't trigger an event again
+            let mut txn = doc.transact();
+            doc_a.load(&mut txn);
+        }
+        assert!(events.borrow().is_empty());
+
+        {
+            let mut txn = doc.transact();
+            map.insert(&mut txn, "b", subdoc("a"));
+            map.remove(&mut txn, "a");
+        }
+        assert_eq!(
+            events.borrow_mut().pop(),
+            Some((
+                vec!["a".to_string()],
+                vec!["a".to_string()],
+                vec!["a".to_string()]
+            ))
+        );
+        assert_eq!(
+            guids(&doc.subdocs().cloned().collect::<Vec<_>>()),
+            vec!["a"]
+        );
+
+        {
+            let mut txn = doc.transact();
+            map.insert(&mut txn, "c", subdoc("c"));
+        }
+        events.borrow_mut().clear();
+        let mut subdoc_guids: Vec<_> = doc.subdoc_guids().map(|g| g.to_string()).collect();
+        subdoc_guids.sort();
+        assert_eq!(subdoc_guids, vec!["a", "c"]);
+
+        // remote peer receives subdocuments, but doesn't load them by default
+        let remote = Doc::with_client_id(2);
+        let remote_events = Rc::new(RefCell::new(Vec::new()));
+        let remote_events_c = remote_events.clone();
+        let _sub = remote.observe_subdocs(move |_, e| {
+            let added = guids(&e.added);
+            let removed = guids(&e.removed);
+            let loaded = guids(&e.loaded);
+            remote_events_c.borrow_mut().push((added, removed, loaded));
+        });
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+        {
+            let mut txn = remote.transact();
+            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+        }
+        assert_eq!(
+            remote_events.borrow_mut().pop(),
+            Some((vec!["a".to_string(), "c".to_string()], vec![], vec![]))
+        );
+
+        let mut txn = remote.transact();
+        let remote_map = txn.get_map("mysubdocs");
+        let remote_c = remote_map.get("c").unwrap().to_ydoc().unwrap();
+        assert!(!remote_c.should_load());
+        remote_c.load(&mut txn);
+        assert!(remote_c.should_load());
+        txn.commit();
+        assert_eq!(
+            remote_events.borrow_mut().pop(),
+            Some((vec![], vec![], vec!["c".to_string()]))
+        );
+    }
+
+    #[test]
+    fn to_json() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact();
+            txn.get_text("text").push(&mut txn, "hello");
+            txn.get_array("array").insert_range(&mut txn, 0, [1, 2]);
+            txn.get_map("map").insert(&mut txn, "key", "value");
+        }
+
+        let expected =
+            Any::from_json(r#"{"text":"hello","array":[1,2],"map":{"key":"value"}}"#).unwrap();
+        assert_eq!(doc.to_json(), expected);
+
+        // root types defined by remote updates are not typed yet
+        let remote = Doc::with_client_id(2);
+        let mut txn = remote.transact();
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+        txn.apply_update(Update::decode_v1(&update).unwrap());
+        let json = txn.to_json();
+        assert_eq!(json, Any::from_json(
+            r#"{"text":["h","e","l","l","o"],"array":[1,2],"map":{"key":"value"}}"#,
+        ).unwrap());
+    }
+
+    #[test]
+    fn encode_diff_multi() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let d3 = Doc::with_client_id(3);
+        let t1 = d1.transact().get_text("text");
+        t1.push(&mut d1.transact(), "abc");
+        let u = d1.encode_state_as_update_v1(&StateVector::default());
+        d2.transact().apply_update(Update::decode_v1(&u).unwrap());
+        t1.push(&mut d1.transact(), "def");
+        t1.remove_range(&mut d1.transact(), 0, 1);
+
+        // d2 knows "abc", d3 knows nothing: a single update must satisfy both of them
+        let svs = [d2.transact().state_vector(), d3.transact().state_vector()];
+        let update = d1.transact().encode_diff_multi_v1(&svs);
+        for doc in [&d2, &d3] {
+            let mut txn = doc.transact();
+            txn.apply_update(Update::decode_v1(&update).unwrap());
+            assert_eq!(txn.get_text("text").to_string(), "bcdef");
+        }
+
+        // restrict update to changes made by a single client
+        let t2 = d2.transact().get_text("text");
+        t2.push(&mut d2.transact(), "x");
+        let u = d2.encode_state_as_update_v1(&StateVector::default());
+        d1.transact().apply_update(Update::decode_v1(&u).unwrap());
+        assert_eq!(t1.to_string(), "bcdefx");
+
+        let txn = d1.transact();
+        let update = txn.encode_diff_for_clients_v1(&StateVector::default(), &[2]);
+        let update = Update::decode_v1(&update).unwrap();
+        assert_eq!(update.state_vector().get(&1), 0);
+        assert_eq!(update.state_vector().get(&2), 1);
+    }
+
+    #[test]
+    fn gc_compaction() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.transact().get_text("text");
+        txt.insert(&mut doc.transact_with("untracked"), 0, "abcdefghij");
+        let mut mgr = UndoManager::new(&doc, &txt);
+        // deleted items tracked by undo manager are kept from being garbage collected
+        txt.remove_range(&mut doc.transact(), 1, 1);
+        txt.remove_range(&mut doc.transact_with("untracked"), 1, 1);
+        txt.remove_range(&mut doc.transact(), 1, 1);
+
+        let blocks = |doc: &Doc| doc.store.blocks.get(&1).unwrap().len();
+        assert_eq!(blocks(&doc), 5);
+
+        // undo manager still keeps deleted items: nothing can be collected
+        let stats = doc.gc();
+        assert_eq!(stats.collected, 0);
+        assert_eq!(blocks(&doc), 5);
+
+        mgr.clear();
+        let stats = doc.gc();
+        assert_eq!(
+            stats,
+            GcStats {
+                collected: 2,
+                squashed: 2,
+                blocks: 3
+            }
+        );
+        assert_eq!(blocks(&doc), 3);
+        assert_eq!(txt.to_string(), "aefghij");
+
+        // compacted document can still be synchronized with remote peers
+        let remote = Doc::with_client_id(2);
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+        let mut txn = remote.transact();
+        txn.apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(txn.get_text("text").to_string(), "aefghij");
+    }
+
+    #[test]
+    fn stats() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.transact().get_text("text");
+        let array = doc.transact().get_array("array");
+        txt.insert(&mut doc.transact(), 0, "hello world");
+        txt.remove_range(&mut doc.transact(), 5, 6);
+        {
+            let mut txn = doc.transact();
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+            let mut map = HashMap::new();
+            map.insert("key".to_string(), "value");
+            array.push_back(&mut txn, PrelimMap::from(map));
+        }
+        array.move_to(&mut doc.transact(), 0, 3);
+
+        let remote = Doc::with_client_id(2);
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+        remote
+            .transact()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        remote
+            .transact()
+            .get_text("text")
+            .push(&mut remote.transact(), "!");
+        let update = remote.encode_state_as_update_v1(&doc.transact().state_vector());
+        doc.transact()
+            .apply_update(Update::decode_v1(&update).unwrap());
+
+        let stats = doc.stats();
+        assert_eq!(stats.blocks.get(&1), Some(&7));
+        assert_eq!(stats.blocks.get(&2), Some(&1));
+        assert_eq!(stats.total_blocks(), 8);
+        assert_eq!(stats.live_items, 7);
+        assert_eq!(stats.deleted_items, 1);
+        assert_eq!(stats.gc_blocks, 0);
+        assert_eq!(stats.moved_items, 1);
+        assert!(stats.content_size["text"] > "hello!".len());
+        assert!(stats.content_size["array"] > "key value".len());
+    }
+
+    #[test]
+    fn read_transaction() {
+        fn sync<R: ReadTxn, W: WriteTxn>(src: &R, dst: &mut W) {
+            let update = src.encode_diff_v1(&dst.state_vector());
+            dst.apply_update(Update::decode_v1(&update).unwrap())
+                .unwrap();
+        }
+
+        let d1 = Doc::with_client_id(1);
+        let updates = Rc::new(Cell::new(0));
+        let _sub = {
+            let updates = updates.clone();
+            d1.observe_update_v1(move |_, _| updates.set(updates.get() + 1))
+        };
+        d1.transact()
+            .get_text("text")
+            .push(&mut d1.transact(), "hello");
+        let before = updates.get();
+
+        // multiple read transactions can coexist and don't emit any updates
+        let r1 = d1.transact_read();
+        let r2 = d1.transact_read();
+        assert_eq!(r1.state_vector(), r2.state_vector());
+        assert_eq!(r1.snapshot(), d1.transact().snapshot());
+        assert_eq!(r2.to_json(), Any::from_json(r#"{"text":"hello"}"#).unwrap());
+
+        let d2 = Doc::with_client_id(2);
+        sync(&r1, &mut d2.transact());
+        let mut txn = d2.transact();
+        assert_eq!(txn.get_text("text").to_string(), "hello");
+        txn.get_text("text").push(&mut txn, " world");
+        sync(&txn, &mut d1.transact());
+        drop(txn);
+        drop((r1, r2));
+        assert_eq!(updates.get(), before + 1);
+        assert_eq!(d1.transact_read().to_json(), d2.transact_read().to_json());
+
+        // getters of read transactions never define new root types
+        let sv = d1.transact_read().state_vector();
+        let r = d1.transact_read();
+        assert_eq!(r.get_text("text").unwrap().to_string(), "hello world");
+        assert!(r.get_map("text").is_none());
+        assert!(r.get_map("map").is_none());
+        assert!(r.get_array("array").is_none());
+        assert!(r.get_xml_element("xml").is_none());
+        assert!(r.get_xml_text("xml").is_none());
+        drop(r);
+        let r = d1.transact_read();
+        assert_eq!(r.roots().count(), 1);
+        assert_eq!(r.state_vector(), sv);
+        assert_eq!(updates.get(), before + 1);
+    }
+
+    #[test]
+    fn rollback() {
+        let doc = Doc::with_client_id(1);
+        let updates = Rc::new(Cell::new(0));
+        let _sub = {
+            let updates = updates.clone();
+            doc.observe_update_v1(move |_, _| updates.set(updates.get() + 1))
+        };
+        let (text, map, array) = {
+            let mut txn = doc.transact();
+            let text = txn.get_text("text");
+            let map = txn.get_map("map");
+            let array = txn.get_array("array");
+            text.push(&mut txn, "hello world");
+            map.insert(&mut txn, "a", 1);
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+            (text, map, array)
+        };
+        let expected = doc.to_json();
+        let sv = doc.transact().state_vector();
+        let before = updates.get();
+
+        let mut txn = doc.transact();
+        text.insert(&mut txn, 5, ",");
+        text.remove_range(&mut txn, 7, 5);
+        text.push(&mut txn, "!");
+        map.insert(&mut txn, "a", 2);
+        map.insert(&mut txn, "b", 3);
+        map.remove(&mut txn, "a");
+        array.remove_range(&mut txn, 0, 2);
+        array.push_back(&mut txn, PrelimMap::<u32>::new());
+        assert_ne!(txn.to_json(), expected);
+        txn.rollback().unwrap();
+
+        assert_eq!(updates.get(), before);
+        assert_eq!(doc.transact().state_vector(), sv);
+        assert_eq!(doc.to_json(), expected);
+        assert_eq!(text.len(), 11);
+        assert_eq!(map.len(), 1);
+        assert_eq!(array.len(), 3);
+
+        // document is still usable after rollback
+        text.push(&mut doc.transact(), "!");
+        assert_eq!(text.to_string(), "hello world!");
+        assert_eq!(updates.get(), before + 1);
+        let remote = Doc::with_client_id(2);
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+        let mut txn = remote.transact();
+        txn.apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(txn.get_text("text").to_string(), "hello world!");
+        assert_eq!(txn.get_map("map").to_json(), map.to_json());
+        assert_eq!(txn.get_array("array").to_json(), array.to_json());
+    }
+
+    #[test]
+    fn rollback_moved_elements() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.transact().get_array("array");
+        array.insert_range(&mut doc.transact(), 0, [1, 2, 3]);
+
+        let mut txn = doc.transact();
+        array.move_to(&mut txn, 0, 3);
+        assert_eq!(txn.rollback(), Err(RollbackError::MovedElements));
+
+        // rejected rollback leaves transaction changes in place
+        assert_eq!(array.to_json(), vec![2, 3, 1].into());
+    }
+
+    #[test]
+    fn update_filter() {
+        let source = Doc::with_client_id(1);
+        source
+            .transact()
+            .get_text("text")
+            .push(&mut source.transact(), "hello");
+        let text_update = source.encode_state_as_update_v1(&StateVector::default());
+        let sv = source.transact().state_vector();
+        source
+            .transact()
+            .get_map("secret")
+            .insert(&mut source.transact(), "key", "value");
+        let map_update = source.encode_state_as_update_v1(&sv);
+
+        let mut options = Options::with_client_id(2);
+        options.update_filter = Some(UpdateFilter::new(|block| {
+            block.root != Some("secret") && block.len <= 10
+        }));
+        let doc = Doc::with_options(options);
+        let mut txn = doc.transact();
+        assert_eq!(
+            txn.try_apply_update(Update::decode_v1(&map_update).unwrap()),
+            Err(UpdateError::Rejected(ID::new(1, 5)))
+        );
+        assert_eq!(
+            txn.try_apply_update(Update::decode_v1(&text_update).unwrap()),
+            Ok(())
+        );
+        // binary updates report rejection
+        assert!(matches!(
+            txn.apply_update_v1(&map_update),
+            Err(Error::Update(_))
+        ));
+        // rejected updates are ignored by apply_update
+        txn.apply_update(Update::decode_v1(&map_update).unwrap());
+        assert_eq!(txn.get_text("text").to_string(), "hello");
+        assert_eq!(txn.state_vector().get(&1), 5);
+    }
+
+    #[test]
+    fn write_policy() {
+        let client = Doc::with_client_id(1);
+        let locked_id = Rc::new(Cell::new(None));
+        let mut options = Options::with_client_id(2);
+        options.write_policy = Some(WritePolicy::new({
+            let locked_id = locked_id.clone();
+            move |target| match locked_id.get() {
+                None => true, // initial state is created before the policy takes place
+                Some(id) => target.root != "config" && !target.nested.contains(&id),
+            }
+        }));
+        let server = Doc::with_options(options);
+        let sync = |txn: &mut Transaction| {
+            let update = client.encode_state_as_update_v1(&txn.state_vector());
+            txn.try_apply_update(Update::decode_v1(&update).unwrap())
+        };
+
+        let mut c = client.transact();
+        let config = c.get_map("config");
+        config.insert(&mut c, "mode", "strict");
+        let data = c.get_map("data");
+        data.insert(&mut c, "locked", PrelimMap::<bool>::new());
+        data.insert(&mut c, "open", PrelimMap::<bool>::new());
+        let locked = data.get("locked").unwrap().to_ymap().unwrap();
+        let open = data.get("open").unwrap().to_ymap().unwrap();
+        locked.insert(&mut c, "a", true);
+        c.commit();
+        let mut txn = server.transact();
+        assert_eq!(sync(&mut txn), Ok(()));
+        locked_id.set(locked.as_ref().id());
+
+        // writes outside of protected collections are allowed
+        let mut c = client.transact();
+        open.insert(&mut c, "b", true);
+        data.insert(&mut c, "c", true);
+        c.commit();
+        assert_eq!(sync(&mut txn), Ok(()));
+
+        // insert into read-only root type
+        let mut c = client.transact();
+        config.insert(&mut c, "mode", "relaxed");
+        c.commit();
+        let denied = ID::new(1, c.state_vector().get(&1) - 1);
+        assert_eq!(sync(&mut txn), Err(UpdateError::Denied(denied)));
+        let server_config = txn.get_map("config");
+        assert_eq!(server_config.get("mode"), Some("strict".into()));
+
+        // the same update with unrelated change is still rejected as a whole
+        let mut c = client.transact();
+        open.insert(&mut c, "d", true);
+        c.commit();
+        assert_eq!(sync(&mut txn), Err(UpdateError::Denied(denied)));
+        assert_eq!(txn.state_vector().get(&1), denied.clock);
+    }
+
+    #[test]
+    fn write_policy_subtree() {
+        let client = Doc::with_client_id(1);
+        let mut c = client.transact();
+        let data = c.get_map("data");
+        data.insert(&mut c, "locked", PrelimMap::<bool>::new());
+        let locked = data.get("locked").unwrap().to_ymap().unwrap();
+        locked.insert(&mut c, "a", PrelimMap::<bool>::new());
+        let nested = locked.get("a").unwrap().to_ymap().unwrap();
+        nested.insert(&mut c, "b", true);
+        c.commit();
+        let locked_id = locked.as_ref().id().unwrap();
+        let init = client.encode_state_as_update_v1(&StateVector::default());
+
+        let mut options = Options::with_client_id(2);
+        options.write_policy = Some(WritePolicy::new(move |target| {
+            !target.deleted || !target.nested.contains(&locked_id)
+        }));
+        let server = Doc::with_options(options);
+        let mut txn = server.transact();
+        assert_eq!(
+            txn.try_apply_update(Update::decode_v1(&init).unwrap()),
+            Ok(())
+        );
+
+        // deletions anywhere within a protected subtree are denied
+        let sv = txn.state_vector();
+        let mut c = client.transact();
+        nested.remove(&mut c, "b");
+        c.commit();
+        let update = client.encode_state_as_update_v1(&sv);
+        assert_eq!(
+            txn.try_apply_update(Update::decode_v1(&update).unwrap()),
+            Err(UpdateError::Denied(ID::new(1, 2)))
+        );
+        let data = txn.get_map("data");
+        let locked = data.get("locked").unwrap().to_ymap().unwrap();
+        let nested = locked.get("a").unwrap().to_ymap().unwrap();
+        assert_eq!(nested.get("b"), Some(true.into()));
+    }
+
+    #[test]
+    fn doc_limits() {
+        let remote = |client_id, content: &str| {
+            let doc = Doc::with_client_id(client_id);
+            let text = doc.transact().get_text("text");
+            text.push(&mut doc.transact(), content);
+            doc.encode_state_as_update_v1(&StateVector::default())
+        };
+
+        let mut options = Options::with_client_id(1);
+        options.limits = DocLimits {
+            max_blocks: Some(3),
+            max_content_size: Some(20),
+            max_clients: Some(2),
+        };
+        let doc = Doc::with_options(options);
+        let mut txn = doc.transact();
+        let text = txn.get_text("text");
+        text.push(&mut txn, "local changes are not limited");
+
+        let update = Update::decode_v1(&remote(2, "hello")).unwrap();
+        assert_eq!(
+            txn.try_apply_update(update),
+            Err(UpdateError::LimitExceeded {
+                kind: LimitKind::ContentSize,
+                limit: 20,
+                size: 36
+            })
+        );
+        drop(txn);
+
+        let mut options = Options::with_client_id(1);
+        options.limits.max_clients = Some(2);
+        options.limits.max_blocks = Some(2);
+        let doc = Doc::with_options(options);
+        let mut txn = doc.transact();
+        let update = Update::decode_v1(&remote(2, "a")).unwrap();
+        assert_eq!(txn.try_apply_update(update), Ok(()));
+        // applying the same update again doesn't add any new blocks
+        let update = Update::decode_v1(&remote(2, "a")).unwrap();
+        assert_eq!(txn.try_apply_update(update), Ok(()));
+        let update = Update::decode_v1(&remote(3, "b")).unwrap();
+        assert_eq!(txn.try_apply_update(update), Ok(()));
+        let update = Update::decode_v1(&remote(4, "c")).unwrap();
+        assert_eq!(
+            txn.try_apply_update(update),
+            Err(UpdateError::LimitExceeded {
+                kind: LimitKind::Blocks,
+                limit: 2,
+                size: 3
+            })
+        );
+        assert_eq!(txn.get_text("text").len(), 2);
+
+        // limits are enforced on binary updates as well
+        match txn.apply_update_v1(&remote(4, "c")) {
+            Err(Error::Update(e)) => assert_eq!(
+                e.downcast_ref::<UpdateError>(),
+                Some(&UpdateError::LimitExceeded {
+                    kind: LimitKind::Blocks,
+                    limit: 2,
+                    size: 3
+                })
+            ),
+            other => panic!("expected exceeded limit, got {:?}", other),
+        }
+        assert_eq!(txn.get_text("text").len(), 2);
+    }
+
+    #[test]
+    fn apply_delete_set() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.transact().get_text("text");
+        t1.push(&mut d1.transact(), "hello world");
+        let u = d1.encode_state_as_update_v1(&StateVector::default());
+        d2.transact().apply_update(Update::decode_v1(&u).unwrap());
+
+        let ds = {
+            let mut txn = d1.transact();
+            t1.remove_range(&mut txn, 5, 6);
+            txn.delete_set.encode_v1()
+        };
+        let mut txn = d2.transact();
+        txn.apply_delete_set(DeleteSet::decode_v1(&ds).unwrap())
+            .unwrap();
+        assert_eq!(txn.get_text("text").to_string(), "hello");
+
+        // deletions of blocks that are not known yet are applied once they arrive
+        let d3 = Doc::with_client_id(3);
+        let mut txn = d3.transact();
+        txn.apply_delete_set(DeleteSet::decode_v1(&ds).unwrap())
+            .unwrap();
+        txn.apply_update(Update::decode_v1(&u).unwrap());
+        assert_eq!(txn.get_text("text").to_string(), "hello");
+    }
+
+    #[test]
+    fn duplicate_and_load_from() {
+        let doc = Doc::with_options(Options {
+            skip_gc: true,
+            ..Options::with_client_id(1)
+        });
+        let text = doc.transact().get_text("text");
+        text.push(&mut doc.transact(), "hello");
+
+        let fork = doc.duplicate();
+        assert_ne!(fork.client_id, doc.client_id);
+        assert_ne!(fork.guid(), doc.guid());
+        assert!(fork.store.options.skip_gc);
+
+        // changes made on a fork are independent of the original
+        let fork_text = fork.transact().get_text("text");
+        assert_eq!(fork_text.to_string(), "hello");
+        fork_text.push(&mut fork.transact(), " world");
+        assert_eq!(text.to_string(), "hello");
+
+        // ... but both documents can still be merged together
+        let update = fork.encode_state_as_update_v1(&doc.transact().state_vector());
+        doc.transact()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(text.to_string(), "hello world");
+
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+        let loaded = Doc::load_from(Update::decode_v1(&update).unwrap());
+        assert_eq!(
+            loaded.transact().get_text("text").to_string(),
+            "hello world"
+        );
+    }
+
+    #[test]
+    fn memory_usage() {
+        fn measure(doc: &Doc) -> MemoryUsage {
+            let mut usage = MemoryUsage::default();
+            let store = &doc.store;
+            for (_, blocks) in store.blocks.iter() {
+                usage += blocks.memory_usage();
+            }
+            for (name, branch) in store.types.iter() {
+                usage.indexes +=
+                    name.len() + std::mem::size_of::<Branch>() + branch.index_heap_size();
+            }
+            usage
+        }
+
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.transact().get_text("text");
+        let t2 = d2.transact().get_text("text");
+        let empty = d1.memory_usage();
+        assert_eq!(empty.blocks, 0);
+
+        let chunk = "lorem ipsum ".repeat(100);
+        t1.push(&mut d1.transact(), &chunk);
+        let usage = d1.memory_usage();
+        assert!(usage.content >= chunk.len());
+        assert_eq!(usage, measure(&d1));
+
+        // concurrent insert splits the blocks of the other client
+        t2.push(&mut d2.transact(), "hello");
+        exchange_updates(&[&d1, &d2]);
+        t1.insert(&mut d1.transact(), 3, "world");
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(d1.memory_usage(), measure(&d1));
+        assert_eq!(d2.memory_usage(), measure(&d2));
+        assert!(d2.memory_usage().total() > usage.total());
+
+        // deleted content is garbage collected
+        let len = t1.len();
+        t1.remove_range(&mut d1.transact(), 0, len);
+        let after_delete = d1.memory_usage();
+        assert_eq!(after_delete, measure(&d1));
+        assert!(after_delete.content < chunk.len());
+    }
+
+    #[test]
+    fn deterministic_options() {
+        fn run() -> (Rc<str>, Rc<str>, Vec<u8>) {
+            let doc = Doc::with_options(Options::deterministic(1));
+            let text = doc.transact().get_text("text");
+            text.push(&mut doc.transact(), "hello");
+            let mut fork = doc.duplicate();
+            fork.transact()
+                .get_text("text")
+                .push(&mut fork.transact(), " world");
+            fork.regenerate_client_id();
+            fork.transact()
+                .get_text("text")
+                .push(&mut fork.transact(), "!");
+            let update = fork.encode_state_as_update_v1(&StateVector::default());
+            (doc.guid(), fork.guid(), update)
+        }
+
+        let (guid, fork_guid, update) = run();
+        assert_ne!(guid, fork_guid);
+        assert_eq!(run(), (guid, fork_guid, update.clone()));
+
+        let doc = Doc::new();
+        doc.transact().apply_update_v1(&update).unwrap();
+        let sv = doc.transact().state_vector();
+        let mut clients: Vec<_> = sv.iter().map(|(&client, _)| client).collect();
+        clients.sort();
+        assert_eq!(clients, vec![1, 4, 7]);
+    }
+
+    #[test]
+    fn set_client_id() {
+        let mut doc = Doc::with_client_id(1);
+        let text = doc.transact().get_text("text");
+        text.push(&mut doc.transact(), "hello");
+
+        let mut remote = Doc::with_client_id(2);
+        let remote_text = remote.transact().get_text("text");
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+        remote.transact().apply_update_v1(&update).unwrap();
+
+        // identifiers used by other replicas cannot be reused
+        assert_eq!(remote.set_client_id(1), Err(ClientIdError::AlreadyInUse(1)));
+        assert_eq!(remote.set_client_id(2), Ok(()));
+
+        doc.set_client_id(3).unwrap();
+        assert_eq!(doc.client_id, 3);
+        assert_eq!(doc.current_client_id(), 3);
+        text.push(&mut doc.transact(), " world");
+
+        let new_id = remote.regenerate_client_id();
+        assert!(new_id != 1 && new_id != 2);
+        assert_eq!(remote.current_client_id(), new_id);
+
+        let update = doc.encode_state_as_update_v1(&remote.transact().state_vector());
+        remote.transact().apply_update_v1(&update).unwrap();
+        assert_eq!(remote_text.to_string(), "hello world");
+        assert_eq!(remote.transact().state_vector().get(&3), 6);
+    }
+
+    #[test]
+    fn client_id_collision() {
+        let collisions = Rc::new(RefCell::new(Vec::new()));
+        let c = collisions.clone();
+        let d1 = Doc::with_options(Options {
+            on_client_id_collision: Some(CollisionHandler::new(move |old, new| {
+                c.borrow_mut().push((old, new))
+            })),
+            ..Options::with_client_id(1)
+        });
+        let d2 = Doc::with_client_id(1);
+        let t1 = d1.transact().get_text("text");
+        let t2 = d2.transact().get_text("text");
+        t1.push(&mut d1.transact(), "a");
+        t2.push(&mut d2.transact(), "bb");
+
+        // echo of d1's own changes is not a collision
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+        d1.transact().apply_update_v1(&update).unwrap();
+        assert!(collisions.borrow().is_empty());
+
+        let update = d2.encode_state_as_update_v1(&StateVector::default());
+        d1.transact().apply_update_v1(&update).unwrap();
+        let new_id = d1.current_client_id();
+        assert_ne!(new_id, 1);
+        assert_eq!(collisions.borrow().as_slice(), &[(1, new_id)]);
+
+        // new changes are created under a regenerated client id
+        t1.push(&mut d1.transact(), "c");
+        assert_eq!(d1.transact().state_vector().get(&new_id), 1);
+    }
+
+    #[test]
+    fn roots() {
+        let d1 = Doc::with_client_id(1);
+        {
+            let mut txn = d1.transact();
+            txn.get_text("text").push(&mut txn, "a");
+            txn.get_map("map").insert(&mut txn, "key", 1);
+            txn.get_array("array").push_back(&mut txn, 1);
+            txn.get_xml_fragment("xml");
+        }
+        let txn = d1.transact_read();
+        let roots: Vec<_> = d1.roots(&txn).collect();
+        assert_eq!(
+            roots,
+            vec![
+                ("array", TypeRef::Array),
+                ("map", TypeRef::Map),
+                ("text", TypeRef::Text),
+                ("xml", TypeRef::XmlFragment),
+            ]
+        );
+
+        // roots defined by remote updates have unknown kinds until they're accessed
+        let d2 = Doc::with_client_id(2);
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+        d2.transact().apply_update_v1(&update).unwrap();
+        let txn = d2.transact();
+        let roots: Vec<_> = txn.roots().collect();
+        assert_eq!(
+            roots,
+            vec![
+                ("array", TypeRef::Undefined),
+                ("map", TypeRef::Undefined),
+                ("text", TypeRef::Undefined),
+            ]
+        );
+        drop(txn);
+        d2.transact().get_text("text");
+        let txn = d2.transact_read();
+        assert_eq!(txn.roots().nth(2), Some(("text", TypeRef::Text)));
+    }
+
+    #[test]
+    fn try_get_root() {
+        let d1 = Doc::with_client_id(1);
+        let updates = Rc::new(Cell::new(0));
+        let _sub = {
+            let updates = updates.clone();
+            d1.observe_update_v1(move |_, _| updates.set(updates.get() + 1))
+        };
+        {
+            let txn = d1.transact_read();
+            assert!(!txn.contains_root("text"));
+            assert!(txn.try_get_text("text").is_none());
+        }
+        {
+            let txn = d1.transact();
+            assert!(txn.try_get_map("map").is_none());
+        }
+        // read paths don't define new root types nor produce any updates
+        assert_eq!(d1.transact_read().roots().len(), 0);
+        assert_eq!(updates.get(), 0);
+
+        {
+            let mut txn = d1.transact();
+            txn.get_text("text").push(&mut txn, "hello");
+        }
+        assert_eq!(updates.get(), 1);
+        let txn = d1.transact_read();
+        assert!(txn.contains_root("text"));
+        let text = txn.try_get_text("text").unwrap();
+        assert_eq!(text.to_string(), "hello");
+        // existing root type of another kind is not reinterpreted
+        assert!(txn.try_get_map("text").is_none());
+        drop(txn);
+
+        // roots defined by remote updates are assigned a requested kind
+        let d2 = Doc::with_client_id(2);
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+        d2.transact().apply_update_v1(&update).unwrap();
+        let txn = d2.transact_read();
+        assert!(txn.contains_root("text"));
+        let text = txn.try_get_text("text").unwrap();
+        assert_eq!(text.to_string(), "hello");
+        assert!(txn.try_get_array("text").is_none());
+        assert_eq!(txn.roots().next(), Some(("text", TypeRef::Text)));
+    }
+
+    #[test]
+    fn rename_root() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        {
+            let mut txn = d1.transact();
+            txn.get_text("title").push(&mut txn, "hello");
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        {
+            let mut txn = d1.transact();
+            assert_eq!(
+                txn.rename_root("missing", "name"),
+                Err(RenameRootError::NotFound("missing".into()))
+            );
+            assert_eq!(
+                txn.rename_root("title", ROOT_ALIASES),
+                Err(RenameRootError::Reserved)
+            );
+            txn.rename_root("title", "name").unwrap();
+            assert_eq!(
+                txn.rename_root("title", "name"),
+                Err(RenameRootError::AlreadyExists("name".into()))
+            );
+            // aliases are followed when renaming already renamed types
+            txn.rename_root("name", "label").unwrap();
+            txn.get_text("label").push(&mut txn, " world");
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        // alias is replicated, while peers using an old name are not affected
+        let mut txn = d2.transact();
+        assert_eq!(txn.get_text("name").to_string(), "hello world");
+        assert_eq!(
+            txn.try_get_text("label").unwrap().to_string(),
+            "hello world"
+        );
+        assert_eq!(txn.get_text("title").to_string(), "hello world");
+        assert!(txn.contains_root("name"));
+        txn.get_text("name").push(&mut txn, "!");
+        drop(txn);
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(d1.transact().get_text("title").to_string(), "hello world!");
+    }
+
+    #[test]
+    fn integration_report() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.transact().get_text("text");
+        let t2 = d2.transact().get_text("text");
+        let m1 = d1.transact().get_map("map");
+        let m2 = d2.transact().get_map("map");
+        t1.push(&mut d1.transact(), "ab");
+        exchange_updates(&[&d1, &d2]);
+        let sv1 = d1.transact().state_vector();
+        let sv2 = d2.transact().state_vector();
+
+        // concurrent changes: the same text position and the same map key
+        t1.insert(&mut d1.transact(), 1, "x");
+        m1.insert(&mut d1.transact(), "key", "d1");
+        t2.insert(&mut d2.transact(), 1, "y");
+        t2.remove_range(&mut d2.transact(), 0, 1);
+        m2.insert(&mut d2.transact(), "key", "d2");
+        let u1 = d1.encode_state_as_update_v1(&sv2);
+        let u2 = d2.encode_state_as_update_v1(&sv1);
+
+        let report = d1
+            .transact()
+            .apply_update_with_report(Update::decode_v1(&u2).unwrap())
+            .unwrap();
+        assert!(report.has_conflicts());
+        // "y" has been placed after concurrently inserted "x"
+        assert_eq!(report.reordered, 1);
+        assert_eq!(report.overridden.len(), 1);
+        let overridden = &report.overridden[0];
+        assert_eq!(overridden.path, vec![PathSegment::Key("map".into())]);
+        assert_eq!(overridden.key.as_ref(), "key");
+        assert_eq!(overridden.previous, ID::new(1, 3));
+        assert_eq!(overridden.current, ID::new(2, 1));
+        assert!(report.inserted.contains(&ID::new(2, 0)));
+        assert!(report.inserted.contains(&ID::new(2, 1)));
+        assert!(report.deleted.contains(&ID::new(1, 0)));
+        assert!(report.deleted.contains(&ID::new(1, 3)));
+        assert!(!report.deleted.contains(&ID::new(1, 1)));
+        assert_eq!(t1.to_string(), "xyb");
+
+        // d1's map value loses, so nothing visible gets overridden on d2
+        let report = d2
+            .transact()
+            .apply_update_with_report(Update::decode_v1(&u1).unwrap())
+            .unwrap();
+        assert_eq!(report.reordered, 0);
+        assert!(report.overridden.is_empty());
+        assert!(!report.has_conflicts());
+        assert_eq!(t2.to_string(), "xyb");
+        assert_eq!(m2.get("key").unwrap().to_string(), "d2");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_export() {
+        use crate::types::Value;
+
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact();
+            let map = txn.get_map("map");
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "b", crate::PrelimArray::from(vec![true, false]));
+        }
+
+        let actual = serde_json::to_value(doc.to_json()).unwrap();
+        assert_eq!(
+            actual,
+            serde_json::json!({ "map": { "a": 1.0, "b": [true, false] } })
+        );
+
+        let value = doc.transact().get_map("map").get("b").unwrap();
+        assert_eq!(serde_json::to_string(&value).unwrap(), "[true,false]");
+
+        // integers are deserialized the same way as by Any::from_json, unless they're outside
+        // of a safe integer range
+        let value: Value = serde_json::from_str(r#"{"x":[1,"y",9007199254740993]}"#).unwrap();
+        let values = vec![
+            Any::Number(1.0),
+            Any::String("y".into()),
+            Any::BigInt(9007199254740993),
+        ];
+        let mut map = HashMap::new();
+        map.insert("x".to_string(), Any::Array(values.into()));
+        let expected = Any::Map(Box::new(map));
+        assert_eq!(value, Value::Any(expected));
+    }
 }
@@ -0,0 +1,188 @@
+use crate::{Doc, StateVector, Transaction};
+use lib0::error::Error;
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Sender};
+
+type Job = Box<dyn FnOnce(&Doc) + Send>;
+
+/// A thread-safe handle to a [Doc]. Since documents and their shared types are built on top of
+/// non-thread-safe pointers, a document itself never leaves the dedicated thread, on which it has
+/// been created. Instead, all operations are sent as closures to that thread and executed there
+/// one after another, in the order in which they were submitted. This way transactions issued from
+/// multiple threads are serialized without any need for global locks.
+///
+/// [DocHandle] can be cloned and shared across threads. A document thread is stopped once all of
+/// its handles are dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, DocHandle};
+/// use std::thread;
+///
+/// let handle = DocHandle::spawn(|| Doc::with_client_id(1));
+/// let threads: Vec<_> = (0..4).map(|_| {
+///     let handle = handle.clone();
+///     thread::spawn(move || {
+///         handle.transact(|txn| {
+///             let text = txn.get_text("text");
+///             text.push(txn, "a");
+///         })
+///     })
+/// }).collect();
+/// for t in threads {
+///     t.join().unwrap();
+/// }
+///
+/// let text = handle.transact(|txn| txn.get_text("text").to_string());
+/// assert_eq!(text, "aaaa");
+/// ```
+#[derive(Debug, Clone)]
+pub struct DocHandle {
+    sender: Sender<Job>,
+}
+
+impl DocHandle {
+    /// Spawns a new thread and creates a document on it using a given `init` function.
+    pub fn spawn<F>(init: F) -> Self
+    where
+        F: FnOnce() -> Doc + Send + 'static,
+    {
+        let (sender, receiver) = channel::<Job>();
+        std::thread::spawn(move || {
+            let doc = init();
+            while let Ok(job) = receiver.recv() {
+                job(&doc);
+            }
+        });
+        DocHandle { sender }
+    }
+
+    /// Executes a given function on a document's thread, blocking current thread until it
+    /// completes and returning its result. If `f` panics, the panic is propagated to the caller.
+    pub fn with_doc<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Doc) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = channel::<Result<R, Box<dyn Any + Send>>>();
+        let job: Job = Box::new(move |doc| {
+            let result = catch_unwind(AssertUnwindSafe(|| f(doc)));
+            let _ = tx.send(result);
+        });
+        self.sender
+            .send(job)
+            .expect("document thread has been stopped");
+        match rx.recv().expect("document thread has been stopped") {
+            Ok(result) => result,
+            Err(panic) => resume_unwind(panic),
+        }
+    }
+
+    /// Executes a given function within a scope of a new transaction created on a document's
+    /// thread. Transaction is committed once `f` completes. Returns a result of `f`.
+    pub fn transact<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Transaction) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.with_doc(move |doc| {
+            let mut txn = doc.transact();
+            f(&mut txn)
+        })
+    }
+
+    /// Applies a lib0 v1 encoded `update` onto a document.
+    pub fn apply_update_v1(&self, update: Vec<u8>) -> Result<(), Error> {
+        self.with_doc(move |doc| doc.transact().apply_update_v1(update.as_slice()))
+    }
+
+    /// Encodes all changes of a document, which have not been observed by a remote peer
+    /// described by a given `state_vector`, using lib0 v1 encoding.
+    pub fn encode_state_as_update_v1(&self, state_vector: StateVector) -> Vec<u8> {
+        self.with_doc(move |doc| doc.encode_state_as_update_v1(&state_vector))
+    }
+
+    /// Returns a state vector of a document.
+    pub fn state_vector(&self) -> StateVector {
+        self.transact(|txn| txn.state_vector())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Doc, DocHandle, Options, StateVector, UpdateError, UpdateFilter, ID};
+    use lib0::error::Error;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn concurrent_transactions() {
+        assert_send_sync::<DocHandle>();
+
+        let handle = DocHandle::spawn(|| Doc::with_client_id(1));
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        handle.transact(move |txn| {
+                            let array = txn.get_array("array");
+                            array.push_back(txn, i);
+                        });
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let len = handle.transact(|txn| txn.get_array("array").len());
+        assert_eq!(len, 40);
+
+        // replicate state to another document handle
+        let remote = DocHandle::spawn(|| Doc::with_client_id(2));
+        let update = handle.encode_state_as_update_v1(remote.state_vector());
+        remote.apply_update_v1(update).unwrap();
+        assert_eq!(remote.state_vector(), handle.state_vector());
+        assert!(remote.apply_update_v1(vec![1, 2, 3]).is_err());
+
+        // there's nothing new to send between synchronized documents
+        let update = remote.encode_state_as_update_v1(handle.state_vector());
+        assert_eq!(update, vec![0, 0]);
+    }
+
+    #[test]
+    fn rejected_update() {
+        let source = DocHandle::spawn(|| Doc::with_client_id(1));
+        source.transact(|txn| {
+            let text = txn.get_text("secret");
+            text.push(txn, "hello");
+        });
+        let update = source.encode_state_as_update_v1(StateVector::default());
+
+        let handle = DocHandle::spawn(|| {
+            let mut options = Options::with_client_id(2);
+            options.update_filter = Some(UpdateFilter::new(|block| block.root != Some("secret")));
+            Doc::with_options(options)
+        });
+        match handle.apply_update_v1(update) {
+            Err(Error::Update(e)) => assert_eq!(
+                e.downcast_ref::<UpdateError>(),
+                Some(&UpdateError::Rejected(ID::new(1, 0)))
+            ),
+            other => panic!("expected rejected update, got {:?}", other),
+        }
+        assert_eq!(handle.state_vector(), StateVector::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "boom")]
+    fn panic_propagation() {
+        let handle = DocHandle::spawn(Doc::new);
+        handle.with_doc(|_| panic!("boom"));
+    }
+}
@@ -1,47 +1,89 @@
-use crate::{DeleteSet, StateVector, Transaction};
+use crate::types::Value;
+use crate::{DeleteSet, DocRef, Origin, StateVector, Transaction};
 use rand::RngCore;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::ptr::NonNull;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
 
 #[repr(transparent)]
-pub(crate) struct EventHandler<T>(Box<Subscriptions<T>>);
+pub(crate) struct EventHandler<T>(RefCell<Subscriptions<T>>);
 
 pub type SubscriptionId = u32;
 
-type Subscriptions<T> = HashMap<SubscriptionId, Box<dyn Fn(&Transaction, &T) -> ()>>;
+type Subscriptions<T> = HashMap<SubscriptionId, Subscriber<T>>;
+
+type Callback<T> = Box<dyn Fn(&Transaction, &T)>;
+
+/// Callback registered in [EventHandler] together with a liveness token of a [Subscription]
+/// guard. Callback stays active as long as any of the guards exists or once subscription has been
+/// detached from its guards (see: [Subscription::into]).
+struct Subscriber<T> {
+    token: Weak<()>,
+    detached: Arc<AtomicBool>,
+    callback: Callback<T>,
+}
+
+impl<T> Subscriber<T> {
+    fn is_alive(&self) -> bool {
+        self.token.strong_count() != 0 || self.detached.load(Ordering::Acquire)
+    }
+}
 
 impl<T> EventHandler<T> {
     pub fn new() -> Self {
-        EventHandler(Box::new(Subscriptions::new()))
+        EventHandler(RefCell::new(Subscriptions::new()))
     }
 
     pub fn subscribe<F>(&mut self, f: F) -> Subscription<T>
     where
         F: Fn(&Transaction, &T) -> () + 'static,
     {
+        let subscribers = self.0.get_mut();
+        subscribers.retain(|_, s| s.is_alive());
+
         let mut rng = rand::thread_rng();
         let id = rng.next_u32();
-        self.0.insert(id, Box::new(f));
-        let subscriptions = NonNull::from(self.0.as_mut());
-        Subscription { id, subscriptions }
+        let token = Arc::new(());
+        let detached = Arc::new(AtomicBool::new(false));
+        let subscriber = Subscriber {
+            token: Arc::downgrade(&token),
+            detached: detached.clone(),
+            callback: Box::new(f),
+        };
+        subscribers.insert(id, subscriber);
+        Subscription::new(id, token, detached)
     }
 
     pub fn unsubscribe(&mut self, subscription_id: u32) {
-        self.0.remove(&subscription_id);
+        self.0.get_mut().remove(&subscription_id);
     }
 
     pub fn publish(&self, txn: &Transaction, arg: &T) {
-        for f in self.0.values() {
-            f(txn, arg);
+        self.cleanup();
+        for s in self.0.borrow().values() {
+            if s.is_alive() {
+                (s.callback)(txn, arg);
+            }
         }
     }
 
     pub fn has_subscribers(&self) -> bool {
-        !self.0.is_empty()
+        self.cleanup();
+        self.0.borrow().values().any(Subscriber::is_alive)
+    }
+
+    /// Removes callbacks, which subscriptions have been dropped. Skipped when called from within
+    /// a callback published by the same handler: these will be removed on the next occasion.
+    fn cleanup(&self) {
+        if let Ok(mut subscribers) = self.0.try_borrow_mut() {
+            subscribers.retain(|_, s| s.is_alive());
+        }
     }
 
     fn subscription_count(&self) -> usize {
-        self.0.len()
+        self.0.borrow().values().filter(|s| s.is_alive()).count()
     }
 }
 
@@ -53,23 +95,103 @@ impl<T> Default for EventHandler<T> {
 
 /// A subscription handle to a custom user-defined callback for an event handler. When dropped,
 /// it will unsubscribe corresponding callback.
+///
+/// Subscription can be cloned: in that case callback will be unsubscribed once the last clone
+/// is dropped. It can also be downgraded into a [WeakSubscription], which doesn't keep callback
+/// alive. Subscription handles don't reference event handlers directly, so they can be safely
+/// dropped after the document they originate from and moved across threads.
 pub struct Subscription<T> {
     id: SubscriptionId,
-    subscriptions: NonNull<Subscriptions<T>>,
+    token: Arc<()>,
+    detached: Arc<AtomicBool>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T> Subscription<T> {
+    fn new(id: SubscriptionId, token: Arc<()>, detached: Arc<AtomicBool>) -> Self {
+        Subscription {
+            id,
+            token,
+            detached,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns an identifier of this subscription.
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Reinterprets current subscription as a subscription to events of another type. Used when
+    /// callbacks of higher level events are registered in the handlers of lower level ones.
+    pub(crate) fn cast<U>(self) -> Subscription<U> {
+        Subscription::new(self.id, self.token, self.detached)
+    }
+
+    /// Creates a weak reference to this subscription, which doesn't prevent its callback from
+    /// being unsubscribed.
+    pub fn downgrade(&self) -> WeakSubscription<T> {
+        WeakSubscription {
+            id: self.id,
+            token: Arc::downgrade(&self.token),
+            detached: self.detached.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Subscription<T> {
+    fn clone(&self) -> Self {
+        Subscription::new(self.id, self.token.clone(), self.detached.clone())
+    }
 }
 
+impl<T> std::fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Subscription({})", self.id)
+    }
+}
+
+/// Converts subscription into its identifier. Callback will no longer be unsubscribed
+/// automatically: it stays active until unsubscribed manually using returned identifier, even
+/// after all clones of this subscription have been dropped. Weak references to a converted
+/// subscription can no longer be upgraded.
 impl<T> Into<SubscriptionId> for Subscription<T> {
     fn into(self) -> SubscriptionId {
-        let id = self.id;
-        std::mem::forget(self);
-        id
+        self.detached.store(true, Ordering::Release);
+        self.id
     }
 }
 
-impl<T> Drop for Subscription<T> {
-    fn drop(&mut self) {
-        let subs = unsafe { self.subscriptions.as_mut() };
-        subs.remove(&self.id);
+/// A weak reference to a [Subscription], which can be obtained using [Subscription::downgrade].
+pub struct WeakSubscription<T> {
+    id: SubscriptionId,
+    token: Weak<()>,
+    detached: Arc<AtomicBool>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T> WeakSubscription<T> {
+    /// Returns an identifier of a referenced subscription.
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Returns a [Subscription] if its callback is still subscribed.
+    pub fn upgrade(&self) -> Option<Subscription<T>> {
+        let token = self.token.upgrade()?;
+        Some(Subscription::new(self.id, token, self.detached.clone()))
+    }
+}
+
+impl<T> Clone for WeakSubscription<T> {
+    fn clone(&self) -> Self {
+        WeakSubscription {
+            id: self.id,
+            token: self.token.clone(),
+            detached: self.detached.clone(),
+            _marker: PhantomData,
+        }
     }
 }
 
@@ -79,11 +201,13 @@ pub struct UpdateEvent {
     /// An update that's about to be applied. Update contains information about all inserted blocks,
     /// which have been send from a remote peer.
     pub update: Vec<u8>,
+    /// An origin of a transaction which produced this update, if it has been provided.
+    pub origin: Option<Origin>,
 }
 
 impl UpdateEvent {
-    pub(crate) fn new(update: Vec<u8>) -> Self {
-        UpdateEvent { update }
+    pub(crate) fn new(update: Vec<u8>, origin: Option<Origin>) -> Self {
+        UpdateEvent { update, origin }
     }
 }
 
@@ -92,11 +216,26 @@ pub struct AfterTransactionEvent {
     pub before_state: StateVector,
     pub after_state: StateVector,
     pub delete_set: DeleteSet,
+    /// An origin of a committed transaction, if it has been provided.
+    pub origin: Option<Origin>,
+    /// Shared types, which have been modified within the scope of a committed transaction.
+    pub changed_parent_types: Vec<Value>,
+}
+
+/// Event triggered after a transaction has been committed, if any subdocuments have been added,
+/// removed or requested to load within its scope.
+pub struct SubdocsEvent {
+    /// Subdocuments inserted into shared collections of a parent document.
+    pub added: Vec<DocRef>,
+    /// Subdocuments removed from shared collections of a parent document.
+    pub removed: Vec<DocRef>,
+    /// Subdocuments which are expected to be loaded (see: [Doc::load]).
+    pub loaded: Vec<DocRef>,
 }
 
 #[cfg(test)]
 mod test {
-    use crate::event::EventHandler;
+    use crate::event::{EventHandler, SubscriptionId};
     use crate::Doc;
     use std::sync::atomic::{AtomicU32, Ordering};
     use std::sync::Arc;
@@ -135,4 +274,55 @@ mod test {
         assert_eq!(s1_state.load(Ordering::Acquire), 2);
         assert_eq!(s2_state.load(Ordering::Acquire), 4);
     }
+
+    #[test]
+    fn subscription_clone_and_downgrade() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let doc = Doc::new();
+        let txn = doc.transact();
+        let mut eh: EventHandler<u32> = EventHandler::new();
+        let state = Arc::new(AtomicU32::new(0));
+
+        let s = state.clone();
+        let sub = eh.subscribe(move |_, value| s.store(*value, Ordering::Release));
+        assert_send_sync(&sub);
+        let weak = sub.downgrade();
+        let clone = sub.clone();
+        assert_eq!(clone.id(), weak.id());
+
+        drop(sub);
+        eh.publish(&txn, &1);
+        assert_eq!(state.load(Ordering::Acquire), 1);
+        assert!(weak.upgrade().is_some());
+
+        // last handle has been dropped
+        drop(clone);
+        eh.publish(&txn, &2);
+        assert_eq!(state.load(Ordering::Acquire), 1);
+        assert!(weak.upgrade().is_none());
+        assert!(!eh.has_subscribers());
+
+        // dropped callbacks are removed from the handler without waiting for the next subscription
+        assert!(eh.0.borrow().is_empty());
+
+        // converted subscription stays active until unsubscribed manually
+        let s = state.clone();
+        let id: SubscriptionId = eh
+            .subscribe(move |_, value| s.store(*value, Ordering::Release))
+            .into();
+        eh.publish(&txn, &3);
+        assert_eq!(state.load(Ordering::Acquire), 3);
+        eh.unsubscribe(id);
+        eh.publish(&txn, &4);
+        assert_eq!(state.load(Ordering::Acquire), 3);
+        assert!(eh.0.borrow().is_empty());
+        // callback and its liveness state have been released
+        assert_eq!(Arc::strong_count(&state), 1);
+
+        // subscription can outlive its event handler
+        let sub = eh.subscribe(|_, _| {});
+        drop(eh);
+        drop(sub);
+    }
 }
@@ -0,0 +1,420 @@
+use crate::block::{ItemContent, Prelim};
+use crate::types::BranchPtr;
+use crate::Transaction;
+use lib0::any::Any;
+use lib0::error::Error;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use std::rc::Rc;
+
+/// Codec of a user-defined content type. Values of [ContentCodec::Value] type can be inserted
+/// into shared collections with [PrelimExtension], once a codec has been registered in
+/// [Options::codecs](crate::Options::codecs) under a unique tag.
+///
+/// Encoded values are stored as [Extension] envelopes: a tag together with an opaque binary
+/// payload. This way they can be exchanged, persisted and merged even by the peers which don't
+/// know about a given codec.
+pub trait ContentCodec: 'static {
+    /// Type of values handled by this codec.
+    type Value: 'static;
+
+    /// Encodes a given `value` into a binary payload.
+    fn encode(&self, value: &Self::Value) -> Vec<u8>;
+
+    /// Decodes a value from a binary payload, previously produced by [ContentCodec::encode].
+    fn decode(&self, payload: &[u8]) -> Result<Self::Value, Error>;
+}
+
+/// Type-erased version of a [ContentCodec], which can be stored in [Codecs] registry.
+trait DynCodec {
+    fn encode(&self, value: &dyn std::any::Any) -> Vec<u8>;
+    fn decode(&self, payload: &[u8]) -> Result<Box<dyn std::any::Any>, Error>;
+}
+
+impl<C: ContentCodec> DynCodec for C {
+    fn encode(&self, value: &dyn std::any::Any) -> Vec<u8> {
+        let value = value
+            .downcast_ref::<C::Value>()
+            .expect("codec called with a value of an unexpected type");
+        ContentCodec::encode(self, value)
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Box<dyn std::any::Any>, Error> {
+        let value = ContentCodec::decode(self, payload)?;
+        Ok(Box::new(value))
+    }
+}
+
+/// Registry of [ContentCodec]s used by a document (see: [Options::codecs](crate::Options::codecs)).
+/// Every codec is identified by a unique tag and there can be only one codec per value type.
+///
+/// Example:
+/// ```
+/// use lib0::error::Error;
+/// use std::convert::TryInto;
+/// use yrs::{ContentCodec, Doc, Options, PrelimExtension};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point(i32, i32);
+///
+/// struct PointCodec;
+///
+/// impl ContentCodec for PointCodec {
+///     type Value = Point;
+///
+///     fn encode(&self, value: &Point) -> Vec<u8> {
+///         [value.0.to_be_bytes(), value.1.to_be_bytes()].concat()
+///     }
+///
+///     fn decode(&self, payload: &[u8]) -> Result<Point, Error> {
+///         if payload.len() != 8 {
+///             return Err(Error::UnexpectedValue);
+///         }
+///         let x = i32::from_be_bytes(payload[..4].try_into().unwrap());
+///         let y = i32::from_be_bytes(payload[4..].try_into().unwrap());
+///         Ok(Point(x, y))
+///     }
+/// }
+///
+/// let mut options = Options::with_client_id(1);
+/// options.codecs.register("point", PointCodec);
+/// let doc = Doc::with_options(options);
+///
+/// let mut txn = doc.transact();
+/// let array = txn.get_array("shapes");
+/// array.push_back(&mut txn, PrelimExtension::new(Point(1, 2)));
+///
+/// let ext = array.get(0).unwrap().to_extension().unwrap();
+/// assert_eq!(ext.tag(), "point");
+/// assert_eq!(ext.decode::<Point>(&txn).unwrap(), Point(1, 2));
+/// ```
+#[derive(Clone, Default)]
+pub struct Codecs {
+    by_tag: HashMap<Rc<str>, Rc<dyn DynCodec>>,
+    by_type: HashMap<TypeId, Rc<str>>,
+}
+
+impl Codecs {
+    /// Registers a `codec` under a given `tag`. If any codec has been registered under the same
+    /// `tag` or for the same value type before, it will be replaced.
+    pub fn register<C: ContentCodec>(&mut self, tag: &str, codec: C) {
+        let tag: Rc<str> = tag.into();
+        self.by_type.retain(|_, t| t != &tag);
+        if let Some(old) = self.by_type.insert(TypeId::of::<C::Value>(), tag.clone()) {
+            self.by_tag.remove(&old);
+        }
+        self.by_tag.insert(tag, Rc::new(codec));
+    }
+
+    /// Checks if a codec has been registered under a given `tag`.
+    pub fn contains(&self, tag: &str) -> bool {
+        self.by_tag.contains_key(tag)
+    }
+
+    /// Encodes a given `value` into an [Extension] envelope, using a codec registered for its
+    /// type.
+    pub fn encode<T: 'static>(&self, value: &T) -> Result<Extension, ExtensionError> {
+        let tag = self
+            .by_type
+            .get(&TypeId::of::<T>())
+            .ok_or_else(|| ExtensionError::UnregisteredType(std::any::type_name::<T>()))?;
+        let codec = &self.by_tag[tag];
+        Ok(Extension::new(tag.clone(), codec.encode(value)))
+    }
+
+    /// Decodes a payload of a given `extension` envelope, using a codec registered under its tag.
+    pub fn decode<T: 'static>(&self, extension: &Extension) -> Result<T, ExtensionError> {
+        let codec = self
+            .by_tag
+            .get(&extension.tag)
+            .ok_or_else(|| ExtensionError::UnknownTag(extension.tag.clone()))?;
+        let value = codec
+            .decode(&extension.payload)
+            .map_err(ExtensionError::Decode)?;
+        match value.downcast::<T>() {
+            Ok(value) => Ok(*value),
+            Err(_) => Err(ExtensionError::TypeMismatch(extension.tag.clone())),
+        }
+    }
+}
+
+impl std::fmt::Debug for Codecs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.by_tag.keys()).finish()
+    }
+}
+
+impl PartialEq for Codecs {
+    fn eq(&self, other: &Self) -> bool {
+        self.by_tag.len() == other.by_tag.len()
+            && self
+                .by_tag
+                .iter()
+                .all(|(tag, codec)| match other.by_tag.get(tag) {
+                    Some(c) => Rc::ptr_eq(codec, c),
+                    None => false,
+                })
+    }
+}
+
+impl Eq for Codecs {}
+
+/// Envelope of a user-defined content: a tag of a [ContentCodec] used to produce it together with
+/// an encoded binary payload. It's stored within a document as a single element.
+///
+/// Extensions are opaque to the documents which don't have a corresponding codec registered:
+/// they can still be read, copied, encoded and exchanged with other peers without any loss.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    tag: Rc<str>,
+    payload: Box<[u8]>,
+}
+
+impl Extension {
+    /// Creates a new extension envelope out of a codec `tag` and already encoded `payload`.
+    pub fn new<T: Into<Rc<str>>, P: Into<Box<[u8]>>>(tag: T, payload: P) -> Self {
+        Extension {
+            tag: tag.into(),
+            payload: payload.into(),
+        }
+    }
+
+    /// Returns a tag of a codec used to encode current extension.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Returns an encoded payload of current extension.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Decodes a payload of current extension using a codec registered in a document of a given
+    /// transaction.
+    pub fn decode<T: 'static>(&self, txn: &Transaction) -> Result<T, ExtensionError> {
+        txn.store().options.codecs.decode(self)
+    }
+
+    /// Converts current extension into a JSON-like map of its tag and binary payload.
+    pub fn to_json(&self) -> Any {
+        let mut map = HashMap::with_capacity(2);
+        map.insert("tag".to_owned(), Any::String(self.tag.as_ref().into()));
+        map.insert("payload".to_owned(), Any::Buffer(self.payload.clone()));
+        Any::Map(Box::new(map))
+    }
+}
+
+impl std::fmt::Display for Extension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.tag)?;
+        for byte in self.payload.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Prelim for Extension {
+    fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
+        (ItemContent::Extension(Box::new(self)), None)
+    }
+
+    fn integrate(self, _txn: &mut Transaction, _inner_ref: BranchPtr) {}
+}
+
+/// A preliminary user-defined value, which can be inserted into shared collections. It's encoded
+/// into an [Extension] using a [ContentCodec] registered for type `T`.
+///
+/// # Panics
+///
+/// Inserting this value panics if no codec for type `T` has been registered in
+/// [Options::codecs](crate::Options::codecs). Use [Codecs::encode] to handle that case gracefully.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrelimExtension<T>(T);
+
+impl<T: 'static> PrelimExtension<T> {
+    pub fn new(value: T) -> Self {
+        PrelimExtension(value)
+    }
+}
+
+impl<T: 'static> Prelim for PrelimExtension<T> {
+    fn into_content(self, txn: &mut Transaction) -> (ItemContent, Option<Self>) {
+        let extension = txn.store().options.codecs.encode(&self.0).unwrap();
+        (ItemContent::Extension(Box::new(extension)), None)
+    }
+
+    fn integrate(self, _txn: &mut Transaction, _inner_ref: BranchPtr) {}
+}
+
+/// Error returned when encoding or decoding of an [Extension] fails.
+#[derive(Debug)]
+pub enum ExtensionError {
+    /// No codec has been registered for a given value type.
+    UnregisteredType(&'static str),
+    /// No codec has been registered under a given tag.
+    UnknownTag(Rc<str>),
+    /// Codec registered under a given tag produces values of a different type than requested.
+    TypeMismatch(Rc<str>),
+    /// Codec failed to decode a payload.
+    Decode(Error),
+}
+
+impl std::fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtensionError::UnregisteredType(name) => {
+                write!(f, "no codec registered for type '{}'", name)
+            }
+            ExtensionError::UnknownTag(tag) => write!(f, "no codec registered under tag '{}'", tag),
+            ExtensionError::TypeMismatch(tag) => {
+                write!(
+                    f,
+                    "codec '{}' doesn't produce values of requested type",
+                    tag
+                )
+            }
+            ExtensionError::Decode(e) => write!(f, "failed to decode extension payload: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionError {}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::exchange_updates;
+    use crate::types::{Change, Value};
+    use crate::{
+        ContentCodec, Doc, Extension, ExtensionError, Options, PrelimExtension, Transaction,
+    };
+    use lib0::error::Error;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Color(u8, u8, u8);
+
+    struct ColorCodec;
+
+    impl ContentCodec for ColorCodec {
+        type Value = Color;
+
+        fn encode(&self, value: &Color) -> Vec<u8> {
+            vec![value.0, value.1, value.2]
+        }
+
+        fn decode(&self, payload: &[u8]) -> Result<Color, Error> {
+            match payload {
+                [r, g, b] => Ok(Color(*r, *g, *b)),
+                _ => Err(Error::UnexpectedValue),
+            }
+        }
+    }
+
+    fn doc_with_codec(client_id: u64) -> Doc {
+        let mut options = Options::with_client_id(client_id);
+        options.codecs.register("color", ColorCodec);
+        Doc::with_options(options)
+    }
+
+    fn decode(txn: &Transaction, value: Value) -> Result<Color, ExtensionError> {
+        value.to_extension().unwrap().decode::<Color>(txn)
+    }
+
+    #[test]
+    fn extension_sync() {
+        let d1 = doc_with_codec(1);
+        let d2 = Doc::with_client_id(2);
+        let d3 = doc_with_codec(3);
+        let a1 = d1.transact().get_array("array");
+        let m1 = d1.transact().get_map("map");
+        {
+            let mut txn = d1.transact();
+            a1.push_back(&mut txn, PrelimExtension::new(Color(255, 0, 0)));
+            a1.push_back(&mut txn, PrelimExtension::new(Color(0, 255, 0)));
+            m1.insert(
+                &mut txn,
+                "background",
+                PrelimExtension::new(Color(0, 0, 255)),
+            );
+        }
+
+        // d2 doesn't know the codec, but it can still relay the content to d3
+        exchange_updates(&[&d1, &d2]);
+        {
+            let mut txn = d2.transact();
+            let array = txn.get_array("array");
+            let ext = array.get(0).unwrap().to_extension().unwrap();
+            assert_eq!(ext.tag(), "color");
+            assert_eq!(ext.payload(), &[255, 0, 0]);
+            assert!(matches!(
+                ext.decode::<Color>(&txn),
+                Err(ExtensionError::UnknownTag(tag)) if tag.as_ref() == "color"
+            ));
+        }
+        exchange_updates(&[&d2, &d3]);
+
+        let mut txn = d3.transact();
+        let a3 = txn.get_array("array");
+        let m3 = txn.get_map("map");
+        assert_eq!(a3.len(), 2);
+        assert_eq!(decode(&txn, a3.get(0).unwrap()).unwrap(), Color(255, 0, 0));
+        assert_eq!(decode(&txn, a3.get(1).unwrap()).unwrap(), Color(0, 255, 0));
+        assert_eq!(
+            decode(&txn, m3.get("background").unwrap()).unwrap(),
+            Color(0, 0, 255)
+        );
+    }
+
+    #[test]
+    fn extension_events() {
+        let d1 = doc_with_codec(1);
+        let d2 = doc_with_codec(2);
+        let mut a2 = d2.transact().get_array("array");
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let r = received.clone();
+        let _sub = a2.observe(move |txn, e| {
+            for change in e.delta(txn) {
+                if let Change::Added(values) = change {
+                    for value in values.iter() {
+                        r.borrow_mut().push(decode(txn, value.clone()).unwrap());
+                    }
+                }
+            }
+        });
+
+        let a1 = d1.transact().get_array("array");
+        a1.insert(&mut d1.transact(), 0, PrelimExtension::new(Color(1, 2, 3)));
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(received.take(), vec![Color(1, 2, 3)]);
+    }
+
+    #[test]
+    fn extension_errors() {
+        let doc = doc_with_codec(1);
+        let codecs = doc.transact().store().options.codecs.clone();
+        assert!(codecs.contains("color"));
+        assert!(matches!(
+            codecs.encode(&"text"),
+            Err(ExtensionError::UnregisteredType(_))
+        ));
+
+        let mut txn = doc.transact();
+        let array = txn.get_array("array");
+        array.push_back(&mut txn, Extension::new("color", vec![1, 2]));
+        array.push_back(&mut txn, Extension::new("color", vec![1, 2, 3]));
+        assert!(matches!(
+            decode(&txn, array.get(0).unwrap()),
+            Err(ExtensionError::Decode(_))
+        ));
+        let ext = array.get(1).unwrap().to_extension().unwrap();
+        assert!(matches!(
+            ext.decode::<String>(&txn),
+            Err(ExtensionError::TypeMismatch(_))
+        ));
+        assert_eq!(ext.decode::<Color>(&txn).unwrap(), Color(1, 2, 3));
+    }
+}
@@ -1,7 +1,7 @@
 use crate::block::{ClientID, ID};
 use crate::block_store::BlockStore;
 use crate::store::Store;
-use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::decoder::{Decode, Decoder, MAX_PREALLOC};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
 use lib0::error::Error;
@@ -24,7 +24,8 @@ impl Decode for Range<u32> {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
         let clock = decoder.read_ds_clock()?;
         let len = decoder.read_ds_len()?;
-        Ok(clock..(clock + len))
+        let end = clock.checked_add(len).ok_or(Error::ClockOverflow)?;
+        Ok(clock..end)
     }
 }
 
@@ -237,7 +238,7 @@ impl Decode for IdRange {
                 Ok(IdRange::Continuous(range))
             }
             len => {
-                let mut ranges = Vec::with_capacity(len as usize);
+                let mut ranges = Vec::with_capacity((len as usize).min(MAX_PREALLOC));
                 let mut i = 0;
                 while i < len {
                     ranges.push(Range::decode(decoder)?);
@@ -302,7 +303,8 @@ impl IdSet {
         self.0.len()
     }
 
-    pub(crate) fn iter(&self) -> Iter<'_> {
+    /// Returns an iterator over all client-range pairs registered in this ID set.
+    pub fn iter(&self) -> Iter<'_> {
         self.0.iter()
     }
 
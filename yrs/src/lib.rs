@@ -22,14 +22,24 @@
 //! build them easily on your own.
 
 mod alt;
+pub mod awareness;
 pub mod block;
 mod block_store;
 mod doc;
+mod doc_handle;
 mod event;
+mod extension;
 mod id_set;
+pub mod migration;
+pub mod persistence;
+pub mod position;
+pub mod registry;
+mod replica;
 mod store;
+pub mod sync;
 mod transaction;
 pub mod types;
+pub mod undo;
 mod update;
 pub mod updates;
 mod utils;
@@ -38,29 +48,77 @@ mod utils;
 mod compatibility_tests;
 
 mod block_iter;
+mod cursor;
 mod moving;
-#[cfg(test)]
-mod test_utils;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "yrs-websocket")]
+pub mod websocket;
 
 pub use crate::alt::{
     diff_updates_v1, diff_updates_v2, encode_state_vector_from_update_v1,
     encode_state_vector_from_update_v2, merge_updates_v1, merge_updates_v2,
 };
 pub use crate::block::ID;
+pub use crate::block_iter::OutOfBounds;
 pub use crate::block_store::Snapshot;
 pub use crate::block_store::StateVector;
+pub use crate::cursor::Cursor;
+pub use crate::doc::BlockInfo;
+pub use crate::doc::ClientIdError;
+pub use crate::doc::Clock;
+pub use crate::doc::CollisionHandler;
 pub use crate::doc::Doc;
+pub use crate::doc::DocLimits;
+pub use crate::doc::DocRef;
+pub use crate::doc::DocStats;
+pub use crate::doc::GcFilter;
+pub use crate::doc::IdGenerator;
+pub use crate::doc::MemoryUsage;
 pub use crate::doc::OffsetKind;
+pub use crate::doc::OffsetUnit;
 pub use crate::doc::Options;
-pub use crate::event::{AfterTransactionEvent, Subscription, SubscriptionId, UpdateEvent};
-pub use crate::id_set::DeleteSet;
-pub use crate::transaction::Transaction;
+pub use crate::doc::Timestamp;
+pub use crate::doc::UpdateFilter;
+pub use crate::doc::WritePolicy;
+pub use crate::doc::WriteTarget;
+pub use crate::doc_handle::DocHandle;
+pub use crate::event::{
+    AfterTransactionEvent, SubdocsEvent, Subscription, SubscriptionId, UpdateEvent,
+    WeakSubscription,
+};
+pub use crate::extension::{Codecs, ContentCodec, Extension, ExtensionError, PrelimExtension};
+pub use crate::id_set::{DeleteSet, IdRange, IdSet};
+pub use crate::moving::{MoveInfo, RelativePosition};
+pub use crate::replica::Replica;
+pub use crate::transaction::{
+    GcStats, IntegrationReport, MapOverride, Origin, ReadTransaction, ReadTxn, RenameRootError,
+    RollbackError, Roots, Transaction, WriteTxn, ROOT_ALIASES,
+};
 pub use crate::types::array::Array;
 pub use crate::types::array::PrelimArray;
+pub use crate::types::counter::Counter;
 pub use crate::types::map::Map;
 pub use crate::types::map::PrelimMap;
+pub use crate::types::table::Table;
+pub use crate::types::text::Attribution;
+pub use crate::types::text::PrelimText;
 pub use crate::types::text::Text;
+pub use crate::types::tree::Tree;
+pub use crate::types::weak::LinkSource;
+pub use crate::types::weak::WeakLink;
+pub use crate::types::weak::WeakPrelim;
+pub use crate::types::xml::NotAChild;
 pub use crate::types::xml::Xml;
 pub use crate::types::xml::XmlElement;
+pub use crate::types::xml::XmlFragment;
 pub use crate::types::xml::XmlText;
-pub use crate::update::Update;
+pub use crate::types::PrelimBinary;
+pub use crate::types::PrelimValue;
+pub use crate::undo::UndoManager;
+pub use crate::update::{
+    ApplyDriver, ItemParent, LimitKind, PendingUpdate, Update, UpdateBlock, UpdateBlockIter,
+    UpdateError, UpdateItem,
+};
@@ -0,0 +1,265 @@
+//! Schema migrations of documents, which evolve their structure over time.
+//!
+//! [Migrations] is a registry of [Migration]s, each of them upgrading a document to a given schema
+//! version. A schema version of a document is stored within a document itself, inside of
+//! a root-level map called [SCHEMA_ROOT], so that peers can tell which migrations have been already
+//! applied, either by themselves or by other peers.
+//!
+//! # Concurrent migrations
+//!
+//! Peers may migrate the same document concurrently, before they've received each other's
+//! changes. Applied versions are recorded as a grow-only set of `<version>/<client id>` entries,
+//! so merging concurrent migrations never moves a schema version backwards, and a migration is
+//! never applied again by a peer which has already seen it applied by someone else.
+//!
+//! However, the same migration can be applied concurrently by many peers, so migrations should be
+//! written in a way that converges when their effects are merged together, eg. by assigning map
+//! entries (only one of the concurrently assigned values wins), renaming root types (see:
+//! [Transaction::rename_root]) or deleting elements instead of appending to sequences. Versions,
+//! which have been applied concurrently, can be listed using [concurrent_migrations] in order to
+//! repair the document once the changes of all peers are merged.
+
+use crate::block::ClientID;
+use crate::{Doc, ReadTxn, Transaction};
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Unbounded};
+
+/// Name of a root-level map, which stores schema versions applied to a document.
+pub const SCHEMA_ROOT: &str = "__yrs_schema";
+
+/// Origin of transactions opened by [Migrations::migrate].
+pub const MIGRATION_ORIGIN: &str = "yrs/migration";
+
+/// A single step of a document schema evolution.
+pub trait Migration {
+    /// Schema version, which a document is upgraded to by this migration. Versions start at 1:
+    /// version 0 describes documents, which were never migrated.
+    fn version(&self) -> u32;
+
+    /// Upgrades a document from the previous schema version. Called within a transaction, in which
+    /// all pending migrations are applied.
+    fn migrate(&self, txn: &mut Transaction);
+}
+
+/// Error returned by [Migrations::migrate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationError {
+    /// Document has been already migrated to a schema version newer than the latest one known to
+    /// a current peer. Current peer should not modify such document.
+    Unsupported { document: u32, supported: u32 },
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::Unsupported {
+                document,
+                supported,
+            } => write!(
+                f,
+                "document schema version {} is newer than supported version {}",
+                document, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// A registry of [Migration]s, which can be used to upgrade documents to the latest schema
+/// version.
+///
+/// Example:
+/// ```rust
+/// use yrs::migration::{Migration, Migrations};
+/// use yrs::{Doc, Transaction};
+///
+/// struct RenameTitle;
+///
+/// impl Migration for RenameTitle {
+///     fn version(&self) -> u32 {
+///         1
+///     }
+///
+///     fn migrate(&self, txn: &mut Transaction) {
+///         txn.rename_root("title", "name").unwrap();
+///     }
+/// }
+///
+/// let doc = Doc::new();
+/// doc.transact().get_text("title").push(&mut doc.transact(), "hello");
+///
+/// let mut migrations = Migrations::new();
+/// migrations.register(RenameTitle);
+/// assert_eq!(migrations.migrate(&doc), Ok(vec![1]));
+/// // migrations are applied only once
+/// assert_eq!(migrations.migrate(&doc), Ok(vec![]));
+///
+/// assert_eq!(doc.transact().get_text("name").to_string(), "hello");
+/// ```
+#[derive(Default)]
+pub struct Migrations {
+    migrations: BTreeMap<u32, Box<dyn Migration>>,
+}
+
+impl Migrations {
+    /// Creates a new registry without any migrations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new `migration`. If there was already a migration registered for the same
+    /// version, it will be replaced.
+    pub fn register<M: Migration + 'static>(&mut self, migration: M) -> &mut Self {
+        self.migrations
+            .insert(migration.version(), Box::new(migration));
+        self
+    }
+
+    /// Returns the latest schema version known to this registry.
+    pub fn latest_version(&self) -> u32 {
+        self.migrations.keys().next_back().cloned().unwrap_or(0)
+    }
+
+    /// Upgrades a `doc` to the latest schema version, applying all pending migrations in order of
+    /// their versions within a single transaction (with [MIGRATION_ORIGIN] as its origin). If a
+    /// document is already up to date, no transaction is opened and no update is produced.
+    ///
+    /// Returns versions of the applied migrations.
+    pub fn migrate(&self, doc: &Doc) -> Result<Vec<u32>, MigrationError> {
+        let version = schema_version(&doc.transact_read());
+        if version >= self.latest_version() {
+            self.check(version)?;
+            Ok(Vec::new())
+        } else {
+            let mut txn = doc.transact_with(MIGRATION_ORIGIN);
+            self.apply(&mut txn)
+        }
+    }
+
+    /// Upgrades a document to the latest schema version within a given transaction. See:
+    /// [Migrations::migrate].
+    pub fn apply(&self, txn: &mut Transaction) -> Result<Vec<u32>, MigrationError> {
+        let version = schema_version(txn);
+        self.check(version)?;
+        let client_id = txn.store().options.client_id;
+        let mut applied = Vec::new();
+        for (&version, migration) in self.migrations.range((Excluded(version), Unbounded)) {
+            migration.migrate(txn);
+            let schema = txn.get_map(SCHEMA_ROOT);
+            schema.insert(txn, format!("{}/{}", version, client_id), true);
+            applied.push(version);
+        }
+        Ok(applied)
+    }
+
+    fn check(&self, version: u32) -> Result<(), MigrationError> {
+        let supported = self.latest_version();
+        if version > supported {
+            Err(MigrationError::Unsupported {
+                document: version,
+                supported,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns a schema version of a document, which is the highest version of the migrations applied
+/// to it. Documents which were never migrated have schema version 0.
+pub fn schema_version<T: ReadTxn>(txn: &T) -> u32 {
+    applied_migrations(txn)
+        .map(|(version, _)| version)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns versions of migrations, which have been applied concurrently by more than one peer,
+/// together with identifiers of these peers, ordered by version.
+pub fn concurrent_migrations<T: ReadTxn>(txn: &T) -> Vec<(u32, Vec<ClientID>)> {
+    let mut versions: BTreeMap<u32, Vec<ClientID>> = BTreeMap::new();
+    for (version, client_id) in applied_migrations(txn) {
+        versions.entry(version).or_default().push(client_id);
+    }
+    versions
+        .into_iter()
+        .filter(|(_, clients)| clients.len() > 1)
+        .map(|(version, mut clients)| {
+            clients.sort_unstable();
+            (version, clients)
+        })
+        .collect()
+}
+
+fn applied_migrations<T: ReadTxn>(txn: &T) -> impl Iterator<Item = (u32, ClientID)> {
+    let keys: Vec<_> = match txn.try_get_map(SCHEMA_ROOT) {
+        Some(schema) => schema.keys().map(String::from).collect(),
+        None => Vec::new(),
+    };
+    keys.into_iter().filter_map(|key| {
+        let (version, client_id) = key.split_once('/')?;
+        Some((version.parse().ok()?, client_id.parse().ok()?))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::migration::{
+        concurrent_migrations, schema_version, Migration, MigrationError, Migrations,
+    };
+    use crate::testing::exchange_updates;
+    use crate::{Doc, ReadTxn, Transaction};
+
+    struct Step(u32);
+
+    impl Migration for Step {
+        fn version(&self) -> u32 {
+            self.0
+        }
+
+        fn migrate(&self, txn: &mut Transaction) {
+            let map = txn.get_map("settings");
+            map.insert(txn, format!("v{}", self.0), true);
+        }
+    }
+
+    #[test]
+    fn migrate_concurrently() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let mut v1 = Migrations::new();
+        v1.register(Step(1));
+        let mut v2 = Migrations::new();
+        v2.register(Step(2)).register(Step(1));
+        assert_eq!(v2.latest_version(), 2);
+
+        // both peers migrate before they see each other's changes
+        assert_eq!(v1.migrate(&d1), Ok(vec![1]));
+        assert_eq!(v2.migrate(&d2), Ok(vec![1, 2]));
+        exchange_updates(&[&d1, &d2]);
+
+        // schema version never goes back
+        assert_eq!(schema_version(&d1.transact_read()), 2);
+        assert_eq!(
+            concurrent_migrations(&d2.transact_read()),
+            vec![(1, vec![1, 2])]
+        );
+        let txn = d1.transact();
+        let settings = txn.try_get_map("settings").unwrap();
+        let mut keys: Vec<_> = settings.keys().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["v1", "v2"]);
+        drop(txn);
+
+        // a peer which doesn't know the latest schema should not touch the document
+        assert_eq!(
+            v1.migrate(&d1),
+            Err(MigrationError::Unsupported {
+                document: 2,
+                supported: 1
+            })
+        );
+        assert_eq!(v2.migrate(&d1), Ok(vec![]));
+    }
+}
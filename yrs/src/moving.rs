@@ -1,11 +1,12 @@
 use crate::block::{Block, BlockPtr, ItemContent, Prelim};
-use crate::block_iter::BlockIter;
+use crate::block_iter::{BlockIter, OutOfBounds};
 use crate::types::BranchPtr;
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::{Transaction, ID};
+use lib0::any::Any;
 use lib0::error::Error;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 
 /// Association type. If true, associate with right block. Otherwise with the left one.
@@ -14,7 +15,9 @@ pub type Assoc = bool;
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Move {
     pub start: RelativePosition,
-    pub end: RelativePosition,
+    /// End of a moved range. `None` if moved range is open-ended, ie. it spans until the end of
+    /// a collection, including elements appended after the move was made.
+    pub end: Option<RelativePosition>,
     pub priority: i32,
 
     /// We store which Items+ContentMove we override. Once we delete
@@ -27,7 +30,7 @@ pub struct Move {
 }
 
 impl Move {
-    pub fn new(start: RelativePosition, end: RelativePosition, priority: i32) -> Self {
+    pub fn new(start: RelativePosition, end: Option<RelativePosition>, priority: i32) -> Self {
         Move {
             start,
             end,
@@ -37,7 +40,10 @@ impl Move {
     }
 
     pub fn is_collapsed(&self) -> bool {
-        self.start.id == self.end.id
+        match &self.end {
+            Some(end) => self.start.id == end.id,
+            None => false,
+        }
     }
 
     pub(crate) fn get_moved_coords(
@@ -45,7 +51,10 @@ impl Move {
         txn: &mut Transaction,
     ) -> (Option<BlockPtr>, Option<BlockPtr>) {
         let start = Self::get_item_ptr(txn, &self.start.id, self.start.assoc);
-        let end = Self::get_item_ptr(txn, &self.end.id, self.end.assoc);
+        let end = match &self.end {
+            Some(end) => Self::get_item_ptr(txn, &end.id, end.assoc),
+            None => None,
+        };
         (start, end)
     }
 
@@ -241,8 +250,10 @@ impl Encode for Move {
             if self.start.assoc {
                 b |= 0b0000_0010
             }
-            if self.end.assoc {
-                b |= 0b0000_0100
+            match &self.end {
+                Some(end) if end.assoc => b |= 0b0000_0100,
+                Some(_) => {}
+                None => b |= 0b0001_0000,
             }
             b |= self.priority << 6;
             b
@@ -250,9 +261,11 @@ impl Encode for Move {
         encoder.write_var(flags);
         encoder.write_var(self.start.id.client);
         encoder.write_var(self.start.id.clock);
-        if !is_collapsed {
-            encoder.write_var(self.end.id.client);
-            encoder.write_var(self.end.id.clock);
+        if let Some(end) = &self.end {
+            if !is_collapsed {
+                encoder.write_var(end.id.client);
+                encoder.write_var(end.id.clock);
+            }
         }
     }
 }
@@ -263,17 +276,20 @@ impl Decode for Move {
         let is_collapsed = flags & 0b0000_0001 != 0;
         let start_assoc = flags & 0b0000_0010 != 0;
         let end_assoc = flags & 0b0000_0100 != 0;
-        //TODO use BIT3 & BIT4 to indicate the case `null` is the start/end
+        //TODO use BIT3 to indicate the case `null` is the start
+        let is_open_ended = flags & 0b0001_0000 != 0;
         // BIT5 is reserved for future extensions
         let priority = flags >> 6;
         let start_id = ID::new(decoder.read_var()?, decoder.read_var()?);
-        let end_id = if is_collapsed {
-            start_id
+        let end = if is_open_ended {
+            None
+        } else if is_collapsed {
+            Some(RelativePosition::create(start_id, end_assoc))
         } else {
-            ID::new(decoder.read_var()?, decoder.read_var()?)
+            let end_id = ID::new(decoder.read_var()?, decoder.read_var()?);
+            Some(RelativePosition::create(end_id, end_assoc))
         };
         let start = RelativePosition::create(start_id, start_assoc);
-        let end = RelativePosition::create(end_id, end_assoc);
         Ok(Move::new(start, end, priority))
     }
 }
@@ -292,8 +308,10 @@ impl std::fmt::Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "move(")?;
         write!(f, "{}", self.start)?;
-        if self.start != self.end {
-            write!(f, "..{}", self.end)?;
+        match &self.end {
+            Some(end) if end != &self.start => write!(f, "..{}", end)?,
+            Some(_) => {}
+            None => write!(f, "..")?,
         }
         if self.priority != 0 {
             write!(f, ", prio: {}", self.priority)?;
@@ -313,6 +331,70 @@ impl std::fmt::Display for Move {
     }
 }
 
+/// A public, read-only snapshot of a move operation performed over an [Array], as returned by
+/// [Array::moves] and [ArrayEvent::moves]. It can be used eg. to animate reordered elements.
+///
+/// [Array]: crate::Array
+/// [Array::moves]: crate::Array::moves
+/// [ArrayEvent::moves]: crate::types::array::ArrayEvent::moves
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MoveInfo {
+    /// Unique identifier of a block which contains this move operation.
+    pub id: ID,
+    /// Position of the first moved element.
+    pub start: RelativePosition,
+    /// Position right after the last moved element, or `None` if moved range spans until the end
+    /// of an array.
+    pub end: Option<RelativePosition>,
+    /// Priority of this move. When several moves target the same elements concurrently, the one
+    /// with the highest priority wins.
+    pub priority: i32,
+    /// Identifiers of the other moves overridden by this one. They will be reapplied once this
+    /// move is undone.
+    pub overrides: Vec<ID>,
+}
+
+impl MoveInfo {
+    pub(crate) fn new(id: ID, m: &Move) -> Self {
+        let mut overrides: Vec<ID> = m.overrides.iter().flatten().map(|ptr| *ptr.id()).collect();
+        overrides.sort();
+        MoveInfo {
+            id,
+            start: m.start.clone(),
+            end: m.end.clone(),
+            priority: m.priority,
+            overrides,
+        }
+    }
+
+    /// Converts current move into JSON-like map of its `id`, `start`, `end`, `priority` and
+    /// `overrides`. Identifiers are represented as `[client, clock]` arrays. Open-ended range has
+    /// a null `end`.
+    pub fn to_json(&self) -> Any {
+        fn id_json(id: &ID) -> Any {
+            Any::Array(Box::new([
+                Any::Number(id.client as f64),
+                Any::Number(id.clock as f64),
+            ]))
+        }
+        fn position_json(pos: &RelativePosition) -> Any {
+            let mut map = HashMap::new();
+            map.insert("id".to_string(), id_json(&pos.id));
+            map.insert("assoc".to_string(), Any::Bool(pos.assoc));
+            Any::Map(Box::new(map))
+        }
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), id_json(&self.id));
+        map.insert("start".to_string(), position_json(&self.start));
+        let end = self.end.as_ref().map(position_json).unwrap_or(Any::Null);
+        map.insert("end".to_string(), end);
+        map.insert("priority".to_string(), Any::Number(self.priority as f64));
+        let overrides = self.overrides.iter().map(id_json).collect();
+        map.insert("overrides".to_string(), Any::Array(overrides));
+        Any::Map(Box::new(map))
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct RelativePosition {
     pub id: ID,
@@ -325,37 +407,40 @@ impl RelativePosition {
         RelativePosition { id, assoc }
     }
 
+    /// Creates a relative position for a given `index` within a `branch`. Returns `Ok(None)` if
+    /// position is unbounded (it points to the beginning or the end of a branch) or an
+    /// [OutOfBounds] error if `index` is outside of the range of a given `branch`.
     pub(crate) fn from_type_index(
         txn: &mut Transaction,
         branch: BranchPtr,
         mut index: u32,
         assoc: Assoc,
-    ) -> Option<Self> {
+    ) -> Result<Option<Self>, OutOfBounds> {
         if !assoc {
             if index == 0 {
-                return None;
+                return Ok(None);
             }
             index -= 1;
         }
 
         let mut walker = BlockIter::new(branch);
-        if !walker.try_forward(txn, index) {
-            panic!("Block iter couldn't move forward");
-        }
-        if walker.finished() {
+        walker.try_forward(txn, index)?;
+        let pos = if walker.finished() {
             if !assoc {
-                let ptr = walker.next_item()?;
-                let id = ptr.last_id();
-                Some(Self::create(id, assoc))
+                walker
+                    .next_item()
+                    .map(|ptr| Self::create(ptr.last_id(), assoc))
             } else {
                 None
             }
         } else {
-            let ptr = walker.next_item()?;
-            let mut id = ptr.id().clone();
-            id.clock += walker.rel();
-            Some(Self::create(id, assoc))
-        }
+            walker.next_item().map(|ptr| {
+                let mut id = ptr.id().clone();
+                id.clock += walker.rel();
+                Self::create(id, assoc)
+            })
+        };
+        Ok(pos)
     }
 
     pub(crate) fn within_range(&self, ptr: Option<BlockPtr>) -> bool {
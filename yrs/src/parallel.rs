@@ -0,0 +1,210 @@
+//! Parallel decoding of updates, enabled with `parallel` feature.
+//!
+//! Block sequences of different clients are independent of each other until they are integrated,
+//! however lib0 v1 encoding doesn't store their offsets, so they have to be found first. This is
+//! done in a cheap sequential scan, which only skips over encoded blocks without materializing
+//! their contents. Found sequences are then decoded in parallel using [rayon].
+
+use crate::block::{
+    ClientID, ItemContent, BLOCK_GC_REF_NUMBER, BLOCK_ITEM_BINARY_REF_NUMBER,
+    BLOCK_ITEM_DELETED_REF_NUMBER, BLOCK_ITEM_EMBED_REF_NUMBER, BLOCK_ITEM_EXTENSION_REF_NUMBER,
+    BLOCK_ITEM_FORMAT_REF_NUMBER, BLOCK_ITEM_JSON_REF_NUMBER, BLOCK_ITEM_STRING_REF_NUMBER,
+    BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
+};
+use crate::id_set::DeleteSet;
+use crate::update::{BlockCarrier, Update};
+use crate::updates::decoder::{Decode, Decoder, DecoderV1, MAX_PREALLOC};
+use crate::ID;
+use lib0::decoding::{Cursor, Read};
+use lib0::error::Error;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+
+/// Updates with less client block sequences than this are decoded sequentially, as splitting
+/// them wouldn't pay off.
+const MIN_PARALLEL_CLIENTS: usize = 4;
+
+/// Encoded block sequence of a single client.
+struct Segment {
+    client: ClientID,
+    clock: u32,
+    len: usize,
+    offset: usize,
+}
+
+/// Blocks decoded by a worker thread.
+struct Decoded(ClientID, VecDeque<BlockCarrier>);
+
+// SAFETY: decoded blocks contain reference counted strings and nested branches, which are not
+// thread safe. However each segment is decoded by its own decoder, so no reference counted value
+// is ever shared between blocks of different segments. A whole block sequence is moved to
+// another thread as a unique owner of all of its values.
+unsafe impl Send for Decoded {}
+
+impl Update {
+    /// Decodes an update encoded using lib0 v1 encoding, decoding block sequences of different
+    /// clients in parallel. Produces the same result as [Decode::decode_v1], but can be
+    /// considerably faster for large updates containing changes of many clients.
+    pub fn decode_v1_parallel(data: &[u8]) -> Result<Update, Error> {
+        let mut decoder = DecoderV1::from(data);
+        let segments = scan(&mut decoder)?;
+        if segments.len() < MIN_PARALLEL_CLIENTS {
+            return Update::decode_v1(data);
+        }
+        let delete_set_offset = decoder.position();
+        let decoded: Vec<Result<Decoded, Error>> = segments
+            .into_par_iter()
+            .map(|segment| decode_segment(data, segment))
+            .collect();
+
+        let mut update = Update::new();
+        for result in decoded {
+            let Decoded(client, blocks) = result?;
+            match update.blocks.clients.get_mut(&client) {
+                Some(existing) => existing.extend(blocks),
+                None => {
+                    update.blocks.clients.insert(client, blocks);
+                }
+            }
+        }
+        let mut decoder = DecoderV1::new(Cursor {
+            buf: data,
+            next: delete_set_offset,
+        });
+        update.delete_set = DeleteSet::decode(&mut decoder)?;
+        Ok(update)
+    }
+}
+
+/// Finds block sequences of all clients in an update, leaving `decoder` at the beginning of
+/// a delete set.
+fn scan(decoder: &mut DecoderV1) -> Result<Vec<Segment>, Error> {
+    let clients_len: u32 = decoder.read_var()?;
+    let mut segments = Vec::with_capacity((clients_len as usize).min(MAX_PREALLOC));
+    for _ in 0..clients_len {
+        let len = decoder.read_var::<u32>()? as usize;
+        let client = decoder.read_client()?;
+        let clock: u32 = decoder.read_var()?;
+        let offset = decoder.position();
+        for _ in 0..len {
+            skip_block(decoder)?;
+        }
+        segments.push(Segment {
+            client,
+            clock,
+            len,
+            offset,
+        });
+    }
+    Ok(segments)
+}
+
+/// Moves a `decoder` past the next encoded block. Contents which are cheap to skip (strings and
+/// binaries) are not decoded at all. Other contents are decoded and dropped.
+fn skip_block(decoder: &mut DecoderV1) -> Result<(), Error> {
+    let info = decoder.read_info()?;
+    match info {
+        BLOCK_SKIP_REF_NUMBER | BLOCK_GC_REF_NUMBER => {
+            decoder.read_var::<u32>()?;
+        }
+        info => {
+            if info & HAS_ORIGIN != 0 {
+                decoder.read_left_id()?;
+            }
+            if info & HAS_RIGHT_ORIGIN != 0 {
+                decoder.read_right_id()?;
+            }
+            let cant_copy_parent_info = info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0;
+            if cant_copy_parent_info {
+                if decoder.read_parent_info()? {
+                    decoder.read_buf()?;
+                } else {
+                    decoder.read_left_id()?;
+                }
+                if info & HAS_PARENT_SUB != 0 {
+                    decoder.read_buf()?;
+                }
+            }
+            match info & 0b1111 {
+                BLOCK_ITEM_DELETED_REF_NUMBER => {
+                    decoder.read_len()?;
+                }
+                BLOCK_ITEM_JSON_REF_NUMBER => {
+                    let mut remaining = decoder.read_len()? as i32;
+                    while remaining >= 0 {
+                        decoder.read_buf()?;
+                        remaining -= 1;
+                    }
+                }
+                BLOCK_ITEM_BINARY_REF_NUMBER
+                | BLOCK_ITEM_STRING_REF_NUMBER
+                | BLOCK_ITEM_EMBED_REF_NUMBER => {
+                    decoder.read_buf()?;
+                }
+                BLOCK_ITEM_FORMAT_REF_NUMBER | BLOCK_ITEM_EXTENSION_REF_NUMBER => {
+                    decoder.read_buf()?;
+                    decoder.read_buf()?;
+                }
+                _ => {
+                    ItemContent::decode(decoder, info)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn decode_segment(data: &[u8], segment: Segment) -> Result<Decoded, Error> {
+    let mut decoder = DecoderV1::new(Cursor {
+        buf: data,
+        next: segment.offset,
+    });
+    let mut blocks = VecDeque::with_capacity(segment.len.min(MAX_PREALLOC));
+    let mut clock = segment.clock;
+    for _ in 0..segment.len {
+        let id = ID::new(segment.client, clock);
+        let block = Update::decode_block(id, &mut decoder)?;
+        clock = clock.checked_add(block.len()).ok_or(Error::ClockOverflow)?;
+        blocks.push_back(block);
+    }
+    Ok(Decoded(segment.client, blocks))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types::Attrs;
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, PrelimMap, StateVector, Update};
+    use lib0::any::Any;
+
+    #[test]
+    fn decode_v1_parallel() {
+        let docs: Vec<_> = (1..=8).map(Doc::with_client_id).collect();
+        for (i, doc) in docs.iter().enumerate() {
+            let mut txn = doc.transact();
+            let text = txn.get_text("text");
+            text.push(&mut txn, &"abc".repeat(i + 1));
+            let map = txn.get_map("map");
+            map.insert(&mut txn, i.to_string(), vec![i as f64, 0.5]);
+            map.insert(&mut txn, "nested", PrelimMap::<bool>::new());
+            let bold = Attrs::from([("bold".into(), Any::Bool(true))]);
+            text.format(&mut txn, 0, 2, bold);
+            text.insert_embed(&mut txn, 1, Any::Number(i as f64));
+            text.remove_range(&mut txn, 0, 1);
+        }
+        let doc = Doc::new();
+        for other in docs.iter() {
+            let update = other.encode_state_as_update_v1(&StateVector::default());
+            doc.transact().apply_update_v1(&update).unwrap();
+        }
+        let update = doc.encode_state_as_update_v1(&StateVector::default());
+
+        let expected = Update::decode_v1(&update).unwrap();
+        let actual = Update::decode_v1_parallel(&update).unwrap();
+        assert_eq!(actual.blocks.clients.len(), 8);
+        assert_eq!(actual, expected);
+
+        // malformed input is rejected, just like in case of sequential decoding
+        assert!(Update::decode_v1_parallel(&update[..update.len() / 2]).is_err());
+    }
+}
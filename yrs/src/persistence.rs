@@ -0,0 +1,228 @@
+//! Persistence layer for documents stored as a log of incremental updates, analogous to
+//! [y-leveldb](https://github.com/yjs/y-leveldb).
+//!
+//! Every document is identified by its name and persisted as a sequence of lib0 v1 encoded
+//! updates. Appending an update is cheap, but the log has to be compacted from time to time by
+//! merging all of its updates into a single one. [DocStore] defines a minimal set of storage
+//! primitives, which have to be implemented by a database backend, and uses them to provide
+//! higher level operations like loading, compacting or computing a state vector of a document.
+//! [MemoryStore] is a reference implementation, which keeps everything in memory, while
+//! `SledStore` (enabled with `sled` feature) persists documents in a
+//! [sled](https://docs.rs/sled) embedded database.
+
+use crate::updates::decoder::Decode;
+use crate::{
+    diff_updates_v1, encode_state_vector_from_update_v1, merge_updates_v1, StateVector,
+    Transaction, Update,
+};
+use lib0::error::Error;
+use std::collections::HashMap;
+
+#[cfg(feature = "sled")]
+mod sled_store;
+#[cfg(feature = "sled")]
+pub use self::sled_store::{SledStore, SledStoreError};
+
+/// When a number of updates stored for a single document reaches this limit,
+/// [DocStore::store_update] compacts them into a single update.
+pub const PREFERRED_TRIM_SIZE: usize = 500;
+
+/// Trait implemented by database backends able to persist documents as logs of lib0 v1 encoded
+/// updates.
+pub trait DocStore {
+    /// Error type returned by a backend. Errors of decoding stored updates must be convertible
+    /// into it.
+    type Error: From<Error>;
+
+    /// Returns all updates stored for a document with a given `name` in the order in which they
+    /// have been appended. Returns an empty vector if there's no such document.
+    fn get_updates(&self, name: &str) -> Result<Vec<Vec<u8>>, Self::Error>;
+
+    /// Appends an update at the end of a document's update log. Returns a number of updates
+    /// stored for that document after the operation. Appending an update must invalidate a
+    /// state vector stored for that document (see: [DocStore::get_state_vector]).
+    fn append_update(&mut self, name: &str, update: &[u8]) -> Result<usize, Self::Error>;
+
+    /// Replaces all updates stored for a document with a given `update` and stores its
+    /// `state_vector`. Backends should execute this operation atomically.
+    fn replace_updates(
+        &mut self,
+        name: &str,
+        update: &[u8],
+        state_vector: &[u8],
+    ) -> Result<(), Self::Error>;
+
+    /// Returns a lib0 v1 encoded state vector stored for a document, if it's still valid.
+    fn get_state_vector(&self, name: &str) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Removes all data stored for a document with a given `name`.
+    fn clear_document(&mut self, name: &str) -> Result<(), Self::Error>;
+
+    /// Returns names of all stored documents.
+    fn doc_names(&self) -> Result<Vec<String>, Self::Error>;
+
+    /// Applies all updates stored for a document with a given `name` within a given transaction.
+    fn load_doc(&self, name: &str, txn: &mut Transaction) -> Result<(), Self::Error> {
+        for update in self.get_updates(name)? {
+            txn.apply_update(Update::decode_v1(update.as_slice())?);
+        }
+        Ok(())
+    }
+
+    /// Appends a lib0 v1 encoded `update` to a document's update log. Once the log reaches
+    /// [PREFERRED_TRIM_SIZE], it's compacted.
+    fn store_update(&mut self, name: &str, update: &[u8]) -> Result<(), Self::Error> {
+        let len = self.append_update(name, update)?;
+        if len >= PREFERRED_TRIM_SIZE {
+            self.compact(name)?;
+        }
+        Ok(())
+    }
+
+    /// Merges all updates stored for a document with a given `name` into a single one and stores
+    /// its state vector. Returns the merged update.
+    fn compact(&mut self, name: &str) -> Result<Vec<u8>, Self::Error> {
+        let updates = self.get_updates(name)?;
+        let updates: Vec<&[u8]> = updates.iter().map(Vec::as_slice).collect();
+        let merged = merge_updates_v1(&updates)?;
+        let sv = encode_state_vector_from_update_v1(&merged)?;
+        self.replace_updates(name, &merged, &sv)?;
+        Ok(merged)
+    }
+
+    /// Returns a state vector of a document with a given `name`. If there's no valid state vector
+    /// stored, a document is compacted first.
+    fn state_vector(&mut self, name: &str) -> Result<StateVector, Self::Error> {
+        let sv = match self.get_state_vector(name)? {
+            Some(sv) => sv,
+            None => {
+                let merged = self.compact(name)?;
+                encode_state_vector_from_update_v1(&merged)?
+            }
+        };
+        Ok(StateVector::decode_v1(sv.as_slice())?)
+    }
+
+    /// Returns a lib0 v1 encoded update containing all changes of a document with a given `name`,
+    /// which are not included in a given state vector.
+    fn diff(&self, name: &str, state_vector: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        let updates = self.get_updates(name)?;
+        let updates: Vec<&[u8]> = updates.iter().map(Vec::as_slice).collect();
+        let merged = merge_updates_v1(&updates)?;
+        Ok(diff_updates_v1(&merged, state_vector)?)
+    }
+}
+
+/// Reference [DocStore] implementation, which keeps all of the data in memory.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore {
+    docs: HashMap<String, StoredDoc>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct StoredDoc {
+    updates: Vec<Vec<u8>>,
+    state_vector: Option<Vec<u8>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DocStore for MemoryStore {
+    type Error = Error;
+
+    fn get_updates(&self, name: &str) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self
+            .docs
+            .get(name)
+            .map(|doc| doc.updates.clone())
+            .unwrap_or_default())
+    }
+
+    fn append_update(&mut self, name: &str, update: &[u8]) -> Result<usize, Self::Error> {
+        let doc = self.docs.entry(name.to_string()).or_default();
+        doc.updates.push(update.to_vec());
+        doc.state_vector = None;
+        Ok(doc.updates.len())
+    }
+
+    fn replace_updates(
+        &mut self,
+        name: &str,
+        update: &[u8],
+        state_vector: &[u8],
+    ) -> Result<(), Self::Error> {
+        let doc = self.docs.entry(name.to_string()).or_default();
+        doc.updates = vec![update.to_vec()];
+        doc.state_vector = Some(state_vector.to_vec());
+        Ok(())
+    }
+
+    fn get_state_vector(&self, name: &str) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.docs.get(name).and_then(|doc| doc.state_vector.clone()))
+    }
+
+    fn clear_document(&mut self, name: &str) -> Result<(), Self::Error> {
+        self.docs.remove(name);
+        Ok(())
+    }
+
+    fn doc_names(&self) -> Result<Vec<String>, Self::Error> {
+        Ok(self.docs.keys().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::persistence::{DocStore, MemoryStore, PREFERRED_TRIM_SIZE};
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, StateVector, Update};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn store_and_load() {
+        let store = Rc::new(RefCell::new(MemoryStore::new()));
+        let d1 = Doc::with_client_id(1);
+        let _sub = {
+            let store = store.clone();
+            d1.observe_update_v1(move |_, e| {
+                store.borrow_mut().store_update("doc", &e.update).unwrap();
+            })
+        };
+        let txt = d1.transact().get_text("text");
+        for i in 0..(PREFERRED_TRIM_SIZE + 10) {
+            txt.insert(&mut d1.transact(), i as u32, "a");
+        }
+
+        let mut store = store.borrow_mut();
+        // log has been compacted once it reached its limit
+        assert_eq!(store.get_updates("doc").unwrap().len(), 11);
+        assert_eq!(store.doc_names().unwrap(), vec!["doc".to_string()]);
+        assert_eq!(
+            store.state_vector("doc").unwrap(),
+            d1.transact().state_vector()
+        );
+        assert_eq!(store.get_updates("doc").unwrap().len(), 1);
+
+        let d2 = Doc::with_client_id(2);
+        let mut txn = d2.transact();
+        store.load_doc("doc", &mut txn).unwrap();
+        assert_eq!(txn.get_text("text").to_string(), txt.to_string());
+
+        let sv = StateVector::default().encode_v1();
+        let diff = store.diff("doc", &sv).unwrap();
+        let d3 = Doc::with_client_id(3);
+        let mut txn = d3.transact();
+        txn.apply_update(Update::decode_v1(diff.as_slice()).unwrap());
+        assert_eq!(txn.get_text("text").to_string(), txt.to_string());
+
+        store.clear_document("doc").unwrap();
+        assert!(store.get_updates("doc").unwrap().is_empty());
+        assert!(store.doc_names().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::persistence::DocStore;
+use ::sled::transaction::{ConflictableTransactionError, TransactionError};
+use ::sled::{Db, Transactional, Tree};
+use std::convert::TryInto;
+
+/// Error returned by [SledStore].
+#[derive(Debug)]
+pub enum SledStoreError {
+    /// Underlying database operation failed.
+    Db(::sled::Error),
+    /// Stored updates could not be decoded.
+    Decode(lib0::error::Error),
+}
+
+impl std::fmt::Display for SledStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SledStoreError::Db(e) => write!(f, "sled database error: {}", e),
+            SledStoreError::Decode(e) => write!(f, "failed to decode stored update: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SledStoreError {}
+
+impl From<::sled::Error> for SledStoreError {
+    fn from(e: ::sled::Error) -> Self {
+        SledStoreError::Db(e)
+    }
+}
+
+impl From<lib0::error::Error> for SledStoreError {
+    fn from(e: lib0::error::Error) -> Self {
+        SledStoreError::Decode(e)
+    }
+}
+
+impl From<TransactionError> for SledStoreError {
+    fn from(e: TransactionError) -> Self {
+        match e {
+            TransactionError::Abort(e) | TransactionError::Storage(e) => SledStoreError::Db(e),
+        }
+    }
+}
+
+/// [DocStore] backend built on top of [sled](https://docs.rs/sled) embedded database. Enabled with
+/// `sled` feature.
+///
+/// Updates of every document are stored in `updates` tree under keys made of a length-prefixed
+/// document name followed by a big-endian sequence number, so that a document's update log can be
+/// read with a single prefix scan in the order of appends. Compacted state vectors are stored in
+/// `state_vectors` tree. Compaction replaces an update log and stores a state vector within
+/// a single sled transaction, so a crash never leaves a document half-compacted.
+///
+/// Example:
+/// ```rust
+/// use yrs::persistence::{DocStore, SledStore};
+/// use yrs::Doc;
+///
+/// let db = sled::Config::new().temporary(true).open().unwrap();
+/// let mut store = SledStore::new(db).unwrap();
+///
+/// let doc = Doc::new();
+/// let text = doc.transact().get_text("text");
+/// text.push(&mut doc.transact(), "hello");
+/// let update = doc.encode_state_as_update_v1(&Default::default());
+/// store.store_update("my-doc", &update).unwrap();
+///
+/// let loaded = Doc::new();
+/// store.load_doc("my-doc", &mut loaded.transact()).unwrap();
+/// assert_eq!(loaded.transact().get_text("text").to_string(), "hello");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SledStore {
+    db: Db,
+    updates: Tree,
+    state_vectors: Tree,
+}
+
+impl SledStore {
+    /// Creates a new store, which keeps documents inside of a given sled database.
+    pub fn new(db: Db) -> Result<Self, SledStoreError> {
+        let updates = db.open_tree("updates")?;
+        let state_vectors = db.open_tree("state_vectors")?;
+        Ok(SledStore {
+            db,
+            updates,
+            state_vectors,
+        })
+    }
+
+    /// Flushes all pending writes to the disk, returning once they're durable.
+    pub fn flush(&self) -> Result<(), SledStoreError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn last_seq(&self, name: &str) -> Result<Option<u32>, SledStoreError> {
+        match self.updates.scan_prefix(doc_prefix(name)).next_back() {
+            Some(entry) => {
+                let (key, _) = entry?;
+                Ok(Some(key_seq(&key)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn keys(&self, name: &str) -> Result<Vec<::sled::IVec>, SledStoreError> {
+        let mut keys = Vec::new();
+        for key in self.updates.scan_prefix(doc_prefix(name)).keys() {
+            keys.push(key?);
+        }
+        Ok(keys)
+    }
+}
+
+/// Prefix of all update keys of a document with a given `name`.
+fn doc_prefix(name: &str) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(4 + name.len());
+    prefix.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(name.as_bytes());
+    prefix
+}
+
+fn update_key(name: &str, seq: u32) -> Vec<u8> {
+    let mut key = doc_prefix(name);
+    key.extend_from_slice(&seq.to_be_bytes());
+    key
+}
+
+fn key_seq(key: &[u8]) -> u32 {
+    u32::from_be_bytes(key[key.len() - 4..].try_into().unwrap())
+}
+
+fn key_name(key: &[u8]) -> String {
+    let len = u32::from_be_bytes(key[..4].try_into().unwrap()) as usize;
+    String::from_utf8_lossy(&key[4..4 + len]).into_owned()
+}
+
+impl DocStore for SledStore {
+    type Error = SledStoreError;
+
+    fn get_updates(&self, name: &str) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let mut updates = Vec::new();
+        for value in self.updates.scan_prefix(doc_prefix(name)).values() {
+            updates.push(value?.to_vec());
+        }
+        Ok(updates)
+    }
+
+    fn append_update(&mut self, name: &str, update: &[u8]) -> Result<usize, Self::Error> {
+        let seq = match self.last_seq(name)? {
+            Some(seq) => seq + 1,
+            None => 0,
+        };
+        let key = update_key(name, seq);
+        (&self.updates, &self.state_vectors).transaction(|(updates, state_vectors)| {
+            updates.insert(key.as_slice(), update)?;
+            state_vectors.remove(name.as_bytes())?;
+            Ok::<_, ConflictableTransactionError>(())
+        })?;
+        Ok(self.updates.scan_prefix(doc_prefix(name)).count())
+    }
+
+    fn replace_updates(
+        &mut self,
+        name: &str,
+        update: &[u8],
+        state_vector: &[u8],
+    ) -> Result<(), Self::Error> {
+        let keys = self.keys(name)?;
+        let key = update_key(name, 0);
+        (&self.updates, &self.state_vectors).transaction(|(updates, state_vectors)| {
+            for key in keys.iter() {
+                updates.remove(key)?;
+            }
+            updates.insert(key.as_slice(), update)?;
+            state_vectors.insert(name.as_bytes(), state_vector)?;
+            Ok::<_, ConflictableTransactionError>(())
+        })?;
+        Ok(())
+    }
+
+    fn get_state_vector(&self, name: &str) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.state_vectors.get(name)?.map(|sv| sv.to_vec()))
+    }
+
+    fn clear_document(&mut self, name: &str) -> Result<(), Self::Error> {
+        let keys = self.keys(name)?;
+        (&self.updates, &self.state_vectors).transaction(|(updates, state_vectors)| {
+            for key in keys.iter() {
+                updates.remove(key)?;
+            }
+            state_vectors.remove(name.as_bytes())?;
+            Ok::<_, ConflictableTransactionError>(())
+        })?;
+        Ok(())
+    }
+
+    fn doc_names(&self) -> Result<Vec<String>, Self::Error> {
+        let mut names: Vec<String> = Vec::new();
+        for key in self.updates.iter().keys() {
+            let name = key_name(&key?);
+            if names.last() != Some(&name) {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::persistence::{DocStore, SledStore, PREFERRED_TRIM_SIZE};
+    use crate::Doc;
+
+    #[test]
+    fn sled_store_durability_and_compaction() {
+        let path = std::env::temp_dir().join(format!("yrs-sled-{}", rand::random::<u64>()));
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.transact().get_text("text");
+        {
+            let mut store = SledStore::new(::sled::open(&path).unwrap()).unwrap();
+            for i in 0..(PREFERRED_TRIM_SIZE + 10) {
+                let sv = d1.transact().state_vector();
+                txt.insert(&mut d1.transact(), i as u32, "a");
+                let update = d1.encode_state_as_update_v1(&sv);
+                store.store_update("doc", &update).unwrap();
+            }
+            // names sharing a prefix are kept apart
+            store.store_update("doc2", &[0, 0]).unwrap();
+            store.flush().unwrap();
+        }
+
+        // reopen a database from the disk
+        let mut store = SledStore::new(::sled::open(&path).unwrap()).unwrap();
+        // log has been compacted once it reached its limit
+        assert_eq!(store.get_updates("doc").unwrap().len(), 11);
+        assert!(store.get_state_vector("doc").unwrap().is_none());
+        assert_eq!(
+            store.doc_names().unwrap(),
+            vec!["doc".to_string(), "doc2".to_string()]
+        );
+        assert_eq!(
+            store.state_vector("doc").unwrap(),
+            d1.transact().state_vector()
+        );
+        assert_eq!(store.get_updates("doc").unwrap().len(), 1);
+        assert!(store.get_state_vector("doc").unwrap().is_some());
+
+        let d2 = Doc::with_client_id(2);
+        store.load_doc("doc", &mut d2.transact()).unwrap();
+        assert_eq!(d2.transact().get_text("text").to_string(), txt.to_string());
+
+        // appending after compaction invalidates a state vector
+        store.store_update("doc", &[0, 0]).unwrap();
+        assert_eq!(store.get_updates("doc").unwrap().len(), 2);
+        assert!(store.get_state_vector("doc").unwrap().is_none());
+
+        store.clear_document("doc").unwrap();
+        assert!(store.get_updates("doc").unwrap().is_empty());
+        assert_eq!(store.doc_names().unwrap(), vec!["doc2".to_string()]);
+        drop(store);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
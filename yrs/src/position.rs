@@ -0,0 +1,435 @@
+//! Relative positions (also known as sticky indexes) allow to point to a place within an indexed
+//! sequence of a shared type (eg. [Text](crate::Text) or [Array](crate::Array)) in a way that
+//! remains valid while concurrent changes are being applied to that sequence. This is a
+//! compatible port of Yjs `RelativePosition`.
+//!
+//! ```
+//! use yrs::Doc;
+//! use yrs::position::{Assoc, RelativePosition};
+//!
+//! let doc = Doc::new();
+//! let mut txn = doc.transact();
+//! let text = txn.get_text("text");
+//! text.insert(&mut txn, 0, "abc");
+//!
+//! // remember a position between "a" and "bc"
+//! let pos = RelativePosition::from_type_index(&txn, &text, 1, Assoc::After).unwrap();
+//! text.insert(&mut txn, 0, "xyz");
+//!
+//! let abs = pos.get_absolute(&txn).unwrap();
+//! assert_eq!(abs.index, 4);
+//! ```
+
+use crate::block::{Block, ItemContent};
+use crate::types::{Branch, BranchPtr, TypePtr};
+use crate::undo::follow_redone;
+use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::encoder::{Encode, Encoder};
+use crate::{Transaction, ID};
+use lib0::any::Any;
+use lib0::error::Error;
+use lib0::number::Signed;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Describes to which side of a position a [RelativePosition] is associated with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Assoc {
+    /// Position sticks to an element placed right after it. It's a default association.
+    #[default]
+    After,
+    /// Position sticks to an element placed right before it.
+    Before,
+}
+
+impl Assoc {
+    fn from_signed(value: &Signed<i32>) -> Self {
+        if value.is_negative() {
+            Assoc::Before
+        } else {
+            Assoc::After
+        }
+    }
+
+    fn to_signed(self) -> Signed<i32> {
+        match self {
+            Assoc::After => Signed::new(0, false),
+            Assoc::Before => Signed::new(-1, true),
+        }
+    }
+
+    fn to_i32(self) -> i32 {
+        match self {
+            Assoc::After => 0,
+            Assoc::Before => -1,
+        }
+    }
+}
+
+/// An anchor of a [RelativePosition].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PositionScope {
+    /// Position is bound to an element identified by a given [ID].
+    Item(ID),
+    /// Position points to either the beginning or the end of a root-level type with a given name.
+    Root(Rc<str>),
+    /// Position points to either the beginning or the end of a nested type, which has been
+    /// inserted as a block with a given [ID].
+    Nested(ID),
+}
+
+/// A position within an indexed sequence of a shared type, which is resilient to concurrent
+/// changes. It can be created from an index using [RelativePosition::from_type_index] and
+/// resolved back into an index using [RelativePosition::get_absolute].
+///
+/// Relative positions can be serialized using either lib0 binary encoding (via [Encode] and
+/// [Decode] traits) or JSON-like [Any] structure - both of which are compatible with Yjs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelativePosition {
+    pub scope: PositionScope,
+    pub assoc: Assoc,
+}
+
+impl RelativePosition {
+    pub fn new(scope: PositionScope, assoc: Assoc) -> Self {
+        RelativePosition { scope, assoc }
+    }
+
+    /// Creates a relative position pointing to a given `index` of an indexed sequence of
+    /// a shared type. Index is counted in the same units as [Branch::len]. If `index` is outside
+    /// of a sequence bounds, a position will point to the end of that sequence.
+    ///
+    /// Returns `None` if given type has not been integrated into a document.
+    pub fn from_type_index<T: AsRef<Branch>>(
+        txn: &Transaction,
+        ty: &T,
+        mut index: u32,
+        assoc: Assoc,
+    ) -> Option<Self> {
+        let branch = ty.as_ref();
+        if assoc == Assoc::Before {
+            if index == 0 {
+                return Self::from_branch(txn, branch, assoc);
+            }
+            index -= 1;
+        }
+
+        let mut current = branch.start;
+        while let Some(Block::Item(item)) = current.as_deref() {
+            if !item.is_deleted() && item.is_countable() {
+                if item.len() > index {
+                    let id = ID::new(item.id.client, item.id.clock + index);
+                    return Some(Self::new(PositionScope::Item(id), assoc));
+                }
+                index -= item.len();
+            }
+            if item.right.is_none() && assoc == Assoc::Before {
+                return Some(Self::new(PositionScope::Item(item.last_id()), assoc));
+            }
+            current = item.right;
+        }
+
+        Self::from_branch(txn, branch, assoc)
+    }
+
+    fn from_branch(txn: &Transaction, branch: &Branch, assoc: Assoc) -> Option<Self> {
+        let scope = match branch.item {
+            Some(ptr) => PositionScope::Nested(*ptr.id()),
+            None => {
+                let name = txn.store().get_type_key(BranchPtr::from(branch))?;
+                PositionScope::Root(name.clone())
+            }
+        };
+        Some(Self::new(scope, assoc))
+    }
+
+    /// Resolves current relative position into an index within a shared type it points to.
+    /// Returns `None` if a position could not be resolved, ie. because its anchor was not yet
+    /// integrated into a document store.
+    pub fn get_absolute(&self, txn: &Transaction) -> Option<AbsolutePosition> {
+        let store = txn.store();
+        let (branch, index) = match &self.scope {
+            PositionScope::Item(id) => {
+                if store.blocks.get_state(&id.client) <= id.clock {
+                    return None;
+                }
+                let (ptr, diff) = follow_redone(store, id)?;
+                let item = ptr.as_item()?;
+                let branch = if let TypePtr::Branch(branch) = item.parent {
+                    branch
+                } else {
+                    return None;
+                };
+                let mut index = 0;
+                let parent_deleted = match branch.item.as_deref() {
+                    Some(Block::Item(parent)) => parent.is_deleted(),
+                    _ => false,
+                };
+                if !parent_deleted {
+                    if !item.is_deleted() && item.is_countable() {
+                        index = diff
+                            + match self.assoc {
+                                Assoc::After => 0,
+                                Assoc::Before => 1,
+                            };
+                    }
+                    let mut left = item.left;
+                    while let Some(Block::Item(item)) = left.as_deref() {
+                        if !item.is_deleted() && item.is_countable() {
+                            index += item.len();
+                        }
+                        left = item.left;
+                    }
+                }
+                (branch, index)
+            }
+            PositionScope::Root(name) => {
+                let branch = store.get_type(name.clone())?;
+                (branch, self.type_edge(branch))
+            }
+            PositionScope::Nested(id) => {
+                if store.blocks.get_state(&id.client) <= id.clock {
+                    return None;
+                }
+                let (ptr, _) = follow_redone(store, id)?;
+                if let ItemContent::Type(branch) = &ptr.as_item()?.content {
+                    let branch = BranchPtr::from(branch);
+                    (branch, self.type_edge(branch))
+                } else {
+                    return None;
+                }
+            }
+        };
+        Some(AbsolutePosition {
+            branch,
+            index,
+            assoc: self.assoc,
+        })
+    }
+
+    /// Returns an index of either the end or the beginning of a given type, depending on
+    /// current position association.
+    fn type_edge(&self, branch: BranchPtr) -> u32 {
+        match self.assoc {
+            Assoc::After => branch.len(),
+            Assoc::Before => 0,
+        }
+    }
+
+    /// Converts current relative position into a JSON-like structure compatible with the one
+    /// produced by Yjs `relativePositionToJSON`.
+    pub fn to_json(&self) -> Any {
+        fn id_to_json(id: &ID) -> Any {
+            let mut map = HashMap::new();
+            map.insert("client".to_string(), Any::Number(id.client as f64));
+            map.insert("clock".to_string(), Any::Number(id.clock as f64));
+            Any::Map(Box::new(map))
+        }
+
+        let mut map = HashMap::new();
+        match &self.scope {
+            PositionScope::Item(id) => {
+                map.insert("item".to_string(), id_to_json(id));
+            }
+            PositionScope::Root(name) => {
+                map.insert("tname".to_string(), Any::String(name.as_ref().into()));
+            }
+            PositionScope::Nested(id) => {
+                map.insert("type".to_string(), id_to_json(id));
+            }
+        }
+        map.insert("assoc".to_string(), Any::Number(self.assoc.to_i32() as f64));
+        Any::Map(Box::new(map))
+    }
+
+    /// Reads a relative position from a JSON-like structure produced by [RelativePosition::to_json]
+    /// or Yjs `relativePositionToJSON`. Returns `None` if a structure is malformed.
+    pub fn from_json(json: &Any) -> Option<Self> {
+        fn number(value: &Any) -> Option<i64> {
+            match value {
+                Any::Number(n) => Some(*n as i64),
+                Any::BigInt(n) => Some(*n),
+                _ => None,
+            }
+        }
+
+        fn id_from_json(value: &Any) -> Option<ID> {
+            if let Any::Map(map) = value {
+                let client = number(map.get("client")?)?;
+                let clock = number(map.get("clock")?)?;
+                Some(ID::new(client as u64, clock as u32))
+            } else {
+                None
+            }
+        }
+
+        let map = if let Any::Map(map) = json {
+            map
+        } else {
+            return None;
+        };
+        let scope = if let Some(item) = map.get("item").filter(|v| !is_empty(v)) {
+            PositionScope::Item(id_from_json(item)?)
+        } else if let Some(Any::String(name)) = map.get("tname") {
+            PositionScope::Root(name.as_ref().into())
+        } else if let Some(ty) = map.get("type").filter(|v| !is_empty(v)) {
+            PositionScope::Nested(id_from_json(ty)?)
+        } else {
+            return None;
+        };
+        let assoc = match map.get("assoc").and_then(number) {
+            Some(n) if n < 0 => Assoc::Before,
+            _ => Assoc::After,
+        };
+        Some(Self::new(scope, assoc))
+    }
+}
+
+fn is_empty(value: &Any) -> bool {
+    matches!(value, Any::Null | Any::Undefined)
+}
+
+impl Encode for RelativePosition {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        match &self.scope {
+            PositionScope::Item(id) => {
+                encoder.write_var(0u8);
+                encoder.write_var(id.client);
+                encoder.write_var(id.clock);
+            }
+            PositionScope::Root(name) => {
+                encoder.write_var(1u8);
+                encoder.write_string(name);
+            }
+            PositionScope::Nested(id) => {
+                encoder.write_var(2u8);
+                encoder.write_var(id.client);
+                encoder.write_var(id.clock);
+            }
+        }
+        encoder.write_var_signed(&self.assoc.to_signed());
+    }
+}
+
+impl Decode for RelativePosition {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let scope = match decoder.read_var::<u8>()? {
+            0 => {
+                let client = decoder.read_var()?;
+                let clock = decoder.read_var()?;
+                PositionScope::Item(ID::new(client, clock))
+            }
+            1 => PositionScope::Root(decoder.read_string()?.into()),
+            2 => {
+                let client = decoder.read_var()?;
+                let clock = decoder.read_var()?;
+                PositionScope::Nested(ID::new(client, clock))
+            }
+            _ => return Err(Error::UnexpectedValue),
+        };
+        // assoc is optional - positions encoded by older Yjs versions don't have it
+        let assoc = match decoder.read_var_signed::<i32>() {
+            Ok(value) => Assoc::from_signed(&value),
+            Err(Error::EndOfBuffer(_)) => Assoc::After,
+            Err(e) => return Err(e),
+        };
+        Ok(Self::new(scope, assoc))
+    }
+}
+
+/// A [RelativePosition] resolved into an index within a shared type at the current state of
+/// a document.
+#[derive(Debug, Clone)]
+pub struct AbsolutePosition {
+    /// Shared type which current position points to.
+    pub branch: BranchPtr,
+    /// Index within an indexed sequence of a shared type, counted in the same units as
+    /// [Branch::len].
+    pub index: u32,
+    pub assoc: Assoc,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::position::{Assoc, PositionScope, RelativePosition};
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, Update, ID};
+
+    #[test]
+    fn resolve_after_concurrent_changes() {
+        let d1 = Doc::with_client_id(1);
+        let mut t1 = d1.transact();
+        let txt1 = t1.get_text("text");
+        txt1.insert(&mut t1, 0, "abcdef");
+
+        let d2 = Doc::with_client_id(2);
+        let mut t2 = d2.transact();
+        let txt2 = t2.get_text("text");
+        t2.apply_update(Update::decode_v1(&t1.encode_update_v1()).unwrap());
+
+        let after = RelativePosition::from_type_index(&t1, &txt1, 3, Assoc::After).unwrap();
+        let before = RelativePosition::from_type_index(&t1, &txt1, 3, Assoc::Before).unwrap();
+        let start = RelativePosition::from_type_index(&t1, &txt1, 0, Assoc::Before).unwrap();
+        let end = RelativePosition::from_type_index(&t1, &txt1, 6, Assoc::After).unwrap();
+        assert_eq!(after.scope, PositionScope::Item(ID::new(1, 3)));
+        assert_eq!(before.scope, PositionScope::Item(ID::new(1, 2)));
+        assert_eq!(start.scope, PositionScope::Root("text".into()));
+        assert_eq!(end.scope, PositionScope::Root("text".into()));
+
+        // insert at the position itself: assoc decides on which side it stays
+        txt2.insert(&mut t2, 3, "xyz");
+        txt2.insert(&mut t2, 0, "12");
+        txt2.remove_range(&mut t2, 9, 1);
+        assert_eq!(txt2.to_string(), "12abcxyzdf");
+
+        assert_eq!(after.get_absolute(&t2).unwrap().index, 8);
+        assert_eq!(before.get_absolute(&t2).unwrap().index, 5);
+        assert_eq!(start.get_absolute(&t2).unwrap().index, 0);
+        assert_eq!(end.get_absolute(&t2).unwrap().index, 10);
+
+        txt2.insert(&mut t2, 10, "!");
+        let pos = RelativePosition::from_type_index(&t2, &txt2, 11, Assoc::Before).unwrap();
+        assert_eq!(pos.get_absolute(&t2).unwrap().index, 11);
+
+        // position has not been integrated yet
+        assert!(pos.get_absolute(&t1).is_none());
+    }
+
+    #[test]
+    fn nested_type_position() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let map = txn.get_map("map");
+        map.insert(&mut txn, "array", crate::PrelimArray::from(vec![1, 2, 3]));
+        let array = map.get("array").unwrap().to_yarray().unwrap();
+
+        let pos = RelativePosition::from_type_index(&txn, &array, 3, Assoc::After).unwrap();
+        assert_eq!(pos.scope, PositionScope::Nested(ID::new(1, 0)));
+        array.push_back(&mut txn, 4);
+        assert_eq!(pos.get_absolute(&txn).unwrap().index, 4);
+    }
+
+    #[test]
+    fn yjs_compatible_encoding() {
+        let item = RelativePosition::new(PositionScope::Item(ID::new(1, 3)), Assoc::Before);
+        let root = RelativePosition::new(PositionScope::Root("text".into()), Assoc::After);
+        let nested = RelativePosition::new(PositionScope::Nested(ID::new(2, 1)), Assoc::After);
+
+        // bytes produced by Yjs `encodeRelativePosition`
+        assert_eq!(item.encode_v1(), vec![0, 1, 3, 0x41]);
+        assert_eq!(root.encode_v1(), vec![1, 4, b't', b'e', b'x', b't', 0]);
+        assert_eq!(nested.encode_v1(), vec![2, 2, 1, 0]);
+
+        for pos in [item, root, nested].iter() {
+            let decoded = RelativePosition::decode_v1(&pos.encode_v1()).unwrap();
+            assert_eq!(&decoded, pos);
+            let decoded = RelativePosition::from_json(&pos.to_json()).unwrap();
+            assert_eq!(&decoded, pos);
+        }
+
+        // positions encoded by older Yjs versions don't carry assoc
+        let decoded = RelativePosition::decode_v1(&[0, 1, 3]).unwrap();
+        assert_eq!(decoded.assoc, Assoc::After);
+    }
+}
@@ -0,0 +1,284 @@
+//! Registry of documents kept open by a server, which serves many of them at once.
+//!
+//! [DocRegistry] loads documents on demand from a [DocStore], keeps track of which of them are
+//! currently in use and evicts the least recently used idle ones once there are too many of them
+//! open, writing their state back to the storage first.
+
+use crate::event::Subscription;
+use crate::persistence::DocStore;
+use crate::updates::encoder::Encode;
+use crate::{Doc, DocRef, Options, StateVector, UpdateEvent};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// A registry of open documents, backed by a [DocStore].
+///
+/// Documents are loaded from a storage the first time they're requested with
+/// [DocRegistry::get] and stay open until they're evicted. A document is in use as long as any
+/// of the [DocLease]s returned for it is alive. Once a number of open documents exceeds the
+/// registry's capacity, the least recently used documents, which are not in use, are evicted.
+/// Documents which have been changed since they were loaded are written back to the storage
+/// before being evicted.
+///
+/// Example:
+/// ```rust
+/// use yrs::persistence::MemoryStore;
+/// use yrs::registry::DocRegistry;
+///
+/// let mut registry = DocRegistry::new(MemoryStore::new(), 1);
+/// {
+///     let doc = registry.get("a").unwrap();
+///     let text = doc.transact().get_text("text");
+///     text.push(&mut doc.transact(), "hello");
+/// }
+/// // document "a" is idle, so it's evicted (and persisted) to make room for "b"
+/// registry.get("b").unwrap();
+/// assert!(!registry.is_loaded("a"));
+///
+/// let doc = registry.get("a").unwrap();
+/// assert_eq!(doc.transact().get_text("text").to_string(), "hello");
+/// ```
+pub struct DocRegistry<S: DocStore> {
+    store: S,
+    options: Options,
+    capacity: usize,
+    clock: u64,
+    docs: HashMap<String, Entry>,
+}
+
+struct Entry {
+    doc: DocRef,
+    token: Rc<()>,
+    last_used: u64,
+    changed: Rc<Cell<bool>>,
+    _subscription: Subscription<UpdateEvent>,
+}
+
+impl Entry {
+    fn in_use(&self) -> bool {
+        Rc::strong_count(&self.token) > 1
+    }
+}
+
+impl<S: DocStore> DocRegistry<S> {
+    /// Creates a new registry, which keeps at most `capacity` documents open, unless more of them
+    /// are in use at the same time.
+    pub fn new(store: S, capacity: usize) -> Self {
+        Self::with_options(store, capacity, Options::default())
+    }
+
+    /// Creates a new registry, which keeps at most `capacity` documents open, unless more of them
+    /// are in use at the same time. All documents are created using a given `options`, except
+    /// their client identifiers, which are generated for every document separately.
+    pub fn with_options(store: S, capacity: usize, options: Options) -> Self {
+        DocRegistry {
+            store,
+            options,
+            capacity,
+            clock: 0,
+            docs: HashMap::new(),
+        }
+    }
+
+    /// Returns a storage used by current registry.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns a mutable reference to a storage used by current registry. Changes made directly
+    /// to a storage are not visible in the documents, which are already open.
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// Returns a number of currently open documents.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Checks if there are no open documents.
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Checks if a document with a given `name` is currently open.
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.docs.contains_key(name)
+    }
+
+    /// Checks if a document with a given `name` is open and used by any [DocLease].
+    pub fn is_in_use(&self, name: &str) -> bool {
+        matches!(self.docs.get(name), Some(entry) if entry.in_use())
+    }
+
+    /// Returns a document with a given `name`, loading it from a storage if it's not open yet.
+    /// Documents which don't exist in a storage are created empty. Opening a new document may
+    /// cause other idle documents to be evicted.
+    pub fn get(&mut self, name: &str) -> Result<DocLease, S::Error> {
+        self.clock += 1;
+        if let Some(entry) = self.docs.get_mut(name) {
+            entry.last_used = self.clock;
+            return Ok(DocLease {
+                doc: entry.doc.clone(),
+                _token: entry.token.clone(),
+            });
+        }
+
+        let mut doc = Doc::with_options(self.options.clone());
+        self.store.load_doc(name, &mut doc.transact())?;
+        // loaded updates may contain changes made under the template's client id
+        doc.regenerate_client_id();
+        let changed = Rc::new(Cell::new(false));
+        let subscription = {
+            let changed = changed.clone();
+            doc.observe_update_v1(move |_, _| changed.set(true))
+        };
+        let doc = DocRef::from(doc);
+        let token = Rc::new(());
+        let lease = DocLease {
+            doc: doc.clone(),
+            _token: token.clone(),
+        };
+        self.docs.insert(
+            name.to_string(),
+            Entry {
+                doc,
+                token,
+                last_used: self.clock,
+                changed,
+                _subscription: subscription,
+            },
+        );
+        self.evict_idle()?;
+        Ok(lease)
+    }
+
+    /// Writes a state of a document with a given `name` back to a storage, if it has been changed
+    /// since it was loaded or flushed for the last time. Returns `true` if a document has been
+    /// written.
+    pub fn flush(&mut self, name: &str) -> Result<bool, S::Error> {
+        match self.docs.get(name) {
+            Some(entry) => Self::write(&mut self.store, name, entry),
+            None => Ok(false),
+        }
+    }
+
+    /// Writes states of all changed open documents back to a storage.
+    pub fn flush_all(&mut self) -> Result<(), S::Error> {
+        for (name, entry) in self.docs.iter() {
+            Self::write(&mut self.store, name, entry)?;
+        }
+        Ok(())
+    }
+
+    /// Closes a document with a given `name`, writing its state back to a storage if it has been
+    /// changed. Documents which are in use are not evicted. Returns `true` if a document has been
+    /// evicted.
+    pub fn evict(&mut self, name: &str) -> Result<bool, S::Error> {
+        match self.docs.get(name) {
+            Some(entry) if !entry.in_use() => {
+                Self::write(&mut self.store, name, entry)?;
+                self.docs.remove(name);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Evicts the least recently used idle documents until a number of open documents fits into
+    /// a registry's capacity, or there are no more idle documents left.
+    fn evict_idle(&mut self) -> Result<(), S::Error> {
+        if self.docs.len() <= self.capacity {
+            return Ok(());
+        }
+        let mut idle: Vec<_> = self
+            .docs
+            .iter()
+            .filter(|(_, entry)| !entry.in_use())
+            .map(|(name, entry)| (entry.last_used, name.clone()))
+            .collect();
+        idle.sort_unstable();
+        let excess = self.docs.len() - self.capacity;
+        for (_, name) in idle.into_iter().take(excess) {
+            self.evict(&name)?;
+        }
+        Ok(())
+    }
+
+    fn write(store: &mut S, name: &str, entry: &Entry) -> Result<bool, S::Error> {
+        if !entry.changed.get() {
+            return Ok(false);
+        }
+        let update = entry.doc.encode_state_as_update_v1(&StateVector::default());
+        let state_vector = entry.doc.transact().state_vector().encode_v1();
+        store.replace_updates(name, &update, &state_vector)?;
+        entry.changed.set(false);
+        Ok(true)
+    }
+}
+
+/// A document returned by [DocRegistry::get]. As long as it's alive, a document is considered to
+/// be in use and won't be evicted from its registry.
+#[derive(Clone)]
+pub struct DocLease {
+    doc: DocRef,
+    _token: Rc<()>,
+}
+
+impl Deref for DocLease {
+    type Target = Doc;
+
+    fn deref(&self) -> &Self::Target {
+        &self.doc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::persistence::{DocStore, MemoryStore};
+    use crate::registry::DocRegistry;
+    use crate::Doc;
+
+    #[test]
+    fn load_on_demand_and_evict() {
+        let mut registry = DocRegistry::new(MemoryStore::new(), 2);
+        for name in ["a", "b"] {
+            let doc = registry.get(name).unwrap();
+            let text = doc.transact().get_text("text");
+            text.push(&mut doc.transact(), name);
+        }
+        assert_eq!(registry.len(), 2);
+        // nothing has been evicted yet
+        assert!(registry.store().doc_names().unwrap().is_empty());
+
+        let c = registry.get("c").unwrap();
+        assert!(registry.is_in_use("c"));
+        // "a" was the least recently used one
+        assert!(!registry.is_loaded("a"));
+        assert!(registry.is_loaded("b"));
+        assert_eq!(registry.store().doc_names().unwrap(), vec!["a".to_string()]);
+
+        // documents in use are never evicted
+        let _b = registry.get("b").unwrap();
+        registry.get("d").unwrap();
+        assert_eq!(registry.len(), 3);
+        assert!(!registry.evict("c").unwrap());
+        drop(c);
+        assert!(registry.evict("c").unwrap());
+
+        let a = registry.get("a").unwrap();
+        let text = a.transact().get_text("text");
+        assert_eq!(text.to_string(), "a");
+        text.push(&mut a.transact(), "!");
+        assert!(registry.flush("a").unwrap());
+        // unchanged documents are not written again
+        assert!(!registry.flush("a").unwrap());
+        assert!(!registry.flush("d").unwrap());
+
+        let doc = Doc::new();
+        registry.store().load_doc("a", &mut doc.transact()).unwrap();
+        assert_eq!(doc.transact().get_text("text").to_string(), "a!");
+    }
+}
@@ -0,0 +1,323 @@
+use crate::block::ClientID;
+use crate::updates::decoder::Decode;
+use crate::updates::encoder::Encode;
+use crate::{diff_updates_v1, merge_updates_v1, Doc, OffsetKind, Options, StateVector, Update};
+use std::sync::Arc;
+
+/// A read-only replica of a [Doc], created with [Doc::replica]. Unlike a document itself, which is
+/// built on top of non-thread-safe pointers, a replica is an immutable snapshot of a document's
+/// state, which can be cheaply cloned and handed to other threads for queries and encoding, while
+/// the primary document continues to accept writes.
+///
+/// Replicas are copy-on-write at the granularity of a document's epoch: every committed transaction
+/// which changes a document starts a new epoch. All replicas created within the same epoch share
+/// a single snapshot, which is only taken again once a primary document has been changed. A new
+/// snapshot shares the already encoded state with the previous one and only encodes changes made
+/// since then. Replicas created before a change are not affected by it.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::Doc;
+/// use std::thread;
+///
+/// let doc = Doc::new();
+/// let text = doc.transact().get_text("text");
+/// text.push(&mut doc.transact(), "hello");
+///
+/// let replica = doc.replica();
+/// let reader = thread::spawn(move || {
+///     let doc = replica.to_doc();
+///     let text = doc.transact().get_text("text");
+///     text.to_string()
+/// });
+/// // primary document keeps accepting writes
+/// text.push(&mut doc.transact(), " world");
+///
+/// assert_eq!(reader.join().unwrap(), "hello");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Replica {
+    snapshot: Arc<ReplicaSnapshot>,
+}
+
+/// Maximum number of updates kept by a single replica snapshot. Once exceeded, all of them are
+/// merged into one.
+const MAX_REPLICA_UPDATES: usize = 16;
+
+#[derive(Debug)]
+pub(crate) struct ReplicaSnapshot {
+    epoch: u64,
+    options: ReplicaOptions,
+    state_vector: StateVector,
+    /// lib0 v1 encoded updates, which applied in order recreate a replicated document. They are
+    /// shared with snapshots of the previous epochs, so that each new snapshot only has to encode
+    /// changes made since the last one.
+    updates: Vec<Arc<[u8]>>,
+}
+
+/// Subset of a primary document [Options], which can be shared between threads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReplicaOptions {
+    client_id: ClientID,
+    guid: Arc<str>,
+    offset_kind: OffsetKind,
+    skip_gc: bool,
+    should_load: bool,
+    auto_load: bool,
+    keep_map_conflicts: bool,
+}
+
+impl ReplicaOptions {
+    fn new(options: &Options) -> Self {
+        ReplicaOptions {
+            client_id: options.client_id,
+            guid: options.guid.as_ref().into(),
+            offset_kind: options.offset_kind,
+            skip_gc: options.skip_gc,
+            should_load: options.should_load,
+            auto_load: options.auto_load,
+            keep_map_conflicts: options.keep_map_conflicts,
+        }
+    }
+
+    fn to_options(&self) -> Options {
+        Options {
+            guid: self.guid.as_ref().into(),
+            offset_kind: self.offset_kind,
+            skip_gc: self.skip_gc,
+            should_load: self.should_load,
+            auto_load: self.auto_load,
+            keep_map_conflicts: self.keep_map_conflicts,
+            ..Options::with_client_id(self.client_id)
+        }
+    }
+}
+
+impl Replica {
+    pub(crate) fn new(doc: &Doc) -> Self {
+        let mut store = doc.store.clone();
+        let mut updates = Vec::new();
+        if let Some(snapshot) = store.replica.as_ref() {
+            if snapshot.epoch == store.epoch {
+                return Replica {
+                    snapshot: snapshot.clone(),
+                };
+            }
+            updates = snapshot.updates.clone();
+        }
+        let state_vector = store.blocks.get_state_vector();
+        let since = match store.replica.as_ref() {
+            Some(snapshot) => snapshot.state_vector.clone(),
+            None => StateVector::default(),
+        };
+        updates.push(doc.encode_state_as_update_v1(&since).into());
+        if updates.len() > MAX_REPLICA_UPDATES {
+            let merged = Self::merge(&updates);
+            updates = vec![merged.into()];
+        }
+        let snapshot = Arc::new(ReplicaSnapshot {
+            epoch: store.epoch,
+            options: ReplicaOptions::new(&store.options),
+            state_vector,
+            updates,
+        });
+        store.replica = Some(snapshot.clone());
+        Replica { snapshot }
+    }
+
+    fn merge(updates: &[Arc<[u8]>]) -> Vec<u8> {
+        match updates {
+            [update] => update.to_vec(),
+            updates => {
+                let updates: Vec<&[u8]> = updates.iter().map(|u| u.as_ref()).collect();
+                merge_updates_v1(&updates).expect("replica snapshot is a valid update")
+            }
+        }
+    }
+
+    /// Returns an epoch of a primary document at which current replica has been created. Epoch
+    /// of a document is incremented with every committed transaction that changes it.
+    pub fn epoch(&self) -> u64 {
+        self.snapshot.epoch
+    }
+
+    /// Checks if a given `doc` has not been changed since current replica was created from it.
+    pub fn is_current(&self, doc: &Doc) -> bool {
+        match doc.store.replica.as_ref() {
+            Some(snapshot) => {
+                Arc::ptr_eq(snapshot, &self.snapshot) && doc.store.epoch == snapshot.epoch
+            }
+            None => false,
+        }
+    }
+
+    /// Checks if current replica shares its snapshot with `other` replica.
+    pub fn ptr_eq(&self, other: &Replica) -> bool {
+        Arc::ptr_eq(&self.snapshot, &other.snapshot)
+    }
+
+    /// Returns a state vector of a document at the moment when current replica was created.
+    pub fn state_vector(&self) -> &StateVector {
+        &self.snapshot.state_vector
+    }
+
+    /// Returns a complete state of a replicated document encoded as an update using lib0 v1
+    /// encoding.
+    pub fn update_v1(&self) -> Vec<u8> {
+        Self::merge(&self.snapshot.updates)
+    }
+
+    /// Encodes a state of a replicated document, which is not yet known to a remote peer with
+    /// a given `state_vector`, using lib0 v1 encoding. Works just like
+    /// [Doc::encode_state_as_update_v1].
+    pub fn encode_state_as_update_v1(&self, state_vector: &StateVector) -> Vec<u8> {
+        let update = self.update_v1();
+        if state_vector.is_empty() {
+            update
+        } else {
+            diff_updates_v1(&update, &state_vector.encode_v1())
+                .expect("replica snapshot is a valid update")
+        }
+    }
+
+    /// Encodes a state of a replicated document, which is not yet known to a remote peer with
+    /// a given `state_vector`, using lib0 v2 encoding. Works just like
+    /// [Doc::encode_state_as_update_v2].
+    pub fn encode_state_as_update_v2(&self, state_vector: &StateVector) -> Vec<u8> {
+        let update = self.encode_state_as_update_v1(state_vector);
+        Update::decode_v1(&update)
+            .expect("replica snapshot is a valid update")
+            .encode_v2()
+    }
+
+    /// Materializes current replica as a new document on a calling thread, which can be used to
+    /// query its contents. Returned document is detached from a primary one: changes made to either
+    /// of them are not propagated to the other. It uses the same client identifier, guid and
+    /// settings as a primary document, except of [Options::limits] and the ones which cannot be
+    /// shared between threads: callbacks (like [Options::gc_filter]), [Options::timestamps] and
+    /// [Options::codecs].
+    pub fn to_doc(&self) -> Doc {
+        let doc = Doc::with_options(self.snapshot.options.to_options());
+        let mut txn = doc.transact();
+        for update in self.snapshot.updates.iter() {
+            txn.apply_update_v1(update)
+                .expect("replica snapshot is a valid update");
+        }
+        drop(txn);
+        doc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::replica::MAX_REPLICA_UPDATES;
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, OffsetKind, Options, StateVector, Update};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn replica_copy_on_write() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.transact().get_text("text");
+        text.push(&mut doc.transact(), "hello");
+
+        let r1 = doc.replica();
+        let r2 = doc.replica();
+        // replicas created within the same epoch share a snapshot
+        assert!(r1.ptr_eq(&r2));
+        assert!(r1.is_current(&doc));
+
+        // read-only transactions don't start a new epoch
+        text.to_string();
+        doc.transact().state_vector();
+        assert!(doc.replica().ptr_eq(&r1));
+
+        text.push(&mut doc.transact(), " world");
+        assert!(!r1.is_current(&doc));
+        let r3 = doc.replica();
+        assert!(!r3.ptr_eq(&r1));
+        assert!(r3.epoch() > r1.epoch());
+
+        let sv = r1.state_vector().clone();
+        let reader = {
+            let sv = sv.clone();
+            thread::spawn(move || {
+                let old = r1.to_doc().transact().get_text("text").to_string();
+                let new = r3.to_doc().transact().get_text("text").to_string();
+                let diff = r3.encode_state_as_update_v1(&sv);
+                (old, new, diff)
+            })
+        };
+        text.push(&mut doc.transact(), "!");
+        let (old, new, diff) = reader.join().unwrap();
+        assert_eq!(old, "hello");
+        assert_eq!(new, "hello world");
+
+        let expected = Update::decode_v1(&doc.encode_state_as_update_v1(&sv)).unwrap();
+        let diff = Update::decode_v1(&diff).unwrap();
+        assert_eq!(diff.state_vector().get(&1), 11);
+        assert!(expected.state_vector().get(&1) > diff.state_vector().get(&1));
+        assert_eq!(
+            r2.encode_state_as_update_v1(&StateVector::default()),
+            r2.update_v1()
+        );
+    }
+
+    #[test]
+    fn replica_shares_encoded_state() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.transact().get_text("text");
+        text.push(&mut doc.transact(), "a");
+        let r1 = doc.replica();
+        text.push(&mut doc.transact(), "b");
+        let r2 = doc.replica();
+        // new snapshot only encodes changes made since the previous one
+        assert_eq!(r2.snapshot.updates.len(), 2);
+        assert!(Arc::ptr_eq(
+            &r1.snapshot.updates[0],
+            &r2.snapshot.updates[0]
+        ));
+
+        for _ in 0..MAX_REPLICA_UPDATES {
+            text.push(&mut doc.transact(), "c");
+            doc.replica();
+        }
+        let r3 = doc.replica();
+        assert!(r3.snapshot.updates.len() <= MAX_REPLICA_UPDATES);
+        assert_eq!(r3.to_doc().transact().get_text("text").len(), 18);
+        // older replicas are not affected by merging
+        assert_eq!(r1.to_doc().transact().get_text("text").to_string(), "a");
+    }
+
+    #[test]
+    fn replica_keeps_options() {
+        let mut options = Options::with_client_id(1);
+        options.offset_kind = OffsetKind::Utf16;
+        options.skip_gc = true;
+        let doc = Doc::with_options(options);
+        let text = doc.transact().get_text("text");
+        text.push(&mut doc.transact(), "😀 hello");
+        text.remove_range(&mut doc.transact(), 2, 5);
+
+        let replica = doc.replica();
+        let copy = thread::spawn(move || {
+            let doc = replica.to_doc();
+            let text = doc.transact().get_text("text");
+            (
+                text.to_string(),
+                text.len(),
+                doc.client_id,
+                doc.guid().to_string(),
+                doc.store.options.skip_gc,
+            )
+        })
+        .join()
+        .unwrap();
+        assert_eq!(
+            copy,
+            ("😀o".to_string(), 3, 1, doc.guid().to_string(), true)
+        );
+    }
+}
@@ -1,18 +1,21 @@
-use crate::block::{ClientID, ItemContent};
+use crate::block::{BlockPtr, ClientID, ItemContent, StringArena};
 use crate::block_store::{BlockStore, StateVector};
-use crate::doc::Options;
-use crate::event::{AfterTransactionEvent, EventHandler};
-use crate::id_set::DeleteSet;
+use crate::doc::{DocAddr, MemoryUsage, Options, Timestamp};
+use crate::event::{AfterTransactionEvent, EventHandler, SubdocsEvent};
+use crate::id_set::{DeleteSet, IdSet};
+use crate::replica::ReplicaSnapshot;
 use crate::types::{Branch, BranchPtr, Path, PathSegment, TypeRefs};
 use crate::update::PendingUpdate;
 use crate::updates::encoder::{Encode, Encoder};
-use crate::{Snapshot, UpdateEvent};
+use crate::{DocRef, Snapshot, UpdateEvent, ID};
 use lib0::error::Error;
+use rand::Rng;
 use std::cell::UnsafeCell;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
+use std::sync::Arc;
 
 /// Store is a core element of a document. It contains all of the information, like block store
 /// map of root types, pending updates waiting to be applied once a missing update information
@@ -29,6 +32,9 @@ pub(crate) struct Store {
     /// operations) integrated - and therefore visible - into a current document.
     pub(crate) blocks: BlockStore,
 
+    /// Storage for the text contents of integrated blocks. See: [StringArena].
+    pub(crate) strings: StringArena,
+
     /// A pending update. It contains blocks, which are not yet integrated into `blocks`, usually
     /// because due to issues in update exchange, there were some missing blocks that need to be
     /// integrated first before the data from `pending` can be applied safely.
@@ -39,6 +45,14 @@ pub(crate) struct Store {
     /// into `blocks`.
     pub pending_ds: Option<DeleteSet>,
 
+    /// Handles subscriptions for the event triggered once a new transaction has been created,
+    /// before any changes have been applied within its scope.
+    pub(crate) before_transaction_events: Option<EventHandler<()>>,
+
+    /// Handles subscriptions for the event triggered once transaction has been committed, but
+    /// before its changes are garbage collected and compacted.
+    pub(crate) before_cleanup_events: Option<EventHandler<()>>,
+
     /// Handles subscriptions for the `afterTransactionCleanup` event. Events are called with the
     /// newest updates once they are committed and compacted.
     pub(crate) after_transaction_events: Option<EventHandler<AfterTransactionEvent>>,
@@ -50,6 +64,33 @@ pub(crate) struct Store {
     /// A subscription handler. It contains all callbacks with registered by user functions that
     /// are supposed to be called, once a new update arrives.
     pub(crate) update_v2_events: Option<EventHandler<UpdateEvent>>,
+
+    /// A subscription handler for the events triggered when subdocuments are added, removed or
+    /// loaded within a committed transaction.
+    pub(crate) subdocs_events: Option<EventHandler<SubdocsEvent>>,
+
+    /// Subdocuments nested within shared collections of this document.
+    pub(crate) subdocs: HashMap<DocAddr, DocRef>,
+
+    /// If this document is a subdocument, it's a pointer to a block in a parent document,
+    /// which contains it.
+    pub(crate) parent: Option<BlockPtr>,
+
+    /// Timestamps of locally created items, recorded when [Options::timestamps] is set. For every
+    /// client it's a list of `(clock, timestamp)` pairs sorted by clock, where each timestamp
+    /// applies to all elements starting from a given clock until the next entry.
+    pub(crate) timestamps: HashMap<ClientID, Vec<(u32, Timestamp)>>,
+
+    /// Approximate heap memory used by blocks of every client together with a number of blocks
+    /// it was measured for. It's updated whenever a transaction modifying these blocks is
+    /// committed. See: [Doc::memory_usage].
+    pub(crate) memory_usage: HashMap<ClientID, (MemoryUsage, usize)>,
+
+    /// Number of committed transactions, which have changed a state of this document.
+    pub(crate) epoch: u64,
+
+    /// The most recent snapshot shared by read replicas of this document. See: [Doc::replica].
+    pub(crate) replica: Option<Arc<ReplicaSnapshot>>,
 }
 
 impl Store {
@@ -59,11 +100,59 @@ impl Store {
             options,
             types: Default::default(),
             blocks: BlockStore::new(),
+            strings: StringArena::default(),
             pending: None,
             pending_ds: None,
             update_v1_events: None,
             update_v2_events: None,
             after_transaction_events: None,
+            before_transaction_events: None,
+            before_cleanup_events: None,
+            subdocs_events: None,
+            subdocs: HashMap::new(),
+            parent: None,
+            timestamps: HashMap::new(),
+            memory_usage: HashMap::new(),
+            epoch: 0,
+            replica: None,
+        }
+    }
+
+    /// Records a timestamp of a locally created item starting at a given `id`, if timestamping
+    /// has been enabled.
+    pub(crate) fn record_timestamp(&mut self, id: &ID) {
+        if let Some(clock) = &self.options.timestamps {
+            let timestamp = clock.now();
+            let entries = self.timestamps.entry(id.client).or_default();
+            match entries.last() {
+                Some((_, last)) if *last == timestamp => { /* same timestamp, nothing to do */ }
+                _ => entries.push((id.clock, timestamp)),
+            }
+        }
+    }
+
+    /// Returns a timestamp of an element with a given `id`, if it was recorded.
+    pub(crate) fn timestamp(&self, id: &ID) -> Option<Timestamp> {
+        self.timestamp_run(id, 1).0
+    }
+
+    /// Returns a timestamp of an element with a given `id` together with a number of consecutive
+    /// elements (starting from `id`, but no more than `max_len`) which share the same timestamp.
+    pub(crate) fn timestamp_run(&self, id: &ID, max_len: u32) -> (Option<Timestamp>, u32) {
+        let entries = match self.timestamps.get(&id.client) {
+            Some(entries) => entries,
+            None => return (None, max_len),
+        };
+        let idx = entries.partition_point(|(clock, _)| *clock <= id.clock);
+        let next = entries.get(idx).map(|(clock, _)| *clock);
+        let len = match next {
+            Some(next) => max_len.min(next - id.clock),
+            None => max_len,
+        };
+        if idx == 0 || id.clock >= self.blocks.get_state(&id.client) {
+            (None, len)
+        } else {
+            (Some(entries[idx - 1].1), len)
         }
     }
 
@@ -75,6 +164,59 @@ impl Store {
         self.blocks.get_state(&self.options.client_id)
     }
 
+    /// Measures heap memory used by blocks of a given `client` again. See: [Doc::memory_usage].
+    pub(crate) fn update_memory_usage(&mut self, client: &ClientID) {
+        match self.blocks.get(client) {
+            Some(blocks) => {
+                let usage = (blocks.memory_usage(), blocks.len());
+                self.memory_usage.insert(*client, usage);
+            }
+            None => {
+                self.memory_usage.remove(client);
+            }
+        }
+    }
+
+    /// Returns identifiers of clients, which number of blocks has changed since the last time
+    /// their memory usage was measured, ie. because some of their blocks have been split.
+    pub(crate) fn resized_clients(&self) -> Vec<ClientID> {
+        self.blocks
+            .iter()
+            .filter(|(client, blocks)| match self.memory_usage.get(client) {
+                Some((_, len)) => *len != blocks.len(),
+                None => true,
+            })
+            .map(|(client, _)| *client)
+            .collect()
+    }
+
+    /// Checks if a given `client` has created any of the blocks known to a current store,
+    /// including the ones still pending for integration.
+    pub(crate) fn is_known_client(&self, client: &ClientID) -> bool {
+        if self.blocks.get_state(client) > 0 {
+            return true;
+        }
+        match &self.pending {
+            Some(pending) => pending.update.blocks.clients.contains_key(client),
+            None => false,
+        }
+    }
+
+    /// Generates a new client identifier, which was not used by any of the blocks known to
+    /// a current store. Identifiers are produced by [Options::id_generator] if it's defined or
+    /// generated at random otherwise.
+    pub(crate) fn generate_client_id(&self) -> ClientID {
+        loop {
+            let client_id = match &self.options.id_generator {
+                Some(generator) => generator.next_client_id(),
+                None => rand::thread_rng().gen::<u32>() as ClientID,
+            };
+            if !self.is_known_client(&client_id) {
+                return client_id;
+            }
+        }
+    }
+
     /// Returns a branch reference to a complex type identified by its pointer. Returns `None` if
     /// no such type could be found or was ever defined.
     pub fn get_type<K: Into<Rc<str>>>(&self, key: K) -> Option<BranchPtr> {
@@ -183,9 +325,35 @@ impl Store {
         delete_set.encode(encoder);
     }
 
+    /// Compute a diff to sync with another client, just like [Store::encode_diff], but restrict
+    /// it only to blocks and deletions made by clients, which satisfy a given `filter`.
+    pub fn encode_diff_filtered<E, F>(&self, sv: &StateVector, filter: F, encoder: &mut E)
+    where
+        E: Encoder,
+        F: Fn(&ClientID) -> bool,
+    {
+        self.write_blocks_filtered(sv, &filter, encoder);
+        let mut ids = IdSet::new();
+        for (client, range) in DeleteSet::from(&self.blocks).iter() {
+            if filter(client) {
+                ids.insert_range(*client, range.clone());
+            }
+        }
+        DeleteSet::from(ids).encode(encoder);
+    }
+
     pub(crate) fn write_blocks_from<E: Encoder>(&self, sv: &StateVector, encoder: &mut E) {
+        self.write_blocks_filtered(sv, &|_| true, encoder)
+    }
+
+    fn write_blocks_filtered<E, F>(&self, sv: &StateVector, filter: &F, encoder: &mut E)
+    where
+        E: Encoder,
+        F: Fn(&ClientID) -> bool,
+    {
         let local_sv = self.blocks.get_state_vector();
         let mut diff = Self::diff_state_vectors(&local_sv, sv);
+        diff.retain(|(client, _)| filter(client));
 
         // Write items with higher client ids first
         // This heavily improves the conflict algorithm.
@@ -316,6 +484,13 @@ impl DerefMut for StoreRef {
     }
 }
 
+impl StoreRef {
+    /// Returns an address of an underlying store, which is the same for all of its references.
+    pub(crate) fn addr(&self) -> DocAddr {
+        self.0.get() as DocAddr
+    }
+}
+
 impl From<Store> for StoreRef {
     fn from(store: Store) -> Self {
         StoreRef(Rc::new(UnsafeCell::new(store)))
@@ -0,0 +1,547 @@
+//! Implementation of the [y-protocols](https://github.com/yjs/y-protocols) sync protocol, used by
+//! Yjs network providers (like y-websocket) to exchange document updates between peers.
+//!
+//! Synchronization of two peers happens in two steps:
+//!
+//! 1. Peer A sends [SyncMessage::SyncStep1] with its own state vector.
+//! 2. Peer B responds with [SyncMessage::SyncStep2] containing all of the updates, which peer A
+//!    has not seen yet.
+//!
+//! Usually both peers initiate this exchange at the same time. Once synchronized, peers send
+//! each other incremental [SyncMessage::Update]s as they are produced by local transactions.
+//! [Connection] implements this whole exchange (together with awareness propagation) for a
+//! single remote peer and can be used as a building block of network providers.
+//!
+//! All of the messages use lib0 v1 encoding, as defined by y-protocols:
+//!
+//! ```rust
+//! use yrs::Doc;
+//! use yrs::sync::{handle_message, Message, SyncMessage};
+//! use yrs::updates::decoder::Decode;
+//! use yrs::updates::encoder::Encode;
+//!
+//! let d1 = Doc::with_client_id(1);
+//! let text = d1.transact().get_text("text");
+//! text.insert(&mut d1.transact(), 0, "hello");
+//!
+//! let d2 = Doc::with_client_id(2);
+//!
+//! // peer 2 sends its state vector
+//! let step1 = Message::Sync(SyncMessage::SyncStep1(d2.transact().state_vector())).encode_v1();
+//!
+//! // peer 1 responds with missing updates
+//! let msg = Message::decode_v1(step1.as_slice()).unwrap();
+//! let reply = handle_message(&mut d1.transact(), msg).unwrap().unwrap();
+//! let step2 = reply.encode_v1();
+//!
+//! // peer 2 applies them
+//! let msg = Message::decode_v1(step2.as_slice()).unwrap();
+//! let mut txn = d2.transact();
+//! assert!(handle_message(&mut txn, msg).unwrap().is_none());
+//! assert_eq!(txn.get_text("text").to_string(), "hello");
+//! ```
+
+use crate::awareness::{Awareness, AwarenessUpdate};
+use crate::event::{Subscription, SubscriptionId};
+use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::encoder::{Encode, Encoder};
+use crate::{Doc, Origin, StateVector, Transaction, Update, UpdateEvent};
+use lib0::error::Error;
+use rand::RngCore;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Tag id for [Message::Sync].
+pub const MSG_SYNC: u8 = 0;
+
+/// Tag id for [Message::Awareness].
+pub const MSG_AWARENESS: u8 = 1;
+
+/// Tag id for [Message::AwarenessQuery].
+pub const MSG_QUERY_AWARENESS: u8 = 3;
+
+/// Tag id for [SyncMessage::SyncStep1].
+pub const MSG_SYNC_STEP_1: u8 = 0;
+
+/// Tag id for [SyncMessage::SyncStep2].
+pub const MSG_SYNC_STEP_2: u8 = 1;
+
+/// Tag id for [SyncMessage::Update].
+pub const MSG_SYNC_UPDATE: u8 = 2;
+
+/// Top level message, as exchanged by y-protocols based providers. Every message is prefixed
+/// with a tag, which defines the protocol it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Message of a document sync protocol.
+    Sync(SyncMessage),
+
+    /// Message of an awareness protocol, containing states of the peers (see: [Awareness]).
+    Awareness(AwarenessUpdate),
+
+    /// Request for a sender to respond with [Message::Awareness] containing all of the peer
+    /// states it knows about.
+    AwarenessQuery,
+
+    /// Message of a protocol not supported by Yrs itself. Contains a message tag and its raw
+    /// (undecoded) contents.
+    Custom(u8, Vec<u8>),
+}
+
+impl Encode for Message {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        match self {
+            Message::Sync(msg) => {
+                encoder.write_var(MSG_SYNC);
+                msg.encode(encoder);
+            }
+            Message::Awareness(update) => {
+                encoder.write_var(MSG_AWARENESS);
+                encoder.write_buf(update.encode_v1());
+            }
+            Message::AwarenessQuery => {
+                encoder.write_var(MSG_QUERY_AWARENESS);
+            }
+            Message::Custom(tag, data) => {
+                encoder.write_var(*tag);
+                encoder.write_all(data);
+            }
+        }
+    }
+}
+
+impl Decode for Message {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let tag: u8 = decoder.read_var()?;
+        match tag {
+            MSG_SYNC => Ok(Message::Sync(SyncMessage::decode(decoder)?)),
+            MSG_AWARENESS => {
+                let buf = decoder.read_buf()?;
+                Ok(Message::Awareness(AwarenessUpdate::decode_v1(buf)?))
+            }
+            MSG_QUERY_AWARENESS => Ok(Message::AwarenessQuery),
+            tag => {
+                let data = decoder.read_to_end()?;
+                Ok(Message::Custom(tag, data.to_vec()))
+            }
+        }
+    }
+}
+
+/// Message of a document sync protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncMessage {
+    /// Sync step 1 contains a state vector of a sender. Receiver should respond with
+    /// [SyncMessage::SyncStep2].
+    SyncStep1(StateVector),
+
+    /// Sync step 2 contains a v1 encoded update with all of the changes, which the receiver of
+    /// [SyncMessage::SyncStep1] has, but its sender has not seen yet.
+    SyncStep2(Vec<u8>),
+
+    /// Incremental v1 encoded update, usually produced by a single transaction.
+    Update(Vec<u8>),
+}
+
+impl Encode for SyncMessage {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        match self {
+            SyncMessage::SyncStep1(sv) => {
+                encoder.write_var(MSG_SYNC_STEP_1);
+                encoder.write_buf(sv.encode_v1());
+            }
+            SyncMessage::SyncStep2(update) => {
+                encoder.write_var(MSG_SYNC_STEP_2);
+                encoder.write_buf(update);
+            }
+            SyncMessage::Update(update) => {
+                encoder.write_var(MSG_SYNC_UPDATE);
+                encoder.write_buf(update);
+            }
+        }
+    }
+}
+
+impl Decode for SyncMessage {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let tag: u8 = decoder.read_var()?;
+        match tag {
+            MSG_SYNC_STEP_1 => {
+                let buf = decoder.read_buf()?;
+                let sv = StateVector::decode_v1(buf)?;
+                Ok(SyncMessage::SyncStep1(sv))
+            }
+            MSG_SYNC_STEP_2 => {
+                let buf = decoder.read_buf()?;
+                Ok(SyncMessage::SyncStep2(buf.into()))
+            }
+            MSG_SYNC_UPDATE => {
+                let buf = decoder.read_buf()?;
+                Ok(SyncMessage::Update(buf.into()))
+            }
+            _ => Err(Error::UnexpectedValue),
+        }
+    }
+}
+
+/// Handles an incoming sync protocol message in a context of a given transaction. Returns a
+/// message, which should be sent back to the sender, if the protocol requires it:
+///
+/// - [SyncMessage::SyncStep1] is answered with [SyncMessage::SyncStep2] containing all updates
+///   missing on the sender side.
+/// - [SyncMessage::SyncStep2] and [SyncMessage::Update] are applied to a document and don't
+///   require any response.
+///
+/// Updates rejected by a document (ie. by its [WritePolicy](crate::WritePolicy)) are not applied
+/// and fail with [Error::Update], which wraps an [UpdateError](crate::UpdateError) that can be
+/// reported back to the sender.
+pub fn handle_sync_message(
+    txn: &mut Transaction,
+    msg: SyncMessage,
+) -> Result<Option<SyncMessage>, Error> {
+    match msg {
+        SyncMessage::SyncStep1(sv) => {
+            let update = txn.encode_diff_v1(&sv);
+            Ok(Some(SyncMessage::SyncStep2(update)))
+        }
+        SyncMessage::SyncStep2(update) | SyncMessage::Update(update) => {
+            let update = Update::decode_v1(update.as_slice())?;
+            txn.try_apply_update(update)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Handles an incoming top level protocol message in a context of a given transaction (see:
+/// [handle_sync_message]). Returns a message, which should be sent back to the sender, if the
+/// protocol requires it. Awareness and [Message::Custom] messages are ignored (see:
+/// [handle_awareness_message]).
+pub fn handle_message(txn: &mut Transaction, msg: Message) -> Result<Option<Message>, Error> {
+    match msg {
+        Message::Sync(msg) => Ok(handle_sync_message(txn, msg)?.map(Message::Sync)),
+        _ => Ok(None),
+    }
+}
+
+/// Handles an incoming awareness protocol message. [Message::Awareness] updates are applied to
+/// a given `awareness` instance, while [Message::AwarenessQuery] is answered with states of all
+/// known peers. Other messages are ignored.
+pub fn handle_awareness_message(awareness: &mut Awareness, msg: Message) -> Option<Message> {
+    match msg {
+        Message::Awareness(update) => {
+            awareness.apply_update(update);
+            None
+        }
+        Message::AwarenessQuery => Some(Message::Awareness(awareness.update())),
+        _ => None,
+    }
+}
+
+/// Transport agnostic driver of a single y-websocket compatible connection. It's responsible for
+/// the protocol logic of a provider: it performs the sync handshake, relays local document
+/// updates and awareness changes to a remote peer and applies the ones received from it. Actual
+/// network I/O is left to the caller, which is expected to:
+///
+/// 1. Pass every binary message received from a remote peer to [Connection::handle].
+/// 2. Send every message returned by [Connection::next_message] to a remote peer, after
+///    creating a connection and after every local change or call to [Connection::handle].
+///
+/// Updates applied by a connection are marked with its [Connection::origin], so that they are
+/// not echoed back to the same peer.
+pub struct Connection {
+    awareness: Awareness,
+    origin: Origin,
+    synced: bool,
+    outbox: Rc<RefCell<VecDeque<Message>>>,
+    applying_remote: Rc<Cell<bool>>,
+    awareness_subscription: SubscriptionId,
+    _update_subscription: Subscription<UpdateEvent>,
+}
+
+impl Connection {
+    /// Creates a new connection for a document bound to a given `awareness` instance. Initial
+    /// [SyncMessage::SyncStep1] and local awareness state are queued right away.
+    pub fn new(mut awareness: Awareness) -> Self {
+        let doc = awareness.doc();
+        let origin = Origin::from(rand::thread_rng().next_u32());
+        let outbox = Rc::new(RefCell::new(VecDeque::new()));
+        let applying_remote = Rc::new(Cell::new(false));
+
+        {
+            let mut outbox = outbox.borrow_mut();
+            let sv = doc.transact().state_vector();
+            outbox.push_back(Message::Sync(SyncMessage::SyncStep1(sv)));
+            if awareness.local_state().is_some() {
+                let update = awareness.update_with_clients(&[awareness.client_id()]);
+                outbox.push_back(Message::Awareness(update));
+            }
+        }
+
+        let update_subscription = {
+            let outbox = outbox.clone();
+            let origin = origin.clone();
+            doc.observe_update_v1(move |_, e| {
+                if e.origin.as_ref() != Some(&origin) {
+                    let msg = Message::Sync(SyncMessage::Update(e.update.clone()));
+                    outbox.borrow_mut().push_back(msg);
+                }
+            })
+        };
+        let awareness_subscription = {
+            let outbox = outbox.clone();
+            let applying_remote = applying_remote.clone();
+            awareness.on_update(move |awareness, e| {
+                if !applying_remote.get() {
+                    let update = awareness.update_with_clients(&e.all_changes());
+                    outbox.borrow_mut().push_back(Message::Awareness(update));
+                }
+            })
+        };
+
+        Connection {
+            awareness,
+            origin,
+            synced: false,
+            outbox,
+            applying_remote,
+            awareness_subscription,
+            _update_subscription: update_subscription,
+        }
+    }
+
+    /// Returns a document synchronized by this connection.
+    pub fn doc(&self) -> &Doc {
+        self.awareness.doc()
+    }
+
+    /// Returns an awareness instance used by this connection.
+    pub fn awareness(&self) -> &Awareness {
+        &self.awareness
+    }
+
+    /// Returns a mutable awareness instance used by this connection. Changes made to a local
+    /// state are propagated to a remote peer.
+    pub fn awareness_mut(&mut self) -> &mut Awareness {
+        &mut self.awareness
+    }
+
+    /// Returns an origin of transactions, which apply updates received by this connection.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// Returns true, once a remote peer has responded with [SyncMessage::SyncStep2], meaning
+    /// that a local document contains all of the remote peer changes.
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Handles a binary message received from a remote peer. Any responses are queued and can be
+    /// retrieved using [Connection::next_message]. Fails with [Error::Update] if a received update
+    /// has been rejected by a local document (see: [handle_sync_message]).
+    pub fn handle(&mut self, data: &[u8]) -> Result<(), Error> {
+        let reply = match Message::decode_v1(data)? {
+            Message::Sync(msg) => {
+                let is_step2 = matches!(msg, SyncMessage::SyncStep2(_));
+                let mut txn = self.awareness.doc().transact_with(self.origin.clone());
+                let reply = handle_sync_message(&mut txn, msg)?.map(Message::Sync);
+                if is_step2 {
+                    self.synced = true;
+                }
+                reply
+            }
+            Message::Custom(_, _) => None,
+            msg => {
+                self.applying_remote.set(true);
+                let reply = handle_awareness_message(&mut self.awareness, msg);
+                self.applying_remote.set(false);
+                reply
+            }
+        };
+        if let Some(reply) = reply {
+            self.outbox.borrow_mut().push_back(reply);
+        }
+        Ok(())
+    }
+
+    /// Returns the next lib0 v1 encoded message, which should be send to a remote peer.
+    pub fn next_message(&mut self) -> Option<Vec<u8>> {
+        let msg = self.outbox.borrow_mut().pop_front()?;
+        Some(msg.encode_v1())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.awareness.unobserve_update(self.awareness_subscription);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::awareness::Awareness;
+    use crate::sync::{handle_awareness_message, handle_message, Connection, Message, SyncMessage};
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, Options, StateVector, UpdateError, WritePolicy, ID};
+    use lib0::error::Error;
+
+    #[test]
+    fn sync_message_encoding() {
+        // compatible with y-protocols: writeSyncStep1(encoder, new Y.Doc())
+        let msg = Message::Sync(SyncMessage::SyncStep1(StateVector::default()));
+        let bin = msg.encode_v1();
+        assert_eq!(bin, vec![0, 0, 1, 0]);
+        assert_eq!(Message::decode_v1(bin.as_slice()).unwrap(), msg);
+
+        let msg = Message::Sync(SyncMessage::Update(vec![0, 0]));
+        let bin = msg.encode_v1();
+        assert_eq!(bin, vec![0, 2, 2, 0, 0]);
+        assert_eq!(Message::decode_v1(bin.as_slice()).unwrap(), msg);
+
+        let msg = Message::Custom(4, vec![1, 2, 3]);
+        let bin = msg.encode_v1();
+        assert_eq!(Message::decode_v1(bin.as_slice()).unwrap(), msg);
+
+        assert!(SyncMessage::decode_v1(&[5, 0]).is_err());
+    }
+
+    #[test]
+    fn awareness_messages() {
+        let mut a1 = Awareness::new(Doc::with_client_id(1));
+        let mut a2 = Awareness::new(Doc::with_client_id(2));
+        a1.set_local_state(r#"{"user":"a1"}"#);
+
+        let query = Message::decode_v1(&Message::AwarenessQuery.encode_v1()).unwrap();
+        let reply = handle_awareness_message(&mut a1, query).unwrap();
+        let reply = Message::decode_v1(&reply.encode_v1()).unwrap();
+        assert!(handle_awareness_message(&mut a2, reply).is_none());
+        assert_eq!(a2.clients().get(&1).unwrap(), r#"{"user":"a1"}"#);
+    }
+
+    #[test]
+    fn sync_two_peers() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("text");
+        txt1.insert(&mut d1.transact(), 0, "hello");
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+        txt2.insert(&mut d2.transact(), 0, "world");
+
+        // both peers initiate sync at the same time
+        let step1_a = Message::Sync(SyncMessage::SyncStep1(d1.transact().state_vector()));
+        let step1_b = Message::Sync(SyncMessage::SyncStep1(d2.transact().state_vector()));
+
+        let step2_a = handle_message(&mut d2.transact(), step1_a)
+            .unwrap()
+            .unwrap();
+        let step2_b = handle_message(&mut d1.transact(), step1_b)
+            .unwrap()
+            .unwrap();
+
+        assert!(handle_message(&mut d1.transact(), step2_a)
+            .unwrap()
+            .is_none());
+        assert!(handle_message(&mut d2.transact(), step2_b)
+            .unwrap()
+            .is_none());
+
+        assert_eq!(txt1.to_string(), txt2.to_string());
+
+        // incremental update
+        let mut txn = d1.transact();
+        txt1.push(&mut txn, "!");
+        txn.commit();
+        let update = Message::Sync(SyncMessage::Update(txn.encode_update_v1()));
+        drop(txn);
+
+        let bin = update.encode_v1();
+        let msg = Message::decode_v1(bin.as_slice()).unwrap();
+        assert!(handle_message(&mut d2.transact(), msg).unwrap().is_none());
+        assert_eq!(txt1.to_string(), txt2.to_string());
+    }
+
+    fn exchange(c1: &mut Connection, c2: &mut Connection) {
+        loop {
+            let mut done = true;
+            while let Some(msg) = c1.next_message() {
+                c2.handle(&msg).unwrap();
+                done = false;
+            }
+            while let Some(msg) = c2.next_message() {
+                c1.handle(&msg).unwrap();
+                done = false;
+            }
+            if done {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn connection_sync() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("text");
+        txt1.insert(&mut d1.transact(), 0, "hello");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+
+        let mut c1 = Connection::new(Awareness::new(d1));
+        let mut c2 = Connection::new(Awareness::new(d2));
+        assert!(!c1.is_synced());
+        exchange(&mut c1, &mut c2);
+        assert!(c1.is_synced());
+        assert!(c2.is_synced());
+        assert_eq!(txt2.to_string(), "hello");
+
+        // local changes are relayed
+        txt2.push(&mut c2.doc().transact(), " world");
+        c1.awareness_mut().set_local_state(r#"{"user":"c1"}"#);
+        exchange(&mut c1, &mut c2);
+        assert_eq!(txt1.to_string(), "hello world");
+        assert_eq!(
+            c2.awareness().clients().get(&1).unwrap(),
+            r#"{"user":"c1"}"#
+        );
+
+        // remote changes are not echoed back
+        txt1.push(&mut c1.doc().transact(), "!");
+        let msg = c1.next_message().unwrap();
+        c2.handle(&msg).unwrap();
+        assert!(c1.next_message().is_none());
+        assert!(c2.next_message().is_none());
+        assert_eq!(txt2.to_string(), "hello world!");
+    }
+
+    #[test]
+    fn denied_update() {
+        let mut options = Options::with_client_id(1);
+        options.write_policy = Some(WritePolicy::read_only(["config"]));
+        let mut server = Connection::new(Awareness::new(Doc::with_options(options)));
+        let mut client = Connection::new(Awareness::new(Doc::with_client_id(2)));
+        exchange(&mut server, &mut client);
+        assert!(client.is_synced());
+
+        let config = client.doc().transact().get_map("config");
+        config.insert(&mut client.doc().transact(), "mode", "relaxed");
+        let msg = client.next_message().unwrap();
+        match server.handle(&msg) {
+            Err(Error::Update(e)) => assert_eq!(
+                e.downcast_ref::<UpdateError>(),
+                Some(&UpdateError::Denied(ID::new(2, 0)))
+            ),
+            other => panic!("expected denied update, got {:?}", other),
+        }
+        assert_eq!(server.doc().transact().get_map("config").len(), 0);
+
+        // the same error is reported by handle_message
+        let msg = Message::decode_v1(msg.as_slice()).unwrap();
+        let mut txn = server.doc().transact();
+        assert!(matches!(
+            handle_message(&mut txn, msg),
+            Err(Error::Update(_))
+        ));
+    }
+}
@@ -0,0 +1,989 @@
+//! Utilities for property-based testing of documents, used by the yrs test suite itself and
+//! available to other crates with the `testing` feature enabled.
+//!
+//! [run_scenario] simulates a number of peers, which apply random edits (see: [Edit]) to their
+//! own replicas of a document, while randomly exchanging, delaying and reordering messages and
+//! going offline. At the end all peers are synchronized and checked for convergence. Edits of the
+//! built-in collections are provided by [text_edits], [map_edits] and [array_edits], while
+//! crates building their own types on top of yrs can supply their own edits.
+//!
+//! Real-world editing sessions can be replayed onto a [Text] using a [Trace].
+//!
+//! Example:
+//! ```rust
+//! use yrs::testing::{run_scenario, text_edits};
+//!
+//! // seed equal to 0 picks a random one, which is returned and included in a panic message of
+//! // a failing run, so that it can be reproduced
+//! let seed = run_scenario(42, &text_edits(), 3, 50);
+//! assert_eq!(seed, 42);
+//! ```
+
+use crate::block::ClientID;
+use crate::types::text::PrelimText;
+use crate::types::Value;
+use crate::updates::decoder::{Decode, Decoder, DecoderV1};
+use crate::updates::encoder::{Encode, Encoder, EncoderV1};
+use crate::{Doc, PrelimArray, PrelimMap, StateVector, Text, Update};
+use lib0::any::Any;
+use lib0::decoding::{Cursor, Read};
+use lib0::error::Error;
+use rand::distributions::Alphanumeric;
+use rand::prelude::SliceRandom;
+use rand::{random, RngCore, SeedableRng};
+use std::cell::{RefCell, RefMut};
+use std::collections::{HashMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+pub use rand::rngs::StdRng;
+pub use rand::Rng;
+
+/// A single random edit applied by a peer onto its own document replica. See: [run_scenario].
+pub type Edit = Box<dyn Fn(&mut Doc, &mut StdRng)>;
+
+/// Synchronizes all given documents with each other by exchanging their missing updates.
+pub fn exchange_updates(docs: &[&Doc]) {
+    for i in 0..docs.len() {
+        for j in 0..docs.len() {
+            if i != j {
+                let a = docs[i];
+                let ta = a.transact();
+                let b = docs[j];
+                let mut tb = b.transact();
+
+                let sv = tb.state_vector().encode_v1();
+                let update = ta.encode_diff_v1(&StateVector::decode_v1(sv.as_slice()).unwrap());
+                tb.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+            }
+        }
+    }
+}
+
+const MSG_SYNC_STEP_1: usize = 0;
+const MSG_SYNC_STEP_2: usize = 1;
+const MSG_SYNC_UPDATE: usize = 2;
+
+/// Runs a randomized scenario, in which a given number of `users` perform `iterations` of random
+/// `mods` (picked one per iteration) on their document replicas, while being randomly
+/// disconnected, reconnected and having their messages delivered in random order. At the end all
+/// replicas are synchronized and checked to have converged to the same state.
+///
+/// Scenario is fully determined by its `seed`. If it's equal to `0`, a random seed is used.
+/// Returns a seed used by the scenario. If the scenario fails, it panics with a message containing
+/// that seed, so that a failing run can be reproduced.
+pub fn run_scenario<F>(seed: u64, mods: &[F], users: usize, iterations: usize) -> u64
+where
+    F: Fn(&mut Doc, &mut StdRng),
+{
+    let seed = if seed == 0 { random() } else { seed };
+    let result = catch_unwind(AssertUnwindSafe(|| simulate(seed, mods, users, iterations)));
+    if let Err(cause) = result {
+        let reason = cause
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| cause.downcast_ref::<&str>().copied())
+            .unwrap_or("unknown reason");
+        panic!("scenario with seed {} failed: {}", seed, reason);
+    }
+    seed
+}
+
+fn simulate<F>(seed: u64, mods: &[F], users: usize, iterations: usize)
+where
+    F: Fn(&mut Doc, &mut StdRng),
+{
+    let rng = StdRng::seed_from_u64(seed);
+    let tc = TestConnector::with_peer_num(rng, users as u64);
+    for _ in 0..iterations {
+        if tc.0.borrow_mut().rng.gen_range(0, 100) <= 2 {
+            // 2% chance to disconnect/reconnect a random user
+            if tc.0.borrow_mut().rng.gen_bool(0.5) {
+                tc.disconnect_random();
+            } else {
+                tc.reconnect_random();
+            }
+        } else if tc.0.borrow_mut().rng.gen_range(0, 100) <= 1 {
+            // 1% chance to flush all
+            tc.flush_all();
+        } else if tc.0.borrow_mut().rng.gen_range(0, 100) <= 50 {
+            tc.flush_random();
+        }
+
+        {
+            let inner = &mut *tc.0.borrow_mut();
+            let rng = &mut inner.rng;
+            let idx = rng.gen_range(0, inner.peers.len());
+            let peer = &mut inner.peers[idx];
+            let test = mods.choose(rng).unwrap();
+            test(&mut peer.doc, rng);
+        };
+    }
+
+    tc.assert_final_state();
+}
+
+/// Simulated network of peers, which exchange messages of y-sync protocol with each other.
+/// Messages are not delivered right away: they are queued and flushed in random order.
+pub struct TestConnector(Rc<RefCell<Inner>>);
+
+struct Inner {
+    rng: StdRng,
+    peers: Vec<TestPeer>,
+    /// Maps all Client IDs to indexes in the `docs` vector.
+    all: HashMap<ClientID, usize>,
+    /// Maps online Client IDs to indexes in the `docs` vector.
+    online: HashMap<ClientID, usize>,
+}
+
+impl TestConnector {
+    /// Create new [TestConnector] with provided randomizer.
+    pub fn with_rng(rng: StdRng) -> Self {
+        TestConnector(Rc::new(RefCell::new(Inner {
+            rng,
+            peers: Vec::new(),
+            all: HashMap::new(),
+            online: HashMap::new(),
+        })))
+    }
+
+    /// Create a new [TestConnector] with pre-initialized number of peers.
+    pub fn with_peer_num(rng: StdRng, peer_num: u64) -> Self {
+        let mut tc = Self::with_rng(rng);
+        for client_id in 0..peer_num {
+            let peer = tc.create_peer(client_id as ClientID);
+            let mut txn = peer.doc.transact();
+            txn.get_text("text");
+            txn.get_map("map");
+        }
+        tc.sync_all();
+        tc
+    }
+
+    /// Returns random number generator attached to current [TestConnector].
+    pub fn rng(&self) -> RefMut<StdRng> {
+        let inner = self.0.borrow_mut();
+        RefMut::map(inner, |i| &mut i.rng)
+    }
+
+    /// Create a new [TestPeer] with provided `client_id` or return one, if such `client_id`
+    /// was already created before.
+    pub fn create_peer(&self, client_id: ClientID) -> &mut TestPeer {
+        if let Some(peer) = self.get_mut(&client_id) {
+            peer
+        } else {
+            let rc = self.0.clone();
+            let inner = unsafe { self.0.as_ptr().as_mut().unwrap() };
+            let instance = TestPeer::new(client_id);
+            instance.doc.observe_update_v1(move |_, e| {
+                let mut inner = rc.borrow_mut();
+                Self::broadcast(&mut inner, client_id, &e.update);
+            });
+            let idx = inner.peers.len();
+            inner.peers.push(instance);
+            inner.all.insert(client_id, idx);
+            inner.online.insert(client_id, idx);
+            &mut inner.peers[idx]
+        }
+    }
+
+    fn broadcast(inner: &mut RefMut<Inner>, sender: ClientID, payload: &Vec<u8>) {
+        let online: Vec<_> = inner
+            .online
+            .iter()
+            .filter_map(|(&id, &idx)| if id != sender { Some(idx) } else { None })
+            .collect();
+        for idx in online {
+            let peer = &mut inner.peers[idx];
+            peer.receive(sender, payload.clone());
+        }
+    }
+
+    /// Try to retrieve a reference to [TestPeer] for a given `client_id`, if such node was created.
+    pub fn get(&self, client_id: &ClientID) -> Option<&TestPeer> {
+        let inner = unsafe { self.0.as_ptr().as_ref().unwrap() };
+        let idx = inner.all.get(client_id)?;
+        Some(&inner.peers[*idx])
+    }
+
+    /// Try to retrieve a mutable reference to [TestPeer] for a given `client_id`,
+    /// if such node was created.
+    pub fn get_mut(&self, client_id: &ClientID) -> Option<&mut TestPeer> {
+        let inner = self.0.borrow_mut();
+        let idx = *inner.all.get(client_id)?;
+        unsafe {
+            let peers = inner.peers.as_ptr() as *mut TestPeer;
+            let peer = peers.offset(idx as isize);
+            peer.as_mut()
+        }
+    }
+
+    /// Disconnects test node with given `client_id` from the rest of known nodes.
+    pub fn disconnect(&self, client_id: ClientID) {
+        if let Some(peer) = self.get_mut(&client_id) {
+            peer.receiving.clear();
+        }
+        let mut inner = self.0.borrow_mut();
+        inner.online.remove(&client_id);
+    }
+
+    /// Append `client_id` to the list of known Y instances in [TestConnector].
+    /// Also initiate sync with all clients.
+    pub fn connect(&self, client_id: ClientID) {
+        let mut inner = self.0.borrow_mut();
+        Self::connect_inner(&mut inner, client_id);
+    }
+
+    fn connect_inner(inner: &mut RefMut<Inner>, client_id: ClientID) {
+        if !inner.online.contains_key(&client_id) {
+            let idx = *inner.all.get(&client_id).expect("unknown client_id");
+            inner.online.insert(client_id, idx);
+        }
+
+        let client_idx = *inner.all.get(&client_id).unwrap();
+        let payload = {
+            let sender = &mut inner.peers[client_idx];
+            let mut encoder = EncoderV1::new();
+            Self::write_step1(sender, &mut encoder);
+            encoder.to_vec()
+        };
+        Self::broadcast(inner, client_id, &payload);
+
+        let online: Vec<_> = inner
+            .online
+            .iter()
+            .filter_map(|(&id, &idx)| {
+                if id != client_id {
+                    Some((id, idx))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for (remote_id, idx) in online {
+            let payload = {
+                let peer = &inner.peers[idx];
+                let mut encoder = EncoderV1::new();
+                Self::write_step1(peer, &mut encoder);
+                encoder.to_vec()
+            };
+
+            let sender = &mut inner.peers[client_idx];
+            sender.receive(remote_id, payload);
+        }
+    }
+
+    /// Reconnects back all known peers.
+    pub fn reconnect_all(&mut self) {
+        let mut inner = self.0.borrow_mut();
+        let all_ids: Vec<_> = inner.all.keys().cloned().collect();
+        for client_id in all_ids {
+            Self::connect_inner(&mut inner, client_id);
+        }
+    }
+
+    /// Disconnects all known peers from each other.
+    pub fn disconnect_all(&mut self) {
+        let mut inner = self.0.borrow_mut();
+        let all_ids: Vec<_> = inner.all.keys().cloned().collect();
+        for client_id in all_ids {
+            Self::connect_inner(&mut inner, client_id);
+        }
+    }
+
+    /// Reconnects all known peers and processes their pending messages.
+    pub fn sync_all(&mut self) {
+        self.reconnect_all();
+        self.flush_all();
+    }
+
+    /// Processes all pending messages of connected peers in random order.
+    pub fn flush_all(&self) -> bool {
+        let mut did_something = false;
+        while self.flush_random() {
+            did_something = true;
+        }
+        did_something
+    }
+
+    /// Choose random connection and flush a random message from a random sender.
+    /// If this function was unable to flush a message, because there are no more messages to flush,
+    /// it returns false. true otherwise.
+    pub fn flush_random(&self) -> bool {
+        let mut inner = self.0.borrow_mut();
+        Self::flush_random_inner(&mut inner)
+    }
+
+    fn flush_random_inner(inner: &mut RefMut<Inner>) -> bool {
+        if let Some((receiver, sender)) = Self::pick_random_pair(inner) {
+            if let Some(m) = receiver
+                .receiving
+                .get_mut(&sender.client_id())
+                .unwrap()
+                .pop_front()
+            {
+                let mut encoder = EncoderV1::new();
+                let mut decoder = DecoderV1::new(Cursor::new(m.as_slice()));
+                Self::read_sync_message(receiver, &mut decoder, &mut encoder);
+                let payload = encoder.to_vec();
+                if !payload.is_empty() {
+                    sender.receive(receiver.client_id(), payload); // send reply message
+                }
+
+                // If update message, add the received message to the list of received messages
+                {
+                    let mut decoder = DecoderV1::new(Cursor::new(m.as_slice()));
+                    let msg_type: usize = decoder.read_var().unwrap();
+                    if msg_type == MSG_SYNC_STEP_2 || msg_type == MSG_SYNC_UPDATE {
+                        receiver
+                            .updates
+                            .push_back(decoder.read_buf().unwrap().to_vec())
+                    }
+                }
+                true
+            } else {
+                receiver.receiving.remove(&sender.client_id());
+                Self::flush_random_inner(inner)
+            }
+        } else {
+            false
+        }
+    }
+
+    fn pick_random_pair<'a>(
+        inner: &'a mut RefMut<Inner>,
+    ) -> Option<(&'a mut TestPeer, &'a mut TestPeer)> {
+        let pairs: Vec<_> = inner
+            .peers
+            .iter()
+            .enumerate()
+            .flat_map(|(receiver_idx, conn)| {
+                if conn.receiving.is_empty() {
+                    vec![]
+                } else {
+                    conn.receiving
+                        .keys()
+                        .map(|id| (receiver_idx, *inner.all.get(id).unwrap()))
+                        .collect()
+                }
+            })
+            .collect();
+        let (receiver_idx, sender_idx) = pairs.choose(&mut inner.rng)?;
+        unsafe {
+            let ptr = inner.peers.as_ptr() as *mut TestPeer;
+            let receiver = ptr.offset(*receiver_idx as isize);
+            let sender = ptr.offset(*sender_idx as isize);
+            Some((receiver.as_mut().unwrap(), sender.as_mut().unwrap()))
+        }
+    }
+
+    /// Disconnects one peer at random.
+    pub fn disconnect_random(&self) -> bool {
+        let id = {
+            let mut inner = self.0.borrow_mut();
+            let keys: Vec<_> = inner.online.keys().cloned().collect();
+            let rng = &mut inner.rng;
+            keys.choose(rng).cloned()
+        };
+        if let Some(id) = id {
+            self.disconnect(id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Reconnects one previously disconnected peer at random.
+    pub fn reconnect_random(&self) -> bool {
+        let mut inner = self.0.borrow_mut();
+        let reconnectable: Vec<_> = inner
+            .all
+            .keys()
+            .filter(|&id| !inner.online.contains_key(id))
+            .cloned()
+            .collect();
+        if let Some(&id) = reconnectable.choose(&mut inner.rng) {
+            Self::connect_inner(&mut inner, id);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_sync_message<D: Decoder, E: Encoder>(
+        peer: &TestPeer,
+        decoder: &mut D,
+        encoder: &mut E,
+    ) -> usize {
+        let msg_type = decoder.read_var().unwrap();
+        match msg_type {
+            MSG_SYNC_STEP_1 => Self::read_sync_step1(peer, decoder, encoder),
+            MSG_SYNC_STEP_2 => Self::read_sync_step2(peer, decoder),
+            MSG_SYNC_UPDATE => Self::read_update(peer, decoder),
+            other => panic!(
+                "Unknown message type: {} to {}",
+                other,
+                peer.doc().client_id
+            ),
+        }
+        msg_type
+    }
+
+    fn read_sync_step1<D: Decoder, E: Encoder>(peer: &TestPeer, decoder: &mut D, encoder: &mut E) {
+        Self::write_step2(peer, decoder.read_buf().unwrap(), encoder)
+    }
+
+    fn read_sync_step2<D: Decoder>(peer: &TestPeer, decoder: &mut D) {
+        let mut txn = peer.doc.transact();
+
+        let update = Update::decode_v1(decoder.read_buf().unwrap()).unwrap();
+        txn.apply_update(update);
+    }
+
+    fn read_update<D: Decoder>(peer: &TestPeer, decoder: &mut D) {
+        Self::read_sync_step2(peer, decoder)
+    }
+
+    /// Create a sync step 1 message based on the state of the current shared document.
+    fn write_step1<E: Encoder>(peer: &TestPeer, encoder: &mut E) {
+        let txn = peer.doc.transact();
+
+        encoder.write_var(MSG_SYNC_STEP_1);
+        encoder.write_buf(txn.state_vector().encode_v1());
+    }
+
+    fn write_step2<E: Encoder>(peer: &TestPeer, sv: &[u8], encoder: &mut E) {
+        let txn = peer.doc.transact();
+        let remote_sv = StateVector::decode_v1(sv).unwrap();
+
+        encoder.write_var(MSG_SYNC_STEP_2);
+        encoder.write_buf(txn.encode_diff_v1(&remote_sv));
+    }
+
+    pub fn assert_final_state(mut self) {
+        self.reconnect_all();
+        while self.flush_all() { /* do nothing */ }
+        // For each document, merge all received document updates with Y.mergeUpdates
+        // and create a new document which will be added to the list of "users"
+        // This ensures that mergeUpdates works correctly
+        /*
+        const mergedDocs = users.map(user => {
+          const ydoc = new Y.Doc()
+          enc.applyUpdate(ydoc, enc.mergeUpdates(user.updates))
+          return ydoc
+        })
+        users.push(.../** @type {any} */(mergedDocs))
+        */
+        let inner = self.0.borrow();
+        let docs: Vec<_> = inner.peers.iter().map(|peer| &peer.doc).collect();
+        assert_converged(&docs);
+    }
+
+    pub fn peers(&self) -> Peers {
+        let inner = unsafe { self.0.as_ptr().as_ref().unwrap() };
+        let iter = inner.peers.iter();
+        Peers(iter)
+    }
+}
+
+/// Iterator over all peers of a [TestConnector].
+pub struct Peers<'a>(std::slice::Iter<'a, TestPeer>);
+
+impl<'a> Iterator for Peers<'a> {
+    type Item = &'a TestPeer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// A single peer of a [TestConnector] together with its own document replica.
+pub struct TestPeer {
+    doc: Doc,
+    receiving: HashMap<ClientID, VecDeque<Vec<u8>>>,
+    updates: VecDeque<Vec<u8>>,
+}
+
+impl TestPeer {
+    pub fn new(client_id: ClientID) -> Self {
+        TestPeer {
+            doc: Doc::with_client_id(client_id),
+            receiving: HashMap::new(),
+            updates: VecDeque::new(),
+        }
+    }
+
+    pub fn client_id(&self) -> ClientID {
+        self.doc.client_id
+    }
+
+    pub fn doc(&self) -> &Doc {
+        &self.doc
+    }
+
+    pub fn doc_mut(&mut self) -> &mut Doc {
+        &mut self.doc
+    }
+
+    /// Receive a message from another client. This message is only appended to the list of
+    /// receiving messages. TestConnector decides when this client actually reads this message.
+    fn receive(&mut self, from: ClientID, message: Vec<u8>) {
+        let messages = self.receiving.entry(from).or_default();
+        messages.push_back(message);
+    }
+}
+
+/// Helper methods for generating random edits.
+pub trait RngExt: RngCore {
+    fn between(&mut self, x: u32, y: u32) -> u32 {
+        let a = x.min(y);
+        let b = x.max(y);
+        if a == b {
+            a
+        } else {
+            self.gen_range(a, b)
+        }
+    }
+
+    fn random_string(&mut self) -> String {
+        let len = self.gen_range(1, 10);
+        self.sample_iter(&Alphanumeric)
+            .take(len)
+            .map(char::from)
+            .collect()
+    }
+}
+
+impl<T> RngExt for T where T: RngCore {}
+
+/// Asserts that all given documents have converged to the same state: they contain the same
+/// blocks, including the pending ones, and the same delete sets. Documents should be synchronized
+/// first, ie. with [exchange_updates].
+pub fn assert_converged(docs: &[&Doc]) {
+    for pair in docs.windows(2) {
+        let a = pair[0].transact();
+        let b = pair[1].transact();
+
+        let astore = a.store();
+        let bstore = b.store();
+        assert_eq!(astore.blocks, bstore.blocks);
+        assert_eq!(astore.pending, bstore.pending);
+        assert_eq!(astore.pending_ds, bstore.pending_ds);
+    }
+}
+
+static UNIQUE_NUMBER: AtomicI64 = AtomicI64::new(0);
+
+/// Random edits of a root-level [Text](crate::Text) named `"text"`: insertions and deletions of
+/// random strings.
+pub fn text_edits() -> Vec<Edit> {
+    fn insert_text(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let ytext = txn.get_text("text");
+        let pos = rng.between(0, ytext.len());
+        let word = rng.random_string();
+        ytext.insert(&mut txn, pos, word.as_str());
+    }
+
+    fn delete_text(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let ytext = txn.get_text("text");
+        let len = ytext.len();
+        if len > 0 {
+            let pos = rng.between(0, len - 1);
+            let to_delete = rng.between(2, len - pos);
+            ytext.remove_range(&mut txn, pos, to_delete);
+        }
+    }
+
+    vec![Box::new(insert_text), Box::new(delete_text)]
+}
+
+/// Random edits of a root-level [Map](crate::Map) named `"map"`: insertions of strings and
+/// nested collections and deletions of entries under a small set of keys, so that concurrent
+/// peers often conflict with each other.
+pub fn map_edits() -> Vec<Edit> {
+    fn set(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let map = txn.get_map("map");
+        let key = ["one", "two"].choose(rng).unwrap();
+        let value: String = rng.random_string();
+        map.insert(&mut txn, key.to_string(), value);
+    }
+
+    fn set_type(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let map = txn.get_map("map");
+        let key = ["one", "two", "three"].choose(rng).unwrap();
+        if rng.gen_bool(0.33) {
+            map.insert(
+                &mut txn,
+                key.to_string(),
+                PrelimArray::from(vec![1, 2, 3, 4]),
+            );
+        } else if rng.gen_bool(0.33) {
+            map.insert(&mut txn, key.to_string(), PrelimText("deeptext"));
+        } else {
+            map.insert(
+                &mut txn,
+                key.to_string(),
+                PrelimMap::from({
+                    let mut map = HashMap::default();
+                    map.insert("deepkey".to_owned(), "deepvalue");
+                    map
+                }),
+            );
+        }
+    }
+
+    fn delete(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let map = txn.get_map("map");
+        let key = ["one", "two"].choose(rng).unwrap();
+        map.remove(&mut txn, key);
+    }
+    vec![Box::new(set), Box::new(set_type), Box::new(delete)]
+}
+
+/// Random edits of a root-level [Array](crate::Array) named `"array"`: insertions of numbers and
+/// nested collections, deletions and moves of elements. Every edit verifies that its local
+/// effect matches the expected one.
+pub fn array_edits() -> Vec<Edit> {
+    fn move_one(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let yarray = txn.get_array("array");
+        if yarray.len() != 0 {
+            let pos = rng.between(0, yarray.len() - 1);
+            let len = 1;
+            let new_pos_adjusted = rng.between(0, yarray.len() - 1);
+            let new_pos = new_pos_adjusted + if new_pos_adjusted > pos { len } else { 0 };
+            if let Any::Array(expected) = yarray.to_json() {
+                let mut expected = Vec::from(expected);
+                let moved = expected.remove(pos as usize);
+                let insert_pos = if pos < new_pos {
+                    new_pos - len
+                } else {
+                    new_pos
+                } as usize;
+                expected.insert(insert_pos, moved);
+
+                yarray.move_to(&mut txn, pos, new_pos);
+
+                let actual = yarray.to_json();
+                assert_eq!(actual, Any::Array(expected.into_boxed_slice()))
+            } else {
+                panic!("should not happen")
+            }
+        }
+    }
+    fn insert(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let yarray = txn.get_array("array");
+        let unique_number = UNIQUE_NUMBER.fetch_add(1, Ordering::SeqCst);
+        let len = rng.between(1, 4);
+        let content: Vec<_> = (0..len)
+            .into_iter()
+            .map(|_| Any::BigInt(unique_number))
+            .collect();
+        let mut pos = rng.between(0, yarray.len()) as usize;
+        if let Any::Array(expected) = yarray.to_json() {
+            let mut expected = Vec::from(expected);
+            yarray.insert_range(&mut txn, pos as u32, content.clone());
+
+            for any in content {
+                expected.insert(pos, any);
+                pos += 1;
+            }
+            let actual = yarray.to_json();
+            assert_eq!(actual, Any::Array(expected.into_boxed_slice()))
+        } else {
+            panic!("should not happen")
+        }
+    }
+
+    fn insert_type_array(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let yarray = txn.get_array("array");
+        let pos = rng.between(0, yarray.len());
+        yarray.insert(&mut txn, pos, PrelimArray::from([1, 2, 3, 4]));
+        if let Value::YArray(array2) = yarray.get(pos).unwrap() {
+            let expected: Box<[Any]> = (1..=4).map(|i| Any::Number(i as f64)).collect();
+            assert_eq!(array2.to_json(), Any::Array(expected));
+        } else {
+            panic!("should not happen")
+        }
+    }
+
+    fn insert_type_map(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let yarray = txn.get_array("array");
+        let pos = rng.between(0, yarray.len());
+        yarray.insert(&mut txn, pos, PrelimMap::<i32>::from(HashMap::default()));
+        if let Value::YMap(map) = yarray.get(pos).unwrap() {
+            map.insert(&mut txn, "someprop".to_string(), 42);
+            map.insert(&mut txn, "someprop".to_string(), 43);
+            map.insert(&mut txn, "someprop".to_string(), 44);
+        } else {
+            panic!("should not happen")
+        }
+    }
+
+    fn delete(doc: &mut Doc, rng: &mut StdRng) {
+        let mut txn = doc.transact();
+        let yarray = txn.get_array("array");
+        let len = yarray.len();
+        if len > 0 {
+            let pos = rng.between(0, len - 1);
+            let del_len = rng.between(1, 2.min(len - pos));
+            if rng.gen_bool(0.5) {
+                if let Value::YArray(array2) = yarray.get(pos).unwrap() {
+                    let pos = rng.between(0, array2.len() - 1);
+                    let del_len = rng.between(0, 2.min(array2.len() - pos));
+                    array2.remove_range(&mut txn, pos, del_len);
+                }
+            } else {
+                if let Any::Array(old_content) = yarray.to_json() {
+                    let mut old_content = Vec::from(old_content);
+                    yarray.remove_range(&mut txn, pos, del_len);
+                    old_content.drain(pos as usize..(pos + del_len) as usize);
+                    assert_eq!(yarray.to_json(), Any::Array(old_content.into_boxed_slice()));
+                } else {
+                    panic!("should not happen")
+                }
+            }
+        }
+    }
+
+    vec![
+        Box::new(insert),
+        Box::new(insert_type_array),
+        Box::new(insert_type_map),
+        Box::new(delete),
+        Box::new(move_one),
+    ]
+}
+
+/// A single operation of an editing [Trace].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceOp {
+    /// Insert a string at a given position.
+    Insert(u32, String),
+    /// Delete a given number of characters, starting at a given position.
+    Delete(u32, u32),
+}
+
+/// A recorded sequence of text edits, ie. a real-world editing session, which can be replayed
+/// onto a [Text] in order to benchmark or test it.
+///
+/// Traces can be decoded from a binary format used by yrs benchmarks (see: [Trace::decode]) or
+/// from JSON editing traces (see: [Trace::from_json]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    ops: Vec<TraceOp>,
+}
+
+impl Trace {
+    pub fn new(ops: Vec<TraceOp>) -> Self {
+        Trace { ops }
+    }
+
+    /// Returns operations of current trace in order of their execution.
+    pub fn ops(&self) -> &[TraceOp] {
+        &self.ops
+    }
+
+    /// Returns a number of operations in current trace.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Checks if current trace has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Decodes a trace from a binary format, which starts with a number of operations followed by
+    /// the operations themselves - each one prefixed with a tag: `1` for an insert (followed by
+    /// a position and a string) and `2` for a delete (followed by a position and a length).
+    /// All numbers use variable length encoding. Positions are expressed in UTF-8 bytes.
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let mut decoder = DecoderV1::new(Cursor::new(data));
+        let len: u32 = decoder.read_var()?;
+        let mut ops = Vec::with_capacity(len.min(1 << 16) as usize);
+        for _ in 0..len {
+            let op = match decoder.read_var::<u32>()? {
+                1 => {
+                    let index = decoder.read_var()?;
+                    let chunk = decoder.read_string()?;
+                    TraceOp::Insert(index, chunk.to_string())
+                }
+                2 => {
+                    let index = decoder.read_var()?;
+                    let len = decoder.read_var()?;
+                    TraceOp::Delete(index, len)
+                }
+                _ => return Err(Error::UnexpectedValue),
+            };
+            ops.push(op);
+        }
+        Ok(Trace { ops })
+    }
+
+    /// Parses a trace from JSON. Both the [automerge-perf](https://github.com/automerge/automerge-perf)
+    /// format (an array of `[position, deleted, ...inserted]` edits) and the
+    /// [editing-traces](https://github.com/josephg/editing-traces) format (an object with
+    /// `txns`, each having `patches` of `[position, deleted, inserted]`) are supported. Positions
+    /// are expressed in Unicode code points, so these traces should be replayed onto documents
+    /// using [OffsetKind::Utf32](crate::OffsetKind::Utf32).
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        fn invalid() -> Error {
+            Error::Other("invalid editing trace".to_string())
+        }
+        fn number(any: Option<&Any>) -> Result<u32, Error> {
+            match any {
+                Some(Any::Number(n)) if *n >= 0.0 => Ok(*n as u32),
+                Some(Any::BigInt(n)) if *n >= 0 => Ok(*n as u32),
+                _ => Err(invalid()),
+            }
+        }
+        fn push_patch(ops: &mut Vec<TraceOp>, patch: &Any) -> Result<(), Error> {
+            let patch = match patch {
+                Any::Array(patch) => patch,
+                _ => return Err(invalid()),
+            };
+            let index = number(patch.first())?;
+            let deleted = number(patch.get(1))?;
+            if deleted > 0 {
+                ops.push(TraceOp::Delete(index, deleted));
+            }
+            let mut inserted = String::new();
+            for chunk in patch.iter().skip(2) {
+                match chunk {
+                    Any::String(chunk) => inserted.push_str(chunk),
+                    _ => return Err(invalid()),
+                }
+            }
+            if !inserted.is_empty() {
+                ops.push(TraceOp::Insert(index, inserted));
+            }
+            Ok(())
+        }
+
+        let mut ops = Vec::new();
+        match Any::from_json(json)? {
+            Any::Array(patches) => {
+                for patch in patches.iter() {
+                    push_patch(&mut ops, patch)?;
+                }
+            }
+            Any::Map(map) => {
+                let txns = match map.get("txns") {
+                    Some(Any::Array(txns)) => txns,
+                    _ => return Err(invalid()),
+                };
+                for txn in txns.iter() {
+                    let patches = match txn {
+                        Any::Map(txn) => match txn.get("patches") {
+                            Some(Any::Array(patches)) => patches,
+                            _ => return Err(invalid()),
+                        },
+                        _ => return Err(invalid()),
+                    };
+                    for patch in patches.iter() {
+                        push_patch(&mut ops, patch)?;
+                    }
+                }
+            }
+            _ => return Err(invalid()),
+        }
+        Ok(Trace { ops })
+    }
+
+    /// Reads a trace from a file. Files with `.json` extension are parsed with
+    /// [Trace::from_json], all others are decoded with [Trace::decode].
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            Self::from_json(std::str::from_utf8(&data)?)
+        } else {
+            Self::decode(&data)
+        }
+    }
+
+    /// Replays all operations of current trace onto a given `text`, each one in a separate
+    /// transaction, just like they would be performed by a user typing.
+    pub fn replay(&self, doc: &Doc, text: &Text) {
+        for op in self.ops.iter() {
+            let mut txn = doc.transact();
+            match op {
+                TraceOp::Insert(index, chunk) => text.insert(&mut txn, *index, chunk),
+                TraceOp::Delete(index, len) => text.remove_range(&mut txn, *index, *len),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{run_scenario, text_edits, Edit, Trace, TraceOp};
+    use crate::{Doc, OffsetKind, Options};
+    use lib0::encoding::Write;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn run_scenario_seed() {
+        assert_eq!(run_scenario(42, &text_edits(), 2, 10), 42);
+
+        let edits: Vec<Edit> = vec![Box::new(|_, _| panic!("boom"))];
+        let cause = catch_unwind(AssertUnwindSafe(|| run_scenario(7, &edits, 2, 1))).unwrap_err();
+        let msg = cause.downcast_ref::<String>().unwrap();
+        assert_eq!(msg, "scenario with seed 7 failed: boom");
+    }
+
+    #[test]
+    fn trace_decode() {
+        let mut buf = Vec::new();
+        buf.write_var(2u32);
+        buf.write_var(1u32);
+        buf.write_var(0u32);
+        buf.write_string("hello");
+        buf.write_var(2u32);
+        buf.write_var(1u32);
+        buf.write_var(3u32);
+        let trace = Trace::decode(&buf).unwrap();
+        assert_eq!(
+            trace.ops(),
+            &[
+                TraceOp::Insert(0, "hello".to_string()),
+                TraceOp::Delete(1, 3)
+            ]
+        );
+
+        let doc = Doc::new();
+        let text = doc.transact().get_text("text");
+        trace.replay(&doc, &text);
+        assert_eq!(text.to_string(), "ho");
+    }
+
+    #[test]
+    fn trace_from_json() {
+        let automerge = Trace::from_json(r#"[[0, 0, "a", "ż"], [1, 1], [1, 0, "b"]]"#).unwrap();
+        let editing_traces = Trace::from_json(
+            r#"{"txns": [{"patches": [[0, 0, "aż"], [1, 1, ""]]}, {"patches": [[1, 0, "b"]]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(automerge, editing_traces);
+        assert_eq!(automerge.len(), 3);
+        assert!(Trace::from_json(r#"{"txns": 1}"#).is_err());
+
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf32,
+            ..Options::default()
+        });
+        let text = doc.transact().get_text("text");
+        automerge.replay(&doc, &text);
+        assert_eq!(text.to_string(), "ab");
+    }
+}
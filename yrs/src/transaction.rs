@@ -1,18 +1,25 @@
 use crate::*;
 
-use crate::block::{Block, BlockPtr, Item, ItemContent, Prelim, ID};
+use crate::block::{Block, BlockPtr, ClientID, Item, ItemContent, Prelim, ID};
 use crate::block_store::{Snapshot, StateVector};
-use crate::event::AfterTransactionEvent;
-use crate::id_set::DeleteSet;
+use crate::doc::{DocAddr, Timestamp};
+use crate::event::{AfterTransactionEvent, SubdocsEvent};
+use crate::id_set::{DeleteSet, IdSet};
 use crate::store::{Store, StoreRef};
 use crate::types::array::Array;
-use crate::types::xml::{XmlElement, XmlText};
+use crate::types::counter::Counter;
+use crate::types::xml::{XmlElement, XmlFragment, XmlText};
 use crate::types::{
-    BranchPtr, Event, Events, Map, Text, TypePtr, TYPE_REFS_ARRAY, TYPE_REFS_MAP, TYPE_REFS_TEXT,
-    TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_TEXT,
+    Branch, BranchPtr, Event, Events, Map, Path, PathSegment, Text, TypePtr, TypeRef, TypeRefs,
+    Value, TYPE_REFS_ARRAY, TYPE_REFS_COUNTER, TYPE_REFS_MAP, TYPE_REFS_TEXT,
+    TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_FRAGMENT, TYPE_REFS_XML_TEXT,
 };
-use crate::update::Update;
+use crate::update::{PendingUpdate, Update};
+use crate::updates::checksum;
+use crate::updates::decoder::{Decode, StreamDecoderV1};
+use lib0::any::Any;
 use lib0::error::Error;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
@@ -36,14 +43,463 @@ pub struct Transaction {
     pub(crate) prev_moved: HashMap<BlockPtr, BlockPtr>,
     /// All types that were directly modified (property added or child inserted/deleted).
     /// New types are not included in this Set.
-    changed: HashMap<TypePtr, HashSet<Option<Rc<str>>>>,
+    pub(crate) changed: HashMap<TypePtr, HashSet<Option<Rc<str>>>>,
+    /// An optional descriptor of a transaction origin, provided by its creator.
+    pub(crate) origin: Option<Origin>,
+    /// Subdocuments added, removed or loaded within the scope of this transaction.
+    pub(crate) subdocs: Option<Box<Subdocs>>,
+    /// Changes made by an update, recorded while it's applied using
+    /// [Transaction::apply_update_with_report].
+    pub(crate) report: Option<Box<IntegrationReport>>,
     committed: bool,
 }
 
+/// Read-only operations over a document state, which are available from within any kind of
+/// transaction. Implemented by both read-write [Transaction] and [ReadTransaction].
+pub trait ReadTxn {
+    /// Returns state vector describing current state of the updates.
+    fn state_vector(&self) -> StateVector;
+
+    /// Returns a snapshot which describes a current state of updates and removals made within
+    /// the corresponding document.
+    fn snapshot(&self) -> Snapshot;
+
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer.
+    fn encode_diff<E: Encoder>(&self, state_vector: &StateVector, encoder: &mut E);
+
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v1 encoding.
+    fn encode_diff_v1(&self, state_vector: &StateVector) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        self.encode_diff(state_vector, &mut encoder);
+        encoder.to_vec()
+    }
+
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v2 encoding.
+    fn encode_diff_v2(&self, state_vector: &StateVector) -> Vec<u8> {
+        let mut encoder = EncoderV2::new();
+        self.encode_diff(state_vector, &mut encoder);
+        encoder.to_vec()
+    }
+
+    /// Converts all root-level types of a current document into a JSON-like map. See:
+    /// [Transaction::to_json].
+    fn to_json(&self) -> Any;
+
+    /// Returns an iterator over names and kinds of all root-level types of a current document.
+    /// See: [Transaction::roots].
+    fn roots(&self) -> Roots<'_>;
+
+    /// Returns a branch of a root-level type stored under a given `name`, if it was already
+    /// defined either locally or by remote updates. Unlike typed getters of [Transaction] (eg.
+    /// [Transaction::get_text]), it never creates a new root type.
+    fn root_branch(&self, name: &str) -> Option<BranchPtr>;
+
+    /// Checks if a root-level type with a given `name` has been defined within a current document.
+    fn contains_root(&self, name: &str) -> bool {
+        self.root_branch(name).is_some()
+    }
+
+    /// Returns a [Text] data structure stored under a given `name`, if it exists. Unlike
+    /// [Transaction::get_text] it never creates a new root type, so it can be safely used on read
+    /// paths. Returns `None` if there's no root type under a given `name` or it's of another kind.
+    fn try_get_text(&self, name: &str) -> Option<Text> {
+        typed_root(self.root_branch(name)?, TYPE_REFS_TEXT).map(Text::from)
+    }
+
+    /// Returns a [Map] data structure stored under a given `name`, if it exists. Unlike
+    /// [Transaction::get_map] it never creates a new root type. Returns `None` if there's no root
+    /// type under a given `name` or it's of another kind.
+    fn try_get_map(&self, name: &str) -> Option<Map> {
+        typed_root(self.root_branch(name)?, TYPE_REFS_MAP).map(Map::from)
+    }
+
+    /// Returns an [Array] data structure stored under a given `name`, if it exists. Unlike
+    /// [Transaction::get_array] it never creates a new root type. Returns `None` if there's no root
+    /// type under a given `name` or it's of another kind.
+    fn try_get_array(&self, name: &str) -> Option<Array> {
+        typed_root(self.root_branch(name)?, TYPE_REFS_ARRAY).map(Array::from)
+    }
+
+    /// Returns a [XmlElement] data structure stored under a given `name`, if it exists. Unlike
+    /// [Transaction::get_xml_element] it never creates a new root type. Returns `None` if there's
+    /// no root type under a given `name` or it's of another kind.
+    fn try_get_xml_element(&self, name: &str) -> Option<XmlElement> {
+        typed_root(self.root_branch(name)?, TYPE_REFS_XML_ELEMENT).map(XmlElement::from)
+    }
+
+    /// Returns a [XmlFragment] data structure stored under a given `name`, if it exists. Unlike
+    /// [Transaction::get_xml_fragment] it never creates a new root type. Returns `None` if there's
+    /// no root type under a given `name` or it's of another kind.
+    fn try_get_xml_fragment(&self, name: &str) -> Option<XmlFragment> {
+        typed_root(self.root_branch(name)?, TYPE_REFS_XML_FRAGMENT).map(XmlFragment::from)
+    }
+
+    /// Returns a [XmlText] data structure stored under a given `name`, if it exists. Unlike
+    /// [Transaction::get_xml_text] it never creates a new root type. Returns `None` if there's no
+    /// root type under a given `name` or it's of another kind.
+    fn try_get_xml_text(&self, name: &str) -> Option<XmlText> {
+        typed_root(self.root_branch(name)?, TYPE_REFS_XML_TEXT).map(XmlText::from)
+    }
+
+    /// Returns a [Counter] stored under a given `name`, if it exists. Unlike
+    /// [Transaction::get_counter] it never creates a new root type. Returns `None` if there's no
+    /// root type under a given `name` or it's of another kind.
+    fn try_get_counter(&self, name: &str) -> Option<Counter> {
+        typed_root(self.root_branch(name)?, TYPE_REFS_COUNTER).map(Counter::from)
+    }
+}
+
+/// Narrows an existing root type `branch` down to a given `type_ref`. Root types defined by remote
+/// updates have no type information until they're accessed for the first time, so they are
+/// assigned a requested type, just like typed getters of [Transaction] do. This only changes
+/// a local interpretation of a root type and doesn't produce any updates.
+fn typed_root(mut branch: BranchPtr, type_ref: TypeRefs) -> Option<BranchPtr> {
+    branch.repair_type_ref(type_ref);
+    if branch.type_ref() == type_ref {
+        Some(branch)
+    } else {
+        None
+    }
+}
+
+fn root_branch(store: &StoreRef, name: &str) -> Option<BranchPtr> {
+    let name = resolve_root_name(store, name);
+    let mut ptr = BranchPtr::from(store.types.get(&*name)?);
+    if ptr.store.is_none() {
+        // root types defined by remote updates are not bound to a store yet
+        ptr.store = Some(store.clone());
+    }
+    Some(ptr)
+}
+
+/// Operations modifying a document state, which are available only from within read-write
+/// transactions.
+pub trait WriteTxn: ReadTxn {
+    /// Applies a deserialized update contents into a document. Returns an error if an update has
+    /// been rejected. See: [Transaction::try_apply_update].
+    fn apply_update(&mut self, update: Update) -> Result<(), UpdateError>;
+
+    /// Applies a standalone delete set onto a document. See: [Transaction::apply_delete_set].
+    fn apply_delete_set(&mut self, delete_set: DeleteSet) -> Result<(), UpdateError>;
+}
+
+impl ReadTxn for Transaction {
+    fn state_vector(&self) -> StateVector {
+        Transaction::state_vector(self)
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Transaction::snapshot(self)
+    }
+
+    fn encode_diff<E: Encoder>(&self, state_vector: &StateVector, encoder: &mut E) {
+        Transaction::encode_diff(self, state_vector, encoder)
+    }
+
+    fn to_json(&self) -> Any {
+        Transaction::to_json(self)
+    }
+
+    fn roots(&self) -> Roots<'_> {
+        Transaction::roots(self)
+    }
+
+    fn root_branch(&self, name: &str) -> Option<BranchPtr> {
+        root_branch(&self.store, name)
+    }
+}
+
+impl WriteTxn for Transaction {
+    fn apply_update(&mut self, update: Update) -> Result<(), UpdateError> {
+        Transaction::try_apply_update(self, update)
+    }
+
+    fn apply_delete_set(&mut self, delete_set: DeleteSet) -> Result<(), UpdateError> {
+        Transaction::apply_delete_set(self, delete_set)
+    }
+}
+
+/// A read-only transaction (see: [Doc::transact_read]). Unlike [Transaction] it doesn't track
+/// any changes, it's not committed and it doesn't trigger any events once dropped, which makes
+/// it cheap to create. Multiple read-only transactions can exist at the same time.
+///
+/// Shared types can be accessed using getters like [ReadTransaction::get_text], which never
+/// create new root types. Since all of the operations modifying a document require
+/// a [Transaction], read-only transactions cannot be used to change it:
+///
+/// ```compile_fail
+/// use yrs::Doc;
+///
+/// let doc = Doc::new();
+/// doc.transact().get_text("text");
+/// let mut txn = doc.transact_read();
+/// let text = txn.get_text("text").unwrap();
+/// text.push(&mut txn, "hello");
+/// ```
+pub struct ReadTransaction {
+    store: StoreRef,
+}
+
+impl ReadTransaction {
+    pub(crate) fn new(store: StoreRef) -> Self {
+        ReadTransaction { store }
+    }
+
+    /// Returns a [Text] data structure stored under a given `name`, if it exists. Returns `None`
+    /// if there's no root type under a given `name` or it's of another kind. See:
+    /// [ReadTxn::try_get_text].
+    pub fn get_text(&self, name: &str) -> Option<Text> {
+        self.try_get_text(name)
+    }
+
+    /// Returns a [Map] data structure stored under a given `name`, if it exists. Returns `None`
+    /// if there's no root type under a given `name` or it's of another kind. See:
+    /// [ReadTxn::try_get_map].
+    pub fn get_map(&self, name: &str) -> Option<Map> {
+        self.try_get_map(name)
+    }
+
+    /// Returns an [Array] data structure stored under a given `name`, if it exists. Returns `None`
+    /// if there's no root type under a given `name` or it's of another kind. See:
+    /// [ReadTxn::try_get_array].
+    pub fn get_array(&self, name: &str) -> Option<Array> {
+        self.try_get_array(name)
+    }
+
+    /// Returns a [XmlElement] data structure stored under a given `name`, if it exists. Returns
+    /// `None` if there's no root type under a given `name` or it's of another kind. See:
+    /// [ReadTxn::try_get_xml_element].
+    pub fn get_xml_element(&self, name: &str) -> Option<XmlElement> {
+        self.try_get_xml_element(name)
+    }
+
+    /// Returns a [XmlText] data structure stored under a given `name`, if it exists. Returns
+    /// `None` if there's no root type under a given `name` or it's of another kind. See:
+    /// [ReadTxn::try_get_xml_text].
+    pub fn get_xml_text(&self, name: &str) -> Option<XmlText> {
+        self.try_get_xml_text(name)
+    }
+}
+
+impl ReadTxn for ReadTransaction {
+    fn state_vector(&self) -> StateVector {
+        self.store.blocks.get_state_vector()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let blocks = &self.store.blocks;
+        Snapshot::new(blocks.get_state_vector(), DeleteSet::from(blocks))
+    }
+
+    fn encode_diff<E: Encoder>(&self, state_vector: &StateVector, encoder: &mut E) {
+        self.store.encode_diff(state_vector, encoder)
+    }
+
+    fn to_json(&self) -> Any {
+        root_types_to_json(&self.store)
+    }
+
+    fn roots(&self) -> Roots<'_> {
+        Roots::new(&self.store)
+    }
+
+    fn root_branch(&self, name: &str) -> Option<BranchPtr> {
+        root_branch(&self.store, name)
+    }
+}
+
+fn root_types_to_json(store: &StoreRef) -> Any {
+    let mut res = HashMap::new();
+    for (name, branch) in store.types.iter() {
+        let mut ptr = BranchPtr::from(branch);
+        if ptr.store.is_none() {
+            // root types defined by remote updates are not bound to a store yet
+            ptr.store = Some(store.clone());
+        }
+        let json = match ptr.type_ref() {
+            TYPE_REFS_ARRAY => Array::from(ptr).to_json(),
+            TYPE_REFS_MAP => Map::from(ptr).to_json(),
+            TYPE_REFS_TEXT => Text::from(ptr).to_json(),
+            TYPE_REFS_XML_ELEMENT => XmlElement::from(ptr).to_json(),
+            TYPE_REFS_XML_FRAGMENT => XmlFragment::from(ptr).to_json(),
+            TYPE_REFS_XML_TEXT => XmlText::from(ptr).to_json(),
+            TYPE_REFS_COUNTER => Counter::from(ptr).to_json(),
+            _ if branch.map.is_empty() => Array::from(ptr).to_json(),
+            _ => Map::from(ptr).to_json(),
+        };
+        res.insert(name.to_string(), json);
+    }
+    Any::Map(Box::new(res))
+}
+
+/// Iterator over names and kinds of root-level types of a document. See: [Transaction::roots].
+pub struct Roots<'a>(std::vec::IntoIter<(&'a str, TypeRef)>);
+
+impl<'a> Roots<'a> {
+    fn new(store: &'a Store) -> Self {
+        let mut roots: Vec<_> = store
+            .types
+            .iter()
+            .map(|(name, branch)| (name.as_ref(), branch.kind()))
+            .collect();
+        roots.sort_unstable_by_key(|(name, _)| *name);
+        Roots(roots.into_iter())
+    }
+}
+
+impl<'a> Iterator for Roots<'a> {
+    type Item = (&'a str, TypeRef);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Roots<'a> {}
+
+/// Name of a root-level map, which contains aliases of renamed root types. Its keys are new
+/// names of root types, while values are names they have been renamed from. See:
+/// [Transaction::rename_root].
+pub const ROOT_ALIASES: &str = "__yrs_aliases";
+
+/// Resolves a root type `name` through an alias table recorded by [Transaction::rename_root].
+/// Returns `name` itself if it's not an alias.
+fn resolve_root_name<'a>(store: &Store, name: &'a str) -> Cow<'a, str> {
+    let mut name = Cow::Borrowed(name);
+    if let Some(aliases) = store.types.get(ROOT_ALIASES) {
+        // concurrent renames may form chains of aliases, or in corner cases even cycles
+        for _ in 0..aliases.map.len() {
+            match aliases.get(&name) {
+                Some(Value::Any(Any::String(target))) if *name != *target => {
+                    name = Cow::Owned(target.into())
+                }
+                _ => break,
+            }
+        }
+    }
+    name
+}
+
+/// Error returned by [Transaction::rename_root].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameRootError {
+    /// There's no root type to be renamed.
+    NotFound(Rc<str>),
+    /// A new name is already used by another root type or alias.
+    AlreadyExists(Rc<str>),
+    /// An alias table ([ROOT_ALIASES]) cannot be renamed nor used as a new name.
+    Reserved,
+}
+
+impl std::fmt::Display for RenameRootError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameRootError::NotFound(name) => write!(f, "root type '{}' not found", name),
+            RenameRootError::AlreadyExists(name) => {
+                write!(f, "root type '{}' already exists", name)
+            }
+            RenameRootError::Reserved => write!(f, "'{}' is a reserved name", ROOT_ALIASES),
+        }
+    }
+}
+
+impl std::error::Error for RenameRootError {}
+
+/// Error returned by [Transaction::rollback].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackError {
+    /// Transaction has moved elements of an [Array], which is not supported by rollbacks.
+    MovedElements,
+}
+
+impl std::fmt::Display for RollbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RollbackError::MovedElements => {
+                write!(f, "transaction which moved elements cannot be rolled back")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RollbackError {}
+
+/// Statistics returned by [Transaction::gc].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GcStats {
+    /// Number of deleted items, which contents have been garbage collected.
+    pub collected: usize,
+    /// Number of blocks removed from a block store as a result of squashing them together with
+    /// their neighbors.
+    pub squashed: usize,
+    /// Total number of blocks left in a block store after compaction.
+    pub blocks: usize,
+}
+
+/// Summary of changes made by an update, returned by [Transaction::apply_update_with_report]. It
+/// can be used to inform users about their local changes being merged with concurrent changes of
+/// other peers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IntegrationReport {
+    /// Number of integrated items, which have not been placed directly next to their origin,
+    /// because of a conflict with other items concurrently inserted at the same position.
+    pub reordered: usize,
+    /// Map entries, which visible values have been overridden by an update.
+    pub overridden: Vec<MapOverride>,
+    /// Ranges of blocks integrated by an update.
+    pub inserted: IdSet,
+    /// Ranges of blocks deleted by an update.
+    pub deleted: IdSet,
+}
+
+impl IntegrationReport {
+    /// Checks if an update has overridden or reordered any of the existing values.
+    pub fn has_conflicts(&self) -> bool {
+        self.reordered > 0 || !self.overridden.is_empty()
+    }
+}
+
+/// A map entry, which visible value has been overridden by an update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapOverride {
+    /// Path to a map-like collection containing an overridden entry. It starts with a name of
+    /// a root-level type.
+    pub path: Path,
+    /// Key of an overridden entry.
+    pub key: Rc<str>,
+    /// ID of a value, which has been visible before an update was applied.
+    pub previous: ID,
+    /// ID of a value, which has replaced it.
+    pub current: ID,
+}
+
+/// Subdocuments changes tracked by a transaction.
+#[derive(Default)]
+pub(crate) struct Subdocs {
+    pub added: HashMap<DocAddr, DocRef>,
+    pub removed: HashMap<DocAddr, DocRef>,
+    pub loaded: HashMap<DocAddr, DocRef>,
+}
+
 impl Transaction {
     pub(crate) fn new(store: StoreRef) -> Transaction {
+        Self::create(store, None)
+    }
+
+    pub(crate) fn with_origin(store: StoreRef, origin: Origin) -> Transaction {
+        Self::create(store, Some(origin))
+    }
+
+    fn create(store: StoreRef, origin: Option<Origin>) -> Transaction {
         let begin_timestamp = store.blocks.get_state_vector();
-        Transaction {
+        let txn = Transaction {
             store,
             before_state: begin_timestamp,
             merge_blocks: Vec::new(),
@@ -51,8 +507,21 @@ impl Transaction {
             after_state: StateVector::default(),
             changed: HashMap::new(),
             prev_moved: HashMap::default(),
+            origin,
+            subdocs: None,
+            report: None,
             committed: false,
+        };
+        if let Some(eh) = txn.store.before_transaction_events.as_ref() {
+            eh.publish(&txn, &());
         }
+        txn
+    }
+
+    /// Returns an origin of current transaction, if it was provided at the moment of its creation
+    /// (see: [Doc::transact_with]).
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
     }
 
     #[inline]
@@ -65,6 +534,29 @@ impl Transaction {
         &mut self.store
     }
 
+    /// Converts all root-level types of a current document into a JSON-like map, where every
+    /// entry contains a JSON representation of a root type (see: [Value::to_json]) stored under
+    /// its name. Root types, which were never accessed through a typed getter (eg. because they
+    /// were defined by remote updates), are converted into maps if they have any map entries
+    /// and into arrays otherwise.
+    pub fn to_json(&self) -> Any {
+        root_types_to_json(&self.store)
+    }
+
+    /// Returns an iterator over names and kinds of all root-level types of a current document,
+    /// ordered by their names. Root types defined by remote updates, which were never accessed
+    /// through a typed getter, are reported as [TypeRef::Undefined].
+    pub fn roots(&self) -> Roots<'_> {
+        Roots::new(&self.store)
+    }
+
+    /// Returns a wall-clock time at which an element with a given `id` has been created, if it was
+    /// created locally with [Options::timestamps](crate::doc::Options::timestamps) enabled. This can
+    /// be used i.e. within event callbacks to check when inserted content has been written.
+    pub fn timestamp(&self, id: &ID) -> Option<Timestamp> {
+        self.store().timestamp(id)
+    }
+
     /// Returns state vector describing current state of the updates.
     pub fn state_vector(&self) -> StateVector {
         self.store().blocks.get_state_vector()
@@ -96,12 +588,74 @@ impl Transaction {
         self.store().encode_diff(state_vector, encoder)
     }
 
+    /// Encodes a single update, which contains all changes missing by any of the remote peers
+    /// described by given `state_vectors`. This way the same update can be broadcast to all of
+    /// them at once instead of encoding a separate diff for every single peer.
+    pub fn encode_diff_multi<E: Encoder>(&self, state_vectors: &[StateVector], encoder: &mut E) {
+        let store = self.store();
+        let mut sv = StateVector::default();
+        for (client, _) in store.blocks.get_state_vector().iter() {
+            let clock = state_vectors
+                .iter()
+                .map(|remote| remote.get(client))
+                .min()
+                .unwrap_or(0);
+            if clock > 0 {
+                sv.set_max(*client, clock);
+            }
+        }
+        store.encode_diff(&sv, encoder)
+    }
+
+    /// Encodes a single update, which contains all changes missing by any of the remote peers
+    /// described by given `state_vectors` using lib0 v1 encoding. See: [Transaction::encode_diff_multi].
+    pub fn encode_diff_multi_v1(&self, state_vectors: &[StateVector]) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        self.encode_diff_multi(state_vectors, &mut encoder);
+        encoder.to_vec()
+    }
+
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer, restricted only to changes made by given `clients`.
+    pub fn encode_diff_for_clients<E: Encoder>(
+        &self,
+        state_vector: &StateVector,
+        clients: &[ClientID],
+        encoder: &mut E,
+    ) {
+        self.store()
+            .encode_diff_filtered(state_vector, |client| clients.contains(client), encoder)
+    }
+
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer, restricted only to changes made by given `clients` using lib0 v1
+    /// encoding. See: [Transaction::encode_diff_for_clients].
+    pub fn encode_diff_for_clients_v1(
+        &self,
+        state_vector: &StateVector,
+        clients: &[ClientID],
+    ) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        self.encode_diff_for_clients(state_vector, clients, &mut encoder);
+        encoder.to_vec()
+    }
+
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v1 encoding.
     pub fn encode_diff_v1(&self, state_vector: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV1::new();
         self.encode_diff(state_vector, &mut encoder);
         encoder.to_vec()
     }
 
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v2 encoding.
+    pub fn encode_diff_v2(&self, state_vector: &StateVector) -> Vec<u8> {
+        let mut encoder = EncoderV2::new();
+        self.encode_diff(state_vector, &mut encoder);
+        encoder.to_vec()
+    }
+
     /// Returns a [Text] data structure stored under a given `name`. Text structures are used for
     /// collaborative text editing: they expose operations to append and remove chunks of text,
     /// which are free to execute concurrently by multiple peers over remote boundaries.
@@ -112,10 +666,13 @@ impl Transaction {
     /// If a structure under defined `name` already existed, but its type was different it will be
     /// reinterpreted as a text (in such case a sequence component of complex data type will be
     /// interpreted as a list of text chunks).
+    ///
+    /// Use [ReadTxn::try_get_text] to access an existing text without creating it.
     pub fn get_text(&mut self, name: &str) -> Text {
+        let name = resolve_root_name(self.store(), name);
         let mut c = self
             .store_mut()
-            .get_or_create_type(name, None, TYPE_REFS_TEXT);
+            .get_or_create_type(&*name, None, TYPE_REFS_TEXT);
         c.store = Some(self.store.clone());
         Text::from(c)
     }
@@ -132,13 +689,94 @@ impl Transaction {
     /// reinterpreted as a map (in such case a map component of complex data type will be
     /// interpreted as native map).
     pub fn get_map(&mut self, name: &str) -> Map {
+        let name = resolve_root_name(self.store(), name);
         let mut c = self
             .store_mut()
-            .get_or_create_type(name, None, TYPE_REFS_MAP);
+            .get_or_create_type(&*name, None, TYPE_REFS_MAP);
         c.store = Some(self.store.clone());
         Map::from(c)
     }
 
+    /// Returns a [Counter] stored under a given `name`. Counters are used to keep track of numeric
+    /// values, which can be incremented and decremented concurrently by many peers.
+    ///
+    /// If not structure under defined `name` existed before, it will be created and returned
+    /// instead.
+    ///
+    /// If a structure under defined `name` already existed, but its type was different it will be
+    /// reinterpreted as a counter (in such case numeric entries of a map component of complex data
+    /// type will be interpreted as contributions of the peers).
+    pub fn get_counter(&mut self, name: &str) -> Counter {
+        let name = resolve_root_name(self.store(), name);
+        let mut c = self
+            .store_mut()
+            .get_or_create_type(&*name, None, TYPE_REFS_COUNTER);
+        c.store = Some(self.store.clone());
+        Counter::from(c)
+    }
+
+    /// Returns a [Table] stored under a given `name`. Tables are grids of cells, which rows and
+    /// columns can be inserted, removed and reordered concurrently by many peers.
+    ///
+    /// Table is composed of root-level types named `<name>/rows`, `<name>/columns` and
+    /// `<name>/cells`. If they didn't exist before, they will be created. See: [Table] for
+    /// details.
+    pub fn get_table(&mut self, name: &str) -> Table {
+        let rows = self.get_array(&format!("{}/rows", name));
+        let columns = self.get_array(&format!("{}/columns", name));
+        let cells = self.get_map(&format!("{}/cells", name));
+        Table::new(rows, columns, cells)
+    }
+
+    /// Returns a [Tree] stored under a given `name`. Trees are ordered hierarchies of nodes, which
+    /// can be created, reparented, reordered and deleted concurrently by many peers.
+    ///
+    /// Tree is composed of root-level types named `<name>/parents`, `<name>/values` and
+    /// `<name>/order`. If they didn't exist before, they will be created. See: [Tree] for
+    /// details.
+    pub fn get_tree(&mut self, name: &str) -> Tree {
+        let parents = self.get_map(&format!("{}/parents", name));
+        let values = self.get_map(&format!("{}/values", name));
+        let order = self.get_array(&format!("{}/order", name));
+        Tree::new(parents, values, order)
+    }
+
+    /// Renames a root-level type stored under `from` name to `to`. Root types are referenced by
+    /// their names within the encoded blocks, so they cannot be moved. Instead an alias is recorded
+    /// in a document itself, inside of a root-level map called [ROOT_ALIASES]. Since it's a regular
+    /// part of a document state, it's replicated to other peers together with other changes.
+    ///
+    /// Once an alias has been recorded, all typed getters (eg. [Transaction::get_text] or
+    /// [ReadTxn::try_get_text]) called with a `to` name resolve to the same root type as `from`.
+    /// Peers which still use the old name are not affected, so both of them can be used during
+    /// schema migration without the need to read from both of them.
+    ///
+    /// Since aliases are resolved whenever a root type is accessed, a rename should be recorded
+    /// before any peer starts using a new name: content written by a peer to a `to` root type,
+    /// which it defined before it has received an alias, will be shadowed by the alias.
+    ///
+    /// Returns an error if `from` root type doesn't exist or if `to` name is already in use.
+    pub fn rename_root(&mut self, from: &str, to: &str) -> Result<(), RenameRootError> {
+        if from == ROOT_ALIASES || to == ROOT_ALIASES {
+            return Err(RenameRootError::Reserved);
+        }
+        let target: Rc<str> = resolve_root_name(self.store(), from).into();
+        if !self.store().types.contains_key(&target) {
+            return Err(RenameRootError::NotFound(from.into()));
+        }
+        let store = self.store();
+        let aliased = match store.types.get(ROOT_ALIASES) {
+            Some(aliases) => aliases.get(to).is_some(),
+            None => false,
+        };
+        if aliased || store.types.contains_key(to) {
+            return Err(RenameRootError::AlreadyExists(to.into()));
+        }
+        let aliases = self.get_map(ROOT_ALIASES);
+        aliases.insert(self, to, target.to_string());
+        Ok(())
+    }
+
     /// Returns an [Array] data structure stored under a given `name`. Array structures are used for
     /// storing a sequences of elements in ordered manner, positioning given element accordingly
     /// to its index.
@@ -150,9 +788,10 @@ impl Transaction {
     /// reinterpreted as an array (in such case a sequence component of complex data type will be
     /// interpreted as a list of inserted values).
     pub fn get_array(&mut self, name: &str) -> Array {
+        let name = resolve_root_name(self.store(), name);
         let mut c = self
             .store_mut()
-            .get_or_create_type(name, None, TYPE_REFS_ARRAY);
+            .get_or_create_type(&*name, None, TYPE_REFS_ARRAY);
         c.store = Some(self.store.clone());
         Array::from(c)
     }
@@ -170,8 +809,9 @@ impl Transaction {
     /// interpreted as map of its attributes, while a sequence component - as a list of its child
     /// XML nodes).
     pub fn get_xml_element(&mut self, name: &str) -> XmlElement {
+        let name = resolve_root_name(self.store(), name);
         let mut c = self.store_mut().get_or_create_type(
-            name,
+            &*name,
             Some("UNDEFINED".into()),
             TYPE_REFS_XML_ELEMENT,
         );
@@ -179,6 +819,25 @@ impl Transaction {
         XmlElement::from(c)
     }
 
+    /// Returns a [XmlFragment] data structure stored under a given `name`. XML fragments are
+    /// nameless containers of XML nodes, commonly used as a root of XML-based documents (eg. by
+    /// ProseMirror bindings). Unlike [XmlElement], they have no tag name nor attributes.
+    ///
+    /// If not structure under defined `name` existed before, it will be created and returned
+    /// instead.
+    ///
+    /// If a structure under defined `name` already existed, but its type was different it will be
+    /// reinterpreted as a XML fragment (in such case a sequence component of complex data type
+    /// will be interpreted as a list of its child XML nodes).
+    pub fn get_xml_fragment(&mut self, name: &str) -> XmlFragment {
+        let name = resolve_root_name(self.store(), name);
+        let mut c = self
+            .store_mut()
+            .get_or_create_type(&*name, None, TYPE_REFS_XML_FRAGMENT);
+        c.store = Some(self.store.clone());
+        XmlFragment::from(c)
+    }
+
     /// Returns a [XmlText] data structure stored under a given `name`. Text structures are used for
     /// collaborative text editing: they expose operations to append and remove chunks of text,
     /// which are free to execute concurrently by multiple peers over remote boundaries.
@@ -190,9 +849,10 @@ impl Transaction {
     /// reinterpreted as a text (in such case a sequence component of complex data type will be
     /// interpreted as a list of text chunks).
     pub fn get_xml_text(&mut self, name: &str) -> XmlText {
+        let name = resolve_root_name(self.store(), name);
         let mut c = self
             .store_mut()
-            .get_or_create_type(name, None, TYPE_REFS_XML_TEXT);
+            .get_or_create_type(&*name, None, TYPE_REFS_XML_TEXT);
         c.store = Some(self.store.clone());
         XmlText::from(c)
     }
@@ -326,6 +986,11 @@ impl Transaction {
                         unapplied.insert(ID::new(*client, clock), clock_end - clock);
                     }
                 }
+            } else {
+                // no blocks of that client have been integrated yet
+                for range in ranges.iter() {
+                    unapplied.insert(ID::new(*client, range.start), range.end - range.start);
+                }
             }
         }
 
@@ -355,17 +1020,19 @@ impl Transaction {
 
                 item.mark_as_deleted();
                 self.delete_set.insert(item.id.clone(), item.len());
+                if let Some(report) = self.report.as_mut() {
+                    report.deleted.insert(item.id, item.len());
+                }
                 let parent = *item.parent.as_branch().unwrap();
                 self.add_changed_type(parent, item.parent_sub.clone());
 
                 match &item.content {
-                    ItemContent::Doc(_, _) => {
-                        //if (transaction.subdocsAdded.has(this.doc)) {
-                        //    transaction.subdocsAdded.delete(this.doc)
-                        //} else {
-                        //    transaction.subdocsRemoved.add(this.doc)
-                        //}
-                        todo!()
+                    ItemContent::Doc(doc) => {
+                        let subdocs = self.subdocs_mut();
+                        let addr = doc.addr();
+                        if subdocs.added.remove(&addr).is_none() {
+                            subdocs.removed.insert(addr, doc.clone());
+                        }
                     }
                     ItemContent::Type(inner) => {
                         let mut ptr = inner.start;
@@ -405,8 +1072,193 @@ impl Transaction {
         result
     }
 
+    /// Returns blocks, which have been received but couldn't be integrated yet, because some of
+    /// the blocks they depend on are still missing. They are stashed and will be integrated
+    /// automatically once the missing blocks are applied.
+    pub fn pending_update(&self) -> Option<&PendingUpdate> {
+        self.store().pending.as_ref()
+    }
+
+    /// Returns deletions, which have been received but couldn't be applied yet, because blocks
+    /// they refer to are still missing.
+    pub fn pending_delete_set(&self) -> Option<&DeleteSet> {
+        self.store().pending_ds.as_ref()
+    }
+
+    /// Checks if there are any received changes, which couldn't be applied yet due to missing
+    /// dependencies (see: [Transaction::pending_update], [Transaction::pending_delete_set]).
+    pub fn has_pending(&self) -> bool {
+        let store = self.store();
+        store.pending.is_some() || store.pending_ds.is_some()
+    }
+
+    /// Returns ID ranges of blocks, which have not been received yet, but are required in order
+    /// to integrate pending updates and deletions (see: [Transaction::pending_update] and
+    /// [Transaction::pending_delete_set]). A sync layer can use them to request exactly these
+    /// ranges from remote peers.
+    pub fn missing(&self) -> IdSet {
+        let store = self.store();
+        let mut missing = IdSet::new();
+        if let Some(pending) = store.pending.as_ref() {
+            let local_sv = store.blocks.get_state_vector();
+            missing.merge(pending.update.missing_ranges(&local_sv));
+        }
+        if let Some(ds) = store.pending_ds.as_ref() {
+            for (client, range) in ds.iter() {
+                let state = store.blocks.get_state(client);
+                let end = range.iter().map(|r| r.end).max().unwrap_or(0);
+                if end > state {
+                    missing.insert(ID::new(*client, state), end - state);
+                }
+            }
+        }
+        missing.squash();
+        missing
+    }
+
     /// Applies a deserialized update contents into a document owning current transaction.
+    ///
+    /// Blocks, which depend on other blocks that have not been received yet, are stashed as
+    /// pending and integrated automatically once their dependencies are applied.
+    ///
+    /// Updates rejected by [Options::update_filter], [Options::write_policy] or [Options::limits]
+    /// are silently ignored, therefore this method should be used only with trusted updates. Use
+    /// [Transaction::try_apply_update] to find out if an update has been applied.
     pub fn apply_update(&mut self, update: Update) {
+        let _ = self.try_apply_update(update);
+    }
+
+    /// Applies a deserialized update contents into a document owning current transaction, just
+    /// like [Transaction::apply_update]. If [Options::update_filter] was defined, all blocks of
+    /// an update are validated first and an entire update is rejected with an error if any of them
+    /// didn't pass the validation. Updates, which would make a document exceed any of its
+    /// [Options::limits] or modify collections not permitted by [Options::write_policy], are
+    /// rejected as well.
+    pub fn try_apply_update(&mut self, update: Update) -> Result<(), UpdateError> {
+        self.check_update(&update)?;
+        self.apply_checked_update(update);
+        Ok(())
+    }
+
+    /// Applies a deserialized update contents into a document owning current transaction, just
+    /// like [Transaction::try_apply_update], and returns a summary of changes it has made,
+    /// including conflicts it had to resolve.
+    ///
+    /// Blocks stashed as pending are not included in a report until they are integrated, in which
+    /// case they are reported by an update which made their integration possible.
+    pub fn apply_update_with_report(
+        &mut self,
+        update: Update,
+    ) -> Result<IntegrationReport, UpdateError> {
+        self.check_update(&update)?;
+        let outer = self.report.replace(Box::default());
+        self.apply_checked_update(update);
+        let mut report = std::mem::replace(&mut self.report, outer).unwrap_or_default();
+        report.inserted.squash();
+        report.deleted.squash();
+        Ok(*report)
+    }
+
+    /// Records an entry of a map-like `parent` collection, which visible value has been overridden
+    /// while an update was applied with [Transaction::apply_update_with_report].
+    pub(crate) fn report_override(
+        &mut self,
+        parent: BranchPtr,
+        key: Rc<str>,
+        previous: ID,
+        current: ID,
+    ) {
+        if self.report.is_none() {
+            return;
+        }
+        let mut root = parent;
+        while let Some(ptr) = root.item {
+            match ptr.as_item().and_then(|item| item.parent.as_branch()) {
+                Some(branch) => root = *branch,
+                None => break,
+            }
+        }
+        let mut path = Branch::path(root, parent);
+        if let Some(name) = self.store().get_type_key(root) {
+            path.push_front(PathSegment::Key(name.clone()));
+        }
+        if let Some(report) = self.report.as_mut() {
+            report.overridden.push(MapOverride {
+                path,
+                key,
+                previous,
+                current,
+            });
+        }
+    }
+
+    /// Validates a given `update` against [Options::update_filter], [Options::write_policy] and
+    /// [Options::limits] of a document owning current transaction, without applying it.
+    pub(crate) fn check_update(&self, update: &Update) -> Result<(), UpdateError> {
+        let store = self.store();
+        if let Some(filter) = store.options.update_filter.as_ref() {
+            update.validate(filter)?;
+        }
+        if let Some(policy) = store.options.write_policy.as_ref() {
+            update.check_permissions(store, policy)?;
+        }
+        if store.options.limits != DocLimits::default() {
+            update.check_limits(store, &store.options.limits)?;
+        }
+        Ok(())
+    }
+
+    /// Applies an `update`, which has already been validated using [Transaction::check_update].
+    pub(crate) fn apply_checked_update(&mut self, update: Update) {
+        let store = self.store();
+        let collision = update.has_foreign_blocks_of(store.options.client_id, store);
+        self.integrate_update(update);
+        if collision {
+            let store = self.store_mut();
+            let old_client_id = store.options.client_id;
+            let new_client_id = store.generate_client_id();
+            store.options.client_id = new_client_id;
+            if let Some(handler) = store.options.on_client_id_collision.clone() {
+                handler.call(old_client_id, new_client_id);
+            }
+        }
+    }
+
+    /// Decodes an update encoded using lib0 v1 encoding and applies it onto a document owning
+    /// current transaction. Malformed input is never applied partially: it's rejected with an
+    /// error before any of its blocks gets integrated. Updates rejected for the reasons described
+    /// in [Transaction::try_apply_update] are reported with [Error::Update], which wraps
+    /// an [UpdateError].
+    pub fn apply_update_v1(&mut self, update: &[u8]) -> Result<(), Error> {
+        let update = Update::decode_v1(update)?;
+        self.try_apply_update(update)?;
+        Ok(())
+    }
+
+    /// Decodes an update encoded using lib0 v2 encoding and applies it onto a document owning
+    /// current transaction, just like [Transaction::apply_update_v1].
+    pub fn apply_update_v2(&mut self, update: &[u8]) -> Result<(), Error> {
+        let update = Update::decode_v2(update)?;
+        self.try_apply_update(update)?;
+        Ok(())
+    }
+
+    /// Verifies an integrity trailer of a payload produced by
+    /// [Doc::encode_state_as_update_v1_checked] and applies an update it contains, just like
+    /// [Transaction::apply_update_v1]. Truncated or corrupted payloads are rejected with
+    /// [Error::LengthMismatch] or [Error::ChecksumMismatch] before being decoded.
+    pub fn apply_update_v1_checked(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.apply_update_v1(checksum::verify(data)?)
+    }
+
+    /// Verifies an integrity trailer of a payload produced by
+    /// [Doc::encode_state_as_update_v2_checked] and applies an update it contains, just like
+    /// [Transaction::apply_update_v2].
+    pub fn apply_update_v2_checked(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.apply_update_v2(checksum::verify(data)?)
+    }
+
+    fn integrate_update(&mut self, update: Update) {
         let (remaining, remaining_ds) = update.integrate(self);
         let mut retry = false;
         {
@@ -426,8 +1278,8 @@ impl Transaction {
                         pending.missing.set_min(client, clock);
                     }
                     pending.update = Update::merge_updates(vec![pending.update, remaining.update]);
-                    store.pending = Some(pending);
                 }
+                store.pending = Some(pending);
             } else {
                 store.pending = remaining;
             }
@@ -454,12 +1306,58 @@ impl Transaction {
                 let ds = store.pending_ds.take().unwrap_or_default();
                 let mut ds_update = Update::new();
                 ds_update.delete_set = ds;
-                self.apply_update(pending.update);
-                self.apply_update(ds_update)
+                self.integrate_update(pending.update);
+                self.integrate_update(ds_update)
             }
         }
     }
 
+    /// Applies a standalone delete set onto a document owning current transaction. Delete sets can
+    /// be encoded and decoded on their own (see: [DeleteSet]), which makes it possible to send
+    /// tombstone-only messages without a full update framing.
+    ///
+    /// Deletions of blocks, which have not been received yet, are stashed as pending and applied
+    /// once these blocks arrive - just like in case of regular updates. Delete sets are validated
+    /// the same way as updates applied with [Transaction::try_apply_update].
+    pub fn apply_delete_set(&mut self, delete_set: DeleteSet) -> Result<(), UpdateError> {
+        let mut update = Update::new();
+        update.delete_set = delete_set;
+        self.try_apply_update(update)
+    }
+
+    /// Applies an update read incrementally from a given `reader`, which provides its contents
+    /// using lib0 v1 encoding. Unlike [Transaction::apply_update], a whole update doesn't have to
+    /// be loaded into memory at once: blocks are decoded and integrated in small batches. Blocks,
+    /// which are missing their dependencies at the moment of integration, are stashed as pending
+    /// and integrated once these dependencies arrive - just like in case of regular updates.
+    ///
+    /// Every batch is validated just like in case of [Transaction::try_apply_update]. Returns an
+    /// error if the data read from `reader` couldn't be decoded or if any of the batches has been
+    /// rejected ([Error::Update]). In that case all of the batches applied before the error
+    /// occurred remain applied.
+    pub fn apply_update_from<R: std::io::Read>(&mut self, reader: R) -> Result<(), Error> {
+        const CHUNK_SIZE: usize = 1024;
+        let mut decoder = StreamDecoderV1::new(reader);
+        Update::decode_chunked(&mut decoder, CHUNK_SIZE, |update| {
+            Ok(self.try_apply_update(update)?)
+        })
+    }
+
+    /// Moves a new `block` into its client's arena, integrates it starting from a given `offset`
+    /// and appends it to the client's block list. Returns a pointer to the integrated block and
+    /// a flag telling if it should be deleted right away.
+    ///
+    /// Arena slots are allocated only here and by block splits, which always add a new block to
+    /// the block list, so that every allocated slot is either in use or released for reuse.
+    pub(crate) fn integrate_new_block(&mut self, block: Block, offset: u32) -> (BlockPtr, bool) {
+        let client = block.id().client;
+        let mut block_ptr = self.store_mut().blocks.alloc(client, block);
+        let should_delete = block_ptr.integrate(self, offset);
+        let blocks = self.store_mut().blocks.get_client_blocks_mut(client);
+        blocks.push(block_ptr);
+        (block_ptr, should_delete)
+    }
+
     pub(crate) fn create_item<T: Prelim>(
         &mut self,
         pos: &block::ItemPosition,
@@ -477,6 +1375,7 @@ impl Transaction {
             };
             let client_id = store.options.client_id;
             let id = ID::new(client_id, store.get_local_state());
+            store.record_timestamp(&id);
 
             (left, right, origin, id)
         };
@@ -486,7 +1385,7 @@ impl Transaction {
         } else {
             None
         };
-        let mut block = Item::new(
+        let block = Item::new(
             id,
             left,
             origin,
@@ -496,12 +1395,7 @@ impl Transaction {
             parent_sub,
             content,
         );
-        let mut block_ptr = BlockPtr::from(&mut block);
-
-        block_ptr.integrate(self, 0);
-
-        let local_block_list = self.store_mut().blocks.get_client_blocks_mut(id.client);
-        local_block_list.push(block);
+        let (block_ptr, _) = self.integrate_new_block(block, 0);
 
         if let Some(remainder) = remainder {
             remainder.integrate(self, inner_ref.unwrap().into())
@@ -577,6 +1471,17 @@ impl Transaction {
             }
         }
 
+        if let Some(eh) = self.store().before_cleanup_events.as_ref() {
+            eh.publish(self, &());
+        }
+
+        // changed types need to be collected before GC, which may release deleted ones
+        let changed_parent_types = if self.store.after_transaction_events.is_some() {
+            self.changed_parent_types()
+        } else {
+            Vec::new()
+        };
+
         // 4. try GC delete set
         if !self.store.options.skip_gc {
             self.try_gc();
@@ -612,6 +1517,22 @@ impl Transaction {
             }
         }
 
+        // update memory usage of the clients, which blocks have been changed
+        let mut changed_clients: HashSet<ClientID> = self
+            .after_state
+            .iter()
+            .filter(|(client, &clock)| self.before_state.get(client) != clock)
+            .map(|(client, _)| *client)
+            .collect();
+        changed_clients.extend(self.delete_set.iter().map(|(client, _)| *client));
+        changed_clients.extend(self.store.resized_clients());
+        for client in changed_clients {
+            self.store.update_memory_usage(&client);
+        }
+        if !self.delete_set.is_empty() || self.after_state != self.before_state {
+            self.store.epoch += 1;
+        }
+
         // 8. emit 'afterTransactionCleanup'
         let store = self.store();
         if let Some(eh) = store.after_transaction_events.as_ref() {
@@ -619,6 +1540,11 @@ impl Transaction {
                 before_state: self.before_state.clone(),
                 after_state: self.after_state.clone(),
                 delete_set: self.delete_set.clone(),
+                origin: self.origin.clone(),
+                changed_parent_types: changed_parent_types
+                    .into_iter()
+                    .map(BranchPtr::into)
+                    .collect(),
             };
             eh.publish(&self, &event);
         }
@@ -626,7 +1552,7 @@ impl Transaction {
         if let Some(eh) = store.update_v1_events.as_ref() {
             if !self.delete_set.is_empty() || self.after_state != self.before_state {
                 // produce update only if anything changed
-                let update = UpdateEvent::new(self.encode_update_v1());
+                let update = UpdateEvent::new(self.encode_update_v1(), self.origin.clone());
                 eh.publish(&self, &update);
             }
         }
@@ -634,16 +1560,241 @@ impl Transaction {
         if let Some(eh) = store.update_v2_events.as_ref() {
             if !self.delete_set.is_empty() || self.after_state != self.before_state {
                 // produce update only if anything changed
-                let update = UpdateEvent::new(self.encode_update_v2());
+                let update = UpdateEvent::new(self.encode_update_v2(), self.origin.clone());
                 eh.publish(&self, &update);
             }
         }
         // 11. add and remove subdocs
-        // 12. emit 'subdocs'
+        if let Some(subdocs) = self.subdocs.take() {
+            let mut store = self.store.clone();
+            for (addr, doc) in subdocs.added.iter() {
+                store.subdocs.insert(*addr, doc.clone());
+            }
+            for addr in subdocs.removed.keys() {
+                store.subdocs.remove(addr);
+            }
+            // 12. emit 'subdocs'
+            if let Some(eh) = store.subdocs_events.as_ref() {
+                let event = SubdocsEvent {
+                    added: subdocs.added.into_values().collect(),
+                    removed: subdocs.removed.into_values().collect(),
+                    loaded: subdocs.loaded.into_values().collect(),
+                };
+                eh.publish(&self, &event);
+            }
+        }
+    }
+
+    /// Returns a (lazily initialized) collection of subdocuments changed within the scope of
+    /// current transaction.
+    pub(crate) fn subdocs_mut(&mut self) -> &mut Subdocs {
+        self.subdocs.get_or_insert_with(Box::default)
+    }
+
+    /// Returns shared types, which were changed within the scope of current transaction and
+    /// have not been deleted.
+    fn changed_parent_types(&self) -> Vec<BranchPtr> {
+        let mut result = Vec::new();
+        for ptr in self.changed.keys() {
+            if let TypePtr::Branch(branch) = ptr {
+                let deleted = match branch.item {
+                    Some(item) => item.is_deleted(),
+                    None => false,
+                };
+                if !deleted {
+                    result.push(*branch);
+                }
+            }
+        }
+        result
+    }
+
+    /// Discards all changes made within the scope of a current transaction and restores a document
+    /// to the state from before the transaction has been created. Blocks inserted within this
+    /// transaction are removed from the block store and deleted blocks are restored. Since
+    /// transaction is not committed, no events are triggered and no updates are produced.
+    ///
+    /// Any references to shared types created within the scope of a rolled back transaction are
+    /// no longer valid and must not be used.
+    ///
+    /// Transactions which moved elements of any [Array] can't be rolled back: in such case
+    /// [RollbackError::MovedElements] is returned and a document is left untouched, while all of
+    /// the transaction changes are committed once it's dropped.
+    pub fn rollback(mut self) -> Result<(), RollbackError> {
+        if !self.prev_moved.is_empty() {
+            return Err(RollbackError::MovedElements);
+        }
+        let before_state = self.before_state.clone();
+        let store = self.store_mut();
+        let offset_kind = store.options.offset_kind;
+
+        // collect all blocks inserted within the scope of current transaction
+        let mut removed: HashSet<BlockPtr> = HashSet::new();
+        for (client, blocks) in store.blocks.iter() {
+            if let Some(start) = blocks.find_pivot(before_state.get(client)) {
+                for i in start..blocks.len() {
+                    let ptr = blocks.get(i);
+                    if let Block::Item(item) = ptr.deref() {
+                        if let ItemContent::Move(_) = &item.content {
+                            return Err(RollbackError::MovedElements);
+                        }
+                    }
+                    removed.insert(ptr);
+                }
+            }
+        }
+
+        // restore blocks deleted within the scope of current transaction
+        for (client, range) in self.delete_set.iter() {
+            let blocks = match self.store.blocks.get(client) {
+                Some(blocks) => blocks,
+                None => continue,
+            };
+            let end = before_state.get(client);
+            for r in range.iter() {
+                if let Some(mut i) = blocks.find_pivot(r.start) {
+                    while i < blocks.len() {
+                        let mut ptr = blocks.get(i);
+                        let id = *ptr.id();
+                        if id.clock >= r.end || id.clock >= end {
+                            break;
+                        }
+                        if let Block::Item(item) = ptr.deref_mut() {
+                            if item.is_deleted() {
+                                item.info.clear_deleted();
+                                if let TypePtr::Branch(parent) = item.parent {
+                                    parent.string_cache.take();
+                                    parent.search_markers.take();
+                                }
+                                if item.parent_sub.is_none() && item.is_countable() {
+                                    if let TypePtr::Branch(mut parent) = item.parent {
+                                        parent.block_len += item.len();
+                                        parent.content_len += item.content_len(offset_kind);
+                                    }
+                                }
+                            }
+                        }
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        // unlink inserted blocks from their neighbors and parents
+        for &ptr in removed.iter() {
+            if let Block::Item(item) = ptr.deref() {
+                if let Some(Block::Item(left)) = item.left.clone().as_deref_mut() {
+                    left.right = item.right;
+                }
+                if let Some(Block::Item(right)) = item.right.clone().as_deref_mut() {
+                    right.left = item.left;
+                }
+                if let TypePtr::Branch(mut parent) = item.parent {
+                    parent.string_cache.take();
+                    parent.search_markers.take();
+                    if let Some(parent_sub) = &item.parent_sub {
+                        if parent.map.get(parent_sub) == Some(&ptr) {
+                            // restore the last value which was not inserted by this transaction
+                            let mut left = item.left;
+                            while let Some(l) = left.filter(|l| removed.contains(l)) {
+                                left = if let Block::Item(i) = l.deref() {
+                                    i.left
+                                } else {
+                                    None
+                                };
+                            }
+                            match left {
+                                Some(left) => parent.map.insert(parent_sub.clone(), left),
+                                None => parent.map.remove(parent_sub),
+                            };
+                        }
+                    } else {
+                        if parent.start == Some(ptr) {
+                            parent.start = item.right;
+                        }
+                        if item.is_countable() && !item.is_deleted() {
+                            parent.block_len -= item.len();
+                            parent.content_len -= item.content_len(offset_kind);
+                        }
+                    }
+                }
+            }
+        }
+        self.store_mut().blocks.truncate(&before_state);
+
+        self.delete_set = DeleteSet::new();
+        self.merge_blocks.clear();
+        self.changed.clear();
+        self.subdocs = None;
+        self.after_state = before_state;
+        self.committed = true;
+        Ok(())
+    }
+
+    /// Runs a garbage collection over an entire block store of a document owning current
+    /// transaction. Unlike regular GC, which is performed on commit and only affects items
+    /// deleted within the scope of a committed transaction, this method revisits all tombstones
+    /// which were left behind, ie. because they were protected by [UndoManager] at the time of
+    /// their deletion. Afterwards all neighboring blocks, which can be merged together, are
+    /// squashed in order to reduce block store fragmentation.
+    ///
+    /// Contents of deleted items are not released if [Options::skip_gc] is set, however their
+    /// blocks are still subject to squashing. Blocks inserted within the scope of current
+    /// transaction are not affected, since they will be compacted on commit.
+    pub fn gc(&mut self) -> GcStats {
+        let mut stats = GcStats::default();
+        let before_state = self.before_state.clone();
+        let prev_moved: HashSet<BlockPtr> =
+            self.prev_moved.iter().flat_map(|(k, v)| [*k, *v]).collect();
+        let store = self.store_mut();
+        let filter = store.options.gc_filter.clone();
+        let collect = !store.options.skip_gc;
+        let clients: Vec<ClientID> = store.blocks.iter().map(|(client, _)| *client).collect();
+        for client in clients {
+            let blocks = store.blocks.get_mut(&client).unwrap();
+            let end = before_state.get(&client);
+            let last = blocks.last();
+            let upper = if end >= last.id().clock + last.len() {
+                blocks.len()
+            } else {
+                blocks.find_pivot(end).unwrap_or(blocks.len())
+            };
+            if collect {
+                for i in 0..upper {
+                    let mut block = blocks.get(i);
+                    if let Block::Item(item) = block.deref() {
+                        let collectable = item.is_deleted()
+                            && !item.info.is_keep()
+                            && !matches!(item.content, ItemContent::Deleted(_))
+                            && filter
+                                .as_ref()
+                                .map(|f| f.call(&item.id, &item.content))
+                                .unwrap_or(true);
+                        if collectable {
+                            block.gc(false);
+                            stats.collected += 1;
+                        }
+                    }
+                }
+            }
+
+            let len = blocks.len();
+            for i in (1..upper).rev() {
+                if !prev_moved.contains(&blocks.get(i)) && !prev_moved.contains(&blocks.get(i - 1))
+                {
+                    blocks.squash_left(i);
+                }
+            }
+            stats.squashed += len - blocks.len();
+            stats.blocks += blocks.len();
+            store.update_memory_usage(&client);
+        }
+        stats
     }
 
     fn try_gc(&self) {
         let store = self.store();
+        let filter = store.options.gc_filter.as_ref();
         for (client, range) in self.delete_set.iter() {
             if let Some(blocks) = store.blocks.get(client) {
                 for delete_item in range.iter().rev() {
@@ -656,7 +1807,15 @@ impl Transaction {
                             if start > delete_item.end {
                                 break;
                             } else {
-                                block.gc(false);
+                                if let Block::Item(item) = block.deref() {
+                                    let collect = match filter {
+                                        Some(f) => f.call(&item.id, &item.content),
+                                        None => true,
+                                    };
+                                    if collect && !item.info.is_keep() {
+                                        block.gc(false);
+                                    }
+                                }
                                 i += 1;
                             }
                         }
@@ -667,6 +1826,8 @@ impl Transaction {
     }
 
     pub(crate) fn add_changed_type(&mut self, parent: BranchPtr, parent_sub: Option<Rc<str>>) {
+        parent.string_cache.take();
+        parent.search_markers.take();
         let trigger = if let Some(ptr) = parent.item {
             (ptr.id().clock < self.before_state.get(&ptr.id().client)) && !ptr.is_deleted()
         } else {
@@ -760,6 +1921,51 @@ impl Transaction {
     }
 }
 
+/// A descriptor of a transaction origin. It can be used to tag transactions in order to recognize
+/// them later on, e.g. to tell apart local and remote changes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin(Box<[u8]>);
+
+impl AsRef<[u8]> for Origin {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<'a> From<&'a [u8]> for Origin {
+    fn from(value: &'a [u8]) -> Self {
+        Origin(value.into())
+    }
+}
+
+impl<'a> From<&'a str> for Origin {
+    fn from(value: &'a str) -> Self {
+        Origin(value.as_bytes().into())
+    }
+}
+
+impl From<String> for Origin {
+    fn from(value: String) -> Self {
+        Origin(value.into_bytes().into_boxed_slice())
+    }
+}
+
+macro_rules! impl_origin_from_number {
+    ($t:ty) => {
+        impl From<$t> for Origin {
+            fn from(value: $t) -> Self {
+                Origin(value.to_be_bytes().as_ref().into())
+            }
+        }
+    };
+}
+
+impl_origin_from_number!(i32);
+impl_origin_from_number!(i64);
+impl_origin_from_number!(u32);
+impl_origin_from_number!(u64);
+impl_origin_from_number!(usize);
+
 impl Drop for Transaction {
     fn drop(&mut self) {
         self.commit()
@@ -1,11 +1,12 @@
-use crate::block::{ItemContent, Prelim};
-use crate::block_iter::{BlockIter, SliceConcat};
+use crate::block::{Block, BlockPtr, Item, ItemContent, Prelim};
+use crate::block_iter::{BlockIter, OutOfBounds, SliceConcat};
 use crate::event::Subscription;
-use crate::moving::RelativePosition;
+use crate::moving::{MoveInfo, RelativePosition};
 use crate::types::{
-    event_change_set, Branch, BranchPtr, Change, ChangeSet, Observers, Path, Value, TYPE_REFS_ARRAY,
+    event_change_set, Branch, BranchPtr, Change, ChangeSet, Observers, Path, PrelimValue, Value,
+    TYPE_REFS_ARRAY,
 };
-use crate::{SubscriptionId, Transaction, ID};
+use crate::{Cursor, LinkSource, SubscriptionId, Transaction, WeakPrelim, ID};
 use lib0::any::Any;
 use std::cell::UnsafeCell;
 use std::collections::HashSet;
@@ -44,20 +45,38 @@ impl Array {
     /// current array with given `value`, while inserting at array length is equivalent to appending
     /// that value at the end of it.
     ///
-    /// Using `index` value that's higher than current array length results in panic.
+    /// Using `index` value that's higher than current array length results in panic. See
+    /// [Array::try_insert] for a non-panicking alternative.
     pub fn insert<V: Prelim>(&self, txn: &mut Transaction, index: u32, value: V) {
-        let mut walker = BlockIter::new(self.0);
-        if walker.try_forward(txn, index) {
-            walker.insert_contents(txn, value)
-        } else {
+        if self.try_insert(txn, index, value).is_err() {
             panic!("Index {} is outside of the range of an array", index);
         }
     }
 
+    /// Inserts a `value` at the given `index`. Inserting at index `0` is equivalent to prepending
+    /// current array with given `value`, while inserting at array length is equivalent to appending
+    /// that value at the end of it.
+    ///
+    /// Returns an [OutOfBounds] error if `index` is higher than current array length.
+    pub fn try_insert<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        value: V,
+    ) -> Result<(), OutOfBounds> {
+        let mut walker = BlockIter::new(self.0);
+        walker.try_forward(txn, index)?;
+        walker.insert_contents(txn, value);
+        Ok(())
+    }
+
     /// Inserts multiple `values` at the given `index`. Inserting at index `0` is equivalent to
     /// prepending current array with given `values`, while inserting at array length is equivalent
     /// to appending that value at the end of it.
     ///
+    /// All `values` are integrated together as a single block, which is much cheaper than inserting
+    /// them one by one.
+    ///
     /// Using `index` value that's higher than current array length results in panic.
     pub fn insert_range<T, V>(&self, txn: &mut Transaction, index: u32, values: T)
     where
@@ -67,6 +86,30 @@ impl Array {
         self.insert(txn, index, PrelimRange(values))
     }
 
+    /// Inserts a JSON-like `value` at the given `index`. Unlike [Array::insert], nested [Any::Map]
+    /// and [Any::Array] values are inserted as nested [Map](crate::Map)s and [Array]s rather than
+    /// primitives.
+    ///
+    /// Using `index` value that's higher than current array length results in panic.
+    pub fn insert_json(&self, txn: &mut Transaction, index: u32, value: Any) {
+        self.insert(txn, index, PrelimValue::from_json(value))
+    }
+
+    /// Inserts multiple `values` at the given `index`. Returns an [OutOfBounds] error if `index`
+    /// is higher than current array length.
+    pub fn try_insert_range<T, V>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        values: T,
+    ) -> Result<(), OutOfBounds>
+    where
+        T: IntoIterator<Item = V>,
+        V: Into<Any>,
+    {
+        self.try_insert(txn, index, PrelimRange(values))
+    }
+
     /// Inserts given `value` at the end of the current array.
     pub fn push_back<V: Prelim>(&self, txn: &mut Transaction, value: V) {
         let len = self.len();
@@ -86,44 +129,128 @@ impl Array {
     /// Removes a range of elements from current array, starting at given `index` up until
     /// a particular number described by `len` has been deleted. This method panics in case when
     /// not all expected elements were removed (due to insufficient number of elements in an array)
-    /// or `index` is outside of the bounds of an array.
+    /// or `index` is outside of the bounds of an array. See [Array::try_remove_range] for
+    /// a non-panicking alternative.
     pub fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) {
-        let mut walker = BlockIter::new(self.0);
-        if walker.try_forward(txn, index) {
-            walker.delete(txn, len)
-        } else {
-            panic!("Index {} is outside of the range of an array", index);
+        if self.try_remove_range(txn, index, len).is_err() {
+            panic!(
+                "Range of {} elements starting at {} is outside of the range of an array",
+                len, index
+            );
         }
     }
 
+    /// Removes a range of elements from current array, starting at given `index` up until
+    /// a particular number described by `len` has been deleted. Returns an [OutOfBounds] error if
+    /// requested range doesn't fit within the bounds of an array. Bounds are checked against
+    /// the length of an array before any element is removed.
+    pub fn try_remove_range(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<(), OutOfBounds> {
+        let mut walker = BlockIter::new(self.0);
+        walker.try_forward(txn, index)?;
+        walker.try_delete(txn, len)
+    }
+
+    /// Removes a range of elements from current array, starting at given `index` up until
+    /// a particular number described by `len` has been deleted, and returns removed elements.
+    /// Returns an [OutOfBounds] error if requested range doesn't fit within the bounds of an
+    /// array. In that case array is not modified.
+    ///
+    /// This is equivalent of reading a range of elements followed by [Array::try_remove_range],
+    /// but traverses an array only once.
+    pub fn drain(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<Vec<Value>, OutOfBounds> {
+        let mut walker = BlockIter::new(self.0);
+        walker.try_forward(txn, index)?;
+        walker.try_drain(txn, len)
+    }
+
     /// Retrieves a value stored at a given `index`. Returns `None` when provided index was out
     /// of the range of a current array.
     pub fn get(&self, index: u32) -> Option<Value> {
         let mut txn = self.0.try_transact().expect("Array is not integrated");
         let mut walker = BlockIter::new(self.0);
-        if walker.try_forward(&mut txn, index) {
-            walker.read_value(&mut txn)
-        } else {
-            None
+        walker.try_forward(&mut txn, index).ok()?;
+        walker.read_value(&mut txn)
+    }
+
+    /// Returns binary contents stored at a given `index`, either as a single binary blob or as
+    /// an [Array] of binary chunks (see: [PrelimBinary](crate::PrelimBinary)). Returns `None` if
+    /// `index` was out of the range of a current array or it was not a binary value.
+    pub fn get_binary(&self, index: u32) -> Option<Vec<u8>> {
+        self.get(index).and_then(Value::to_binary)
+    }
+
+    /// Returns a preliminary weak link to `len` consecutive elements starting at a given `index`,
+    /// which can be inserted into other collections. Returns an [OutOfBounds] error if quoted
+    /// range is empty or doesn't fit within the bounds of an array.
+    ///
+    /// A link points to the quoted elements themselves, so it keeps referencing them even if they
+    /// are moved. Elements inserted in between quoted ones become a part of a linked range.
+    /// See: [WeakLink](crate::WeakLink).
+    pub fn quote(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<WeakPrelim, OutOfBounds> {
+        match index.checked_add(len) {
+            Some(end) if len > 0 && end <= self.len() => {}
+            _ => return Err(OutOfBounds),
+        }
+        let start = RelativePosition::from_type_index(txn, self.0, index, true)?;
+        let end = RelativePosition::from_type_index(txn, self.0, index + len - 1, true)?;
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(WeakPrelim::new(LinkSource::new(start.id, end.id))),
+            _ => Err(OutOfBounds),
         }
     }
 
     /// Moves element found at `source` index into `target` index position.
+    ///
+    /// This method panics if either `source` or `target` are outside of the bounds of an array.
+    /// See [Array::try_move_to] for a non-panicking alternative.
     pub fn move_to(&self, txn: &mut Transaction, source: u32, target: u32) {
-        if source == target || source + 1 == target {
+        if self.try_move_to(txn, source, target).is_err() {
+            panic!(
+                "Cannot move element from {} to {}: index is outside of the range of an array",
+                source, target
+            );
+        }
+    }
+
+    /// Moves element found at `source` index into `target` index position. Returns an
+    /// [OutOfBounds] error if either `source` or `target` are outside of the bounds of an array.
+    pub fn try_move_to(
+        &self,
+        txn: &mut Transaction,
+        source: u32,
+        target: u32,
+    ) -> Result<(), OutOfBounds> {
+        let len = self.len();
+        if source >= len || target > len {
+            return Err(OutOfBounds);
+        } else if source == target || source.checked_add(1) == Some(target) {
             // It doesn't make sense to move a range into the same range (it's basically a no-op).
-            return;
+            return Ok(());
         }
-        let left = RelativePosition::from_type_index(txn, self.0, source, true)
-            .expect("unbounded relative positions are not supported yet");
+        // `source` can be unbounded only if it points at the end of an array
+        let left =
+            RelativePosition::from_type_index(txn, self.0, source, true)?.ok_or(OutOfBounds)?;
         let mut right = left.clone();
         right.assoc = false;
         let mut walker = BlockIter::new(self.0);
-        if walker.try_forward(txn, target) {
-            walker.insert_move(txn, left, right);
-        } else {
-            panic!("Index {} is outside of the range of an array", target);
-        }
+        walker.try_forward(txn, target)?;
+        walker.insert_move(txn, left, Some(right));
+        Ok(())
     }
 
     /// Moves all elements found within `start`..`end` indexes range (both side inclusive) into
@@ -134,6 +261,9 @@ impl Array {
     /// `assoc_start`/`assoc_end` flags are used to mark if ranges should include elements that
     /// might have been inserted concurrently at the edges of the range definition.
     ///
+    /// This method panics if any of the provided indexes is outside of the bounds of an array.
+    /// See [Array::try_move_range_to] for a non-panicking alternative.
+    ///
     /// Example:
     /// ```
     /// use yrs::Doc;
@@ -152,37 +282,198 @@ impl Array {
         assoc_end: bool,
         target: u32,
     ) {
-        if start <= target && target <= end {
+        if self
+            .try_move_range_to(txn, start, assoc_start, end, assoc_end, target)
+            .is_err()
+        {
+            panic!(
+                "Cannot move range {}..={} to {}: index is outside of the range of an array",
+                start, end, target
+            );
+        }
+    }
+
+    /// Moves all elements found within `start`..`end` indexes range (both side inclusive) into
+    /// new position pointed by `target` index. Returns an [OutOfBounds] error if any of the
+    /// provided indexes is outside of the bounds of an array or `start` is greater than `end`.
+    ///
+    /// See [Array::move_range_to] for more details.
+    pub fn try_move_range_to(
+        &self,
+        txn: &mut Transaction,
+        start: u32,
+        assoc_start: bool,
+        end: u32,
+        assoc_end: bool,
+        target: u32,
+    ) -> Result<(), OutOfBounds> {
+        let len = self.len();
+        if start > end || end >= len || target > len {
+            return Err(OutOfBounds);
+        } else if start <= target && target <= end {
             // It doesn't make sense to move a range into the same range (it's basically a no-op).
-            return;
+            return Ok(());
         }
-        let left = RelativePosition::from_type_index(txn, self.0, start, assoc_start)
-            .expect("unbounded relative positions are not supported yet");
-        let right = RelativePosition::from_type_index(txn, self.0, end + 1, assoc_end)
-            .expect("unbounded relative positions are not supported yet");
+        let after_end = end.checked_add(1).ok_or(OutOfBounds)?;
+        // if range starts at the beginning or ends at the end of an array, associate it with the
+        // edge element instead (see: Array::move_range_to_end for open-ended ranges)
+        let left = match RelativePosition::from_type_index(txn, self.0, start, assoc_start)? {
+            Some(pos) => pos,
+            None => {
+                RelativePosition::from_type_index(txn, self.0, start, true)?.ok_or(OutOfBounds)?
+            }
+        };
+        let right = match RelativePosition::from_type_index(txn, self.0, after_end, assoc_end)? {
+            Some(pos) => pos,
+            None => RelativePosition::from_type_index(txn, self.0, after_end, false)?
+                .ok_or(OutOfBounds)?,
+        };
         let mut walker = BlockIter::new(self.0);
-        if walker.try_forward(txn, target) {
-            walker.insert_move(txn, left, right);
-        } else {
-            panic!("Index {} is outside of the range of an array", target);
+        walker.try_forward(txn, target)?;
+        walker.insert_move(txn, left, Some(right));
+        Ok(())
+    }
+
+    /// Moves all elements starting from `start` index up to the end of an array into new position
+    /// pointed by `target` index. Unlike [Array::move_range_to],
+    /// moved range is open-ended: elements appended at the end of an array later on, also
+    /// concurrently by other peers, become a part of a moved range as well.
+    ///
+    /// `assoc_start` flag is used to mark if range should include elements that might have been
+    /// inserted concurrently right before the `start` element.
+    ///
+    /// This method panics if any of the provided indexes is outside of the bounds of an array.
+    /// See [Array::try_move_range_to_end] for a non-panicking alternative.
+    ///
+    /// Example:
+    /// ```
+    /// use yrs::Doc;
+    /// let doc = Doc::new();
+    /// let array = doc.transact().get_array("array");
+    /// array.insert_range(&mut doc.transact(), 0, [1,2,3,4]);
+    /// // move elements 3 and 4 (and everything appended after them) in front of 1
+    /// array.move_range_to_end(&mut doc.transact(), 2, true, 0);
+    /// array.push_back(&mut doc.transact(), 5);
+    /// assert_eq!(array.to_json().to_string(), "[3, 4, 5, 1, 2]");
+    /// ```
+    pub fn move_range_to_end(
+        &self,
+        txn: &mut Transaction,
+        start: u32,
+        assoc_start: bool,
+        target: u32,
+    ) {
+        if self
+            .try_move_range_to_end(txn, start, assoc_start, target)
+            .is_err()
+        {
+            panic!(
+                "Cannot move range {}.. to {}: index is outside of the range of an array",
+                start, target
+            );
         }
     }
 
+    /// Moves all elements starting from `start` index up to the end of an array into new position
+    /// pointed by `target` index. Returns an [OutOfBounds] error if any of the provided indexes
+    /// is outside of the bounds of an array.
+    ///
+    /// See [Array::move_range_to_end] for more details.
+    pub fn try_move_range_to_end(
+        &self,
+        txn: &mut Transaction,
+        start: u32,
+        assoc_start: bool,
+        target: u32,
+    ) -> Result<(), OutOfBounds> {
+        let len = self.len();
+        if start >= len || target > len {
+            return Err(OutOfBounds);
+        } else if start <= target {
+            // It doesn't make sense to move a range into the same range (it's basically a no-op).
+            return Ok(());
+        }
+        let left = match RelativePosition::from_type_index(txn, self.0, start, assoc_start)? {
+            Some(pos) => pos,
+            None => {
+                RelativePosition::from_type_index(txn, self.0, start, true)?.ok_or(OutOfBounds)?
+            }
+        };
+        let mut walker = BlockIter::new(self.0);
+        walker.try_forward(txn, target)?;
+        walker.insert_move(txn, left, None);
+        Ok(())
+    }
+
+    /// Returns a [Cursor] pointing at the beginning of a current array, which can be used to
+    /// perform a sequence of reads and edits at nearby positions within a scope of a given
+    /// transaction.
+    pub fn cursor<'txn>(&self, txn: &'txn mut Transaction) -> Cursor<'txn> {
+        Cursor::new(self.0, txn)
+    }
+
     /// Returns an iterator, that can be used to lazely traverse over all values stored in a current
-    /// array.
+    /// array. Values are returned in index order, which is the same on every replica which has
+    /// integrated the same set of changes.
     pub fn iter(&self) -> ArrayIter {
         ArrayIter::new(self)
     }
 
+    /// Returns an iterator, that can be used to lazely traverse over `len` consecutive values
+    /// starting at a given `start` index, without reading the elements outside of that range.
+    /// Returns an [OutOfBounds] error if requested range doesn't fit within the bounds of an
+    /// array.
+    ///
+    /// Example:
+    /// ```
+    /// use yrs::Doc;
+    /// use yrs::types::Value;
+    /// let doc = Doc::new();
+    /// let array = doc.transact().get_array("array");
+    /// array.insert_range(&mut doc.transact(), 0, [1, 2, 3, 4]);
+    ///
+    /// let window: Vec<Value> = array.iter_range(1, 2).unwrap().collect();
+    /// assert_eq!(window, vec![Value::from(2), Value::from(3)]);
+    /// ```
+    pub fn iter_range(&self, start: u32, len: u32) -> Result<ArrayIter, OutOfBounds> {
+        match start.checked_add(len) {
+            Some(end) if end <= self.len() => {}
+            _ => return Err(OutOfBounds),
+        }
+        let mut iter = ArrayIter::new(self);
+        iter.inner.try_forward(&mut iter.txn, start)?;
+        iter.remaining = Some(len);
+        Ok(iter)
+    }
+
+    /// Returns `len` consecutive values starting at a given `start` index. Returns an
+    /// [OutOfBounds] error if requested range doesn't fit within the bounds of an array.
+    ///
+    /// Unlike [Array::iter_range], all values are read in a single pass over the underlying
+    /// blocks.
+    pub fn slice(&self, start: u32, len: u32) -> Result<Vec<Value>, OutOfBounds> {
+        let remaining = self.len().checked_sub(start).ok_or(OutOfBounds)?;
+        if len > remaining {
+            return Err(OutOfBounds);
+        }
+        let mut txn = self.0.try_transact().expect("Array is not integrated");
+        let mut walker = BlockIter::new(self.0);
+        walker.try_forward(&mut txn, start)?;
+        walker.slice::<ArraySliceConcat>(&mut txn, len, Vec::with_capacity(len as usize))
+    }
+
+    /// Returns metadata of all move operations (see: [Array::move_to] and
+    /// [Array::move_range_to]) which are currently in effect within this array, in the order
+    /// in which move blocks are stored in the document.
+    pub fn moves(&self) -> Vec<MoveInfo> {
+        moves(self.0.start, |item| !item.is_deleted())
+    }
+
     /// Converts all contents of current array into a JSON-like representation.
     pub fn to_json(&self) -> Any {
-        let len = self.0.len();
         let mut walker = BlockIter::new(self.0);
         let mut txn = self.0.try_transact().unwrap();
-        let values = walker
-            .slice::<ArraySliceConcat>(&mut txn, len, Vec::default())
-            .unwrap();
-        let res = values.into_iter().map(Value::to_json).collect();
+        let res = walker.values(&mut txn).map(Value::to_json).collect();
         Any::Array(res)
     }
 
@@ -227,6 +518,7 @@ impl AsMut<Branch> for Array {
 pub struct ArrayIter<'a> {
     inner: BlockIter,
     txn: Transaction,
+    remaining: Option<u32>,
     _marker: PhantomData<&'a Array>,
 }
 
@@ -235,6 +527,7 @@ impl<'a> ArrayIter<'a> {
         ArrayIter {
             inner: BlockIter::new(array.0),
             txn: array.0.try_transact().unwrap(),
+            remaining: None,
             _marker: PhantomData,
         }
     }
@@ -244,13 +537,13 @@ impl<'b> Iterator for ArrayIter<'b> {
     type Item = Value;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.inner.finished() {
-            None
-        } else {
-            let mut res = self
-                .inner
-                .slice::<ArraySliceConcat>(&mut self.txn, 1, Vec::default())?;
-            res.pop()
+        match self.remaining.as_mut() {
+            Some(0) => None,
+            Some(remaining) => {
+                *remaining -= 1;
+                self.inner.read_value(&mut self.txn)
+            }
+            None => self.inner.read_value(&mut self.txn),
         }
     }
 }
@@ -359,26 +652,47 @@ impl ArrayEvent {
         &self.changes(txn).deleted
     }
 
+    /// Returns metadata of move operations, which have been applied to corresponding [Array]
+    /// within a bounds of current transaction. Elements reordered by these moves are reported by
+    /// [ArrayEvent::delta] as removed from their former and added at their new positions.
+    pub fn moves(&self, txn: &Transaction) -> Vec<MoveInfo> {
+        moves(self.target.0.start, |item| {
+            !item.is_deleted() && txn.has_added(&item.id)
+        })
+    }
+
     fn changes(&self, txn: &Transaction) -> &ChangeSet<Change> {
         let change_set = unsafe { self.change_set.get().as_mut().unwrap() };
         change_set.get_or_insert_with(|| Box::new(event_change_set(txn, self.target.0.start)))
     }
 }
 
+/// Collects metadata of the move blocks, starting from a given block, that satisfy a `predicate`.
+fn moves<F>(start: Option<BlockPtr>, predicate: F) -> Vec<MoveInfo>
+where
+    F: Fn(&Item) -> bool,
+{
+    let mut result = Vec::new();
+    let mut current = start;
+    while let Some(Block::Item(item)) = current.as_deref() {
+        if let ItemContent::Move(m) = &item.content {
+            if predicate(item) {
+                result.push(MoveInfo::new(item.id, m));
+            }
+        }
+        current = item.right;
+    }
+    result
+}
+
 pub(crate) struct ArraySliceConcat;
 
 impl SliceConcat for ArraySliceConcat {
     fn slice(content: &mut ItemContent, offset: usize, len: usize) -> Vec<Value> {
         let mut content = content.get_content();
-        if content.len() <= len && offset == 0 {
-            content
-        } else {
-            if offset != 0 {
-                for _ in content.drain(0..offset) { /* do nothing */ }
-            }
-            for _ in content.drain(len..) { /* do nothing */ }
-            content
-        }
+        content.drain(0..offset.min(content.len()));
+        content.truncate(len);
+        content
     }
 
     #[inline]
@@ -390,13 +704,12 @@ impl SliceConcat for ArraySliceConcat {
 
 #[cfg(test)]
 mod test {
-    use crate::test_utils::{exchange_updates, run_scenario, RngExt};
+    use crate::block_iter::{BlockIter, OutOfBounds};
+    use crate::testing::{array_edits, exchange_updates, run_scenario};
     use crate::types::map::PrelimMap;
     use crate::types::{Change, DeepObservable, Event, Path, PathSegment, Value};
-    use crate::{Doc, PrelimArray, StateVector, Update, ID};
+    use crate::{Doc, PrelimArray, PrelimBinary, RelativePosition, StateVector, Update, ID};
     use lib0::any::Any;
-    use rand::prelude::StdRng;
-    use rand::Rng;
     use std::cell::{Cell, RefCell};
     use std::collections::{HashMap, HashSet};
     use std::fs::File;
@@ -509,6 +822,84 @@ mod test {
         assert_eq!(a.len(), 4);
     }
 
+    #[test]
+    fn iter_range_and_slice() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.transact().get_array("array");
+        {
+            let mut txn = doc.transact();
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+            array.insert_range(&mut txn, 3, [4, 5, 6]);
+            array.move_to(&mut txn, 0, 6);
+        }
+        assert_eq!(array.to_json(), vec![2, 3, 4, 5, 6, 1].into());
+
+        let window: Vec<_> = array.iter_range(1, 3).unwrap().collect();
+        assert_eq!(window, vec![3.into(), 4.into(), 5.into()]);
+        assert_eq!(array.slice(1, 3).unwrap(), window);
+
+        let tail: Vec<_> = array.iter_range(4, 2).unwrap().collect();
+        assert_eq!(tail, vec![6.into(), 1.into()]);
+        assert_eq!(array.slice(4, 2).unwrap(), tail);
+
+        assert_eq!(array.iter_range(6, 0).unwrap().count(), 0);
+        assert_eq!(array.slice(6, 0).unwrap(), vec![]);
+        assert!(array.iter_range(4, 3).is_err());
+        assert_eq!(array.slice(7, 0), Err(OutOfBounds));
+        assert!(array.iter_range(1, u32::MAX).is_err());
+        assert_eq!(array.slice(0, u32::MAX), Err(OutOfBounds));
+        assert_eq!(array.slice(u32::MAX, 1), Err(OutOfBounds));
+    }
+
+    #[test]
+    fn binary_blobs() {
+        let data: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let array = txn.get_array("array");
+        array.push_back(&mut txn, PrelimBinary::new(data.clone()));
+        array.push_back(&mut txn, PrelimBinary::chunked(data.clone(), 100));
+        array.push_back(&mut txn, 1);
+
+        assert_eq!(array.get(1).unwrap().to_yarray().unwrap().len(), 3);
+        assert_eq!(array.get_binary(0), Some(data.clone()));
+        assert_eq!(array.get_binary(1), Some(data));
+        assert_eq!(array.get_binary(2), None);
+        assert_eq!(array.get_binary(3), None);
+    }
+
+    #[test]
+    fn drain() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.transact().get_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.transact().get_array("array");
+        {
+            let mut txn = d1.transact();
+            a1.insert_range(&mut txn, 0, [1, 2, 3]);
+            a1.insert_range(&mut txn, 3, ["a", "b"]);
+            a1.push_back(&mut txn, PrelimArray::from([true]));
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let removed = a1.drain(&mut d1.transact(), 1, 3).unwrap();
+        assert_eq!(removed, vec![2.into(), 3.into(), "a".into()]);
+        assert_eq!(a1.to_json(), Any::from_json(r#"[1,"b",[true]]"#).unwrap());
+
+        // removed shared collections are returned as references to deleted types
+        let removed = a1.drain(&mut d1.transact(), 2, 1).unwrap();
+        assert!(matches!(removed.as_slice(), [Value::YArray(_)]));
+        assert_eq!(
+            a1.drain(&mut d1.transact(), 1, 2),
+            Err(OutOfBounds),
+            "range outside of the array bounds"
+        );
+        assert_eq!(a1.len(), 2);
+
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(a2.to_json(), Any::from_json(r#"[1,"b"]"#).unwrap());
+    }
+
     #[test]
     fn remove_insert() {
         let d1 = Doc::with_client_id(1);
@@ -746,6 +1137,104 @@ mod test {
         }
     }
 
+    #[test]
+    fn iter_array_entries() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let a1 = {
+            let mut txn = d1.transact();
+            let a1 = txn.get_array("arr");
+            a1.insert_range(&mut txn, 0, [1, 2, 3]);
+            a1
+        };
+        exchange_updates(&[&d1, &d2]);
+        let a2 = {
+            let mut txn = d2.transact();
+            let a2 = txn.get_array("arr");
+            a2.insert(&mut txn, 2, "a");
+            a2
+        };
+        exchange_updates(&[&d1, &d2]);
+
+        let mut txn = d1.transact();
+        let mut walker = BlockIter::new(a1.0);
+        walker.try_forward(&mut txn, 1).unwrap();
+        let actual: Vec<_> = walker.entries(&mut txn).collect();
+        assert_eq!(
+            actual,
+            vec![
+                (ID::new(1, 1), Value::from(2.0)),
+                (ID::new(2, 0), Value::from("a")),
+                (ID::new(1, 2), Value::from(3.0)),
+            ]
+        );
+        assert!(walker.finished());
+        assert_eq!(a1.to_json(), a2.to_json());
+
+        let mut walker = BlockIter::new(a1.0);
+        let taken: Vec<_> = walker.values(&mut txn).take(2).collect();
+        assert_eq!(taken, vec![Value::from(1.0), Value::from(2.0)]);
+        assert_eq!(walker.read_value(&mut txn), Some(Value::from("a")));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let d = Doc::with_client_id(1);
+        let mut txn = d.transact();
+        let a = txn.get_array("arr");
+        a.insert_range(&mut txn, 0, [1, 2, 3]);
+
+        assert_eq!(a.try_insert(&mut txn, 4, "a"), Err(OutOfBounds));
+        assert_eq!(a.try_remove_range(&mut txn, 2, 2), Err(OutOfBounds));
+        assert_eq!(a.try_remove_range(&mut txn, 4, 0), Err(OutOfBounds));
+        assert_eq!(a.try_remove_range(&mut txn, 1, u32::MAX), Err(OutOfBounds));
+        assert_eq!(a.try_insert(&mut txn, u32::MAX, "a"), Err(OutOfBounds));
+        assert_eq!(a.try_move_to(&mut txn, 3, 0), Err(OutOfBounds));
+        assert_eq!(a.try_move_to(&mut txn, 0, 5), Err(OutOfBounds));
+        assert_eq!(
+            a.try_move_range_to(&mut txn, 0, true, 1, false, 4),
+            Err(OutOfBounds)
+        );
+        assert_eq!(a.try_move_to(&mut txn, u32::MAX, 0), Err(OutOfBounds));
+        assert_eq!(a.try_move_to(&mut txn, 0, u32::MAX), Err(OutOfBounds));
+        assert_eq!(
+            a.try_move_range_to(&mut txn, 0, true, u32::MAX, false, 0),
+            Err(OutOfBounds)
+        );
+        assert_eq!(
+            a.try_move_range_to(&mut txn, u32::MAX, true, u32::MAX, false, 0),
+            Err(OutOfBounds)
+        );
+        assert_eq!(
+            a.try_move_range_to(&mut txn, 2, true, 1, false, 0),
+            Err(OutOfBounds)
+        );
+        assert_eq!(
+            a.try_move_range_to_end(&mut txn, u32::MAX, true, 0),
+            Err(OutOfBounds)
+        );
+        assert_eq!(a.get(3), None);
+        assert_eq!(a.to_json(), vec![1, 2, 3].into());
+
+        assert_eq!(a.try_insert(&mut txn, 3, "a"), Ok(()));
+        assert_eq!(a.try_remove_range(&mut txn, 0, 1), Ok(()));
+        assert_eq!(a.try_move_to(&mut txn, 0, 3), Ok(()));
+        assert_eq!(
+            a.to_json(),
+            Any::Array(vec![Any::Number(3.0), "a".into(), Any::Number(2.0)].into())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Range of 4294967295 elements starting at 1")]
+    fn remove_range_out_of_bounds() {
+        let d = Doc::with_client_id(1);
+        let mut txn = d.transact();
+        let a = txn.get_array("arr");
+        a.insert_range(&mut txn, 0, [1, 2, 3]);
+        a.remove_range(&mut txn, 1, u32::MAX);
+    }
+
     #[test]
     fn insert_and_remove_events() {
         let d = Doc::with_client_id(1);
@@ -891,6 +1380,36 @@ mod test {
         );
     }
 
+    #[test]
+    fn delta_mixed_runs() {
+        let doc = Doc::with_client_id(1);
+        let mut array = doc.transact().get_array("array");
+        array.insert_range(&mut doc.transact(), 0, [1, 2, 3, 4, 5]);
+
+        let delta = Rc::new(RefCell::new(None));
+        let delta_c = delta.clone();
+        let _sub = array.observe(move |txn, e| {
+            *delta_c.borrow_mut() = Some(e.delta(txn).to_vec());
+        });
+
+        {
+            let mut txn = doc.transact();
+            array.remove_range(&mut txn, 1, 2); // [1,4,5]
+            array.insert(&mut txn, 2, 9); // [1,4,9,5]
+            array.insert(&mut txn, 3, 10); // [1,4,9,10,5]
+        }
+        assert_eq!(
+            delta.borrow_mut().take(),
+            Some(vec![
+                Change::Retain(1),
+                Change::Removed(2),
+                Change::Retain(1),
+                Change::Added(vec![Any::Number(9.0).into(), Any::Number(10.0).into()]),
+            ])
+        );
+        assert_eq!(array.to_json().to_string(), "[1, 4, 9, 10, 5]");
+    }
+
     #[test]
     fn target_on_local_and_remote() {
         let d1 = Doc::with_client_id(1);
@@ -928,131 +1447,9 @@ mod test {
     use crate::updates::decoder::{Decode, Decoder, DecoderV1};
     use crate::updates::encoder::{Encoder, EncoderV1};
     use lib0::decoding::{Cursor, Read};
-    use std::sync::atomic::{AtomicI64, Ordering};
-
-    static UNIQUE_NUMBER: AtomicI64 = AtomicI64::new(0);
-
-    fn get_unique_number() -> i64 {
-        UNIQUE_NUMBER.fetch_add(1, Ordering::SeqCst)
-    }
-
-    fn array_transactions() -> [Box<dyn Fn(&mut Doc, &mut StdRng)>; 5] {
-        fn move_one(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let yarray = txn.get_array("array");
-            if yarray.len() != 0 {
-                let pos = rng.between(0, yarray.len() - 1);
-                let len = 1;
-                let new_pos_adjusted = rng.between(0, yarray.len() - 1);
-                let new_pos = new_pos_adjusted + if new_pos_adjusted > pos { len } else { 0 };
-                if let Any::Array(expected) = yarray.to_json() {
-                    let mut expected = Vec::from(expected);
-                    let moved = expected.remove(pos as usize);
-                    let insert_pos = if pos < new_pos {
-                        new_pos - len
-                    } else {
-                        new_pos
-                    } as usize;
-                    expected.insert(insert_pos, moved);
-
-                    yarray.move_to(&mut txn, pos, new_pos);
-
-                    let actual = yarray.to_json();
-                    assert_eq!(actual, Any::Array(expected.into_boxed_slice()))
-                } else {
-                    panic!("should not happen")
-                }
-            }
-        }
-        fn insert(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let yarray = txn.get_array("array");
-            let unique_number = get_unique_number();
-            let len = rng.between(1, 4);
-            let content: Vec<_> = (0..len)
-                .into_iter()
-                .map(|_| Any::BigInt(unique_number))
-                .collect();
-            let mut pos = rng.between(0, yarray.len()) as usize;
-            if let Any::Array(expected) = yarray.to_json() {
-                let mut expected = Vec::from(expected);
-                yarray.insert_range(&mut txn, pos as u32, content.clone());
-
-                for any in content {
-                    expected.insert(pos, any);
-                    pos += 1;
-                }
-                let actual = yarray.to_json();
-                assert_eq!(actual, Any::Array(expected.into_boxed_slice()))
-            } else {
-                panic!("should not happen")
-            }
-        }
-
-        fn insert_type_array(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let yarray = txn.get_array("array");
-            let pos = rng.between(0, yarray.len());
-            yarray.insert(&mut txn, pos, PrelimArray::from([1, 2, 3, 4]));
-            if let Value::YArray(array2) = yarray.get(pos).unwrap() {
-                let expected: Box<[Any]> = (1..=4).map(|i| Any::Number(i as f64)).collect();
-                assert_eq!(array2.to_json(), Any::Array(expected));
-            } else {
-                panic!("should not happen")
-            }
-        }
-
-        fn insert_type_map(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let yarray = txn.get_array("array");
-            let pos = rng.between(0, yarray.len());
-            yarray.insert(&mut txn, pos, PrelimMap::<i32>::from(HashMap::default()));
-            if let Value::YMap(map) = yarray.get(pos).unwrap() {
-                map.insert(&mut txn, "someprop".to_string(), 42);
-                map.insert(&mut txn, "someprop".to_string(), 43);
-                map.insert(&mut txn, "someprop".to_string(), 44);
-            } else {
-                panic!("should not happen")
-            }
-        }
-
-        fn delete(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let yarray = txn.get_array("array");
-            let len = yarray.len();
-            if len > 0 {
-                let pos = rng.between(0, len - 1);
-                let del_len = rng.between(1, 2.min(len - pos));
-                if rng.gen_bool(0.5) {
-                    if let Value::YArray(array2) = yarray.get(pos).unwrap() {
-                        let pos = rng.between(0, array2.len() - 1);
-                        let del_len = rng.between(0, 2.min(array2.len() - pos));
-                        array2.remove_range(&mut txn, pos, del_len);
-                    }
-                } else {
-                    if let Any::Array(old_content) = yarray.to_json() {
-                        let mut old_content = Vec::from(old_content);
-                        yarray.remove_range(&mut txn, pos, del_len);
-                        old_content.drain(pos as usize..(pos + del_len) as usize);
-                        assert_eq!(yarray.to_json(), Any::Array(old_content.into_boxed_slice()));
-                    } else {
-                        panic!("should not happen")
-                    }
-                }
-            }
-        }
-
-        [
-            Box::new(insert),
-            Box::new(insert_type_array),
-            Box::new(insert_type_map),
-            Box::new(delete),
-            Box::new(move_one),
-        ]
-    }
 
     fn fuzzy(iterations: usize) {
-        run_scenario(0, &array_transactions(), 5, iterations)
+        run_scenario(0, &array_edits(), 5, iterations);
     }
 
     #[test]
@@ -1111,6 +1508,55 @@ mod test {
         assert_eq!(actual.as_slice(), expected);
     }
 
+    #[test]
+    fn observe_deep_path_index() {
+        let doc = Doc::with_client_id(1);
+        let mut array = doc.transact().get_array("array");
+        {
+            let mut txn = doc.transact();
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+            array.push_back(&mut txn, PrelimMap::<String>::new());
+        }
+
+        let paths = Rc::new(RefCell::new(Vec::new()));
+        let paths_copy = paths.clone();
+        let _sub = array.observe_deep(move |_txn, e| {
+            let path: Vec<Path> = e.iter().map(Event::path).collect();
+            paths_copy.borrow_mut().push(path);
+        });
+
+        {
+            let mut txn = doc.transact();
+            let map = array.get(3).unwrap().to_ymap().unwrap();
+            map.insert(&mut txn, "a", "a");
+        }
+
+        let expected = &[vec![Path::from([PathSegment::Index(3)])]];
+        let actual = RefCell::borrow(&paths);
+        assert_eq!(actual.as_slice(), expected);
+    }
+
+    #[test]
+    fn move_range_to_array_edges() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.transact().get_array("array");
+        array.insert_range(&mut doc.transact(), 0, [1, 2, 3, 4]);
+
+        // range ending at the last element, associated to the right
+        array.move_range_to(&mut doc.transact(), 2, true, 3, true, 0);
+        assert_eq!(array.to_json().to_string(), "[3, 4, 1, 2]");
+
+        // range starting at the first element, associated to the left
+        let array = doc.transact().get_array("array2");
+        array.insert_range(&mut doc.transact(), 0, [1, 2, 3, 4]);
+        array.move_range_to(&mut doc.transact(), 0, false, 1, true, 4);
+        assert_eq!(array.to_json().to_string(), "[3, 4, 1, 2]");
+
+        assert!(array
+            .try_move_range_to(&mut doc.transact(), 3, true, 4, true, 0)
+            .is_err());
+    }
+
     #[test]
     fn move_1() {
         let d1 = Doc::with_client_id(1);
@@ -1250,6 +1696,132 @@ mod test {
         assert_eq!(a1.to_json(), a2.to_json());
     }
 
+    #[test]
+    fn move_range_to_end() {
+        let d1 = Doc::with_client_id(1);
+        let mut a1 = d1.transact().get_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.transact().get_array("array");
+
+        let e1: Rc<RefCell<Vec<Change>>> = Rc::new(RefCell::new(Vec::default()));
+        let _s1 = {
+            let e1 = e1.clone();
+            a1.observe(move |txn, e| *e1.borrow_mut() = e.delta(txn).to_vec())
+        };
+
+        a1.insert_range(&mut d1.transact(), 0, [1, 2, 3, 4]);
+        exchange_updates(&[&d1, &d2]);
+
+        a1.move_range_to_end(&mut d1.transact(), 2, true, 0);
+        assert_eq!(a1.to_json(), vec![3, 4, 1, 2].into());
+        assert_eq!(
+            e1.take(),
+            vec![
+                Change::Added(vec![3.into(), 4.into()]),
+                Change::Retain(2),
+                Change::Removed(2)
+            ]
+        );
+        assert_eq!(a1.moves()[0].end, None);
+
+        // elements appended concurrently become a part of the moved range
+        a2.push_back(&mut d2.transact(), 5);
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(a1.to_json(), vec![3, 4, 5, 1, 2].into());
+        assert_eq!(a2.to_json(), vec![3, 4, 5, 1, 2].into());
+        a1.push_back(&mut d1.transact(), 6);
+        assert_eq!(a1.to_json(), vec![3, 4, 5, 6, 1, 2].into());
+
+        // indexed reads, inserts and deletes traverse open-ended range both ways
+        assert_eq!(a1.get(3), Some(6.into()));
+        assert_eq!(a1.get(4), Some(1.into()));
+        a1.insert(&mut d1.transact(), 4, 7);
+        assert_eq!(a1.to_json(), vec![3, 4, 5, 6, 7, 1, 2].into());
+        a1.remove_range(&mut d1.transact(), 2, 3);
+        assert_eq!(a1.to_json(), vec![3, 4, 1, 2].into());
+        {
+            let mut txn = d1.transact();
+            let mut walker = BlockIter::new(a1.0);
+            walker.try_forward(&mut txn, 4).unwrap();
+            walker.try_backward(&mut txn, 3).unwrap();
+            assert_eq!(walker.read_value(&mut txn), Some(4.into()));
+        }
+
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(a2.to_json(), vec![3, 4, 1, 2].into());
+        let d3 = Doc::with_client_id(3);
+        let a3 = d3.transact().get_array("array");
+        exchange_updates(&[&d1, &d3]);
+        assert_eq!(a3.to_json(), vec![3, 4, 1, 2].into());
+        assert_eq!(a3.moves(), a1.moves());
+
+        assert!(a1
+            .try_move_range_to_end(&mut d1.transact(), 4, true, 0)
+            .is_err());
+        assert!(a1
+            .try_move_range_to_end(&mut d1.transact(), 1, true, 5)
+            .is_err());
+    }
+
+    #[test]
+    fn move_inspection() {
+        let d1 = Doc::with_client_id(1);
+        let mut a1 = d1.transact().get_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.transact().get_array("array");
+
+        let moves = Rc::new(RefCell::new(Vec::new()));
+        let _s = {
+            let moves = moves.clone();
+            a1.observe(move |txn, e| moves.borrow_mut().push(e.moves(txn)))
+        };
+
+        a1.insert_range(&mut d1.transact(), 0, [1, 2, 3, 4]);
+        assert!(a1.moves().is_empty());
+        exchange_updates(&[&d1, &d2]);
+
+        a1.move_range_to(&mut d1.transact(), 0, true, 1, false, 4);
+        assert_eq!(a1.to_json(), vec![3, 4, 1, 2].into());
+        let m1 = a1.moves();
+        assert_eq!(m1.len(), 1);
+        assert_eq!(m1[0].start, RelativePosition::create(ID::new(1, 0), true));
+        assert_eq!(
+            m1[0].end,
+            Some(RelativePosition::create(ID::new(1, 1), false))
+        );
+        assert!(m1[0].overrides.is_empty());
+        assert_eq!(moves.borrow().last(), Some(&m1));
+        assert_eq!(
+            Value::YMove(Box::new(m1[0].clone())).to_json(),
+            Any::from_json(
+                r#"{
+                    "id":[1,4],
+                    "start":{"id":[1,0],"assoc":true},
+                    "end":{"id":[1,1],"assoc":false},
+                    "priority":1,
+                    "overrides":[]
+                }"#
+            )
+            .unwrap()
+        );
+
+        // concurrent move of an overlapping range overrides the former one
+        a2.move_range_to(&mut d2.transact(), 1, true, 2, false, 4);
+        assert_eq!(a2.to_json(), vec![1, 4, 2, 3].into());
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(a1.to_json(), a2.to_json());
+        let m2 = a2.moves();
+        assert_eq!(m2.len(), 2);
+        assert_eq!(m2, a1.moves());
+        let received = moves.borrow().last().cloned().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].id, ID::new(2, 0));
+        let winner = m2.iter().find(|m| !m.overrides.is_empty()).unwrap();
+        assert_eq!(winner.id, ID::new(2, 0));
+        assert_eq!(winner.overrides, vec![ID::new(1, 4)]);
+    }
+
     fn move_tests<P: AsRef<std::path::Path>>(path: P) {
         let mut file = File::open(path).unwrap();
         let mut buf = Vec::new();
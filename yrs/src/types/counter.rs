@@ -0,0 +1,305 @@
+use crate::block::{Block, ClientID};
+use crate::event::Subscription;
+use crate::types::{event_keys, Branch, BranchPtr, EntryChange, Observers, Value};
+use crate::*;
+use lib0::any::Any;
+use std::cell::UnsafeCell;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+/// A convergent counter, which value can be incremented and decremented by many peers
+/// concurrently without losing any of their changes.
+///
+/// Counter is a root-level type and can be obtained with [Transaction::get_counter]. Internally
+/// every peer keeps its own contribution to a total counter value stored as a separate entry of
+/// a map component, which key is a client ID of that peer. Since only one peer ever writes to
+/// its own entry, concurrent changes never conflict and a counter value is a sum of all
+/// contributions. This also means, that counters are encoded using a regular update format:
+/// other clients (ie. Yjs) will see them as maps of numbers.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::Doc;
+/// use yrs::updates::decoder::Decode;
+/// use yrs::{StateVector, Update};
+///
+/// let d1 = Doc::with_client_id(1);
+/// let c1 = d1.transact().get_counter("likes");
+/// c1.increment(&mut d1.transact(), 2);
+///
+/// let d2 = Doc::with_client_id(2);
+/// let c2 = d2.transact().get_counter("likes");
+/// c2.decrement(&mut d2.transact(), 1);
+///
+/// // exchange updates
+/// let u1 = d1.encode_state_as_update_v1(&StateVector::default());
+/// let u2 = d2.encode_state_as_update_v1(&StateVector::default());
+/// d1.transact().apply_update(Update::decode_v1(&u2).unwrap());
+/// d2.transact().apply_update(Update::decode_v1(&u1).unwrap());
+///
+/// assert_eq!(c1.get(), 1);
+/// assert_eq!(c2.get(), 1);
+/// ```
+#[repr(transparent)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Counter(BranchPtr);
+
+impl Counter {
+    /// Returns a current value of this counter.
+    pub fn get(&self) -> i64 {
+        self.0
+            .map
+            .values()
+            .filter_map(|ptr| contribution(ptr.deref()))
+            .fold(0, i64::wrapping_add)
+    }
+
+    /// Returns contributions of the individual peers to a current counter value.
+    pub fn contributions(&self) -> HashMap<ClientID, i64> {
+        let mut result = HashMap::new();
+        for (key, ptr) in self.0.map.iter() {
+            if let (Ok(client), Some(value)) = (key.parse(), contribution(ptr.deref())) {
+                result.insert(client, value);
+            }
+        }
+        result
+    }
+
+    /// Increments a counter value by a given `delta`.
+    pub fn increment(&self, txn: &mut Transaction, delta: i64) {
+        if delta == 0 {
+            return;
+        }
+        let client_id = txn.store().options.client_id;
+        let key: Rc<str> = client_id.to_string().into();
+        let current = self
+            .0
+            .map
+            .get(&key)
+            .and_then(|ptr| contribution(ptr.deref()))
+            .unwrap_or(0);
+        Map::from(self.0).insert(txn, key, Any::BigInt(current.wrapping_add(delta)));
+    }
+
+    /// Decrements a counter value by a given `delta`.
+    pub fn decrement(&self, txn: &mut Transaction, delta: i64) {
+        self.increment(txn, delta.wrapping_neg())
+    }
+
+    /// Converts a current counter value into JSON-like representation.
+    pub fn to_json(&self) -> Any {
+        Any::BigInt(self.get())
+    }
+
+    /// Subscribes a given callback to be triggered whenever a value of current counter has been
+    /// changed. A callback is triggered whenever a transaction gets committed.
+    ///
+    /// Returns an [Observer] which, when dropped, will unsubscribe current callback.
+    pub fn observe<F>(&mut self, f: F) -> Subscription<CounterEvent>
+    where
+        F: Fn(&Transaction, &CounterEvent) + 'static,
+    {
+        if let Observers::Counter(eh) = self.0.observers.get_or_insert_with(Observers::counter) {
+            eh.subscribe(f)
+        } else {
+            panic!("Observed collection is of different type") //TODO: this should be Result::Err
+        }
+    }
+
+    /// Unsubscribes a previously subscribed event callback identified by given `subscription_id`.
+    pub fn unobserve(&mut self, subscription_id: SubscriptionId) {
+        if let Some(Observers::Counter(eh)) = self.0.observers.as_mut() {
+            eh.unsubscribe(subscription_id);
+        }
+    }
+}
+
+/// Returns a contribution stored in a counter entry, unless it was deleted.
+fn contribution(block: &Block) -> Option<i64> {
+    if let Block::Item(item) = block {
+        if !item.is_deleted() {
+            return match item.content.get_last()? {
+                Value::Any(any) => as_i64(&any),
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+fn as_i64(any: &Any) -> Option<i64> {
+    match any {
+        Any::BigInt(n) => Some(*n),
+        Any::Number(n) => Some(*n as i64),
+        _ => None,
+    }
+}
+
+impl AsRef<Branch> for Counter {
+    fn as_ref(&self) -> &Branch {
+        self.0.deref()
+    }
+}
+
+impl AsMut<Branch> for Counter {
+    fn as_mut(&mut self) -> &mut Branch {
+        self.0.deref_mut()
+    }
+}
+
+impl From<BranchPtr> for Counter {
+    fn from(inner: BranchPtr) -> Self {
+        Counter(inner)
+    }
+}
+
+/// Event generated by [Counter::observe] method. Emitted during transaction commit phase.
+pub struct CounterEvent {
+    target: Counter,
+    keys: UnsafeCell<Result<HashMap<Rc<str>, EntryChange>, ChangedKeys>>,
+}
+
+type ChangedKeys = HashSet<Option<Rc<str>>>;
+
+impl CounterEvent {
+    pub(crate) fn new(branch_ref: BranchPtr, key_changes: HashSet<Option<Rc<str>>>) -> Self {
+        CounterEvent {
+            target: Counter::from(branch_ref),
+            keys: UnsafeCell::new(Err(key_changes)),
+        }
+    }
+
+    /// Returns a [Counter] instance which emitted this event.
+    pub fn target(&self) -> &Counter {
+        &self.target
+    }
+
+    /// Returns a change of counter value made within bounds of current transaction.
+    pub fn delta(&self, txn: &Transaction) -> i64 {
+        self.changes(txn)
+            .values()
+            .map(|(old, new)| new.wrapping_sub(*old))
+            .fold(0, i64::wrapping_add)
+    }
+
+    /// Returns contributions of the peers, which have been changed within bounds of current
+    /// transaction, as `(old, new)` pairs.
+    pub fn changes(&self, txn: &Transaction) -> HashMap<ClientID, (i64, i64)> {
+        let value = |v: &Value| match v {
+            Value::Any(any) => as_i64(any).unwrap_or(0),
+            _ => 0,
+        };
+        let mut result = HashMap::new();
+        for (key, change) in self.keys(txn).iter() {
+            let (old, new) = match change {
+                EntryChange::Inserted(new) => (0, value(new)),
+                EntryChange::Updated(old, new) => (value(old), value(new)),
+                EntryChange::Removed(old) => (value(old), 0),
+            };
+            if let Ok(client) = key.parse() {
+                result.insert(client, (old, new));
+            }
+        }
+        result
+    }
+
+    fn keys(&self, txn: &Transaction) -> &HashMap<Rc<str>, EntryChange> {
+        let keys = unsafe { self.keys.get().as_mut().unwrap() };
+        if let Err(subs) = keys {
+            *keys = Ok(event_keys(txn, self.target.0, subs));
+        }
+        match keys {
+            Ok(keys) => keys,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::exchange_updates;
+    use crate::{Doc, StateVector};
+    use lib0::any::Any;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    #[test]
+    fn counter_concurrent_increments() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let d3 = Doc::with_client_id(3);
+        let c1 = d1.transact().get_counter("counter");
+        let c2 = d2.transact().get_counter("counter");
+        let c3 = d3.transact().get_counter("counter");
+
+        c1.increment(&mut d1.transact(), 5);
+        c2.increment(&mut d2.transact(), 3);
+        c3.decrement(&mut d3.transact(), 2);
+        c1.increment(&mut d1.transact(), 1);
+        exchange_updates(&[&d1, &d2, &d3]);
+
+        for c in [&c1, &c2, &c3] {
+            assert_eq!(c.get(), 7);
+            assert_eq!(c.contributions(), HashMap::from([(1, 6), (2, 3), (3, -2)]));
+        }
+
+        // concurrent changes after sync
+        c2.increment(&mut d2.transact(), 10);
+        c3.increment(&mut d3.transact(), 10);
+        exchange_updates(&[&d1, &d2, &d3]);
+        assert_eq!(c1.get(), 27);
+        assert_eq!(c2.get(), 27);
+        assert_eq!(c3.get(), 27);
+
+        assert_eq!(c1.to_json(), Any::BigInt(27));
+        let expected = HashMap::from([("counter".to_string(), Any::BigInt(27))]);
+        assert_eq!(d1.transact().to_json(), Any::Map(Box::new(expected)));
+
+        // remote root types are not bound to a specific type until requested
+        let d4 = Doc::with_client_id(4);
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+        d4.transact().apply_update_v1(&update).unwrap();
+        assert_eq!(d4.transact().get_counter("counter").get(), 27);
+    }
+
+    #[test]
+    fn counter_observe() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let mut c1 = d1.transact().get_counter("counter");
+        let c2 = d2.transact().get_counter("counter");
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let _sub = {
+            let events = events.clone();
+            c1.observe(move |txn, e| {
+                events
+                    .borrow_mut()
+                    .push((e.delta(txn), e.target().get(), e.changes(txn)));
+            })
+        };
+
+        c1.increment(&mut d1.transact(), 5);
+        c2.decrement(&mut d2.transact(), 2);
+        exchange_updates(&[&d1, &d2]);
+        {
+            let mut txn = d1.transact();
+            c1.decrement(&mut txn, 1);
+            c1.increment(&mut txn, 4);
+        }
+        // no-op changes don't emit events
+        c1.increment(&mut d1.transact(), 0);
+
+        assert_eq!(
+            events.take(),
+            vec![
+                (5, 5, HashMap::from([(1, (0, 5))])),
+                (-2, 3, HashMap::from([(2, (0, -2))])),
+                (3, 6, HashMap::from([(1, (5, 8))])),
+            ]
+        );
+    }
+}
@@ -1,7 +1,8 @@
-use crate::block::{Block, ItemContent, ItemPosition, Prelim};
+use crate::block::{Block, BlockPtr, ItemContent, ItemPosition, Prelim};
 use crate::event::Subscription;
 use crate::types::{
-    event_keys, Branch, BranchPtr, Entries, EntryChange, Observers, Path, Value, TYPE_REFS_MAP,
+    event_keys, Branch, BranchPtr, Entries, EntryChange, FromValue, Observers, Path, PrelimValue,
+    Value, TYPE_REFS_MAP,
 };
 use crate::*;
 use lib0::any::Any;
@@ -10,9 +11,9 @@ use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
 
-/// Collection used to store key-value entries in an unordered manner. Keys are always represented
-/// as UTF-8 strings. Values can be any value type supported by Yrs: JSON-like primitives as well as
-/// shared data types.
+/// Collection used to store key-value entries. Keys are always represented as UTF-8 strings.
+/// Values can be any value type supported by Yrs: JSON-like primitives as well as shared data
+/// types. Entries are iterated in a deterministic order described by [Map::iter].
 ///
 /// In terms of conflict resolution, [Map] uses logical last-write-wins principle, meaning the past
 /// updates are automatically overridden and discarded by newer ones, while concurrent updates made
@@ -62,22 +63,48 @@ impl Map {
     }
 
     /// Returns an iterator that enables to traverse over all keys of entries stored within
-    /// current map. These keys are not ordered.
+    /// current map. Keys are returned in the same order as by [Map::iter].
     pub fn keys(&self) -> Keys {
         Keys(self.entries())
     }
 
     /// Returns an iterator that enables to traverse over all values stored within current map.
+    /// Values are returned in the same order as by [Map::iter].
     pub fn values(&self) -> Values {
         Values(self.entries())
     }
 
     /// Returns an iterator that enables to traverse over all entries - tuple of key-value pairs -
     /// stored within current map.
+    ///
+    /// Entries are ordered by IDs of their current values: by clock first and by client
+    /// identifier second. For the changes made by a single client it's the order in which these
+    /// values were inserted (updating an entry moves it to the end). This order is the same on
+    /// every replica which has integrated the same set of changes.
     pub fn iter(&self) -> MapIter {
         MapIter(self.entries())
     }
 
+    /// Returns an iterator that enables to traverse over all entries - tuple of key-value pairs -
+    /// stored within current map, ordered by their keys. It can be used to produce deterministic
+    /// exports, ie. for hashing or signing map contents.
+    ///
+    /// Example:
+    /// ```rust
+    /// use yrs::Doc;
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.transact().get_map("map");
+    /// map.insert(&mut doc.transact(), "b", 2);
+    /// map.insert(&mut doc.transact(), "a", 1);
+    ///
+    /// let keys: Vec<_> = map.iter_sorted_by_key().map(|(key, _)| key).collect();
+    /// assert_eq!(keys, vec!["a", "b"]);
+    /// ```
+    pub fn iter_sorted_by_key(&self) -> MapIter<'_> {
+        MapIter(Entries::sorted_by_key(&self.0.map))
+    }
+
     /// Inserts a new `value` under given `key` into current map. Returns a value stored previously
     /// under the same key (if any existed).
     pub fn insert<K: Into<Rc<str>>, V: Prelim>(
@@ -104,6 +131,19 @@ impl Map {
         previous
     }
 
+    /// Inserts a JSON-like `value` under given `key` into current map. Unlike [Map::insert],
+    /// nested [Any::Map] and [Any::Array] values are inserted as nested [Map]s and [Array]s
+    /// rather than primitives. Returns a value stored previously under the same key (if any
+    /// existed).
+    pub fn insert_json<K: Into<Rc<str>>>(
+        &self,
+        txn: &mut Transaction,
+        key: K,
+        value: Any,
+    ) -> Option<Value> {
+        self.insert(txn, key, PrelimValue::from_json(value))
+    }
+
     /// Removes a stored within current map under a given `key`. Returns that value or `None` if
     /// no entry with a given `key` was present in current map.
     pub fn remove(&self, txn: &mut Transaction, key: &str) -> Option<Value> {
@@ -116,6 +156,84 @@ impl Map {
         self.0.get(key)
     }
 
+    /// Returns a preliminary weak link to an entry stored under a given `key`, which can be
+    /// inserted into other collections. Returns `None` if no entry with such `key` existed.
+    ///
+    /// A link points to a current value of an entry: once it's overridden or removed, a link
+    /// becomes dead. See: [WeakLink].
+    pub fn link(&self, key: &str) -> Option<WeakPrelim> {
+        match self.0.map.get(key).map(BlockPtr::deref) {
+            Some(Block::Item(item)) if !item.is_deleted() => {
+                Some(WeakPrelim::new(LinkSource::new(item.id, item.last_id())))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns all values written concurrently under a given `key` on top of the same previous
+    /// value, ordered the same way on every peer. The last one is a value returned by [Map::get].
+    /// Returns an empty vector if no entry with such `key` existed.
+    ///
+    /// Values, which lost a conflict, are accessible only if a document was created with
+    /// [Options::keep_map_conflicts] set. Otherwise only the winning value is returned. Once
+    /// an entry gets overridden or removed, all of its conflicting values are gone as well.
+    pub fn get_all(&self, key: &str) -> Vec<Value> {
+        let mut result = Vec::new();
+        let winner = match self.0.map.get(key).map(BlockPtr::deref) {
+            Some(Block::Item(item)) if !item.is_deleted() => item,
+            _ => return result,
+        };
+        let mut current = Some(winner);
+        while let Some(item) = current {
+            if !item.is_deleted() || item.info.is_keep() {
+                if let Some(value) = item.content.get_last() {
+                    result.push(value);
+                }
+            }
+            current = match item.left.as_deref() {
+                Some(Block::Item(left)) if left.origin == winner.origin => Some(left),
+                _ => None,
+            };
+        }
+        result.reverse();
+        result
+    }
+
+    /// Returns binary contents stored under a given `key`, either as a single binary blob or as
+    /// an [Array] of binary chunks (see: [PrelimBinary](crate::PrelimBinary)). Returns `None` if
+    /// no entry with such `key` existed or it was not a binary value.
+    pub fn get_binary(&self, key: &str) -> Option<Vec<u8>> {
+        self.get(key).and_then(Value::to_binary)
+    }
+
+    /// Returns a value stored under a given `key` within current map, converted into a requested
+    /// type `T`. Returns an error if no entry with such `key` existed or if its value could not be
+    /// converted. Use `Option<T>` to treat null values as `None`.
+    ///
+    /// ```
+    /// use yrs::Doc;
+    /// use yrs::types::map::MapEntryError;
+    ///
+    /// let doc = Doc::new();
+    /// let mut txn = doc.transact();
+    /// let map = txn.get_map("map");
+    /// map.insert(&mut txn, "count", 3);
+    ///
+    /// assert_eq!(map.get_as::<u64>("count"), Ok(3));
+    /// assert!(matches!(map.get_as::<String>("count"), Err(MapEntryError::Mismatch { .. })));
+    /// assert!(matches!(map.get_as::<u64>("other"), Err(MapEntryError::Missing(_))));
+    /// ```
+    pub fn get_as<T: FromValue>(&self, key: &str) -> Result<T, MapEntryError> {
+        let value = self
+            .get(key)
+            .ok_or_else(|| MapEntryError::Missing(key.into()))?;
+        T::from_value(value).map_err(|actual| MapEntryError::Mismatch {
+            key: key.into(),
+            expected: std::any::type_name::<T>(),
+            actual,
+        })
+    }
+
     /// Checks if an entry with given `key` can be found within current map.
     pub fn contains(&self, key: &str) -> bool {
         if let Some(ptr) = self.0.map.get(key) {
@@ -171,6 +289,7 @@ impl AsMut<Branch> for Map {
     }
 }
 
+/// Iterator over the entries of a [Map]. See [Map::iter] for their order.
 pub struct MapIter<'a>(Entries<'a>);
 
 impl<'a> Iterator for MapIter<'a> {
@@ -186,7 +305,7 @@ impl<'a> Iterator for MapIter<'a> {
     }
 }
 
-/// An unordered iterator over the keys of a [Map].
+/// Iterator over the keys of a [Map].
 pub struct Keys<'a>(Entries<'a>);
 
 impl<'a> Iterator for Keys<'a> {
@@ -216,6 +335,40 @@ impl From<BranchPtr> for Map {
     }
 }
 
+/// Error returned by [Map::get_as].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapEntryError {
+    /// There was no entry stored under a given key.
+    Missing(Rc<str>),
+    /// An entry stored under a given key could not be converted into an `expected` type.
+    Mismatch {
+        key: Rc<str>,
+        expected: &'static str,
+        actual: Value,
+    },
+}
+
+impl std::fmt::Display for MapEntryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapEntryError::Missing(key) => write!(f, "map entry '{}' not found", key),
+            MapEntryError::Mismatch {
+                key,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "map entry '{}' could not be converted into {}: {}",
+                key,
+                expected,
+                actual.clone().to_string()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MapEntryError {}
+
 /// A preliminary map. It can be used to early initialize the contents of a [Map], when it's about
 /// to be inserted into another Yrs collection, such as [Array] or another [Map].
 pub struct PrelimMap<T>(HashMap<String, T>);
@@ -297,16 +450,16 @@ impl MapEvent {
 
 #[cfg(test)]
 mod test {
-    use crate::test_utils::{exchange_updates, run_scenario};
+    use crate::testing::{exchange_updates, map_edits, run_scenario};
+    use crate::types::map::MapEntryError;
     use crate::types::text::PrelimText;
     use crate::types::{DeepObservable, EntryChange, Event, Map, Path, PathSegment, Value};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encoder, EncoderV1};
-    use crate::{Doc, PrelimArray, PrelimMap, StateVector, Update};
+    use crate::{
+        Doc, Options, PrelimArray, PrelimBinary, PrelimMap, PrelimValue, StateVector, Update,
+    };
     use lib0::any::Any;
-    use rand::distributions::Alphanumeric;
-    use rand::prelude::{SliceRandom, StdRng};
-    use rand::Rng;
     use std::cell::RefCell;
     use std::collections::HashMap;
     use std::ops::{Deref, DerefMut};
@@ -748,59 +901,154 @@ mod test {
         );
     }
 
-    fn random_string(rng: &mut StdRng) -> String {
-        let len = rng.gen_range(1, 10);
-        rng.sample_iter(&Alphanumeric)
-            .take(len)
-            .map(char::from)
-            .collect()
+    #[test]
+    fn insert_prelim_value() {
+        let d1 = Doc::with_client_id(1);
+        let mut t1 = d1.transact();
+        let map = t1.get_map("map");
+        let value = PrelimValue::Map(HashMap::from([
+            ("title".to_string(), PrelimValue::Text("hello".to_string())),
+            (
+                "items".to_string(),
+                PrelimValue::Array(vec![
+                    1.into(),
+                    "two".into(),
+                    PrelimValue::Map(HashMap::from([("three".to_string(), 3.into())])),
+                ]),
+            ),
+        ]));
+        map.insert(&mut t1, "nested", value);
+
+        let nested = map.get("nested").unwrap().to_ymap().unwrap();
+        let title = nested.get("title").unwrap().to_ytext().unwrap();
+        assert_eq!(title.to_string(), "hello");
+        let items = nested.get("items").unwrap().to_yarray().unwrap();
+        assert_eq!(items.get(1), Some(Value::from("two")));
+        assert!(items.get(2).unwrap().to_ymap().is_some());
+
+        let d2 = Doc::with_client_id(2);
+        let mut t2 = d2.transact();
+        let remote = t2.get_map("map");
+        t2.apply_update(Update::decode_v1(&t1.encode_update_v1()).unwrap());
+        assert_eq!(remote.to_json(), map.to_json());
+        let items = remote
+            .get("nested")
+            .unwrap()
+            .to_ymap()
+            .unwrap()
+            .get("items");
+        assert_eq!(items.unwrap().to_json().to_string(), "[1, two, {three: 3}]");
     }
 
-    fn map_transactions() -> [Box<dyn Fn(&mut Doc, &mut StdRng)>; 3] {
-        fn set(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let map = txn.get_map("map");
-            let key = ["one", "two"].choose(rng).unwrap();
-            let value: String = random_string(rng);
-            map.insert(&mut txn, key.to_string(), value);
-        }
+    #[test]
+    fn insert_json() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let map = txn.get_map("map");
+        let json =
+            Any::from_json(r#"{"name":"doc","tags":["a",{"b":1}],"meta":{"ok":true}}"#).unwrap();
+        map.insert_json(&mut txn, "root", json.clone());
+
+        let root = map.get("root").unwrap().to_ymap().unwrap();
+        let tags = root.get("tags").unwrap().to_yarray().unwrap();
+        assert!(tags.get(1).unwrap().to_ymap().is_some());
+        assert!(root.get("meta").unwrap().to_ymap().is_some());
+        assert_eq!(root.to_json(), json);
+
+        tags.insert_json(&mut txn, 0, Any::from_json("[1,[2]]").unwrap());
+        assert!(tags.get(0).unwrap().to_yarray().is_some());
+        assert_eq!(
+            tags.to_json(),
+            Any::from_json(r#"[[1,[2]],"a",{"b":1}]"#).unwrap()
+        );
+    }
 
-        fn set_type(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let map = txn.get_map("map");
-            let key = ["one", "two", "three"].choose(rng).unwrap();
-            if rng.gen_bool(0.33) {
-                map.insert(
-                    &mut txn,
-                    key.to_string(),
-                    PrelimArray::from(vec![1, 2, 3, 4]),
-                );
-            } else if rng.gen_bool(0.33) {
-                map.insert(&mut txn, key.to_string(), PrelimText("deeptext"));
-            } else {
-                map.insert(
-                    &mut txn,
-                    key.to_string(),
-                    PrelimMap::from({
-                        let mut map = HashMap::default();
-                        map.insert("deepkey".to_owned(), "deepvalue");
-                        map
-                    }),
-                );
-            }
-        }
+    #[test]
+    fn get_as() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let map = txn.get_map("map");
+        map.insert(&mut txn, "count", 42);
+        map.insert(&mut txn, "ratio", 0.5);
+        map.insert(&mut txn, "name", "doc");
+        map.insert(&mut txn, "none", Any::Null);
+        map.insert(&mut txn, "tags", vec!["a", "b"]);
+        map.insert(&mut txn, "items", PrelimArray::from(vec![1, 2]));
+        map.insert(&mut txn, "nested", PrelimMap::<i32>::new());
+
+        assert_eq!(map.get_as::<u64>("count"), Ok(42));
+        assert_eq!(map.get_as::<i8>("count"), Ok(42));
+        assert_eq!(map.get_as::<f64>("ratio"), Ok(0.5));
+        assert_eq!(map.get_as::<String>("name"), Ok("doc".to_string()));
+        assert_eq!(map.get_as::<Option<String>>("none"), Ok(None));
+        assert_eq!(map.get_as::<Option<String>>("name"), Ok(Some("doc".into())));
+        assert_eq!(
+            map.get_as::<Vec<String>>("tags"),
+            Ok(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(map.get_as::<Vec<u32>>("items"), Ok(vec![1, 2]));
+        assert!(map.get_as::<Map>("nested").is_ok());
 
-        fn delete(doc: &mut Doc, rng: &mut StdRng) {
-            let mut txn = doc.transact();
-            let map = txn.get_map("map");
-            let key = ["one", "two"].choose(rng).unwrap();
-            map.remove(&mut txn, key);
+        assert_eq!(
+            map.get_as::<u8>("other"),
+            Err(MapEntryError::Missing("other".into()))
+        );
+        assert_eq!(
+            map.get_as::<u32>("ratio"),
+            Err(MapEntryError::Mismatch {
+                key: "ratio".into(),
+                expected: "u32",
+                actual: Value::from(0.5),
+            })
+        );
+        assert!(map.get_as::<Vec<String>>("items").is_err());
+        assert!(map.get_as::<Map>("items").is_err());
+        assert_eq!(
+            map.get_as::<bool>("name").unwrap_err().to_string(),
+            "map entry 'name' could not be converted into bool: doc"
+        );
+    }
+
+    #[test]
+    fn remote_key_changes() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.transact().get_map("map");
+        let d2 = Doc::with_client_id(2);
+        let mut m2 = d2.transact().get_map("map");
+
+        m1.insert(&mut d1.transact(), "a", 1);
+        m1.insert(&mut d1.transact(), "b", PrelimMap::<u32>::new());
+        exchange_updates(&[&d1, &d2]);
+
+        let entries = Rc::new(RefCell::new(None));
+        let entries_c = entries.clone();
+        let _sub = m2.observe(move |txn, e| {
+            *entries_c.borrow_mut() = Some(e.keys(txn).clone());
+        });
+
+        {
+            let mut txn = d1.transact();
+            m1.insert(&mut txn, "a", 2);
+            m1.remove(&mut txn, "b");
+        }
+        exchange_updates(&[&d1, &d2]);
+        let keys = entries.take().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(
+            keys.get("a"),
+            Some(&EntryChange::Updated(
+                Any::Number(1.0).into(),
+                Any::Number(2.0).into()
+            ))
+        );
+        match keys.get("b") {
+            Some(EntryChange::Removed(Value::YMap(_))) => { /* expected */ }
+            other => panic!("expected removed map, got {:?}", other),
         }
-        [Box::new(set), Box::new(set_type), Box::new(delete)]
     }
 
     fn fuzzy(iterations: usize) {
-        run_scenario(0, &map_transactions(), 5, iterations)
+        run_scenario(0, &map_edits(), 5, iterations);
     }
 
     #[test]
@@ -858,4 +1106,138 @@ mod test {
             ]
         );
     }
+
+    fn conflict_doc(client_id: u64) -> Doc {
+        let mut options = Options::with_client_id(client_id);
+        options.keep_map_conflicts = true;
+        Doc::with_options(options)
+    }
+
+    #[test]
+    fn get_all_concurrent_values() {
+        let d1 = conflict_doc(1);
+        let d2 = conflict_doc(2);
+        let d3 = conflict_doc(3);
+        let m1 = d1.transact().get_map("map");
+        let m2 = d2.transact().get_map("map");
+        let m3 = d3.transact().get_map("map");
+
+        m1.insert(&mut d1.transact(), "key", "a");
+        m2.insert(&mut d2.transact(), "key", "b");
+        m3.insert(&mut d3.transact(), "key", "c");
+        exchange_updates(&[&d1, &d2, &d3]);
+
+        let expected: Vec<Value> = vec!["a".into(), "b".into(), "c".into()];
+        for m in [&m1, &m2, &m3] {
+            assert_eq!(m.get_all("key"), expected);
+            assert_eq!(m.get("key"), expected.last().cloned());
+        }
+
+        // conflicting values survive garbage collection and are passed to new peers
+        d1.transact().gc();
+        let d4 = conflict_doc(4);
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+        d4.transact().apply_update_v1(&update).unwrap();
+        let m4 = d4.transact().get_map("map");
+        assert_eq!(m4.get_all("key"), expected);
+
+        // causal override resolves a conflict
+        m2.insert(&mut d2.transact(), "key", "d");
+        exchange_updates(&[&d1, &d2, &d3, &d4]);
+        for m in [&m1, &m2, &m3, &m4] {
+            assert_eq!(m.get_all("key"), vec![Value::from("d")]);
+        }
+
+        // removed entry has no values
+        m3.remove(&mut d3.transact(), "key");
+        exchange_updates(&[&d1, &d2, &d3]);
+        for m in [&m1, &m2, &m3] {
+            assert!(m.get_all("key").is_empty());
+        }
+        assert!(m1.get_all("unknown").is_empty());
+    }
+
+    #[test]
+    fn get_all_without_keeping_conflicts() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let m1 = d1.transact().get_map("map");
+        let m2 = d2.transact().get_map("map");
+
+        m1.insert(&mut d1.transact(), "key", "a");
+        m2.insert(&mut d2.transact(), "key", "b");
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(m1.get_all("key"), vec![Value::from("b")]);
+        assert_eq!(m2.get_all("key"), vec![Value::from("b")]);
+    }
+
+    #[test]
+    fn binary_blobs() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.transact().get_map("map");
+        {
+            let mut txn = d1.transact();
+            m1.insert(&mut txn, "plain", PrelimBinary::new(&data[..100]));
+            m1.insert(
+                &mut txn,
+                "chunked",
+                PrelimBinary::chunked(data.clone(), 128),
+            );
+            m1.insert(&mut txn, "small", PrelimBinary::chunked(&data[..10], 128));
+            m1.insert(&mut txn, "text", "hello");
+        }
+
+        let chunks = m1.get("chunked").unwrap().to_yarray().unwrap();
+        assert_eq!(chunks.len(), 8);
+        assert!(m1.get("small").unwrap().to_yarray().is_none());
+
+        let d2 = Doc::with_client_id(2);
+        let m2 = d2.transact().get_map("map");
+        exchange_updates(&[&d1, &d2]);
+
+        for m in [&m1, &m2] {
+            assert_eq!(m.get_binary("plain"), Some(data[..100].to_vec()));
+            assert_eq!(m.get_binary("chunked"), Some(data.clone()));
+            assert_eq!(m.get_binary("small"), Some(data[..10].to_vec()));
+            assert_eq!(m.get_binary("text"), None);
+            assert_eq!(m.get_binary("missing"), None);
+        }
+    }
+
+    #[test]
+    fn map_iteration_order() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let m1 = d1.transact().get_map("map");
+        let m2 = d2.transact().get_map("map");
+        let keys: Vec<String> = (0..20).rev().map(|i| format!("key{:02}", i)).collect();
+        for key in keys.iter() {
+            m1.insert(&mut d1.transact(), key.as_str(), 1);
+        }
+        // updated entry is moved to the end
+        m1.insert(&mut d1.transact(), "key19", 2);
+        let mut expected: Vec<&str> = keys.iter().skip(1).map(String::as_str).collect();
+        expected.push("key19");
+        assert_eq!(m1.keys().collect::<Vec<_>>(), expected);
+        let values: Vec<_> = m1.iter().map(|(key, _)| key).collect();
+        assert_eq!(values, expected);
+
+        let mut sorted = expected.clone();
+        sorted.sort();
+        let actual: Vec<_> = m1.iter_sorted_by_key().map(|(key, _)| key).collect();
+        assert_eq!(actual, sorted);
+
+        // replicas containing the same changes iterate in the same order
+        m2.insert(&mut d2.transact(), "other", 3);
+        m1.remove(&mut d1.transact(), "key00");
+        exchange_updates(&[&d1, &d2]);
+        let e1: Vec<_> = m1.iter().collect();
+        let e2: Vec<_> = m2.iter().collect();
+        assert_eq!(e1, e2);
+        assert_eq!(e1.len(), 20);
+        assert_eq!(e1[0].0, "other");
+    }
 }
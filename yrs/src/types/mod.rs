@@ -1,7 +1,12 @@
 pub mod array;
+pub mod counter;
 pub mod map;
+pub mod table;
 pub mod text;
+pub mod tree;
+pub mod weak;
 pub mod xml;
+mod xml_parser;
 
 use crate::*;
 pub use map::Map;
@@ -9,12 +14,16 @@ pub use text::Text;
 
 use crate::block::{Block, BlockPtr, Item, ItemContent, ItemPosition, Prelim};
 use crate::event::EventHandler;
+use crate::moving::MoveInfo;
 use crate::store::StoreRef;
 use crate::types::array::{Array, ArrayEvent};
+use crate::types::counter::CounterEvent;
 use crate::types::map::MapEvent;
-use crate::types::text::TextEvent;
+use crate::types::text::{SearchMarkers, TextEvent};
+use crate::types::weak::{LinkSource, WeakLink};
 use crate::types::xml::{XmlElement, XmlEvent, XmlText, XmlTextEvent};
 use lib0::any::Any;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Formatter;
 use std::ops::{Deref, DerefMut};
@@ -44,10 +53,78 @@ pub const TYPE_REFS_XML_HOOK: TypeRefs = 5;
 /// Type ref identifier for a [XmlText] type.
 pub const TYPE_REFS_XML_TEXT: TypeRefs = 6;
 
+/// Type ref identifier for a [WeakLink] type.
+pub const TYPE_REFS_WEAK: TypeRefs = 7;
+
+/// Type ref identifier for a [Counter](crate::Counter) type. Counters can only be used as
+/// root-level types, so this identifier is never encoded.
+pub const TYPE_REFS_COUNTER: TypeRefs = 8;
+
 /// Placeholder type ref identifier for non-specialized AbstractType. Used only for root-level types
 /// which have been integrated from remote peers before they were defined locally.
 pub const TYPE_REFS_UNDEFINED: TypeRefs = 15;
 
+/// Kind of a shared type. It's a typed counterpart of [TypeRefs] identifiers, which can be used by
+/// generic tools to inspect documents of unknown structure (see: [Doc::roots]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeRef {
+    /// An [Array] type.
+    Array,
+    /// A [Map] type.
+    Map,
+    /// A [Text] type.
+    Text,
+    /// An [XmlElement] type.
+    XmlElement,
+    /// An [XmlFragment] type.
+    XmlFragment,
+    /// An XML hook. Used for compatibility.
+    XmlHook,
+    /// An [XmlText] type.
+    XmlText,
+    /// A [WeakLink] type.
+    WeakLink,
+    /// A [Counter](crate::Counter) type.
+    Counter,
+    /// A root-level type integrated from remote peers, which has not been defined locally yet,
+    /// so its kind is not known.
+    Undefined,
+}
+
+impl From<TypeRefs> for TypeRef {
+    fn from(type_ref: TypeRefs) -> Self {
+        match type_ref & 0b1111 {
+            TYPE_REFS_ARRAY => TypeRef::Array,
+            TYPE_REFS_MAP => TypeRef::Map,
+            TYPE_REFS_TEXT => TypeRef::Text,
+            TYPE_REFS_XML_ELEMENT => TypeRef::XmlElement,
+            TYPE_REFS_XML_FRAGMENT => TypeRef::XmlFragment,
+            TYPE_REFS_XML_HOOK => TypeRef::XmlHook,
+            TYPE_REFS_XML_TEXT => TypeRef::XmlText,
+            TYPE_REFS_WEAK => TypeRef::WeakLink,
+            TYPE_REFS_COUNTER => TypeRef::Counter,
+            _ => TypeRef::Undefined,
+        }
+    }
+}
+
+impl From<TypeRef> for TypeRefs {
+    fn from(type_ref: TypeRef) -> Self {
+        match type_ref {
+            TypeRef::Array => TYPE_REFS_ARRAY,
+            TypeRef::Map => TYPE_REFS_MAP,
+            TypeRef::Text => TYPE_REFS_TEXT,
+            TypeRef::XmlElement => TYPE_REFS_XML_ELEMENT,
+            TypeRef::XmlFragment => TYPE_REFS_XML_FRAGMENT,
+            TypeRef::XmlHook => TYPE_REFS_XML_HOOK,
+            TypeRef::XmlText => TYPE_REFS_XML_TEXT,
+            TypeRef::WeakLink => TYPE_REFS_WEAK,
+            TypeRef::Counter => TYPE_REFS_COUNTER,
+            TypeRef::Undefined => TYPE_REFS_UNDEFINED,
+        }
+    }
+}
+
 /// A wrapper around [Branch] cell, supplied with a bunch of convenience methods to operate on both
 /// map-like and array-like contents of a [Branch].
 #[repr(transparent)]
@@ -61,7 +138,7 @@ impl BranchPtr {
         subs: HashSet<Option<Rc<str>>>,
     ) -> Option<Event> {
         if let Some(observers) = self.observers.as_ref() {
-            Some(observers.publish(*self, txn, subs))
+            observers.publish(*self, txn, subs)
         } else {
             match self.type_ref() {
                 TYPE_REFS_TEXT => Some(Event::Text(TextEvent::new(*self))),
@@ -136,6 +213,7 @@ impl Into<Value> for BranchPtr {
             TYPE_REFS_XML_ELEMENT => Value::YXmlElement(XmlElement::from(self)),
             TYPE_REFS_XML_FRAGMENT => Value::YXmlElement(XmlElement::from(self)),
             TYPE_REFS_XML_TEXT => Value::YXmlText(XmlText::from(self)),
+            TYPE_REFS_WEAK => Value::YWeakLink(WeakLink::from(self)),
             //TYPE_REFS_XML_HOOK => Value::YXmlElement(XmlElement::from(self)),
             other => panic!("Cannot convert to value - unsupported type ref: {}", other),
         }
@@ -204,6 +282,9 @@ pub struct Branch {
     /// A tag name identifier, used only by [XmlElement].
     pub name: Option<Rc<str>>,
 
+    /// A source of elements referenced by a [WeakLink], used only by weak links.
+    pub(crate) link: Option<LinkSource>,
+
     /// A length of an indexed sequence component of a current branch node. Map component elements
     /// are computed on demand.
     pub block_len: u32,
@@ -216,6 +297,13 @@ pub struct Branch {
     pub(crate) observers: Option<Observers>,
 
     pub(crate) deep_observers: Option<EventHandler<Events>>,
+
+    /// String content of [Text] and [XmlText] cached after the last read. It's reset whenever any
+    /// of the branch's blocks is integrated or deleted.
+    pub(crate) string_cache: RefCell<Option<String>>,
+
+    /// Positions cached by recent [Text] lookups, used to speed up the following ones.
+    pub(crate) search_markers: RefCell<SearchMarkers>,
 }
 
 impl std::fmt::Debug for Branch {
@@ -226,18 +314,42 @@ impl std::fmt::Debug for Branch {
 
 impl Eq for Branch {}
 
+impl AsRef<Branch> for Branch {
+    fn as_ref(&self) -> &Branch {
+        self
+    }
+}
+
 impl PartialEq for Branch {
     fn eq(&self, other: &Self) -> bool {
         self.item == other.item
             && self.start == other.start
             && self.map == other.map
             && self.name == other.name
+            && self.link == other.link
             && self.block_len == other.block_len
             && self.type_ref == other.type_ref
     }
 }
 
 impl Branch {
+    /// Returns an approximate number of bytes allocated on the heap by indexes of current branch:
+    /// map entries and cached string content.
+    pub(crate) fn index_heap_size(&self) -> usize {
+        let map: usize = self
+            .map
+            .keys()
+            .map(|key| std::mem::size_of::<(Rc<str>, BlockPtr)>() + key.len())
+            .sum();
+        let cache = self
+            .string_cache
+            .borrow()
+            .as_ref()
+            .map(String::capacity)
+            .unwrap_or_default();
+        map + cache
+    }
+
     pub fn new(type_ref: TypeRefs, name: Option<Rc<str>>) -> Box<Self> {
         Box::new(Self {
             start: None,
@@ -247,9 +359,12 @@ impl Branch {
             item: None,
             store: None,
             name,
+            link: None,
             type_ref,
             observers: None,
             deep_observers: None,
+            string_cache: RefCell::new(None),
+            search_markers: RefCell::default(),
         })
     }
 
@@ -263,6 +378,18 @@ impl Branch {
         self.type_ref & 0b1111
     }
 
+    /// Returns a kind of an underlying complex data type. Works just like [Branch::type_ref],
+    /// but returns a typed [TypeRef] instead of a raw identifier.
+    pub fn kind(&self) -> TypeRef {
+        TypeRef::from(self.type_ref)
+    }
+
+    /// Returns an ID of a block, which current branch has been inserted with as a nested type.
+    /// Root-level types have no ID.
+    pub fn id(&self) -> Option<ID> {
+        self.item.map(|ptr| *ptr.id())
+    }
+
     pub(crate) fn repair_type_ref(&mut self, type_ref: TypeRefs) {
         if self.type_ref() == TYPE_REFS_UNDEFINED {
             // cleanup the TYPE_REFS_UNDEFINED bytes and set a new type ref
@@ -505,10 +632,10 @@ impl Branch {
                     if ptr.id() == &item_id {
                         break;
                     }
-                    if !ptr.is_deleted() {
-                        i += 1;
-                    }
                     if let Block::Item(cci) = ptr.deref() {
+                        if !cci.is_deleted() && cci.is_countable() {
+                            i += cci.len();
+                        }
                         c = cci.right;
                     } else {
                         break;
@@ -582,6 +709,14 @@ pub enum Value {
     YMap(Map),
     YXmlElement(XmlElement),
     YXmlText(XmlText),
+    /// Weak reference to the elements of another collection.
+    YWeakLink(WeakLink),
+    /// Read-only snapshot of a move operation performed over an [Array].
+    YMove(Box<MoveInfo>),
+    /// User-defined content (see: [ContentCodec](crate::ContentCodec)).
+    Extension(Box<Extension>),
+    /// Subdocument.
+    YDoc(DocRef),
 }
 
 impl Default for Value {
@@ -599,6 +734,10 @@ impl Value {
     /// - [Value::YMap] is converted into JSON-like object map.
     /// - [Value::YText], [Value::YXmlText] and [Value::YXmlElement] are converted into strings
     ///   (XML types are stringified XML representation).
+    /// - [Value::YWeakLink] is converted into a JSON representation of the linked content.
+    /// - [Value::YMove] is converted into a JSON-like map of its metadata (see: [MoveInfo::to_json]).
+    /// - [Value::Extension] is converted into a JSON-like map of its tag and payload.
+    /// - [Value::YDoc] is converted into its unique identifier.
     pub fn to_json(self) -> Any {
         match self {
             Value::Any(a) => a,
@@ -607,6 +746,10 @@ impl Value {
             Value::YMap(v) => v.to_json(),
             Value::YXmlElement(v) => Any::String(v.to_string().into_boxed_str()),
             Value::YXmlText(v) => Any::String(v.to_string().into_boxed_str()),
+            Value::YWeakLink(v) => v.to_json(),
+            Value::YMove(v) => v.to_json(),
+            Value::Extension(v) => v.to_json(),
+            Value::YDoc(v) => Any::String(v.guid().as_ref().into()),
         }
     }
 
@@ -619,6 +762,10 @@ impl Value {
             Value::YMap(v) => v.to_json().to_string(),
             Value::YXmlElement(v) => v.to_string(),
             Value::YXmlText(v) => v.to_string(),
+            Value::YWeakLink(v) => v.to_json().to_string(),
+            Value::YMove(v) => v.to_json().to_string(),
+            Value::Extension(v) => v.to_string(),
+            Value::YDoc(v) => v.guid().to_string(),
         }
     }
 
@@ -661,6 +808,57 @@ impl Value {
             None
         }
     }
+
+    pub fn to_yweak(self) -> Option<WeakLink> {
+        if let Value::YWeakLink(link) = self {
+            Some(link)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_ymove(self) -> Option<Box<MoveInfo>> {
+        if let Value::YMove(m) = self {
+            Some(m)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_extension(self) -> Option<Box<Extension>> {
+        if let Value::Extension(e) = self {
+            Some(e)
+        } else {
+            None
+        }
+    }
+
+    /// Returns binary contents of a current value. It can be either a binary blob or an [Array]
+    /// of binary chunks (see: [PrelimBinary::chunked]). Returns `None` for any other value.
+    pub fn to_binary(self) -> Option<Vec<u8>> {
+        match self {
+            Value::Any(Any::Buffer(buf)) => Some(buf.into()),
+            Value::YArray(array) => {
+                let mut result = Vec::new();
+                for chunk in array.iter() {
+                    match chunk {
+                        Value::Any(Any::Buffer(buf)) => result.extend_from_slice(&buf),
+                        _ => return None,
+                    }
+                }
+                Some(result)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_ydoc(self) -> Option<DocRef> {
+        if let Value::YDoc(doc) = self {
+            Some(doc)
+        } else {
+            None
+        }
+    }
 }
 
 impl<T> From<T> for Value
@@ -673,6 +871,331 @@ where
     }
 }
 
+/// Trait implemented by types, which can be extracted out of a [Value] returned by shared types,
+/// eg. using [Map::get_as].
+pub trait FromValue: Sized {
+    /// Tries to convert a given `value` into an instance of current type. If conversion is not
+    /// possible, an original `value` is returned back as an error.
+    fn from_value(value: Value) -> Result<Self, Value>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        Ok(value)
+    }
+}
+
+impl FromValue for Any {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Any(any) => Ok(any),
+            other => Err(other),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Any(Any::Bool(v)) => Ok(v),
+            other => Err(other),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Any(Any::String(v)) => Ok(v.into()),
+            other => Err(other),
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Any(Any::Number(v)) => Ok(v),
+            Value::Any(Any::BigInt(v)) => Ok(v as f64),
+            other => Err(other),
+        }
+    }
+}
+
+macro_rules! impl_from_value_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl FromValue for $t {
+                fn from_value(value: Value) -> Result<Self, Value> {
+                    use std::convert::TryFrom;
+                    let converted = match &value {
+                        Value::Any(Any::BigInt(v)) => <$t>::try_from(*v).ok(),
+                        Value::Any(Any::Number(v)) if v.fract() == 0.0 => {
+                            // check bounds with floats, since i64 can't represent every u64
+                            if *v >= <$t>::MIN as f64 && *v <= <$t>::MAX as f64 {
+                                Some(*v as $t)
+                            } else {
+                                None
+                            }
+                        }
+                        _ => None,
+                    };
+                    converted.ok_or(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value_for_int!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Any(Any::Null) | Value::Any(Any::Undefined) => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Any(Any::Array(values)) => {
+                let mut result = Vec::with_capacity(values.len());
+                for value in values.iter() {
+                    match T::from_value(Value::Any(value.clone())) {
+                        Ok(v) => result.push(v),
+                        Err(_) => return Err(Value::Any(Any::Array(values))),
+                    }
+                }
+                Ok(result)
+            }
+            Value::YArray(array) => {
+                let mut result = Vec::with_capacity(array.len() as usize);
+                for value in array.iter() {
+                    match T::from_value(value) {
+                        Ok(v) => result.push(v),
+                        Err(_) => return Err(Value::YArray(array)),
+                    }
+                }
+                Ok(result)
+            }
+            other => Err(other),
+        }
+    }
+}
+
+macro_rules! impl_from_value_for_shared {
+    ($($t:ty => $variant:ident),*) => {
+        $(
+            impl FromValue for $t {
+                fn from_value(value: Value) -> Result<Self, Value> {
+                    match value {
+                        Value::$variant(v) => Ok(v),
+                        other => Err(other),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value_for_shared!(
+    Text => YText,
+    Array => YArray,
+    Map => YMap,
+    XmlElement => YXmlElement,
+    XmlText => YXmlText,
+    DocRef => YDoc
+);
+
+/// A preliminary value, which can be used to insert a heterogeneous tree of nested shared types and
+/// primitive values in one go, eg. a map containing both text and array entries. Unlike
+/// [PrelimMap](crate::PrelimMap) or [PrelimArray](crate::PrelimArray), elements of a single
+/// collection don't need to be of the same type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrelimValue {
+    /// Primitive value.
+    Any(Any),
+    /// Value integrated as [Text].
+    Text(String),
+    /// Value integrated as [Array].
+    Array(Vec<PrelimValue>),
+    /// Value integrated as [Map].
+    Map(HashMap<String, PrelimValue>),
+}
+
+impl PrelimValue {
+    /// Converts a JSON-like [Any] value into a preliminary value, which will be integrated as
+    /// a tree of nested shared types: [Any::Map] becomes a [Map] and [Any::Array] becomes an
+    /// [Array], while all other values are inserted as primitives.
+    pub fn from_json(value: Any) -> Self {
+        match value {
+            Any::Array(values) => PrelimValue::Array(
+                Vec::from(values)
+                    .into_iter()
+                    .map(PrelimValue::from_json)
+                    .collect(),
+            ),
+            Any::Map(entries) => PrelimValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, PrelimValue::from_json(value)))
+                    .collect(),
+            ),
+            other => PrelimValue::Any(other),
+        }
+    }
+}
+
+impl<T> From<T> for PrelimValue
+where
+    T: Into<Any>,
+{
+    fn from(v: T) -> Self {
+        PrelimValue::Any(v.into())
+    }
+}
+
+impl Prelim for PrelimValue {
+    fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
+        let type_ref = match &self {
+            PrelimValue::Any(any) => return (ItemContent::Any(vec![any.clone()]), None),
+            PrelimValue::Text(_) => TYPE_REFS_TEXT,
+            PrelimValue::Array(_) => TYPE_REFS_ARRAY,
+            PrelimValue::Map(_) => TYPE_REFS_MAP,
+        };
+        (ItemContent::Type(Branch::new(type_ref, None)), Some(self))
+    }
+
+    fn integrate(self, txn: &mut Transaction, inner_ref: BranchPtr) {
+        match self {
+            PrelimValue::Any(_) => {}
+            PrelimValue::Text(value) => Text::from(inner_ref).push(txn, &value),
+            PrelimValue::Array(values) => {
+                let array = Array::from(inner_ref);
+                for value in values {
+                    array.push_back(txn, value);
+                }
+            }
+            PrelimValue::Map(entries) => {
+                let map = Map::from(inner_ref);
+                for (key, value) in entries {
+                    map.insert(txn, key, value);
+                }
+            }
+        }
+    }
+}
+
+/// A preliminary binary blob, which can be inserted into an [Array] or a [Map]. Unlike
+/// [Any::Buffer] values, it's stored as a dedicated binary block - the same way as `Uint8Array`
+/// values are stored by Yjs.
+///
+/// Large blobs can be split into chunks of a limited size (see: [PrelimBinary::chunked]). Such
+/// blob is stored as a nested [Array] of binary blocks, so that a single attachment doesn't
+/// produce one giant block. Both representations can be read back with [Value::to_binary],
+/// [Map::get_binary] or [Array::get_binary].
+///
+/// Example:
+/// ```
+/// use yrs::{Doc, PrelimBinary};
+/// let doc = Doc::new();
+/// let mut txn = doc.transact();
+/// let map = txn.get_map("attachments");
+/// let data: Vec<u8> = (0..=255).collect();
+/// map.insert(&mut txn, "small", PrelimBinary::new(&data[..16]));
+/// map.insert(&mut txn, "large", PrelimBinary::chunked(data.clone(), 64));
+///
+/// assert_eq!(map.get_binary("small"), Some(data[..16].to_vec()));
+/// assert_eq!(map.get_binary("large"), Some(data));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrelimBinary {
+    data: Vec<u8>,
+    chunk_size: Option<usize>,
+}
+
+impl PrelimBinary {
+    /// Creates a new binary blob, which will be stored as a single block.
+    pub fn new<B: Into<Vec<u8>>>(data: B) -> Self {
+        PrelimBinary {
+            data: data.into(),
+            chunk_size: None,
+        }
+    }
+
+    /// Creates a new binary blob, which will be split into blocks of at most `chunk_size` bytes
+    /// each, if it's larger than that.
+    pub fn chunked<B: Into<Vec<u8>>>(data: B, chunk_size: usize) -> Self {
+        PrelimBinary {
+            data: data.into(),
+            chunk_size: Some(chunk_size.max(1)),
+        }
+    }
+}
+
+impl Prelim for PrelimBinary {
+    fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
+        match self.chunk_size {
+            Some(chunk_size) if self.data.len() > chunk_size => {
+                let inner = Branch::new(TYPE_REFS_ARRAY, None);
+                (ItemContent::Type(inner), Some(self))
+            }
+            _ => (ItemContent::Binary(self.data), None),
+        }
+    }
+
+    fn integrate(self, txn: &mut Transaction, inner_ref: BranchPtr) {
+        if let Some(chunk_size) = self.chunk_size {
+            let array = Array::from(inner_ref);
+            let mut cursor = array.cursor(txn);
+            for chunk in self.data.chunks(chunk_size) {
+                cursor.insert(PrelimBinary::new(chunk));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    /// Serializes a JSON-like representation of a current value (see: [Value::to_json]).
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.clone().to_json().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    /// Deserializes a primitive [Value::Any]. Shared types can't be deserialized, as they can
+    /// only exist as a part of a document. Integers are deserialized as [Any::Number] whenever
+    /// they can be represented exactly, just like when parsed with [Any::from_json].
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Any::deserialize(deserializer).map(|any| Value::Any(json_numbers(any)))
+    }
+}
+
+/// Converts integers, which lib0 serde support deserializes as [Any::BigInt], into [Any::Number]s
+/// if they fit into a safe integer range of JavaScript numbers.
+#[cfg(feature = "serde")]
+fn json_numbers(any: Any) -> Any {
+    const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+    match any {
+        Any::BigInt(n) if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&n) => {
+            Any::Number(n as f64)
+        }
+        Any::Array(values) => Any::Array(values.into_vec().into_iter().map(json_numbers).collect()),
+        Any::Map(entries) => Any::Map(Box::new(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key, json_numbers(value)))
+                .collect(),
+        )),
+        other => other,
+    }
+}
+
 impl std::fmt::Display for Branch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.type_ref() {
@@ -759,38 +1282,48 @@ impl std::fmt::Display for Branch {
     }
 }
 
+/// Iterator over visible entries of a map-like [Branch]. Entries are ordered by IDs of their current
+/// values - by clock first and client second - which for the changes made by a single client is
+/// the order in which these values were inserted. This order is the same on every replica which
+/// has integrated the same changes.
 pub(crate) struct Entries<'a> {
-    iter: std::collections::hash_map::Iter<'a, Rc<str>, BlockPtr>,
+    iter: std::vec::IntoIter<(&'a str, &'a Item)>,
 }
 
 impl<'a> Entries<'a> {
     pub(crate) fn new(source: &'a HashMap<Rc<str>, BlockPtr>) -> Self {
+        let mut entries = Self::visible(source);
+        entries.sort_unstable_by_key(|(_, item)| (item.id.clock, item.id.client));
         Entries {
-            iter: source.iter(),
+            iter: entries.into_iter(),
         }
     }
+
+    /// Returns visible entries of a `source` map ordered by their keys.
+    pub(crate) fn sorted_by_key(source: &'a HashMap<Rc<str>, BlockPtr>) -> Self {
+        let mut entries = Self::visible(source);
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        Entries {
+            iter: entries.into_iter(),
+        }
+    }
+
+    fn visible(source: &'a HashMap<Rc<str>, BlockPtr>) -> Vec<(&'a str, &'a Item)> {
+        source
+            .iter()
+            .filter_map(|(key, ptr)| match ptr.deref() {
+                Block::Item(item) if !item.is_deleted() => Some((key.as_ref(), item)),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl<'a> Iterator for Entries<'a> {
     type Item = (&'a str, &'a Item);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (mut key, ptr) = self.iter.next()?;
-        let mut block = ptr;
-        loop {
-            match block.deref() {
-                Block::Item(item) if !item.is_deleted() => {
-                    break;
-                }
-                _ => {
-                    let (k, ptr) = self.iter.next()?;
-                    key = k;
-                    block = ptr;
-                }
-            }
-        }
-        let item = block.as_item().unwrap();
-        Some((key, item))
+        self.iter.next()
     }
 }
 
@@ -866,6 +1399,7 @@ pub(crate) enum Observers {
     Map(EventHandler<crate::types::map::MapEvent>),
     Xml(EventHandler<crate::types::xml::XmlEvent>),
     XmlText(EventHandler<crate::types::xml::XmlTextEvent>),
+    Counter(EventHandler<CounterEvent>),
 }
 
 impl Observers {
@@ -884,38 +1418,47 @@ impl Observers {
     pub fn xml_text() -> Self {
         Observers::XmlText(EventHandler::default())
     }
+    pub fn counter() -> Self {
+        Observers::Counter(EventHandler::default())
+    }
 
     pub fn publish(
         &self,
         branch_ref: BranchPtr,
         txn: &Transaction,
         keys: HashSet<Option<Rc<str>>>,
-    ) -> Event {
+    ) -> Option<Event> {
         match self {
             Observers::Text(eh) => {
                 let e = TextEvent::new(branch_ref);
                 eh.publish(txn, &e);
-                Event::Text(e)
+                Some(Event::Text(e))
             }
             Observers::Array(eh) => {
                 let e = ArrayEvent::new(branch_ref);
                 eh.publish(txn, &e);
-                Event::Array(e)
+                Some(Event::Array(e))
             }
             Observers::Map(eh) => {
                 let e = MapEvent::new(branch_ref, keys);
                 eh.publish(txn, &e);
-                Event::Map(e)
+                Some(Event::Map(e))
             }
             Observers::Xml(eh) => {
                 let e = XmlEvent::new(branch_ref, keys);
                 eh.publish(txn, &e);
-                Event::XmlElement(e)
+                Some(Event::XmlElement(e))
             }
             Observers::XmlText(eh) => {
                 let e = XmlTextEvent::new(branch_ref, keys);
                 eh.publish(txn, &e);
-                Event::XmlText(e)
+                Some(Event::XmlText(e))
+            }
+            Observers::Counter(eh) => {
+                // counters are root-level types, so there are no deep observers to notify
+                let e = CounterEvent::new(branch_ref, keys);
+                eh.publish(txn, &e);
+                None
             }
         }
     }
@@ -0,0 +1,462 @@
+use crate::block::Prelim;
+use crate::block_iter::OutOfBounds;
+use crate::event::Subscription;
+use crate::store::StoreRef;
+use crate::types::array::ArrayEvent;
+use crate::types::{Branch, BranchPtr, Change, TypePtr, Value};
+use crate::*;
+use lib0::any::Any;
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// A collaborative table (grid) of cells, which rows and columns can be inserted, removed and
+/// reordered concurrently by many peers.
+///
+/// Table is a root-level type and can be obtained with [Transaction::get_table]. It's built on
+/// top of other shared types, each of them being a separate root-level type named after a table:
+///
+/// - `<name>/rows` is an [Array] of unique row identifiers, in the order of rows.
+/// - `<name>/columns` is an [Array] of unique column identifiers, in the order of columns.
+/// - `<name>/cells` is a [Map] of cell values, which keys are made of row and column identifiers.
+///
+/// Since cells are not bound to row or column indexes, rows and columns can be reordered
+/// (see: [Table::move_row]) without affecting the cells edited concurrently. Cells of rows and
+/// columns that have been removed are no longer visible.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::Doc;
+/// use yrs::types::Value;
+///
+/// let doc = Doc::new();
+/// let mut txn = doc.transact();
+/// let table = txn.get_table("table");
+/// table.insert_row(&mut txn, 0).unwrap();
+/// table.insert_row(&mut txn, 1).unwrap();
+/// table.insert_column(&mut txn, 0).unwrap();
+/// table.set(&mut txn, 0, 0, "a").unwrap();
+/// table.set(&mut txn, 1, 0, "b").unwrap();
+///
+/// table.move_row(&mut txn, 1, 0).unwrap();
+/// assert_eq!(table.get(0, 0), Some(Value::from("b")));
+/// assert_eq!(table.get(1, 0), Some(Value::from("a")));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Table {
+    rows: Array,
+    columns: Array,
+    cells: Map,
+}
+
+impl Table {
+    pub(crate) fn new(rows: Array, columns: Array, cells: Map) -> Self {
+        Table {
+            rows,
+            columns,
+            cells,
+        }
+    }
+
+    /// Returns a number of rows in current table.
+    pub fn row_count(&self) -> u32 {
+        self.rows.len()
+    }
+
+    /// Returns a number of columns in current table.
+    pub fn column_count(&self) -> u32 {
+        self.columns.len()
+    }
+
+    /// Returns identifiers of all rows, in their current order.
+    pub fn row_ids(&self) -> Vec<Rc<str>> {
+        self.rows.iter().filter_map(as_id).collect()
+    }
+
+    /// Returns identifiers of all columns, in their current order.
+    pub fn column_ids(&self) -> Vec<Rc<str>> {
+        self.columns.iter().filter_map(as_id).collect()
+    }
+
+    /// Inserts a new empty row at a given `index` and returns its unique identifier.
+    pub fn insert_row(&self, txn: &mut Transaction, index: u32) -> Result<Rc<str>, OutOfBounds> {
+        insert_id(&self.rows, txn, index)
+    }
+
+    /// Inserts a new empty column at a given `index` and returns its unique identifier.
+    pub fn insert_column(&self, txn: &mut Transaction, index: u32) -> Result<Rc<str>, OutOfBounds> {
+        insert_id(&self.columns, txn, index)
+    }
+
+    /// Removes a row found at a given `index` together with all of its cells.
+    pub fn remove_row(&self, txn: &mut Transaction, index: u32) -> Result<(), OutOfBounds> {
+        let row = self.rows.get(index).and_then(as_id).ok_or(OutOfBounds)?;
+        self.rows.try_remove_range(txn, index, 1)?;
+        self.remove_cells(txn, |r, _| r == row.as_ref());
+        Ok(())
+    }
+
+    /// Removes a column found at a given `index` together with all of its cells.
+    pub fn remove_column(&self, txn: &mut Transaction, index: u32) -> Result<(), OutOfBounds> {
+        let column = self.columns.get(index).and_then(as_id).ok_or(OutOfBounds)?;
+        self.columns.try_remove_range(txn, index, 1)?;
+        self.remove_cells(txn, |_, c| c == column.as_ref());
+        Ok(())
+    }
+
+    /// Moves a row found at `source` index into `target` index position, using the same rules
+    /// as [Array::move_to]. Cells are moved together with their row.
+    pub fn move_row(
+        &self,
+        txn: &mut Transaction,
+        source: u32,
+        target: u32,
+    ) -> Result<(), OutOfBounds> {
+        self.rows.try_move_to(txn, source, target)
+    }
+
+    /// Moves a column found at `source` index into `target` index position, using the same rules
+    /// as [Array::move_to]. Cells are moved together with their column.
+    pub fn move_column(
+        &self,
+        txn: &mut Transaction,
+        source: u32,
+        target: u32,
+    ) -> Result<(), OutOfBounds> {
+        self.columns.try_move_to(txn, source, target)
+    }
+
+    /// Returns a value of a cell found at given `row` and `column` indexes, or `None` if a cell
+    /// was empty or indexes were outside of the table bounds.
+    pub fn get(&self, row: u32, column: u32) -> Option<Value> {
+        let key = self.cell_key(row, column)?;
+        self.cells.get(&key)
+    }
+
+    /// Sets a `value` of a cell found at given `row` and `column` indexes. Returns a value
+    /// stored previously in that cell (if any existed).
+    pub fn set<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        row: u32,
+        column: u32,
+        value: V,
+    ) -> Result<Option<Value>, OutOfBounds> {
+        let key = self.cell_key(row, column).ok_or(OutOfBounds)?;
+        Ok(self.cells.insert(txn, key, value))
+    }
+
+    /// Clears a cell found at given `row` and `column` indexes. Returns a value stored
+    /// previously in that cell (if any existed).
+    pub fn clear(
+        &self,
+        txn: &mut Transaction,
+        row: u32,
+        column: u32,
+    ) -> Result<Option<Value>, OutOfBounds> {
+        let key = self.cell_key(row, column).ok_or(OutOfBounds)?;
+        Ok(self.cells.remove(txn, &key))
+    }
+
+    /// Converts current table into JSON-like array of rows, each of them being an array of
+    /// cell values. Empty cells are represented as nulls.
+    pub fn to_json(&self) -> Any {
+        let columns = self.column_ids();
+        let rows = self
+            .row_ids()
+            .into_iter()
+            .map(|row| {
+                let cells = columns
+                    .iter()
+                    .map(|column| {
+                        self.cells
+                            .get(&cell_key(&row, column))
+                            .map(Value::to_json)
+                            .unwrap_or(Any::Null)
+                    })
+                    .collect();
+                Any::Array(cells)
+            })
+            .collect();
+        Any::Array(rows)
+    }
+
+    /// Subscribes a given callback to be triggered whenever current table has been changed.
+    /// A callback is triggered once per committed transaction, which inserted, removed or moved
+    /// any of the rows or columns or changed any of the cells.
+    ///
+    /// Returns an [Observer] which, when dropped, will unsubscribe current callback.
+    pub fn observe<F>(&self, f: F) -> Subscription<TableEvent>
+    where
+        F: Fn(&Transaction, &TableEvent) + 'static,
+    {
+        let table = self.clone();
+        let mut store = self.store();
+        let subscription = store
+            .before_cleanup_events
+            .get_or_insert_with(Default::default)
+            .subscribe(move |txn, _| {
+                if let Some(e) = TableEvent::new(&table, txn) {
+                    f(txn, &e);
+                }
+            });
+        subscription.cast()
+    }
+
+    /// Unsubscribes a previously subscribed event callback identified by given `subscription_id`.
+    pub fn unobserve(&self, subscription_id: SubscriptionId) {
+        if let Some(eh) = self.store().before_cleanup_events.as_mut() {
+            eh.unsubscribe(subscription_id);
+        }
+    }
+
+    fn store(&self) -> StoreRef {
+        let branch: &Branch = self.rows.as_ref();
+        branch.store.clone().expect("Table is not integrated")
+    }
+
+    fn cell_key(&self, row: u32, column: u32) -> Option<String> {
+        let row = self.rows.get(row).and_then(as_id)?;
+        let column = self.columns.get(column).and_then(as_id)?;
+        Some(cell_key(&row, &column))
+    }
+
+    fn remove_cells<F>(&self, txn: &mut Transaction, predicate: F)
+    where
+        F: Fn(&str, &str) -> bool,
+    {
+        let keys: Vec<String> = self
+            .cells
+            .keys()
+            .filter(|key| match key.split_once(CELL_KEY_SEPARATOR) {
+                Some((row, column)) => predicate(row, column),
+                None => false,
+            })
+            .map(String::from)
+            .collect();
+        for key in keys {
+            self.cells.remove(txn, &key);
+        }
+    }
+}
+
+const CELL_KEY_SEPARATOR: char = '/';
+
+fn cell_key(row: &str, column: &str) -> String {
+    format!("{}{}{}", row, CELL_KEY_SEPARATOR, column)
+}
+
+pub(crate) fn as_id(value: Value) -> Option<Rc<str>> {
+    match value {
+        Value::Any(Any::String(id)) => Some(id.into()),
+        _ => None,
+    }
+}
+
+/// Inserts a new unique identifier into a given `array` of rows or columns. Identifiers are
+/// generated from a client ID and a clock of a block which is about to be inserted.
+pub(crate) fn insert_id(
+    array: &Array,
+    txn: &mut Transaction,
+    index: u32,
+) -> Result<Rc<str>, OutOfBounds> {
+    let store = txn.store();
+    let id: Rc<str> = format!("{}:{}", store.options.client_id, store.get_local_state()).into();
+    array.try_insert(txn, index, id.to_string())?;
+    Ok(id)
+}
+
+pub(crate) fn same_branch<T: AsRef<Branch>>(ptr: &TypePtr, collection: &T) -> bool {
+    match ptr {
+        TypePtr::Branch(branch) => std::ptr::eq(branch.deref(), collection.as_ref()),
+        _ => false,
+    }
+}
+
+/// Event generated by [Table::observe] method. Emitted during transaction commit phase.
+pub struct TableEvent {
+    target: Table,
+    rows: Option<ArrayEvent>,
+    columns: Option<ArrayEvent>,
+    cells: HashSet<(Rc<str>, Rc<str>)>,
+}
+
+impl TableEvent {
+    fn new(table: &Table, txn: &Transaction) -> Option<Self> {
+        let mut rows = None;
+        let mut columns = None;
+        let mut cells = HashSet::new();
+        for (ptr, keys) in txn.changed.iter() {
+            if same_branch(ptr, &table.rows) {
+                rows = Some(ArrayEvent::new(BranchPtr::from(table.rows.as_ref())));
+            } else if same_branch(ptr, &table.columns) {
+                columns = Some(ArrayEvent::new(BranchPtr::from(table.columns.as_ref())));
+            } else if same_branch(ptr, &table.cells) {
+                for key in keys.iter().flatten() {
+                    if let Some((row, column)) = key.split_once(CELL_KEY_SEPARATOR) {
+                        cells.insert((row.into(), column.into()));
+                    }
+                }
+            }
+        }
+        if rows.is_none() && columns.is_none() && cells.is_empty() {
+            None
+        } else {
+            Some(TableEvent {
+                target: table.clone(),
+                rows,
+                columns,
+                cells,
+            })
+        }
+    }
+
+    /// Returns a [Table] instance which emitted this event.
+    pub fn target(&self) -> &Table {
+        &self.target
+    }
+
+    /// Returns changes made to the rows of a table within bounds of current transaction, as
+    /// a delta of row identifiers. Returns an empty slice if rows have not been changed.
+    pub fn rows(&self, txn: &Transaction) -> &[Change] {
+        match self.rows.as_ref() {
+            Some(e) => e.delta(txn),
+            None => &[],
+        }
+    }
+
+    /// Returns changes made to the columns of a table within bounds of current transaction, as
+    /// a delta of column identifiers. Returns an empty slice if columns have not been changed.
+    pub fn columns(&self, txn: &Transaction) -> &[Change] {
+        match self.columns.as_ref() {
+            Some(e) => e.delta(txn),
+            None => &[],
+        }
+    }
+
+    /// Returns `(row, column)` identifier pairs of the cells, which have been set or cleared
+    /// within bounds of current transaction. This includes cells of removed rows and columns.
+    pub fn cells(&self) -> &HashSet<(Rc<str>, Rc<str>)> {
+        &self.cells
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::exchange_updates;
+    use crate::types::{Change, Value};
+    use crate::Doc;
+    use lib0::any::Any;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    #[test]
+    fn table_concurrent_edits() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.transact().get_table("table");
+        let t2 = d2.transact().get_table("table");
+        {
+            let mut txn = d1.transact();
+            t1.insert_row(&mut txn, 0).unwrap();
+            t1.insert_row(&mut txn, 1).unwrap();
+            t1.insert_column(&mut txn, 0).unwrap();
+            t1.insert_column(&mut txn, 1).unwrap();
+            t1.set(&mut txn, 0, 0, "a").unwrap();
+            t1.set(&mut txn, 0, 1, "b").unwrap();
+            t1.set(&mut txn, 1, 0, "c").unwrap();
+            t1.set(&mut txn, 1, 1, "d").unwrap();
+        }
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(
+            t2.to_json(),
+            Any::from_json(r#"[["a","b"],["c","d"]]"#).unwrap()
+        );
+
+        // concurrently: d1 moves a row and inserts a column, d2 edits a cell and inserts a row
+        {
+            let mut txn = d1.transact();
+            t1.move_row(&mut txn, 1, 0).unwrap();
+            t1.insert_column(&mut txn, 2).unwrap();
+            t1.set(&mut txn, 0, 2, "x").unwrap();
+        }
+        {
+            let mut txn = d2.transact();
+            t2.set(&mut txn, 1, 1, "D").unwrap();
+            t2.insert_row(&mut txn, 2).unwrap();
+            t2.set(&mut txn, 2, 0, "e").unwrap();
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let expected = Any::from_json(r#"[["c","D","x"],["a","b",null],["e",null,null]]"#).unwrap();
+        assert_eq!(t1.to_json(), expected);
+        assert_eq!(t2.to_json(), expected);
+        assert_eq!(t1.row_ids(), t2.row_ids());
+        assert_eq!(t1.column_ids(), t2.column_ids());
+
+        // removing a column removes its cells
+        t2.remove_column(&mut d2.transact(), 0).unwrap();
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(t1.column_count(), 2);
+        assert_eq!(t1.get(0, 0), Some(Value::from("D")));
+        assert_eq!(t1.get(2, 0), None);
+        assert_eq!(t1.get(3, 0), None);
+        assert!(t1.set(&mut d1.transact(), 0, 5, "y").is_err());
+        assert!(t1.remove_row(&mut d1.transact(), 3).is_err());
+    }
+
+    #[test]
+    fn table_observe() {
+        let doc = Doc::with_client_id(1);
+        let table = doc.transact().get_table("table");
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let _sub = {
+            let events = events.clone();
+            table.observe(move |txn, e| {
+                let cells: HashSet<_> = e
+                    .cells()
+                    .iter()
+                    .map(|(r, c)| (r.to_string(), c.to_string()))
+                    .collect();
+                events.borrow_mut().push((
+                    e.rows(txn).to_vec(),
+                    e.columns(txn).to_vec(),
+                    cells,
+                    e.target().row_count(),
+                ));
+            })
+        };
+
+        let (row, column) = {
+            let mut txn = doc.transact();
+            let row = table.insert_row(&mut txn, 0).unwrap();
+            let column = table.insert_column(&mut txn, 0).unwrap();
+            table.set(&mut txn, 0, 0, 1).unwrap();
+            (row, column)
+        };
+        assert_eq!(row.as_ref(), "1:0");
+        assert_eq!(column.as_ref(), "1:1");
+        table.set(&mut doc.transact(), 0, 0, 2).unwrap();
+        // unrelated transactions don't emit events
+        {
+            let mut txn = doc.transact();
+            txn.get_map("other").insert(&mut txn, "a", 1);
+        }
+
+        let cell = HashSet::from([(row.to_string(), column.to_string())]);
+        assert_eq!(
+            events.take(),
+            vec![
+                (
+                    vec![Change::Added(vec![Value::from("1:0")])],
+                    vec![Change::Added(vec![Value::from("1:1")])],
+                    cell.clone(),
+                    1
+                ),
+                (vec![], vec![], cell, 1),
+            ]
+        );
+    }
+}
@@ -1,13 +1,16 @@
-use crate::block::{Block, BlockPtr, Item, ItemContent, ItemPosition, Prelim};
+use crate::block::{Block, BlockPtr, ClientID, Item, ItemContent, ItemPosition, Prelim};
+use crate::block_iter::{BlockIter, OutOfBounds};
 use crate::block_store::Snapshot;
 use crate::event::Subscription;
 use crate::transaction::Transaction;
 use crate::types::{Attrs, Branch, BranchPtr, Delta, Observers, Path, Value, TYPE_REFS_TEXT};
+use crate::utils::diff::{self, Edit};
 use crate::*;
 use lib0::any::Any;
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
+use std::rc::Rc;
 
 /// A shared data type used for collaborative text editing. It enables multiple users to add and
 /// remove chunks of text in efficient manner. This type is internally represented as a mutable
@@ -26,9 +29,168 @@ use std::ops::{Deref, DerefMut};
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Text(BranchPtr);
 
+/// Maximum number of [SearchMarker]s kept by a single [Text].
+const MAX_SEARCH_MARKERS: usize = 80;
+
+/// A position within a [Text] found by one of the recent lookups. Subsequent lookups of positions
+/// placed after it can start from there instead of walking a whole block list from its beginning,
+/// which makes consecutive edits in long documents cheap.
+///
+/// Search marker describes a gap directly after its block.
+#[derive(Clone)]
+pub(crate) struct SearchMarker {
+    /// A block directly preceding the marked position.
+    ptr: BlockPtr,
+    /// Index of the marked position, measured in `offset_kind` units.
+    index: u32,
+    /// Block length of all blocks preceding the marked position.
+    block_index: u32,
+    offset_kind: OffsetKind,
+    /// Formatting blocks active at the marked position.
+    formats: HashMap<Rc<str>, Option<BlockPtr>>,
+    /// Value of [SearchMarkers] clock, when this marker has been used for the last time.
+    last_used: u64,
+}
+
+impl SearchMarker {
+    /// Updates a marker after a `right` block has been squashed into a `left` one.
+    fn squashed(&mut self, left: BlockPtr, right_ptr: BlockPtr, right: &Item) {
+        if self.ptr == left {
+            if !right.is_deleted() {
+                self.index += right.content_len(self.offset_kind);
+            }
+            self.block_index += right.len();
+        } else if self.ptr == right_ptr {
+            self.ptr = left;
+        }
+    }
+}
+
+/// A bounded list of [SearchMarker]s of a single [Text]. Once it's full, a least recently used
+/// marker is replaced by a new one.
+///
+/// Search markers are reset whenever their text is changed, except for local edits, which keep
+/// the markers placed before an edited position and, in case of plain inserts, shift the ones
+/// placed after it.
+#[derive(Default)]
+pub(crate) struct SearchMarkers {
+    markers: Vec<SearchMarker>,
+    /// Logical clock incremented by every lookup.
+    clock: u64,
+}
+
+impl SearchMarkers {
+    /// Returns a position of the marker placed closest to a given `index`, but not after it.
+    fn closest(&mut self, index: u32, offset_kind: OffsetKind) -> Option<usize> {
+        self.clock += 1;
+        let (i, marker) = self
+            .markers
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, m)| m.index <= index && m.offset_kind == offset_kind)
+            .max_by_key(|(_, m)| m.index)?;
+        marker.last_used = self.clock;
+        Some(i)
+    }
+
+    /// Records a new `marker` found by a lookup, which started from a `closest` marker. If that
+    /// one is placed less than `distance` before a new marker, it's moved instead.
+    fn record(&mut self, mut marker: SearchMarker, closest: Option<usize>, distance: u32) {
+        marker.last_used = self.clock;
+        let slot = match closest {
+            Some(i) if marker.index - self.markers[i].index < distance.max(1) => Some(i),
+            _ if self.markers.len() < MAX_SEARCH_MARKERS => None,
+            _ => self
+                .markers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, m)| m.last_used)
+                .map(|(i, _)| i),
+        };
+        match slot {
+            Some(i) => self.markers[i] = marker,
+            None => self.markers.push(marker),
+        }
+    }
+
+    /// Updates markers after a `right` block has been squashed into a `left` one.
+    pub(crate) fn squashed(&mut self, left: BlockPtr, right_ptr: BlockPtr, right: &Item) {
+        for marker in self.markers.iter_mut() {
+            marker.squashed(left, right_ptr, right);
+        }
+    }
+
+    /// Updates markers after a `left` block has been split, with its tail moved to a `right` one.
+    fn split(&mut self, left: BlockPtr, right: BlockPtr) {
+        for marker in self.markers.iter_mut() {
+            if marker.ptr == left {
+                marker.ptr = right;
+            }
+        }
+    }
+
+    /// Drops markers placed after a given `index`. If `shift` is provided, such markers are moved
+    /// by a given content and block length instead.
+    fn keep_until(&mut self, index: u32, shift: Option<(u32, u32)>) {
+        match shift {
+            Some((content_len, block_len)) => {
+                for marker in self.markers.iter_mut() {
+                    if marker.index > index {
+                        marker.index += content_len;
+                        marker.block_index += block_len;
+                    }
+                }
+            }
+            None => self.markers.retain(|m| m.index <= index),
+        }
+    }
+}
+
+/// A range of [Text] content inserted by a single client within the same edit, returned by
+/// [Text::attribution].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribution {
+    /// Client which inserted the content.
+    pub client: ClientID,
+    /// ID of the first element of the content. Consecutive elements have consecutive clocks.
+    pub id: ID,
+    /// Range of the content within a text, expressed in [OffsetKind] units of a document.
+    pub range: Range<u32>,
+    /// Time at which the content was inserted, if it has been recorded. See: [Transaction::timestamp].
+    pub timestamp: Option<Timestamp>,
+}
+
+/// Returns a length of `len` elements of a given `content` starting at `offset` (both expressed in
+/// block units, which for strings are UTF-16 code units), in terms of a given offset `kind`.
+fn content_len(content: &ItemContent, offset: u32, len: u32, kind: OffsetKind) -> u32 {
+    match content {
+        ItemContent::String(s) if kind != OffsetKind::Utf16 => {
+            let mut i = 0;
+            let mut result = 0;
+            for c in s.as_str().chars() {
+                if i >= offset + len {
+                    break;
+                } else if i >= offset {
+                    result += match kind {
+                        OffsetKind::Bytes => c.len_utf8() as u32,
+                        _ => 1,
+                    };
+                }
+                i += c.len_utf16() as u32;
+            }
+            result
+        }
+        _ => len,
+    }
+}
+
 impl Text {
     /// Converts context of this text data structure into a single string value.
     pub fn to_string(&self) -> String {
+        let mut cache = self.0.string_cache.borrow_mut();
+        if let Some(s) = cache.as_ref() {
+            return s.clone();
+        }
         let mut start = self.0.start;
         let mut s = String::new();
         while let Some(Block::Item(item)) = start.as_deref() {
@@ -39,34 +201,291 @@ impl Text {
             }
             start = item.right.clone();
         }
+        *cache = Some(s.clone());
+        s
+    }
+
+    /// Converts content of this text data structure into a JSON-like string value.
+    pub fn to_json(&self) -> Any {
+        Any::String(self.to_string().into_boxed_str())
+    }
+
+    /// Converts content of this text data structure into a single string value, as it was at
+    /// the time when a given `snapshot` has been made.
+    ///
+    /// Content removed since then can only be restored if a document has been created with
+    /// [Options::skip_gc](crate::Options::skip_gc) flag set, otherwise it's garbage collected.
+    pub fn to_string_at(&self, txn: &mut Transaction, snapshot: &Snapshot) -> String {
+        txn.split_by_snapshot(snapshot);
+        let mut start = self.0.start;
+        let mut s = String::new();
+        while let Some(Block::Item(item)) = start.as_deref() {
+            if snapshot.is_visible(&item.id) {
+                if let block::ItemContent::String(item_string) = &item.content {
+                    s.push_str(item_string);
+                }
+            }
+            start = item.right.clone();
+        }
         s
     }
 
+    /// Returns a fragment of a current text found within a given `range` of indexes, measured
+    /// using [OffsetKind] configured for a document. Unlike [Text::to_string], it only reads
+    /// the blocks containing requested fragment. Embedded values are skipped, but they count
+    /// towards the range. Returns an [OutOfBounds] error if `range` doesn't fit within the bounds
+    /// of a current text.
+    ///
+    /// Example:
+    /// ```
+    /// use yrs::Doc;
+    /// let doc = Doc::new();
+    /// let text = doc.transact().get_text("text");
+    /// text.push(&mut doc.transact(), "hello world");
+    ///
+    /// assert_eq!(text.slice(6..11).unwrap(), "world");
+    /// ```
+    pub fn slice(&self, range: Range<u32>) -> Result<String, OutOfBounds> {
+        if range.start > range.end {
+            return Err(OutOfBounds);
+        }
+        let mut txn = self.0.try_transact().expect("Text is not integrated");
+        let mut walker = BlockIter::new(self.0);
+        walker.try_forward(&mut txn, range.start)?;
+        walker.read_str(&mut txn, range.end - range.start)
+    }
+
     /// Returns a number of characters visible in a current text data structure.
     pub fn len(&self) -> u32 {
         self.0.content_len
     }
 
+    /// Returns a length of a current text expressed in a given `unit`. Unlike [Text::len], which
+    /// always uses [OffsetKind] of a document, this can be used to i.e. count grapheme clusters.
+    /// Embeds always have a length of 1, regardless of the unit used.
+    pub fn len_in(&self, unit: OffsetUnit) -> u32 {
+        let mut len = 0;
+        self.for_each_atom(unit, unit, |atom_len, _| {
+            len += atom_len;
+            false
+        });
+        len
+    }
+
+    /// Converts an `index` within a current text expressed in `from` units into an index
+    /// expressed in `to` units. An index pointing into the middle of a character or a grapheme
+    /// cluster, which cannot be represented in `to` units, is rounded down to its beginning.
+    /// Returns `None` if `index` is beyond the end of a text.
+    pub fn convert_offset(&self, index: u32, from: OffsetUnit, to: OffsetUnit) -> Option<u32> {
+        let (mut from_pos, mut to_pos) = (0, 0);
+        let mut found = None;
+        self.for_each_atom(from, to, |from_len, to_len| {
+            if from_pos + from_len > index {
+                found = Some(to_pos);
+                true
+            } else {
+                from_pos += from_len;
+                to_pos += to_len;
+                false
+            }
+        });
+        match found {
+            Some(pos) => Some(pos),
+            None if from_pos == index => Some(to_pos),
+            None => None,
+        }
+    }
+
+    /// Iterates over indivisible atoms of a current text - characters or, if any of the units is
+    /// [OffsetUnit::Grapheme], grapheme clusters - passing their lengths expressed in both units
+    /// to a given callback, until it returns `true`. Grapheme clusters may span over many blocks,
+    /// which is why adjacent string blocks are joined together before segmenting them.
+    fn for_each_atom<F>(&self, a: OffsetUnit, b: OffsetUnit, mut f: F)
+    where
+        F: FnMut(u32, u32) -> bool,
+    {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        fn unit_len(atom: &str, unit: OffsetUnit) -> u32 {
+            match unit {
+                OffsetUnit::Bytes => atom.len() as u32,
+                OffsetUnit::Utf16 => atom.encode_utf16().count() as u32,
+                OffsetUnit::Utf32 => atom.chars().count() as u32,
+                OffsetUnit::Grapheme => 1,
+            }
+        }
+
+        let graphemes = a == OffsetUnit::Grapheme || b == OffsetUnit::Grapheme;
+        // returns true if iteration should be stopped
+        let flush = |run: &mut String, f: &mut F| -> bool {
+            let stop = if graphemes {
+                run.graphemes(true)
+                    .any(|g| f(unit_len(g, a), unit_len(g, b)))
+            } else {
+                let mut buf = [0u8; 4];
+                run.chars().any(|c| {
+                    let c = c.encode_utf8(&mut buf);
+                    f(unit_len(c, a), unit_len(c, b))
+                })
+            };
+            run.clear();
+            stop
+        };
+
+        let mut run = String::new();
+        let mut ptr = self.0.start;
+        while let Some(Block::Item(item)) = ptr.as_deref() {
+            if !item.is_deleted() {
+                match &item.content {
+                    ItemContent::String(s) => run.push_str(s.as_str()),
+                    ItemContent::Format(_, _) => {}
+                    _ if item.is_countable() => {
+                        let stop = flush(&mut run, &mut f) || f(1, 1);
+                        if stop {
+                            return;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ptr = item.right;
+        }
+        flush(&mut run, &mut f);
+    }
+
+    /// Returns authorship information of a current text content: a list of consecutive ranges,
+    /// each of which has been inserted by a single client as a part of the same edit. Ranges are
+    /// expressed in [OffsetKind] units of a document and they cover the whole text (including
+    /// embedded values) in order. Formatting attributes and deleted content are not reported.
+    /// When [Options::timestamps](crate::doc::Options::timestamps) are recorded, content written
+    /// at different times is reported as separate ranges.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Attribution, Doc, ID};
+    ///
+    /// let doc = Doc::with_client_id(1);
+    /// let mut txn = doc.transact();
+    /// let text = txn.get_text("text");
+    /// text.insert(&mut txn, 0, "hello world");
+    /// text.remove_range(&mut txn, 5, 1);
+    ///
+    /// assert_eq!(text.attribution(&txn), vec![
+    ///     Attribution { client: 1, id: ID::new(1, 0), range: 0..5, timestamp: None },
+    ///     Attribution { client: 1, id: ID::new(1, 6), range: 5..10, timestamp: None },
+    /// ]);
+    /// ```
+    pub fn attribution(&self, txn: &Transaction) -> Vec<Attribution> {
+        let store = txn.store();
+        let encoding = store.options.offset_kind;
+        let mut result: Vec<Attribution> = Vec::new();
+        let mut index = 0;
+        // ID right after the last attributed element
+        let mut next_id = None;
+        let mut ptr = self.0.start;
+        while let Some(Block::Item(item)) = ptr.as_deref() {
+            if !item.is_deleted() && item.is_countable() {
+                // a single item may contain elements written at different times
+                let mut offset = 0;
+                while offset < item.len {
+                    let id = ID::new(item.id.client, item.id.clock + offset);
+                    let (timestamp, len) = store.timestamp_run(&id, item.len - offset);
+                    let end = index + content_len(&item.content, offset, len, encoding);
+                    match result.last_mut() {
+                        // continuation of the same edit, ie. split by formatting attributes
+                        Some(last) if next_id == Some(id) && last.timestamp == timestamp => {
+                            last.range.end = end
+                        }
+                        _ => result.push(Attribution {
+                            client: id.client,
+                            id,
+                            range: index..end,
+                            timestamp,
+                        }),
+                    }
+                    index = end;
+                    offset += len;
+                    next_id = Some(ID::new(id.client, id.clock + len));
+                }
+            }
+            ptr = item.right;
+        }
+        result
+    }
+
     pub(crate) fn inner(&self) -> BranchPtr {
         self.0
     }
 
+    /// Executes a local edit `f`, which is performed at or after a given `index`. Such edits don't
+    /// change the content preceding that position, therefore search markers placed up to it can be
+    /// safely restored. Markers placed after it are dropped, unless `f` only inserts new blocks
+    /// (`shift` is `true`), in which case they are moved by the length of inserted content.
+    fn keep_markers<F, R>(&self, index: u32, shift: bool, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let mut markers = self.0.search_markers.take();
+        let (content_len, block_len) = (self.0.content_len, self.0.block_len);
+        let result = f();
+        let shift = if shift {
+            Some((
+                self.0.content_len - content_len,
+                self.0.block_len - block_len,
+            ))
+        } else {
+            None
+        };
+        markers.keep_until(index, shift);
+        self.0.search_markers.replace(markers);
+        result
+    }
+
+    /// Checks if a range of `len` elements starting at `index` fits within current text and returns
+    /// a position at which it begins.
+    fn find_position_checked(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<ItemPosition, OutOfBounds> {
+        match index.checked_add(len) {
+            Some(end) if end <= self.len() => self.find_position(txn, index).ok_or(OutOfBounds),
+            _ => Err(OutOfBounds),
+        }
+    }
+
     pub(crate) fn find_position(&self, txn: &mut Transaction, index: u32) -> Option<ItemPosition> {
-        let mut pos = {
-            let inner = self.as_ref();
-            ItemPosition {
+        let store = txn.store_mut();
+        let encoding = store.options.offset_kind;
+        // resume from the closest position found before, unless it's past the requested index
+        let closest = self.0.search_markers.borrow_mut().closest(index, encoding);
+        let marker = closest.map(|i| self.0.search_markers.borrow().markers[i].clone());
+        let (mut pos, mut format_ptrs, mut remaining) = if let Some(marker) = marker {
+            let right = if let Block::Item(item) = marker.ptr.deref() {
+                item.right
+            } else {
+                None
+            };
+            let pos = ItemPosition {
+                parent: self.0.into(),
+                left: Some(marker.ptr),
+                right,
+                index: marker.block_index,
+                current_attrs: None,
+            };
+            (pos, marker.formats, index - marker.index)
+        } else {
+            let pos = ItemPosition {
                 parent: self.0.into(),
                 left: None,
-                right: inner.start,
+                right: self.0.start,
                 index: 0,
                 current_attrs: None,
-            }
+            };
+            (pos, HashMap::new(), index)
         };
-
-        let mut format_ptrs = HashMap::new();
-        let store = txn.store_mut();
-        let encoding = store.options.offset_kind;
-        let mut remaining = index;
         while let Some(mut right_ptr) = pos.right {
             if remaining == 0 {
                 break;
@@ -92,10 +511,14 @@ impl Text {
                                 } else {
                                     remaining
                                 };
-                                store
+                                let new_right = store
                                     .blocks
                                     .split_block(right_ptr, offset, OffsetKind::Utf16)
                                     .unwrap();
+                                self.0
+                                    .search_markers
+                                    .borrow_mut()
+                                    .split(right_ptr, new_right);
                                 block_len -= offset;
                                 remaining = 0;
                             } else {
@@ -116,6 +539,22 @@ impl Text {
             }
         }
 
+        if let (Some(ptr), 0) = (pos.left, remaining) {
+            let marker = SearchMarker {
+                ptr,
+                index,
+                block_index: pos.index,
+                offset_kind: encoding,
+                formats: format_ptrs.clone(),
+                last_used: 0,
+            };
+            let distance = self.0.content_len / MAX_SEARCH_MARKERS as u32;
+            self.0
+                .search_markers
+                .borrow_mut()
+                .record(marker, closest, distance);
+        }
+
         for (_, block_ptr) in format_ptrs {
             if let Some(mut ptr) = block_ptr {
                 if let Block::Item(item) = ptr.deref_mut() {
@@ -132,30 +571,84 @@ impl Text {
         Some(pos)
     }
 
+    /// Returns a preliminary weak link to a fragment of current text, which is `len` long and
+    /// starts at a given `index`. It can be inserted into other collections. Returns an
+    /// [OutOfBounds] error if quoted fragment is empty or doesn't fit within the bounds of
+    /// current text.
+    ///
+    /// A link points to the quoted characters themselves, so it keeps referencing them while
+    /// text is edited around them. Text inserted inside of a quoted fragment becomes a part of
+    /// it. See: [WeakLink].
+    pub fn quote(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<WeakPrelim, OutOfBounds> {
+        let end_index = match index.checked_add(len) {
+            Some(end) if len > 0 && end <= self.len() => end,
+            _ => return Err(OutOfBounds),
+        };
+        let mut start = self.find_position(txn, index).ok_or(OutOfBounds)?.right;
+        while let Some(Block::Item(item)) = start.as_deref() {
+            if !item.is_deleted() && item.is_countable() {
+                break;
+            }
+            start = item.right;
+        }
+        let mut end = self.find_position(txn, end_index).ok_or(OutOfBounds)?.left;
+        while let Some(Block::Item(item)) = end.as_deref() {
+            if !item.is_deleted() && item.is_countable() {
+                break;
+            }
+            end = item.left;
+        }
+        match (start.as_deref(), end.as_deref()) {
+            (Some(start), Some(end)) => {
+                Ok(WeakPrelim::new(LinkSource::new(*start.id(), end.last_id())))
+            }
+            _ => Err(OutOfBounds),
+        }
+    }
+
     /// Inserts a `chunk` of text at a given `index`.
     /// If `index` is `0`, this `chunk` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
     /// the end of it.
     ///
     /// This method will panic if provided `index` is greater than the length of a current text.
+    /// See [Text::try_insert] for a non-panicking alternative.
     pub fn insert(&self, txn: &mut Transaction, index: u32, chunk: &str) {
+        if self.try_insert(txn, index, chunk).is_err() {
+            panic!("Index {} is outside of the range of a text", index);
+        }
+    }
+
+    /// Inserts a `chunk` of text at a given `index`, just like [Text::insert]. Returns an
+    /// [OutOfBounds] error if provided `index` is greater than the length of a current text.
+    pub fn try_insert(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        chunk: &str,
+    ) -> Result<(), OutOfBounds> {
+        let mut pos = self.find_position_checked(txn, index, 0)?;
         if chunk.is_empty() {
-            return;
+            return Ok(());
         }
-        if let Some(mut pos) = self.find_position(txn, index) {
-            let value = crate::block::PrelimString(chunk.into());
-            while let Some(right) = pos.right.as_ref() {
-                if right.is_deleted() {
-                    // skip over deleted blocks, just like Yjs does
-                    pos.forward();
-                } else {
-                    break;
-                }
+        let value = crate::block::PrelimString(chunk.into());
+        while let Some(right) = pos.right.as_ref() {
+            if right.is_deleted() {
+                // skip over deleted blocks, just like Yjs does
+                pos.forward();
+            } else {
+                break;
             }
-            txn.create_item(&pos, value, None);
-        } else {
-            panic!("The type or the position doesn't exist!");
         }
+        self.keep_markers(index, true, || {
+            txn.create_item(&pos, value, None);
+        });
+        Ok(())
     }
 
     /// Inserts a `chunk` of text at a given `index`.
@@ -166,47 +659,78 @@ impl Text {
     /// formatting blocks.
     ///
     /// This method will panic if provided `index` is greater than the length of a current text.
+    /// See [Text::try_insert_with_attributes] for a non-panicking alternative.
     pub fn insert_with_attributes(
         &self,
         txn: &mut Transaction,
         index: u32,
         chunk: &str,
-        mut attributes: Attrs,
+        attributes: Attrs,
     ) {
-        if let Some(mut pos) = self.find_position(txn, index) {
-            pos.unset_missing(&mut attributes);
-            Text::minimize_attr_changes(&mut pos, &attributes);
-            let negated_attrs = self.insert_attributes(txn, &mut pos, attributes);
-
-            let value = crate::block::PrelimString(chunk.into());
-            let item = txn.create_item(&pos, value, None);
-
-            pos.right = Some(item);
-            pos.forward();
+        if self
+            .try_insert_with_attributes(txn, index, chunk, attributes)
+            .is_err()
+        {
+            panic!("Index {} is outside of the range of a text", index);
+        }
+    }
 
-            self.insert_negated_attributes(txn, &mut pos, negated_attrs);
-        } else {
-            panic!("The type or the position doesn't exist!");
+    /// Inserts a `chunk` of text wrapped with formatting `attributes` at a given `index`, just
+    /// like [Text::insert_with_attributes]. Returns an [OutOfBounds] error if provided `index` is
+    /// greater than the length of a current text.
+    pub fn try_insert_with_attributes(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        chunk: &str,
+        attributes: Attrs,
+    ) -> Result<(), OutOfBounds> {
+        let mut pos = self.find_position_checked(txn, index, 0)?;
+        if chunk.is_empty() {
+            return Ok(());
         }
+        let value = crate::block::PrelimString(chunk.into());
+        self.keep_markers(index, false, || {
+            self.insert_at(txn, &mut pos, value, attributes)
+        });
+        Ok(())
     }
 
-    /// Inserts an embed `content` at a given `index`.
+    /// Inserts an embed `content` at a given `index`. Embed can be either a JSON-like value (eg.
+    /// a map describing an image) or a preliminary shared type (like [PrelimMap] or [PrelimText]),
+    /// which will be integrated as a nested collection. Every embed counts as a single element
+    /// towards the text length.
     ///
     /// If `index` is `0`, this `content` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `embed` will be appended at
     /// the end of it.
     ///
     /// This method will panic if provided `index` is greater than the length of a current text.
-    pub fn insert_embed(&self, txn: &mut Transaction, index: u32, content: Any) {
-        if let Some(pos) = self.find_position(txn, index) {
-            let value = crate::block::PrelimEmbed(content);
-            txn.create_item(&pos, value, None);
-        } else {
-            panic!("The type or the position doesn't exist!");
+    /// See [Text::try_insert_embed] for a non-panicking alternative.
+    pub fn insert_embed<V: Prelim>(&self, txn: &mut Transaction, index: u32, content: V) {
+        if self.try_insert_embed(txn, index, content).is_err() {
+            panic!("Index {} is outside of the range of a text", index);
         }
     }
 
-    /// Inserts an embed `content` of text at a given `index`.
+    /// Inserts an embed `content` at a given `index`, just like [Text::insert_embed]. Returns an
+    /// [OutOfBounds] error if provided `index` is greater than the length of a current text.
+    pub fn try_insert_embed<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        content: V,
+    ) -> Result<(), OutOfBounds> {
+        let pos = self.find_position_checked(txn, index, 0)?;
+        let value = crate::block::PrelimEmbed(content);
+        self.keep_markers(index, true, || {
+            txn.create_item(&pos, value, None);
+        });
+        Ok(())
+    }
+
+    /// Inserts an embed `content` of text at a given `index`. See [Text::insert_embed] for
+    /// the types of content that can be embedded.
     /// If `index` is `0`, this `content` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
     /// the end of it.
@@ -214,30 +738,214 @@ impl Text {
     /// a formatting blocks.
     ///
     /// This method will panic if provided `index` is greater than the length of a current text.
-    pub fn insert_embed_with_attributes(
+    /// See [Text::try_insert_embed_with_attributes] for a non-panicking alternative.
+    pub fn insert_embed_with_attributes<V: Prelim>(
         &self,
         txn: &mut Transaction,
         index: u32,
-        embed: Any,
-        mut attributes: Attrs,
+        embed: V,
+        attributes: Attrs,
     ) {
+        if self
+            .try_insert_embed_with_attributes(txn, index, embed, attributes)
+            .is_err()
+        {
+            panic!("Index {} is outside of the range of a text", index);
+        }
+    }
+
+    /// Inserts an `embed` wrapped with formatting `attributes` at a given `index`, just like
+    /// [Text::insert_embed_with_attributes]. Returns an [OutOfBounds] error if provided `index` is
+    /// greater than the length of a current text.
+    pub fn try_insert_embed_with_attributes<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        embed: V,
+        attributes: Attrs,
+    ) -> Result<(), OutOfBounds> {
+        let mut pos = self.find_position_checked(txn, index, 0)?;
+        let value = crate::block::PrelimEmbed(embed);
+        self.keep_markers(index, false, || {
+            self.insert_at(txn, &mut pos, value, attributes)
+        });
+        Ok(())
+    }
+
+    /// Inserts a sequence of text `chunks` one after another, starting at a given `index`. Each
+    /// chunk may come with its own formatting attributes, which work the same way as in
+    /// [Text::insert_with_attributes]. Chunks without attributes inherit formatting of the text
+    /// they were inserted into, just like [Text::insert] does.
+    ///
+    /// Unlike calling [Text::insert] repeatedly, the insertion position is looked up only once
+    /// for all chunks.
+    ///
+    /// This method will panic if provided `index` is greater than the length of a current text.
+    pub fn insert_chunks<I, S>(&self, txn: &mut Transaction, index: u32, chunks: I)
+    where
+        I: IntoIterator<Item = (S, Option<Attrs>)>,
+        S: AsRef<str>,
+    {
         if let Some(mut pos) = self.find_position(txn, index) {
-            pos.unset_missing(&mut attributes);
-            Text::minimize_attr_changes(&mut pos, &attributes);
-            let negated_attrs = self.insert_attributes(txn, &mut pos, attributes);
+            self.keep_markers(index, false, || {
+                for (chunk, attributes) in chunks {
+                    let chunk = chunk.as_ref();
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    let attributes = match attributes {
+                        Some(attributes) => attributes,
+                        None => pos.current_attrs.as_deref().cloned().unwrap_or_default(),
+                    };
+                    let value = crate::block::PrelimString(chunk.into());
+                    self.insert_at(txn, &mut pos, value, attributes);
+                }
+            });
+        } else {
+            panic!("The type or the position doesn't exist!");
+        }
+    }
 
-            let value = crate::block::PrelimEmbed(embed);
-            let item = txn.create_item(&pos, value, None);
+    /// Applies a sequence of changes, such as the one produced by [TextEvent::delta], on top of
+    /// the current text contents in a single pass:
+    ///
+    /// - [Delta::Retain] skips over a given number of characters, formatting them with optional
+    ///   attributes.
+    /// - [Delta::Inserted] inserts a string (or an embed, for any other value) formatted with
+    ///   given attributes. Inserts without attributes are not formatted. Shared types are embedded
+    ///   using their JSON representation.
+    /// - [Delta::Deleted] removes a given number of characters.
+    ///
+    /// This method will panic if the delta tries to remove characters past the end of a text.
+    pub fn apply_delta<I>(&self, txn: &mut Transaction, delta: I)
+    where
+        I: IntoIterator<Item = Delta>,
+    {
+        if let Some(mut pos) = self.find_position(txn, 0) {
+            self.keep_markers(0, false, || {
+                for change in delta {
+                    match change {
+                        Delta::Inserted(value, attrs) => {
+                            let attrs = attrs.map(|attrs| *attrs).unwrap_or_default();
+                            match value {
+                                Value::Any(Any::String(chunk)) => {
+                                    if !chunk.is_empty() {
+                                        let value = crate::block::PrelimString(chunk.into());
+                                        self.insert_at(txn, &mut pos, value, attrs);
+                                    }
+                                }
+                                Value::YDoc(doc) => {
+                                    let value = crate::block::PrelimEmbed(doc);
+                                    self.insert_at(txn, &mut pos, value, attrs);
+                                }
+                                other => {
+                                    let value = crate::block::PrelimEmbed(other.to_json());
+                                    self.insert_at(txn, &mut pos, value, attrs);
+                                }
+                            }
+                        }
+                        Delta::Retain(len, attrs) => {
+                            let attrs = attrs.map(|attrs| *attrs).unwrap_or_default();
+                            self.insert_format(txn, &mut pos, len, attrs);
+                        }
+                        Delta::Deleted(len) => Self::remove(txn, &mut pos, len, None),
+                    }
+                }
+            });
+        } else {
+            panic!("The type or the position doesn't exist!");
+        }
+    }
 
-            pos.right = Some(item);
-            pos.forward();
+    /// Replaces the contents of a current text with `new_content`, but instead of removing and
+    /// inserting everything, it computes a minimal diff between the current and the new text and
+    /// only applies the changed spans. This way concurrent edits made to the unchanged parts of
+    /// the text are preserved when merging. Inserted text inherits formatting of the position
+    /// it's inserted at, while embeds are removed, since they cannot be a part of `new_content`.
+    pub fn set_string_diff(&self, txn: &mut Transaction, new_content: &str) {
+        let encoding = txn.store().options.offset_kind;
+        // string characters of a current text, with embeds represented as `None`
+        let mut current: Vec<Option<char>> = Vec::with_capacity(self.len() as usize);
+        let mut ptr = self.0.start;
+        while let Some(Block::Item(item)) = ptr.as_deref() {
+            if !item.is_deleted() {
+                match &item.content {
+                    ItemContent::String(s) => current.extend(s.as_str().chars().map(Some)),
+                    ItemContent::Format(_, _) => {}
+                    _ if item.is_countable() => current.push(None),
+                    _ => {}
+                }
+            }
+            ptr = item.right;
+        }
+        let target: Vec<Option<char>> = new_content.chars().map(Some).collect();
+        let len = |chars: &[Option<char>]| -> u32 {
+            chars
+                .iter()
+                .map(|c| match (c, encoding) {
+                    (Some(c), OffsetKind::Bytes) => c.len_utf8() as u32,
+                    (Some(c), OffsetKind::Utf16) => c.len_utf16() as u32,
+                    _ => 1,
+                })
+                .sum()
+        };
 
-            self.insert_negated_attributes(txn, &mut pos, negated_attrs);
+        if let Some(mut pos) = self.find_position(txn, 0) {
+            self.keep_markers(0, false, || {
+                let mut i = 0;
+                for edit in diff::diff(&current, &target) {
+                    match edit {
+                        Edit::Equal(n) => {
+                            let n_units = len(&current[i..i + n]);
+                            self.insert_format(txn, &mut pos, n_units, Attrs::new());
+                            i += n;
+                        }
+                        Edit::Delete(n) => {
+                            Self::remove(txn, &mut pos, len(&current[i..i + n]), None);
+                            i += n;
+                        }
+                        Edit::Insert(range) => {
+                            let chunk: String = target[range].iter().flatten().collect();
+                            let attrs = pos.current_attrs.as_deref().cloned().unwrap_or_default();
+                            let value = crate::block::PrelimString(chunk.as_str().into());
+                            self.insert_at(txn, &mut pos, value, attrs);
+                        }
+                    }
+                }
+            });
         } else {
             panic!("The type or the position doesn't exist!");
         }
     }
 
+    /// Inserts a `value` at a given position, wrapping it with formatting `attributes`, and moves
+    /// the position right after the inserted value.
+    fn insert_at<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        pos: &mut ItemPosition,
+        value: V,
+        mut attributes: Attrs,
+    ) {
+        pos.unset_missing(&mut attributes);
+        Text::minimize_attr_changes(pos, &attributes);
+        let negated_attrs = self.insert_attributes(txn, pos, attributes);
+
+        let item = txn.create_item(pos, value, None);
+
+        pos.right = Some(item);
+        pos.forward();
+
+        self.insert_negated_attributes(txn, pos, negated_attrs);
+    }
+
+    /// Returns a [Cursor] pointing at the beginning of a current text, which can be used to
+    /// perform a sequence of reads and edits at nearby positions within a scope of a given
+    /// transaction.
+    pub fn cursor<'txn>(&self, txn: &'txn mut Transaction) -> Cursor<'txn> {
+        Cursor::new(self.0, txn)
+    }
+
     /// Appends a given `chunk` of text at the end of a current text structure.
     pub fn push(&self, txn: &mut Transaction, chunk: &str) {
         let idx = self.len();
@@ -247,15 +955,55 @@ impl Text {
     /// Removes up to a `len` characters from a current text structure, starting at given `index`.
     /// This method panics in case when not all expected characters were removed (due to
     /// insufficient number of characters to remove) or `index` is outside of the bounds of text.
+    /// See [Text::try_remove_range] for a non-panicking alternative.
     pub fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) {
-        if let Some(pos) = self.find_position(txn, index) {
-            Self::remove(txn, pos, len)
+        if self.try_remove_range(txn, index, len).is_err() {
+            panic!(
+                "Range of {} elements starting at {} is outside of the range of a text",
+                len, index
+            );
+        }
+    }
+
+    /// Removes `len` characters from a current text structure, starting at given `index`. Returns
+    /// an [OutOfBounds] error if requested range doesn't fit within the bounds of a text. Bounds
+    /// are checked before any character is removed.
+    pub fn try_remove_range(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<(), OutOfBounds> {
+        let mut pos = self.find_position_checked(txn, index, len)?;
+        self.keep_markers(index, false, || Self::remove(txn, &mut pos, len, None));
+        Ok(())
+    }
+
+    /// Removes a number of characters specified by a `len` parameter from this text structure,
+    /// starting at a given `index`, and returns removed string. This method may panic if `index`
+    /// is out of the range of this text structure.
+    ///
+    /// This is equivalent of reading a fragment of a text followed by [Text::remove_range], but
+    /// traverses a text only once. Embedded values within removed range are not included in
+    /// a returned string.
+    pub fn drain(&self, txn: &mut Transaction, index: u32, len: u32) -> String {
+        let mut removed = String::new();
+        if let Some(mut pos) = self.find_position(txn, index) {
+            self.keep_markers(index, false, || {
+                Self::remove(txn, &mut pos, len, Some(&mut removed))
+            })
         } else {
             panic!("The type or the position doesn't exist!");
         }
+        removed
     }
 
-    fn remove(txn: &mut Transaction, mut pos: ItemPosition, len: u32) {
+    fn remove(
+        txn: &mut Transaction,
+        pos: &mut ItemPosition,
+        len: u32,
+        mut removed: Option<&mut String>,
+    ) {
         let encoding = txn.store().options.offset_kind;
         let mut remaining = len;
         let start = pos.right.clone();
@@ -284,6 +1032,12 @@ impl Text {
                         } else {
                             remaining -= content_len;
                         };
+                        if let (Some(removed), Block::Item(item)) = (removed.as_mut(), ptr.deref())
+                        {
+                            if let ItemContent::String(s) = &item.content {
+                                removed.push_str(s.as_str());
+                            }
+                        }
                         txn.delete(ptr);
                     }
                     _ => {}
@@ -352,23 +1106,48 @@ impl Text {
 
     /// Wraps an existing piece of text within a range described by `index`-`len` parameters with
     /// formatting blocks containing provided `attributes` metadata.
+    ///
+    /// Setting an attribute value to [Any::Null] removes that attribute from a given range.
+    ///
+    /// This method will panic if `index` is greater than the length of a current text. Ranges
+    /// exceeding the end of a text are formatted up to its end. See [Text::try_format] for
+    /// a non-panicking alternative.
     pub fn format(&self, txn: &mut Transaction, index: u32, len: u32, attributes: Attrs) {
-        if let Some(pos) = self.find_position(txn, index) {
-            self.insert_format(txn, pos, len, attributes)
-        } else {
+        let len = len.min(self.len().saturating_sub(index));
+        if self.try_format(txn, index, len, attributes).is_err() {
             panic!("Index {} is outside of the range.", index);
         }
     }
 
+    /// Wraps an existing piece of text within a range described by `index`-`len` parameters with
+    /// formatting blocks containing provided `attributes` metadata, just like [Text::format].
+    /// Returns an [OutOfBounds] error if requested range doesn't fit within the bounds of a text.
+    pub fn try_format(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+        attributes: Attrs,
+    ) -> Result<(), OutOfBounds> {
+        let mut pos = self.find_position_checked(txn, index, len)?;
+        if len == 0 || attributes.is_empty() {
+            return Ok(());
+        }
+        self.keep_markers(index, false, || {
+            self.insert_format(txn, &mut pos, len, attributes)
+        });
+        Ok(())
+    }
+
     fn insert_format(
         &self,
         txn: &mut Transaction,
-        mut pos: ItemPosition,
+        pos: &mut ItemPosition,
         mut len: u32,
         attrs: Attrs,
     ) {
-        Self::minimize_attr_changes(&mut pos, &attrs);
-        let mut negated_attrs = self.insert_attributes(txn, &mut pos, attrs.clone()); //TODO: remove `attrs.clone()`
+        Self::minimize_attr_changes(pos, &attrs);
+        let mut negated_attrs = self.insert_attributes(txn, pos, attrs.clone()); //TODO: remove `attrs.clone()`
         let encoding = txn.store().options.offset_kind;
         while let Some(right) = pos.right {
             if len <= 0 {
@@ -427,7 +1206,7 @@ impl Text {
             }
         }
 
-        self.insert_negated_attributes(txn, &mut pos, negated_attrs);
+        self.insert_negated_attributes(txn, pos, negated_attrs);
     }
 
     fn minimize_attr_changes(pos: &mut ItemPosition, attrs: &Attrs) {
@@ -470,7 +1249,7 @@ impl Text {
 
                 let client_id = store.options.client_id;
                 let parent = { self.0.into() };
-                let mut item = Item::new(
+                let item = Item::new(
                     ID::new(client_id, store.blocks.get_state(&client_id)),
                     pos.left.clone(),
                     pos.left.map(|ptr| ptr.last_id()),
@@ -480,14 +1259,8 @@ impl Text {
                     None,
                     ItemContent::Format(k, v.into()),
                 );
-                let mut item_ptr = BlockPtr::from(&mut item);
+                let (item_ptr, _) = txn.integrate_new_block(item, 0);
                 pos.right = Some(item_ptr);
-                item_ptr.integrate(txn, 0);
-                let local_block_list = txn
-                    .store_mut()
-                    .blocks
-                    .get_client_blocks_mut(item.id().client);
-                local_block_list.push(item);
 
                 pos.forward();
                 store = txn.store_mut();
@@ -524,7 +1297,7 @@ impl Text {
         for (k, v) in attrs {
             let client_id = store.options.client_id;
             let parent = { self.0.into() };
-            let mut item = Item::new(
+            let item = Item::new(
                 ID::new(client_id, store.blocks.get_state(&client_id)),
                 pos.left.clone(),
                 pos.left.map(|ptr| ptr.last_id()),
@@ -534,15 +1307,8 @@ impl Text {
                 None,
                 ItemContent::Format(k, v.into()),
             );
-            let mut item_ptr = BlockPtr::from(&mut item);
+            let (item_ptr, _) = txn.integrate_new_block(item, 0);
             pos.right = Some(item_ptr);
-            item_ptr.integrate(txn, 0);
-
-            let local_block_list = txn
-                .store_mut()
-                .blocks
-                .get_client_blocks_mut(item.id().client);
-            local_block_list.push(item);
 
             pos.forward();
             store = txn.store_mut();
@@ -583,6 +1349,10 @@ impl Text {
         }
     }
 
+    /// Returns a current text content as a sequence of [Diff] chunks. Every chunk contains
+    /// a piece of text (or an embedded value) together with the formatting attributes that have
+    /// been applied to it. Consecutive characters sharing the same attributes are grouped into
+    /// a single chunk.
     pub fn diff<T, F>(&self, txn: &mut Transaction, compute_ychange: F) -> Vec<Diff<T>>
     where
         F: Fn(YChange) -> T,
@@ -590,7 +1360,9 @@ impl Text {
         self.diff_range(txn, None, None, compute_ychange)
     }
 
-    /// Returns the Delta representation of this YText type.
+    /// Returns the Delta representation of this YText type. When `hi` and `lo` snapshots are
+    /// provided, returned chunks contain also the content that has been inserted or removed
+    /// between these snapshots, marked by a [YChange] computed using `compute_ychange`.
     pub fn diff_range<T, F>(
         &self,
         txn: &mut Transaction,
@@ -630,16 +1402,29 @@ impl Text {
                     let attrs = self.attrs_boxed();
                     let mut buf = std::mem::replace(&mut self.buf, String::new());
                     buf.shrink_to_fit();
-                    let change = if let Some(ychange) = self.curr_ychange.take() {
-                        Some((self.compute_ychange)(ychange))
-                    } else {
-                        None
-                    };
+                    let change = self.change();
                     let op = Diff::with_change(Value::Any(buf.into()), attrs, change);
                     self.ops.push(op);
                 }
             }
 
+            fn change(&self) -> Option<T> {
+                let ychange = self.curr_ychange.clone()?;
+                Some((self.compute_ychange)(ychange))
+            }
+
+            fn set_change(&mut self, ychange: Option<YChange>) {
+                let same = match (&self.curr_ychange, &ychange) {
+                    (None, None) => true,
+                    (Some(a), Some(b)) => a.kind == b.kind && a.id.client == b.id.client,
+                    _ => false,
+                };
+                if !same {
+                    self.pack_str();
+                    self.curr_ychange = ychange;
+                }
+            }
+
             fn finish(self) -> Vec<Diff<T>> {
                 self.ops
             }
@@ -675,27 +1460,22 @@ impl Text {
             if seen(hi, item) || (lo.is_some() && seen(lo, item)) {
                 match &item.content {
                     ItemContent::String(s) => {
-                        if let Some(snapshot) = hi {
-                            if !snapshot.is_visible(&item.id) {
-                                asm.pack_str();
-                                asm.curr_ychange = Some(YChange::new(ChangeKind::Removed, item.id));
-                            } else if let Some(snapshot) = lo {
-                                if !snapshot.is_visible(&item.id) {
-                                    asm.pack_str();
-                                    asm.curr_ychange =
-                                        Some(YChange::new(ChangeKind::Added, item.id));
-                                } else if asm.curr_ychange.is_some() {
-                                    asm.pack_str();
-                                }
-                            }
-                        }
+                        let ychange = if hi.map_or(false, |s| !s.is_visible(&item.id)) {
+                            Some(YChange::new(ChangeKind::Removed, item.id))
+                        } else if lo.map_or(false, |s| !s.is_visible(&item.id)) {
+                            Some(YChange::new(ChangeKind::Added, item.id))
+                        } else {
+                            None
+                        };
+                        asm.set_change(ychange);
                         asm.buf.push_str(s.as_str());
                     }
                     ItemContent::Type(_) | ItemContent::Embed(_) => {
                         asm.pack_str();
                         if let Some(value) = item.content.get_last() {
                             let attrs = asm.attrs_boxed();
-                            asm.ops.push(Diff::new(value, attrs));
+                            let change = asm.change();
+                            asm.ops.push(Diff::with_change(value, attrs, change));
                         }
                     }
                     ItemContent::Format(key, value) => {
@@ -737,10 +1517,14 @@ impl AsMut<Branch> for Text {
     }
 }
 
+/// A single chunk of a formatted text, returned by [Text::diff] method.
 #[derive(Debug, PartialEq)]
 pub struct Diff<T> {
+    /// Inserted piece of text or an embedded value.
     pub insert: Value,
+    /// Formatting attributes applied over inserted value, if any.
     pub attributes: Option<Box<Attrs>>,
+    /// Optional change marker, used when diffing between two snapshots.
     pub ychange: Option<T>,
 }
 
@@ -1036,18 +1820,115 @@ impl Prelim for PrelimText<'_> {
 
 #[cfg(test)]
 mod test {
-    use crate::doc::{OffsetKind, Options};
-    use crate::test_utils::{exchange_updates, run_scenario, RngExt};
-    use crate::types::text::{Attrs, ChangeKind, Delta, Diff, YChange};
+    use crate::block_iter::BlockIter;
+    use crate::doc::{OffsetKind, OffsetUnit, Options};
+    use crate::testing::{exchange_updates, run_scenario, text_edits, RngExt};
+    use crate::types::text::{
+        Attribution, Attrs, ChangeKind, Delta, Diff, Text, YChange, MAX_SEARCH_MARKERS,
+    };
+    use crate::types::Value;
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
-    use crate::{Doc, StateVector, Update, ID};
+    use crate::{Clock, Doc, OutOfBounds, PrelimMap, Snapshot, StateVector, Update, ID};
     use lib0::any::Any;
     use rand::prelude::StdRng;
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
     use std::collections::HashMap;
     use std::rc::Rc;
 
+    #[test]
+    fn to_string_cache_invalidation() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let txt1 = d1.transact().get_text("text");
+        let txt2 = d2.transact().get_text("text");
+
+        txt1.insert(&mut d1.transact(), 0, "hello");
+        assert_eq!(txt1.to_string(), "hello");
+        txt1.insert(&mut d1.transact(), 5, " world");
+        assert_eq!(txt1.to_string(), "hello world");
+        txt1.remove_range(&mut d1.transact(), 0, 6);
+        assert_eq!(txt1.to_string(), "world");
+
+        // remote changes
+        assert_eq!(txt2.to_string(), "");
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(txt2.to_string(), "world");
+        txt2.insert(&mut d2.transact(), 0, "hello ");
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(txt1.to_string(), "hello world");
+
+        // changes reverted by a rollback
+        let mut txn = d1.transact();
+        txt1.remove_range(&mut txn, 0, 6);
+        txt1.insert(&mut txn, 5, "!");
+        assert_eq!(txt1.to_string(), "world!");
+        txn.rollback().unwrap();
+        assert_eq!(txt1.to_string(), "hello world");
+    }
+
+    #[test]
+    fn try_edits_out_of_bounds() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let txt = txn.get_text("text");
+        txt.push(&mut txn, "hello");
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+
+        assert_eq!(txt.try_insert(&mut txn, 6, "!"), Err(OutOfBounds));
+        assert_eq!(
+            txt.try_insert_with_attributes(&mut txn, 6, "!", bold.clone()),
+            Err(OutOfBounds)
+        );
+        assert_eq!(txt.try_insert_embed(&mut txn, 6, true), Err(OutOfBounds));
+        assert_eq!(
+            txt.try_insert_embed_with_attributes(&mut txn, 6, true, bold.clone()),
+            Err(OutOfBounds)
+        );
+        assert_eq!(txt.try_remove_range(&mut txn, 3, 3), Err(OutOfBounds));
+        assert_eq!(
+            txt.try_remove_range(&mut txn, 1, u32::MAX),
+            Err(OutOfBounds)
+        );
+        assert_eq!(
+            txt.try_format(&mut txn, 4, 2, bold.clone()),
+            Err(OutOfBounds)
+        );
+        // failed edits leave text untouched
+        assert_eq!(txt.to_string(), "hello");
+        assert!(txn.delete_set.is_empty());
+
+        assert_eq!(txt.try_insert(&mut txn, 5, "!"), Ok(()));
+        assert_eq!(txt.try_remove_range(&mut txn, 0, 1), Ok(()));
+        assert_eq!(txt.try_format(&mut txn, 0, 4, bold.clone()), Ok(()));
+        assert_eq!(
+            txt.diff(&mut txn, YChange::identity),
+            vec![
+                Diff::new("ello".into(), Some(Box::new(bold))),
+                Diff::new("!".into(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_entry_inside_character() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::with_client_id(1)
+        });
+        let mut txn = doc.transact();
+        let txt = txn.get_text("text");
+        txt.push(&mut txn, "a😀b");
+
+        let mut walker = BlockIter::new(txt.0);
+        walker.try_forward(&mut txn, 2).unwrap();
+        // iterator points into the middle of a surrogate pair
+        assert_eq!(walker.read_entry(&mut txn), None);
+        assert_eq!(walker.index(), 2);
+        walker.try_forward(&mut txn, 1).unwrap();
+        assert_eq!(walker.read_value(&mut txn), Some(Value::from("b")));
+    }
+
     #[test]
     fn insert_empty_string() {
         let doc = Doc::new();
@@ -1322,8 +2203,43 @@ mod test {
         txt.insert(&mut txn, 6, "beautiful");
         txt.insert(&mut txn, 15, " world");
 
-        txt.remove_range(&mut txn, 5, 11);
-        assert_eq!(txt.to_string().as_str(), "helloworld");
+        txt.remove_range(&mut txn, 5, 11);
+        assert_eq!(txt.to_string().as_str(), "helloworld");
+    }
+
+    #[test]
+    fn drain_multiple_blocks_with_slicing() {
+        let doc = Doc::new();
+        let mut txn = doc.transact();
+        let txt = txn.get_text("test");
+
+        txt.insert(&mut txn, 0, "hello ");
+        txt.insert(&mut txn, 6, "beautiful");
+        txt.insert_embed(&mut txn, 15, Any::Bool(true));
+        txt.insert(&mut txn, 16, " wörld");
+
+        assert_eq!(txt.drain(&mut txn, 5, 13).as_str(), " beautiful w");
+        assert_eq!(txt.to_string().as_str(), "helloörld");
+        assert_eq!(txt.drain(&mut txn, 5, 2).as_str(), "ö");
+        assert_eq!(txt.to_string().as_str(), "hellorld");
+    }
+
+    #[test]
+    fn slice() {
+        let doc = Doc::new();
+        let txt = doc.transact().get_text("test");
+        {
+            let mut txn = doc.transact();
+            txt.insert(&mut txn, 0, "hello ");
+            txt.insert_embed(&mut txn, 6, Any::Bool(true));
+            txt.insert(&mut txn, 7, "wörld");
+        }
+
+        assert_eq!(txt.slice(0..5).unwrap(), "hello");
+        assert_eq!(txt.slice(4..10).unwrap(), "o wö");
+        assert_eq!(txt.slice(8..10).unwrap(), "ö");
+        assert_eq!(txt.slice(13..13).unwrap(), "");
+        assert_eq!(txt.slice(10..14), Err(OutOfBounds));
     }
 
     #[test]
@@ -1521,31 +2437,350 @@ mod test {
         }
     }
 
-    fn text_transactions() -> [Box<dyn Fn(&mut Doc, &mut StdRng)>; 2] {
-        fn insert_text(doc: &mut Doc, rng: &mut StdRng) {
+    #[test]
+    fn insert_chunks() {
+        let d1 = Doc::with_client_id(1);
+        let mut txt1 = d1.transact().get_text("text");
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        let italic: Attrs = HashMap::from([("italic".into(), Any::Bool(true))]);
+        txt1.insert(&mut d1.transact(), 0, "[]");
+
+        let events = Rc::new(RefCell::new(0));
+        let events_c = events.clone();
+        let _sub = txt1.observe(move |_, _| *events_c.borrow_mut() += 1);
+
+        txt1.insert_chunks(
+            &mut d1.transact(),
+            1,
+            vec![
+                ("hello ", None),
+                ("world", Some(bold.clone())),
+                ("", Some(italic.clone())),
+                ("!", None),
+            ],
+        );
+        assert_eq!(*events.borrow(), 1);
+        assert_eq!(txt1.to_string(), "[hello world!]");
+
+        // chunks without attributes inherit formatting of surrounding text
+        txt1.insert_chunks(
+            &mut d1.transact(),
+            8,
+            vec![("o", None), ("r", Some(italic.clone()))],
+        );
+        assert_eq!(*events.borrow(), 2);
+
+        // explicit attributes replace the inherited ones
+        let expected = vec![
+            Diff::new("[hello ".into(), None),
+            Diff::new("wo".into(), Some(Box::new(bold.clone()))),
+            Diff::new("r".into(), Some(Box::new(italic.clone()))),
+            Diff::new("orld".into(), Some(Box::new(bold.clone()))),
+            Diff::new("!]".into(), None),
+        ];
+        assert_eq!(txt1.diff(&mut d1.transact(), YChange::identity), expected);
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(txt2.diff(&mut d2.transact(), YChange::identity), expected);
+    }
+
+    #[test]
+    fn apply_delta() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.transact().get_text("text");
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        txt.insert(&mut doc.transact(), 0, "abcd");
+
+        txt.apply_delta(
+            &mut doc.transact(),
+            vec![
+                Delta::Retain(1, None),
+                Delta::Inserted("XY".into(), Some(Box::new(bold.clone()))),
+                Delta::Deleted(2),
+                Delta::Retain(1, Some(Box::new(bold.clone()))),
+                Delta::Inserted(Any::Number(1.0).into(), None),
+            ],
+        );
+
+        assert_eq!(
+            merged_diff(&txt, &doc),
+            vec![
+                Diff::new("a".into(), None),
+                Diff::new("XYd".into(), Some(Box::new(bold.clone()))),
+                Diff::new(Any::Number(1.0).into(), None),
+            ]
+        );
+    }
+
+    /// Returns a text diff with adjacent strings of the same formatting merged together, so that
+    /// texts can be compared regardless of how their formatting blocks are laid out.
+    fn merged_diff(txt: &Text, doc: &Doc) -> Vec<Diff<YChange>> {
+        let mut result: Vec<Diff<YChange>> = Vec::new();
+        for diff in txt.diff(&mut doc.transact(), YChange::identity) {
+            if let Some(last) = result.last_mut() {
+                if let (Value::Any(Any::String(a)), Value::Any(Any::String(b))) =
+                    (&last.insert, &diff.insert)
+                {
+                    if last.attributes == diff.attributes {
+                        last.insert = Value::Any(Any::String(format!("{}{}", a, b).into()));
+                        continue;
+                    }
+                }
+            }
+            result.push(diff);
+        }
+        result
+    }
+
+    #[test]
+    fn apply_delta_from_events() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(58);
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let mut txt1 = d1.transact().get_text("text");
+        let txt2 = d2.transact().get_text("text");
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        let unbold: Attrs = HashMap::from([("bold".into(), Any::Null)]);
+
+        let deltas = Rc::new(RefCell::new(Vec::new()));
+        let deltas_c = deltas.clone();
+        let _sub = txt1.observe(move |txn, e| {
+            deltas_c.borrow_mut().push(e.delta(txn).to_vec());
+        });
+
+        for _ in 0..200 {
+            let mut txn = d1.transact();
+            let len = txt1.len();
+            let index = rng.between(0, len);
+            match rng.gen_range(0, 4) {
+                0 => txt1.insert(&mut txn, index, &rng.random_string()),
+                1 => txt1.insert_with_attributes(&mut txn, index, "b", bold.clone()),
+                2 if index < len => txt1.remove_range(&mut txn, index, rng.between(1, len - index)),
+                _ if index < len => {
+                    let attrs = if rng.gen_bool(0.5) { &bold } else { &unbold };
+                    let n = rng.between(1, len - index);
+                    txt1.format(&mut txn, index, n, attrs.clone())
+                }
+                _ => {}
+            }
+            drop(txn);
+
+            for delta in deltas.borrow_mut().drain(..) {
+                txt2.apply_delta(&mut d2.transact(), delta);
+            }
+            assert_eq!(merged_diff(&txt2, &d2), merged_diff(&txt1, &d1));
+        }
+    }
+
+    #[test]
+    fn set_string_diff_preserves_concurrent_edits() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let txt1 = d1.transact().get_text("text");
+        let txt2 = d2.transact().get_text("text");
+        txt1.insert(&mut d1.transact(), 0, "hello world");
+        exchange_updates(&[&d1, &d2]);
+
+        txt1.set_string_diff(&mut d1.transact(), "hello big world");
+        txt2.insert(&mut d2.transact(), 11, "!");
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(txt1.to_string(), "hello big world!");
+        assert_eq!(txt2.to_string(), "hello big world!");
+    }
+
+    #[test]
+    fn set_string_diff_formatting_and_embeds() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.transact().get_text("text");
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        txt.insert(&mut doc.transact(), 0, "say ");
+        txt.insert_with_attributes(&mut doc.transact(), 4, "hello", bold.clone());
+        txt.insert_embed(&mut doc.transact(), 9, Any::Bool(true));
+
+        txt.set_string_diff(&mut doc.transact(), "say help!");
+
+        assert_eq!(
+            merged_diff(&txt, &doc),
+            vec![
+                Diff::new("say ".into(), None),
+                Diff::new("help!".into(), Some(Box::new(bold))),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_string_diff_random() {
+        use rand::{Rng, SeedableRng};
+
+        const CHARS: [char; 5] = ['a', 'b', 'ż', '😀', ' '];
+        let mut rng = StdRng::seed_from_u64(59);
+        for kind in [OffsetKind::Bytes, OffsetKind::Utf16, OffsetKind::Utf32] {
+            let mut options = Options::with_client_id(1);
+            options.offset_kind = kind;
+            let doc = Doc::with_options(options);
+            let txt = doc.transact().get_text("text");
+            for _ in 0..50 {
+                let content: String = (0..rng.gen_range(0, 30))
+                    .map(|_| CHARS[rng.gen_range(0, CHARS.len())])
+                    .collect();
+                txt.set_string_diff(&mut doc.transact(), &content);
+                assert_eq!(txt.to_string(), content);
+            }
+        }
+    }
+
+    #[test]
+    fn convert_offset() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.transact().get_text("text");
+        {
             let mut txn = doc.transact();
-            let ytext = txn.get_text("text");
-            let pos = rng.between(0, ytext.len());
-            let word = rng.random_string();
-            ytext.insert(&mut txn, pos, word.as_str());
+            let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+            // combining accent is stored in a separate block than the letter it modifies
+            txt.insert_with_attributes(&mut txn, 0, "ae", bold);
+            txt.insert_with_attributes(&mut txn, 2, "\u{301}👍", HashMap::new());
+            txt.insert_embed(&mut txn, 8, Any::Bool(true));
+            txt.insert(&mut txn, 9, "👨‍👩‍👧x");
+        }
+
+        assert_eq!(txt.len(), 28);
+        assert_eq!(txt.len_in(OffsetUnit::Bytes), 28);
+        assert_eq!(txt.len_in(OffsetUnit::Utf16), 15);
+        assert_eq!(txt.len_in(OffsetUnit::Utf32), 11);
+        assert_eq!(txt.len_in(OffsetUnit::Grapheme), 6);
+
+        use OffsetUnit::*;
+        assert_eq!(txt.convert_offset(3, Grapheme, Bytes), Some(8));
+        assert_eq!(txt.convert_offset(4, Grapheme, Utf16), Some(6));
+        assert_eq!(txt.convert_offset(6, Grapheme, Utf32), Some(11));
+        assert_eq!(txt.convert_offset(7, Grapheme, Bytes), None);
+        // indexes in the middle of a grapheme cluster are rounded down
+        assert_eq!(txt.convert_offset(2, Utf16, Grapheme), Some(1));
+        assert_eq!(txt.convert_offset(10, Bytes, Grapheme), Some(4));
+        // indexes in the middle of a character are rounded down
+        assert_eq!(txt.convert_offset(4, Utf16, Utf32), Some(3));
+        assert_eq!(txt.convert_offset(5, Bytes, Utf16), Some(3));
+        assert_eq!(txt.convert_offset(28, Bytes, Utf16), Some(15));
+        assert_eq!(txt.convert_offset(29, Bytes, Utf16), None);
+
+        for unit in [Bytes, Utf16, Utf32] {
+            for i in 0..=txt.len_in(Grapheme) {
+                let converted = txt.convert_offset(i, Grapheme, unit).unwrap();
+                assert_eq!(txt.convert_offset(converted, unit, Grapheme), Some(i));
+            }
+        }
+    }
+
+    #[test]
+    fn search_marker_consistency() {
+        use rand::{Rng, SeedableRng};
+
+        // the same edits applied with and without cached search markers must produce
+        // exactly the same document
+        let mut rng = StdRng::seed_from_u64(55);
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("text");
+        let txt2 = d2.transact().get_text("text");
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        let mut last = 0u32;
+
+        for _ in 0..500 {
+            let mut t1 = d1.transact();
+            let mut t2 = d2.transact();
+            for _ in 0..rng.gen_range(1, 4) {
+                txt2.0.search_markers.take();
+                let len = txt1.len();
+                let index = if rng.gen_bool(0.7) {
+                    (last + rng.gen_range(0, 3)).saturating_sub(1).min(len)
+                } else {
+                    rng.between(0, len)
+                };
+                match rng.gen_range(0, 5) {
+                    0 | 1 => {
+                        let chunk = rng.random_string();
+                        txt1.insert(&mut t1, index, &chunk);
+                        txt2.insert(&mut t2, index, &chunk);
+                        last = index + chunk.len() as u32;
+                    }
+                    2 => {
+                        txt1.insert_with_attributes(&mut t1, index, "b", bold.clone());
+                        txt2.insert_with_attributes(&mut t2, index, "b", bold.clone());
+                        last = index + 1;
+                    }
+                    3 if index < len => {
+                        let n = rng.between(1, len - index);
+                        txt1.remove_range(&mut t1, index, n);
+                        txt2.remove_range(&mut t2, index, n);
+                        last = index;
+                    }
+                    _ if index < len => {
+                        let n = rng.between(1, len - index);
+                        txt1.format(&mut t1, index, n, bold.clone());
+                        txt2.format(&mut t2, index, n, bold.clone());
+                        last = index + n;
+                    }
+                    _ => {}
+                }
+            }
         }
 
-        fn delete_text(doc: &mut Doc, rng: &mut StdRng) {
+        assert_eq!(txt1.to_string(), txt2.to_string());
+        assert_eq!(
+            d1.encode_state_as_update_v1(&StateVector::default()),
+            d2.encode_state_as_update_v1(&StateVector::default())
+        );
+    }
+
+    #[test]
+    fn search_markers_bounded() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.transact().get_text("text");
+        let mut expected = "a".repeat(10_000);
+        txt.insert(&mut doc.transact(), 0, &expected);
+
+        // interleaved edits at distant positions keep separate markers
+        for i in 0..10u32 {
             let mut txn = doc.transact();
-            let ytext = txn.get_text("text");
-            let len = ytext.len();
-            if len > 0 {
-                let pos = rng.between(0, len - 1);
-                let to_delete = rng.between(2, len - pos);
-                ytext.remove_range(&mut txn, pos, to_delete);
+            for &index in &[1_000 + i, 5_000 + 2 * i, 9_000 + 3 * i] {
+                txt.insert(&mut txn, index, "b");
+                expected.insert(index as usize, 'b');
             }
         }
+        assert_eq!(txt.0.search_markers.borrow().markers.len(), 3);
+        assert_eq!(txt.to_string(), expected);
+
+        // lookups of many distinct positions never exceed a marker limit
+        let mut txn = doc.transact();
+        for index in (1..10_000).step_by(100).rev() {
+            txt.insert(&mut txn, index, "c");
+            expected.insert(index as usize, 'c');
+            assert!(txt.0.search_markers.borrow().markers.len() <= MAX_SEARCH_MARKERS);
+        }
+        drop(txn);
+        assert_eq!(
+            txt.0.search_markers.borrow().markers.len(),
+            MAX_SEARCH_MARKERS
+        );
+        assert_eq!(txt.to_string(), expected);
 
-        [Box::new(insert_text), Box::new(delete_text)]
+        // shifted markers remain valid
+        let mut txn = doc.transact();
+        for index in (1..10_000).step_by(100) {
+            txt.insert(&mut txn, index, "d");
+            expected.insert(index as usize, 'd');
+        }
+        drop(txn);
+        assert_eq!(txt.to_string(), expected);
     }
 
     fn fuzzy(iterations: usize) {
-        run_scenario(0, &text_transactions(), 5, iterations)
+        run_scenario(0, &text_edits(), 5, iterations);
     }
 
     #[test]
@@ -1739,6 +2974,95 @@ mod test {
         }
     }
 
+    #[test]
+    fn concurrent_format() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("text");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+
+        txt1.push(&mut d1.transact(), "abcd");
+        exchange_updates(&[&d1, &d2]);
+
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        let italic: Attrs = HashMap::from([("italic".into(), Any::Bool(true))]);
+        txt1.format(&mut d1.transact(), 0, 2, bold.clone());
+        txt2.format(&mut d2.transact(), 1, 2, italic.clone());
+        exchange_updates(&[&d1, &d2]);
+
+        let both: Attrs = HashMap::from([
+            ("bold".into(), Any::Bool(true)),
+            ("italic".into(), Any::Bool(true)),
+        ]);
+        let expected = vec![
+            Diff::new("a".into(), Some(Box::new(bold.clone()))),
+            Diff::new("b".into(), Some(Box::new(both))),
+            Diff::new("c".into(), Some(Box::new(italic))),
+            Diff::new("d".into(), None),
+        ];
+        assert_eq!(txt1.diff(&mut d1.transact(), YChange::identity), expected);
+        assert_eq!(txt2.diff(&mut d2.transact(), YChange::identity), expected);
+
+        // empty inserts and formats should not produce any changes
+        let sv = d1.transact().state_vector();
+        txt1.insert_with_attributes(&mut d1.transact(), 4, "", bold.clone());
+        txt1.format(&mut d1.transact(), 0, 0, bold.clone());
+        assert_eq!(d1.transact().state_vector(), sv);
+
+        // unsetting an attribute
+        let unset: Attrs = HashMap::from([("italic".into(), Any::Null)]);
+        txt1.format(&mut d1.transact(), 0, 4, unset);
+        assert_eq!(
+            txt1.diff(&mut d1.transact(), YChange::identity),
+            vec![
+                Diff::new("ab".into(), Some(Box::new(bold))),
+                Diff::new("cd".into(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn delta_with_mixed_changes() {
+        let d1 = Doc::with_client_id(1);
+        let mut txt1 = d1.transact().get_text("text");
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        let italic: Attrs = HashMap::from([("italic".into(), Any::Bool(true))]);
+        txt1.insert_with_attributes(&mut d1.transact(), 0, "abcdef", bold.clone());
+
+        let delta = Rc::new(RefCell::new(None));
+        let delta_clone = delta.clone();
+        let _sub = txt1.observe(move |txn, e| {
+            delta_clone.replace(Some(e.delta(txn).to_vec()));
+        });
+
+        {
+            let mut txn = d1.transact();
+            txt1.insert_with_attributes(&mut txn, 2, "xy", italic.clone());
+            txt1.remove_range(&mut txn, 4, 2);
+            txt1.format(&mut txn, 0, 1, HashMap::from([("bold".into(), Any::Null)]));
+        }
+
+        let unbold: Attrs = HashMap::from([("bold".into(), Any::Null)]);
+        assert_eq!(
+            delta.take(),
+            Some(vec![
+                Delta::Retain(1, Some(Box::new(unbold))),
+                Delta::Retain(1, None),
+                Delta::Inserted("xy".into(), Some(Box::new(italic.clone()))),
+                Delta::Deleted(2),
+            ])
+        );
+        assert_eq!(
+            txt1.diff(&mut d1.transact(), YChange::identity),
+            vec![
+                Diff::new("a".into(), None),
+                Diff::new("b".into(), Some(Box::new(bold.clone()))),
+                Diff::new("xy".into(), Some(Box::new(italic))),
+                Diff::new("ef".into(), Some(Box::new(bold))),
+            ]
+        );
+    }
+
     #[test]
     fn embed_with_attributes() {
         let d1 = Doc::with_client_id(1);
@@ -1787,6 +3111,54 @@ mod test {
         }
     }
 
+    #[test]
+    fn embed_shared_type() {
+        let d1 = Doc::with_client_id(1);
+        let mut txt1 = d1.transact().get_text("text");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+
+        let delta = Rc::new(RefCell::new(None));
+        let delta_clone = delta.clone();
+        let _sub = txt1.observe(move |txn, e| {
+            delta_clone.replace(Some(e.delta(txn).to_vec()));
+        });
+
+        {
+            let mut txn = d1.transact();
+            txt1.push(&mut txn, "ab");
+            let mention = PrelimMap::from(HashMap::from([("user".to_owned(), "Alice")]));
+            txt1.insert_embed(&mut txn, 1, mention);
+        }
+        let mention = Any::Map(Box::new(HashMap::from([(
+            "user".to_owned(),
+            Any::String("Alice".into()),
+        )])));
+        match delta.take().unwrap().as_slice() {
+            [Delta::Inserted(a, None), Delta::Inserted(Value::YMap(m), None), Delta::Inserted(b, None)] =>
+            {
+                assert_eq!(a, &Value::from("a"));
+                assert_eq!(m.to_json(), mention);
+                assert_eq!(b, &Value::from("b"));
+            }
+            other => panic!("unexpected delta: {:?}", other),
+        }
+        assert_eq!(txt1.len(), 3);
+        assert_eq!(txt1.to_string(), "ab");
+
+        exchange_updates(&[&d1, &d2]);
+        let diff = txt2.diff(&mut d2.transact(), YChange::identity);
+        assert_eq!(diff.len(), 3);
+        assert_eq!(diff[1].insert.clone().to_json(), mention);
+
+        txt2.remove_range(&mut d2.transact(), 1, 1);
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(
+            txt1.diff(&mut d1.transact(), YChange::identity),
+            vec![Diff::new("ab".into(), None)]
+        );
+    }
+
     #[test]
     fn issue_101() {
         let d1 = Doc::with_client_id(1);
@@ -2061,4 +3433,195 @@ mod test {
             ]
         )
     }
+
+    #[test]
+    fn to_string_at_snapshot() {
+        let mut options = Options::with_client_id(1);
+        options.skip_gc = true;
+        let doc = Doc::with_options(options);
+        let text = doc.transact().get_text("text");
+        text.insert(&mut doc.transact(), 0, "hello world");
+        let s1 = doc.snapshot();
+        text.remove_range(&mut doc.transact(), 2, 7);
+        let s2 = doc.snapshot();
+        text.insert(&mut doc.transact(), 2, "y, w");
+        text.insert(&mut doc.transact(), 0, ">");
+
+        let mut txn = doc.transact();
+        assert_eq!(text.to_string_at(&mut txn, &s1), "hello world");
+        assert_eq!(text.to_string_at(&mut txn, &s2), "held");
+        assert_eq!(text.to_string_at(&mut txn, &Snapshot::default()), "");
+        assert_eq!(text.to_string(), ">hey, wld");
+    }
+
+    #[test]
+    fn diff_range_between_snapshots() {
+        let doc = Doc::with_options(Options {
+            skip_gc: true,
+            ..Options::with_client_id(1)
+        });
+        let text = doc.transact().get_text("text");
+        text.insert(&mut doc.transact(), 0, "abcdef");
+        let prev = doc.transact().snapshot();
+        text.remove_range(&mut doc.transact(), 1, 1);
+        text.remove_range(&mut doc.transact(), 1, 1);
+        text.insert(&mut doc.transact(), 2, "x");
+        text.insert(&mut doc.transact(), 3, "y");
+        let next = doc.transact().snapshot();
+        text.insert(&mut doc.transact(), 0, "z");
+
+        let diff = text.diff_range(
+            &mut doc.transact(),
+            Some(&next),
+            Some(&prev),
+            YChange::identity,
+        );
+        assert_eq!(
+            diff,
+            vec![
+                Diff::new("a".into(), None),
+                Diff::with_change(
+                    "bc".into(),
+                    None,
+                    Some(YChange::new(ChangeKind::Removed, ID::new(1, 1)))
+                ),
+                Diff::new("d".into(), None),
+                Diff::with_change(
+                    "xy".into(),
+                    None,
+                    Some(YChange::new(ChangeKind::Added, ID::new(1, 6)))
+                ),
+                Diff::new("ef".into(), None),
+            ]
+        );
+
+        // without upper snapshot, removed content is not marked, just like in Yjs
+        let diff = text.diff_range(&mut doc.transact(), None, Some(&prev), YChange::identity);
+        assert_eq!(
+            diff,
+            vec![
+                Diff::with_change(
+                    "z".into(),
+                    None,
+                    Some(YChange::new(ChangeKind::Added, ID::new(1, 8)))
+                ),
+                Diff::new("abcd".into(), None),
+                Diff::with_change(
+                    "xy".into(),
+                    None,
+                    Some(YChange::new(ChangeKind::Added, ID::new(1, 6)))
+                ),
+                Diff::new("ef".into(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn attribution() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let txt1 = d1.transact().get_text("text");
+        let txt2 = d2.transact().get_text("text");
+
+        txt1.insert(&mut d1.transact(), 0, "hello world");
+        exchange_updates(&[&d1, &d2]);
+        txt2.insert(&mut d2.transact(), 6, "big ");
+        exchange_updates(&[&d1, &d2]);
+
+        // formatting splits blocks, but shouldn't split attributed ranges
+        let attrs: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        txt1.format(&mut d1.transact(), 2, 6, attrs);
+        txt1.insert_embed(&mut d1.transact(), 15, Any::Bool(true));
+        txt1.remove_range(&mut d1.transact(), 0, 1);
+        exchange_updates(&[&d1, &d2]);
+
+        let expected = vec![
+            Attribution {
+                client: 1,
+                id: ID::new(1, 1),
+                range: 0..5,
+                timestamp: None,
+            },
+            Attribution {
+                client: 2,
+                id: ID::new(2, 0),
+                range: 5..9,
+                timestamp: None,
+            },
+            Attribution {
+                client: 1,
+                id: ID::new(1, 6),
+                range: 9..14,
+                timestamp: None,
+            },
+            Attribution {
+                client: 1,
+                id: ID::new(1, 13),
+                range: 14..15,
+                timestamp: None,
+            },
+        ];
+        assert_eq!(txt1.attribution(&d1.transact()), expected);
+        assert_eq!(txt2.attribution(&d2.transact()), expected);
+    }
+
+    #[test]
+    fn attribution_timestamps() {
+        let now = Rc::new(Cell::new(100));
+        let mut options = Options::with_client_id(1);
+        options.timestamps = Some({
+            let now = now.clone();
+            Clock::new(move || now.get())
+        });
+        let d1 = Doc::with_options(options);
+        let d2 = Doc::with_client_id(2);
+        let mut txt1 = d1.transact().get_text("text");
+        let txt2 = d2.transact().get_text("text");
+
+        let observed = Rc::new(RefCell::new(Vec::new()));
+        let _sub = {
+            let observed = observed.clone();
+            txt1.observe(move |txn, e| {
+                let last = e.target().attribution(txn).pop().unwrap();
+                observed.borrow_mut().push(last.timestamp);
+            })
+        };
+
+        txt1.insert(&mut d1.transact(), 0, "abc");
+        now.set(200);
+        txt1.insert(&mut d1.transact(), 3, "dèf");
+        exchange_updates(&[&d1, &d2]);
+        txt2.insert(&mut d2.transact(), 7, "ghi");
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(observed.borrow().as_slice(), &[Some(100), Some(200), None]);
+        assert_eq!(
+            txt1.attribution(&d1.transact()),
+            vec![
+                Attribution {
+                    client: 1,
+                    id: ID::new(1, 0),
+                    range: 0..3,
+                    timestamp: Some(100),
+                },
+                Attribution {
+                    client: 1,
+                    id: ID::new(1, 3),
+                    range: 3..7,
+                    timestamp: Some(200),
+                },
+                Attribution {
+                    client: 2,
+                    id: ID::new(2, 0),
+                    range: 7..10,
+                    timestamp: None,
+                },
+            ]
+        );
+
+        // timestamps are not part of the document state shared with other peers
+        let t2 = d2.transact();
+        assert_eq!(t2.timestamp(&ID::new(1, 0)), None);
+        assert_eq!(txt2.attribution(&t2)[0].timestamp, None);
+    }
 }
@@ -0,0 +1,659 @@
+use crate::block::{Block, Prelim};
+use crate::event::Subscription;
+use crate::store::StoreRef;
+use crate::types::array::ArrayEvent;
+use crate::types::table::{as_id, insert_id, same_branch};
+use crate::types::{Branch, BranchPtr, Change, Value};
+use crate::*;
+use lib0::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// A collaborative ordered tree of nodes, which can be created, reparented, reordered and
+/// deleted concurrently by many peers.
+///
+/// Tree is a root-level type and can be obtained with [Transaction::get_tree]. It's built on
+/// top of other shared types, each of them being a separate root-level type named after a tree:
+///
+/// - `<name>/parents` is a [Map] of node identifiers to identifiers of their parent nodes.
+/// - `<name>/values` is a [Map] of node identifiers to values stored in these nodes.
+/// - `<name>/order` is an [Array] of node identifiers. Order of siblings is the order in which
+///   they appear in this array.
+///
+/// Every tree has an implicit [Tree::ROOT] node, which cannot be moved or deleted.
+///
+/// Since every node has a single parent entry, concurrent reparenting of the same node is
+/// resolved by the last-writer-wins rule of a [Map]. Concurrent reparenting of different nodes
+/// may however form a cycle (eg. one peer moves `A` under `B` while another one moves `B` under
+/// `A`). Such cycles are resolved at read time, in the same way on every peer: a node of the
+/// cycle which was reparented most recently is attached to the [Tree::ROOT] node instead.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, Tree};
+///
+/// let doc = Doc::new();
+/// let mut txn = doc.transact();
+/// let tree = txn.get_tree("tree");
+/// let a = tree.create_node(&mut txn, Tree::ROOT, 0, "a").unwrap();
+/// let b = tree.create_node(&mut txn, Tree::ROOT, 1, "b").unwrap();
+///
+/// tree.move_node(&mut txn, &b, &a, 0).unwrap();
+/// assert_eq!(tree.children(Tree::ROOT), vec![a.clone()]);
+/// assert_eq!(tree.children(&a), vec![b.clone()]);
+///
+/// // a node cannot be moved under its own descendant
+/// assert!(tree.move_node(&mut txn, &a, &b, 0).is_err());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Tree {
+    parents: Map,
+    values: Map,
+    order: Array,
+}
+
+impl Tree {
+    /// Identifier of an implicit root node of every tree.
+    pub const ROOT: &'static str = "root";
+
+    pub(crate) fn new(parents: Map, values: Map, order: Array) -> Self {
+        Tree {
+            parents,
+            values,
+            order,
+        }
+    }
+
+    /// Checks if a node with a given identifier exists within current tree.
+    pub fn contains(&self, node: &str) -> bool {
+        node == Tree::ROOT || self.resolve().contains_key(node)
+    }
+
+    /// Returns an identifier of a parent of a given `node`, or `None` if such node didn't exist
+    /// or it was a [Tree::ROOT] node.
+    pub fn parent(&self, node: &str) -> Option<Rc<str>> {
+        self.resolve().remove(node)
+    }
+
+    /// Returns identifiers of direct children of a given `node`, in their current order.
+    pub fn children(&self, node: &str) -> Vec<Rc<str>> {
+        let parents = self.resolve();
+        self.order_ids()
+            .into_iter()
+            .filter(|id| parents.get(id).map(Rc::as_ref) == Some(node))
+            .collect()
+    }
+
+    /// Returns a value stored in a given `node`, or `None` if such node didn't exist.
+    pub fn get(&self, node: &str) -> Option<Value> {
+        if self.contains(node) {
+            self.values.get(node)
+        } else {
+            None
+        }
+    }
+
+    /// Sets a `value` of a given `node`. Returns a value stored previously in that node (if any
+    /// existed).
+    pub fn set<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        node: &str,
+        value: V,
+    ) -> Result<Option<Value>, TreeError> {
+        if node == Tree::ROOT || !self.contains(node) {
+            return Err(TreeError::NodeNotFound(node.into()));
+        }
+        Ok(self.values.insert(txn, node, value))
+    }
+
+    /// Creates a new node with a given `value` as a child of a `parent` node, at a given `index`
+    /// among its siblings. Returns a unique identifier of a created node.
+    pub fn create_node<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        parent: &str,
+        index: u32,
+        value: V,
+    ) -> Result<Rc<str>, TreeError> {
+        let parents = self.resolve();
+        if parent != Tree::ROOT && !parents.contains_key(parent) {
+            return Err(TreeError::NodeNotFound(parent.into()));
+        }
+        let position = self.position(&parents, parent, index, None)?;
+        let node = insert_id(&self.order, txn, position).map_err(|_| TreeError::OutOfBounds)?;
+        self.parents.insert(txn, node.clone(), parent.to_string());
+        self.values.insert(txn, node.clone(), value);
+        Ok(node)
+    }
+
+    /// Moves a given `node` (together with its descendants) under a `new_parent` node, at
+    /// a given `index` among its new siblings. A node can also be moved within its current
+    /// parent, changing the order of its siblings.
+    ///
+    /// Returns [TreeError::Cycle] if `new_parent` is the `node` itself or one of its descendants.
+    pub fn move_node(
+        &self,
+        txn: &mut Transaction,
+        node: &str,
+        new_parent: &str,
+        index: u32,
+    ) -> Result<(), TreeError> {
+        let parents = self.resolve();
+        let current = match parents.get(node) {
+            Some(current) => current.clone(),
+            None => return Err(TreeError::NodeNotFound(node.into())),
+        };
+        if new_parent != Tree::ROOT && !parents.contains_key(new_parent) {
+            return Err(TreeError::NodeNotFound(new_parent.into()));
+        }
+        if is_ancestor(&parents, node, new_parent) {
+            return Err(TreeError::Cycle);
+        }
+        let order = self.order_ids();
+        let source = order.iter().position(|id| id.as_ref() == node).unwrap() as u32;
+        let mut target = self.position(&parents, new_parent, index, Some(node))?;
+        if target >= source {
+            // position was computed as if the node wasn't present in order array
+            target += 1;
+        }
+        // persist parents of the nodes detached from the cycles, so that they won't form a cycle
+        // again once one of the other nodes of that cycle is moved
+        for (id, parent) in parents.iter() {
+            if self.parents.get(id).and_then(as_id).as_ref() != Some(parent) {
+                self.parents.insert(txn, id.clone(), parent.to_string());
+            }
+        }
+        if current.as_ref() != new_parent {
+            self.parents.insert(txn, node, new_parent.to_string());
+        }
+        self.order
+            .try_move_to(txn, source, target)
+            .map_err(|_| TreeError::OutOfBounds)
+    }
+
+    /// Deletes a given `node` together with all of its descendants.
+    pub fn delete_node(&self, txn: &mut Transaction, node: &str) -> Result<(), TreeError> {
+        let parents = self.resolve();
+        if !parents.contains_key(node) {
+            return Err(TreeError::NodeNotFound(node.into()));
+        }
+        let order = self.order_ids();
+        for (index, id) in order.iter().enumerate().rev() {
+            if is_ancestor(&parents, node, id) {
+                self.order.remove(txn, index as u32);
+                self.parents.remove(txn, id);
+                self.values.remove(txn, id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts current tree into JSON-like array of nodes attached to a [Tree::ROOT] node.
+    /// Every node is represented as a map of `id`, `value` and an array of its `children`.
+    pub fn to_json(&self) -> Any {
+        let parents = self.resolve();
+        let order = self.order_ids();
+        let mut children: HashMap<&str, Vec<&Rc<str>>> = HashMap::new();
+        for id in order.iter() {
+            if let Some(parent) = parents.get(id) {
+                children.entry(parent).or_default().push(id);
+            }
+        }
+        self.node_json(&children, Tree::ROOT)
+    }
+
+    fn node_json(&self, children: &HashMap<&str, Vec<&Rc<str>>>, node: &str) -> Any {
+        let nodes = children
+            .get(node)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|id| {
+                let mut map = HashMap::new();
+                map.insert(
+                    "id".to_string(),
+                    Any::String(id.to_string().into_boxed_str()),
+                );
+                let value = self.values.get(id).map(Value::to_json);
+                map.insert("value".to_string(), value.unwrap_or(Any::Null));
+                map.insert("children".to_string(), self.node_json(children, id));
+                Any::Map(Box::new(map))
+            })
+            .collect();
+        Any::Array(nodes)
+    }
+
+    /// Subscribes a given callback to be triggered whenever current tree has been changed.
+    /// A callback is triggered once per committed transaction, which created, moved, deleted
+    /// or changed a value of any of the nodes.
+    ///
+    /// Returns an [Observer] which, when dropped, will unsubscribe current callback.
+    pub fn observe<F>(&self, f: F) -> Subscription<TreeEvent>
+    where
+        F: Fn(&Transaction, &TreeEvent) + 'static,
+    {
+        let tree = self.clone();
+        let mut store = self.store();
+        let subscription = store
+            .before_cleanup_events
+            .get_or_insert_with(Default::default)
+            .subscribe(move |txn, _| {
+                if let Some(e) = TreeEvent::new(&tree, txn) {
+                    f(txn, &e);
+                }
+            });
+        subscription.cast()
+    }
+
+    /// Subscribes a given callback to be triggered whenever any node within a subtree of a given
+    /// `node` has been changed. This includes nodes moved into or out of that subtree, as well
+    /// as the nodes deleted from it. [TreeEvent::nodes] contains only the nodes which belong
+    /// (or used to belong) to that subtree.
+    ///
+    /// Returns an [Observer] which, when dropped, will unsubscribe current callback.
+    pub fn observe_subtree<F>(&self, node: &str, f: F) -> Subscription<TreeEvent>
+    where
+        F: Fn(&Transaction, &TreeEvent) + 'static,
+    {
+        let tree = self.clone();
+        let node: Rc<str> = node.into();
+        // structure of a tree as it was before the observed transaction
+        let previous = RefCell::new(self.resolve());
+        let mut store = self.store();
+        let subscription = store
+            .before_cleanup_events
+            .get_or_insert_with(Default::default)
+            .subscribe(move |txn, _| {
+                if let Some(mut e) = TreeEvent::new(&tree, txn) {
+                    let current = tree.resolve();
+                    let before = previous.replace(current);
+                    let after = previous.borrow();
+                    e.nodes.retain(|id| {
+                        is_ancestor(&before, &node, id) || is_ancestor(&after, &node, id)
+                    });
+                    if !e.nodes.is_empty() {
+                        f(txn, &e);
+                    }
+                }
+            });
+        subscription.cast()
+    }
+
+    /// Unsubscribes a previously subscribed event callback identified by given `subscription_id`.
+    pub fn unobserve(&self, subscription_id: SubscriptionId) {
+        if let Some(eh) = self.store().before_cleanup_events.as_mut() {
+            eh.unsubscribe(subscription_id);
+        }
+    }
+
+    fn store(&self) -> StoreRef {
+        let branch: &Branch = self.order.as_ref();
+        branch.store.clone().expect("Tree is not integrated")
+    }
+
+    fn order_ids(&self) -> Vec<Rc<str>> {
+        self.order.iter().filter_map(as_id).collect()
+    }
+
+    /// Returns an index within an order array, at which a new child of a given `parent` should
+    /// be inserted in order to become its `index`-th child. A `skip` node is not counted as one
+    /// of the siblings.
+    fn position(
+        &self,
+        parents: &HashMap<Rc<str>, Rc<str>>,
+        parent: &str,
+        index: u32,
+        skip: Option<&str>,
+    ) -> Result<u32, TreeError> {
+        let order: Vec<_> = self
+            .order_ids()
+            .into_iter()
+            .filter(|id| Some(id.as_ref()) != skip)
+            .collect();
+        let siblings: Vec<usize> = order
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| parents.get(*id).map(Rc::as_ref) == Some(parent))
+            .map(|(i, _)| i)
+            .collect();
+        let index = index as usize;
+        if index < siblings.len() {
+            Ok(siblings[index] as u32)
+        } else if index > siblings.len() {
+            Err(TreeError::OutOfBounds)
+        } else if let Some(last) = siblings.last() {
+            Ok(*last as u32 + 1)
+        } else {
+            Ok(order.len() as u32)
+        }
+    }
+
+    /// Returns an effective parent of every node reachable from a [Tree::ROOT] node. Cycles
+    /// created by concurrent reparenting are broken by attaching the most recently reparented
+    /// node of each cycle to the root. Nodes which ancestors are missing are not returned.
+    fn resolve(&self) -> HashMap<Rc<str>, Rc<str>> {
+        let branch: &Branch = self.parents.as_ref();
+        let mut edges: HashMap<Rc<str>, (Rc<str>, ID)> = HashMap::new();
+        for (node, ptr) in branch.map.iter() {
+            if let Block::Item(item) = ptr.deref() {
+                if !item.is_deleted() {
+                    if let Some(parent) = item.content.get_last().and_then(as_id) {
+                        edges.insert(node.clone(), (parent, item.id));
+                    }
+                }
+            }
+        }
+
+        while let Some(cycle) = find_cycle(&edges) {
+            let last = cycle
+                .into_iter()
+                .max_by_key(|node| {
+                    let id = edges[node].1;
+                    (id.clock, id.client)
+                })
+                .unwrap();
+            edges.get_mut(&last).unwrap().0 = Tree::ROOT.into();
+        }
+
+        let mut result = HashMap::with_capacity(edges.len());
+        for (node, (parent, _)) in edges.iter() {
+            let mut current = parent;
+            while current.as_ref() != Tree::ROOT {
+                match edges.get(current) {
+                    Some((parent, _)) => current = parent,
+                    None => break,
+                }
+            }
+            if current.as_ref() == Tree::ROOT {
+                result.insert(node.clone(), parent.clone());
+            }
+        }
+        result
+    }
+}
+
+/// Checks if a given `node` is the `ancestor` itself or one of its descendants.
+fn is_ancestor(parents: &HashMap<Rc<str>, Rc<str>>, ancestor: &str, node: &str) -> bool {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if node == ancestor {
+            return true;
+        }
+        current = parents.get(node).map(Rc::as_ref);
+    }
+    false
+}
+
+/// Returns nodes of any cycle found in a graph of parent `edges`.
+fn find_cycle(edges: &HashMap<Rc<str>, (Rc<str>, ID)>) -> Option<Vec<Rc<str>>> {
+    let mut visited: HashSet<Rc<str>> = HashSet::new();
+    for start in edges.keys() {
+        let mut path: Vec<Rc<str>> = Vec::new();
+        let mut current = Some(start);
+        while let Some(node) = current {
+            if let Some(pos) = path.iter().position(|n| n == node) {
+                return Some(path.split_off(pos));
+            }
+            if !visited.insert(node.clone()) {
+                break;
+            }
+            path.push(node.clone());
+            current = edges.get(node).map(|(parent, _)| parent);
+        }
+    }
+    None
+}
+
+/// Error returned by [Tree] operations.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeError {
+    /// There was no node with a given identifier in a tree.
+    NodeNotFound(Rc<str>),
+    /// An index was outside of the range of node's children.
+    OutOfBounds,
+    /// A node could not be moved under itself or one of its descendants.
+    Cycle,
+}
+
+impl std::fmt::Display for TreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TreeError::NodeNotFound(node) => write!(f, "tree node '{}' not found", node),
+            TreeError::OutOfBounds => write!(f, "index outside of the node's children range"),
+            TreeError::Cycle => write!(f, "node cannot be moved under its own descendant"),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+/// Event generated by [Tree::observe] and [Tree::observe_subtree] methods. Emitted during
+/// transaction commit phase.
+pub struct TreeEvent {
+    target: Tree,
+    nodes: HashSet<Rc<str>>,
+}
+
+impl TreeEvent {
+    fn new(tree: &Tree, txn: &Transaction) -> Option<Self> {
+        let mut nodes = HashSet::new();
+        for (ptr, keys) in txn.changed.iter() {
+            if same_branch(ptr, &tree.parents) || same_branch(ptr, &tree.values) {
+                nodes.extend(keys.iter().flatten().cloned());
+            } else if same_branch(ptr, &tree.order) {
+                let e = ArrayEvent::new(BranchPtr::from(tree.order.as_ref()));
+                for change in e.delta(txn) {
+                    if let Change::Added(values) = change {
+                        nodes.extend(values.iter().cloned().filter_map(as_id));
+                    }
+                }
+            }
+        }
+        if nodes.is_empty() {
+            None
+        } else {
+            Some(TreeEvent {
+                target: tree.clone(),
+                nodes,
+            })
+        }
+    }
+
+    /// Returns a [Tree] instance which emitted this event.
+    pub fn target(&self) -> &Tree {
+        &self.target
+    }
+
+    /// Returns identifiers of the nodes, which have been created, moved, deleted or had their
+    /// values changed within bounds of current transaction.
+    pub fn nodes(&self) -> &HashSet<Rc<str>> {
+        &self.nodes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::exchange_updates;
+    use crate::types::tree::TreeError;
+    use crate::types::Value;
+    use crate::{Doc, Tree};
+    use lib0::any::Any;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    #[test]
+    fn tree_children_order_and_moves() {
+        let doc = Doc::with_client_id(1);
+        let tree = doc.transact().get_tree("tree");
+        let mut txn = doc.transact();
+        let a = tree.create_node(&mut txn, Tree::ROOT, 0, "a").unwrap();
+        let c = tree.create_node(&mut txn, Tree::ROOT, 1, "c").unwrap();
+        let b = tree.create_node(&mut txn, Tree::ROOT, 1, "b").unwrap();
+        let a1 = tree.create_node(&mut txn, &a, 0, "a1").unwrap();
+        assert_eq!(
+            tree.children(Tree::ROOT),
+            vec![a.clone(), b.clone(), c.clone()]
+        );
+        assert_eq!(tree.children(&a), vec![a1.clone()]);
+        assert_eq!(tree.parent(&a1), Some(a.clone()));
+        assert_eq!(tree.get(&b), Some(Value::from("b")));
+
+        // reorder within the same parent
+        tree.move_node(&mut txn, &c, Tree::ROOT, 0).unwrap();
+        assert_eq!(
+            tree.children(Tree::ROOT),
+            vec![c.clone(), a.clone(), b.clone()]
+        );
+        tree.move_node(&mut txn, &c, Tree::ROOT, 2).unwrap();
+        assert_eq!(
+            tree.children(Tree::ROOT),
+            vec![a.clone(), b.clone(), c.clone()]
+        );
+
+        // reparent
+        tree.move_node(&mut txn, &c, &a, 0).unwrap();
+        tree.move_node(&mut txn, &b, &a, 2).unwrap();
+        assert_eq!(tree.children(Tree::ROOT), vec![a.clone()]);
+        assert_eq!(tree.children(&a), vec![c.clone(), a1.clone(), b.clone()]);
+
+        assert_eq!(tree.move_node(&mut txn, &a, &a1, 0), Err(TreeError::Cycle));
+        assert_eq!(tree.move_node(&mut txn, &a, &a, 0), Err(TreeError::Cycle));
+        assert_eq!(
+            tree.move_node(&mut txn, &a, Tree::ROOT, 5),
+            Err(TreeError::OutOfBounds)
+        );
+        assert_eq!(
+            tree.move_node(&mut txn, "x", Tree::ROOT, 0),
+            Err(TreeError::NodeNotFound("x".into()))
+        );
+
+        tree.set(&mut txn, &c, 1).unwrap();
+        assert_eq!(
+            tree.to_json(),
+            Any::from_json(&format!(
+                r#"[{{"id":"{a}","value":"a","children":[
+                    {{"id":"{c}","value":1,"children":[]}},
+                    {{"id":"{a1}","value":"a1","children":[]}},
+                    {{"id":"{b}","value":"b","children":[]}}
+                ]}}]"#,
+                a = a,
+                b = b,
+                c = c,
+                a1 = a1
+            ))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn tree_delete_subtree() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.transact().get_tree("tree");
+        let t2 = d2.transact().get_tree("tree");
+        let (a, a1, b) = {
+            let mut txn = d1.transact();
+            let a = t1.create_node(&mut txn, Tree::ROOT, 0, "a").unwrap();
+            let a1 = t1.create_node(&mut txn, &a, 0, "a1").unwrap();
+            let b = t1.create_node(&mut txn, Tree::ROOT, 1, "b").unwrap();
+            (a, a1, b)
+        };
+        exchange_updates(&[&d1, &d2]);
+
+        // d1 deletes `a` while d2 concurrently creates a grandchild of `a`
+        t1.delete_node(&mut d1.transact(), &a).unwrap();
+        let a2 = t2.create_node(&mut d2.transact(), &a1, 0, "a2").unwrap();
+        exchange_updates(&[&d1, &d2]);
+
+        for t in [&t1, &t2] {
+            assert_eq!(t.children(Tree::ROOT), vec![b.clone()]);
+            assert!(!t.contains(&a));
+            assert!(!t.contains(&a1));
+            assert!(!t.contains(&a2));
+            assert_eq!(t.get(&a2), None);
+        }
+        assert_eq!(t1.to_json(), t2.to_json());
+    }
+
+    #[test]
+    fn tree_concurrent_reparenting_cycle() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.transact().get_tree("tree");
+        let t2 = d2.transact().get_tree("tree");
+        let (a, b) = {
+            let mut txn = d1.transact();
+            let a = t1.create_node(&mut txn, Tree::ROOT, 0, "a").unwrap();
+            let b = t1.create_node(&mut txn, Tree::ROOT, 1, "b").unwrap();
+            (a, b)
+        };
+        exchange_updates(&[&d1, &d2]);
+
+        // concurrently: d1 moves `a` under `b`, d2 moves `b` under `a`
+        t1.move_node(&mut d1.transact(), &a, &b, 0).unwrap();
+        t2.move_node(&mut d2.transact(), &b, &a, 0).unwrap();
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(t1.to_json(), t2.to_json());
+        assert_eq!(t1.children(Tree::ROOT), t2.children(Tree::ROOT));
+        assert_eq!(t1.children(Tree::ROOT).len(), 1);
+        let top = t1.children(Tree::ROOT).remove(0);
+        let bottom = if top == a { b.clone() } else { a.clone() };
+        assert_eq!(t1.children(&top), vec![bottom.clone()]);
+        assert_eq!(t2.children(&top), vec![bottom.clone()]);
+
+        // cycle is resolved, so nodes can be moved freely again
+        t2.move_node(&mut d2.transact(), &top, &bottom, 0)
+            .unwrap_err();
+        t2.move_node(&mut d2.transact(), &bottom, Tree::ROOT, 0)
+            .unwrap();
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(t1.children(Tree::ROOT), vec![bottom, top]);
+    }
+
+    #[test]
+    fn tree_observe_subtree() {
+        let doc = Doc::with_client_id(1);
+        let tree = doc.transact().get_tree("tree");
+        let (a, b) = {
+            let mut txn = doc.transact();
+            let a = tree.create_node(&mut txn, Tree::ROOT, 0, "a").unwrap();
+            let b = tree.create_node(&mut txn, Tree::ROOT, 1, "b").unwrap();
+            (a, b)
+        };
+
+        let all = Rc::new(RefCell::new(Vec::new()));
+        let subtree = Rc::new(RefCell::new(Vec::new()));
+        let _s1 = {
+            let all = all.clone();
+            tree.observe(move |_, e| all.borrow_mut().push(e.nodes().clone()))
+        };
+        let _s2 = {
+            let subtree = subtree.clone();
+            tree.observe_subtree(&a, move |_, e| subtree.borrow_mut().push(e.nodes().clone()))
+        };
+
+        let set =
+            |ids: &[&Rc<str>]| -> HashSet<Rc<str>> { ids.iter().map(|&id| id.clone()).collect() };
+
+        // changes outside of the subtree
+        tree.set(&mut doc.transact(), &b, "B").unwrap();
+        // moving a node into the subtree
+        tree.move_node(&mut doc.transact(), &b, &a, 0).unwrap();
+        // creating a node within subtree
+        let c = tree.create_node(&mut doc.transact(), &b, 0, "c").unwrap();
+        // deleting the nodes of the subtree
+        tree.delete_node(&mut doc.transact(), &b).unwrap();
+
+        assert_eq!(
+            all.take(),
+            vec![set(&[&b]), set(&[&b]), set(&[&c]), set(&[&b, &c])]
+        );
+        assert_eq!(subtree.take(), vec![set(&[&b]), set(&[&c]), set(&[&b, &c])]);
+    }
+}
@@ -0,0 +1,489 @@
+use crate::block::{Block, Item, ItemContent, Prelim};
+use crate::types::{Branch, BranchPtr, Value, TYPE_REFS_WEAK};
+use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::encoder::{Encode, Encoder};
+use crate::*;
+use lib0::any::Any;
+use lib0::error::Error;
+use std::ops::{Deref, DerefMut};
+
+/// A weak reference to an element or a range of elements of another shared collection: an entry
+/// of a [Map], elements of an [Array] or a fragment of a [Text].
+///
+/// Weak links don't copy the content they point to. Instead they're resolved lazily, every time
+/// they are read, so that they always reflect the current state of a referenced content. Since
+/// links point to the elements themselves rather than to their indexes, they keep referencing
+/// the same elements while they're moved around (see: [Array::move_to]) or while other elements
+/// are inserted or removed around them.
+///
+/// Once all of the linked elements have been deleted (or map entry has been overridden), a link
+/// becomes dead - see: [WeakLink::is_dead].
+///
+/// Weak links are created using [WeakPrelim], which can be obtained with [Map::link],
+/// [Array::quote] or [Text::quote] and then inserted into any other collection.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::Doc;
+/// use yrs::types::Value;
+///
+/// let doc = Doc::new();
+/// let mut txn = doc.transact();
+/// let users = txn.get_map("users");
+/// let refs = txn.get_array("refs");
+/// users.insert(&mut txn, "alice", "Alice Smith");
+///
+/// let prelim = users.link("alice").unwrap();
+/// refs.push_back(&mut txn, prelim);
+///
+/// let link = refs.get(0).unwrap().to_yweak().unwrap();
+/// assert_eq!(link.try_deref_value(), Some(Value::from("Alice Smith")));
+///
+/// users.insert(&mut txn, "alice", "Alice Brown");
+/// assert!(link.is_dead());
+/// ```
+#[repr(transparent)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WeakLink(BranchPtr);
+
+impl WeakLink {
+    /// Returns a source of the elements referenced by current link.
+    pub fn source(&self) -> &LinkSource {
+        self.0
+            .link
+            .as_ref()
+            .expect("WeakLink has no source defined")
+    }
+
+    /// Returns a first linked value, which has not been deleted. This is the main way to read
+    /// links to map entries or single array elements. Returns `None` if current link is dead.
+    pub fn try_deref_value(&self) -> Option<Value> {
+        let mut result = None;
+        self.for_each_quoted(|item, start, end| {
+            if result.is_none() {
+                result = slice_values(&item.content, start, end).into_iter().next();
+            }
+        });
+        result
+    }
+
+    /// Returns all linked values, which have not been deleted, in the order they are stored in
+    /// a source collection. This includes elements that have been inserted in between quoted ones
+    /// after a link was created.
+    pub fn unquote(&self) -> Vec<Value> {
+        let mut result = Vec::new();
+        self.for_each_quoted(|item, start, end| {
+            result.extend(slice_values(&item.content, start, end));
+        });
+        result
+    }
+
+    /// Returns a string content of linked [Text] fragment. Embedded and formatting elements are
+    /// skipped.
+    pub fn get_string(&self) -> String {
+        let mut result = String::new();
+        self.for_each_quoted(|item, start, end| {
+            if let ItemContent::String(s) = &item.content {
+                result.push_str(utf16_slice(s.as_str(), start, end));
+            }
+        });
+        result
+    }
+
+    /// Checks if all elements referenced by current link have been deleted, or if they are not
+    /// (yet) known to a current document.
+    pub fn is_dead(&self) -> bool {
+        let mut dead = true;
+        self.for_each_quoted(|_, _, _| dead = false);
+        dead
+    }
+
+    /// Converts a linked content into JSON-like representation: links to map entries are
+    /// converted into their values (or null if they're dead), links to text fragments into
+    /// strings and links to array elements into arrays.
+    pub fn to_json(&self) -> Any {
+        match self.source_kind() {
+            SourceKind::Entry => self
+                .try_deref_value()
+                .map(Value::to_json)
+                .unwrap_or(Any::Null),
+            SourceKind::Text => Any::String(self.get_string().into_boxed_str()),
+            SourceKind::Elements => {
+                Any::Array(self.unquote().into_iter().map(Value::to_json).collect())
+            }
+        }
+    }
+
+    fn source_kind(&self) -> SourceKind {
+        if let Some(store) = self.0.store.as_ref() {
+            let start = &self.source().quote_start;
+            if let Some(Block::Item(item)) = store.blocks.get_block(start).as_deref() {
+                if item.parent_sub.is_some() {
+                    return SourceKind::Entry;
+                }
+                // parent type may be yet undefined, but only text contains string chunks
+                if let ItemContent::String(_) | ItemContent::Embed(_) = &item.content {
+                    return SourceKind::Text;
+                }
+            }
+        }
+        SourceKind::Elements
+    }
+
+    /// Calls `f` for every non-deleted countable item that is a part of a quoted range, together
+    /// with `start`..`end` range of clock offsets of that item which are being quoted.
+    fn for_each_quoted<F>(&self, mut f: F)
+    where
+        F: FnMut(&Item, u32, u32),
+    {
+        let store = match self.0.store.as_ref() {
+            Some(store) => store,
+            None => return,
+        };
+        let source = self.source();
+        let (start, end) = (&source.quote_start, &source.quote_end);
+        if store.blocks.get_state(&start.client) <= start.clock
+            || store.blocks.get_state(&end.client) <= end.clock
+        {
+            return;
+        }
+        let mut current = store.blocks.get_block(start);
+        let mut offset = match current.as_deref() {
+            Some(Block::Item(item)) => start.clock - item.id.clock,
+            _ => return,
+        };
+        while let Some(Block::Item(item)) = current.as_deref() {
+            let is_last = item.id.client == end.client
+                && item.id.clock <= end.clock
+                && end.clock < item.id.clock + item.len;
+            let limit = if is_last {
+                end.clock - item.id.clock + 1
+            } else {
+                item.len
+            };
+            if !item.is_deleted() && item.is_countable() && offset < limit {
+                f(item, offset, limit);
+            }
+            if is_last {
+                break;
+            }
+            offset = 0;
+            current = item.right;
+        }
+    }
+}
+
+enum SourceKind {
+    Entry,
+    Text,
+    Elements,
+}
+
+/// Returns values of a given `content` found within `start`..`end` range of clock offsets.
+fn slice_values(content: &ItemContent, start: u32, end: u32) -> Vec<Value> {
+    match content {
+        ItemContent::String(s) => utf16_slice(s.as_str(), start, end)
+            .chars()
+            .map(|c| Value::Any(Any::String(c.to_string().into_boxed_str())))
+            .collect(),
+        other => {
+            let mut values = other.get_content();
+            values.truncate(end as usize);
+            values.drain(..(start as usize).min(values.len()));
+            values
+        }
+    }
+}
+
+/// Returns a fragment of a given string found within `start`..`end` range of UTF-16 offsets.
+fn utf16_slice(s: &str, start: u32, end: u32) -> &str {
+    let mut from = s.len();
+    let mut offset = 0;
+    for (i, c) in s.char_indices() {
+        if offset == start {
+            from = i;
+        }
+        if offset >= end {
+            return &s[from..i];
+        }
+        offset += c.len_utf16() as u32;
+    }
+    &s[from.min(s.len())..]
+}
+
+impl AsRef<Branch> for WeakLink {
+    fn as_ref(&self) -> &Branch {
+        self.0.deref()
+    }
+}
+
+impl AsMut<Branch> for WeakLink {
+    fn as_mut(&mut self) -> &mut Branch {
+        self.0.deref_mut()
+    }
+}
+
+impl From<BranchPtr> for WeakLink {
+    fn from(inner: BranchPtr) -> Self {
+        WeakLink(inner)
+    }
+}
+
+/// Identifies a range of elements referenced by a [WeakLink]. Both ends of a range are
+/// inclusive and point to the individual elements rather than to the blocks containing them.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct LinkSource {
+    /// Identifier of a first linked element.
+    pub quote_start: ID,
+    /// Identifier of a last linked element. For single element links it's the same as
+    /// [LinkSource::quote_start].
+    pub quote_end: ID,
+}
+
+impl LinkSource {
+    pub fn new(quote_start: ID, quote_end: ID) -> Self {
+        LinkSource {
+            quote_start,
+            quote_end,
+        }
+    }
+
+    /// Checks if current source points to a single element.
+    pub fn is_single(&self) -> bool {
+        self.quote_start == self.quote_end
+    }
+
+    /// Creates a new [Branch] of a weak link type, which points to current source.
+    pub(crate) fn into_branch(self) -> Box<Branch> {
+        let mut branch = Branch::new(TYPE_REFS_WEAK, None);
+        branch.link = Some(self);
+        branch
+    }
+}
+
+impl Encode for LinkSource {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        let is_single = self.is_single();
+        let flags: u32 = if is_single { 0 } else { 0b0000_0001 };
+        encoder.write_var(flags);
+        encoder.write_var(self.quote_start.client);
+        encoder.write_var(self.quote_start.clock);
+        if !is_single {
+            encoder.write_var(self.quote_end.client);
+            encoder.write_var(self.quote_end.clock);
+        }
+    }
+}
+
+impl Decode for LinkSource {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let flags: u32 = decoder.read_var()?;
+        let start = ID::new(decoder.read_var()?, decoder.read_var()?);
+        let end = if flags & 0b0000_0001 != 0 {
+            ID::new(decoder.read_var()?, decoder.read_var()?)
+        } else {
+            start
+        };
+        Ok(LinkSource::new(start, end))
+    }
+}
+
+/// A preliminary weak link. It can be inserted into any shared collection, which will result in
+/// creating a new [WeakLink] there.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WeakPrelim(LinkSource);
+
+impl WeakPrelim {
+    pub fn new(source: LinkSource) -> Self {
+        WeakPrelim(source)
+    }
+
+    /// Returns a source of the elements referenced by current link.
+    pub fn source(&self) -> &LinkSource {
+        &self.0
+    }
+}
+
+impl Prelim for WeakPrelim {
+    fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
+        (ItemContent::Type(self.0.into_branch()), None)
+    }
+
+    fn integrate(self, _txn: &mut Transaction, _inner_ref: BranchPtr) {}
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::exchange_updates;
+    use crate::types::weak::LinkSource;
+    use crate::types::Value;
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, StateVector, Update, ID};
+    use lib0::any::Any;
+
+    #[test]
+    fn link_map_entry() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let users = d1.transact().get_map("users");
+        let refs = d1.transact().get_map("refs");
+        users.insert(&mut d1.transact(), "alice", "Alice");
+        {
+            let mut txn = d1.transact();
+            let prelim = users.link("alice").unwrap();
+            refs.insert(&mut txn, "owner", prelim);
+        }
+        assert!(users.link("bob").is_none());
+        exchange_updates(&[&d1, &d2]);
+
+        let remote = d2.transact().get_map("refs");
+        let link = remote.get("owner").unwrap().to_yweak().unwrap();
+        assert!(link.source().is_single());
+        assert_eq!(link.try_deref_value(), Some(Value::from("Alice")));
+        assert_eq!(
+            d2.transact().get_map("refs").to_json(),
+            Any::from_json(r#"{"owner":"Alice"}"#).unwrap()
+        );
+
+        // overriding an entry makes a link dead on all peers
+        users.insert(&mut d1.transact(), "alice", "Alicia");
+        exchange_updates(&[&d1, &d2]);
+        assert!(link.is_dead());
+        assert_eq!(link.try_deref_value(), None);
+        assert_eq!(link.to_json(), Any::Null);
+    }
+
+    #[test]
+    fn quote_array_range() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let array = d1.transact().get_array("array");
+        let links = d1.transact().get_array("links");
+        array.insert_range(&mut d1.transact(), 0, [1, 2, 3, 4, 5]);
+        {
+            let mut txn = d1.transact();
+            let prelim = array.quote(&mut txn, 1, 3).unwrap();
+            assert_eq!(
+                prelim.source(),
+                &LinkSource::new(ID::new(1, 1), ID::new(1, 3))
+            );
+            links.push_back(&mut txn, prelim);
+            assert!(array.quote(&mut txn, 4, 2).is_err());
+            assert!(array.quote(&mut txn, 1, u32::MAX).is_err());
+        }
+        let link = links.get(0).unwrap().to_yweak().unwrap();
+        let values = |link: &crate::WeakLink| -> Vec<Any> {
+            link.unquote().into_iter().map(Value::to_json).collect()
+        };
+        assert_eq!(values(&link), vec![2.into(), 3.into(), 4.into()]);
+
+        // concurrent changes: insert inside of a quoted range and delete its first element
+        let array2 = d2.transact().get_array("array");
+        exchange_updates(&[&d1, &d2]);
+        array2.insert(&mut d2.transact(), 2, 10);
+        array.remove(&mut d1.transact(), 1);
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(values(&link), vec![10.into(), 3.into(), 4.into()]);
+
+        let remote = d2.transact().get_array("links").get(0);
+        let remote = remote.unwrap().to_yweak().unwrap();
+        assert_eq!(values(&remote), vec![10.into(), 3.into(), 4.into()]);
+        assert_eq!(remote.to_json(), Any::from_json("[10,3,4]").unwrap());
+
+        array.remove_range(&mut d1.transact(), 1, 3);
+        assert!(link.is_dead());
+        assert!(link.unquote().is_empty());
+    }
+
+    #[test]
+    fn quote_survives_moves() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.transact().get_array("array");
+        let map = doc.transact().get_map("map");
+        array.insert_range(&mut doc.transact(), 0, ["a", "b", "c", "d"]);
+        {
+            let mut txn = doc.transact();
+            let prelim = array.quote(&mut txn, 1, 1).unwrap();
+            map.insert(&mut txn, "link", prelim);
+        }
+        let link = map.get("link").unwrap().to_yweak().unwrap();
+        assert_eq!(link.try_deref_value(), Some(Value::from("b")));
+
+        array.move_to(&mut doc.transact(), 1, 4);
+        array.insert(&mut doc.transact(), 0, "x");
+        assert_eq!(
+            array.to_json(),
+            Any::from_json(r#"["x","a","c","d","b"]"#).unwrap()
+        );
+        assert_eq!(link.try_deref_value(), Some(Value::from("b")));
+    }
+
+    #[test]
+    fn quote_text() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.transact().get_text("text");
+        let map = d1.transact().get_map("map");
+        text.insert(&mut d1.transact(), 0, "hello wörld😀!");
+        {
+            let mut txn = d1.transact();
+            let prelim = text.quote(&mut txn, 6, 10).unwrap();
+            map.insert(&mut txn, "quote", prelim);
+            assert!(text.quote(&mut txn, 10, 20).is_err());
+            assert!(text.quote(&mut txn, 1, u32::MAX).is_err());
+        }
+        let link = map.get("quote").unwrap().to_yweak().unwrap();
+        assert_eq!(link.get_string(), "wörld😀");
+
+        text.insert(&mut d1.transact(), 9, "--");
+        text.insert(&mut d1.transact(), 0, ">> ");
+        assert_eq!(link.get_string(), "wö--rld😀");
+
+        // weak links are preserved in encoded updates
+        let update = d1.encode_state_as_update_v2(&StateVector::default());
+        let d2 = Doc::with_client_id(2);
+        d2.transact()
+            .apply_update(Update::decode_v2(&update).unwrap());
+        let remote = d2.transact().get_map("map").get("quote");
+        let remote = remote.unwrap().to_yweak().unwrap();
+        assert_eq!(remote.source(), link.source());
+        assert_eq!(remote.to_json(), Any::String("wö--rld😀".into()));
+    }
+
+    #[test]
+    fn weak_link_update_formats() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.transact().get_array("array");
+        array.insert_range(&mut doc.transact(), 0, [1, 2, 3]);
+        {
+            let mut txn = doc.transact();
+            let prelim = array.quote(&mut txn, 0, 2).unwrap();
+            array.push_back(&mut txn, prelim);
+        }
+        let v1 = doc.encode_state_as_update_v1(&StateVector::default());
+        let update = Update::decode_v1(&v1).unwrap();
+        #[cfg(feature = "protobuf")]
+        {
+            let proto = Update::decode_protobuf(&update.encode_protobuf()).unwrap();
+            assert_eq!(proto.encode_v1(), v1);
+        }
+        let json = Update::from_json(&update.to_json()).unwrap();
+        assert_eq!(json.encode_v1(), v1);
+
+        let remote = Doc::with_client_id(2);
+        remote.transact().apply_update(json);
+        let array = remote.transact().get_array("array");
+        assert_eq!(array.to_json(), Any::from_json("[1,2,3,[1,2]]").unwrap());
+    }
+
+    #[test]
+    fn link_source_encoding() {
+        let single = LinkSource::new(ID::new(1, 2), ID::new(1, 2));
+        let range = LinkSource::new(ID::new(1, 2), ID::new(3, 4));
+        for source in [single, range] {
+            let bytes = source.encode_v1();
+            assert_eq!(LinkSource::decode_v1(&bytes).unwrap(), source);
+        }
+    }
+}
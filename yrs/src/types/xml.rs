@@ -1,21 +1,25 @@
 use crate::block::{Block, Item, ItemContent, ItemPosition, Prelim};
 use crate::block_store::Snapshot;
 use crate::event::Subscription;
-use crate::types::text::{Diff, TextEvent, YChange};
+use crate::types::text::{Attribution, Diff, TextEvent, YChange};
+use crate::types::xml_parser;
+use crate::types::xml_parser::{XmlNode, XmlParser};
 use crate::types::{
     event_change_set, event_keys, Attrs, Branch, BranchPtr, Change, ChangeSet, Delta, Entries,
     EntryChange, Map, Observers, Path, Text, TypePtr, Value, TYPE_REFS_XML_ELEMENT,
     TYPE_REFS_XML_FRAGMENT, TYPE_REFS_XML_TEXT,
 };
-use crate::{SubscriptionId, Transaction, ID};
+use crate::{Cursor, OffsetUnit, OutOfBounds, SubscriptionId, Transaction, ID};
 use lib0::any::Any;
 use std::cell::UnsafeCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryInto;
 use std::fmt::Write;
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
 
+pub use crate::types::xml_parser::XmlParseError;
+
 /// An return type from XML elements retrieval methods. It's an enum of all supported values, that
 /// can be nested inside of [XmlElement]. These are other [XmlElement]s or [XmlText] values.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -57,6 +61,19 @@ impl From<BranchPtr> for Xml {
     }
 }
 
+/// Error returned when a reference node passed to XML insertion methods, like
+/// [XmlElement::try_insert_elem_after], is not a child of an XML node it's being inserted into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotAChild;
+
+impl std::fmt::Display for NotAChild {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "reference node is not a child of a current XML fragment")
+    }
+}
+
+impl std::error::Error for NotAChild {}
+
 /// XML element data type. It represents an XML node, which can contain key-value attributes
 /// (interpreted as strings) as well as other nested XML elements or rich text (represented by
 /// [XmlText] type).
@@ -80,27 +97,25 @@ impl XmlElement {
     }
 
     /// Converts current XML node into a textual representation. This representation if flat, it
-    /// doesn't include any indentation.
+    /// doesn't include any indentation. Just like in Yjs, attributes are sorted by their names,
+    /// while text content and attribute values are not escaped (see: [XmlElement::to_xml_string]).
     pub fn to_string(&self) -> String {
-        let inner = self.inner();
         let mut s = String::new();
-        let tag = inner
-            .name
-            .as_ref()
-            .map(|s| s.as_ref())
-            .unwrap_or(&"UNDEFINED");
-        write!(&mut s, "<{}", tag).unwrap();
-        let attributes = Attributes(inner.entries());
-        for (k, v) in attributes {
-            write!(&mut s, " \"{}\"=\"{}\"", k, v).unwrap();
-        }
-        write!(&mut s, ">").unwrap();
-        for i in inner.iter() {
-            for content in i.content.get_content() {
-                write!(&mut s, "{}", content.to_string()).unwrap();
-            }
-        }
-        write!(&mut s, "</{}>", tag).unwrap();
+        write_element(&mut s, self.inner(), false);
+        s
+    }
+
+    /// Converts current XML node into a JSON-like string value (see: [XmlElement::to_string]).
+    pub fn to_json(&self) -> Any {
+        Any::String(self.to_string().into_boxed_str())
+    }
+
+    /// Converts current XML node into a well-formed XML string. Unlike [XmlElement::to_string],
+    /// characters with special meaning in XML found in text content and attribute values are
+    /// escaped.
+    pub fn to_xml_string(&self) -> String {
+        let mut s = String::new();
+        write_element(&mut s, self.inner(), true);
         s
     }
 
@@ -147,8 +162,8 @@ impl XmlElement {
         Some(value.to_string())
     }
 
-    /// Returns an unordered iterator over all attributes (key-value pairs), that can be found
-    /// inside of a current XML element.
+    /// Returns an iterator over all attributes (key-value pairs), that can be found inside of
+    /// a current XML element. Attributes are ordered just like the entries of a [Map::iter].
     pub fn attributes(&self) -> Attributes {
         Attributes(self.0 .0.entries())
     }
@@ -240,6 +255,18 @@ impl XmlElement {
         self.0.insert_elem(txn, index, name)
     }
 
+    /// Inserts another [XmlElement] with a given tag `name` into a current one at the given `index`
+    /// and returns it. Returns an [OutOfBounds] error if `index` is greater than the length of
+    /// current XML element.
+    pub fn try_insert_elem<S: Into<Rc<str>>>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        name: S,
+    ) -> Result<XmlElement, OutOfBounds> {
+        self.0.try_insert_elem(txn, index, name)
+    }
+
     /// Inserts a [XmlText] into a current XML element at the given `index` and returns it.
     /// If `index` is equal to `0`, new text field will be inserted as a first child.
     /// If `index` is equal to length of current XML element, new text field will be inserted
@@ -249,6 +276,73 @@ impl XmlElement {
         self.0.insert_text(txn, index)
     }
 
+    /// Inserts a [XmlText] into a current XML element at the given `index` and returns it.
+    /// Returns an [OutOfBounds] error if `index` is greater than the length of current XML
+    /// element.
+    pub fn try_insert_text(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+    ) -> Result<XmlText, OutOfBounds> {
+        self.0.try_insert_text(txn, index)
+    }
+
+    /// Inserts another [XmlElement] with a given tag `name` right after a `prev` child node of
+    /// a current XML element and returns it. If `prev` is `None`, new element will be inserted as
+    /// a first child.
+    /// This method will panic if `prev` is not a child of a current XML element.
+    pub fn insert_elem_after<S: Into<Rc<str>>>(
+        &self,
+        txn: &mut Transaction,
+        prev: Option<&Xml>,
+        name: S,
+    ) -> XmlElement {
+        self.0.insert_elem_after(txn, prev, name)
+    }
+
+    /// Inserts another [XmlElement] with a given tag `name` right after a `prev` child node of
+    /// a current XML element and returns it. Returns a [NotAChild] error if `prev` is not a child
+    /// of a current XML element.
+    pub fn try_insert_elem_after<S: Into<Rc<str>>>(
+        &self,
+        txn: &mut Transaction,
+        prev: Option<&Xml>,
+        name: S,
+    ) -> Result<XmlElement, NotAChild> {
+        self.0.try_insert_elem_after(txn, prev, name)
+    }
+
+    /// Inserts a [XmlText] right after a `prev` child node of a current XML element and returns
+    /// it. If `prev` is `None`, new text will be inserted as a first child.
+    /// This method will panic if `prev` is not a child of a current XML element.
+    pub fn insert_text_after(&self, txn: &mut Transaction, prev: Option<&Xml>) -> XmlText {
+        self.0.insert_text_after(txn, prev)
+    }
+
+    /// Inserts a [XmlText] right after a `prev` child node of a current XML element and returns
+    /// it. Returns a [NotAChild] error if `prev` is not a child of a current XML element.
+    pub fn try_insert_text_after(
+        &self,
+        txn: &mut Transaction,
+        prev: Option<&Xml>,
+    ) -> Result<XmlText, NotAChild> {
+        self.0.try_insert_text_after(txn, prev)
+    }
+
+    /// Parses a given `xml` string and inserts all of its top-level nodes as children of
+    /// a current XML element, starting at a given `index`. Returns a number of inserted top-level
+    /// nodes. Nothing is inserted if `xml` is not well-formed.
+    ///
+    /// This method will panic if `index` is greater than the length of current XML element.
+    pub fn insert_xml(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        xml: &str,
+    ) -> Result<u32, XmlParseError> {
+        self.0.insert_xml(txn, index, xml)
+    }
+
     /// Removes a range (defined by `len`) of XML nodes from the current XML element, starting at
     /// the given `index`. Returns the result which may contain an error if a number of elements
     /// removed is lesser than the expected one provided in `len` parameter.
@@ -256,6 +350,18 @@ impl XmlElement {
         self.0.remove(txn, index, len)
     }
 
+    /// Removes a range (defined by `len`) of XML nodes from the current XML element, starting at
+    /// the given `index`. Returns an [OutOfBounds] error if requested range doesn't fit within
+    /// the bounds of current XML element. Bounds are checked before any node is removed.
+    pub fn try_remove_range(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<(), OutOfBounds> {
+        self.0.try_remove(txn, index, len)
+    }
+
     /// Pushes a new [XmlElement] with a given tag `name` as the last child of a current one and
     /// returns it.
     pub fn push_elem_back<S: Into<Rc<str>>>(&self, txn: &mut Transaction, name: S) -> XmlElement {
@@ -384,15 +490,74 @@ impl XmlFragment {
         TreeWalker::new(&self.0)
     }
 
+    /// Returns an iterator that enables a deep traversal of this XML fragment - starting from its
+    /// first child and going in depth-first manner over all of the nested XML nodes in document
+    /// order. Use [Iterator::filter] to narrow down visited nodes, eg. to find all elements with
+    /// a given tag name.
+    pub fn successors(&self) -> TreeWalker {
+        TreeWalker::new(&self.0)
+    }
+
+    /// Converts all child nodes of current XML fragment into a textual representation, without
+    /// escaping (see: [XmlElement::to_string]).
     pub fn to_string(&self) -> String {
         let mut s = String::new();
-        let inner = self.inner();
-        for i in inner.iter() {
-            for content in i.content.get_content() {
-                write!(&mut s, "{}", content.to_string()).unwrap();
+        write_children(&mut s, self.inner(), false);
+        s
+    }
+
+    /// Converts all child nodes of current XML fragment into a JSON-like string value (see:
+    /// [XmlFragment::to_string]).
+    pub fn to_json(&self) -> Any {
+        Any::String(self.to_string().into_boxed_str())
+    }
+
+    /// Converts all child nodes of current XML fragment into a well-formed XML string (see:
+    /// [XmlElement::to_xml_string]).
+    pub fn to_xml_string(&self) -> String {
+        let mut s = String::new();
+        write_children(&mut s, self.inner(), true);
+        s
+    }
+
+    /// Parses a given `xml` string and inserts all of its top-level nodes into a current XML
+    /// fragment, starting at a given `index`. Returns a number of inserted top-level nodes.
+    /// Nothing is inserted if `xml` is not well-formed.
+    ///
+    /// This method will panic if `index` is greater than the length of current XML fragment.
+    pub fn insert_xml(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        xml: &str,
+    ) -> Result<u32, XmlParseError> {
+        let nodes = XmlParser::new(xml).parse()?;
+        for (i, node) in nodes.iter().enumerate() {
+            self.insert_node(txn, index + i as u32, node);
+        }
+        Ok(nodes.len() as u32)
+    }
+
+    fn insert_node(&self, txn: &mut Transaction, index: u32, node: &XmlNode) {
+        match node {
+            XmlNode::Text(text) => {
+                let txt = self.insert_text(txn, index);
+                txt.push(txn, text);
+            }
+            XmlNode::Element {
+                tag,
+                attributes,
+                children,
+            } => {
+                let elem = self.insert_elem(txn, index, tag.as_str());
+                for (name, value) in attributes {
+                    elem.insert_attribute(txn, name.as_str(), value);
+                }
+                for (i, child) in children.iter().enumerate() {
+                    elem.0.insert_node(txn, i as u32, child);
+                }
             }
         }
-        s
     }
 
     pub fn insert_elem<S: Into<Rc<str>>>(
@@ -401,20 +566,133 @@ impl XmlFragment {
         index: u32,
         name: S,
     ) -> XmlElement {
-        let ptr = self.0.insert_at(txn, index, PrelimXml::Elem(name.into()));
+        XmlElement::from(self.insert_node_at(txn, index, PrelimXml::Elem(name.into())))
+    }
+
+    /// Inserts a new [XmlElement] with a given tag `name` at the given `index` and returns it.
+    /// Returns an [OutOfBounds] error if `index` is greater than the length of current XML
+    /// fragment.
+    pub fn try_insert_elem<S: Into<Rc<str>>>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        name: S,
+    ) -> Result<XmlElement, OutOfBounds> {
+        if index > self.len() {
+            return Err(OutOfBounds);
+        }
+        Ok(self.insert_elem(txn, index, name))
+    }
+
+    pub fn insert_text(&self, txn: &mut Transaction, index: u32) -> XmlText {
+        XmlText::from(self.insert_node_at(txn, index, PrelimXml::Text))
+    }
+
+    /// Inserts a new [XmlText] at the given `index` and returns it. Returns an [OutOfBounds]
+    /// error if `index` is greater than the length of current XML fragment.
+    pub fn try_insert_text(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+    ) -> Result<XmlText, OutOfBounds> {
+        if index > self.len() {
+            return Err(OutOfBounds);
+        }
+        Ok(self.insert_text(txn, index))
+    }
+
+    fn insert_node_at(&self, txn: &mut Transaction, index: u32, value: PrelimXml) -> BranchPtr {
+        let ptr = self.0.insert_at(txn, index, value);
         let item = ptr.as_item().unwrap();
         if let ItemContent::Type(inner) = &item.content {
-            XmlElement::from(BranchPtr::from(inner))
+            BranchPtr::from(inner)
         } else {
             panic!("Defect: inserted XML element returned primitive value block")
         }
     }
 
-    pub fn insert_text(&self, txn: &mut Transaction, index: u32) -> XmlText {
-        let ptr = self.0.insert_at(txn, index, PrelimXml::Text);
-        let item = ptr.as_item().unwrap();
-        if let ItemContent::Type(inner) = &item.content {
-            XmlText::from(BranchPtr::from(inner))
+    /// Inserts a new [XmlElement] with a given tag `name` right after a `prev` child node of
+    /// a current XML fragment and returns it. If `prev` is `None`, new element will be inserted
+    /// as a first child.
+    /// This method will panic if `prev` is not a child of a current XML fragment.
+    pub fn insert_elem_after<S: Into<Rc<str>>>(
+        &self,
+        txn: &mut Transaction,
+        prev: Option<&Xml>,
+        name: S,
+    ) -> XmlElement {
+        match self.try_insert_elem_after(txn, prev, name) {
+            Ok(elem) => elem,
+            Err(e) => panic!("Cannot insert XML node: {}", e),
+        }
+    }
+
+    /// Inserts a new [XmlElement] with a given tag `name` right after a `prev` child node of
+    /// a current XML fragment and returns it. Returns a [NotAChild] error if `prev` is not a child
+    /// of a current XML fragment.
+    pub fn try_insert_elem_after<S: Into<Rc<str>>>(
+        &self,
+        txn: &mut Transaction,
+        prev: Option<&Xml>,
+        name: S,
+    ) -> Result<XmlElement, NotAChild> {
+        let inner = self.insert_after(txn, prev, PrelimXml::Elem(name.into()))?;
+        Ok(XmlElement::from(inner))
+    }
+
+    /// Inserts a new [XmlText] right after a `prev` child node of a current XML fragment and
+    /// returns it. If `prev` is `None`, new text will be inserted as a first child.
+    /// This method will panic if `prev` is not a child of a current XML fragment.
+    pub fn insert_text_after(&self, txn: &mut Transaction, prev: Option<&Xml>) -> XmlText {
+        match self.try_insert_text_after(txn, prev) {
+            Ok(text) => text,
+            Err(e) => panic!("Cannot insert XML node: {}", e),
+        }
+    }
+
+    /// Inserts a new [XmlText] right after a `prev` child node of a current XML fragment and
+    /// returns it. Returns a [NotAChild] error if `prev` is not a child of a current XML fragment.
+    pub fn try_insert_text_after(
+        &self,
+        txn: &mut Transaction,
+        prev: Option<&Xml>,
+    ) -> Result<XmlText, NotAChild> {
+        let inner = self.insert_after(txn, prev, PrelimXml::Text)?;
+        Ok(XmlText::from(inner))
+    }
+
+    fn insert_after(
+        &self,
+        txn: &mut Transaction,
+        prev: Option<&Xml>,
+        value: PrelimXml,
+    ) -> Result<BranchPtr, NotAChild> {
+        let inner = self.inner();
+        let (left, right) = match prev {
+            None => (None, inner.start),
+            Some(prev) => {
+                let prev = match prev {
+                    Xml::Element(e) => e.inner(),
+                    Xml::Text(t) => t.inner(),
+                };
+                match prev.item.as_deref() {
+                    Some(Block::Item(item)) if item.parent == TypePtr::Branch(inner) => {
+                        (prev.item, item.right)
+                    }
+                    _ => return Err(NotAChild),
+                }
+            }
+        };
+        let pos = ItemPosition {
+            parent: TypePtr::Branch(inner),
+            left,
+            right,
+            index: 0,
+            current_attrs: None,
+        };
+        let ptr = txn.create_item(&pos, value, None);
+        if let ItemContent::Type(inner) = &ptr.as_item().unwrap().content {
+            Ok(BranchPtr::from(inner))
         } else {
             panic!("Defect: inserted XML element returned primitive value block")
         }
@@ -427,6 +705,24 @@ impl XmlFragment {
         }
     }
 
+    /// Removes a range of `len` child nodes, starting at the given `index`. Returns an
+    /// [OutOfBounds] error if requested range doesn't fit within the bounds of current XML
+    /// fragment. Bounds are checked before any node is removed.
+    pub fn try_remove(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<(), OutOfBounds> {
+        match index.checked_add(len) {
+            Some(end) if end <= self.len() => {
+                self.remove(txn, index, len);
+                Ok(())
+            }
+            _ => Err(OutOfBounds),
+        }
+    }
+
     pub fn push_elem_back<S: Into<Rc<str>>>(&self, txn: &mut Transaction, name: S) -> XmlElement {
         let len = self.len();
         self.insert_elem(txn, len, name)
@@ -474,6 +770,12 @@ impl XmlFragment {
     }
 }
 
+impl From<BranchPtr> for XmlFragment {
+    fn from(inner: BranchPtr) -> Self {
+        XmlFragment(inner)
+    }
+}
+
 impl AsRef<Branch> for XmlFragment {
     fn as_ref(&self) -> &Branch {
         self.0.deref()
@@ -519,6 +821,7 @@ impl<'a> Iterator for TreeWalker<'a> {
         if let Some(current) = n {
             if !self.first_call || current.is_deleted() {
                 while {
+                    let current = n.unwrap();
                     if let ItemContent::Type(t) = &current.content {
                         let inner = t.as_ref();
                         let type_ref = inner.type_ref();
@@ -653,9 +956,78 @@ impl XmlText {
         self.0.inner()
     }
 
-    /// Returns a string representation of a current XML text.
+    /// Returns a string representation of a current XML text. Just like in Yjs, formatted chunks
+    /// of text are wrapped with XML nodes named after their formatting attributes, eg. text
+    /// formatted with `{ "b": {} }` is returned as `<b>text</b>`. If formatting attribute value
+    /// is a map, its entries are used as XML node attributes.
     pub fn to_string(&self) -> String {
-        self.0.to_string()
+        let mut s = String::new();
+        self.write_string(&mut s, false);
+        s
+    }
+
+    /// Converts current XML text into a JSON-like string value (see: [XmlText::to_string]).
+    pub fn to_json(&self) -> Any {
+        Any::String(self.to_string().into_boxed_str())
+    }
+
+    /// Returns a well-formed XML representation of a current XML text. Unlike
+    /// [XmlText::to_string], characters with special meaning in XML are escaped.
+    pub fn to_xml_string(&self) -> String {
+        let mut s = String::new();
+        self.write_string(&mut s, true);
+        s
+    }
+
+    fn write_string(&self, s: &mut String, escape: bool) {
+        let mut attrs: BTreeMap<Rc<str>, Any> = BTreeMap::new();
+        let mut chunk = String::new();
+        let mut start = self.inner().start;
+        while let Some(Block::Item(item)) = start.as_deref() {
+            if !item.is_deleted() {
+                match &item.content {
+                    ItemContent::String(str) => chunk.push_str(str),
+                    ItemContent::Format(key, value) => {
+                        Self::write_chunk(s, &attrs, &chunk, escape);
+                        chunk.clear();
+                        if let Any::Null = value.as_ref() {
+                            attrs.remove(key);
+                        } else {
+                            attrs.insert(key.clone(), value.as_ref().clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            start = item.right;
+        }
+        Self::write_chunk(s, &attrs, &chunk, escape);
+    }
+
+    fn write_chunk(s: &mut String, attrs: &BTreeMap<Rc<str>, Any>, chunk: &str, escape: bool) {
+        if chunk.is_empty() {
+            return;
+        }
+        for (node_name, value) in attrs.iter() {
+            write!(s, "<{}", node_name).unwrap();
+            if let Any::Map(map) = value {
+                let mut node_attrs: Vec<_> = map.iter().collect();
+                node_attrs.sort_by(|a, b| a.0.cmp(b.0));
+                for (key, value) in node_attrs {
+                    write!(s, " {}=\"", key).unwrap();
+                    match value {
+                        Any::String(value) => write_escaped(s, value, escape),
+                        value => write_escaped(s, &value.to_string(), escape),
+                    }
+                    s.push('"');
+                }
+            }
+            s.push('>');
+        }
+        write_escaped(s, chunk, escape);
+        for node_name in attrs.keys().rev() {
+            write!(s, "</{}>", node_name).unwrap();
+        }
     }
 
     pub fn remove_attribute(&self, txn: &mut Transaction, attr_name: &str) {
@@ -712,26 +1084,58 @@ impl XmlText {
         parent(self.inner())
     }
 
+    /// Returns a [Cursor] pointing at the beginning of a current XML text, which can be used to
+    /// perform a sequence of reads and edits at nearby positions within a scope of a given
+    /// transaction.
+    pub fn cursor<'txn>(&self, txn: &'txn mut Transaction) -> Cursor<'txn> {
+        self.0.cursor(txn)
+    }
+
     /// Returns a number of characters contained under this XML text structure.
     pub fn len(&self) -> u32 {
         self.0.len()
     }
 
+    /// Returns a length of this XML text expressed in a given `unit`. See [Text::len_in].
+    pub fn len_in(&self, unit: OffsetUnit) -> u32 {
+        self.0.len_in(unit)
+    }
+
+    /// Converts an `index` expressed in `from` units into an index expressed in `to` units.
+    /// See [Text::convert_offset] for details.
+    pub fn convert_offset(&self, index: u32, from: OffsetUnit, to: OffsetUnit) -> Option<u32> {
+        self.0.convert_offset(index, from, to)
+    }
+
+    /// Returns authorship information of this XML text content. See [Text::attribution].
+    pub fn attribution(&self, txn: &Transaction) -> Vec<Attribution> {
+        self.0.attribution(txn)
+    }
+
     /// Inserts a `chunk` of text at a given `index`.
     /// If `index` is `0`, this `chunk` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
     /// the end of it.
     ///
     /// This method will panic if provided `index` is greater than the length of a current text.
+    /// See [XmlText::try_insert] for a non-panicking alternative.
     pub fn insert(&self, txn: &mut Transaction, index: u32, content: &str) {
-        if let Some(mut pos) = self.0.find_position(txn, index) {
-            pos.parent = TypePtr::Branch(self.inner());
-            txn.create_item(&pos, crate::block::PrelimString(content.into()), None);
-        } else {
+        if self.try_insert(txn, index, content).is_err() {
             panic!("Cannot insert string content into an XML text: provided index is outside of the current text range!");
         }
     }
 
+    /// Inserts a `chunk` of text at a given `index`. Returns an [OutOfBounds] error if provided
+    /// `index` is greater than the length of a current text. See [Text::try_insert].
+    pub fn try_insert(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        content: &str,
+    ) -> Result<(), OutOfBounds> {
+        self.0.try_insert(txn, index, content)
+    }
+
     /// Inserts a `chunk` of text at a given `index`.
     /// If `index` is `0`, this `chunk` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
@@ -750,12 +1154,54 @@ impl XmlText {
         self.0.insert_with_attributes(txn, index, content, attrs);
     }
 
+    /// Inserts a `chunk` of text wrapped with formatting `attrs` at a given `index`. Returns an
+    /// [OutOfBounds] error if provided `index` is greater than the length of a current text. See
+    /// [Text::try_insert_with_attributes].
+    pub fn try_insert_with_attributes(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        content: &str,
+        attrs: Attrs,
+    ) -> Result<(), OutOfBounds> {
+        self.0
+            .try_insert_with_attributes(txn, index, content, attrs)
+    }
+
     /// Wraps an existing piece of text within a range described by `index`-`len` parameters with
     /// formatting blocks containing provided `attributes` metadata.
     pub fn format(&self, txn: &mut Transaction, index: u32, len: u32, attrs: Attrs) {
         self.0.format(txn, index, len, attrs);
     }
 
+    /// Wraps an existing piece of text within a range described by `index`-`len` parameters with
+    /// formatting blocks. Returns an [OutOfBounds] error if requested range doesn't fit within
+    /// the bounds of a text. See [Text::try_format].
+    pub fn try_format(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+        attrs: Attrs,
+    ) -> Result<(), OutOfBounds> {
+        self.0.try_format(txn, index, len, attrs)
+    }
+
+    /// Applies a sequence of changes on top of the current text contents in a single pass. See
+    /// [Text::apply_delta] for details.
+    pub fn apply_delta<I>(&self, txn: &mut Transaction, delta: I)
+    where
+        I: IntoIterator<Item = Delta>,
+    {
+        self.0.apply_delta(txn, delta)
+    }
+
+    /// Replaces the contents of a current text with `new_content`, applying only the spans that
+    /// differ. See [Text::set_string_diff] for details.
+    pub fn set_string_diff(&self, txn: &mut Transaction, new_content: &str) {
+        self.0.set_string_diff(txn, new_content)
+    }
+
     /// Inserts an embed `content` at a given `index`.
     ///
     /// If `index` is `0`, this `content` will be inserted at the beginning of a current text.
@@ -763,10 +1209,21 @@ impl XmlText {
     /// the end of it.
     ///
     /// This method will panic if provided `index` is greater than the length of a current text.
-    pub fn insert_embed(&self, txn: &mut Transaction, index: u32, content: Any) {
+    pub fn insert_embed<V: Prelim>(&self, txn: &mut Transaction, index: u32, content: V) {
         self.0.insert_embed(txn, index, content)
     }
 
+    /// Inserts an embed `content` at a given `index`. Returns an [OutOfBounds] error if provided
+    /// `index` is greater than the length of a current text. See [Text::try_insert_embed].
+    pub fn try_insert_embed<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        content: V,
+    ) -> Result<(), OutOfBounds> {
+        self.0.try_insert_embed(txn, index, content)
+    }
+
     /// Inserts an embed `content` of text at a given `index`.
     /// If `index` is `0`, this `content` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
@@ -775,17 +1232,31 @@ impl XmlText {
     /// a formatting blocks.
     ///
     /// This method will panic if provided `index` is greater than the length of a current text.
-    pub fn insert_embed_with_attributes(
+    pub fn insert_embed_with_attributes<V: Prelim>(
         &self,
         txn: &mut Transaction,
         index: u32,
-        content: Any,
+        content: V,
         attributes: Attrs,
     ) {
         self.0
             .insert_embed_with_attributes(txn, index, content, attributes)
     }
 
+    /// Inserts an embed `content` wrapped with formatting `attributes` at a given `index`. Returns
+    /// an [OutOfBounds] error if provided `index` is greater than the length of a current text.
+    /// See [Text::try_insert_embed_with_attributes].
+    pub fn try_insert_embed_with_attributes<V: Prelim>(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        content: V,
+        attributes: Attrs,
+    ) -> Result<(), OutOfBounds> {
+        self.0
+            .try_insert_embed_with_attributes(txn, index, content, attributes)
+    }
+
     /// Appends a new string `content` at the end of this XML text structure.
     pub fn push(&self, txn: &mut Transaction, content: &str) {
         let len = self.len();
@@ -799,6 +1270,18 @@ impl XmlText {
         self.0.remove_range(txn, index, len)
     }
 
+    /// Removes a number of characters specified by a `len` parameter from this XML text structure,
+    /// starting at given `index`. Returns an [OutOfBounds] error if requested range doesn't fit
+    /// within the bounds of a text. See [Text::try_remove_range].
+    pub fn try_remove_range(
+        &self,
+        txn: &mut Transaction,
+        index: u32,
+        len: u32,
+    ) -> Result<(), OutOfBounds> {
+        self.0.try_remove_range(txn, index, len)
+    }
+
     pub fn diff<T, F>(&self, txn: &mut Transaction, compute_ychange: F) -> Vec<Diff<T>>
     where
         F: Fn(YChange) -> T,
@@ -987,6 +1470,45 @@ fn prev_sibling(inner: BranchPtr) -> Option<Xml> {
     None
 }
 
+fn write_element(s: &mut String, inner: BranchPtr, escape: bool) {
+    let tag = inner.name.as_deref().unwrap_or("UNDEFINED");
+    write!(s, "<{}", tag).unwrap();
+    let mut attributes: Vec<_> = Attributes(inner.entries()).collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    for (k, v) in attributes {
+        write!(s, " {}=\"", k).unwrap();
+        write_escaped(s, &v, escape);
+        s.push('"');
+    }
+    s.push('>');
+    write_children(s, inner, escape);
+    write!(s, "</{}>", tag).unwrap();
+}
+
+fn write_children(s: &mut String, inner: BranchPtr, escape: bool) {
+    for item in inner.iter() {
+        if item.is_deleted() {
+            continue;
+        }
+        if let ItemContent::Type(branch) = &item.content {
+            let branch = BranchPtr::from(branch);
+            if branch.type_ref() == TYPE_REFS_XML_TEXT {
+                XmlText::from(branch).write_string(s, escape);
+            } else {
+                write_element(s, branch, escape);
+            }
+        }
+    }
+}
+
+fn write_escaped(s: &mut String, value: &str, escape: bool) {
+    if escape {
+        xml_parser::escape(value, s);
+    } else {
+        s.push_str(value);
+    }
+}
+
 fn parent(inner: BranchPtr) -> Option<XmlElement> {
     let block = inner.item?;
     let item = block.as_item()?;
@@ -1076,11 +1598,13 @@ impl XmlEvent {
 
 #[cfg(test)]
 mod test {
-    use crate::types::xml::Xml;
-    use crate::types::{Change, EntryChange, Value};
+    use crate::types::xml::{NotAChild, Xml, XmlElement, XmlFragment};
+    use crate::types::{
+        Attrs, Change, DeepObservable, Delta, EntryChange, Event, Path, PathSegment, Value,
+    };
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encoder, EncoderV1};
-    use crate::{Doc, StateVector, Update};
+    use crate::{Doc, OutOfBounds, StateVector, Update};
     use lib0::any::Any;
     use std::cell::RefCell;
     use std::collections::HashMap;
@@ -1135,6 +1659,57 @@ mod test {
         assert_eq!(actual[1], p2, "query selector found 2nd paragraph");
     }
 
+    #[test]
+    fn fragment_tree_walker() {
+        let d1 = Doc::with_client_id(1);
+        let mut t1 = d1.transact();
+        /*
+            <p>{txt}<b></b></p>
+            <div><p></p><span></span></div>
+            <p></p>
+        */
+        let root = t1.get_xml_fragment("fragment");
+        let p1 = root.push_elem_back(&mut t1, "p");
+        p1.push_text_back(&mut t1).push(&mut t1, "hello");
+        p1.push_elem_back(&mut t1, "b");
+        let div = root.push_elem_back(&mut t1, "div");
+        div.push_elem_back(&mut t1, "p");
+        div.push_elem_back(&mut t1, "span");
+        root.push_elem_back(&mut t1, "p");
+
+        let tags = |root: &XmlFragment| -> Vec<String> {
+            root.successors()
+                .map(|n| match n {
+                    Xml::Element(e) => e.tag().to_string(),
+                    Xml::Text(t) => t.to_string(),
+                })
+                .collect()
+        };
+        assert_eq!(
+            tags(&root),
+            vec!["p", "hello", "b", "div", "p", "span", "p"]
+        );
+
+        // filtering doesn't prevent from visiting children of skipped nodes
+        let paragraphs = root
+            .successors()
+            .filter(|n| matches!(n, Xml::Element(e) if e.tag() == "p"))
+            .count();
+        assert_eq!(paragraphs, 3);
+
+        // deleted nodes and their children are skipped
+        div.remove_range(&mut t1, 0, 1);
+        p1.remove_range(&mut t1, 0, 1);
+        assert_eq!(tags(&root), vec!["p", "b", "div", "span", "p"]);
+
+        let d2 = Doc::with_client_id(2);
+        let mut t2 = d2.transact();
+        let remote = t2.get_xml_fragment("fragment");
+        t2.apply_update(Update::decode_v1(&t1.encode_update_v1()).unwrap());
+        assert_eq!(tags(&remote), tags(&root));
+        assert_eq!(remote.to_string(), root.to_string());
+    }
+
     #[test]
     fn text_attributes() {
         let doc = Doc::with_client_id(1);
@@ -1179,6 +1754,42 @@ mod test {
         );
     }
 
+    #[test]
+    fn insert_after() {
+        let d1 = Doc::with_client_id(1);
+        let mut t1 = d1.transact();
+        let root = t1.get_xml_element("root");
+        let b = root.insert_elem_after(&mut t1, None, "b");
+        let a = root.insert_elem_after(&mut t1, None, "a");
+        let b = Xml::Element(b);
+        let d = root.insert_elem_after(&mut t1, Some(&b), "d");
+        let c = root.insert_text_after(&mut t1, Some(&b));
+        c.push(&mut t1, "c");
+        assert_eq!(
+            root.to_string(),
+            "<UNDEFINED><a></a><b></b>c<d></d></UNDEFINED>"
+        );
+
+        assert_eq!(root.first_child(), Some(Xml::Element(a.clone())));
+        assert_eq!(a.next_sibling(), Some(b.clone()));
+        assert_eq!(c.prev_sibling(), Some(b));
+        assert_eq!(c.next_sibling(), Some(Xml::Element(d.clone())));
+        assert_eq!(d.parent(), Some(root.clone()));
+
+        // concurrent insert after the same node
+        let d2 = Doc::with_client_id(2);
+        let mut t2 = d2.transact();
+        let remote = t2.get_xml_element("root");
+        t2.apply_update(Update::decode_v1(&t1.encode_update_v1()).unwrap());
+        let remote_a = remote.first_child().unwrap();
+        remote.insert_elem_after(&mut t2, Some(&remote_a), "x");
+        root.insert_elem_after(&mut t1, Some(&Xml::Element(a)), "y");
+        t2.apply_update(Update::decode_v1(&t1.encode_update_v1()).unwrap());
+        t1.apply_update(Update::decode_v1(&t2.encode_update_v1()).unwrap());
+        assert_eq!(remote.to_string(), root.to_string());
+        assert_eq!(root.len(), 6);
+    }
+
     #[test]
     fn serialization() {
         let d1 = Doc::with_client_id(1);
@@ -1201,6 +1812,43 @@ mod test {
         assert_eq!(r2.to_string(), expected);
     }
 
+    #[test]
+    fn xml_string_roundtrip() {
+        let d1 = Doc::with_client_id(1);
+        let mut t1 = d1.transact();
+        let root = t1.get_xml_fragment("fragment");
+        let input = r#"<svg:svg xmlns:svg="http://www.w3.org/2000/svg"><p title="a &amp; &quot;b&quot;" id='1'>x &lt; y<br/></p></svg:svg>tail"#;
+        assert_eq!(root.insert_xml(&mut t1, 0, input).unwrap(), 2);
+
+        assert_eq!(
+            root.to_xml_string(),
+            "<svg:svg xmlns:svg=\"http://www.w3.org/2000/svg\"><p id=\"1\" title=\"a &amp; &quot;b&quot;\">x &lt; y<br></br></p></svg:svg>tail"
+        );
+        assert_eq!(
+            root.to_string(),
+            "<svg:svg xmlns:svg=\"http://www.w3.org/2000/svg\"><p id=\"1\" title=\"a & \"b\"\">x < y<br></br></p></svg:svg>tail"
+        );
+
+        // serialized content can be parsed back
+        let d2 = Doc::with_client_id(2);
+        let mut t2 = d2.transact();
+        let copy = t2.get_xml_fragment("fragment");
+        copy.insert_xml(&mut t2, 0, &root.to_xml_string()).unwrap();
+        assert_eq!(copy.to_xml_string(), root.to_xml_string());
+
+        // deleted children are not serialized
+        let svg: XmlElement = root.get(0).unwrap();
+        svg.remove_range(&mut t1, 0, 1);
+        assert_eq!(
+            root.to_xml_string(),
+            "<svg:svg xmlns:svg=\"http://www.w3.org/2000/svg\"></svg:svg>tail"
+        );
+
+        // malformed input is rejected as a whole
+        assert!(svg.insert_xml(&mut t1, 0, "<p><b></p>").is_err());
+        assert_eq!(svg.len(), 0);
+    }
+
     #[test]
     fn serialization_compatibility() {
         let d1 = Doc::with_client_id(1);
@@ -1364,4 +2012,180 @@ mod test {
             )]))
         );
     }
+
+    #[test]
+    fn observe_deep() {
+        let doc = Doc::with_client_id(1);
+        let mut xml = doc.transact().get_xml_element("xml");
+        let (p, txt) = {
+            let mut txn = doc.transact();
+            let p = xml.insert_elem(&mut txn, 0, "p");
+            let txt = p.insert_text(&mut txn, 0);
+            (p, txt)
+        };
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_c = events.clone();
+        let _sub = xml.observe_deep(move |_, e| {
+            for e in e.iter() {
+                let kind = match e {
+                    Event::XmlElement(_) => "element",
+                    Event::XmlText(_) => "text",
+                    _ => "other",
+                };
+                events_c.borrow_mut().push((kind, e.path()));
+            }
+        });
+
+        {
+            let mut txn = doc.transact();
+            txt.insert(&mut txn, 0, "hello");
+            p.insert_attribute(&mut txn, "class", "bold");
+        }
+
+        let mut actual = events.take();
+        actual.sort_by_key(|(kind, _)| *kind);
+        assert_eq!(
+            actual,
+            vec![
+                ("element", Path::from([PathSegment::Index(0)])),
+                (
+                    "text",
+                    Path::from([PathSegment::Index(0), PathSegment::Index(0)])
+                ),
+            ]
+        );
+
+        // changes of the observed type itself are reported with an empty path
+        xml.insert_attribute(&mut doc.transact(), "id", "root");
+        assert_eq!(events.take(), vec![("element", Path::default())]);
+    }
+
+    #[test]
+    fn text_formatting() {
+        let d1 = Doc::with_client_id(1);
+        let root = {
+            let mut txn = d1.transact();
+            txn.get_xml_element("root")
+        };
+        let mut txt = root.push_text_back(&mut d1.transact());
+        let delta = Rc::new(RefCell::new(None));
+        let _sub = {
+            let delta = delta.clone();
+            txt.observe(move |txn, e| {
+                *delta.borrow_mut() = Some(e.delta(txn).to_vec());
+            })
+        };
+
+        let bold: Attrs = HashMap::from([("b".into(), Any::Map(Box::new(HashMap::new())))]);
+        let link: Attrs = HashMap::from([(
+            "a".into(),
+            Any::Map(Box::new(HashMap::from([(
+                "href".to_string(),
+                Any::String("http://x.com".into()),
+            )]))),
+        )]);
+        {
+            let mut txn = d1.transact();
+            txt.insert(&mut txn, 0, "hello world");
+            txt.format(&mut txn, 0, 5, bold.clone());
+        }
+        assert_eq!(
+            delta.borrow_mut().take(),
+            Some(vec![
+                Delta::Inserted("hello".into(), Some(Box::new(bold.clone()))),
+                Delta::Inserted(" world".into(), None),
+            ])
+        );
+        assert_eq!(txt.to_string(), "<b>hello</b> world");
+
+        {
+            let mut txn = d1.transact();
+            txt.format(&mut txn, 3, 5, link.clone());
+        }
+        assert_eq!(
+            delta.borrow_mut().take(),
+            Some(vec![
+                Delta::Retain(3, None),
+                Delta::Retain(5, Some(Box::new(link))),
+            ])
+        );
+        assert_eq!(
+            txt.to_string(),
+            "<b>hel</b><a href=\"http://x.com\"><b>lo</b></a><a href=\"http://x.com\"> wo</a>rld"
+        );
+
+        // formatting attributes are replicated together with the text
+        let d2 = Doc::with_client_id(2);
+        let mut t2 = d2.transact();
+        let r2 = t2.get_xml_element("root");
+        t2.apply_update(
+            Update::decode_v1(&d1.encode_state_as_update_v1(&StateVector::default())).unwrap(),
+        );
+        assert_eq!(r2.to_string(), root.to_string());
+
+        // removing formatting
+        let unbold: Attrs = HashMap::from([("b".into(), Any::Null)]);
+        txt.format(&mut d1.transact(), 0, 5, unbold);
+        assert_eq!(
+            txt.to_string(),
+            "hel<a href=\"http://x.com\">lo</a><a href=\"http://x.com\"> wo</a>rld"
+        );
+    }
+
+    #[test]
+    fn try_edits_out_of_bounds() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let root = txn.get_xml_element("root");
+        let p = root.push_elem_back(&mut txn, "p");
+        let other = txn.get_xml_element("other").push_elem_back(&mut txn, "div");
+
+        assert_eq!(root.try_insert_elem(&mut txn, 2, "b"), Err(OutOfBounds));
+        assert_eq!(root.try_insert_text(&mut txn, 2), Err(OutOfBounds));
+        assert_eq!(root.try_remove_range(&mut txn, 0, 2), Err(OutOfBounds));
+        let other = Xml::Element(other);
+        assert_eq!(
+            root.try_insert_elem_after(&mut txn, Some(&other), "b"),
+            Err(NotAChild)
+        );
+        assert_eq!(
+            root.try_insert_text_after(&mut txn, Some(&other)),
+            Err(NotAChild)
+        );
+        assert_eq!(root.len(), 1);
+
+        let txt = root.try_insert_text(&mut txn, 1).unwrap();
+        let b = root
+            .try_insert_elem_after(&mut txn, Some(&Xml::Element(p.clone())), "b")
+            .unwrap();
+        assert_eq!(root.get(1), Some(Xml::Element(b)));
+        assert!(root.try_insert_elem(&mut txn, 0, "i").is_ok());
+        assert_eq!(root.try_remove_range(&mut txn, 0, 1), Ok(()));
+        assert_eq!(root.to_string(), "<UNDEFINED><p></p><b></b></UNDEFINED>");
+
+        txt.push(&mut txn, "hello");
+        let bold: Attrs = HashMap::from([("b".into(), Any::Map(Box::new(HashMap::new())))]);
+        assert_eq!(txt.try_insert(&mut txn, 6, "!"), Err(OutOfBounds));
+        assert_eq!(
+            txt.try_insert_with_attributes(&mut txn, 6, "!", bold.clone()),
+            Err(OutOfBounds)
+        );
+        assert_eq!(txt.try_insert_embed(&mut txn, 6, true), Err(OutOfBounds));
+        assert_eq!(
+            txt.try_insert_embed_with_attributes(&mut txn, 6, true, bold.clone()),
+            Err(OutOfBounds)
+        );
+        assert_eq!(txt.try_remove_range(&mut txn, 4, 2), Err(OutOfBounds));
+        assert_eq!(
+            txt.try_format(&mut txn, 0, 6, bold.clone()),
+            Err(OutOfBounds)
+        );
+        assert_eq!(txt.to_string(), "hello");
+
+        assert_eq!(txt.try_insert(&mut txn, 5, "!"), Ok(()));
+        assert_eq!(txt.try_remove_range(&mut txn, 0, 1), Ok(()));
+        assert_eq!(txt.try_format(&mut txn, 0, 4, bold), Ok(()));
+        assert_eq!(txt.to_string(), "<b>ello</b>!");
+    }
 }
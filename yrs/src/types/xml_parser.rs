@@ -0,0 +1,344 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Error returned when a string passed to [XmlFragment::insert_xml](crate::types::xml::XmlFragment::insert_xml)
+/// is not a well-formed XML.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlParseError {
+    msg: String,
+    line: usize,
+    col: usize,
+}
+
+impl XmlParseError {
+    fn new(msg: String, line: usize, col: usize) -> Self {
+        XmlParseError { msg, line, col }
+    }
+}
+
+impl fmt::Display for XmlParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "XML parse error at line:{}, col:{}: {}",
+            self.line, self.col, &self.msg
+        )
+    }
+}
+
+impl std::error::Error for XmlParseError {}
+
+/// Intermediate XML tree produced by [XmlParser], which is later on used to construct shared XML
+/// types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum XmlNode {
+    Element {
+        tag: String,
+        attributes: Vec<(String, String)>,
+        children: Vec<XmlNode>,
+    },
+    Text(String),
+}
+
+/// Minimal, non-validating XML parser. It supports elements, attributes, character and entity
+/// references, CDATA sections and skips comments, processing instructions and document type
+/// declarations. Namespace prefixes are kept as a part of tag and attribute names.
+pub(crate) struct XmlParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        XmlParser {
+            chars: input.chars().peekable(),
+            line: 1,
+            col: 0,
+        }
+    }
+
+    /// Parses an entire input into a list of top-level XML nodes.
+    pub fn parse(mut self) -> Result<Vec<XmlNode>, XmlParseError> {
+        let nodes = self.parse_nodes(None)?;
+        Ok(nodes)
+    }
+
+    fn err<T>(&self, msg: String) -> Result<T, XmlParseError> {
+        Err(XmlParseError::new(msg, self.line, self.col))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 0;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), XmlParseError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => self.err(format!("expected '{}' but found '{}'", expected, c)),
+            None => self.err(format!("expected '{}' but reached end of input", expected)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Consumes characters until a given `terminator` sequence is found. Returns consumed
+    /// characters without the terminator.
+    fn read_until(&mut self, terminator: &str) -> Result<String, XmlParseError> {
+        let mut s = String::new();
+        loop {
+            if s.ends_with(terminator) {
+                s.truncate(s.len() - terminator.len());
+                return Ok(s);
+            }
+            match self.next() {
+                Some(c) => s.push(c),
+                None => return self.err(format!("expected '{}' before end of input", terminator)),
+            }
+        }
+    }
+
+    fn read_name(&mut self) -> Result<String, XmlParseError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == ':' || c == '_' || c == '-' || c == '.' {
+                name.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            self.err("expected a name".to_string())
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn read_reference(&mut self) -> Result<char, XmlParseError> {
+        let name = self.read_until(";")?;
+        let c = match name.as_str() {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            other if other.starts_with("#x") => u32::from_str_radix(&other[2..], 16)
+                .ok()
+                .and_then(std::char::from_u32),
+            other if other.starts_with('#') => {
+                other[1..].parse::<u32>().ok().and_then(std::char::from_u32)
+            }
+            _ => None,
+        };
+        match c {
+            Some(c) => Ok(c),
+            None => self.err(format!("unknown entity reference '&{};'", name)),
+        }
+    }
+
+    fn parse_nodes(&mut self, parent: Option<&str>) -> Result<Vec<XmlNode>, XmlParseError> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                None => {
+                    if let Some(tag) = parent {
+                        return self.err(format!("element <{}> has not been closed", tag));
+                    }
+                    break;
+                }
+                Some('&') => text.push(self.read_reference()?),
+                Some('<') => match self.peek() {
+                    Some('/') => {
+                        self.next();
+                        let tag = self.read_name()?;
+                        self.skip_whitespace();
+                        self.expect('>')?;
+                        if parent == Some(tag.as_str()) {
+                            break;
+                        } else {
+                            return self.err(format!("unexpected closing tag </{}>", tag));
+                        }
+                    }
+                    Some('?') => {
+                        self.read_until("?>")?;
+                    }
+                    Some('!') => {
+                        self.next();
+                        if self.peek() == Some('-') {
+                            self.expect('-')?;
+                            self.expect('-')?;
+                            self.read_until("-->")?;
+                        } else if self.peek() == Some('[') {
+                            self.expect('[')?;
+                            let section = self.read_name()?;
+                            if section != "CDATA" {
+                                return self.err(format!("unsupported section <![{}[", section));
+                            }
+                            self.expect('[')?;
+                            text.push_str(&self.read_until("]]>")?);
+                        } else {
+                            self.read_until(">")?;
+                        }
+                    }
+                    _ => {
+                        if !text.is_empty() {
+                            nodes.push(XmlNode::Text(std::mem::take(&mut text)));
+                        }
+                        nodes.push(self.parse_element()?);
+                    }
+                },
+                Some(c) => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            nodes.push(XmlNode::Text(text));
+        }
+        Ok(nodes)
+    }
+
+    fn parse_element(&mut self) -> Result<XmlNode, XmlParseError> {
+        let tag = self.read_name()?;
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('/') => {
+                    self.next();
+                    self.expect('>')?;
+                    return Ok(XmlNode::Element {
+                        tag,
+                        attributes,
+                        children: Vec::new(),
+                    });
+                }
+                Some('>') => {
+                    self.next();
+                    break;
+                }
+                _ => {
+                    let name = self.read_name()?;
+                    self.skip_whitespace();
+                    self.expect('=')?;
+                    self.skip_whitespace();
+                    let value = self.parse_attribute_value()?;
+                    attributes.push((name, value));
+                }
+            }
+        }
+        let children = self.parse_nodes(Some(&tag))?;
+        Ok(XmlNode::Element {
+            tag,
+            attributes,
+            children,
+        })
+    }
+
+    fn parse_attribute_value(&mut self) -> Result<String, XmlParseError> {
+        let quote = match self.next() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => return self.err("expected quoted attribute value".to_string()),
+        };
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some(c) if c == quote => return Ok(value),
+                Some('&') => value.push(self.read_reference()?),
+                Some('<') => return self.err("unexpected '<' in attribute value".to_string()),
+                Some(c) => value.push(c),
+                None => return self.err("unterminated attribute value".to_string()),
+            }
+        }
+    }
+}
+
+/// Escapes characters which have special meaning in XML text and attribute values.
+pub(crate) fn escape(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types::xml_parser::{XmlNode, XmlParser};
+
+    fn elem(tag: &str, attributes: &[(&str, &str)], children: Vec<XmlNode>) -> XmlNode {
+        XmlNode::Element {
+            tag: tag.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            children,
+        }
+    }
+
+    #[test]
+    fn parse_nodes() {
+        let input = r#"<?xml version="1.0"?>
+<!-- comment --><svg:svg xmlns:svg='http://www.w3.org/2000/svg' title="a &amp; &quot;b&quot;"><p>1 &lt; 2<br/><![CDATA[<raw>]]>&#65;&#x42;</p></svg:svg>"#;
+        let actual = XmlParser::new(input).parse().unwrap();
+        let expected = vec![
+            XmlNode::Text("\n".to_string()),
+            elem(
+                "svg:svg",
+                &[
+                    ("xmlns:svg", "http://www.w3.org/2000/svg"),
+                    ("title", "a & \"b\""),
+                ],
+                vec![elem(
+                    "p",
+                    &[],
+                    vec![
+                        XmlNode::Text("1 < 2".to_string()),
+                        elem("br", &[], vec![]),
+                        XmlNode::Text("<raw>AB".to_string()),
+                    ],
+                )],
+            ),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(XmlParser::new("<p>").parse().is_err());
+        assert!(XmlParser::new("<p></b>").parse().is_err());
+        assert!(XmlParser::new("<p a=1></p>").parse().is_err());
+        assert!(XmlParser::new("a &unknown; b").parse().is_err());
+        let e = XmlParser::new("<p>\n</b>").parse().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "XML parse error at line:2, col:4: unexpected closing tag </b>"
+        );
+    }
+}
@@ -0,0 +1,812 @@
+use crate::block::{Block, BlockPtr, Item, ID};
+use crate::event::Subscription;
+use crate::store::{Store, StoreRef};
+use crate::transaction::{Origin, Transaction};
+use crate::types::{Branch, BranchPtr, TypePtr};
+use crate::{DeleteSet, Doc};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Undo manager is a structure used to perform undo/redo operations over the changes made to
+/// a specific scope of shared types. Scope is defined by shared types passed to an undo manager
+/// (see: [UndoManager::expand_scope]) and includes all nested types living within them.
+///
+/// Changes are tracked on per transaction basis. Only transactions without an origin or with
+/// one of the tracked origins (see: [UndoManager::include_origin]) are tracked. Transactions
+/// committed within a [Options::capture_timeout_millis] interval are grouped together and undone
+/// or redone as a single step, unless [UndoManager::stop_capturing] has been called in between.
+///
+/// Example:
+/// ```
+/// use yrs::{Doc, UndoManager};
+/// let doc = Doc::new();
+/// let text = doc.transact().get_text("text");
+/// let mut undo_manager = UndoManager::new(&doc, &text);
+///
+/// text.push(&mut doc.transact(), "hello");
+/// undo_manager.stop_capturing();
+/// text.push(&mut doc.transact(), " world");
+/// assert_eq!(text.to_string(), "hello world");
+///
+/// undo_manager.undo();
+/// assert_eq!(text.to_string(), "hello");
+///
+/// undo_manager.redo();
+/// assert_eq!(text.to_string(), "hello world");
+/// ```
+pub struct UndoManager {
+    _subscription: Subscription<()>,
+    inner: Rc<RefCell<Inner>>,
+    store: StoreRef,
+    origin: Origin,
+}
+
+struct Inner {
+    scope: Vec<BranchPtr>,
+    options: Options,
+    undo_stack: Vec<StackItem>,
+    redo_stack: Vec<StackItem>,
+    undoing: bool,
+    redoing: bool,
+    last_change: u64,
+}
+
+/// A single undo/redo step, describing blocks inserted and deleted by tracked transactions.
+struct StackItem {
+    insertions: DeleteSet,
+    deletions: DeleteSet,
+}
+
+/// Configuration options of [UndoManager].
+#[derive(Clone)]
+pub struct Options {
+    /// Changes committed within this time window (measured in milliseconds since the last tracked
+    /// change) are merged together into a single undo/redo step. Default: 500ms.
+    pub capture_timeout_millis: u64,
+    /// Origins of transactions, which should be tracked by undo manager in addition to
+    /// transactions that have no origin at all.
+    pub tracked_origins: HashSet<Origin>,
+    /// A function returning current timestamp in milliseconds, used to determine if changes
+    /// should be merged together. Default: system time.
+    pub timestamp: Rc<dyn Fn() -> u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            capture_timeout_millis: 500,
+            tracked_origins: HashSet::new(),
+            timestamp: Rc::new(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default()
+            }),
+        }
+    }
+}
+
+impl UndoManager {
+    /// Creates a new undo manager tracking changes made to a given `scope` shared type and all
+    /// types nested within it.
+    pub fn new<T: AsRef<Branch>>(doc: &Doc, scope: &T) -> Self {
+        Self::with_options(doc, scope, Options::default())
+    }
+
+    /// Creates a new undo manager tracking changes made to a given `scope` shared type and all
+    /// types nested within it, using provided configuration `options`.
+    pub fn with_options<T: AsRef<Branch>>(doc: &Doc, scope: &T, options: Options) -> Self {
+        let mut store = doc.store.clone();
+        let inner = Rc::new(RefCell::new(Inner {
+            scope: vec![BranchPtr::from(scope.as_ref())],
+            options,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            undoing: false,
+            redoing: false,
+            last_change: 0,
+        }));
+        let origin = Origin::from(Rc::as_ptr(&inner) as *const () as usize);
+        let subscription = {
+            let inner = inner.clone();
+            let origin = origin.clone();
+            store
+                .before_cleanup_events
+                .get_or_insert_with(Default::default)
+                .subscribe(move |txn, _| {
+                    inner.borrow_mut().handle_transaction(txn, &origin);
+                })
+        };
+        UndoManager {
+            _subscription: subscription,
+            inner,
+            store,
+            origin,
+        }
+    }
+
+    /// Extends a scope of shared types tracked by current undo manager.
+    pub fn expand_scope<T: AsRef<Branch>>(&mut self, scope: &T) {
+        let ptr = BranchPtr::from(scope.as_ref());
+        let mut inner = self.inner.borrow_mut();
+        if !inner.scope.iter().any(|s| same_branch(*s, ptr)) {
+            inner.scope.push(ptr);
+        }
+    }
+
+    /// Adds an `origin` to the set of origins tracked by current undo manager. Transactions
+    /// created with that origin (see: [Doc::transact_with]) will be tracked.
+    pub fn include_origin<O: Into<Origin>>(&mut self, origin: O) {
+        let mut inner = self.inner.borrow_mut();
+        inner.options.tracked_origins.insert(origin.into());
+    }
+
+    /// Removes an `origin` from the set of origins tracked by current undo manager.
+    pub fn exclude_origin<O: Into<Origin>>(&mut self, origin: O) {
+        let mut inner = self.inner.borrow_mut();
+        inner.options.tracked_origins.remove(&origin.into());
+    }
+
+    /// Makes the next tracked change to be captured as a separate undo/redo step, even if it
+    /// happens within [Options::capture_timeout_millis] since the previous one.
+    pub fn stop_capturing(&mut self) {
+        self.inner.borrow_mut().last_change = 0;
+    }
+
+    /// Checks if there are any changes that can be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.inner.borrow().undo_stack.is_empty()
+    }
+
+    /// Checks if there are any changes that can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.inner.borrow().redo_stack.is_empty()
+    }
+
+    /// Clears undo and redo stacks of current undo manager, releasing the deleted content
+    /// they were holding, so it can be garbage collected.
+    pub fn clear(&mut self) {
+        let mut inner = self.inner.borrow_mut();
+        let undo_stack = std::mem::take(&mut inner.undo_stack);
+        let redo_stack = std::mem::take(&mut inner.redo_stack);
+        for item in undo_stack.iter().chain(redo_stack.iter()) {
+            inner.keep(&self.store, &item.deletions, false);
+        }
+    }
+
+    /// Reverts the last undo step. Returns `true` if any changes have been applied as a result.
+    pub fn undo(&mut self) -> bool {
+        self.inner.borrow_mut().undoing = true;
+        let result = self.pop_stack_item(false);
+        self.inner.borrow_mut().undoing = false;
+        result
+    }
+
+    /// Reapplies the last step reverted by [UndoManager::undo]. Returns `true` if any changes
+    /// have been applied as a result.
+    pub fn redo(&mut self) -> bool {
+        self.inner.borrow_mut().redoing = true;
+        let result = self.pop_stack_item(true);
+        self.inner.borrow_mut().redoing = false;
+        result
+    }
+
+    fn pop_stack_item(&mut self, redo: bool) -> bool {
+        let mut txn = Transaction::with_origin(self.store.clone(), self.origin.clone());
+        let mut result = false;
+        while !result {
+            let (stack_item, scope) = {
+                let mut inner = self.inner.borrow_mut();
+                let stack = if redo {
+                    &mut inner.redo_stack
+                } else {
+                    &mut inner.undo_stack
+                };
+                match stack.pop() {
+                    Some(item) => (item, inner.scope.clone()),
+                    None => break,
+                }
+            };
+
+            let mut to_delete = Vec::new();
+            for mut ptr in blocks_in(&mut txn, &stack_item.insertions) {
+                if let Some(redone) = ptr.as_item().and_then(|item| item.redone) {
+                    if let Some((item, diff)) = follow_redone(txn.store(), &redone) {
+                        ptr = if diff > 0 {
+                            let id = ID::new(item.id().client, item.id().clock + diff);
+                            let blocks = &mut txn.store_mut().blocks;
+                            blocks.get_item_clean_start(&id).unwrap_or(item)
+                        } else {
+                            item
+                        };
+                    }
+                }
+                if let Block::Item(item) = ptr.deref() {
+                    if !item.is_deleted() && in_scope(&scope, item) {
+                        to_delete.push(ptr);
+                    }
+                }
+            }
+
+            let mut to_redo = Vec::new();
+            for ptr in blocks_in(&mut txn, &stack_item.deletions) {
+                if let Block::Item(item) = ptr.deref() {
+                    if in_scope(&scope, item) && !stack_item.insertions.is_deleted(&item.id) {
+                        to_redo.push(ptr);
+                    }
+                }
+            }
+
+            let redo_set: HashSet<BlockPtr> = to_redo.iter().cloned().collect();
+            for &ptr in to_redo.iter() {
+                if redo_item(&mut txn, ptr, &redo_set, &stack_item.insertions).is_some() {
+                    result = true;
+                }
+            }
+            // delete in reverse order, so that children are deleted before their parents
+            for &ptr in to_delete.iter().rev() {
+                txn.delete(ptr);
+                result = true;
+            }
+        }
+        txn.commit();
+        result
+    }
+}
+
+impl Inner {
+    fn handle_transaction(&mut self, txn: &Transaction, origin: &Origin) {
+        let tracked = match txn.origin() {
+            None => true,
+            Some(o) => o == origin || self.options.tracked_origins.contains(o),
+        };
+        let changed = txn.changed.keys().any(|ptr| match ptr {
+            TypePtr::Branch(branch) => self.scope.iter().any(|s| is_parent_of(*s, *branch)),
+            _ => false,
+        });
+        if !tracked || !changed {
+            return;
+        }
+
+        let undoing = self.undoing;
+        let redoing = self.redoing;
+        if undoing {
+            // next undo should not be appended to last stack item
+            self.last_change = 0;
+        } else if !redoing {
+            // neither undoing nor redoing: new changes invalidate redo stack
+            let redo_stack = std::mem::take(&mut self.redo_stack);
+            for item in redo_stack.iter() {
+                self.keep(&txn.store, &item.deletions, false);
+            }
+        }
+
+        let mut insertions = DeleteSet::new();
+        for (client, &end) in txn.after_state.iter() {
+            let start = txn.before_state.get(client);
+            if end > start {
+                insertions.insert(ID::new(*client, start), end - start);
+            }
+        }
+
+        let now = (self.options.timestamp)();
+        let capture = !undoing
+            && !redoing
+            && self.last_change > 0
+            && now.saturating_sub(self.last_change) < self.options.capture_timeout_millis;
+        let stack = if undoing {
+            &mut self.redo_stack
+        } else {
+            &mut self.undo_stack
+        };
+        match stack.last_mut() {
+            Some(last) if capture => {
+                last.deletions.merge(txn.delete_set.clone());
+                last.insertions.merge(insertions);
+            }
+            _ => stack.push(StackItem {
+                insertions,
+                deletions: txn.delete_set.clone(),
+            }),
+        }
+        if !undoing && !redoing {
+            self.last_change = now;
+        }
+
+        // make sure that deleted blocks are not garbage collected
+        self.keep(&txn.store, &txn.delete_set, true);
+    }
+
+    /// Sets or clears a keep flag of all items within a current scope described by `ds`.
+    fn keep(&self, store: &Store, ds: &DeleteSet, keep: bool) {
+        for (client, range) in ds.iter() {
+            if let Some(blocks) = store.blocks.get(client) {
+                for r in range.iter() {
+                    if let Some(mut i) = blocks.find_pivot(r.start) {
+                        while i < blocks.len() {
+                            let mut ptr = blocks.get(i);
+                            if ptr.id().clock >= r.end {
+                                break;
+                            }
+                            if let Block::Item(item) = ptr.deref_mut() {
+                                if in_scope(&self.scope, item) {
+                                    if keep {
+                                        item.info.set_keep();
+                                    } else {
+                                        item.info.clear_keep();
+                                    }
+                                }
+                            }
+                            i += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns all blocks within the ranges of a given delete set, splitting blocks on the range
+/// boundaries if necessary.
+fn blocks_in(txn: &mut Transaction, ds: &DeleteSet) -> Vec<BlockPtr> {
+    let mut result = Vec::new();
+    for (client, range) in ds.iter() {
+        for r in range.iter() {
+            let state = txn.store().blocks.get_state(client);
+            if r.start >= state {
+                continue;
+            }
+            let start = ID::new(*client, r.start);
+            txn.store_mut().blocks.get_item_clean_start(&start);
+            txn.merge_blocks.push(start);
+            if r.end < state {
+                let end = ID::new(*client, r.end);
+                txn.store_mut().blocks.get_item_clean_start(&end);
+                txn.merge_blocks.push(end);
+            }
+            let blocks = txn.store().blocks.get(client).unwrap();
+            if let Some(mut i) = blocks.find_pivot(r.start) {
+                while i < blocks.len() {
+                    let ptr = blocks.get(i);
+                    if ptr.id().clock >= r.end {
+                        break;
+                    }
+                    result.push(ptr);
+                    i += 1;
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Follows the chain of items redoing the item with a given `id`. Returns the last block in that
+/// chain together with an offset within it, that corresponds to a given `id`.
+pub(crate) fn follow_redone(store: &Store, id: &ID) -> Option<(BlockPtr, u32)> {
+    let mut next = Some(*id);
+    let mut result = None;
+    let mut diff = 0;
+    while let Some(mut next_id) = next {
+        next_id.clock += diff;
+        let ptr = store.blocks.get_block(&next_id)?;
+        diff = next_id.clock - ptr.id().clock;
+        next = ptr.as_item().and_then(|item| item.redone);
+        result = Some(ptr);
+    }
+    result.map(|ptr| (ptr, diff))
+}
+
+/// Restores a deleted item by inserting its copy at the position it used to occupy. Returns
+/// `None` if item could not be restored, e.g. because its parent type couldn't be restored or
+/// in case of a conflicting concurrent change of a map entry.
+fn redo_item(
+    txn: &mut Transaction,
+    mut ptr: BlockPtr,
+    redo_items: &HashSet<BlockPtr>,
+    items_to_delete: &DeleteSet,
+) -> Option<BlockPtr> {
+    let item = ptr.as_item()?;
+    if let Some(redone) = item.redone {
+        return txn.store_mut().blocks.get_item_clean_start(&redone);
+    }
+    let mut parent_block = item.parent.as_branch()?.item;
+    // make sure that parent is redone
+    if let Some(mut parent) = parent_block {
+        if parent.is_deleted() {
+            let parent_redone = parent.as_item()?.redone.is_some();
+            if !parent_redone
+                && (!redo_items.contains(&parent)
+                    || redo_item(txn, parent, redo_items, items_to_delete).is_none())
+            {
+                return None;
+            }
+            while let Some(redone) = parent.as_item()?.redone {
+                parent = txn.store_mut().blocks.get_item_clean_start(&redone)?;
+            }
+            parent_block = Some(parent);
+        }
+    }
+    let parent_branch = match parent_block {
+        Some(parent) => parent.as_branch()?,
+        None => *item.parent.as_branch()?,
+    };
+
+    let (left, right) = if let Some(parent_sub) = item.parent_sub.as_ref() {
+        if item.right.is_some() {
+            let mut left = ptr;
+            // iterate right while right is in items_to_delete: if it is intended to delete right
+            // while item is redone, we can expect that item should replace right
+            while let Some(right) = left.as_item().and_then(|item| item.right) {
+                let right_redone = right.as_item().and_then(|item| item.redone).is_some();
+                if right_redone || items_to_delete.is_deleted(right.id()) {
+                    left = right;
+                    while let Some(redone) = left.as_item().and_then(|item| item.redone) {
+                        left = txn.store_mut().blocks.get_item_clean_start(&redone)?;
+                    }
+                } else {
+                    // not possible to redo this item, as it conflicts with a change from
+                    // another client
+                    return None;
+                }
+            }
+            (Some(left), None)
+        } else {
+            (parent_branch.map.get(parent_sub).cloned(), None)
+        }
+    } else {
+        // is an array item: insert at the old position
+        let mut left = item.left;
+        while let Some(l) = left {
+            if let Some(trace) = trace_redone(txn, l, parent_block) {
+                left = Some(trace);
+                break;
+            }
+            left = l.as_item().and_then(|item| item.left);
+        }
+        let mut right = Some(ptr);
+        while let Some(r) = right {
+            if let Some(trace) = trace_redone(txn, r, parent_block) {
+                right = Some(trace);
+                break;
+            }
+            right = r.as_item().and_then(|item| item.right);
+        }
+        (left, right)
+    };
+
+    let store = txn.store();
+    let id = ID::new(store.options.client_id, store.get_local_state());
+    let mut block = Item::new(
+        id,
+        left,
+        left.map(|l| l.last_id()),
+        right,
+        right.map(|r| *r.id()),
+        TypePtr::Branch(parent_branch),
+        item.parent_sub.clone(),
+        item.content.copy(),
+    );
+    if let Block::Item(item) = ptr.deref_mut() {
+        item.redone = Some(id);
+    }
+    if let Block::Item(item) = &mut block {
+        item.info.set_keep();
+    }
+    let (block_ptr, _) = txn.integrate_new_block(block, 0);
+    Some(block_ptr)
+}
+
+/// Follows the redone chain of a given block until it reaches a block living inside of
+/// a given `parent`.
+fn trace_redone(
+    txn: &mut Transaction,
+    ptr: BlockPtr,
+    parent: Option<BlockPtr>,
+) -> Option<BlockPtr> {
+    let mut trace = ptr;
+    loop {
+        let item = trace.as_item()?;
+        let item_parent = item.parent.as_branch()?.item;
+        if item_parent == parent {
+            return Some(trace);
+        }
+        let redone = item.redone?;
+        trace = txn.store_mut().blocks.get_item_clean_start(&redone)?;
+    }
+}
+
+fn in_scope(scope: &[BranchPtr], item: &Item) -> bool {
+    match item.parent.as_branch() {
+        Some(branch) => scope.iter().any(|s| is_parent_of(*s, *branch)),
+        None => false,
+    }
+}
+
+/// Checks if `parent` is the same branch as `child` or one of its ancestors.
+fn is_parent_of(parent: BranchPtr, child: BranchPtr) -> bool {
+    let mut curr = Some(child);
+    while let Some(branch) = curr {
+        if same_branch(parent, branch) {
+            return true;
+        }
+        curr = match branch.item.as_deref() {
+            Some(Block::Item(item)) => item.parent.as_branch().cloned(),
+            _ => None,
+        };
+    }
+    false
+}
+
+fn same_branch(a: BranchPtr, b: BranchPtr) -> bool {
+    std::ptr::eq(a.deref(), b.deref())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types::text::{Diff, YChange};
+    use crate::types::{Attrs, Value};
+    use crate::undo::{Options, UndoManager};
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, PrelimArray, PrelimMap, StateVector, Update, Xml};
+    use lib0::any::Any;
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    fn exchange_updates(d1: &Doc, d2: &Doc) {
+        for (a, b) in [(d1, d2), (d2, d1)] {
+            let sv = b.transact().state_vector().encode_v1();
+            let sv = StateVector::decode_v1(&sv).unwrap();
+            let update = a.encode_state_as_update_v1(&sv);
+            b.transact_with("remote")
+                .apply_update(Update::decode_v1(&update).unwrap());
+        }
+    }
+
+    #[test]
+    fn undo_text() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("test");
+        let mut mgr = UndoManager::new(&d1, &txt1);
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("test");
+
+        // items that are added & deleted in the same transaction won't be undo
+        {
+            let mut txn = d1.transact();
+            txt1.insert(&mut txn, 0, "test");
+            txt1.remove_range(&mut txn, 0, 4);
+        }
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "");
+
+        // follow redone items
+        txt1.insert(&mut d1.transact(), 0, "a");
+        mgr.stop_capturing();
+        txt1.remove_range(&mut d1.transact(), 0, 1);
+        mgr.stop_capturing();
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "a");
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "");
+
+        txt1.insert(&mut d1.transact(), 0, "abc");
+        txt2.insert(&mut d2.transact(), 0, "xyz");
+        exchange_updates(&d1, &d2);
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "xyz");
+        mgr.redo();
+        assert_eq!(txt1.to_string(), "abcxyz");
+
+        exchange_updates(&d1, &d2);
+        txt2.remove_range(&mut d2.transact(), 0, 1);
+        exchange_updates(&d1, &d2);
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "xyz");
+        mgr.redo();
+        assert_eq!(txt1.to_string(), "bcxyz");
+
+        // test marks
+        let attrs = Attrs::from([("bold".into(), Any::Bool(true))]);
+        txt1.format(&mut d1.transact(), 1, 3, attrs.clone());
+        assert_eq!(
+            txt1.diff(&mut d1.transact(), YChange::identity),
+            vec![
+                Diff::new(Value::from("b"), None),
+                Diff::new(Value::from("cxy"), Some(Box::new(attrs))),
+                Diff::new(Value::from("z"), None),
+            ]
+        );
+        mgr.undo();
+        assert_eq!(
+            txt1.diff(&mut d1.transact(), YChange::identity),
+            vec![Diff::new(Value::from("bcxyz"), None)]
+        );
+    }
+
+    #[test]
+    fn undo_map() {
+        let d1 = Doc::with_client_id(1);
+        let map1 = d1.transact().get_map("test");
+        let mut mgr = UndoManager::new(&d1, &map1);
+
+        map1.insert(&mut d1.transact(), "a", 0);
+        mgr.stop_capturing();
+        map1.insert(&mut d1.transact(), "a", 1);
+        mgr.undo();
+        assert_eq!(map1.get("a"), Some(Value::from(0.0)));
+        mgr.undo();
+        assert_eq!(map1.get("a"), None);
+        mgr.redo();
+        assert_eq!(map1.get("a"), Some(Value::from(0.0)));
+        mgr.redo();
+        assert_eq!(map1.get("a"), Some(Value::from(1.0)));
+
+        // nested types are restored together with their content
+        let nested = PrelimMap::from(HashMap::from([("x".to_owned(), 42)]));
+        map1.insert(&mut d1.transact(), "b", nested);
+        mgr.stop_capturing();
+        map1.remove(&mut d1.transact(), "b");
+        assert_eq!(map1.get("b"), None);
+        mgr.undo();
+        assert_eq!(
+            map1.to_json(),
+            Any::Map(Box::new(HashMap::from([
+                ("a".to_owned(), Any::Number(1.0)),
+                (
+                    "b".to_owned(),
+                    Any::Map(Box::new(HashMap::from([("x".to_owned(), 42.into())])))
+                )
+            ])))
+        );
+        mgr.undo();
+        assert_eq!(map1.get("b"), None);
+    }
+
+    #[test]
+    fn undo_array() {
+        let d1 = Doc::with_client_id(1);
+        let array1 = d1.transact().get_array("test");
+        let mut mgr = UndoManager::new(&d1, &array1);
+
+        array1.insert_range(&mut d1.transact(), 0, [1, 2, 3]);
+        mgr.stop_capturing();
+        array1.insert(&mut d1.transact(), 0, PrelimArray::from([4, 5]));
+        mgr.stop_capturing();
+        array1.remove_range(&mut d1.transact(), 0, 2);
+        assert_eq!(
+            array1.to_json(),
+            Any::Array(vec![2.into(), 3.into()].into())
+        );
+
+        mgr.undo();
+        assert_eq!(
+            array1.to_json(),
+            Any::Array(
+                vec![
+                    Any::Array(vec![4.into(), 5.into()].into()),
+                    1.into(),
+                    2.into(),
+                    3.into()
+                ]
+                .into()
+            )
+        );
+        mgr.undo();
+        mgr.undo();
+        assert_eq!(array1.len(), 0);
+        assert!(!mgr.can_undo());
+
+        mgr.redo();
+        mgr.redo();
+        mgr.redo();
+        assert_eq!(
+            array1.to_json(),
+            Any::Array(vec![2.into(), 3.into()].into())
+        );
+        assert!(!mgr.can_redo());
+    }
+
+    #[test]
+    fn undo_xml() {
+        let d1 = Doc::with_client_id(1);
+        let xml1 = d1.transact().get_xml_element("undefined");
+        let mut mgr = UndoManager::new(&d1, &xml1);
+
+        let child = xml1.insert_elem(&mut d1.transact(), 0, "p");
+        let text = child.insert_text(&mut d1.transact(), 0);
+        text.push(&mut d1.transact(), "content");
+        mgr.stop_capturing();
+        child.insert_attribute(&mut d1.transact(), "class", "bold");
+        assert_eq!(child.get_attribute("class"), Some("bold".to_owned()));
+        mgr.undo();
+        assert_eq!(child.get_attribute("class"), None);
+        mgr.undo();
+        assert_eq!(xml1.len(), 0);
+        mgr.redo();
+        assert_eq!(xml1.len(), 1);
+        match xml1.get(0) {
+            Some(Xml::Element(p)) => {
+                assert_eq!(p.tag(), "p");
+                match p.get(0) {
+                    Some(Xml::Text(t)) => assert_eq!(t.to_string(), "content"),
+                    other => panic!("expected text node, found {:?}", other),
+                }
+            }
+            other => panic!("expected <p> element, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn capture_timeout() {
+        let time = Rc::new(Cell::new(1000));
+        let mut options = Options::default();
+        let clock = time.clone();
+        options.timestamp = Rc::new(move || clock.get());
+
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("test");
+        let mut mgr = UndoManager::with_options(&d1, &txt1, options);
+
+        txt1.push(&mut d1.transact(), "a");
+        time.set(1100);
+        txt1.push(&mut d1.transact(), "b");
+        time.set(2000);
+        txt1.push(&mut d1.transact(), "c");
+
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "ab");
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "");
+        mgr.redo();
+        assert_eq!(txt1.to_string(), "ab");
+    }
+
+    #[test]
+    fn tracked_origins() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("test");
+        let mut mgr = UndoManager::new(&d1, &txt1);
+
+        txt1.push(&mut d1.transact_with("untracked"), "a");
+        assert!(!mgr.can_undo());
+
+        mgr.include_origin("tracked");
+        txt1.push(&mut d1.transact_with("tracked"), "b");
+        mgr.stop_capturing();
+        txt1.push(&mut d1.transact(), "c");
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "ab");
+        mgr.undo();
+        assert_eq!(txt1.to_string(), "a");
+        assert!(!mgr.can_undo());
+
+        // changes outside of the scope are not tracked
+        let other = d1.transact().get_text("other");
+        other.push(&mut d1.transact(), "d");
+        assert!(!mgr.can_undo());
+    }
+
+    #[test]
+    fn new_changes_clear_redo_stack() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("test");
+        let mut mgr = UndoManager::new(&d1, &txt1);
+
+        txt1.push(&mut d1.transact(), "a");
+        mgr.undo();
+        assert!(mgr.can_redo());
+        txt1.push(&mut d1.transact(), "b");
+        assert!(!mgr.can_redo());
+        assert!(!mgr.redo());
+        assert_eq!(txt1.to_string(), "b");
+
+        mgr.clear();
+        assert!(!mgr.can_undo());
+    }
+}
@@ -2,24 +2,27 @@ use crate::block::{
     Block, BlockPtr, BlockRange, ClientID, Item, ItemContent, BLOCK_GC_REF_NUMBER,
     BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
 };
-use crate::id_set::DeleteSet;
-#[cfg(test)]
+use crate::doc::{
+    encoded_content_size, BlockInfo, DocLimits, UpdateFilter, WritePolicy, WriteTarget,
+};
+use crate::id_set::{DeleteSet, IdSet};
 use crate::store::Store;
-use crate::types::TypePtr;
-use crate::updates::decoder::{Decode, Decoder};
+use crate::types::{BranchPtr, TypePtr};
+use crate::updates::decoder::{Decode, Decoder, MAX_PREALLOC};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
 use crate::{OffsetKind, StateVector, Transaction, ID};
 use lib0::error::Error;
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::BuildHasherDefault;
+use std::ops::Deref;
 use std::rc::Rc;
 
 #[derive(Debug, Default, PartialEq)]
 pub(crate) struct UpdateBlocks {
-    clients: HashMap<ClientID, VecDeque<BlockCarrier>, BuildHasherDefault<ClientHasher>>,
+    pub(crate) clients: HashMap<ClientID, VecDeque<BlockCarrier>, BuildHasherDefault<ClientHasher>>,
 }
 
 impl UpdateBlocks {
@@ -77,6 +80,92 @@ impl std::fmt::Display for BlockCarrier {
     }
 }
 
+/// Error returned when an update couldn't be applied onto a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateError {
+    /// Update has been rejected by [Options::update_filter](crate::Options::update_filter),
+    /// because one of its blocks (identified by a given ID) didn't pass validation.
+    Rejected(ID),
+    /// Update has been rejected by [Options::write_policy](crate::Options::write_policy),
+    /// because a block (identified by a given ID) modifies a collection it's not allowed to.
+    Denied(ID),
+    /// Applying an update would make a document exceed one of its
+    /// [Options::limits](crate::Options::limits).
+    LimitExceeded {
+        /// Limit, which would be exceeded.
+        kind: LimitKind,
+        /// Configured value of the limit.
+        limit: usize,
+        /// Size of a document after applying an update.
+        size: usize,
+    },
+}
+
+/// Kind of a document size limit. See: [DocLimits].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    /// [DocLimits::max_blocks]
+    Blocks,
+    /// [DocLimits::max_content_size]
+    ContentSize,
+    /// [DocLimits::max_clients]
+    Clients,
+}
+
+/// Result of resolving a parent collection of an update block. See: [Update::check_permissions].
+enum ParentRef {
+    /// Name of a root-level type and IDs of nested types leading to a parent collection.
+    Resolved(Rc<str>, Vec<ID>),
+    /// Parent collection has been already garbage collected.
+    Collected,
+    /// Parent collection cannot be resolved with currently known blocks.
+    Missing,
+}
+
+impl From<Option<(Rc<str>, Vec<ID>)>> for ParentRef {
+    fn from(parent: Option<(Rc<str>, Vec<ID>)>) -> Self {
+        match parent {
+            Some((root, nested)) => ParentRef::Resolved(root, nested),
+            None => ParentRef::Missing,
+        }
+    }
+}
+
+/// Returns a name of a root-level type and IDs of nested types leading to a given `branch`.
+fn resolve_branch(store: &Store, branch: BranchPtr) -> Option<(Rc<str>, Vec<ID>)> {
+    let mut nested = Vec::new();
+    let mut current = branch;
+    while let Some(ptr) = current.item {
+        nested.push(*ptr.id());
+        current = *ptr.as_item()?.parent.as_branch()?;
+    }
+    nested.reverse();
+    let root = store.get_type_key(current)?.clone();
+    Some((root, nested))
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::Rejected(id) => write!(f, "update rejected by filter at block {}", id),
+            UpdateError::Denied(id) => write!(f, "write access denied at block {}", id),
+            UpdateError::LimitExceeded { kind, limit, size } => write!(
+                f,
+                "update exceeds document limit of {:?}: {} > {}",
+                kind, size, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
+
+impl From<UpdateError> for Error {
+    fn from(e: UpdateError) -> Self {
+        Error::Update(Box::new(e))
+    }
+}
+
 /// Update type which contains an information about all decoded blocks which are incoming from a
 /// remote peer. Since these blocks are not yet integrated into current document's block store,
 /// they still may require repairing before doing so as they don't contain full data about their
@@ -90,6 +179,236 @@ pub struct Update {
 }
 
 impl Update {
+    /// Checks if applying current update onto a given `store` would exceed any of the `limits`.
+    /// Blocks already present in the store are not taken into account.
+    pub(crate) fn check_limits(
+        &self,
+        store: &Store,
+        limits: &DocLimits,
+    ) -> Result<(), UpdateError> {
+        let local_sv = store.blocks.get_state_vector();
+        let mut new_blocks = 0;
+        let mut new_clients = HashSet::new();
+        let mut new_content_size = 0;
+        for carrier in self.blocks.blocks() {
+            if let BlockCarrier::Block(block) = carrier {
+                let id = block.id();
+                if id.clock + block.len() > local_sv.get(&id.client) {
+                    new_blocks += 1;
+                    if !local_sv.contains_client(&id.client) {
+                        new_clients.insert(id.client);
+                    }
+                    if limits.max_content_size.is_some() {
+                        if let Block::Item(item) = block.as_ref() {
+                            new_content_size += encoded_content_size(&item.content);
+                        }
+                    }
+                }
+            }
+        }
+
+        let check = |kind: LimitKind, limit: Option<usize>, size: usize| match limit {
+            Some(limit) if size > limit => Err(UpdateError::LimitExceeded { kind, limit, size }),
+            _ => Ok(()),
+        };
+        let blocks: usize = store.blocks.iter().map(|(_, blocks)| blocks.len()).sum();
+        check(LimitKind::Blocks, limits.max_blocks, blocks + new_blocks)?;
+        check(
+            LimitKind::Clients,
+            limits.max_clients,
+            local_sv.len() + new_clients.len(),
+        )?;
+        if limits.max_content_size.is_some() {
+            let mut content_size = new_content_size;
+            for (_, blocks) in store.blocks.iter() {
+                for block in blocks.iter() {
+                    if let Block::Item(item) = block {
+                        content_size += encoded_content_size(&item.content);
+                    }
+                }
+            }
+            check(
+                LimitKind::ContentSize,
+                limits.max_content_size,
+                content_size,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Checks all blocks of current update against a given `filter`. Returns an ID of the first
+    /// block, which was rejected by it.
+    pub(crate) fn validate(&self, filter: &UpdateFilter) -> Result<(), UpdateError> {
+        for carrier in self.blocks.blocks() {
+            if let BlockCarrier::Block(block) = carrier {
+                if let Block::Item(item) = block.as_ref() {
+                    let info = BlockInfo {
+                        id: &item.id,
+                        len: item.len,
+                        content: &item.content,
+                        root: match &item.parent {
+                            TypePtr::Named(name) => Some(name.as_ref()),
+                            _ => None,
+                        },
+                        parent_sub: item.parent_sub.as_deref(),
+                    };
+                    if !filter.call(&info) {
+                        return Err(UpdateError::Rejected(item.id));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks all collections modified by current update against a given write `policy`, using
+    /// `store` to resolve parents of the blocks. Returns an ID of the first block, which was
+    /// denied. Blocks which parents cannot be resolved, because they depend on blocks that are
+    /// neither a part of this update nor a `store`, are denied as well - they couldn't be checked
+    /// once they get integrated.
+    pub(crate) fn check_permissions(
+        &self,
+        store: &Store,
+        policy: &WritePolicy,
+    ) -> Result<(), UpdateError> {
+        let check = |id: &ID, parent: Option<(Rc<str>, Vec<ID>)>, deleted: bool| {
+            let (root, nested) = parent.ok_or(UpdateError::Denied(*id))?;
+            let target = WriteTarget {
+                id,
+                root: &root,
+                nested: &nested,
+                deleted,
+            };
+            if policy.call(&target) {
+                Ok(())
+            } else {
+                Err(UpdateError::Denied(*id))
+            }
+        };
+
+        let max_depth = self.blocks.clients.values().map(VecDeque::len).sum();
+        for carrier in self.blocks.blocks() {
+            if let BlockCarrier::Block(block) = carrier {
+                if let Block::Item(item) = block.as_ref() {
+                    match self.resolve_parent(store, item, max_depth) {
+                        ParentRef::Resolved(root, nested) => {
+                            check(&item.id, Some((root, nested)), false)?
+                        }
+                        ParentRef::Collected => { /* item will be GCed on integration */ }
+                        ParentRef::Missing => check(&item.id, None, false)?,
+                    }
+                }
+            }
+        }
+
+        for (client, range) in self.delete_set.iter() {
+            let state = store.blocks.get_state(client);
+            let update_state = match self.blocks.clients.get(client).and_then(|b| b.back()) {
+                Some(last) => last.last_id().clock + 1,
+                None => 0,
+            };
+            for r in range.iter() {
+                if let Some(blocks) = store.blocks.get(client) {
+                    if let Some(mut i) = blocks.find_pivot(r.start) {
+                        while let Some(ptr) = blocks.try_get(i) {
+                            if ptr.id().clock >= r.end {
+                                break;
+                            }
+                            if let Block::Item(item) = ptr.deref() {
+                                if !item.is_deleted() {
+                                    if let TypePtr::Branch(branch) = &item.parent {
+                                        check(&item.id, resolve_branch(store, *branch), true)?;
+                                    }
+                                }
+                            }
+                            i += 1;
+                        }
+                    }
+                }
+                // blocks inserted by this update have been already checked
+                if r.end > state.max(update_state) {
+                    let clock = r.start.max(state).max(update_state);
+                    return Err(UpdateError::Denied(ID::new(*client, clock)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves a root-level type and nested types path of a collection, which a given update
+    /// `item` belongs to.
+    fn resolve_parent(&self, store: &Store, item: &Item, depth: usize) -> ParentRef {
+        if depth == 0 {
+            return ParentRef::Missing; // malformed update with circular references
+        }
+        match &item.parent {
+            TypePtr::Named(name) => ParentRef::Resolved(name.clone(), Vec::new()),
+            TypePtr::Branch(branch) => resolve_branch(store, *branch).into(),
+            TypePtr::ID(id) => match store.blocks.get_block(id) {
+                Some(ptr) => match ptr.deref() {
+                    Block::Item(_) => ptr
+                        .as_branch()
+                        .and_then(|b| resolve_branch(store, b))
+                        .into(),
+                    Block::GC(_) => ParentRef::Collected,
+                },
+                None => match self.find_item(id) {
+                    Some(parent) if matches!(parent.content, ItemContent::Type(_)) => {
+                        match self.resolve_parent(store, parent, depth - 1) {
+                            ParentRef::Resolved(root, mut nested) => {
+                                nested.push(parent.id);
+                                ParentRef::Resolved(root, nested)
+                            }
+                            other => other,
+                        }
+                    }
+                    _ => ParentRef::Missing,
+                },
+            },
+            TypePtr::Unknown => {
+                // parent is the same as the one of a neighbor block
+                let id = match item.origin.or(item.right_origin) {
+                    Some(id) => id,
+                    None => return ParentRef::Missing,
+                };
+                match store.blocks.get_block(&id) {
+                    Some(ptr) => match ptr.deref() {
+                        Block::Item(neighbor) => match &neighbor.parent {
+                            TypePtr::Branch(branch) => resolve_branch(store, *branch).into(),
+                            _ => ParentRef::Missing,
+                        },
+                        Block::GC(_) => ParentRef::Collected,
+                    },
+                    None => match self.find_item(&id) {
+                        Some(neighbor) => self.resolve_parent(store, neighbor, depth - 1),
+                        None => ParentRef::Missing,
+                    },
+                }
+            }
+        }
+    }
+
+    /// Returns an item of current update, which contains a given `id`.
+    fn find_item(&self, id: &ID) -> Option<&Item> {
+        let blocks = self.blocks.clients.get(&id.client)?;
+        let i = blocks
+            .binary_search_by(|b| {
+                let start = b.id().clock;
+                if start > id.clock {
+                    Ordering::Greater
+                } else if start + b.len() <= id.clock {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .ok()?;
+        match &blocks[i] {
+            BlockCarrier::Block(block) => block.as_item(),
+            BlockCarrier::Skip(_) => None,
+        }
+    }
+
     pub fn new() -> Self {
         Self::default()
     }
@@ -98,6 +417,21 @@ impl Update {
         self.blocks.is_empty() && self.delete_set.is_empty()
     }
 
+    /// Checks if current update contains blocks created under a given local `client_id`, which
+    /// are not known to a local `store`. Since only a local replica should create blocks under
+    /// its own identifier, this means that another replica uses the same one.
+    pub(crate) fn has_foreign_blocks_of(&self, client_id: ClientID, store: &Store) -> bool {
+        match self
+            .blocks
+            .clients
+            .get(&client_id)
+            .and_then(|blocks| blocks.back())
+        {
+            Some(last) => last.last_id().clock >= store.get_local_state(),
+            None => false,
+        }
+    }
+
     /// Returns a state vector representing an upper bound of client clocks included by blocks
     /// stored in current update.
     pub fn state_vector(&self) -> StateVector {
@@ -109,6 +443,37 @@ impl Update {
         sv
     }
 
+    /// Returns an iterator over all blocks of current update, which have been decoded but not
+    /// integrated into any document. Blocks are grouped by their client (higher client IDs go
+    /// first) and ordered by their clock values. This way updates can be inspected - ie. to check
+    /// which clients have modified which root types - without applying them to a [Doc](crate::Doc).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, ItemParent, Update, UpdateBlock};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let doc = Doc::with_client_id(1);
+    /// let mut txn = doc.transact();
+    /// let text = txn.get_text("article");
+    /// text.insert(&mut txn, 0, "hello");
+    /// let update = Update::decode_v1(&txn.encode_update_v1()).unwrap();
+    ///
+    /// let mut blocks = update.blocks();
+    /// match blocks.next() {
+    ///     Some(UpdateBlock::Item(item)) => {
+    ///         assert_eq!(item.id.client, 1);
+    ///         assert_eq!(item.parent, Some(ItemParent::Root("article")));
+    ///     }
+    ///     other => panic!("unexpected block: {:?}", other),
+    /// }
+    /// assert!(blocks.next().is_none());
+    /// ```
+    pub fn blocks(&self) -> UpdateBlockIter<'_> {
+        UpdateBlockIter(self.blocks.blocks())
+    }
+
     /// Merges another update into current one. Their blocks are deduplicated and reordered.
     pub fn merge(&mut self, other: Self) {
         for (client, other_blocks) in other.blocks.clients {
@@ -188,7 +553,10 @@ impl Update {
 
             while let Some(mut block) = stack_head {
                 let id = *block.id();
-                if local_sv.contains(&id) {
+                if block.is_skip() {
+                    // skips only mark gaps in merged updates: they don't carry any content, so
+                    // blocks following them must wait for the skipped range to be received
+                } else if local_sv.contains(&id) {
                     let offset = local_sv.get(&id.client) as i32 - id.clock as i32;
                     if let Some(dep) = Self::missing(&block, &local_sv) {
                         stack.push(block);
@@ -211,31 +579,25 @@ impl Update {
                         let offset = offset as u32;
                         let client = id.client;
                         local_sv.set_max(client, id.clock + block.len());
-                        if let BlockCarrier::Block(block) = &mut block {
-                            if let Block::Item(item) = block.as_mut() {
-                                item.repair(store);
+                        match block {
+                            BlockCarrier::Block(mut block) => {
+                                if let Block::Item(item) = block.as_mut() {
+                                    item.repair(store);
+                                }
+                                let (ptr, should_delete) = txn.integrate_new_block(*block, offset);
+                                if should_delete {
+                                    txn.delete(ptr);
+                                }
+                            }
+                            mut skip => {
+                                skip.integrate(txn, offset);
                             }
-                        }
-                        let should_delete = block.integrate(txn, offset);
-                        let delete_ptr = if should_delete {
-                            let ptr = block.as_block_ptr();
-                            ptr
-                        } else {
-                            None
-                        };
-                        if let BlockCarrier::Block(block) = block {
-                            store = txn.store_mut();
-                            let blocks = store.blocks.get_client_blocks_mut(client);
-                            blocks.push(block);
-                        }
-
-                        if let Some(ptr) = delete_ptr {
-                            txn.delete(ptr);
                         }
                         store = txn.store_mut();
                     }
                 } else {
                     // update from the same client is missing
+                    missing_sv.set_min(id.client, id.clock - 1);
                     stack.push(block);
                     // hid a dead wall, add all items from stack to restSS
                     Self::return_stack(stack, &mut self.blocks, &mut remaining);
@@ -289,6 +651,65 @@ impl Update {
         return (remaining_blocks, remaining_ds);
     }
 
+    /// Returns ranges of block IDs, which are not present in `local_sv` but are required in
+    /// order to integrate blocks of this update. Dependencies satisfied by blocks of this
+    /// update itself are not reported.
+    pub(crate) fn missing_ranges(&self, local_sv: &StateVector) -> IdSet {
+        let mut required: HashMap<ClientID, u32> = HashMap::new();
+        let mut require = |id: &ID| {
+            let end = required.entry(id.client).or_default();
+            *end = (*end).max(id.clock + 1);
+        };
+        for (client, blocks) in self.blocks.clients.iter() {
+            if let Some(first) = blocks.front() {
+                let clock = first.id().clock;
+                if clock > 0 {
+                    require(&ID::new(*client, clock - 1));
+                }
+            }
+            for block in blocks.iter() {
+                if let BlockCarrier::Block(block) = block {
+                    if let Block::Item(item) = block.as_ref() {
+                        if let Some(origin) = &item.origin {
+                            require(origin);
+                        }
+                        if let Some(right_origin) = &item.right_origin {
+                            require(right_origin);
+                        }
+                        match &item.parent {
+                            TypePtr::Branch(parent) => {
+                                if let Some(block) = &parent.item {
+                                    require(block.id());
+                                }
+                            }
+                            TypePtr::ID(parent_id) => require(parent_id),
+                            _ => {}
+                        }
+                        if let ItemContent::Move(m) = &item.content {
+                            require(&m.start.id);
+                            if let Some(end) = &m.end {
+                                if !m.is_collapsed() {
+                                    require(&end.id);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let mut missing = IdSet::new();
+        for (client, mut end) in required {
+            if let Some(first) = self.blocks.clients.get(&client).and_then(|b| b.front()) {
+                end = end.min(first.id().clock);
+            }
+            let state = local_sv.get(&client);
+            if end > state {
+                missing.insert(ID::new(client, state), end - state);
+            }
+        }
+        missing
+    }
+
     fn missing(block: &BlockCarrier, local_sv: &StateVector) -> Option<ClientID> {
         if let BlockCarrier::Block(block) = block {
             if let Block::Item(item) = block.as_ref() {
@@ -334,10 +755,9 @@ impl Update {
                     if start.clock >= local_sv.get(&start.client) {
                         return Some(start.client);
                     }
-                    if !m.is_collapsed() {
-                        let end = m.end.id;
-                        if end.clock >= local_sv.get(&end.client) {
-                            return Some(end.client);
+                    if let Some(end) = &m.end {
+                        if !m.is_collapsed() && end.id.clock >= local_sv.get(&end.id.client) {
+                            return Some(end.id.client);
                         }
                     }
                 }
@@ -392,15 +812,29 @@ impl Update {
         }
     }
 
-    fn decode_block<D: Decoder>(id: ID, decoder: &mut D) -> Result<BlockCarrier, Error> {
+    /// Checks if a decoded block of a given `len` is not empty and its clock range fits within
+    /// the bounds of a clock counter.
+    pub(crate) fn check_block_range(id: &ID, len: u32) -> Result<(), Error> {
+        if len == 0 {
+            Err(Error::UnexpectedValue)
+        } else if id.clock.checked_add(len).is_none() {
+            Err(Error::ClockOverflow)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn decode_block<D: Decoder>(id: ID, decoder: &mut D) -> Result<BlockCarrier, Error> {
         let info = decoder.read_info()?;
         match info {
             BLOCK_SKIP_REF_NUMBER => {
                 let len: u32 = decoder.read_var()?;
+                Self::check_block_range(&id, len)?;
                 Ok(BlockCarrier::Skip(BlockRange { id, len }))
             }
             BLOCK_GC_REF_NUMBER => {
                 let len: u32 = decoder.read_len()?;
+                Self::check_block_range(&id, len)?;
                 Ok(Box::new(Block::GC(BlockRange { id, len })).into())
             }
             info => {
@@ -417,7 +851,7 @@ impl Update {
                 };
                 let parent = if cant_copy_parent_info {
                     if decoder.read_parent_info()? {
-                        TypePtr::Named(decoder.read_string()?.into())
+                        TypePtr::Named(decoder.read_interned()?)
                     } else {
                         TypePtr::ID(decoder.read_left_id()?)
                     }
@@ -426,11 +860,22 @@ impl Update {
                 };
                 let parent_sub: Option<Rc<str>> =
                     if cant_copy_parent_info && (info & HAS_PARENT_SUB != 0) {
-                        Some(decoder.read_string()?.into())
+                        Some(decoder.read_interned()?)
                     } else {
                         None
                     };
                 let content = ItemContent::decode(decoder, info)?;
+                Self::check_block_range(&id, content.len(OffsetKind::Utf16))?;
+                // blocks can only refer to blocks created before them
+                let parent_id = match &parent {
+                    TypePtr::ID(id) => Some(id),
+                    _ => None,
+                };
+                for dep in origin.iter().chain(right_origin.iter()).chain(parent_id) {
+                    if dep.client == id.client && dep.clock >= id.clock {
+                        return Err(Error::UnexpectedValue);
+                    }
+                }
                 let item = Item::new(
                     id,
                     None,
@@ -494,6 +939,10 @@ impl Update {
         self.delete_set.encode(encoder)
     }
 
+    /// Merges a sequence of updates into a single one, which can be applied to a document with
+    /// the same effect as applying all of them one by one. Blocks of the same client, which are
+    /// present in many updates, are deduplicated, consecutive blocks are squashed together and
+    /// gaps between them are marked with skips.
     pub fn merge_updates<T>(block_stores: T) -> Update
     where
         T: IntoIterator<Item = Update>,
@@ -507,7 +956,7 @@ impl Update {
             })
             .collect();
 
-        let mut lazy_struct_decoders: VecDeque<_> = update_blocks
+        let mut lazy_struct_decoders: Vec<_> = update_blocks
             .into_iter()
             .filter(|block_store| !block_store.is_empty())
             .map(|update_blocks| {
@@ -523,135 +972,113 @@ impl Update {
         // Note: Should merge document updates whenever possible - even from different updates
         // Note: Should handle that some operations cannot be applied yet ()
         loop {
-            {
-                // sort
-                lazy_struct_decoders
-                    .make_contiguous()
-                    .sort_by(|dec1, dec2| {
-                        // Write higher clients first ⇒ sort by clientID & clock and remove decoders without content
-                        let left = dec1.current().unwrap();
-                        let right = dec2.current().unwrap();
-                        let lid = left.id();
-                        let rid = right.id();
-                        match lid.client.cmp(&rid.client) {
-                            Ordering::Equal => match lid.clock.cmp(&rid.clock) {
-                                Ordering::Equal if left.same_type(right) => Ordering::Equal,
-                                Ordering::Equal if left.is_skip() => Ordering::Greater,
-                                Ordering::Equal => Ordering::Less,
-                                Ordering::Less if !left.is_skip() || right.is_skip() => {
-                                    Ordering::Less
-                                }
-                                ordering => ordering,
-                            },
-                            ordering => ordering,
-                        }
-                    });
-            }
-
-            if let Some(mut curr_decoder) = lazy_struct_decoders.pop_front() {
-                let mut curr = curr_decoder.next();
-                let mut first_client = 0;
-                if let Some(curr_block) = curr.take() {
-                    // write from currDecoder until the next operation is from another client or if filler-struct
-                    // then we need to reorder the decoders and find the next operation to write
-                    first_client = curr_block.id().client;
-                    if let Some(mut curr_write_block) = curr_write.take() {
-                        let mut iterated = false;
-
-                        // iterate until we find something that we haven't written already
-                        // remember: first the high client-ids are written
-                        let curr_write_last = curr_write_block.id().clock + curr_write_block.len();
-                        let mut forwarder = Some(curr_block);
-                        while let Some(block) = forwarder.take() {
-                            let last = block.id().clock + block.len();
-                            if last < curr_write_last
-                                && block.id().client >= curr_write_block.id().client
-                            {
-                                forwarder = curr_decoder.next();
-                                iterated = true;
-                            } else {
-                                forwarder = Some(block);
-                                break;
-                            }
-                        }
-
-                        if let Some(mut curr_block) = forwarder.take() {
-                            let cid = curr_block.id();
-                            if cid.client != first_client || // check whether there is another decoder that has has updates from `firstClient`
-                                (iterated && cid.clock > curr_write_last)
-                            // the above while loop was used and we are potentially missing updates
-                            {
-                                continue;
-                            }
+            lazy_struct_decoders.retain(|dec| dec.current().is_some());
+            // Write higher clients first ⇒ sort by clientID & clock and remove decoders without content
+            lazy_struct_decoders.sort_by(|dec1, dec2| {
+                let left = dec1.current().unwrap();
+                let right = dec2.current().unwrap();
+                let lid = left.id();
+                let rid = right.id();
+                match rid.client.cmp(&lid.client) {
+                    Ordering::Equal => match lid.clock.cmp(&rid.clock) {
+                        Ordering::Equal if left.same_type(right) => Ordering::Equal,
+                        Ordering::Equal if left.is_skip() => Ordering::Greater,
+                        Ordering::Equal => Ordering::Less,
+                        ordering => ordering,
+                    },
+                    ordering => ordering,
+                }
+            });
 
-                            if first_client != curr_write_block.id().client {
-                                result.blocks.add_block(curr_write_block);
-                                curr_write = Some(curr_block);
-                                curr = curr_decoder.next();
-                            } else {
-                                if curr_write_last < curr_block.id().clock {
-                                    //TODO: write currStruct & set currStruct = Skip(clock = currStruct.id.clock + currStruct.length, length = curr.id.clock - self.clock)
-                                    let skip =
-                                        if let BlockCarrier::Skip(mut skip) = curr_write_block {
-                                            // extend existing skip
-                                            skip.len = curr_block.id().clock + curr_block.len()
-                                                - skip.id.clock;
-                                            skip
-                                        } else {
-                                            result.blocks.add_block(curr_write_block);
-                                            let diff = curr_block.id().clock - curr_write_last;
-                                            BlockRange::new(
-                                                ID::new(first_client, curr_write_last),
-                                                diff,
-                                            )
-                                        };
-                                    curr_write_block = BlockCarrier::Skip(skip);
-                                } else {
-                                    // if (currWrite.struct.id.clock + currWrite.struct.length >= curr.id.clock) {
-                                    let diff =
-                                        curr_write_last as i32 - curr_block.id().clock as i32;
+            let curr_decoder = match lazy_struct_decoders.first_mut() {
+                Some(decoder) => decoder,
+                None => break,
+            };
+            let first_client = curr_decoder.current().unwrap().id().client;
+
+            if let Some(mut curr_write_block) = curr_write.take() {
+                // iterate until we find something that we haven't written already
+                // remember: first the high client-ids are written
+                let curr_write_last = curr_write_block.id().clock + curr_write_block.len();
+                let mut iterated = false;
+                while let Some(curr) = curr_decoder.current() {
+                    if curr.id().clock + curr.len() <= curr_write_last
+                        && curr.id().client >= curr_write_block.id().client
+                    {
+                        curr_decoder.advance();
+                        iterated = true;
+                    } else {
+                        break;
+                    }
+                }
 
-                                    if diff > 0 {
-                                        if let BlockCarrier::Skip(skip) = &mut curr_write_block {
-                                            // prefer to slice Skip because the other struct might contain more information
-                                            skip.len -= diff as u32;
-                                        } else {
-                                            curr_block = curr_block.splice(diff as u32).unwrap();
-                                        }
-                                    }
+                let skip_decoder = match curr_decoder.current() {
+                    None => true,
+                    Some(curr) => {
+                        // check whether there is another decoder that has has updates from `firstClient`
+                        // or the above while loop was used and we are potentially missing updates
+                        curr.id().client != first_client
+                            || (iterated && curr.id().clock > curr_write_last)
+                    }
+                };
+                if skip_decoder {
+                    curr_write = Some(curr_write_block);
+                    continue;
+                }
 
-                                    if !curr_write_block.try_squash(&curr_block) {
-                                        result.blocks.add_block(curr_write_block);
-                                        curr_write = Some(curr_block);
-                                        curr = curr_decoder.next();
-                                    }
-                                }
-                            }
-                        } else {
-                            // current decoder is empty
-                            continue;
-                        }
+                let curr_id = *curr_decoder.current().unwrap().id();
+                if first_client != curr_write_block.id().client {
+                    result.blocks.add_block(curr_write_block);
+                    curr_write = curr_decoder.advance();
+                } else if curr_write_last < curr_id.clock {
+                    // fill the gap between written and current block with a skip
+                    if let BlockCarrier::Skip(skip) = &mut curr_write_block {
+                        // extend existing skip
+                        skip.len = curr_id.clock - skip.id.clock;
+                        curr_write = Some(curr_write_block);
                     } else {
-                        curr_write = Some(curr_block);
-                        curr = curr_decoder.next();
+                        result.blocks.add_block(curr_write_block);
+                        let diff = curr_id.clock - curr_write_last;
+                        let skip = BlockRange::new(ID::new(first_client, curr_write_last), diff);
+                        curr_write = Some(BlockCarrier::Skip(skip));
                     }
                 } else {
-                    continue;
-                }
+                    let mut curr = curr_decoder.advance().unwrap();
+                    let diff = curr_write_last - curr_id.clock;
+                    if diff > 0 {
+                        if let BlockCarrier::Skip(skip) = &mut curr_write_block {
+                            // prefer to slice Skip because the other struct might contain more information
+                            skip.len -= diff;
+                        } else {
+                            curr = curr.splice(diff).unwrap();
+                        }
+                    }
 
-                while let Some(next) = curr.take() {
-                    let block = curr_write.take().unwrap();
-                    let nid = next.id();
-                    if nid.client == first_client && nid.clock == block.id().clock + block.len() {
-                        result.blocks.add_block(block);
-                        curr_write = Some(next);
-                        curr = curr_decoder.next();
+                    if curr_write_block.try_squash(&curr) {
+                        curr_write = Some(curr_write_block);
                     } else {
-                        break;
+                        result.blocks.add_block(curr_write_block);
+                        curr_write = Some(curr);
                     }
                 }
             } else {
-                break;
+                curr_write = curr_decoder.advance();
+            }
+
+            loop {
+                let block = curr_write.as_ref().unwrap();
+                let next_clock = block.id().clock + block.len();
+                match curr_decoder.current() {
+                    Some(next)
+                        if next.id().client == first_client
+                            && next.id().clock == next_clock
+                            && !next.is_skip() =>
+                    {
+                        result.blocks.add_block(curr_write.take().unwrap());
+                        curr_write = curr_decoder.advance();
+                    }
+                    _ => break,
+                }
             }
         }
 
@@ -670,13 +1097,165 @@ impl Encode for Update {
     }
 }
 
+impl Update {
+    /// Decodes an update in chunks of at most `chunk_size` blocks. Every chunk is passed to a
+    /// given callback `f` as a separate update as soon as it has been decoded. Delete set is
+    /// passed as the last chunk. This way a whole update never has to be kept in memory at once.
+    pub(crate) fn decode_chunked<D, F>(
+        decoder: &mut D,
+        chunk_size: usize,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        D: Decoder,
+        F: FnMut(Update) -> Result<(), Error>,
+    {
+        let clients_len: u32 = decoder.read_var()?;
+        for _ in 0..clients_len {
+            let mut blocks_len = decoder.read_var::<u32>()? as usize;
+            let client = decoder.read_client()?;
+            let mut clock: u32 = decoder.read_var()?;
+            while blocks_len > 0 {
+                let len = blocks_len.min(chunk_size);
+                let mut blocks = VecDeque::with_capacity(len.min(MAX_PREALLOC));
+                for _ in 0..len {
+                    let id = ID::new(client, clock);
+                    let block = Self::decode_block(id, decoder)?;
+                    clock = clock.checked_add(block.len()).ok_or(Error::ClockOverflow)?;
+                    blocks.push_back(block);
+                }
+                blocks_len -= len;
+                let mut update = Update::new();
+                update.blocks.clients.insert(client, blocks);
+                f(update)?;
+            }
+        }
+        let delete_set = DeleteSet::decode(decoder)?;
+        if !delete_set.is_empty() {
+            let mut update = Update::new();
+            update.delete_set = delete_set;
+            f(update)?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies an [Update] incrementally, a batch of blocks at a time. This way integration of a huge
+/// update can be spread over many calls, i.e. to let an async executor run other tasks in between,
+/// instead of blocking a thread until an entire update is applied.
+///
+/// An update is validated against [Options::update_filter], [Options::write_policy] and
+/// [Options::limits] as a whole, when the first batch is applied. Each batch is integrated as a
+/// separate update: blocks, which depend on blocks from the batches that have not been applied
+/// yet, are stashed as pending and integrated once their dependencies arrive. Delete set is applied
+/// as the last batch.
+///
+/// Example:
+/// ```rust
+/// use yrs::{ApplyDriver, Doc, StateVector, Update};
+/// use yrs::updates::decoder::Decode;
+///
+/// let source = Doc::new();
+/// let text = source.transact().get_text("text");
+/// for i in 0..100 {
+///     text.push(&mut source.transact(), &i.to_string());
+/// }
+/// let update = source.encode_state_as_update_v1(&StateVector::default());
+///
+/// let doc = Doc::new();
+/// let mut driver = ApplyDriver::new(Update::decode_v1(&update).unwrap(), 10);
+/// while driver.step(&mut doc.transact()).unwrap() {
+///     // yield to other tasks here, i.e. `tokio::task::yield_now().await`
+/// }
+/// assert_eq!(doc.transact().get_text("text").to_string(), text.to_string());
+/// ```
+///
+/// [Options::update_filter]: crate::Options::update_filter
+/// [Options::write_policy]: crate::Options::write_policy
+/// [Options::limits]: crate::Options::limits
+pub struct ApplyDriver {
+    update: Update,
+    clients: Vec<ClientID>,
+    batch_size: usize,
+    checked: bool,
+}
+
+impl ApplyDriver {
+    /// Creates a new driver, which applies a given `update` in batches of at most `batch_size`
+    /// blocks.
+    pub fn new(update: Update, batch_size: usize) -> Self {
+        let mut clients: Vec<ClientID> = update.blocks.clients.keys().cloned().collect();
+        // higher client ids are integrated first, just like in case of Update::integrate
+        clients.sort();
+        ApplyDriver {
+            update,
+            clients,
+            batch_size: batch_size.max(1),
+            checked: false,
+        }
+    }
+
+    /// Checks if an entire update has already been applied.
+    pub fn is_finished(&self) -> bool {
+        self.remaining() == 0 && self.update.delete_set.is_empty()
+    }
+
+    /// Returns a number of blocks, which have not been applied yet.
+    pub fn remaining(&self) -> usize {
+        self.update.blocks.clients.values().map(VecDeque::len).sum()
+    }
+
+    /// Applies the next batch of blocks within a given transaction. Returns `true` if there are
+    /// still more batches left to apply.
+    ///
+    /// Returns an error if an update has been rejected by the document's options. In that case
+    /// none of its blocks are applied.
+    pub fn step(&mut self, txn: &mut Transaction) -> Result<bool, UpdateError> {
+        if !self.checked {
+            txn.check_update(&self.update)?;
+            self.checked = true;
+        }
+        if let Some(batch) = self.next_batch() {
+            txn.apply_checked_update(batch);
+        }
+        Ok(!self.is_finished())
+    }
+
+    fn next_batch(&mut self) -> Option<Update> {
+        while let Some(&client) = self.clients.last() {
+            if let Some(blocks) = self.update.blocks.clients.get_mut(&client) {
+                let len = blocks.len().min(self.batch_size);
+                let batch: VecDeque<_> = blocks.drain(..len).collect();
+                if blocks.is_empty() {
+                    self.update.blocks.clients.remove(&client);
+                    self.clients.pop();
+                }
+                if !batch.is_empty() {
+                    let mut update = Update::new();
+                    update.blocks.clients.insert(client, batch);
+                    return Some(update);
+                }
+            } else {
+                self.clients.pop();
+            }
+        }
+        if self.update.delete_set.is_empty() {
+            None
+        } else {
+            let mut update = Update::new();
+            update.delete_set = std::mem::take(&mut self.update.delete_set);
+            Some(update)
+        }
+    }
+}
+
 impl Decode for Update {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
         // read blocks
         let clients_len: u32 = decoder.read_var()?;
         let mut blocks = UpdateBlocks {
             clients: HashMap::with_capacity_and_hasher(
-                clients_len as usize,
+                (clients_len as usize).min(MAX_PREALLOC),
                 BuildHasherDefault::default(),
             ),
         };
@@ -688,12 +1267,12 @@ impl Decode for Update {
             let blocks = blocks
                 .clients
                 .entry(client)
-                .or_insert_with(|| VecDeque::with_capacity(blocks_len));
+                .or_insert_with(|| VecDeque::with_capacity(blocks_len.min(MAX_PREALLOC)));
 
             for _ in 0..blocks_len {
                 let id = ID::new(client, clock);
                 let block = Self::decode_block(id, decoder)?;
-                clock += block.len();
+                clock = clock.checked_add(block.len()).ok_or(Error::ClockOverflow)?;
                 blocks.push_back(block);
             }
         }
@@ -704,9 +1283,9 @@ impl Decode for Update {
 }
 
 /// Similar to [Peekable], but can be used in situation when [Peekable::peek] is not allowed
-/// due to a lack of of `&mut self` reference. [Memo] can be proactively advanced using
-/// [Memo::advance] which works similar to [Peekable::peek], but later peeked element can still be
-/// accessed using [Memo::current] which doesn't require mutable reference.
+/// due to a lack of of `&mut self` reference. [Memo::current] returns currently memoized element
+/// without a need for mutable reference, while [Memo::advance] returns it and memoizes the next
+/// one.
 struct Memo<I: Iterator + ?Sized> {
     current: Option<I::Item>,
     iter: I,
@@ -717,25 +1296,8 @@ impl<I: Iterator> Memo<I> {
         self.current.as_ref()
     }
 
-    fn advance(&mut self) -> bool {
-        match self.iter.next() {
-            None => false,
-            other => {
-                self.current = other;
-                true
-            }
-        }
-    }
-}
-
-impl<I: Iterator> Iterator for Memo<I> {
-    type Item = I::Item;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.current.take() {
-            None => self.iter.next(),
-            Some(n) => Some(n),
-        }
+    fn advance(&mut self) -> Option<I::Item> {
+        std::mem::replace(&mut self.current, self.iter.next())
     }
 }
 
@@ -862,6 +1424,14 @@ impl From<Box<Block>> for BlockCarrier {
     }
 }
 
+impl From<Block> for BlockCarrier {
+    fn from(block: Block) -> Self {
+        let mut block = Box::new(block);
+        BlockPtr::from(&mut block).link_branch();
+        BlockCarrier::Block(block)
+    }
+}
+
 impl Encode for BlockCarrier {
     fn encode<E: Encoder>(&self, encoder: &mut E) {
         match self {
@@ -916,7 +1486,8 @@ impl Into<Store> for Update {
                 .get_client_blocks_with_capacity_mut(client_id, vec.len());
             for block in vec {
                 if let BlockCarrier::Block(block) = block {
-                    blocks.push(block);
+                    let ptr = blocks.alloc(*block);
+                    blocks.push(ptr);
                 } else {
                     panic!("Cannot convert Update into block store - Skip block detected");
                 }
@@ -967,6 +1538,95 @@ impl<'a> Iterator for Blocks<'a> {
     }
 }
 
+/// Iterator over blocks of an [Update], returned by [Update::blocks].
+pub struct UpdateBlockIter<'a>(Blocks<'a>);
+
+impl<'a> Iterator for UpdateBlockIter<'a> {
+    type Item = UpdateBlock<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = match self.0.next()? {
+            BlockCarrier::Skip(skip) => UpdateBlock::Skip {
+                id: skip.id,
+                len: skip.len,
+            },
+            BlockCarrier::Block(block) => match block.as_ref() {
+                Block::GC(gc) => UpdateBlock::GC {
+                    id: gc.id,
+                    len: gc.len,
+                },
+                Block::Item(item) => UpdateBlock::Item(UpdateItem {
+                    id: item.id,
+                    len: item.len,
+                    origin: item.origin,
+                    right_origin: item.right_origin,
+                    parent: match &item.parent {
+                        TypePtr::Named(name) => Some(ItemParent::Root(name)),
+                        TypePtr::ID(id) => Some(ItemParent::Nested(*id)),
+                        _ => None,
+                    },
+                    parent_sub: item.parent_sub.as_deref(),
+                    content: &item.content,
+                }),
+            },
+        };
+        Some(block)
+    }
+}
+
+/// A single block of an [Update], returned by [Update::blocks].
+#[derive(Debug)]
+pub enum UpdateBlock<'a> {
+    /// Block containing inserted content.
+    Item(UpdateItem<'a>),
+    /// Block of content, which has been deleted and garbage collected.
+    GC { id: ID, len: u32 },
+    /// A range of client's clock values, which is not included in an update.
+    Skip { id: ID, len: u32 },
+}
+
+impl<'a> UpdateBlock<'a> {
+    /// Returns an identifier of the first element of this block.
+    pub fn id(&self) -> &ID {
+        match self {
+            UpdateBlock::Item(item) => &item.id,
+            UpdateBlock::GC { id, .. } => id,
+            UpdateBlock::Skip { id, .. } => id,
+        }
+    }
+}
+
+/// An item block of an [Update]. Unlike items integrated into a document, it only knows its
+/// neighbours by IDs of its origins.
+#[derive(Debug)]
+pub struct UpdateItem<'a> {
+    /// Unique identifier of this item.
+    pub id: ID,
+    /// Number of clock values occupied by this item.
+    pub len: u32,
+    /// ID of an element, that was a left neighbour of this item when it was inserted.
+    pub origin: Option<ID>,
+    /// ID of an element, that was a right neighbour of this item when it was inserted.
+    pub right_origin: Option<ID>,
+    /// Parent collection of this item. It's only encoded when an item has no origins - otherwise
+    /// it's the same as the parent of its origins.
+    pub parent: Option<ItemParent<'a>>,
+    /// A key under which this item was inserted, if its parent acts as a map. Just like
+    /// [UpdateItem::parent], it's only present when an item has no origins.
+    pub parent_sub: Option<&'a str>,
+    /// Content of this item.
+    pub content: &'a ItemContent,
+}
+
+/// A reference to a parent collection of an [UpdateItem].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemParent<'a> {
+    /// Root-level type with a given name.
+    Root(&'a str),
+    /// Nested type, which has been inserted as an item with a given ID.
+    Nested(ID),
+}
+
 pub(crate) struct IntoBlocks {
     current_client: std::vec::IntoIter<(ClientID, VecDeque<BlockCarrier>)>,
     current_block: Option<std::collections::vec_deque::IntoIter<BlockCarrier>>,
@@ -1011,11 +1671,15 @@ impl Iterator for IntoBlocks {
 #[cfg(test)]
 mod test {
     use crate::block::{Item, ItemContent};
-    use crate::types::TypePtr;
-    use crate::update::{BlockCarrier, Update};
+    use crate::testing::exchange_updates;
+    use crate::types::{BranchPtr, TypePtr};
+    use crate::update::{ApplyDriver, BlockCarrier, Update, UpdateError};
     use crate::updates::decoder::{Decode, DecoderV1};
-    use crate::{Doc, ID};
+    use crate::{Doc, ItemParent, Options, PrelimMap, StateVector, UpdateBlock, UpdateFilter, ID};
     use lib0::decoding::Cursor;
+    use lib0::error::Error;
+    use std::collections::HashMap;
+    use std::rc::Rc;
 
     #[test]
     fn update_decode() {
@@ -1058,6 +1722,60 @@ mod test {
         assert_eq!(block, &expected);
     }
 
+    #[test]
+    fn update_blocks() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        {
+            let mut txn = d1.transact();
+            let text = txn.get_text("text");
+            text.insert(&mut txn, 0, "abc");
+            text.remove_range(&mut txn, 1, 1);
+        }
+        {
+            let mut txn = d2.transact();
+            let map = txn.get_map("map");
+            map.insert(&mut txn, "nested", PrelimMap::<bool>::new());
+            let nested = map.get("nested").unwrap().to_ymap().unwrap();
+            nested.insert(&mut txn, "key", true);
+        }
+        let u1 = d1.encode_state_as_update_v1(&StateVector::default());
+        let u2 = d2.encode_state_as_update_v1(&StateVector::default());
+        let update = Update::merge_updates(vec![
+            Update::decode_v1(&u1).unwrap(),
+            Update::decode_v1(&u2).unwrap(),
+        ]);
+
+        let mut touched = Vec::new();
+        for block in update.blocks() {
+            if let UpdateBlock::Item(item) = block {
+                touched.push((item.id.client, item.parent, item.parent_sub));
+            }
+        }
+        assert_eq!(
+            touched,
+            vec![
+                (2, Some(ItemParent::Root("map")), Some("nested")),
+                (2, Some(ItemParent::Nested(ID::new(2, 0))), Some("key")),
+                (1, Some(ItemParent::Root("text")), None),
+                (1, None, None),
+                (1, None, None),
+            ]
+        );
+
+        let ids: Vec<_> = update.blocks().map(|b| *b.id()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                ID::new(2, 0),
+                ID::new(2, 1),
+                ID::new(1, 0),
+                ID::new(1, 1),
+                ID::new(1, 2),
+            ]
+        );
+    }
+
     #[test]
     fn update_merge() {
         let d1 = Doc::with_client_id(1);
@@ -1099,4 +1817,346 @@ mod test {
         assert_eq!(str1, str2);
         assert_eq!(str2, str3);
     }
+
+    #[test]
+    fn merge_updates_of_many_clients() {
+        let docs: Vec<_> = (1..=3).map(Doc::with_client_id).collect();
+        let mut updates = Vec::new();
+        for (i, doc) in docs.iter().enumerate() {
+            let mut txn = doc.transact();
+            txn.get_text("text").push(&mut txn, &"abc".repeat(i + 1));
+            txn.get_map("map").insert(&mut txn, i.to_string(), i as f64);
+            drop(txn);
+            updates.push(doc.encode_state_as_update_v1(&StateVector::default()));
+        }
+        let expected = Doc::with_client_id(4);
+        for u in updates.iter() {
+            expected.transact().apply_update_v1(u).unwrap();
+        }
+        let expected = expected.transact().to_json();
+
+        // merged update contains blocks of all clients, no matter the order of merged updates
+        for order in [[0, 1, 2], [2, 0, 1], [1, 2, 0]] {
+            let merged = Update::merge_updates(
+                order.map(|i| Update::decode_v1(updates[i].as_slice()).unwrap()),
+            );
+            let sv = merged.state_vector();
+            assert_eq!((sv.get(&1), sv.get(&2), sv.get(&3)), (4, 7, 10));
+
+            let doc = Doc::with_client_id(4);
+            let mut txn = doc.transact();
+            txn.apply_update(merged);
+            assert!(!txn.has_pending());
+            assert_eq!(txn.to_json(), expected);
+        }
+    }
+
+    #[test]
+    fn merge_updates_with_gaps_and_overlaps() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.transact().get_text("text");
+        let mut updates = Vec::new();
+        for chunk in ["abc", "def", "ghi"] {
+            let sv = d1.transact().state_vector();
+            text.push(&mut d1.transact(), chunk);
+            updates.push(d1.encode_state_as_update_v1(&sv));
+        }
+        let full = d1.encode_state_as_update_v1(&StateVector::default());
+        let decode = |u: &Vec<u8>| Update::decode_v1(u.as_slice()).unwrap();
+
+        // a gap between merged updates is filled with a skip, which can be filled later on
+        let merged = Update::merge_updates(vec![decode(&updates[2]), decode(&updates[0])]);
+        assert_eq!(merged.state_vector().get(&1), 9);
+        let d2 = Doc::with_client_id(2);
+        let mut txn = d2.transact();
+        txn.apply_update(merged);
+        assert!(txn.has_pending());
+        assert_eq!(txn.get_text("text").to_string(), "abc");
+        txn.apply_update(decode(&updates[1]));
+        assert!(!txn.has_pending());
+        assert_eq!(txn.get_text("text").to_string(), "abcdefghi");
+        drop(txn);
+
+        // overlapping updates don't duplicate blocks
+        let merged = Update::merge_updates(vec![
+            decode(&updates[1]),
+            decode(&full),
+            decode(&updates[0]),
+        ]);
+        let d3 = Doc::with_client_id(3);
+        let mut txn = d3.transact();
+        txn.apply_update(merged);
+        assert!(!txn.has_pending());
+        assert_eq!(txn.get_text("text").to_string(), "abcdefghi");
+    }
+
+    #[test]
+    fn apply_update_from_reader() {
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.transact().get_text("text");
+        // inserting at the beginning prevents blocks from being squashed together
+        for i in 0..1500 {
+            txt.insert(&mut d1.transact(), 0, if i % 2 == 0 { "a" } else { "b" });
+        }
+        txt.remove_range(&mut d1.transact(), 0, 10);
+        let expected = txt.to_string();
+
+        // update contains more blocks than a single chunk can fit
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+        let d2 = Doc::with_client_id(2);
+        let mut txn = d2.transact();
+        txn.apply_update_from(update.as_slice()).unwrap();
+        assert_eq!(txn.get_text("text").to_string(), expected);
+        assert_eq!(txn.get_text("text").len(), 1490);
+
+        // truncated stream is reported as an error
+        let d3 = Doc::with_client_id(3);
+        let mut txn = d3.transact();
+        assert!(txn.apply_update_from(&update[..update.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn decode_malformed_updates() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact();
+            let txt = txn.get_text("text");
+            txt.insert(&mut txn, 0, "hello world");
+            txt.remove_range(&mut txn, 2, 3);
+            let map = txn.get_map("map");
+            map.insert(&mut txn, "key", vec![1, 2, 3]);
+        }
+        let v1 = doc.encode_state_as_update_v1(&StateVector::default());
+        let v2 = doc.encode_state_as_update_v2(&StateVector::default());
+        for i in 0..v1.len() {
+            assert!(Update::decode_v1(&v1[..i]).is_err());
+        }
+        for i in 0..v2.len() {
+            let _ = Update::decode_v2(&v2[..i]);
+        }
+
+        // var int exceeding 64 bits
+        let e = Update::decode_v1(&[0xff; 16]).unwrap_err();
+        assert!(matches!(e, Error::VarIntSizeExceeded(_)));
+        let e = StateVector::decode_v1(&[
+            1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        ])
+        .unwrap_err();
+        assert!(matches!(e, Error::VarIntSizeExceeded(_)));
+        // forged collection length
+        let e = StateVector::decode_v1(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1]).unwrap_err();
+        assert!(matches!(e, Error::EndOfBuffer(_)));
+        // unknown type ref
+        let e = Update::decode_v1(&[1, 1, 1, 0, 7, 1, 1, b'a', 9, 0]).unwrap_err();
+        assert!(matches!(e, Error::InvalidTypeRef(9)));
+        // unknown Any value tag
+        let e = Update::decode_v1(&[1, 1, 1, 0, 8, 1, 1, b'a', 1, 0, 0]).unwrap_err();
+        assert!(matches!(e, Error::UnexpectedValue));
+        // empty block
+        let e = Update::decode_v1(&[1, 1, 1, 0, 4, 1, 1, b'a', 0, 0]).unwrap_err();
+        assert!(matches!(e, Error::UnexpectedValue));
+        // block which is its own origin
+        let e = Update::decode_v1(&[1, 1, 1, 0, 0x84, 1, 0, 1, b'a', 0]).unwrap_err();
+        assert!(matches!(e, Error::UnexpectedValue));
+        // block clock range exceeding u32::MAX
+        let e = Update::decode_v1(&[
+            1, 1, 1, 0xff, 0xff, 0xff, 0xff, 0x0f, 4, 1, 1, b'a', 2, b'a', b'b', 0,
+        ])
+        .unwrap_err();
+        assert!(matches!(e, Error::ClockOverflow));
+
+        // malformed updates are never applied
+        let d2 = Doc::with_client_id(2);
+        let mut txn = d2.transact();
+        assert!(txn.apply_update_v1(&v1[..v1.len() - 1]).is_err());
+        assert!(txn.apply_update_v2(&[0xff; 8]).is_err());
+        assert_eq!(txn.state_vector(), StateVector::default());
+        txn.apply_update_v1(&v1).unwrap();
+        assert_eq!(txn.get_text("text").to_string(), "he world");
+    }
+
+    #[test]
+    fn decode_interned_strings() {
+        let doc = Doc::with_client_id(1);
+        for name in ["a", "b", "a", "b"] {
+            // each change is a separate block without an origin, so it needs to store both
+            // its parent name and a key
+            let mut txn = doc.transact();
+            let map = txn.get_map(name);
+            map.insert(&mut txn, "key", map.len());
+            map.insert(&mut txn, map.len().to_string(), true);
+        }
+        let v1 = doc.encode_state_as_update_v1(&StateVector::default());
+        let v2 = doc.encode_state_as_update_v2(&StateVector::default());
+        for update in [
+            Update::decode_v1(&v1).unwrap(),
+            Update::decode_v2(&v2).unwrap(),
+        ] {
+            let mut names: HashMap<&str, &Rc<str>> = HashMap::new();
+            let mut keys: HashMap<&str, &Rc<str>> = HashMap::new();
+            let mut count = 0;
+            for block in update.blocks.blocks() {
+                if let BlockCarrier::Block(block) = block {
+                    let item = block.as_item().unwrap();
+                    if let (TypePtr::Named(name), Some(key)) = (&item.parent, &item.parent_sub) {
+                        let interned = names.entry(name.as_ref()).or_insert(name);
+                        assert!(Rc::ptr_eq(interned, name));
+                        let interned = keys.entry(key.as_ref()).or_insert(key);
+                        assert!(Rc::ptr_eq(interned, key));
+                        count += 1;
+                    }
+                }
+            }
+            assert_eq!(names.len(), 2);
+            assert!(count > names.len());
+            assert!(count > keys.len());
+        }
+    }
+
+    #[test]
+    fn pending_updates_out_of_order() {
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.transact().get_text("text");
+        txt.insert(&mut d1.transact(), 0, "abc");
+        let u1 = d1.encode_state_as_update_v1(&StateVector::default());
+        let sv = d1.transact().state_vector();
+        txt.insert(&mut d1.transact(), 3, "def");
+        txt.remove_range(&mut d1.transact(), 0, 1);
+        let u2 = d1.encode_state_as_update_v1(&sv);
+
+        let d2 = Doc::with_client_id(2);
+        let mut txn = d2.transact();
+        txn.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
+        assert!(txn.has_pending());
+        let pending = txn.pending_update().unwrap();
+        // blocks of client 1 up to clock 2 are missing
+        assert_eq!(pending.missing.get(&1), 2);
+        assert!(txn.pending_delete_set().is_some());
+        assert_eq!(txn.get_text("text").to_string(), "");
+
+        txn.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        assert!(!txn.has_pending());
+        assert_eq!(txn.get_text("text").to_string(), "bcdef");
+    }
+
+    #[test]
+    fn pending_updates_from_reader() {
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.transact().get_text("text");
+        for i in 0..1500 {
+            txt.insert(&mut d1.transact(), 0, if i % 2 == 0 { "a" } else { "b" });
+        }
+        let d2 = Doc::with_client_id(2);
+        {
+            let mut txn = d2.transact();
+            let u = d1.encode_state_as_update_v1(&StateVector::default());
+            txn.apply_update(Update::decode_v1(u.as_slice()).unwrap());
+            let txt = txn.get_text("text");
+            for i in 0..1100 {
+                txt.insert(&mut txn, i * 2, "c");
+            }
+            txt.remove_range(&mut txn, 0, 10);
+        }
+        let expected = d2.transact().get_text("text").to_string();
+
+        // blocks of client 2 are decoded first, but they depend on blocks of client 1,
+        // which are read later on in chunks
+        let update = d2.encode_state_as_update_v1(&StateVector::default());
+        let d3 = Doc::with_client_id(3);
+        let mut txn = d3.transact();
+        txn.apply_update_from(update.as_slice()).unwrap();
+        assert_eq!(txn.get_text("text").to_string(), expected);
+        assert!(!txn.has_pending());
+    }
+
+    #[test]
+    fn missing_ranges() {
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.transact().get_text("text");
+        txt.insert(&mut d1.transact(), 0, "abc");
+        let u1 = d1.encode_state_as_update_v1(&StateVector::default());
+        let sv = d1.transact().state_vector();
+        txt.insert(&mut d1.transact(), 3, "def");
+        let u2 = d1.encode_state_as_update_v1(&sv);
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+        d2.transact()
+            .apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        let mut txn = d2.transact();
+        txt2.insert(&mut txn, 3, "x");
+        txt2.remove_range(&mut txn, 0, 1);
+        txn.commit();
+        let u3 = txn.encode_update_v1();
+
+        let d3 = Doc::with_client_id(3);
+        let mut txn = d3.transact();
+        assert!(txn.missing().is_empty());
+        txn.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
+        txn.apply_update(Update::decode_v1(u3.as_slice()).unwrap());
+        let missing = txn.missing();
+        assert!(missing.contains(&ID::new(1, 0)));
+        assert!(missing.contains(&ID::new(1, 2)));
+        assert!(!missing.contains(&ID::new(1, 3)));
+        assert!(!missing.contains(&ID::new(2, 0)));
+
+        txn.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        assert!(txn.missing().is_empty());
+        assert_eq!(txn.get_text("text").to_string(), "bcdefx");
+    }
+
+    #[test]
+    fn apply_driver() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.transact().get_text("text");
+        let t2 = d2.transact().get_text("text");
+        for i in 0..10 {
+            t1.insert(&mut d1.transact(), 0, &i.to_string());
+            exchange_updates(&[&d1, &d2]);
+            t2.push(&mut d2.transact(), "ab");
+            t2.remove_range(&mut d2.transact(), 0, 1);
+            exchange_updates(&[&d1, &d2]);
+        }
+        let expected = t1.to_string();
+        let update = d1.encode_state_as_update_v1(&StateVector::default());
+
+        let d3 = Doc::with_client_id(3);
+        let mut driver = ApplyDriver::new(Update::decode_v1(&update).unwrap(), 3);
+        assert_eq!(
+            driver.remaining(),
+            Update::decode_v1(&update).unwrap().blocks().count()
+        );
+        let mut steps = 0;
+        while driver.step(&mut d3.transact()).unwrap() {
+            steps += 1;
+        }
+        assert!(steps > 1);
+        assert!(driver.is_finished());
+        assert_eq!(driver.remaining(), 0);
+        let mut txn = d3.transact();
+        assert_eq!(txn.get_text("text").to_string(), expected);
+        assert!(!txn.has_pending());
+    }
+
+    #[test]
+    fn apply_driver_rejected() {
+        let source = Doc::with_client_id(1);
+        let text = source.transact().get_text("text");
+        for _ in 0..5 {
+            text.push(&mut source.transact(), "abc");
+        }
+        text.remove_range(&mut source.transact(), 0, 1);
+        let update = source.encode_state_as_update_v1(&StateVector::default());
+
+        let mut options = Options::with_client_id(2);
+        options.update_filter = Some(UpdateFilter::new(|block| block.len < 3));
+        let doc = Doc::with_options(options);
+        let mut driver = ApplyDriver::new(Update::decode_v1(&update).unwrap(), 1);
+        assert!(matches!(
+            driver.step(&mut doc.transact()),
+            Err(UpdateError::Rejected(_))
+        ));
+        assert_eq!(doc.transact().state_vector().get(&1), 0);
+    }
 }
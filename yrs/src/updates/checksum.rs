@@ -0,0 +1,161 @@
+//! Integrity trailer appended to encoded updates, used to detect truncated or corrupted payloads
+//! (ie. read back from a persistent storage) before they are decoded.
+//!
+//! Trailer is 8 bytes long and consists of a payload length and its CRC-32 (IEEE) checksum, both
+//! written as 32-bit little endian unsigned integers. See
+//! [Doc::encode_state_as_update_v1_checked](crate::Doc::encode_state_as_update_v1_checked) and
+//! [Transaction::apply_update_v1_checked](crate::Transaction::apply_update_v1_checked).
+
+use lib0::error::Error;
+use std::convert::TryInto;
+
+/// Length of an integrity trailer in bytes.
+pub const TRAILER_LEN: usize = 8;
+
+/// Appends an integrity trailer to a given `payload`.
+pub fn append(mut payload: Vec<u8>) -> Vec<u8> {
+    let len = payload.len() as u32;
+    let checksum = crc32(&payload);
+    payload.extend_from_slice(&len.to_le_bytes());
+    payload.extend_from_slice(&checksum.to_le_bytes());
+    payload
+}
+
+/// Verifies an integrity trailer of given `data` and returns a payload it protects. Fails with
+/// [Error::LengthMismatch] if `data` has been truncated or extended, and with
+/// [Error::ChecksumMismatch] if its contents have been corrupted.
+pub fn verify(data: &[u8]) -> Result<&[u8], Error> {
+    if data.len() < TRAILER_LEN {
+        return Err(Error::LengthMismatch {
+            expected: TRAILER_LEN,
+            actual: data.len(),
+        });
+    }
+    let (payload, trailer) = data.split_at(data.len() - TRAILER_LEN);
+    let expected = u32::from_le_bytes(trailer[..4].try_into().unwrap()) as usize;
+    if expected != payload.len() {
+        return Err(Error::LengthMismatch {
+            expected,
+            actual: payload.len(),
+        });
+    }
+    let expected = u32::from_le_bytes(trailer[4..].try_into().unwrap());
+    let actual = crc32(payload);
+    if expected != actual {
+        return Err(Error::ChecksumMismatch { expected, actual });
+    }
+    Ok(payload)
+}
+
+/// Computes CRC-32 checksum (IEEE 802.3 polynomial) of a given `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = crc32_table();
+    let mut crc = !0u32;
+    for &b in data {
+        crc = TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use crate::updates::checksum::{append, crc32, verify, TRAILER_LEN};
+    use crate::{Doc, StateVector};
+    use lib0::error::Error;
+
+    #[test]
+    fn crc32_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414FA339
+        );
+    }
+
+    #[test]
+    fn checked_update_roundtrip() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.transact().get_text("text");
+        text.insert(&mut d1.transact(), 0, "hello world");
+        let sv = StateVector::default();
+        let v1 = d1.encode_state_as_update_v1_checked(&sv);
+        let v2 = d1.encode_state_as_update_v2_checked(&sv);
+        assert_eq!(verify(&v1).unwrap(), d1.encode_state_as_update_v1(&sv));
+        assert_eq!(verify(&v2).unwrap(), d1.encode_state_as_update_v2(&sv));
+
+        let d2 = Doc::with_client_id(2);
+        d2.transact().apply_update_v1_checked(&v1).unwrap();
+        assert_eq!(d2.transact().get_text("text").to_string(), "hello world");
+
+        let d3 = Doc::with_client_id(3);
+        d3.transact().apply_update_v2_checked(&v2).unwrap();
+        assert_eq!(d3.transact().get_text("text").to_string(), "hello world");
+    }
+
+    #[test]
+    fn checked_update_corruption() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.transact().get_text("text");
+        text.insert(&mut doc.transact(), 0, "hello world");
+        let data = doc.encode_state_as_update_v1_checked(&StateVector::default());
+        let payload_len = data.len() - TRAILER_LEN;
+
+        // truncation
+        let target = Doc::with_client_id(2);
+        let res = target
+            .transact()
+            .apply_update_v1_checked(&data[..data.len() - 3]);
+        assert!(matches!(res, Err(Error::LengthMismatch { .. })));
+        let res = target.transact().apply_update_v1_checked(&data[..4]);
+        assert!(matches!(
+            res,
+            Err(Error::LengthMismatch {
+                expected: TRAILER_LEN,
+                actual: 4
+            })
+        ));
+
+        // flipped bit in a payload
+        let mut corrupted = data.clone();
+        corrupted[payload_len / 2] ^= 0x10;
+        let res = target.transact().apply_update_v1_checked(&corrupted);
+        assert!(matches!(res, Err(Error::ChecksumMismatch { .. })));
+
+        // nothing was applied
+        assert_eq!(target.transact().state_vector(), StateVector::default());
+
+        // trailer of an unchecked payload
+        let unchecked = append(b"abc".to_vec());
+        assert_eq!(verify(&unchecked).unwrap(), b"abc");
+        let mut extended = unchecked;
+        extended.insert(0, 0);
+        assert!(matches!(
+            verify(&extended),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                actual: 4
+            })
+        ));
+    }
+}
@@ -1,11 +1,17 @@
 use crate::block::ClientID;
 use crate::types::TypeRefs;
 use crate::*;
-use lib0::decoding::Read;
+use lib0::decoding::{IoReader, Read};
 use lib0::error::Error;
 use lib0::{any::Any, decoding::Cursor};
+use std::collections::HashSet;
 use std::rc::Rc;
 
+/// Upper bound on a number of elements preallocated by decoders up front. Collection lengths are
+/// read from untrusted input, so a forged length must not be able to exhaust memory before the
+/// decoder finds out that there's not enough data to back it.
+pub(crate) const MAX_PREALLOC: usize = 1024;
+
 /// A trait that can be implemented by any other type in order to support lib0 decoding capability.
 pub trait Decode: Sized {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error>;
@@ -71,18 +77,46 @@ pub trait Decoder: Read {
     /// Read key string.
     fn read_key(&mut self) -> Result<Rc<str>, Error>;
 
+    /// Read a string, which is likely to repeat many times within the same payload, ie. a name of
+    /// a root type or a key of a map entry. Decoders are free to intern such strings, so that all
+    /// of their occurrences share a single allocation.
+    fn read_interned(&mut self) -> Result<Rc<str>, Error> {
+        let str: Rc<str> = self.read_string()?.into();
+        Ok(str)
+    }
+
     /// Consume a rest of the decoded buffer data and return it without parsing.
     fn read_to_end(&mut self) -> Result<&[u8], Error>;
 }
 
+/// A set of strings, which have already been decoded from a current payload.
+#[derive(Default)]
+struct StringPool(HashSet<Rc<str>>);
+
+impl StringPool {
+    fn intern(&mut self, str: &str) -> Rc<str> {
+        if let Some(interned) = self.0.get(str) {
+            interned.clone()
+        } else {
+            let interned: Rc<str> = str.into();
+            self.0.insert(interned.clone());
+            interned
+        }
+    }
+}
+
 /// Version 1 of lib0 decoder.
 pub struct DecoderV1<'a> {
     cursor: Cursor<'a>,
+    strings: StringPool,
 }
 
 impl<'a> DecoderV1<'a> {
     pub fn new(cursor: Cursor<'a>) -> Self {
-        DecoderV1 { cursor }
+        DecoderV1 {
+            cursor,
+            strings: StringPool::default(),
+        }
     }
 
     fn read_id(&mut self) -> Result<ID, Error> {
@@ -90,6 +124,12 @@ impl<'a> DecoderV1<'a> {
         let clock = self.read_var()?;
         Ok(ID::new(client as ClientID, clock))
     }
+
+    /// Returns an offset of the next byte to be read from an underlying buffer.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn position(&self) -> usize {
+        self.cursor.next
+    }
 }
 
 impl<'a> From<Cursor<'a>> for DecoderV1<'a> {
@@ -182,8 +222,12 @@ impl<'a> Decoder for DecoderV1<'a> {
 
     #[inline]
     fn read_key(&mut self) -> Result<Rc<str>, Error> {
-        let str: Rc<str> = self.read_string()?.into();
-        Ok(str)
+        self.read_interned()
+    }
+
+    fn read_interned(&mut self) -> Result<Rc<str>, Error> {
+        let str = self.cursor.read_string()?;
+        Ok(self.strings.intern(str))
     }
 
     #[inline]
@@ -192,10 +236,122 @@ impl<'a> Decoder for DecoderV1<'a> {
     }
 }
 
+/// Version 1 of lib0 decoder, which reads its data incrementally from an underlying
+/// [std::io::Read] source instead of an in-memory buffer. Only the bytes necessary to decode
+/// the next requested value are being read.
+///
+/// Version 2 of lib0 encoding uses column-oriented layout, which requires a whole payload to be
+/// available before decoding, therefore it has no streaming equivalent.
+pub struct StreamDecoderV1<R> {
+    reader: IoReader<R>,
+    strings: StringPool,
+}
+
+impl<R: std::io::Read> StreamDecoderV1<R> {
+    pub fn new(reader: R) -> Self {
+        StreamDecoderV1 {
+            reader: IoReader::new(reader),
+            strings: StringPool::default(),
+        }
+    }
+
+    fn read_id(&mut self) -> Result<ID, Error> {
+        let client: u32 = self.read_var()?;
+        let clock = self.read_var()?;
+        Ok(ID::new(client as ClientID, clock))
+    }
+}
+
+impl<R: std::io::Read> Read for StreamDecoderV1<R> {
+    #[inline]
+    fn read_exact(&mut self, len: usize) -> Result<&[u8], Error> {
+        self.reader.read_exact(len)
+    }
+}
+
+impl<R: std::io::Read> Decoder for StreamDecoderV1<R> {
+    #[inline]
+    fn reset_ds_cur_val(&mut self) {
+        /* no op */
+    }
+
+    #[inline]
+    fn read_ds_clock(&mut self) -> Result<u32, Error> {
+        self.read_var()
+    }
+
+    #[inline]
+    fn read_ds_len(&mut self) -> Result<u32, Error> {
+        self.read_var()
+    }
+
+    #[inline]
+    fn read_left_id(&mut self) -> Result<ID, Error> {
+        self.read_id()
+    }
+
+    #[inline]
+    fn read_right_id(&mut self) -> Result<ID, Error> {
+        self.read_id()
+    }
+
+    #[inline]
+    fn read_client(&mut self) -> Result<ClientID, Error> {
+        let client: u32 = self.read_var()?;
+        Ok(client as ClientID)
+    }
+
+    #[inline]
+    fn read_info(&mut self) -> Result<u8, Error> {
+        self.read_u8()
+    }
+
+    #[inline]
+    fn read_parent_info(&mut self) -> Result<bool, Error> {
+        let info: u32 = self.read_var()?;
+        Ok(info == 1)
+    }
+
+    #[inline]
+    fn read_type_ref(&mut self) -> Result<u8, Error> {
+        self.read_u8()
+    }
+
+    #[inline]
+    fn read_len(&mut self) -> Result<u32, Error> {
+        self.read_var()
+    }
+
+    #[inline]
+    fn read_any(&mut self) -> Result<Any, Error> {
+        Any::decode(self)
+    }
+
+    fn read_json(&mut self) -> Result<Any, Error> {
+        let src = self.read_string()?;
+        Any::from_json(src)
+    }
+
+    #[inline]
+    fn read_key(&mut self) -> Result<Rc<str>, Error> {
+        self.read_interned()
+    }
+
+    fn read_interned(&mut self) -> Result<Rc<str>, Error> {
+        let str = self.reader.read_string()?;
+        Ok(self.strings.intern(str))
+    }
+
+    fn read_to_end(&mut self) -> Result<&[u8], Error> {
+        self.reader.read_to_end()
+    }
+}
+
 /// Version 2 of lib0 decoder.
 pub struct DecoderV2<'a> {
     cursor: Cursor<'a>,
     keys: Vec<Rc<str>>,
+    strings: StringPool,
     ds_curr_val: u32,
     key_clock_decoder: IntDiffOptRleDecoder<'a>,
     client_decoder: UIntOptRleDecoder<'a>,
@@ -234,6 +390,7 @@ impl<'a> DecoderV2<'a> {
             cursor,
             ds_curr_val: 0,
             keys: Vec::new(),
+            strings: StringPool::default(),
             key_clock_decoder: IntDiffOptRleDecoder::new(Cursor::new(key_clock_buf)),
             client_decoder: UIntOptRleDecoder::new(Cursor::new(client_buf)),
             left_clock_decoder: IntDiffOptRleDecoder::new(Cursor::new(left_clock_buf)),
@@ -246,27 +403,27 @@ impl<'a> DecoderV2<'a> {
         })
     }
 
-    fn read_usize(buf: &[u8], idx: &mut usize) -> usize {
+    fn read_usize(buf: &[u8], idx: &mut usize) -> Result<usize, Error> {
         let mut num: usize = 0;
         let mut len: usize = 0;
         loop {
-            let r = buf[*idx];
+            let r = *buf.get(*idx).ok_or(Error::EndOfBuffer(1))?;
             *idx += 1;
             num |= (r as usize & 127) << len;
             len += 7;
             if r < 128 {
-                return num;
+                return Ok(num);
             }
-            if len > 128 {
-                panic!("Integer out of range!");
+            if len >= usize::BITS as usize {
+                return Err(Error::VarIntSizeExceeded(usize::BITS as u8));
             }
         }
     }
 
     fn read_buf(buf: &'a [u8], idx: &mut usize) -> Result<&'a [u8], Error> {
-        let len = Self::read_usize(buf, idx);
+        let len = Self::read_usize(buf, idx)?;
         let start = *idx;
-        let end = start + len;
+        let end = start.checked_add(len).ok_or(Error::EndOfBuffer(len))?;
         if end <= buf.len() {
             let slice = &buf[start..end];
             *idx += len as usize;
@@ -363,6 +520,11 @@ impl<'a> Decoder for DecoderV2<'a> {
         }
     }
 
+    fn read_interned(&mut self) -> Result<Rc<str>, Error> {
+        let str = self.string_decoder.read_str()?;
+        Ok(self.strings.intern(str))
+    }
+
     #[inline]
     fn read_to_end(&mut self) -> Result<&[u8], Error> {
         Ok(&self.cursor.buf[self.cursor.next..])
@@ -477,7 +639,7 @@ impl<'a> StringDecoder<'a> {
         let buf = cursor.buf;
         let mut next = cursor.next;
         let str_bin = DecoderV2::read_buf(buf, &mut next)?;
-        let str = unsafe { std::str::from_utf8_unchecked(str_bin) };
+        let str = std::str::from_utf8(str_bin)?;
         let len_decoder = UIntOptRleDecoder::new(Cursor { buf, next });
         Ok(StringDecoder {
             pos: 0,
@@ -495,7 +657,10 @@ impl<'a> StringDecoder<'a> {
                 break;
             }
             i += c.len_utf8(); // rust uses offsets as utf-8 bytes
-            remaining -= c.len_utf16(); // but yjs provides them as utf-16
+            remaining = remaining.saturating_sub(c.len_utf16()); // but yjs provides them as utf-16
+        }
+        if remaining > 0 {
+            return Err(Error::EndOfBuffer(remaining));
         }
         let result = &start[..i];
         self.pos += i;
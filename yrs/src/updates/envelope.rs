@@ -0,0 +1,377 @@
+//! Authenticated-encryption envelope for encoded updates, which can be used to layer an end-to-end
+//! encrypted synchronization on top of existing providers. This module doesn't implement any
+//! cryptographic primitives by itself: an actual AEAD algorithm (ie. AES-GCM or
+//! ChaCha20-Poly1305) is plugged in via [Cipher] trait.
+//!
+//! Envelope is encoded using lib0 encoding and has a following layout:
+//!
+//! - envelope version (single byte, currently [ENVELOPE_VERSION]),
+//! - identifier of a key used to encrypt the payload (variable-length buffer),
+//! - nonce (variable-length buffer),
+//! - ciphertext, including an authentication tag (variable-length buffer).
+//!
+//! Everything that precedes the ciphertext is not encrypted, but it's authenticated as an
+//! additional data. Key identifier allows the receiver to pick a right key in case of key
+//! rotation, without having to decrypt an envelope first - see [Keyring].
+//!
+//! # Example
+//!
+//! ```rust
+//! use yrs::{Doc, Update};
+//! use yrs::updates::decoder::Decode;
+//! use yrs::updates::envelope::{self, Cipher, EnvelopeError};
+//!
+//! /// Your AEAD implementation of choice. This one does no encryption at all.
+//! struct Plain;
+//!
+//! impl Cipher for Plain {
+//!     fn key_id(&self) -> &[u8] { b"key-1" }
+//!     fn nonce(&self) -> Vec<u8> { vec![0; 12] }
+//!     fn seal(&self, _: &[u8], _: &[u8], data: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+//!         Ok(data.to_vec())
+//!     }
+//!     fn open(&self, _: &[u8], _: &[u8], data: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+//!         Ok(data.to_vec())
+//!     }
+//! }
+//!
+//! let doc = Doc::new();
+//! let mut txn = doc.transact();
+//! let text = txn.get_text("text");
+//! text.insert(&mut txn, 0, "hello");
+//!
+//! let sealed = envelope::seal(&Plain, &txn.encode_update_v1()).unwrap();
+//! assert_eq!(envelope::key_id(&sealed).unwrap(), b"key-1");
+//!
+//! let remote = Doc::new();
+//! let update = envelope::open(&Plain, &sealed).unwrap();
+//! remote.transact().apply_update(Update::decode_v1(&update).unwrap());
+//! ```
+
+use lib0::decoding::{Cursor, Read};
+use lib0::encoding::Write;
+use lib0::error::Error;
+
+/// Version of an envelope format produced by [seal].
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Authenticated encryption algorithm used to seal and open envelopes, bound to a single key.
+pub trait Cipher {
+    /// Identifier of a key used by current cipher. It's stored in plain text within every sealed
+    /// envelope, so it must not contain any secret information.
+    fn key_id(&self) -> &[u8];
+
+    /// Generates a new nonce for an envelope about to be sealed. Nonces must never be reused with
+    /// the same key.
+    fn nonce(&self) -> Vec<u8>;
+
+    /// Encrypts and authenticates a `plaintext`, additionally authenticating (but not encrypting)
+    /// given `aad`. Returns a ciphertext together with its authentication tag.
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, EnvelopeError>;
+
+    /// Verifies and decrypts a `ciphertext` produced by [Cipher::seal]. It should fail with
+    /// [EnvelopeError::Unauthenticated] if either `ciphertext` or `aad` has been tampered with.
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, EnvelopeError>;
+}
+
+/// Collection of ciphers used to open envelopes sealed with different keys.
+pub trait Keyring {
+    /// Returns a cipher for a key with a given identifier, if it's known.
+    fn cipher(&self, key_id: &[u8]) -> Option<&dyn Cipher>;
+}
+
+impl<C: Cipher> Keyring for C {
+    fn cipher(&self, key_id: &[u8]) -> Option<&dyn Cipher> {
+        if self.key_id() == key_id {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+impl<C: Cipher> Keyring for [C] {
+    fn cipher(&self, key_id: &[u8]) -> Option<&dyn Cipher> {
+        let cipher = self.iter().find(|c| c.key_id() == key_id)?;
+        Some(cipher)
+    }
+}
+
+impl<C: Cipher> Keyring for Vec<C> {
+    fn cipher(&self, key_id: &[u8]) -> Option<&dyn Cipher> {
+        self.as_slice().cipher(key_id)
+    }
+}
+
+/// Error returned when an envelope cannot be sealed or opened.
+#[derive(Debug)]
+pub enum EnvelopeError {
+    /// Envelope could not be decoded.
+    Malformed(Error),
+    /// Envelope has been produced using a format version not supported by this library.
+    UnsupportedVersion(u8),
+    /// Envelope has been sealed using a key not present in a [Keyring].
+    UnknownKey(Vec<u8>),
+    /// Envelope failed authentication: it has been tampered with or it was sealed using
+    /// a different key.
+    Unauthenticated,
+    /// Other, cipher-specific error.
+    Cipher(String),
+}
+
+impl std::fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvelopeError::Malformed(e) => write!(f, "malformed envelope: {}", e),
+            EnvelopeError::UnsupportedVersion(v) => {
+                write!(f, "unsupported envelope version: {}", v)
+            }
+            EnvelopeError::UnknownKey(id) => write!(f, "unknown envelope key: {:?}", id),
+            EnvelopeError::Unauthenticated => write!(f, "envelope authentication failed"),
+            EnvelopeError::Cipher(msg) => write!(f, "cipher error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+impl From<Error> for EnvelopeError {
+    fn from(e: Error) -> Self {
+        EnvelopeError::Malformed(e)
+    }
+}
+
+/// Encrypts a `payload` (ie. an update encoded using lib0 v1 or v2 encoding) using a given
+/// `cipher` and wraps it into an envelope.
+pub fn seal<C: Cipher + ?Sized>(cipher: &C, payload: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+    let nonce = cipher.nonce();
+    let mut buf = Vec::new();
+    buf.write_u8(ENVELOPE_VERSION);
+    buf.write_buf(cipher.key_id());
+    buf.write_buf(&nonce);
+    let ciphertext = cipher.seal(&nonce, &buf, payload)?;
+    buf.write_buf(&ciphertext);
+    Ok(buf)
+}
+
+/// Opens an envelope produced by [seal], using a cipher from a given `keyring` which matches the
+/// envelope's key identifier, and returns a decrypted payload.
+pub fn open<K: Keyring + ?Sized>(keyring: &K, envelope: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+    let header = Header::decode(envelope)?;
+    let cipher = keyring
+        .cipher(header.key_id)
+        .ok_or_else(|| EnvelopeError::UnknownKey(header.key_id.to_vec()))?;
+    let aad = &envelope[..header.len];
+    cipher.open(header.nonce, aad, header.ciphertext)
+}
+
+/// Returns an identifier of a key used to seal a given `envelope`, without opening it.
+pub fn key_id(envelope: &[u8]) -> Result<&[u8], EnvelopeError> {
+    Ok(Header::decode(envelope)?.key_id)
+}
+
+struct Header<'a> {
+    key_id: &'a [u8],
+    nonce: &'a [u8],
+    /// Length of an authenticated header, preceding ciphertext.
+    len: usize,
+    ciphertext: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    fn decode(envelope: &'a [u8]) -> Result<Self, EnvelopeError> {
+        let mut cursor = Cursor::new(envelope);
+        let version = cursor.read_u8()?;
+        if version != ENVELOPE_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+        let key_id = read_slice(&mut cursor, envelope)?;
+        let nonce = read_slice(&mut cursor, envelope)?;
+        let len = cursor.next;
+        let ciphertext = read_slice(&mut cursor, envelope)?;
+        if cursor.has_content() {
+            return Err(EnvelopeError::Malformed(Error::UnexpectedValue));
+        }
+        Ok(Header {
+            key_id,
+            nonce,
+            len,
+            ciphertext,
+        })
+    }
+}
+
+/// Reads a variable-length buffer, borrowing it directly from `buf` read by a `cursor`.
+fn read_slice<'a>(cursor: &mut Cursor<'a>, buf: &'a [u8]) -> Result<&'a [u8], Error> {
+    let len: usize = cursor.read_var()?;
+    let start = cursor.next;
+    let end = start.checked_add(len).filter(|&end| end <= buf.len());
+    let end = end.ok_or(Error::EndOfBuffer(len))?;
+    cursor.next = end;
+    Ok(&buf[start..end])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::updates::decoder::Decode;
+    use crate::updates::envelope::{self, Cipher, EnvelopeError, ENVELOPE_VERSION};
+    use crate::{Doc, Update};
+    use std::cell::Cell;
+
+    /// Toy cipher used only for testing: XOR keystream and a non-cryptographic checksum as a tag.
+    struct XorCipher {
+        key_id: Vec<u8>,
+        key: u8,
+        counter: Cell<u8>,
+    }
+
+    impl XorCipher {
+        fn new(key_id: &str, key: u8) -> Self {
+            XorCipher {
+                key_id: key_id.as_bytes().to_vec(),
+                key,
+                counter: Cell::new(0),
+            }
+        }
+
+        fn tag(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> u8 {
+            let bytes = nonce.iter().chain(aad).chain(ciphertext);
+            bytes.fold(self.key, |acc, b| acc.rotate_left(3) ^ b)
+        }
+
+        fn xor(&self, nonce: &[u8], data: &[u8]) -> Vec<u8> {
+            data.iter().map(|b| b ^ self.key ^ nonce[0]).collect()
+        }
+    }
+
+    impl Cipher for XorCipher {
+        fn key_id(&self) -> &[u8] {
+            &self.key_id
+        }
+
+        fn nonce(&self) -> Vec<u8> {
+            let n = self.counter.get() + 1;
+            self.counter.set(n);
+            vec![n]
+        }
+
+        fn seal(
+            &self,
+            nonce: &[u8],
+            aad: &[u8],
+            plaintext: &[u8],
+        ) -> Result<Vec<u8>, EnvelopeError> {
+            let mut ciphertext = self.xor(nonce, plaintext);
+            ciphertext.push(self.tag(nonce, aad, &ciphertext));
+            Ok(ciphertext)
+        }
+
+        fn open(
+            &self,
+            nonce: &[u8],
+            aad: &[u8],
+            ciphertext: &[u8],
+        ) -> Result<Vec<u8>, EnvelopeError> {
+            let (tag, data) = ciphertext
+                .split_last()
+                .ok_or(EnvelopeError::Unauthenticated)?;
+            if nonce.len() != 1 || *tag != self.tag(nonce, aad, data) {
+                return Err(EnvelopeError::Unauthenticated);
+            }
+            Ok(self.xor(nonce, data))
+        }
+    }
+
+    fn update() -> Vec<u8> {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let text = txn.get_text("text");
+        text.insert(&mut txn, 0, "hello world");
+        txn.encode_update_v1()
+    }
+
+    #[test]
+    fn envelope_roundtrip() {
+        let payload = update();
+        let cipher = XorCipher::new("k1", 0x5a);
+        let sealed = envelope::seal(&cipher, &payload).unwrap();
+        assert_eq!(sealed[0], ENVELOPE_VERSION);
+        assert_eq!(envelope::key_id(&sealed).unwrap(), b"k1");
+        assert!(!sealed.windows(5).any(|w| w == b"hello"));
+
+        let opened = envelope::open(&cipher, &sealed).unwrap();
+        assert_eq!(opened, payload);
+
+        let doc = Doc::with_client_id(2);
+        let mut txn = doc.transact();
+        txn.apply_update(Update::decode_v1(&opened).unwrap());
+        assert_eq!(txn.get_text("text").to_string(), "hello world");
+
+        // each envelope uses a new nonce
+        let sealed2 = envelope::seal(&cipher, &payload).unwrap();
+        assert_ne!(sealed, sealed2);
+        assert_eq!(envelope::open(&cipher, &sealed2).unwrap(), payload);
+    }
+
+    #[test]
+    fn envelope_key_rotation() {
+        let payload = update();
+        let old = XorCipher::new("k1", 1);
+        let new = XorCipher::new("k2", 2);
+        let sealed_old = envelope::seal(&old, &payload).unwrap();
+        let sealed_new = envelope::seal(&new, &payload).unwrap();
+
+        let keyring = vec![old, new];
+        assert_eq!(envelope::open(&keyring, &sealed_old).unwrap(), payload);
+        assert_eq!(envelope::open(&keyring, &sealed_new).unwrap(), payload);
+
+        let other = XorCipher::new("k3", 3);
+        match envelope::open(&other, &sealed_new) {
+            Err(EnvelopeError::UnknownKey(id)) => assert_eq!(id, b"k2"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn envelope_tampering() {
+        let cipher = XorCipher::new("k1", 0x5a);
+        let sealed = envelope::seal(&cipher, &update()).unwrap();
+
+        // header is authenticated: [version, key id len, k, 1, nonce len, nonce, ...]
+        let mut tampered = sealed.clone();
+        tampered[5] ^= 1; // nonce
+        assert!(matches!(
+            envelope::open(&cipher, &tampered),
+            Err(EnvelopeError::Unauthenticated)
+        ));
+
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 2;
+        tampered[last] ^= 1;
+        assert!(matches!(
+            envelope::open(&cipher, &tampered),
+            Err(EnvelopeError::Unauthenticated)
+        ));
+
+        let mut tampered = sealed.clone();
+        tampered[0] = ENVELOPE_VERSION + 1;
+        assert!(matches!(
+            envelope::open(&cipher, &tampered),
+            Err(EnvelopeError::UnsupportedVersion(2))
+        ));
+
+        for truncated in [&sealed[..0], &sealed[..4], &sealed[..sealed.len() - 1]] {
+            assert!(matches!(
+                envelope::open(&cipher, truncated),
+                Err(EnvelopeError::Malformed(_))
+            ));
+        }
+
+        let mut trailing = sealed.clone();
+        trailing.push(0);
+        assert!(matches!(
+            envelope::open(&cipher, &trailing),
+            Err(EnvelopeError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,792 @@
+use crate::block::{Block, BlockRange, ClientID, Item, ItemContent};
+use crate::id_set::{DeleteSet, IdRange, IdSet};
+use crate::moving::{Move, RelativePosition};
+use crate::types::{
+    Branch, TypePtr, TypeRefs, TYPE_REFS_ARRAY, TYPE_REFS_MAP, TYPE_REFS_TEXT, TYPE_REFS_UNDEFINED,
+    TYPE_REFS_WEAK, TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_FRAGMENT, TYPE_REFS_XML_HOOK,
+    TYPE_REFS_XML_TEXT,
+};
+use crate::update::{BlockCarrier, Update};
+use crate::{Doc, Extension, LinkSource, OffsetKind, ID};
+use lib0::any::Any;
+use lib0::error::Error;
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Tags used to represent values, which have no native JSON representation.
+const TAG_UNDEFINED: &str = "$undefined";
+const TAG_BIGINT: &str = "$bigint";
+const TAG_INT128: &str = "$int128";
+const TAG_DECIMAL: &str = "$decimal";
+const TAG_DATE: &str = "$date";
+const TAG_BUFFER: &str = "$buffer";
+const TAG_NUMBER: &str = "$number";
+const TAG_MAP: &str = "$map";
+
+impl Update {
+    /// Returns a human-readable JSON representation of the full update structure: blocks
+    /// (together with their origins, parents and content) grouped by client and a delete set.
+    /// It's meant for debugging and tooling purposes - use [Any::to_json] to serialize it.
+    ///
+    /// Conversion is lossless and can be reversed using [Update::from_json]. Values which have no
+    /// JSON equivalent are represented as single-entry objects: `{"$undefined":null}`,
+    /// `{"$bigint":"<digits>"}`, `{"$int128":"<digits>"}`, `{"$decimal":"<digits>.<digits>"}`,
+    /// `{"$date":"<epoch millis>"}`, `{"$buffer":[<bytes>]}` and `{"$number":"NaN"}`. Maps which
+    /// could be mistaken for such tags are wrapped into `{"$map":{...}}`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lib0::any::Any;
+    /// use yrs::{Doc, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let doc = Doc::with_client_id(1);
+    /// let mut txn = doc.transact();
+    /// let text = txn.get_text("text");
+    /// text.insert(&mut txn, 0, "abc");
+    ///
+    /// let update = Update::decode_v1(&txn.encode_update_v1()).unwrap();
+    /// let mut json = String::new();
+    /// update.to_json().to_json(&mut json);
+    ///
+    /// let parsed = Update::from_json(&Any::from_json(&json).unwrap()).unwrap();
+    /// assert_eq!(parsed, update);
+    /// ```
+    pub fn to_json(&self) -> Any {
+        let mut clients: Vec<_> = self.blocks.clients.iter().collect();
+        clients.sort_by(|(a, _), (b, _)| b.cmp(a));
+        let clients: Vec<Any> = clients
+            .into_iter()
+            .filter(|(_, blocks)| !blocks.is_empty())
+            .map(|(client, blocks)| {
+                let blocks: Vec<Any> = blocks.iter().map(block_to_json).collect();
+                object([
+                    ("client", Any::Number(*client as f64)),
+                    ("blocks", Any::Array(blocks.into())),
+                ])
+            })
+            .collect();
+
+        let mut delete_set: Vec<_> = self.delete_set.iter().collect();
+        delete_set.sort_by(|(a, _), (b, _)| b.cmp(a));
+        let delete_set: Vec<Any> = delete_set
+            .into_iter()
+            .map(|(client, range)| {
+                let ranges: Vec<Any> = range
+                    .iter()
+                    .map(|r| {
+                        object([
+                            ("clock", Any::Number(r.start as f64)),
+                            ("len", Any::Number((r.end - r.start) as f64)),
+                        ])
+                    })
+                    .collect();
+                object([
+                    ("client", Any::Number(*client as f64)),
+                    ("ranges", Any::Array(ranges.into())),
+                ])
+            })
+            .collect();
+
+        object([
+            ("clients", Any::Array(clients.into())),
+            ("delete_set", Any::Array(delete_set.into())),
+        ])
+    }
+
+    /// Reconstructs an update from its JSON representation produced by [Update::to_json].
+    /// Returned error describes which part of the JSON structure was malformed.
+    pub fn from_json(json: &Any) -> Result<Update, Error> {
+        let mut update = Update::new();
+        let root = as_map(json, "update")?;
+        for client in as_array(field(root, "clients")?, "clients")? {
+            let client = as_map(client, "client")?;
+            let client_id = as_client(field(client, "client")?)?;
+            let mut blocks = VecDeque::new();
+            let mut next_clock = None;
+            for block in as_array(field(client, "blocks")?, "blocks")? {
+                let block = block_from_json(client_id, block)?;
+                let id = block.id();
+                if let Some(expected) = next_clock {
+                    if id.clock != expected {
+                        return Err(invalid(format!(
+                            "block {} doesn't follow the previous one (expected clock: {})",
+                            id, expected
+                        )));
+                    }
+                }
+                next_clock = Some(
+                    id.clock
+                        .checked_add(block.len())
+                        .ok_or(Error::ClockOverflow)?,
+                );
+                blocks.push_back(block);
+            }
+            if !blocks.is_empty() {
+                update.blocks.clients.insert(client_id, blocks);
+            }
+        }
+        let mut delete_set = IdSet::new();
+        for entry in as_array(field(root, "delete_set")?, "delete_set")? {
+            let entry = as_map(entry, "delete set entry")?;
+            let client = as_client(field(entry, "client")?)?;
+            let mut ranges = Vec::new();
+            for range in as_array(field(entry, "ranges")?, "ranges")? {
+                let range = as_map(range, "range")?;
+                let clock = as_u32(field(range, "clock")?, "clock")?;
+                let len = as_u32(field(range, "len")?, "len")?;
+                let end = clock.checked_add(len).ok_or(Error::ClockOverflow)?;
+                ranges.push(Range { start: clock, end });
+            }
+            let range = if ranges.len() == 1 {
+                IdRange::Continuous(ranges.pop().unwrap())
+            } else {
+                IdRange::Fragmented(ranges)
+            };
+            delete_set.insert_range(client, range);
+        }
+        update.delete_set = DeleteSet::from(delete_set);
+        Ok(update)
+    }
+}
+
+fn object<const N: usize>(entries: [(&str, Any); N]) -> Any {
+    let map: HashMap<String, Any> = IntoIterator::into_iter(entries)
+        .map(|(key, value)| (key.to_owned(), value))
+        .collect();
+    Any::Map(Box::new(map))
+}
+
+fn string(s: &str) -> Any {
+    Any::String(s.into())
+}
+
+fn id_to_json(id: &ID) -> Any {
+    object([
+        ("client", Any::Number(id.client as f64)),
+        ("clock", Any::Number(id.clock as f64)),
+    ])
+}
+
+fn block_to_json(block: &BlockCarrier) -> Any {
+    match block {
+        BlockCarrier::Skip(skip) => object([
+            ("type", string("skip")),
+            ("id", id_to_json(&skip.id)),
+            ("len", Any::Number(skip.len as f64)),
+        ]),
+        BlockCarrier::Block(block) => match block.as_ref() {
+            Block::GC(gc) => object([
+                ("type", string("gc")),
+                ("id", id_to_json(&gc.id)),
+                ("len", Any::Number(gc.len as f64)),
+            ]),
+            Block::Item(item) => item_to_json(item),
+        },
+    }
+}
+
+fn item_to_json(item: &Item) -> Any {
+    let mut map = HashMap::new();
+    map.insert("type".to_owned(), string("item"));
+    map.insert("id".to_owned(), id_to_json(&item.id));
+    map.insert("len".to_owned(), Any::Number(item.len as f64));
+    if let Some(origin) = item.origin.as_ref() {
+        map.insert("origin".to_owned(), id_to_json(origin));
+    }
+    if let Some(right_origin) = item.right_origin.as_ref() {
+        map.insert("right_origin".to_owned(), id_to_json(right_origin));
+    }
+    if item.origin.is_none() && item.right_origin.is_none() {
+        let parent = match &item.parent {
+            TypePtr::Named(name) => Some(string(name.as_ref())),
+            TypePtr::ID(id) => Some(id_to_json(id)),
+            TypePtr::Branch(branch) => branch.item.map(|block| id_to_json(block.id())),
+            TypePtr::Unknown => None,
+        };
+        if let Some(parent) = parent {
+            map.insert("parent".to_owned(), parent);
+        }
+        if let Some(parent_sub) = item.parent_sub.as_ref() {
+            map.insert("parent_sub".to_owned(), string(parent_sub.as_ref()));
+        }
+    }
+    map.insert("content".to_owned(), content_to_json(&item.content));
+    Any::Map(Box::new(map))
+}
+
+fn content_to_json(content: &ItemContent) -> Any {
+    match content {
+        ItemContent::Any(values) => {
+            let values: Vec<Any> = values.iter().map(value_to_json).collect();
+            object([("any", Any::Array(values.into()))])
+        }
+        ItemContent::Binary(buf) => object([("binary", bytes_to_json(buf))]),
+        ItemContent::Deleted(len) => object([("deleted", Any::Number(*len as f64))]),
+        ItemContent::Doc(doc) => object([(
+            "doc",
+            object([
+                ("guid", string(doc.guid().as_ref())),
+                ("options", value_to_json(&doc.encode_options())),
+            ]),
+        )]),
+        ItemContent::JSON(values) => {
+            let values: Vec<Any> = values.iter().map(|s| string(s.as_str())).collect();
+            object([("json", Any::Array(values.into()))])
+        }
+        ItemContent::Embed(value) => object([("embed", value_to_json(value))]),
+        ItemContent::Extension(e) => object([(
+            "extension",
+            object([
+                ("tag", string(e.tag())),
+                ("payload", bytes_to_json(e.payload())),
+            ]),
+        )]),
+        ItemContent::Format(key, value) => object([(
+            "format",
+            object([
+                ("key", string(key.as_ref())),
+                ("value", value_to_json(value)),
+            ]),
+        )]),
+        ItemContent::String(s) => object([("string", string(s.as_str()))]),
+        ItemContent::Type(branch) => {
+            let mut map = HashMap::new();
+            map.insert(
+                "type_ref".to_owned(),
+                string(type_ref_name(branch.type_ref())),
+            );
+            if let Some(name) = branch.name.as_ref() {
+                map.insert("name".to_owned(), string(name.as_ref()));
+            }
+            if let Some(link) = branch.link.as_ref() {
+                map.insert("quote_start".to_owned(), id_to_json(&link.quote_start));
+                map.insert("quote_end".to_owned(), id_to_json(&link.quote_end));
+            }
+            object([("type", Any::Map(Box::new(map)))])
+        }
+        ItemContent::Move(m) => object([(
+            "move",
+            object([
+                ("start", id_to_json(&m.start.id)),
+                ("start_assoc", Any::Bool(m.start.assoc)),
+                (
+                    "end",
+                    m.end
+                        .as_ref()
+                        .map(|e| id_to_json(&e.id))
+                        .unwrap_or(Any::Null),
+                ),
+                (
+                    "end_assoc",
+                    Any::Bool(m.end.as_ref().map(|e| e.assoc).unwrap_or(false)),
+                ),
+                ("priority", Any::Number(m.priority as f64)),
+            ]),
+        )]),
+    }
+}
+
+fn bytes_to_json(buf: &[u8]) -> Any {
+    let bytes: Vec<Any> = buf.iter().map(|b| Any::Number(*b as f64)).collect();
+    Any::Array(bytes.into())
+}
+
+/// Converts a value into its JSON-compatible representation, tagging the variants which have no
+/// JSON equivalent.
+fn value_to_json(value: &Any) -> Any {
+    match value {
+        Any::Undefined => object([(TAG_UNDEFINED, Any::Null)]),
+        Any::BigInt(n) => object([(TAG_BIGINT, string(&n.to_string()))]),
+        Any::Int128(n) => object([(TAG_INT128, string(&n.to_string()))]),
+        Any::Decimal(n) => object([(TAG_DECIMAL, string(&n.to_string()))]),
+        Any::Date(millis) => object([(TAG_DATE, string(&millis.to_string()))]),
+        Any::Buffer(buf) => object([(TAG_BUFFER, bytes_to_json(buf))]),
+        Any::Number(n) if !n.is_finite() => object([(TAG_NUMBER, string(&n.to_string()))]),
+        Any::Array(values) => {
+            let values: Vec<Any> = values.iter().map(value_to_json).collect();
+            Any::Array(values.into())
+        }
+        Any::Map(entries) => {
+            let map: HashMap<String, Any> = entries
+                .iter()
+                .map(|(key, value)| (key.clone(), value_to_json(value)))
+                .collect();
+            let is_ambiguous = map.len() == 1 && map.keys().any(|key| key.starts_with('$'));
+            let map = Any::Map(Box::new(map));
+            if is_ambiguous {
+                object([(TAG_MAP, map)])
+            } else {
+                map
+            }
+        }
+        other => other.clone(),
+    }
+}
+
+fn value_from_json(json: &Any) -> Result<Any, Error> {
+    match json {
+        Any::Array(values) => {
+            let values = values
+                .iter()
+                .map(value_from_json)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Any::Array(values.into()))
+        }
+        Any::Map(entries) if entries.len() == 1 => {
+            let (key, value) = entries.iter().next().unwrap();
+            match key.as_str() {
+                TAG_UNDEFINED => Ok(Any::Undefined),
+                TAG_BIGINT => {
+                    let n = as_str(value, TAG_BIGINT)?
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid {} value: {}", TAG_BIGINT, value)))?;
+                    Ok(Any::BigInt(n))
+                }
+                TAG_INT128 => {
+                    let n = as_str(value, TAG_INT128)?
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid {} value: {}", TAG_INT128, value)))?;
+                    Ok(Any::Int128(n))
+                }
+                TAG_DECIMAL => {
+                    let n = as_str(value, TAG_DECIMAL)?.parse().map_err(|_| {
+                        invalid(format!("invalid {} value: {}", TAG_DECIMAL, value))
+                    })?;
+                    Ok(Any::Decimal(n))
+                }
+                TAG_DATE => {
+                    let millis = as_str(value, TAG_DATE)?
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid {} value: {}", TAG_DATE, value)))?;
+                    Ok(Any::Date(millis))
+                }
+                TAG_BUFFER => Ok(Any::Buffer(bytes_from_json(value)?.into())),
+                TAG_NUMBER => {
+                    let n = as_str(value, TAG_NUMBER)?
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid {} value: {}", TAG_NUMBER, value)))?;
+                    Ok(Any::Number(n))
+                }
+                TAG_MAP => map_from_json(as_map(value, TAG_MAP)?),
+                _ => map_from_json(entries),
+            }
+        }
+        Any::Map(entries) => map_from_json(entries),
+        other => Ok(other.clone()),
+    }
+}
+
+fn map_from_json(entries: &HashMap<String, Any>) -> Result<Any, Error> {
+    let mut map = HashMap::with_capacity(entries.len());
+    for (key, value) in entries.iter() {
+        map.insert(key.clone(), value_from_json(value)?);
+    }
+    Ok(Any::Map(Box::new(map)))
+}
+
+fn bytes_from_json(json: &Any) -> Result<Vec<u8>, Error> {
+    as_array(json, "bytes")?
+        .iter()
+        .map(|b| match b {
+            Any::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n <= 255.0 => Ok(*n as u8),
+            other => Err(invalid(format!("expected byte value but found {}", other))),
+        })
+        .collect()
+}
+
+fn block_from_json(client: ClientID, json: &Any) -> Result<BlockCarrier, Error> {
+    let map = as_map(json, "block")?;
+    let id = id_from_json(field(map, "id")?)?;
+    if id.client != client {
+        return Err(invalid(format!(
+            "block {} is listed under client {}",
+            id, client
+        )));
+    }
+    match as_str(field(map, "type")?, "type")? {
+        "skip" => {
+            let len = as_u32(field(map, "len")?, "len")?;
+            Update::check_block_range(&id, len)?;
+            Ok(BlockCarrier::Skip(BlockRange::new(id, len)))
+        }
+        "gc" => {
+            let len = as_u32(field(map, "len")?, "len")?;
+            Update::check_block_range(&id, len)?;
+            Ok(Box::new(Block::GC(BlockRange::new(id, len))).into())
+        }
+        "item" => Ok(item_from_json(id, map)?.into()),
+        other => Err(invalid(format!("unknown block type: {}", other))),
+    }
+}
+
+fn item_from_json(id: ID, map: &HashMap<String, Any>) -> Result<Block, Error> {
+    let origin = map.get("origin").map(id_from_json).transpose()?;
+    let right_origin = map.get("right_origin").map(id_from_json).transpose()?;
+    let (parent, parent_sub) = if origin.is_none() && right_origin.is_none() {
+        let parent = match field(map, "parent")? {
+            Any::String(name) => TypePtr::Named(name.as_ref().into()),
+            other => TypePtr::ID(id_from_json(other)?),
+        };
+        let parent_sub = match map.get("parent_sub") {
+            None => None,
+            Some(key) => Some(Rc::from(as_str(key, "parent_sub")?)),
+        };
+        (parent, parent_sub)
+    } else {
+        (TypePtr::Unknown, None)
+    };
+    let content = content_from_json(field(map, "content")?)?;
+    Update::check_block_range(&id, content.len(OffsetKind::Utf16))?;
+    // blocks can only refer to blocks created before them
+    let parent_id = match &parent {
+        TypePtr::ID(id) => Some(id),
+        _ => None,
+    };
+    for dep in origin.iter().chain(right_origin.iter()).chain(parent_id) {
+        if dep.client == id.client && dep.clock >= id.clock {
+            return Err(invalid(format!(
+                "block {} refers to a later block {}",
+                id, dep
+            )));
+        }
+    }
+    Ok(Item::new(
+        id,
+        None,
+        origin,
+        None,
+        right_origin,
+        parent,
+        parent_sub,
+        content,
+    ))
+}
+
+fn content_from_json(json: &Any) -> Result<ItemContent, Error> {
+    let map = as_map(json, "content")?;
+    let mut entries = map.iter();
+    let (kind, value) = match (entries.next(), entries.next()) {
+        (Some(entry), None) => entry,
+        _ => return Err(invalid("content must have exactly one entry".to_owned())),
+    };
+    let content = match kind.as_str() {
+        "any" => {
+            let values = as_array(value, "any")?
+                .iter()
+                .map(value_from_json)
+                .collect::<Result<Vec<_>, _>>()?;
+            ItemContent::Any(values)
+        }
+        "binary" => ItemContent::Binary(bytes_from_json(value)?),
+        "deleted" => ItemContent::Deleted(as_u32(value, "deleted")?),
+        "doc" => {
+            let doc = as_map(value, "doc")?;
+            let guid = as_str(field(doc, "guid")?, "guid")?;
+            let options = value_from_json(field(doc, "options")?)?;
+            ItemContent::Doc(Doc::decode_options(guid.into(), &options).into())
+        }
+        "json" => {
+            let values = as_array(value, "json")?
+                .iter()
+                .map(|s| as_str(s, "json").map(str::to_owned))
+                .collect::<Result<Vec<_>, _>>()?;
+            ItemContent::JSON(values)
+        }
+        "embed" => ItemContent::Embed(Box::new(value_from_json(value)?)),
+        "format" => {
+            let format = as_map(value, "format")?;
+            let key = as_str(field(format, "key")?, "key")?;
+            let value = value_from_json(field(format, "value")?)?;
+            ItemContent::Format(key.into(), Box::new(value))
+        }
+        "extension" => {
+            let e = as_map(value, "extension")?;
+            let tag = as_str(field(e, "tag")?, "tag")?;
+            let payload = bytes_from_json(field(e, "payload")?)?;
+            ItemContent::Extension(Box::new(Extension::new(tag, payload)))
+        }
+        "string" => ItemContent::String(as_str(value, "string")?.into()),
+        "type" => {
+            let branch = as_map(value, "type")?;
+            let type_ref = type_ref_from_name(as_str(field(branch, "type_ref")?, "type_ref")?)?;
+            if type_ref == TYPE_REFS_WEAK {
+                let start = id_from_json(field(branch, "quote_start")?)?;
+                let end = id_from_json(field(branch, "quote_end")?)?;
+                return Ok(ItemContent::Type(LinkSource::new(start, end).into_branch()));
+            }
+            let name = if type_ref == TYPE_REFS_XML_ELEMENT || type_ref == TYPE_REFS_XML_HOOK {
+                Some(Rc::from(as_str(field(branch, "name")?, "name")?))
+            } else {
+                None
+            };
+            ItemContent::Type(Branch::new(type_ref, name))
+        }
+        "move" => {
+            let m = as_map(value, "move")?;
+            let start = id_from_json(field(m, "start")?)?;
+            let end = match field(m, "end")? {
+                Any::Null => None,
+                end => Some(id_from_json(end)?),
+            };
+            let start_assoc = as_bool(field(m, "start_assoc")?, "start_assoc")?;
+            let end_assoc = as_bool(field(m, "end_assoc")?, "end_assoc")?;
+            let priority = match field(m, "priority")? {
+                Any::Number(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => *n as i32,
+                other => return Err(invalid(format!("invalid move priority: {}", other))),
+            };
+            ItemContent::Move(Box::new(Move::new(
+                RelativePosition::create(start, start_assoc),
+                end.map(|end| RelativePosition::create(end, end_assoc)),
+                priority,
+            )))
+        }
+        other => return Err(invalid(format!("unknown content type: {}", other))),
+    };
+    Ok(content)
+}
+
+fn type_ref_name(type_ref: TypeRefs) -> &'static str {
+    match type_ref {
+        TYPE_REFS_ARRAY => "array",
+        TYPE_REFS_MAP => "map",
+        TYPE_REFS_TEXT => "text",
+        TYPE_REFS_XML_ELEMENT => "xml_element",
+        TYPE_REFS_XML_FRAGMENT => "xml_fragment",
+        TYPE_REFS_XML_HOOK => "xml_hook",
+        TYPE_REFS_XML_TEXT => "xml_text",
+        TYPE_REFS_WEAK => "weak",
+        _ => "undefined",
+    }
+}
+
+fn type_ref_from_name(name: &str) -> Result<TypeRefs, Error> {
+    match name {
+        "array" => Ok(TYPE_REFS_ARRAY),
+        "map" => Ok(TYPE_REFS_MAP),
+        "text" => Ok(TYPE_REFS_TEXT),
+        "xml_element" => Ok(TYPE_REFS_XML_ELEMENT),
+        "xml_fragment" => Ok(TYPE_REFS_XML_FRAGMENT),
+        "xml_hook" => Ok(TYPE_REFS_XML_HOOK),
+        "xml_text" => Ok(TYPE_REFS_XML_TEXT),
+        "weak" => Ok(TYPE_REFS_WEAK),
+        "undefined" => Ok(TYPE_REFS_UNDEFINED),
+        other => Err(invalid(format!("unknown shared type: {}", other))),
+    }
+}
+
+fn invalid(msg: String) -> Error {
+    Error::Other(msg)
+}
+
+fn field<'a>(map: &'a HashMap<String, Any>, key: &str) -> Result<&'a Any, Error> {
+    map.get(key)
+        .ok_or_else(|| invalid(format!("missing field: {}", key)))
+}
+
+fn as_map<'a>(json: &'a Any, what: &str) -> Result<&'a HashMap<String, Any>, Error> {
+    match json {
+        Any::Map(map) => Ok(map),
+        other => Err(invalid(format!(
+            "expected {} object but found {}",
+            what, other
+        ))),
+    }
+}
+
+fn as_array<'a>(json: &'a Any, what: &str) -> Result<&'a [Any], Error> {
+    match json {
+        Any::Array(values) => Ok(values),
+        other => Err(invalid(format!(
+            "expected {} array but found {}",
+            what, other
+        ))),
+    }
+}
+
+fn as_str<'a>(json: &'a Any, what: &str) -> Result<&'a str, Error> {
+    match json {
+        Any::String(s) => Ok(s),
+        other => Err(invalid(format!(
+            "expected {} string but found {}",
+            what, other
+        ))),
+    }
+}
+
+fn as_bool(json: &Any, what: &str) -> Result<bool, Error> {
+    match json {
+        Any::Bool(b) => Ok(*b),
+        other => Err(invalid(format!(
+            "expected {} boolean but found {}",
+            what, other
+        ))),
+    }
+}
+
+fn as_u32(json: &Any, what: &str) -> Result<u32, Error> {
+    match json {
+        Any::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n <= u32::MAX as f64 => Ok(*n as u32),
+        Any::BigInt(n) if *n >= 0 && *n <= u32::MAX as i64 => Ok(*n as u32),
+        other => Err(invalid(format!(
+            "expected {} to be u32 but found {}",
+            what, other
+        ))),
+    }
+}
+
+fn as_client(json: &Any) -> Result<ClientID, Error> {
+    match json {
+        Any::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n <= u64::MAX as f64 => {
+            Ok(*n as ClientID)
+        }
+        Any::BigInt(n) if *n >= 0 => Ok(*n as ClientID),
+        other => Err(invalid(format!("expected client ID but found {}", other))),
+    }
+}
+
+fn id_from_json(json: &Any) -> Result<ID, Error> {
+    let map = as_map(json, "ID")?;
+    let client = as_client(field(map, "client")?)?;
+    let clock = as_u32(field(map, "clock")?, "clock")?;
+    Ok(ID::new(client, clock))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::exchange_updates;
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, Extension, PrelimArray, PrelimMap, StateVector, Update};
+    use lib0::any::Any;
+    use lib0::decimal::Decimal;
+    use std::collections::HashMap;
+
+    #[test]
+    fn json_roundtrip() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        {
+            let mut txn = d1.transact();
+            let text = txn.get_text("text");
+            text.insert(&mut txn, 0, "hello \"world\"\n");
+            text.format(
+                &mut txn,
+                0,
+                5,
+                HashMap::from([("bold".into(), Any::Bool(true))]),
+            );
+            text.insert_embed(&mut txn, 5, Any::BigInt(i64::MAX));
+            text.remove_range(&mut txn, 7, 2);
+            let array = txn.get_array("array");
+            array.insert_range(&mut txn, 0, [1.5, f64::NAN]);
+            array.push_back(&mut txn, vec![0u8, 1, 255]);
+            array.push_back(&mut txn, PrelimArray::from([Any::Null, Any::Undefined]));
+            array.move_to(&mut txn, 0, 2);
+            array.push_back(&mut txn, Doc::with_client_id(3));
+            array.move_range_to_end(&mut txn, 3, true, 1);
+            array.push_back(&mut txn, i128::MIN);
+            array.push_back(&mut txn, Any::Date(-1));
+            array.push_back(&mut txn, Extension::new("ext", vec![0, 1, 255]));
+            array.push_back(&mut txn, "-1234.56".parse::<Decimal>().unwrap());
+        }
+        {
+            let mut txn = d2.transact();
+            let map = txn.get_map("map");
+            map.insert(&mut txn, "a", "value");
+            map.insert(
+                &mut txn,
+                "b",
+                PrelimMap::from(HashMap::from([(
+                    "$bigint".to_owned(),
+                    Any::Buffer(vec![1, 2].into()),
+                )])),
+            );
+            map.remove(&mut txn, "a");
+            let xml = txn.get_xml_element("xml");
+            xml.insert_elem(&mut txn, 0, "div");
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let v1 = d1.encode_state_as_update_v1(&StateVector::default());
+        let update = Update::decode_v1(&v1).unwrap();
+        let mut json = String::new();
+        update.to_json().to_json(&mut json);
+
+        let parsed = Update::from_json(&Any::from_json(&json).unwrap()).unwrap();
+        // NaN != NaN, so compare binary representations instead
+        assert_eq!(parsed.encode_v1(), update.encode_v1());
+    }
+
+    #[test]
+    fn json_structure() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact();
+        let text = txn.get_text("text");
+        text.insert(&mut txn, 0, "ab");
+        text.insert(&mut txn, 1, "c");
+        text.remove_range(&mut txn, 0, 1);
+        let update = Update::decode_v1(&txn.encode_update_v1()).unwrap();
+
+        let json = update.to_json();
+        let expected = Any::from_json(
+            r#"{
+              "clients": [{
+                "client": 1,
+                "blocks": [
+                  {
+                    "type": "item",
+                    "id": {"client": 1, "clock": 0},
+                    "len": 1,
+                    "parent": "text",
+                    "content": {"string": "a"}
+                  },
+                  {
+                    "type": "item",
+                    "id": {"client": 1, "clock": 1},
+                    "len": 1,
+                    "origin": {"client": 1, "clock": 0},
+                    "content": {"string": "b"}
+                  },
+                  {
+                    "type": "item",
+                    "id": {"client": 1, "clock": 2},
+                    "len": 1,
+                    "origin": {"client": 1, "clock": 0},
+                    "right_origin": {"client": 1, "clock": 1},
+                    "content": {"string": "c"}
+                  }
+                ]
+              }],
+              "delete_set": [{"client": 1, "ranges": [{"clock": 0, "len": 1}]}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn json_malformed_input() {
+        let parse = |json: &str| Update::from_json(&Any::from_json(json).unwrap());
+        assert!(parse(r#"{"clients": [], "delete_set": []}"#).unwrap() == Update::new());
+        assert!(parse(r#"{"clients": []}"#).is_err());
+        // block refers to itself
+        let err = parse(
+            r#"{"clients": [{"client": 1, "blocks": [{
+                "type": "item", "id": {"client": 1, "clock": 0},
+                "origin": {"client": 1, "clock": 0}, "content": {"string": "a"}
+            }]}], "delete_set": []}"#,
+        );
+        assert!(err.is_err());
+        // non-contiguous blocks
+        let err = parse(
+            r#"{"clients": [{"client": 1, "blocks": [
+                {"type": "gc", "id": {"client": 1, "clock": 0}, "len": 2},
+                {"type": "gc", "id": {"client": 1, "clock": 3}, "len": 2}
+            ]}], "delete_set": []}"#,
+        );
+        assert!(err.is_err());
+    }
+}
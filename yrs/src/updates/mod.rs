@@ -1,2 +1,8 @@
+pub mod checksum;
 pub mod decoder;
 pub mod encoder;
+#[cfg(feature = "envelope")]
+pub mod envelope;
+mod json;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
@@ -0,0 +1,773 @@
+//! Alternative, schema-described wire format of document updates, based on
+//! [Protocol Buffers](https://developers.google.com/protocol-buffers). Schema used by this module
+//! can be found in `proto/update.proto` file of this crate and can be used to generate readers and
+//! writers in any language supported by protobuf compiler.
+//!
+//! Conversion is lossless: an [Update] decoded from lib0 binary format, encoded using protobuf and
+//! decoded back produces the same lib0 binary payload.
+//!
+//! # Example
+//!
+//! ```rust
+//! use yrs::{Doc, Update};
+//! use yrs::updates::decoder::Decode;
+//! use yrs::updates::encoder::Encode;
+//!
+//! let doc = Doc::new();
+//! let mut txn = doc.transact();
+//! let text = txn.get_text("text");
+//! text.insert(&mut txn, 0, "hello");
+//! let v1 = txn.encode_update_v1();
+//!
+//! let update = Update::decode_v1(&v1).unwrap();
+//! let proto = update.encode_protobuf();
+//! let update = Update::decode_protobuf(&proto).unwrap();
+//! assert_eq!(update.encode_v1(), v1);
+//! ```
+
+use crate::block::{Block, BlockRange, ClientID, Item, ItemContent};
+use crate::id_set::{DeleteSet, IdRange, IdSet};
+use crate::moving::{Move, RelativePosition};
+use crate::types::{
+    Branch, TypePtr, TYPE_REFS_UNDEFINED, TYPE_REFS_WEAK, TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_HOOK,
+};
+use crate::update::{BlockCarrier, Update};
+use crate::{Doc, Extension, LinkSource, OffsetKind, ID};
+use lib0::any::Any;
+use lib0::decoding::{Cursor, Read};
+use lib0::encoding::Write;
+use lib0::error::Error;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::rc::Rc;
+
+const WIRE_VARINT: u32 = 0;
+const WIRE_FIXED64: u32 = 1;
+const WIRE_LEN: u32 = 2;
+const WIRE_FIXED32: u32 = 5;
+
+impl Update {
+    /// Encodes current update using Protocol Buffers format described by `proto/update.proto`
+    /// schema (`yrs.Update` message).
+    pub fn encode_protobuf(&self) -> Vec<u8> {
+        let mut w = ProtoWriter::default();
+        let mut clients: Vec<_> = self.blocks.clients.iter().collect();
+        clients.sort_by(|(a, _), (b, _)| b.cmp(a));
+        for (client, blocks) in clients {
+            if let Some(first) = blocks.front() {
+                w.message(1, |w| {
+                    w.uint(1, *client);
+                    w.uint(2, first.id().clock as u64);
+                    for block in blocks.iter() {
+                        w.message(3, |w| write_block(w, block));
+                    }
+                });
+            }
+        }
+        let mut delete_set: Vec<_> = self.delete_set.iter().collect();
+        delete_set.sort_by(|(a, _), (b, _)| b.cmp(a));
+        for (client, range) in delete_set {
+            w.message(2, |w| {
+                w.uint(1, *client);
+                for r in range.iter() {
+                    w.message(2, |w| {
+                        w.uint(1, r.start as u64);
+                        w.uint(2, (r.end - r.start) as u64);
+                    });
+                }
+            });
+        }
+        w.buf
+    }
+
+    /// Decodes an update encoded using Protocol Buffers format described by `proto/update.proto`
+    /// schema (`yrs.Update` message). Unknown fields are skipped.
+    pub fn decode_protobuf(data: &[u8]) -> Result<Update, Error> {
+        let mut update = Update::new();
+        let mut delete_set = IdSet::new();
+        let mut r = ProtoReader::new(data);
+        while let Some((field, value)) = r.next()? {
+            match field {
+                1 => {
+                    let (client, blocks) = read_client_blocks(value.as_bytes()?)?;
+                    if !blocks.is_empty() {
+                        update
+                            .blocks
+                            .clients
+                            .entry(client)
+                            .or_default()
+                            .extend(blocks);
+                    }
+                }
+                2 => {
+                    let (client, range) = read_delete_set_entry(value.as_bytes()?)?;
+                    delete_set.insert_range(client, range);
+                }
+                _ => { /* skip unknown field */ }
+            }
+        }
+        update.delete_set = DeleteSet::from(delete_set);
+        Ok(update)
+    }
+}
+
+fn write_id(w: &mut ProtoWriter, field: u32, id: &ID) {
+    w.message(field, |w| {
+        w.uint(1, id.client);
+        w.uint(2, id.clock as u64);
+    })
+}
+
+fn write_block(w: &mut ProtoWriter, block: &BlockCarrier) {
+    match block {
+        BlockCarrier::Skip(skip) => w.uint(2, skip.len as u64),
+        BlockCarrier::Block(block) => match block.as_ref() {
+            Block::GC(gc) => w.uint(1, gc.len as u64),
+            Block::Item(item) => w.message(3, |w| write_item(w, item)),
+        },
+    }
+}
+
+fn write_item(w: &mut ProtoWriter, item: &Item) {
+    if let Some(origin) = item.origin.as_ref() {
+        write_id(w, 1, origin);
+    }
+    if let Some(right_origin) = item.right_origin.as_ref() {
+        write_id(w, 2, right_origin);
+    }
+    if item.origin.is_none() && item.right_origin.is_none() {
+        match &item.parent {
+            TypePtr::Named(name) => w.string(3, name),
+            TypePtr::ID(id) => write_id(w, 4, id),
+            TypePtr::Branch(branch) => {
+                if let Some(block) = branch.item {
+                    write_id(w, 4, block.id())
+                }
+            }
+            TypePtr::Unknown => panic!("Couldn't get item's parent"),
+        }
+        if let Some(parent_sub) = item.parent_sub.as_ref() {
+            w.string(5, parent_sub);
+        }
+    }
+    match &item.content {
+        ItemContent::Any(values) => w.message(10, |w| {
+            for value in values.iter() {
+                w.message(1, |w| write_any(w, value));
+            }
+        }),
+        ItemContent::Binary(buf) => w.bytes(11, buf),
+        ItemContent::Deleted(len) => w.uint(12, *len as u64),
+        ItemContent::Doc(doc) => w.message(13, |w| {
+            w.string(1, doc.guid().as_ref());
+            w.message(2, |w| write_any(w, &doc.encode_options()));
+        }),
+        ItemContent::JSON(values) => w.message(14, |w| {
+            for value in values.iter() {
+                w.string(1, value);
+            }
+        }),
+        ItemContent::Embed(value) => w.message(15, |w| write_any(w, value)),
+        ItemContent::Format(key, value) => w.message(16, |w| {
+            w.string(1, key);
+            w.message(2, |w| write_any(w, value));
+        }),
+        ItemContent::String(s) => w.string(17, s.as_str()),
+        ItemContent::Type(branch) => w.message(18, |w| {
+            w.uint(1, branch.type_ref() as u64);
+            if let Some(name) = branch.name.as_ref() {
+                w.string(2, name);
+            }
+            if let Some(link) = branch.link.as_ref() {
+                write_id(w, 3, &link.quote_start);
+                write_id(w, 4, &link.quote_end);
+            }
+        }),
+        ItemContent::Move(m) => w.message(19, |w| {
+            write_id(w, 1, &m.start.id);
+            w.uint(2, m.start.assoc as u64);
+            match &m.end {
+                Some(end) => {
+                    write_id(w, 3, &end.id);
+                    w.uint(4, end.assoc as u64);
+                }
+                None => w.uint(6, 1),
+            }
+            w.sint(5, m.priority as i64);
+        }),
+        ItemContent::Extension(e) => w.message(20, |w| {
+            w.string(1, e.tag());
+            w.bytes(2, e.payload());
+        }),
+    }
+}
+
+fn write_any(w: &mut ProtoWriter, any: &Any) {
+    match any {
+        Any::Null => w.uint(1, 1),
+        Any::Undefined => w.uint(2, 1),
+        Any::Bool(value) => w.uint(3, *value as u64),
+        Any::Number(value) => w.double(4, *value),
+        Any::BigInt(value) => w.sint(5, *value),
+        Any::Int128(value) => w.string(10, &value.to_string()),
+        Any::Decimal(value) => w.string(11, &value.to_string()),
+        Any::Date(millis) => w.sint(12, *millis),
+        Any::String(value) => w.string(6, value),
+        Any::Buffer(value) => w.bytes(7, value),
+        Any::Array(values) => w.message(8, |w| {
+            for value in values.iter() {
+                w.message(1, |w| write_any(w, value));
+            }
+        }),
+        Any::Map(entries) => w.message(9, |w| {
+            let mut entries: Vec<_> = entries.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            for (key, value) in entries {
+                w.message(1, |w| {
+                    w.string(1, key);
+                    w.message(2, |w| write_any(w, value));
+                });
+            }
+        }),
+    }
+}
+
+fn read_id(data: &[u8]) -> Result<ID, Error> {
+    let mut id = ID::new(0, 0);
+    let mut r = ProtoReader::new(data);
+    while let Some((field, value)) = r.next()? {
+        match field {
+            1 => id.client = value.as_u64()? as ClientID,
+            2 => id.clock = value.as_u32()?,
+            _ => {}
+        }
+    }
+    Ok(id)
+}
+
+fn read_client_blocks(data: &[u8]) -> Result<(ClientID, VecDeque<BlockCarrier>), Error> {
+    let mut client = 0;
+    let mut clock = 0;
+    let mut encoded = Vec::new();
+    let mut r = ProtoReader::new(data);
+    while let Some((field, value)) = r.next()? {
+        match field {
+            1 => client = value.as_u64()? as ClientID,
+            2 => clock = value.as_u32()?,
+            3 => encoded.push(value.as_bytes()?),
+            _ => {}
+        }
+    }
+    let mut blocks = VecDeque::with_capacity(encoded.len());
+    for data in encoded {
+        let id = ID::new(client, clock);
+        let block = read_block(id, data)?;
+        clock = clock.checked_add(block.len()).ok_or(Error::ClockOverflow)?;
+        blocks.push_back(block);
+    }
+    Ok((client, blocks))
+}
+
+fn read_block(id: ID, data: &[u8]) -> Result<BlockCarrier, Error> {
+    let mut block = None;
+    let mut r = ProtoReader::new(data);
+    while let Some((field, value)) = r.next()? {
+        match field {
+            1 => {
+                let len = value.as_u32()?;
+                Update::check_block_range(&id, len)?;
+                block = Some(Box::new(Block::GC(BlockRange::new(id, len))).into());
+            }
+            2 => {
+                let len = value.as_u32()?;
+                Update::check_block_range(&id, len)?;
+                block = Some(BlockCarrier::Skip(BlockRange::new(id, len)));
+            }
+            3 => block = Some(read_item(id, value.as_bytes()?)?.into()),
+            _ => {}
+        }
+    }
+    block.ok_or(Error::UnexpectedValue)
+}
+
+fn read_item(id: ID, data: &[u8]) -> Result<Block, Error> {
+    let mut origin = None;
+    let mut right_origin = None;
+    let mut parent = TypePtr::Unknown;
+    let mut parent_sub = None;
+    let mut content = None;
+    let mut r = ProtoReader::new(data);
+    while let Some((field, value)) = r.next()? {
+        match field {
+            1 => origin = Some(read_id(value.as_bytes()?)?),
+            2 => right_origin = Some(read_id(value.as_bytes()?)?),
+            3 => parent = TypePtr::Named(value.as_str()?.into()),
+            4 => parent = TypePtr::ID(read_id(value.as_bytes()?)?),
+            5 => parent_sub = Some(Rc::from(value.as_str()?)),
+            10..=20 => content = Some(read_content(field, value)?),
+            _ => {}
+        }
+    }
+    let content = content.ok_or(Error::UnexpectedValue)?;
+    if origin.is_some() || right_origin.is_some() {
+        // parent info is inferred from item neighbours
+        parent = TypePtr::Unknown;
+        parent_sub = None;
+    } else if let TypePtr::Unknown = parent {
+        return Err(Error::UnexpectedValue);
+    }
+    Update::check_block_range(&id, content.len(OffsetKind::Utf16))?;
+    // blocks can only refer to blocks created before them
+    let parent_id = match &parent {
+        TypePtr::ID(id) => Some(id),
+        _ => None,
+    };
+    for dep in origin.iter().chain(right_origin.iter()).chain(parent_id) {
+        if dep.client == id.client && dep.clock >= id.clock {
+            return Err(Error::UnexpectedValue);
+        }
+    }
+    Ok(Item::new(
+        id,
+        None,
+        origin,
+        None,
+        right_origin,
+        parent,
+        parent_sub,
+        content,
+    ))
+}
+
+fn read_content(field: u32, value: Value) -> Result<ItemContent, Error> {
+    let content = match field {
+        10 => ItemContent::Any(read_any_list(value.as_bytes()?)?),
+        11 => ItemContent::Binary(value.as_bytes()?.to_vec()),
+        12 => ItemContent::Deleted(value.as_u32()?),
+        13 => {
+            let mut guid = "";
+            let mut options = Any::Null;
+            let mut r = ProtoReader::new(value.as_bytes()?);
+            while let Some((field, value)) = r.next()? {
+                match field {
+                    1 => guid = value.as_str()?,
+                    2 => options = read_any(value.as_bytes()?)?,
+                    _ => {}
+                }
+            }
+            ItemContent::Doc(Doc::decode_options(guid.into(), &options).into())
+        }
+        14 => {
+            let mut values = Vec::new();
+            let mut r = ProtoReader::new(value.as_bytes()?);
+            while let Some((field, value)) = r.next()? {
+                if field == 1 {
+                    values.push(value.as_str()?.to_owned());
+                }
+            }
+            ItemContent::JSON(values)
+        }
+        15 => ItemContent::Embed(Box::new(read_any(value.as_bytes()?)?)),
+        16 => {
+            let mut key = "";
+            let mut any = Any::Null;
+            let mut r = ProtoReader::new(value.as_bytes()?);
+            while let Some((field, value)) = r.next()? {
+                match field {
+                    1 => key = value.as_str()?,
+                    2 => any = read_any(value.as_bytes()?)?,
+                    _ => {}
+                }
+            }
+            ItemContent::Format(key.into(), Box::new(any))
+        }
+        17 => ItemContent::String(value.as_str()?.into()),
+        18 => {
+            let mut type_ref = 0;
+            let mut name = None;
+            let (mut quote_start, mut quote_end) = (None, None);
+            let mut r = ProtoReader::new(value.as_bytes()?);
+            while let Some((field, value)) = r.next()? {
+                match field {
+                    1 => type_ref = value.as_u32()?,
+                    2 => name = Some(Rc::from(value.as_str()?)),
+                    3 => quote_start = Some(read_id(value.as_bytes()?)?),
+                    4 => quote_end = Some(read_id(value.as_bytes()?)?),
+                    _ => {}
+                }
+            }
+            let type_ref = u8::try_from(type_ref).map_err(|_| Error::UnexpectedValue)?;
+            if type_ref > TYPE_REFS_WEAK && type_ref != TYPE_REFS_UNDEFINED {
+                return Err(Error::InvalidTypeRef(type_ref));
+            }
+            if type_ref == TYPE_REFS_WEAK {
+                let start = quote_start.ok_or(Error::UnexpectedValue)?;
+                let source = LinkSource::new(start, quote_end.unwrap_or(start));
+                return Ok(ItemContent::Type(source.into_branch()));
+            }
+            if type_ref != TYPE_REFS_XML_ELEMENT && type_ref != TYPE_REFS_XML_HOOK {
+                name = None;
+            } else if name.is_none() {
+                return Err(Error::UnexpectedValue);
+            }
+            ItemContent::Type(Branch::new(type_ref, name))
+        }
+        19 => {
+            let mut start = ID::new(0, 0);
+            let mut end = None;
+            let (mut start_assoc, mut end_assoc, mut open_ended) = (false, false, false);
+            let mut priority = 0;
+            let mut r = ProtoReader::new(value.as_bytes()?);
+            while let Some((field, value)) = r.next()? {
+                match field {
+                    1 => start = read_id(value.as_bytes()?)?,
+                    2 => start_assoc = value.as_u64()? != 0,
+                    3 => end = Some(read_id(value.as_bytes()?)?),
+                    4 => end_assoc = value.as_u64()? != 0,
+                    6 => open_ended = value.as_u64()? != 0,
+                    5 => {
+                        priority =
+                            i32::try_from(value.as_i64()?).map_err(|_| Error::UnexpectedValue)?
+                    }
+                    _ => {}
+                }
+            }
+            let start = RelativePosition::create(start, start_assoc);
+            let end = if open_ended {
+                None
+            } else {
+                Some(RelativePosition::create(end.unwrap_or(start.id), end_assoc))
+            };
+            ItemContent::Move(Box::new(Move::new(start, end, priority)))
+        }
+        20 => {
+            let mut tag = "";
+            let mut payload: &[u8] = &[];
+            let mut r = ProtoReader::new(value.as_bytes()?);
+            while let Some((field, value)) = r.next()? {
+                match field {
+                    1 => tag = value.as_str()?,
+                    2 => payload = value.as_bytes()?,
+                    _ => {}
+                }
+            }
+            ItemContent::Extension(Box::new(Extension::new(tag, payload)))
+        }
+        _ => return Err(Error::UnexpectedValue),
+    };
+    Ok(content)
+}
+
+fn read_any_list(data: &[u8]) -> Result<Vec<Any>, Error> {
+    let mut values = Vec::new();
+    let mut r = ProtoReader::new(data);
+    while let Some((field, value)) = r.next()? {
+        if field == 1 {
+            values.push(read_any(value.as_bytes()?)?);
+        }
+    }
+    Ok(values)
+}
+
+fn read_any(data: &[u8]) -> Result<Any, Error> {
+    let mut any = None;
+    let mut r = ProtoReader::new(data);
+    while let Some((field, value)) = r.next()? {
+        any = match field {
+            1 => Some(Any::Null),
+            2 => Some(Any::Undefined),
+            3 => Some(Any::Bool(value.as_u64()? != 0)),
+            4 => Some(Any::Number(value.as_f64()?)),
+            5 => Some(Any::BigInt(value.as_i64()?)),
+            6 => Some(Any::String(value.as_str()?.into())),
+            7 => Some(Any::Buffer(value.as_bytes()?.into())),
+            8 => Some(Any::Array(read_any_list(value.as_bytes()?)?.into())),
+            9 => {
+                let mut entries = HashMap::new();
+                let mut r = ProtoReader::new(value.as_bytes()?);
+                while let Some((field, value)) = r.next()? {
+                    if field == 1 {
+                        let mut key = String::new();
+                        let mut value_any = Any::Null;
+                        let mut r = ProtoReader::new(value.as_bytes()?);
+                        while let Some((field, value)) = r.next()? {
+                            match field {
+                                1 => key = value.as_str()?.to_owned(),
+                                2 => value_any = read_any(value.as_bytes()?)?,
+                                _ => {}
+                            }
+                        }
+                        entries.insert(key, value_any);
+                    }
+                }
+                Some(Any::Map(Box::new(entries)))
+            }
+            10 => Some(Any::Int128(
+                value
+                    .as_str()?
+                    .parse()
+                    .map_err(|_| Error::UnexpectedValue)?,
+            )),
+            11 => Some(Any::Decimal(value.as_str()?.parse()?)),
+            12 => Some(Any::Date(value.as_i64()?)),
+            _ => any,
+        };
+    }
+    any.ok_or(Error::UnexpectedValue)
+}
+
+fn read_delete_set_entry(data: &[u8]) -> Result<(ClientID, IdRange), Error> {
+    let mut client = 0;
+    let mut ranges = Vec::new();
+    let mut r = ProtoReader::new(data);
+    while let Some((field, value)) = r.next()? {
+        match field {
+            1 => client = value.as_u64()? as ClientID,
+            2 => {
+                let (mut clock, mut len) = (0u32, 0u32);
+                let mut r = ProtoReader::new(value.as_bytes()?);
+                while let Some((field, value)) = r.next()? {
+                    match field {
+                        1 => clock = value.as_u32()?,
+                        2 => len = value.as_u32()?,
+                        _ => {}
+                    }
+                }
+                let end = clock.checked_add(len).ok_or(Error::ClockOverflow)?;
+                ranges.push(Range { start: clock, end });
+            }
+            _ => {}
+        }
+    }
+    let range = if ranges.len() == 1 {
+        IdRange::Continuous(ranges.pop().unwrap())
+    } else {
+        IdRange::Fragmented(ranges)
+    };
+    Ok((client, range))
+}
+
+/// Minimal protobuf message writer.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    fn tag(&mut self, field: u32, wire_type: u32) {
+        self.buf.write_var(field << 3 | wire_type);
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        self.tag(field, WIRE_VARINT);
+        self.buf.write_var(value);
+    }
+
+    /// Writes zigzag-encoded signed integer (`sint32`/`sint64`).
+    fn sint(&mut self, field: u32, value: i64) {
+        self.uint(field, ((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn double(&mut self, field: u32, value: f64) {
+        self.tag(field, WIRE_FIXED64);
+        self.buf.write_all(&value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.tag(field, WIRE_LEN);
+        self.buf.write_var(value.len());
+        self.buf.write_all(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes())
+    }
+
+    fn message<F: FnOnce(&mut ProtoWriter)>(&mut self, field: u32, f: F) {
+        let mut inner = ProtoWriter::default();
+        f(&mut inner);
+        self.bytes(field, &inner.buf);
+    }
+}
+
+/// Value of a single decoded protobuf field.
+enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Fixed32(u32),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> Value<'a> {
+    fn as_u64(&self) -> Result<u64, Error> {
+        match self {
+            Value::Varint(v) | Value::Fixed64(v) => Ok(*v),
+            Value::Fixed32(v) => Ok(*v as u64),
+            Value::Bytes(_) => Err(Error::UnexpectedValue),
+        }
+    }
+
+    fn as_u32(&self) -> Result<u32, Error> {
+        u32::try_from(self.as_u64()?).map_err(|_| Error::UnexpectedValue)
+    }
+
+    /// Reads zigzag-encoded signed integer (`sint32`/`sint64`).
+    fn as_i64(&self) -> Result<i64, Error> {
+        let v = self.as_u64()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn as_f64(&self) -> Result<f64, Error> {
+        match self {
+            Value::Fixed64(v) => Ok(f64::from_bits(*v)),
+            _ => Err(Error::UnexpectedValue),
+        }
+    }
+
+    fn as_bytes(&self) -> Result<&'a [u8], Error> {
+        match self {
+            Value::Bytes(v) => Ok(v),
+            _ => Err(Error::UnexpectedValue),
+        }
+    }
+
+    fn as_str(&self) -> Result<&'a str, Error> {
+        Ok(std::str::from_utf8(self.as_bytes()?)?)
+    }
+}
+
+/// Minimal protobuf message reader, which iterates over fields of a single message.
+struct ProtoReader<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> ProtoReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        ProtoReader {
+            cursor: Cursor::new(data),
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(u32, Value<'a>)>, Error> {
+        if !self.cursor.has_content() {
+            return Ok(None);
+        }
+        let tag: u32 = self.cursor.read_var()?;
+        let value = match tag & 0b111 {
+            WIRE_VARINT => Value::Varint(self.cursor.read_var()?),
+            WIRE_FIXED64 => {
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(self.cursor.read_exact(8)?);
+                Value::Fixed64(u64::from_le_bytes(buf))
+            }
+            WIRE_LEN => {
+                let len: usize = self.cursor.read_var()?;
+                let start = self.cursor.next;
+                if len > self.cursor.buf.len() - start {
+                    return Err(Error::EndOfBuffer(len));
+                }
+                self.cursor.next += len;
+                Value::Bytes(&self.cursor.buf[start..start + len])
+            }
+            WIRE_FIXED32 => {
+                let mut buf = [0u8; 4];
+                buf.copy_from_slice(self.cursor.read_exact(4)?);
+                Value::Fixed32(u32::from_le_bytes(buf))
+            }
+            _ => return Err(Error::UnexpectedValue),
+        };
+        Ok(Some((tag >> 3, value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::exchange_updates;
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, Extension, PrelimArray, PrelimMap, StateVector, Update};
+    use lib0::any::Any;
+    use lib0::decimal::Decimal;
+    use std::collections::HashMap;
+
+    #[test]
+    fn protobuf_roundtrip() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        {
+            let mut txn = d1.transact();
+            let text = txn.get_text("text");
+            text.insert(&mut txn, 0, "hello world");
+            text.format(
+                &mut txn,
+                0,
+                5,
+                HashMap::from([("bold".into(), Any::Bool(true))]),
+            );
+            text.insert_embed(&mut txn, 5, Any::BigInt(-1));
+            text.remove_range(&mut txn, 7, 2);
+            let array = txn.get_array("array");
+            array.insert_range(&mut txn, 0, [1.5, -2.0]);
+            array.push_back(&mut txn, vec![0u8, 1, 2]);
+            array.push_back(&mut txn, PrelimArray::from([Any::Null, Any::Undefined]));
+            array.move_to(&mut txn, 0, 2);
+            array.push_back(&mut txn, Doc::with_client_id(3));
+            array.move_range_to_end(&mut txn, 3, true, 1);
+            array.push_back(&mut txn, i128::MAX);
+            array.push_back(&mut txn, Any::Date(1_700_000_000_123));
+            array.push_back(&mut txn, Extension::new("ext", vec![0, 1, 255]));
+            array.push_back(&mut txn, "0.001".parse::<Decimal>().unwrap());
+        }
+        {
+            let mut txn = d2.transact();
+            let map = txn.get_map("map");
+            map.insert(&mut txn, "a", "value");
+            map.insert(
+                &mut txn,
+                "b",
+                PrelimMap::from(HashMap::from([(
+                    "nested".to_owned(),
+                    Any::Array(vec![Any::Bool(false)].into()),
+                )])),
+            );
+            map.remove(&mut txn, "a");
+            let xml = txn.get_xml_element("xml");
+            xml.insert_elem(&mut txn, 0, "div");
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let v1 = d1.encode_state_as_update_v1(&StateVector::default());
+        let update = Update::decode_v1(&v1).unwrap();
+        let proto = update.encode_protobuf();
+        let decoded = Update::decode_protobuf(&proto).unwrap();
+        assert_eq!(decoded, update);
+        assert_eq!(decoded.encode_v1(), update.encode_v1());
+
+        let d3 = Doc::new();
+        d3.transact().apply_update(decoded);
+        let text = d3.transact().get_text("text");
+        assert_eq!(text.to_string(), d1.transact().get_text("text").to_string());
+        let map = d3.transact().get_map("map");
+        assert_eq!(map.to_json(), d2.transact().get_map("map").to_json());
+    }
+
+    #[test]
+    fn protobuf_malformed_input() {
+        assert_eq!(Update::decode_protobuf(&[]).unwrap(), Update::new());
+        // truncated length-delimited field
+        assert!(Update::decode_protobuf(&[0x0a, 0x05, 0x08]).is_err());
+        // item without content
+        let proto = [0x0a, 0x08, 0x08, 0x01, 0x10, 0x00, 0x1a, 0x02, 0x1a, 0x00];
+        assert!(Update::decode_protobuf(&proto).is_err());
+        // item referring to itself as its origin
+        let mut proto = [
+            0x0a, 0x12, 0x08, 0x01, 0x10, 0x00, 0x1a, 0x0c, 0x1a, 0x0a, 0x0a, 0x04, 0x08, 0x01,
+            0x10, 0x00, 0x8a, 0x01, 0x01, 0x61,
+        ];
+        proto[13] = 0x02; // origin from another client is fine
+        assert!(Update::decode_protobuf(&proto).is_ok());
+        proto[13] = 0x01;
+        assert!(Update::decode_protobuf(&proto).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::ops::Range;
+
+/// Maximum number of edits the diff algorithm will look for. Beyond that point a diff falls back
+/// to replacing the whole changed region, which keeps both time and memory bounded for inputs
+/// that have almost nothing in common.
+const MAX_EDIT_DISTANCE: usize = 1024;
+
+/// A single step of an edit script, which transforms one sequence into another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Edit {
+    /// A number of elements common for both sequences.
+    Equal(usize),
+    /// A number of elements present only in the old sequence.
+    Delete(usize),
+    /// A range of elements of the new sequence, which are not present in the old one.
+    Insert(Range<usize>),
+}
+
+/// Computes an edit script, which turns `old` sequence into `new` one, using Myers' diff
+/// algorithm. Consecutive edits of the same kind are merged together.
+pub(crate) fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut edits = Vec::new();
+    push(&mut edits, Edit::Equal(prefix));
+    match shortest_edit(a, b) {
+        Some(middle) => {
+            for edit in middle {
+                let edit = match edit {
+                    Edit::Insert(range) => Edit::Insert(range.start + prefix..range.end + prefix),
+                    other => other,
+                };
+                push(&mut edits, edit);
+            }
+        }
+        None => {
+            push(&mut edits, Edit::Delete(a.len()));
+            push(&mut edits, Edit::Insert(prefix..prefix + b.len()));
+        }
+    }
+    push(&mut edits, Edit::Equal(suffix));
+    edits
+}
+
+/// Appends an `edit` to an edit script, merging it with the last one if they're of the same kind.
+fn push(edits: &mut Vec<Edit>, edit: Edit) {
+    match (edits.last_mut(), edit) {
+        (_, Edit::Equal(0)) | (_, Edit::Delete(0)) => {}
+        (_, Edit::Insert(range)) if range.is_empty() => {}
+        (Some(Edit::Equal(n)), Edit::Equal(m)) => *n += m,
+        (Some(Edit::Delete(n)), Edit::Delete(m)) => *n += m,
+        (Some(Edit::Insert(r1)), Edit::Insert(r2)) if r1.end == r2.start => r1.end = r2.end,
+        (_, edit) => edits.push(edit),
+    }
+}
+
+/// Greedy Myers' algorithm. Returns `None` if sequences differ by more than [MAX_EDIT_DISTANCE]
+/// edits.
+fn shortest_edit<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Edit>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+    let offset = max + 1;
+    // v[k + offset] is the furthest x reached on a diagonal k = x - y
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m, d));
+            }
+            k += 2;
+        }
+    }
+    None
+}
+
+/// Walks back through the snapshots of furthest reaching paths, recovering an edit script.
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize, d: isize) -> Vec<Edit> {
+    let mut steps = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..=d).rev() {
+        // snapshot of `v` from before step `d`, covering diagonals -d..=d
+        let v = &trace[d as usize];
+        let get = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            steps.push(Edit::Equal(1));
+            x -= 1;
+            y -= 1;
+        }
+        if x == prev_x {
+            steps.push(Edit::Insert(prev_y as usize..y as usize));
+        } else {
+            steps.push(Edit::Delete(1));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    while x > 0 && y > 0 {
+        steps.push(Edit::Equal(1));
+        x -= 1;
+        y -= 1;
+    }
+
+    let mut edits = Vec::new();
+    for step in steps.into_iter().rev() {
+        push(&mut edits, step);
+    }
+    edits
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::diff::{diff, Edit};
+
+    fn apply(old: &str, edits: &[Edit], new: &str) -> String {
+        let old: Vec<char> = old.chars().collect();
+        let new: Vec<char> = new.chars().collect();
+        let mut result = String::new();
+        let mut i = 0;
+        for edit in edits {
+            match edit {
+                Edit::Equal(n) => {
+                    result.extend(&old[i..i + n]);
+                    i += n;
+                }
+                Edit::Delete(n) => i += n,
+                Edit::Insert(range) => result.extend(&new[range.clone()]),
+            }
+        }
+        assert_eq!(i, old.len());
+        result
+    }
+
+    fn check(old: &str, new: &str) -> Vec<Edit> {
+        let a: Vec<char> = old.chars().collect();
+        let b: Vec<char> = new.chars().collect();
+        let edits = diff(&a, &b);
+        assert_eq!(apply(old, &edits, new), new);
+        edits
+    }
+
+    #[test]
+    fn minimal_edits() {
+        assert_eq!(check("", ""), vec![]);
+        assert_eq!(check("abc", "abc"), vec![Edit::Equal(3)]);
+        assert_eq!(check("", "abc"), vec![Edit::Insert(0..3)]);
+        assert_eq!(check("abc", ""), vec![Edit::Delete(3)]);
+        assert_eq!(
+            check("hello world", "hello big world"),
+            vec![Edit::Equal(6), Edit::Insert(6..10), Edit::Equal(5)]
+        );
+        assert_eq!(
+            check("the quick fox", "the fox"),
+            vec![Edit::Equal(4), Edit::Delete(6), Edit::Equal(3)]
+        );
+
+        let edits = check("ABCABBA", "CBABAC");
+        let changed: usize = edits
+            .iter()
+            .map(|e| match e {
+                Edit::Delete(n) => *n,
+                Edit::Insert(r) => r.len(),
+                Edit::Equal(_) => 0,
+            })
+            .sum();
+        assert_eq!(changed, 5);
+    }
+
+    #[test]
+    fn random_edits() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(59);
+        for _ in 0..200 {
+            let old: String = (0..rng.gen_range(0, 40))
+                .map(|_| rng.gen_range(b'a', b'e') as char)
+                .collect();
+            let new: String = (0..rng.gen_range(0, 40))
+                .map(|_| rng.gen_range(b'a', b'e') as char)
+                .collect();
+            check(&old, &new);
+        }
+    }
+
+    #[test]
+    fn too_many_edits() {
+        let old: String = std::iter::repeat('a').take(3000).collect();
+        let new: String = std::iter::repeat('b').take(3000).collect();
+        let edits = check(&format!("x{}y", old), &format!("x{}y", new));
+        assert_eq!(
+            edits,
+            vec![
+                Edit::Equal(1),
+                Edit::Delete(3000),
+                Edit::Insert(1..3001),
+                Edit::Equal(1)
+            ]
+        );
+    }
+}
@@ -1 +1,2 @@
 pub mod client_hasher;
+pub(crate) mod diff;
@@ -0,0 +1,222 @@
+//! WebSocket provider, which connects a [Doc] to a
+//! [y-websocket](https://github.com/yjs/y-websocket) compatible server. Enabled with
+//! `yrs-websocket` feature.
+//!
+//! [WebsocketProvider] performs a sync handshake with a server, relays document updates in both
+//! directions and propagates awareness states, using [Connection] to drive the y-protocols
+//! exchange. Since documents are not thread-safe, a provider runs on a current thread: it must be
+//! awaited within a single-threaded tokio runtime or a [LocalSet](tokio::task::LocalSet).
+//!
+//! ```no_run
+//! use yrs::awareness::Awareness;
+//! use yrs::websocket::WebsocketProvider;
+//! use yrs::Doc;
+//!
+//! # async fn example() -> Result<(), yrs::websocket::WebsocketError> {
+//! let doc = Doc::new();
+//! let provider = WebsocketProvider::new(Awareness::new(doc));
+//! // runs until a connection is closed
+//! provider.connect("ws://localhost:1234/my-room").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::awareness::Awareness;
+use crate::event::{Subscription, SubscriptionId};
+use crate::sync::Connection;
+use crate::{Doc, UpdateEvent};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::Rc;
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// Error returned by [WebsocketProvider].
+#[derive(Debug)]
+pub enum WebsocketError {
+    /// WebSocket connection failed.
+    Transport(tungstenite::Error),
+    /// Remote peer has sent a message, which could not be decoded or applied.
+    Protocol(lib0::error::Error),
+}
+
+impl std::fmt::Display for WebsocketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebsocketError::Transport(e) => write!(f, "websocket transport error: {}", e),
+            WebsocketError::Protocol(e) => write!(f, "sync protocol error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WebsocketError {}
+
+impl From<tungstenite::Error> for WebsocketError {
+    fn from(e: tungstenite::Error) -> Self {
+        WebsocketError::Transport(e)
+    }
+}
+
+impl From<lib0::error::Error> for WebsocketError {
+    fn from(e: lib0::error::Error) -> Self {
+        WebsocketError::Protocol(e)
+    }
+}
+
+/// A provider, which synchronizes a document and its awareness with a remote peer over
+/// a WebSocket connection. See: [module documentation](crate::websocket).
+pub struct WebsocketProvider {
+    connection: Rc<RefCell<Connection>>,
+    notify: Rc<Notify>,
+    awareness_subscription: SubscriptionId,
+    _update_subscription: Subscription<UpdateEvent>,
+}
+
+impl WebsocketProvider {
+    /// Creates a new provider for a document bound to a given `awareness` instance.
+    pub fn new(awareness: Awareness) -> Self {
+        let mut connection = Connection::new(awareness);
+        let notify = Rc::new(Notify::new());
+        let update_subscription = {
+            let notify = notify.clone();
+            connection
+                .doc()
+                .observe_update_v1(move |_, _| notify.notify_one())
+        };
+        let awareness_subscription = {
+            let notify = notify.clone();
+            connection
+                .awareness_mut()
+                .on_update(move |_, _| notify.notify_one())
+        };
+        WebsocketProvider {
+            connection: Rc::new(RefCell::new(connection)),
+            notify,
+            awareness_subscription,
+            _update_subscription: update_subscription,
+        }
+    }
+
+    /// Returns a document synchronized by this provider.
+    pub fn doc(&self) -> Ref<'_, Doc> {
+        Ref::map(self.connection.borrow(), |c| c.doc())
+    }
+
+    /// Returns a [Connection] used by this provider, ie. to check if it's already synced with
+    /// a remote peer.
+    pub fn connection(&self) -> Ref<'_, Connection> {
+        self.connection.borrow()
+    }
+
+    /// Returns a mutable [Connection] used by this provider, ie. to change a local awareness
+    /// state. It must not be held across `.await` points.
+    pub fn connection_mut(&self) -> RefMut<'_, Connection> {
+        self.connection.borrow_mut()
+    }
+
+    /// Connects to a y-websocket compatible server under a given `url` (usually in form of
+    /// `ws://host:port/room-name`) and synchronizes a document with it until a connection is
+    /// closed.
+    pub async fn connect(&self, url: &str) -> Result<(), WebsocketError> {
+        let (stream, _) = tokio_tungstenite::connect_async(url).await?;
+        self.run(stream).await
+    }
+
+    /// Synchronizes a document over an already established WebSocket `stream` until it's closed.
+    /// It can be used on both client and server side of a connection.
+    pub async fn run<S>(&self, stream: S) -> Result<(), WebsocketError>
+    where
+        S: Stream<Item = Result<Message, tungstenite::Error>>
+            + Sink<Message, Error = tungstenite::Error>
+            + Unpin,
+    {
+        let (mut sink, mut stream) = stream.split();
+        loop {
+            loop {
+                let msg = self.connection.borrow_mut().next_message();
+                match msg {
+                    Some(msg) => sink.send(Message::Binary(msg)).await?,
+                    None => break,
+                }
+            }
+            tokio::select! {
+                _ = self.notify.notified() => {}
+                msg = stream.next() => match msg {
+                    Some(Ok(Message::Binary(data))) => self.connection.borrow_mut().handle(&data)?,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                },
+            }
+        }
+    }
+}
+
+impl Drop for WebsocketProvider {
+    fn drop(&mut self) {
+        self.connection
+            .borrow_mut()
+            .awareness_mut()
+            .unobserve_update(self.awareness_subscription);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::awareness::Awareness;
+    use crate::websocket::WebsocketProvider;
+    use crate::Doc;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    async fn wait_until<F: Fn() -> bool>(f: F) {
+        while !f() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn websocket_sync() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/room", listener.local_addr().unwrap());
+
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.transact().get_text("text");
+        txt1.push(&mut d1.transact(), "hello");
+        let server = WebsocketProvider::new(Awareness::new(d1));
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.transact().get_text("text");
+        let client = WebsocketProvider::new(Awareness::new(d2));
+
+        let serve = async {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let stream = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            server.run(stream).await
+        };
+        let scenario = async {
+            wait_until(|| client.connection().is_synced()).await;
+            assert_eq!(txt2.to_string(), "hello");
+
+            // local changes are relayed in both directions
+            txt2.push(&mut client.doc().transact(), " world");
+            wait_until(|| txt1.to_string() == "hello world").await;
+            txt1.push(&mut server.doc().transact(), "!");
+            wait_until(|| txt2.to_string() == "hello world!").await;
+
+            // awareness states are propagated
+            client
+                .connection_mut()
+                .awareness_mut()
+                .set_local_state(r#"{"user":"client"}"#);
+            wait_until(|| server.connection().awareness().clients().contains_key(&2)).await;
+        };
+        let timeout = tokio::time::sleep(Duration::from_secs(10));
+        tokio::select! {
+            result = serve => panic!("server stopped: {:?}", result),
+            result = client.connect(&url) => panic!("client stopped: {:?}", result),
+            _ = timeout => panic!("timed out"),
+            _ = scenario => {}
+        }
+    }
+}
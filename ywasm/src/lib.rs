@@ -7,7 +7,7 @@ use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
 use wasm_bindgen::__rt::Ref;
 use wasm_bindgen::prelude::{wasm_bindgen, Closure};
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
 use yrs::block::{ClientID, ItemContent, Prelim};
 use yrs::types::array::{ArrayEvent, ArrayIter};
 use yrs::types::map::{MapEvent, MapIter};
@@ -21,8 +21,9 @@ use yrs::types::{
 use yrs::updates::decoder::{Decode, DecoderV1, DecoderV2};
 use yrs::updates::encoder::{Encode, Encoder, EncoderV1, EncoderV2};
 use yrs::{
-    AfterTransactionEvent, Array, DeleteSet, Doc, Map, OffsetKind, Options, Snapshot, StateVector,
-    Subscription, Text, Transaction, Update, UpdateEvent, Xml, XmlElement, XmlText,
+    AfterTransactionEvent, Array, DeleteSet, Doc, DocRef, Map, OffsetKind, Options, Snapshot,
+    StateVector, SubdocsEvent, Subscription, Text, Transaction, Update, UpdateEvent, Xml,
+    XmlElement, XmlText,
 };
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -70,7 +71,7 @@ pub fn set_panic_hook() {
 /// }
 /// ```
 #[wasm_bindgen]
-pub struct YDoc(Doc);
+pub struct YDoc(DocRef);
 
 #[wasm_bindgen]
 impl YDoc {
@@ -88,7 +89,7 @@ impl YDoc {
 
         options.offset_kind = OffsetKind::Utf16;
         options.skip_gc = skip_gc;
-        YDoc(Doc::with_options(options))
+        YDoc(Doc::with_options(options).into())
     }
 
     /// Gets globally unique identifier of this `YDoc` instance.
@@ -97,6 +98,56 @@ impl YDoc {
         self.0.client_id as f64
     }
 
+    /// Gets a unique identifier of this document, which - unlike `id` - is shared by all replicas
+    /// of the same document. It's used to identify subdocuments.
+    #[wasm_bindgen(method, getter)]
+    pub fn guid(&self) -> String {
+        self.0.guid().to_string()
+    }
+
+    /// Checks if this document has been requested to load its contents. Subdocuments received from
+    /// remote peers are not loaded unless they have `autoLoad` flag set or `load` method has been
+    /// called on them.
+    #[wasm_bindgen(method, getter, js_name = shouldLoad)]
+    pub fn should_load(&self) -> bool {
+        self.0.should_load()
+    }
+
+    /// Checks if this document, when used as a subdocument, should be loaded automatically by
+    /// the peers that received it.
+    #[wasm_bindgen(method, getter, js_name = autoLoad)]
+    pub fn auto_load(&self) -> bool {
+        self.0.auto_load()
+    }
+
+    /// Requests current subdocument to be loaded. Parent document observers registered via
+    /// `onSubdocs` will be notified about it once a given `parent_txn` is committed.
+    #[wasm_bindgen(js_name = load)]
+    pub fn load(&self, parent_txn: &mut YTransaction) {
+        self.0.load(parent_txn)
+    }
+
+    /// Returns a set of all subdocuments nested within shared collections of this document.
+    #[wasm_bindgen(js_name = getSubdocs)]
+    pub fn get_subdocs(&self) -> js_sys::Set {
+        let set = js_sys::Set::new(&JsValue::UNDEFINED);
+        for doc in self.0.subdocs() {
+            set.add(&YDoc(doc.clone()).into());
+        }
+        set
+    }
+
+    /// Returns a set of unique identifiers (see: `guid`) of all subdocuments nested within shared
+    /// collections of this document.
+    #[wasm_bindgen(js_name = getSubdocGuids)]
+    pub fn get_subdoc_guids(&self) -> js_sys::Set {
+        let set = js_sys::Set::new(&JsValue::UNDEFINED);
+        for guid in self.0.subdoc_guids() {
+            set.add(&JsValue::from_str(guid));
+        }
+        set
+    }
+
     /// Returns a new transaction for this document. Ywasm shared data types execute their
     /// operations in a context of a given transaction. Each document can have only one active
     /// transaction at the time - subsequent attempts will cause exception to be thrown.
@@ -232,6 +283,34 @@ impl YDoc {
             })
             .into()
     }
+
+    /// Subscribes given function to be called, whenever a new transaction is created by this
+    /// document, before any changes are made in its scope.
+    ///
+    /// Returns an observer, which can be freed in order to unsubscribe this callback.
+    #[wasm_bindgen(js_name = onBeforeTransaction)]
+    pub fn on_before_transaction(&mut self, f: js_sys::Function) -> YBeforeTransactionObserver {
+        self.0
+            .observe_before_transaction(move |_| {
+                f.call0(&JsValue::UNDEFINED).unwrap();
+            })
+            .into()
+    }
+
+    /// Subscribes given function to be called, whenever subdocuments of this document have been
+    /// added, removed or requested to load within a committed transaction. Function takes
+    /// a `YSubdocsEvent` as a parameter.
+    ///
+    /// Returns an observer, which can be freed in order to unsubscribe this callback.
+    #[wasm_bindgen(js_name = onSubdocs)]
+    pub fn on_subdocs(&mut self, f: js_sys::Function) -> YSubdocsObserver {
+        self.0
+            .observe_subdocs(move |_, e| {
+                let arg: JsValue = YSubdocsEvent::new(e).into();
+                f.call1(&JsValue::UNDEFINED, &arg).unwrap();
+            })
+            .into()
+    }
 }
 
 /// Encodes a state vector of a given ywasm document into its binary representation using lib0 v1
@@ -650,10 +729,10 @@ impl YTransaction {
         let diff: Vec<u8> = diff.to_vec();
         let mut decoder = DecoderV1::from(diff.as_slice());
         match Update::decode(&mut decoder) {
-            Ok(update) => {
-                self.0.apply_update(update);
-                Ok(())
-            }
+            Ok(update) => self
+                .0
+                .try_apply_update(update)
+                .map_err(|e| JsValue::from(e.to_string())),
             Err(e) => Err(JsValue::from(e.to_string())),
         }
     }
@@ -687,10 +766,10 @@ impl YTransaction {
     pub fn apply_v2(&mut self, diff: Uint8Array) -> Result<(), JsValue> {
         let mut diff: Vec<u8> = diff.to_vec();
         match Update::decode_v2(&mut diff) {
-            Ok(update) => {
-                self.0.apply_update(update);
-                Ok(())
-            }
+            Ok(update) => self
+                .0
+                .try_apply_update(update)
+                .map_err(|e| JsValue::from(e.to_string())),
             Err(e) => Err(JsValue::from(e.to_string())),
         }
     }
@@ -1314,6 +1393,50 @@ impl YAfterTransactionEvent {
     }
 }
 
+/// Event generated by `YDoc.onSubdocs` subscription. It contains subdocuments added, removed or
+/// requested to load within a committed transaction.
+#[wasm_bindgen]
+pub struct YSubdocsEvent {
+    added: js_sys::Array,
+    removed: js_sys::Array,
+    loaded: js_sys::Array,
+}
+
+#[wasm_bindgen]
+impl YSubdocsEvent {
+    /// Returns an array of subdocuments inserted into shared collections of a parent document.
+    #[wasm_bindgen(method, getter)]
+    pub fn added(&self) -> js_sys::Array {
+        self.added.clone()
+    }
+
+    /// Returns an array of subdocuments removed from shared collections of a parent document.
+    #[wasm_bindgen(method, getter)]
+    pub fn removed(&self) -> js_sys::Array {
+        self.removed.clone()
+    }
+
+    /// Returns an array of subdocuments, which have been requested to load.
+    #[wasm_bindgen(method, getter)]
+    pub fn loaded(&self) -> js_sys::Array {
+        self.loaded.clone()
+    }
+
+    fn new(e: &SubdocsEvent) -> Self {
+        fn docs_into_array(docs: &[DocRef]) -> js_sys::Array {
+            docs.iter()
+                .map(|doc| JsValue::from(YDoc(doc.clone())))
+                .collect()
+        }
+
+        YSubdocsEvent {
+            added: docs_into_array(&e.added),
+            removed: docs_into_array(&e.removed),
+            loaded: docs_into_array(&e.loaded),
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct YAfterTransactionObserver(Subscription<AfterTransactionEvent>);
 
@@ -1323,6 +1446,24 @@ impl From<Subscription<AfterTransactionEvent>> for YAfterTransactionObserver {
     }
 }
 
+#[wasm_bindgen]
+pub struct YBeforeTransactionObserver(Subscription<()>);
+
+impl From<Subscription<()>> for YBeforeTransactionObserver {
+    fn from(o: Subscription<()>) -> Self {
+        YBeforeTransactionObserver(o)
+    }
+}
+
+#[wasm_bindgen]
+pub struct YSubdocsObserver(Subscription<SubdocsEvent>);
+
+impl From<Subscription<SubdocsEvent>> for YSubdocsObserver {
+    fn from(o: Subscription<SubdocsEvent>) -> Self {
+        YSubdocsObserver(o)
+    }
+}
+
 #[wasm_bindgen]
 pub struct YUpdateObserver(Subscription<UpdateEvent>);
 
@@ -2798,6 +2939,8 @@ impl Prelim for JsValueWrapper {
     fn into_content(self, _txn: &mut Transaction) -> (ItemContent, Option<Self>) {
         let content = if let Some(any) = js_into_any(&self.0) {
             ItemContent::Any(vec![any])
+        } else if let Some(doc) = js_into_doc(&self.0) {
+            ItemContent::Doc(doc)
         } else if let Ok(shared) = Shared::try_from(&self.0) {
             if shared.is_prelim() {
                 let branch = Branch::new(shared.type_ref(), None);
@@ -2891,6 +3034,8 @@ fn js_into_any(v: &JsValue) -> Option<Any> {
     } else if v.is_bigint() {
         let i = js_sys::BigInt::from(v.clone()).as_f64()?;
         Some(Any::BigInt(i as i64))
+    } else if let Some(date) = v.dyn_ref::<js_sys::Date>() {
+        Some(Any::Date(date.get_time() as i64))
     } else if v.is_null() {
         Some(Any::Null)
     } else if v.is_undefined() {
@@ -2907,7 +3052,7 @@ fn js_into_any(v: &JsValue) -> Option<Any> {
         }
         Some(Any::Array(result.into_boxed_slice()))
     } else if v.is_object() {
-        if let Ok(_) = Shared::try_from(v) {
+        if Shared::try_from(v).is_ok() || js_into_doc(v).is_some() {
             None
         } else {
             let mut map = HashMap::new();
@@ -2933,6 +3078,9 @@ fn any_into_js(v: &Any) -> JsValue {
         Any::Bool(v) => JsValue::from_bool(*v),
         Any::Number(v) => JsValue::from(*v),
         Any::BigInt(v) => JsValue::from(*v),
+        Any::Int128(v) => JsValue::from(*v),
+        Any::Decimal(v) => JsValue::from(v.to_string()),
+        Any::Date(v) => js_sys::Date::new(&JsValue::from(*v as f64)).into(),
         Any::String(v) => JsValue::from(v.as_ref()),
         Any::Buffer(v) => {
             let v = Uint8Array::from(v.as_ref());
@@ -2965,6 +3113,10 @@ fn value_into_js(v: Value) -> JsValue {
         Value::YMap(v) => YMap::from(v).into(),
         Value::YXmlElement(v) => YXmlElement(v).into(),
         Value::YXmlText(v) => YXmlText(v).into(),
+        Value::YWeakLink(v) => any_into_js(&v.to_json()),
+        Value::YMove(v) => any_into_js(&v.to_json()),
+        Value::Extension(v) => any_into_js(&v.to_json()),
+        Value::YDoc(v) => YDoc(v).into(),
     }
 }
 
@@ -2999,6 +3151,19 @@ enum Shared<'a> {
     XmlText(Ref<'a, YXmlText>),
 }
 
+/// Returns a document handle if a given JavaScript value is a `YDoc` instance.
+fn js_into_doc(js: &JsValue) -> Option<DocRef> {
+    use js_sys::{Object, Reflect};
+    let ctor_name = Object::get_prototype_of(js).constructor().name();
+    if ctor_name == "YDoc" {
+        let ptr = Reflect::get(js, &JsValue::from_str("ptr")).ok()?;
+        let doc: Ref<YDoc> = as_ref(ptr.as_f64()? as u32);
+        Some(doc.0.clone())
+    } else {
+        None
+    }
+}
+
 fn as_ref<'a, T>(js: u32) -> Ref<'a, T> {
     unsafe {
         let js = js as *mut wasm_bindgen::__rt::WasmRefCell<T>;